@@ -70,10 +70,13 @@ impl<'a> AstDumper<'a> {
                 ElemFunction(ref fct) => self.dump_fct(fct),
                 ElemClass(ref cls) => self.dump_class(cls),
                 ElemStruct(ref struc) => self.dump_struct(struc),
+                ElemEnum(ref xenum) => self.dump_enum(xenum),
                 ElemTrait(ref xtrait) => self.dump_trait(xtrait),
                 ElemImpl(ref ximpl) => self.dump_impl(ximpl),
                 ElemGlobal(ref global) => self.dump_global(global),
                 ElemConst(ref xconst) => self.dump_const(xconst),
+                ElemTypeAlias(ref alias) => self.dump_type_alias(alias),
+                ElemImport(ref import) => self.dump_import(import),
             }
         }
     }
@@ -109,6 +112,25 @@ impl<'a> AstDumper<'a> {
                     });
     }
 
+    fn dump_type_alias(&mut self, alias: &TypeAlias) {
+        dump!(self,
+              "type {} @ {} {}",
+              self.str(alias.name),
+              alias.pos,
+              alias.id);
+
+        self.indent(|d| d.dump_type(&alias.data_type));
+    }
+
+    fn dump_import(&mut self, import: &Import) {
+        let path: Vec<String> = import.path
+            .iter()
+            .map(|&name| self.str(name).to_string())
+            .collect();
+
+        dump!(self, "import {} @ {} {}", path.join("::"), import.pos, import.id);
+    }
+
     fn dump_impl(&mut self, ximpl: &Impl) {
         dump!(self,
               "impl {} for {} @ {} {}",
@@ -143,6 +165,29 @@ impl<'a> AstDumper<'a> {
         self.indent(|d| d.dump_type(&field.data_type));
     }
 
+    fn dump_enum(&mut self, xenum: &Enum) {
+        dump!(self,
+              "enum {} @ {} {}",
+              self.str(xenum.name),
+              xenum.pos,
+              xenum.id);
+
+        self.indent(|d| for variant in &xenum.variants {
+                        d.dump_enum_variant(variant);
+                    });
+    }
+
+    fn dump_enum_variant(&mut self, variant: &EnumVariant) {
+        dump!(self,
+              "variant {} @ {} {}",
+              self.str(variant.name),
+              variant.pos,
+              variant.id);
+        self.indent(|d| for ty in &variant.types {
+                        d.dump_type(ty);
+                    });
+    }
+
     fn dump_trait(&mut self, t: &Trait) {
         dump!(self, "trait {} @ {} {}", self.str(t.name), t.pos, t.id);
         self.indent(|d| for m in &t.methods {
@@ -262,9 +307,32 @@ impl<'a> AstDumper<'a> {
             StmtDo(ref stmt) => self.dump_stmt_do(stmt),
             StmtSpawn(ref stmt) => self.dump_stmt_spawn(stmt),
             StmtFor(ref stmt) => self.dump_stmt_for(stmt),
+            StmtLetTuple(ref stmt) => self.dump_stmt_let_tuple(stmt),
+            StmtError(ref stmt) => self.dump_stmt_error(stmt),
         }
     }
 
+    fn dump_stmt_let_tuple(&mut self, stmt: &StmtLetTupleType) {
+        let names = stmt.bindings
+            .iter()
+            .map(|binding| self.str(binding.name).to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        dump!(self, "let ({}) @ {} {}", names, stmt.pos, stmt.id);
+
+        self.indent(|d| {
+            dump!(d, "type");
+            d.indent(|d| if let Some(ref ty) = stmt.data_type {
+                         d.dump_type(ty);
+                     } else {
+                         dump!(d, "<no type given>");
+                     });
+
+            dump!(d, "expr");
+            d.indent(|d| d.dump_expr(&stmt.expr));
+        });
+    }
+
     fn dump_stmt_var(&mut self, stmt: &StmtVarType) {
         dump!(self,
               "let {} @ {} {}",
@@ -376,6 +444,10 @@ impl<'a> AstDumper<'a> {
         self.indent(|d| d.dump_expr(&stmt.expr));
     }
 
+    fn dump_stmt_error(&mut self, stmt: &StmtErrorType) {
+        dump!(self, "error @ {} {}", stmt.pos, stmt.id);
+    }
+
     fn dump_stmt_spawn(&mut self, stmt: &StmtSpawnType) {
         dump!(self, "spawn @ {} {}", stmt.pos, stmt.id);
         self.indent(|d| d.dump_expr(&stmt.expr));
@@ -417,6 +489,7 @@ impl<'a> AstDumper<'a> {
             ExprLitStruct(ref lit) => self.dump_expr_lit_struct(lit),
             ExprLitBool(ref lit) => self.dump_expr_lit_bool(lit),
             ExprIdent(ref ident) => self.dump_expr_ident(ident),
+            ExprPath(ref path) => self.dump_expr_path(path),
             ExprAssign(ref assign) => self.dump_expr_assign(assign),
             ExprCall(ref call) => self.dump_expr_call(call),
             ExprDelegation(ref call) => self.dump_expr_delegation(call),
@@ -426,6 +499,9 @@ impl<'a> AstDumper<'a> {
             ExprConv(ref expr) => self.dump_expr_conv(expr),
             ExprTry(ref expr) => self.dump_expr_try(expr),
             ExprLambda(ref expr) => self.dump_expr_lambda(expr),
+            ExprMatch(ref expr) => self.dump_expr_match(expr),
+            ExprTuple(ref expr) => self.dump_expr_tuple(expr),
+            ExprTupleElement(ref expr) => self.dump_expr_tuple_element(expr),
         }
     }
 
@@ -441,6 +517,46 @@ impl<'a> AstDumper<'a> {
         self.indent(|d| d.dump_expr(&expr.expr));
     }
 
+    fn dump_expr_match(&mut self, expr: &ExprMatchType) {
+        dump!(self, "match @ {} {}", expr.pos, expr.id);
+        self.indent(|d| {
+            d.dump_expr(&expr.expr);
+
+            for arm in &expr.arms {
+                d.dump_match_arm(arm);
+            }
+        });
+    }
+
+    fn dump_match_arm(&mut self, arm: &MatchArm) {
+        dump!(self, "arm @ {} {}", arm.pos, arm.id);
+        self.indent(|d| {
+            match arm.pattern {
+                MatchPattern::Lit(ref lit) => d.dump_expr(lit),
+                MatchPattern::Type(name, ref ty) => {
+                    let name = name.map(|name| d.str(name).to_string())
+                        .unwrap_or("_".into());
+                    dump!(d, "binding {}", name);
+                    d.dump_type(ty);
+                }
+                MatchPattern::Enum(ref path, ref bindings) => {
+                    let path = path.path.iter().map(|&name| d.str(name).to_string())
+                        .collect::<Vec<_>>();
+                    dump!(d, "variant {}", path.join("::"));
+
+                    for binding in bindings {
+                        let name = binding.name.map(|name| d.str(name).to_string())
+                            .unwrap_or("_".into());
+                        dump!(d, "binding {} @ {} {}", name, binding.pos, binding.id);
+                    }
+                }
+                MatchPattern::Underscore => dump!(d, "_"),
+            }
+
+            d.dump_expr(&arm.value);
+        });
+    }
+
     fn dump_expr_delegation(&mut self, expr: &ExprDelegationType) {
         dump!(self, "super @ {} {}", expr.pos, expr.id);
 
@@ -507,6 +623,17 @@ impl<'a> AstDumper<'a> {
               ident.id);
     }
 
+    fn dump_expr_path(&mut self, expr: &ExprPathType) {
+        let path = expr.path
+            .path
+            .iter()
+            .map(|&name| self.str(name).to_string())
+            .collect::<Vec<_>>()
+            .join("::");
+
+        dump!(self, "path {} @ {} {}", path, expr.pos, expr.id);
+    }
+
     fn dump_expr_un(&mut self, expr: &ExprUnType) {
         dump!(self, "unary {:?} @ {} {}", expr.op, expr.pos, expr.id);
         self.indent(|d| d.dump_expr(&expr.opnd));
@@ -526,7 +653,7 @@ impl<'a> AstDumper<'a> {
 
     fn dump_expr_lambda(&mut self, expr: &ExprLambdaType) {
         dump!(self, "lambda @ {} {}", expr.pos, expr.id);
-        self.indent(|d| d.dump_stmt(&expr.block));
+        self.indent(|d| d.dump_stmt(expr.fct.block()));
     }
 
     fn dump_expr_field(&mut self, field: &ExprFieldType) {
@@ -538,9 +665,30 @@ impl<'a> AstDumper<'a> {
         self.indent(|d| d.dump_expr(&field.object));
     }
 
+    fn dump_expr_tuple(&mut self, expr: &ExprTupleType) {
+        dump!(self, "tuple @ {} {}", expr.pos, expr.id);
+        self.indent(|d| for element in &expr.elements {
+                        d.dump_expr(element);
+                    });
+    }
+
+    fn dump_expr_tuple_element(&mut self, expr: &ExprTupleElementType) {
+        dump!(self, "element {} @ {} {}", expr.index, expr.pos, expr.id);
+        self.indent(|d| d.dump_expr(&expr.object));
+    }
+
     fn dump_expr_assign(&mut self, expr: &ExprAssignType) {
         self.indent(|d| d.dump_expr(&expr.rhs));
-        dump!(self, "assign (=) @ {} {}", expr.pos, expr.id);
+        if let Some(op) = expr.op {
+            dump!(self,
+                  "assign ({}=) @ {} {} {}",
+                  op.op.as_str(),
+                  expr.pos,
+                  expr.id,
+                  op.id);
+        } else {
+            dump!(self, "assign (=) @ {} {}", expr.pos, expr.id);
+        }
         self.indent(|d| d.dump_expr(&expr.lhs));
     }
 
@@ -55,6 +55,20 @@ impl Ast {
             .unwrap()
     }
 
+    #[cfg(test)]
+    pub fn enum0(&self) -> &Enum {
+        self.files.last().unwrap().elements[0]
+            .to_enum()
+            .unwrap()
+    }
+
+    #[cfg(test)]
+    pub fn alias0(&self) -> &TypeAlias {
+        self.files.last().unwrap().elements[0]
+            .to_type_alias()
+            .unwrap()
+    }
+
     #[cfg(test)]
     pub fn trai(&self, index: usize) -> &Trait {
         self.files.last().unwrap().elements[index]
@@ -92,6 +106,10 @@ impl Ast {
 #[derive(Clone, Debug)]
 pub struct File {
     pub path: String,
+    // source code of the file, used to show the source of diagnostics
+    pub content: String,
+    // path of the module defined by this file, empty for the root module
+    pub module: Vec<String>,
     pub elements: Vec<Elem>,
 }
 
@@ -109,10 +127,13 @@ pub enum Elem {
     ElemFunction(Function),
     ElemClass(Class),
     ElemStruct(Struct),
+    ElemEnum(Enum),
     ElemTrait(Trait),
     ElemImpl(Impl),
     ElemGlobal(Global),
     ElemConst(Const),
+    ElemTypeAlias(TypeAlias),
+    ElemImport(Import),
 }
 
 impl Elem {
@@ -121,10 +142,13 @@ impl Elem {
             &ElemFunction(ref fct) => fct.id,
             &ElemClass(ref class) => class.id,
             &ElemStruct(ref s) => s.id,
+            &ElemEnum(ref e) => e.id,
             &ElemTrait(ref t) => t.id,
             &ElemImpl(ref i) => i.id,
             &ElemGlobal(ref g) => g.id,
             &ElemConst(ref c) => c.id,
+            &ElemTypeAlias(ref a) => a.id,
+            &ElemImport(ref i) => i.id,
         }
     }
 
@@ -149,6 +173,13 @@ impl Elem {
         }
     }
 
+    pub fn to_enum(&self) -> Option<&Enum> {
+        match self {
+            &ElemEnum(ref xenum) => Some(xenum),
+            _ => None,
+        }
+    }
+
     pub fn to_trait(&self) -> Option<&Trait> {
         match self {
             &ElemTrait(ref trai) => Some(trai),
@@ -176,6 +207,20 @@ impl Elem {
             _ => None,
         }
     }
+
+    pub fn to_type_alias(&self) -> Option<&TypeAlias> {
+        match self {
+            &ElemTypeAlias(ref alias) => Some(alias),
+            _ => None,
+        }
+    }
+
+    pub fn to_import(&self) -> Option<&Import> {
+        match self {
+            &ElemImport(ref import) => Some(import),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -183,6 +228,7 @@ pub struct Global {
     pub id: NodeId,
     pub pos: Position,
     pub name: Name,
+    pub is_pub: bool,
     pub reassignable: bool,
     pub data_type: Type,
     pub expr: Option<Box<Expr>>,
@@ -193,15 +239,34 @@ pub struct Const {
     pub id: NodeId,
     pub pos: Position,
     pub name: Name,
+    pub is_pub: bool,
     pub data_type: Type,
     pub expr: Box<Expr>,
 }
 
+#[derive(Clone, Debug)]
+pub struct TypeAlias {
+    pub id: NodeId,
+    pub pos: Position,
+    pub name: Name,
+    pub is_pub: bool,
+    pub type_params: Option<Vec<TypeParam>>,
+    pub data_type: Type,
+}
+
+#[derive(Clone, Debug)]
+pub struct Import {
+    pub id: NodeId,
+    pub pos: Position,
+    pub path: Vec<Name>,
+}
+
 #[derive(Clone, Debug)]
 pub struct Struct {
     pub id: NodeId,
     pub pos: Position,
     pub name: Name,
+    pub is_pub: bool,
     pub fields: Vec<StructField>,
 }
 
@@ -213,6 +278,23 @@ pub struct StructField {
     pub data_type: Type,
 }
 
+#[derive(Clone, Debug)]
+pub struct Enum {
+    pub id: NodeId,
+    pub pos: Position,
+    pub name: Name,
+    pub is_pub: bool,
+    pub variants: Vec<EnumVariant>,
+}
+
+#[derive(Clone, Debug)]
+pub struct EnumVariant {
+    pub id: NodeId,
+    pub pos: Position,
+    pub name: Name,
+    pub types: Vec<Type>,
+}
+
 #[derive(Clone, Debug)]
 pub enum Type {
     TypeSelf(TypeSelfType),
@@ -248,6 +330,8 @@ pub struct TypeLambdaType {
 pub struct TypeBasicType {
     pub id: NodeId,
     pub pos: Position,
+    // module prefix of a qualified type name
+    pub path: Vec<Name>,
     pub name: Name,
     pub params: Vec<Box<Type>>,
 }
@@ -271,10 +355,16 @@ impl Type {
         Type::TypeSelf(TypeSelfType { id: id, pos: pos })
     }
 
-    pub fn create_basic(id: NodeId, pos: Position, name: Name, params: Vec<Box<Type>>) -> Type {
+    pub fn create_basic(id: NodeId,
+                        pos: Position,
+                        path: Vec<Name>,
+                        name: Name,
+                        params: Vec<Box<Type>>)
+                        -> Type {
         Type::TypeBasic(TypeBasicType {
                             id: id,
                             pos: pos,
+                            path: path,
                             name: name,
                             params: params,
                         })
@@ -359,7 +449,25 @@ impl Type {
     pub fn to_string(&self, interner: &Interner) -> String {
         match *self {
             Type::TypeSelf(_) => "Self".into(),
-            Type::TypeBasic(ref val) => format!("{}", *interner.str(val.name)),
+            Type::TypeBasic(ref val) => {
+                let mut names: Vec<String> = val.path
+                    .iter()
+                    .map(|&name| interner.str(name).to_string())
+                    .collect();
+                names.push(interner.str(val.name).to_string());
+                let name = names.join("::");
+
+                if val.params.is_empty() {
+                    name
+                } else {
+                    let params: Vec<String> = val.params
+                        .iter()
+                        .map(|t| t.to_string(interner))
+                        .collect();
+
+                    format!("{}<{}>", name, params.join(", "))
+                }
+            }
 
             Type::TypeTuple(ref val) => {
                 let types: Vec<String> = val.subtypes
@@ -423,6 +531,7 @@ pub struct Trait {
     pub id: NodeId,
     pub name: Name,
     pub pos: Position,
+    pub is_pub: bool,
     pub methods: Vec<Function>,
 }
 
@@ -435,6 +544,7 @@ pub struct Class {
     pub has_open: bool,
     pub is_abstract: bool,
     pub internal: bool,
+    pub is_pub: bool,
     pub primary_ctor: bool,
 
     pub ctors: Vec<Function>,
@@ -487,6 +597,7 @@ pub struct Field {
     pub primary_ctor: bool,
     pub expr: Option<Box<Expr>>,
     pub reassignable: bool,
+    pub is_pub: bool,
 }
 
 #[derive(Clone, Debug)]
@@ -633,6 +744,8 @@ pub enum Stmt {
     StmtDo(StmtDoType),
     StmtSpawn(StmtSpawnType),
     StmtFor(StmtForType),
+    StmtLetTuple(StmtLetTupleType),
+    StmtError(StmtErrorType),
 }
 
 impl Stmt {
@@ -766,6 +879,27 @@ impl Stmt {
                         })
     }
 
+    pub fn create_error(id: NodeId, pos: Position) -> Stmt {
+        Stmt::StmtError(StmtErrorType { id: id, pos: pos })
+    }
+
+    pub fn create_let_tuple(id: NodeId,
+                            pos: Position,
+                            reassignable: bool,
+                            bindings: Vec<TupleBinding>,
+                            data_type: Option<Type>,
+                            expr: Box<Expr>)
+                            -> Stmt {
+        Stmt::StmtLetTuple(StmtLetTupleType {
+                               id: id,
+                               pos: pos,
+                               reassignable: reassignable,
+                               bindings: bindings,
+                               data_type: data_type,
+                               expr: expr,
+                           })
+    }
+
     pub fn id(&self) -> NodeId {
         match *self {
             Stmt::StmtVar(ref stmt) => stmt.id,
@@ -782,6 +916,8 @@ impl Stmt {
             Stmt::StmtDefer(ref stmt) => stmt.id,
             Stmt::StmtDo(ref stmt) => stmt.id,
             Stmt::StmtSpawn(ref stmt) => stmt.id,
+            Stmt::StmtLetTuple(ref stmt) => stmt.id,
+            Stmt::StmtError(ref stmt) => stmt.id,
         }
     }
 
@@ -801,6 +937,8 @@ impl Stmt {
             Stmt::StmtDefer(ref stmt) => stmt.pos,
             Stmt::StmtDo(ref stmt) => stmt.pos,
             Stmt::StmtSpawn(ref stmt) => stmt.pos,
+            Stmt::StmtLetTuple(ref stmt) => stmt.pos,
+            Stmt::StmtError(ref stmt) => stmt.pos,
         }
     }
 
@@ -999,6 +1137,27 @@ impl Stmt {
             _ => false,
         }
     }
+
+    pub fn is_error(&self) -> bool {
+        match *self {
+            Stmt::StmtError(_) => true,
+            _ => false,
+        }
+    }
+
+    pub fn to_let_tuple(&self) -> Option<&StmtLetTupleType> {
+        match *self {
+            Stmt::StmtLetTuple(ref val) => Some(val),
+            _ => None,
+        }
+    }
+
+    pub fn is_let_tuple(&self) -> bool {
+        match *self {
+            Stmt::StmtLetTuple(_) => true,
+            _ => false,
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -1013,6 +1172,24 @@ pub struct StmtVarType {
 }
 
 
+#[derive(Clone, Debug)]
+pub struct StmtLetTupleType {
+    pub id: NodeId,
+    pub pos: Position,
+    pub reassignable: bool,
+    pub bindings: Vec<TupleBinding>,
+
+    pub data_type: Option<Type>,
+    pub expr: Box<Expr>,
+}
+
+#[derive(Clone, Debug)]
+pub struct TupleBinding {
+    pub id: NodeId,
+    pub pos: Position,
+    pub name: Name,
+}
+
 #[derive(Clone, Debug)]
 pub struct StmtForType {
     pub id: NodeId,
@@ -1111,6 +1288,13 @@ pub struct StmtSpawnType {
     pub expr: Box<Expr>,
 }
 
+// placeholder for a statement that failed to parse
+#[derive(Clone, Debug)]
+pub struct StmtErrorType {
+    pub id: NodeId,
+    pub pos: Position,
+}
+
 #[derive(Clone, Debug)]
 pub struct CatchBlock {
     pub id: NodeId,
@@ -1249,6 +1433,7 @@ pub enum Expr {
     ExprLitBool(ExprLitBoolType),
     ExprLitStruct(ExprLitStructType),
     ExprIdent(ExprIdentType),
+    ExprPath(ExprPathType),
     ExprCall(ExprCallType),
     ExprDelegation(ExprDelegationType),
     ExprAssign(ExprAssignType),
@@ -1260,6 +1445,9 @@ pub enum Expr {
     ExprConv(ExprConvType),
     ExprTry(ExprTryType),
     ExprLambda(ExprLambdaType),
+    ExprMatch(ExprMatchType),
+    ExprTuple(ExprTupleType),
+    ExprTupleElement(ExprTupleElementType),
 }
 
 impl Expr {
@@ -1402,6 +1590,48 @@ impl Expr {
                         })
     }
 
+    pub fn create_match(id: NodeId,
+                        pos: Position,
+                        expr: Box<Expr>,
+                        arms: Vec<MatchArm>)
+                        -> Expr {
+        Expr::ExprMatch(ExprMatchType {
+                            id: id,
+                            pos: pos,
+                            expr: expr,
+                            arms: arms,
+                        })
+    }
+
+    pub fn create_tuple(id: NodeId, pos: Position, elements: Vec<Box<Expr>>) -> Expr {
+        Expr::ExprTuple(ExprTupleType {
+                            id: id,
+                            pos: pos,
+                            elements: elements,
+                        })
+    }
+
+    pub fn create_tuple_element(id: NodeId,
+                                pos: Position,
+                                object: Box<Expr>,
+                                index: usize)
+                                -> Expr {
+        Expr::ExprTupleElement(ExprTupleElementType {
+                                   id: id,
+                                   pos: pos,
+                                   object: object,
+                                   index: index,
+                               })
+    }
+
+    pub fn create_path(id: NodeId, pos: Position, path: Path) -> Expr {
+        Expr::ExprPath(ExprPathType {
+                           id: id,
+                           pos: pos,
+                           path: path,
+                       })
+    }
+
     pub fn create_call(id: NodeId,
                        pos: Position,
                        path: Path,
@@ -1432,10 +1662,16 @@ impl Expr {
                              })
     }
 
-    pub fn create_assign(id: NodeId, pos: Position, lhs: Box<Expr>, rhs: Box<Expr>) -> Expr {
+    pub fn create_assign(id: NodeId,
+                         pos: Position,
+                         op: Option<AssignOp>,
+                         lhs: Box<Expr>,
+                         rhs: Box<Expr>)
+                         -> Expr {
         Expr::ExprAssign(ExprAssignType {
                              id: id,
                              pos: pos,
+                             op: op,
                              lhs: lhs,
                              rhs: rhs,
                          })
@@ -1450,18 +1686,11 @@ impl Expr {
                         })
     }
 
-    pub fn create_lambda(id: NodeId,
-                         pos: Position,
-                         params: Vec<Param>,
-                         ret: Option<Box<Type>>,
-                         block: Box<Stmt>)
-                         -> Expr {
+    pub fn create_lambda(id: NodeId, pos: Position, fct: Box<Function>) -> Expr {
         Expr::ExprLambda(ExprLambdaType {
                              id: id,
                              pos: pos,
-                             params: params,
-                             ret: ret,
-                             block: block,
+                             fct: fct,
                          })
     }
 
@@ -1521,6 +1750,20 @@ impl Expr {
         }
     }
 
+    pub fn to_path(&self) -> Option<&ExprPathType> {
+        match *self {
+            Expr::ExprPath(ref val) => Some(val),
+            _ => None,
+        }
+    }
+
+    pub fn is_path(&self) -> bool {
+        match *self {
+            Expr::ExprPath(_) => true,
+            _ => false,
+        }
+    }
+
     pub fn to_call(&self) -> Option<&ExprCallType> {
         match *self {
             Expr::ExprCall(ref val) => Some(val),
@@ -1731,6 +1974,48 @@ impl Expr {
         }
     }
 
+    pub fn to_match(&self) -> Option<&ExprMatchType> {
+        match *self {
+            Expr::ExprMatch(ref val) => Some(val),
+            _ => None,
+        }
+    }
+
+    pub fn is_match(&self) -> bool {
+        match *self {
+            Expr::ExprMatch(_) => true,
+            _ => false,
+        }
+    }
+
+    pub fn to_tuple(&self) -> Option<&ExprTupleType> {
+        match *self {
+            Expr::ExprTuple(ref val) => Some(val),
+            _ => None,
+        }
+    }
+
+    pub fn is_tuple(&self) -> bool {
+        match *self {
+            Expr::ExprTuple(_) => true,
+            _ => false,
+        }
+    }
+
+    pub fn to_tuple_element(&self) -> Option<&ExprTupleElementType> {
+        match *self {
+            Expr::ExprTupleElement(ref val) => Some(val),
+            _ => None,
+        }
+    }
+
+    pub fn is_tuple_element(&self) -> bool {
+        match *self {
+            Expr::ExprTupleElement(_) => true,
+            _ => false,
+        }
+    }
+
     pub fn pos(&self) -> Position {
         match *self {
             Expr::ExprUn(ref val) => val.pos,
@@ -1742,6 +2027,7 @@ impl Expr {
             Expr::ExprLitBool(ref val) => val.pos,
             Expr::ExprLitStruct(ref val) => val.pos,
             Expr::ExprIdent(ref val) => val.pos,
+            Expr::ExprPath(ref val) => val.pos,
             Expr::ExprAssign(ref val) => val.pos,
             Expr::ExprCall(ref val) => val.pos,
             Expr::ExprDelegation(ref val) => val.pos,
@@ -1753,6 +2039,9 @@ impl Expr {
             Expr::ExprConv(ref val) => val.pos,
             Expr::ExprTry(ref val) => val.pos,
             Expr::ExprLambda(ref val) => val.pos,
+            Expr::ExprMatch(ref val) => val.pos,
+            Expr::ExprTuple(ref val) => val.pos,
+            Expr::ExprTupleElement(ref val) => val.pos,
         }
     }
 
@@ -1767,6 +2056,7 @@ impl Expr {
             Expr::ExprLitBool(ref val) => val.id,
             Expr::ExprLitStruct(ref val) => val.id,
             Expr::ExprIdent(ref val) => val.id,
+            Expr::ExprPath(ref val) => val.id,
             Expr::ExprAssign(ref val) => val.id,
             Expr::ExprCall(ref val) => val.id,
             Expr::ExprDelegation(ref val) => val.id,
@@ -1778,6 +2068,9 @@ impl Expr {
             Expr::ExprConv(ref val) => val.id,
             Expr::ExprTry(ref val) => val.id,
             Expr::ExprLambda(ref val) => val.id,
+            Expr::ExprMatch(ref val) => val.id,
+            Expr::ExprTuple(ref val) => val.id,
+            Expr::ExprTupleElement(ref val) => val.id,
         }
     }
 }
@@ -1807,6 +2100,90 @@ pub struct ExprConvType {
     pub data_type: Box<Type>,
 }
 
+#[derive(Clone, Debug)]
+pub struct ExprTupleType {
+    pub id: NodeId,
+    pub pos: Position,
+    pub elements: Vec<Box<Expr>>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ExprTupleElementType {
+    pub id: NodeId,
+    pub pos: Position,
+    pub object: Box<Expr>,
+    pub index: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct ExprMatchType {
+    pub id: NodeId,
+    pub pos: Position,
+    pub expr: Box<Expr>,
+    pub arms: Vec<MatchArm>,
+}
+
+#[derive(Clone, Debug)]
+pub struct MatchArm {
+    pub id: NodeId,
+    pub pos: Position,
+    pub pattern: MatchPattern,
+    pub value: Box<Expr>,
+}
+
+#[derive(Clone, Debug)]
+pub enum MatchPattern {
+    // integer, char, string or bool literal
+    Lit(Box<Expr>),
+
+    // matches instances of the given class, `_: Foo` omits the binding
+    Type(Option<Name>, Box<Type>),
+
+    // matches a variant of an enum, `Shape::Rect(w, _)` binds the payload
+    Enum(Path, Vec<MatchBinding>),
+
+    Underscore,
+}
+
+#[derive(Clone, Debug)]
+pub struct MatchBinding {
+    pub id: NodeId,
+    pub pos: Position,
+
+    // None for `_`
+    pub name: Option<Name>,
+}
+
+impl MatchPattern {
+    pub fn is_lit(&self) -> bool {
+        match *self {
+            MatchPattern::Lit(_) => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_type(&self) -> bool {
+        match *self {
+            MatchPattern::Type(_, _) => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_enum(&self) -> bool {
+        match *self {
+            MatchPattern::Enum(_, _) => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_underscore(&self) -> bool {
+        match *self {
+            MatchPattern::Underscore => true,
+            _ => false,
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct ExprTryType {
     pub id: NodeId,
@@ -1981,14 +2358,20 @@ pub struct ExprIdentType {
     pub type_params: Option<Vec<Type>>,
 }
 
+#[derive(Clone, Debug)]
+pub struct ExprPathType {
+    pub id: NodeId,
+    pub pos: Position,
+
+    pub path: Path,
+}
+
 #[derive(Clone, Debug)]
 pub struct ExprLambdaType {
     pub id: NodeId,
     pub pos: Position,
 
-    pub params: Vec<Param>,
-    pub ret: Option<Box<Type>>,
-    pub block: Box<Stmt>,
+    pub fct: Box<Function>,
 }
 
 #[derive(Clone, Debug)]
@@ -2036,10 +2419,19 @@ pub struct ExprAssignType {
     pub id: NodeId,
     pub pos: Position,
 
+    pub op: Option<AssignOp>,
     pub lhs: Box<Expr>,
     pub rhs: Box<Expr>,
 }
 
+// operator of compound assignments like `a += b`, it has its own
+// id since it is checked and compiled like a binary expression
+#[derive(Copy, Clone, Debug)]
+pub struct AssignOp {
+    pub id: NodeId,
+    pub op: BinOp,
+}
+
 #[derive(Clone, Debug)]
 pub struct ExprFieldType {
     pub id: NodeId,
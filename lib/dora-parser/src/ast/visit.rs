@@ -33,10 +33,20 @@ pub trait Visitor<'v>: Sized {
         walk_struct(self, s);
     }
 
+    fn visit_enum(&mut self, e: &'v Enum) {
+        walk_enum(self, e);
+    }
+
     fn visit_const(&mut self, c: &'v Const) {
         walk_const(self, c);
     }
 
+    fn visit_type_alias(&mut self, a: &'v TypeAlias) {
+        walk_type_alias(self, a);
+    }
+
+    fn visit_import(&mut self, _: &'v Import) {}
+
     fn visit_struct_field(&mut self, f: &'v StructField) {
         walk_struct_field(self, f);
     }
@@ -90,10 +100,13 @@ pub fn walk_file<'v, V: Visitor<'v>>(v: &mut V, f: &'v File) {
             ElemFunction(ref f) => v.visit_fct(f),
             ElemClass(ref c) => v.visit_class(c),
             ElemStruct(ref s) => v.visit_struct(s),
+            ElemEnum(ref e) => v.visit_enum(e),
             ElemTrait(ref t) => v.visit_trait(t),
             ElemImpl(ref i) => v.visit_impl(i),
             ElemGlobal(ref g) => v.visit_global(g),
             ElemConst(ref c) => v.visit_const(c),
+            ElemTypeAlias(ref a) => v.visit_type_alias(a),
+            ElemImport(ref i) => v.visit_import(i),
         }
     }
 }
@@ -137,6 +150,10 @@ pub fn walk_const<'v, V: Visitor<'v>>(v: &mut V, c: &'v Const) {
     v.visit_expr_top(&c.expr);
 }
 
+pub fn walk_type_alias<'v, V: Visitor<'v>>(v: &mut V, a: &'v TypeAlias) {
+    v.visit_type(&a.data_type);
+}
+
 pub fn walk_struct<'v, V: Visitor<'v>>(v: &mut V, s: &'v Struct) {
     for f in &s.fields {
         v.visit_struct_field(f);
@@ -147,6 +164,14 @@ pub fn walk_struct_field<'v, V: Visitor<'v>>(v: &mut V, f: &'v StructField) {
     v.visit_type(&f.data_type);
 }
 
+pub fn walk_enum<'v, V: Visitor<'v>>(v: &mut V, e: &'v Enum) {
+    for variant in &e.variants {
+        for ty in &variant.types {
+            v.visit_type(ty);
+        }
+    }
+}
+
 pub fn walk_field<'v, V: Visitor<'v>>(v: &mut V, f: &'v Field) {
     v.visit_type(&f.data_type);
 }
@@ -269,12 +294,21 @@ pub fn walk_stmt<'v, V: Visitor<'v>>(v: &mut V, s: &'v Stmt) {
             }
         }
 
+        StmtLetTuple(ref value) => {
+            if let Some(ref ty) = value.data_type {
+                v.visit_type(ty);
+            }
+
+            v.visit_expr_top(&value.expr);
+        }
+
         StmtSpawn(ref value) => {
             v.visit_expr_top(&value.expr);
         }
 
         StmtBreak(_) => {}
         StmtContinue(_) => {}
+        StmtError(_) => {}
     }
 }
 
@@ -334,24 +368,40 @@ pub fn walk_expr<'v, V: Visitor<'v>>(v: &mut V, e: &'v Expr) {
             v.visit_expr(&value.expr);
         }
 
-        ExprLitStruct(ref value) => {
-            for arg in &value.args {
-                v.visit_expr(&arg.expr);
+        ExprTuple(ref value) => {
+            for element in &value.elements {
+                v.visit_expr(element);
             }
         }
 
-        ExprLambda(ref value) => {
-            for param in &value.params {
-                v.visit_type(&param.data_type);
-            }
+        ExprTupleElement(ref value) => {
+            v.visit_expr(&value.object);
+        }
+
+        ExprMatch(ref value) => {
+            v.visit_expr(&value.expr);
 
-            if let Some(ref ret) = value.ret {
-                v.visit_type(ret);
+            for arm in &value.arms {
+                match arm.pattern {
+                    MatchPattern::Lit(ref lit) => v.visit_expr(lit),
+                    MatchPattern::Type(_, ref ty) => v.visit_type(ty),
+                    MatchPattern::Enum(_, _) | MatchPattern::Underscore => {}
+                }
+
+                v.visit_expr(&arm.value);
             }
+        }
 
-            v.visit_stmt(&value.block);
+        ExprLitStruct(ref value) => {
+            for arg in &value.args {
+                v.visit_expr(&arg.expr);
+            }
         }
 
+        // the body of a lambda is a function on its own
+        // and is visited separately
+        ExprLambda(_) => {}
+
         ExprSuper(_) => {}
         ExprSelf(_) => {}
         ExprLitChar(_) => {}
@@ -360,6 +410,7 @@ pub fn walk_expr<'v, V: Visitor<'v>>(v: &mut V, e: &'v Expr) {
         ExprLitStr(_) => {}
         ExprLitBool(_) => {}
         ExprIdent(_) => {}
+        ExprPath(_) => {}
         ExprNil(_) => {}
     }
 }
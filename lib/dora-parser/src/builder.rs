@@ -37,6 +37,7 @@ impl<'a> Builder<'a> {
         Box::new(Expr::ExprAssign(ExprAssignType {
                                       id: id,
                                       pos: Position::new(1, 1),
+                                      op: None,
                                       lhs: lhs,
                                       rhs: rhs,
                                   }))
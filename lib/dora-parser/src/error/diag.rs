@@ -1,21 +1,38 @@
+use ast::Ast;
 use error::msg::Msg;
 use error::msg::MsgWithPos;
 
 use lexer::position::Position;
 
+// tab width of the reader, needed to find the column in the source line
+const TAB_WIDTH: usize = 4;
+
 pub struct Diagnostic {
     errors: Vec<MsgWithPos>,
+    warnings: Vec<MsgWithPos>,
 }
 
 impl Diagnostic {
     pub fn new() -> Diagnostic {
-        Diagnostic { errors: Vec::new() }
+        Diagnostic {
+            errors: Vec::new(),
+            warnings: Vec::new(),
+        }
     }
 
     pub fn errors(&self) -> &[MsgWithPos] {
         &self.errors
     }
 
+    pub fn warnings(&self) -> &[MsgWithPos] {
+        &self.warnings
+    }
+
+    // warnings never stop compilation
+    pub fn warn(&mut self, pos: Position, msg: Msg) {
+        self.warnings.push(MsgWithPos::new(pos, msg));
+    }
+
     pub fn report(&mut self, pos: Position, msg: Msg) {
         self.errors.push(MsgWithPos::new(pos, msg));
     }
@@ -29,9 +46,154 @@ impl Diagnostic {
         !self.errors.is_empty()
     }
 
-    pub fn dump(&self) {
+    pub fn has_warnings(&self) -> bool {
+        !self.warnings.is_empty()
+    }
+
+    pub fn dump(&self, ast: &Ast) {
         for err in &self.errors {
-            println!("{}", &err.message());
+            print!("{}", render(ast, err));
+        }
+    }
+}
+
+// renders the error with its location, the source line and all notes
+pub fn render(ast: &Ast, err: &MsgWithPos) -> String {
+    render_with_severity(ast, "error", err)
+}
+
+pub fn render_warning(ast: &Ast, warning: &MsgWithPos) -> String {
+    render_with_severity(ast, "warning", warning)
+}
+
+fn render_with_severity(ast: &Ast, severity: &str, err: &MsgWithPos) -> String {
+    let mut output = format!("{} at {}: {}\n",
+                             severity,
+                             location(ast, err.pos),
+                             err.msg.message());
+    output.push_str(&snippet(ast, err.pos));
+
+    for (pos, note) in err.notes() {
+        output.push_str(&format!("note at {}: {}\n", location(ast, pos), note));
+        output.push_str(&snippet(ast, pos));
+    }
+
+    output
+}
+
+pub fn location(ast: &Ast, pos: Position) -> String {
+    match pos.file {
+        Some(file) => format!("{}:{}", ast.files[file.idx()].path, pos),
+        None => format!("{}", pos),
+    }
+}
+
+// shows the source line of the position and underlines the token
+// at the column
+fn snippet(ast: &Ast, pos: Position) -> String {
+    let file = match pos.file {
+        Some(file) => &ast.files[file.idx()],
+        None => return String::new(),
+    };
+
+    let line = match file.content.lines().nth(pos.line as usize - 1) {
+        Some(line) => expand_tabs(line),
+        None => return String::new(),
+    };
+
+    let column = pos.column as usize - 1;
+    let len = line.chars()
+        .skip(column)
+        .take_while(|&ch| ch.is_alphanumeric() || ch == '_')
+        .count();
+
+    let lineno = pos.line.to_string();
+    let gutter = " ".repeat(lineno.len());
+
+    format!("{} |\n{} | {}\n{} | {}{}\n",
+            gutter,
+            lineno,
+            line,
+            gutter,
+            " ".repeat(column),
+            "^".repeat(if len > 0 { len } else { 1 }))
+}
+
+fn expand_tabs(line: &str) -> String {
+    let mut result = String::new();
+
+    for ch in line.chars() {
+        if ch == '\t' {
+            let width = TAB_WIDTH - result.chars().count() % TAB_WIDTH;
+            result.push_str(&" ".repeat(width));
+        } else {
+            result.push(ch);
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ast::File;
+    use lexer::position::FileId;
+
+    fn ast(content: &str) -> Ast {
+        let mut ast = Ast::new();
+        ast.files.push(File {
+                           path: "foo.dora".into(),
+                           content: content.into(),
+                           module: Vec::new(),
+                           elements: Vec::new(),
+                       });
+
+        ast
+    }
+
+    fn pos(line: u32, column: u32) -> Position {
+        Position {
+            file: Some(FileId(0)),
+            line: line,
+            column: column,
         }
     }
+
+    #[test]
+    fn test_render() {
+        let ast = ast("fun f() {\n    let x = y;\n}\n");
+        let err = MsgWithPos::new(pos(2, 13), Msg::UnknownIdentifier("y".into()));
+
+        assert_eq!("error at foo.dora:2:13: unknown identifier `y`.\n  |\n2 |     let x = y;\n  |             ^\n",
+                   render(&ast, &err));
+    }
+
+    #[test]
+    fn test_render_without_file() {
+        let ast = ast("");
+        let err = MsgWithPos::new(Position::new(1, 1), Msg::MainNotFound);
+
+        assert_eq!("error at 1:1: no `main` function found in the program\n",
+                   render(&ast, &err));
+    }
+
+    #[test]
+    fn test_render_note() {
+        let ast = ast("class Foo {\n\tfun bar() {}\n\tfun bar() {}\n}\n");
+        let msg = Msg::MethodExists("Foo".into(), "bar".into(), pos(2, 5));
+        let err = MsgWithPos::new(pos(3, 5), msg);
+
+        assert_eq!("error at foo.dora:3:5: method with name `bar` already exists in class `Foo` at line 2:5.\n  |\n3 |     fun bar() {}\n  |     ^^^\nnote at foo.dora:2:5: method `bar` is first defined here.\n  |\n2 |     fun bar() {}\n  |     ^^^\n",
+                   render(&ast, &err));
+    }
+
+    #[test]
+    fn test_render_warning() {
+        let ast = ast("fun f() {\n    let x = 1;\n}\n");
+        let warning = MsgWithPos::new(pos(2, 5), Msg::UnusedVariable("x".into()));
+
+        assert_eq!("warning at foo.dora:2:5: variable `x` is never used.\n  |\n2 |     let x = 1;\n  |     ^^^\n",
+                   render_warning(&ast, &warning));
+    }
 }
@@ -23,6 +23,7 @@ pub enum Msg {
     ShadowParam(String),
     ShadowClass(String),
     ShadowStruct(String),
+    ShadowEnum(String),
     ShadowTrait(String),
     ShadowField(String),
     ShadowGlobal(String),
@@ -51,6 +52,7 @@ pub enum Msg {
     CatchOrFinallyExpected,
     LetMissingInitialization,
     LetReassigned,
+    CapturedVarReassigned(String),
     UnderivableType(String),
     CycleInHierarchy,
     SuperfluousOverride(String),
@@ -88,6 +90,7 @@ pub enum Msg {
     TraitMethodWithBody,
     TryNeedsCall,
     TryCallNonThrowing,
+    TryOptValueType(String),
     ThrowingCallWithoutTry,
     TypeParamsExpected,
     TypeParamNameNotUnique(String),
@@ -114,9 +117,187 @@ pub enum Msg {
     MakeIteratorReturnType(String),
     UnknownStructField(String, String),
     StructFieldNotInitialized(String, String),
+    LambdaExpected(String),
+    SpawnExpected(String),
+    EnumExpected(String),
+    UnknownEnumValue(String, String),
+    ShadowEnumValue(String),
+    EnumArgsIncompatible(String, String, Vec<String>, Vec<String>),
+    ExpectedMatchPattern(String),
+    MatchTypeUnsupported(String),
+    MatchPatternIncompatible(String, String),
+    MatchArmsIncompatible(String, String),
+    MatchNotExhaustive,
+    MatchArmUnreachable,
+    MatchPatternWrongNumberOfBindings(String, String, usize, usize),
+    ShadowTypeAlias(String),
+    RecursiveTypeAlias(String),
+    TypeAliasBoundsNotAllowed(String),
+    WrongNumberTypeAliasParams(String, usize, usize),
+    InvalidTupleIndex(String),
+    LetTupleMismatch(String, usize),
+    ShadowModule(String),
+    UnknownImport(String),
+    NotAccessible(String),
+    UnusedVariable(String),
+    UnusedParam(String),
+    UnusedAssignment(String),
+    UnreachableCode,
+    UnusedImport(String),
+    VarNeverReassigned(String),
+    VarPossiblyUnassigned(String, Position),
+    FieldPossiblyUnassigned(String, String, Position),
 }
 
 impl Msg {
+    // stable identifier of the message for tools, must not change when
+    // the variant or its message is changed
+    pub fn code(&self) -> &'static str {
+        match *self {
+            Unimplemented => "Unimplemented",
+            UnknownClass(..) => "UnknownClass",
+            UnknownType(..) => "UnknownType",
+            UnknownIdentifier(..) => "UnknownIdentifier",
+            UnknownStruct(..) => "UnknownStruct",
+            UnknownFunction(..) => "UnknownFunction",
+            UnknownField(..) => "UnknownField",
+            UnknownMethod(..) => "UnknownMethod",
+            UnknownStaticMethod(..) => "UnknownStaticMethod",
+            UnknownCtor(..) => "UnknownCtor",
+            MethodExists(..) => "MethodExists",
+            IncompatibleWithNil(..) => "IncompatibleWithNil",
+            IdentifierExists(..) => "IdentifierExists",
+            ShadowFunction(..) => "ShadowFunction",
+            ShadowParam(..) => "ShadowParam",
+            ShadowClass(..) => "ShadowClass",
+            ShadowStruct(..) => "ShadowStruct",
+            ShadowEnum(..) => "ShadowEnum",
+            ShadowTrait(..) => "ShadowTrait",
+            ShadowField(..) => "ShadowField",
+            ShadowGlobal(..) => "ShadowGlobal",
+            ShadowConst(..) => "ShadowConst",
+            VarNeedsTypeInfo(..) => "VarNeedsTypeInfo",
+            ParamTypesIncompatible(..) => "ParamTypesIncompatible",
+            WhileCondType(..) => "WhileCondType",
+            IfCondType(..) => "IfCondType",
+            ReturnType(..) => "ReturnType",
+            LvalueExpected => "LvalueExpected",
+            AssignType(..) => "AssignType",
+            AssignField(..) => "AssignField",
+            UnOpType(..) => "UnOpType",
+            BinOpType(..) => "BinOpType",
+            ConstValueExpected => "ConstValueExpected",
+            OutsideLoop => "OutsideLoop",
+            NoReturnValue => "NoReturnValue",
+            MainNotFound => "MainNotFound",
+            WrongMainDefinition => "WrongMainDefinition",
+            ThisUnavailable => "ThisUnavailable",
+            SelfTypeUnavailable => "SelfTypeUnavailable",
+            SuperUnavailable => "SuperUnavailable",
+            SuperNeedsMethodCall => "SuperNeedsMethodCall",
+            ReferenceTypeExpected(..) => "ReferenceTypeExpected",
+            ThrowNil => "ThrowNil",
+            CatchOrFinallyExpected => "CatchOrFinallyExpected",
+            LetMissingInitialization => "LetMissingInitialization",
+            LetReassigned => "LetReassigned",
+            CapturedVarReassigned(..) => "CapturedVarReassigned",
+            UnderivableType(..) => "UnderivableType",
+            CycleInHierarchy => "CycleInHierarchy",
+            SuperfluousOverride(..) => "SuperfluousOverride",
+            SuperfluousOpen(..) => "SuperfluousOpen",
+            MissingOverride(..) => "MissingOverride",
+            ThrowsDifference(..) => "ThrowsDifference",
+            MethodNotOverridable(..) => "MethodNotOverridable",
+            TypesIncompatible(..) => "TypesIncompatible",
+            ReturnTypeMismatch(..) => "ReturnTypeMismatch",
+            UnresolvedInternal => "UnresolvedInternal",
+            UnclosedComment => "UnclosedComment",
+            UnknownChar(..) => "UnknownChar",
+            UnclosedChar => "UnclosedChar",
+            UnclosedString => "UnclosedString",
+            NumberOverflow(..) => "NumberOverflow",
+            ExpectedClass(..) => "ExpectedClass",
+            ExpectedFactor(..) => "ExpectedFactor",
+            ExpectedToken(..) => "ExpectedToken",
+            ExpectedTopLevelElement(..) => "ExpectedTopLevelElement",
+            ExpectedTrait(..) => "ExpectedTrait",
+            ExpectedType(..) => "ExpectedType",
+            ExpectedIdentifier(..) => "ExpectedIdentifier",
+            MisplacedElse => "MisplacedElse",
+            IoError => "IoError",
+            ExpectedClassElement(..) => "ExpectedClassElement",
+            RedundantModifier(..) => "RedundantModifier",
+            MisplacedModifier(..) => "MisplacedModifier",
+            InvalidEscapeSequence(..) => "InvalidEscapeSequence",
+            MissingFctBody => "MissingFctBody",
+            FctCallExpected => "FctCallExpected",
+            ThisOrSuperExpected(..) => "ThisOrSuperExpected",
+            NoSuperDelegationWithPrimaryCtor(..) => "NoSuperDelegationWithPrimaryCtor",
+            NoSuperClass(..) => "NoSuperClass",
+            RecursiveStructure => "RecursiveStructure",
+            TraitMethodWithBody => "TraitMethodWithBody",
+            TryNeedsCall => "TryNeedsCall",
+            TryCallNonThrowing => "TryCallNonThrowing",
+            TryOptValueType(..) => "TryOptValueType",
+            ThrowingCallWithoutTry => "ThrowingCallWithoutTry",
+            TypeParamsExpected => "TypeParamsExpected",
+            TypeParamNameNotUnique(..) => "TypeParamNameNotUnique",
+            StaticMethodNotInTrait(..) => "StaticMethodNotInTrait",
+            MethodNotInTrait(..) => "MethodNotInTrait",
+            StaticMethodMissingFromTrait(..) => "StaticMethodMissingFromTrait",
+            MethodMissingFromTrait(..) => "MethodMissingFromTrait",
+            WrongNumberTypeParams(..) => "WrongNumberTypeParams",
+            ClassExpected(..) => "ClassExpected",
+            ClassExpectedAsTypeParam => "ClassExpectedAsTypeParam",
+            AssignmentToConst => "AssignmentToConst",
+            BoundExpected => "BoundExpected",
+            NoTypeParamsExpected => "NoTypeParamsExpected",
+            MultipleClassBounds => "MultipleClassBounds",
+            DuplicateTraitBound => "DuplicateTraitBound",
+            ClassBoundNotSatisfied(..) => "ClassBoundNotSatisfied",
+            TraitBoundNotSatisfied(..) => "TraitBoundNotSatisfied",
+            AbstractMethodNotInAbstractClass => "AbstractMethodNotInAbstractClass",
+            AbstractMethodWithImplementation => "AbstractMethodWithImplementation",
+            NewAbstractClass => "NewAbstractClass",
+            MissingAbstractOverride(..) => "MissingAbstractOverride",
+            ModifierNotAllowedForStaticMethod(..) => "ModifierNotAllowedForStaticMethod",
+            GlobalInitializerNotSupported => "GlobalInitializerNotSupported",
+            MakeIteratorReturnType(..) => "MakeIteratorReturnType",
+            UnknownStructField(..) => "UnknownStructField",
+            StructFieldNotInitialized(..) => "StructFieldNotInitialized",
+            LambdaExpected(..) => "LambdaExpected",
+            SpawnExpected(..) => "SpawnExpected",
+            EnumExpected(..) => "EnumExpected",
+            UnknownEnumValue(..) => "UnknownEnumValue",
+            ShadowEnumValue(..) => "ShadowEnumValue",
+            EnumArgsIncompatible(..) => "EnumArgsIncompatible",
+            ExpectedMatchPattern(..) => "ExpectedMatchPattern",
+            MatchTypeUnsupported(..) => "MatchTypeUnsupported",
+            MatchPatternIncompatible(..) => "MatchPatternIncompatible",
+            MatchArmsIncompatible(..) => "MatchArmsIncompatible",
+            MatchNotExhaustive => "MatchNotExhaustive",
+            MatchArmUnreachable => "MatchArmUnreachable",
+            MatchPatternWrongNumberOfBindings(..) => "MatchPatternWrongNumberOfBindings",
+            ShadowTypeAlias(..) => "ShadowTypeAlias",
+            RecursiveTypeAlias(..) => "RecursiveTypeAlias",
+            TypeAliasBoundsNotAllowed(..) => "TypeAliasBoundsNotAllowed",
+            WrongNumberTypeAliasParams(..) => "WrongNumberTypeAliasParams",
+            InvalidTupleIndex(..) => "InvalidTupleIndex",
+            LetTupleMismatch(..) => "LetTupleMismatch",
+            ShadowModule(..) => "ShadowModule",
+            UnknownImport(..) => "UnknownImport",
+            NotAccessible(..) => "NotAccessible",
+            UnusedVariable(..) => "UnusedVariable",
+            UnusedParam(..) => "UnusedParam",
+            UnusedAssignment(..) => "UnusedAssignment",
+            UnreachableCode => "UnreachableCode",
+            UnusedImport(..) => "UnusedImport",
+            VarNeverReassigned(..) => "VarNeverReassigned",
+            VarPossiblyUnassigned(..) => "VarPossiblyUnassigned",
+            FieldPossiblyUnassigned(..) => "FieldPossiblyUnassigned",
+        }
+    }
+
     pub fn message(&self) -> String {
         match *self {
             Unimplemented => format!("feature not implemented yet."),
@@ -155,6 +336,7 @@ impl Msg {
             ShadowParam(ref name) => format!("can not shadow param `{}`.", name),
             ShadowClass(ref name) => format!("can not shadow class `{}`.", name),
             ShadowStruct(ref name) => format!("can not shadow struct `{}`.", name),
+            ShadowEnum(ref name) => format!("can not shadow enum `{}`.", name),
             ShadowTrait(ref name) => format!("can not shadow trait `{}`.", name),
             ShadowField(ref name) => format!("field with name `{}` already exists.", name),
             ShadowGlobal(ref name) => format!("can not shadow global variable `{}`.", name),
@@ -227,6 +409,9 @@ impl Msg {
             CatchOrFinallyExpected => "`try` without `catch` or `finally`.".into(),
             LetMissingInitialization => "`let` binding is missing initialization.".into(),
             LetReassigned => "`let` binding cannot be reassigned.".into(),
+            CapturedVarReassigned(ref name) => {
+                format!("captured variable `{}` cannot be reassigned inside a lambda.", name)
+            }
             UnderivableType(ref name) => format!("type `{}` cannot be used as super class.", name),
             CycleInHierarchy => "cycle in type hierarchy detected.".into(),
             SuperfluousOverride(_) => {
@@ -284,6 +469,10 @@ impl Msg {
             TraitMethodWithBody => "trait method is not allowed to have definition".into(),
             TryNeedsCall => "`try` expects function or method call.".into(),
             TryCallNonThrowing => "given function or method call for `try` does not throw.".into(),
+            TryOptValueType(ref ty) => {
+                format!("`try?` cannot be used with value type `{}`, use `try ... else` instead.",
+                        ty)
+            }
             ThrowingCallWithoutTry => {
                 "function or method call that is able to throw, needs `try`.".into()
             }
@@ -363,6 +552,88 @@ impl Msg {
             StructFieldNotInitialized(ref struc, ref field) => {
                 format!("field `{}` in struct `{}` not initialized.", field, struc)
             }
+            LambdaExpected(ref name) => format!("`{}` is not a lambda.", name),
+            SpawnExpected(ref ty) => {
+                format!("spawn expects lambda of type `() -> ()` but got `{}`.", ty)
+            }
+            EnumExpected(ref name) => format!("`{}` is not an enum.", name),
+            UnknownEnumValue(ref xenum, ref name) => {
+                format!("enum `{}` does not have variant named `{}`.", xenum, name)
+            }
+            ShadowEnumValue(ref name) => format!("variant with name `{}` already exists.", name),
+            EnumArgsIncompatible(ref xenum, ref name, ref def, ref expr) => {
+                let def = def.join(", ");
+                let expr = expr.join(", ");
+
+                format!("variant `{}::{}({})` cannot be constructed as `{}::{}({})`.",
+                        xenum,
+                        name,
+                        def,
+                        xenum,
+                        name,
+                        expr)
+            }
+            ExpectedMatchPattern(ref got) => format!("pattern expected but got {}.", got),
+            MatchTypeUnsupported(ref ty) => format!("cannot match on value of type `{}`.", ty),
+            MatchPatternIncompatible(ref pattern, ref value) => {
+                format!("pattern of type `{}` cannot match value of type `{}`.",
+                        pattern,
+                        value)
+            }
+            MatchArmsIncompatible(ref first, ref arm) => {
+                format!("match arm of type `{}` is incompatible with first arm of type `{}`.",
+                        arm,
+                        first)
+            }
+            MatchNotExhaustive => "match is not exhaustive, add a `_` arm.".into(),
+            MatchArmUnreachable => "match arm is unreachable.".into(),
+            MatchPatternWrongNumberOfBindings(ref xenum, ref name, def, pattern) => {
+                format!("variant `{}::{}` has {} payload value(s) but the pattern binds {}.",
+                        xenum,
+                        name,
+                        def,
+                        pattern)
+            }
+            ShadowTypeAlias(ref name) => format!("can not shadow type alias `{}`.", name),
+            RecursiveTypeAlias(ref name) => format!("type alias `{}` refers to itself.", name),
+            TypeAliasBoundsNotAllowed(ref name) => {
+                format!("type params of type alias `{}` cannot have bounds.", name)
+            }
+            WrongNumberTypeAliasParams(ref name, exp, actual) => format!(
+                "type alias `{}` expects {} type parameters but got {}.",
+                name, exp, actual
+            ),
+            InvalidTupleIndex(ref index) => format!("`{}` is not a valid tuple index.", index),
+            LetTupleMismatch(ref ty, count) => {
+                format!("cannot destructure value of type `{}` into {} variables.", ty, count)
+            }
+            ShadowModule(ref name) => format!("can not shadow module `{}`.", name),
+            UnknownImport(ref path) => format!("unresolved import `{}`.", path),
+            NotAccessible(ref path) => format!("`{}` is not public.", path),
+            UnusedVariable(ref name) => format!("variable `{}` is never used.", name),
+            UnusedParam(ref name) => format!("parameter `{}` is never used.", name),
+            UnusedAssignment(ref name) => {
+                format!("value assigned to `{}` is never read.", name)
+            }
+            UnreachableCode => "unreachable code.".into(),
+            UnusedImport(ref path) => format!("import `{}` is never used.", path),
+            VarNeverReassigned(ref name) => {
+                format!("variable `{}` is never reassigned, use `let` instead of `var`.",
+                        name)
+            }
+            VarPossiblyUnassigned(ref name, pos) => {
+                format!("variable `{}` is possibly unassigned, \
+                         no assignment on the path through line {}.",
+                        name,
+                        pos)
+            }
+            FieldPossiblyUnassigned(ref cls, ref name, pos) => {
+                format!("field `{}` of class `{}` is possibly unassigned when the constructor \
+                         returns, no assignment on the path through line {}.",
+                        name,
+                        cls,
+                        pos)
+            }
         }
     }
 }
@@ -381,6 +652,20 @@ impl MsgWithPos {
     pub fn message(&self) -> String {
         format!("error at {}: {}", self.pos, self.msg.message())
     }
+
+    // secondary positions of the message, e.g. previous definitions
+    pub fn notes(&self) -> Vec<(Position, String)> {
+        match self.msg {
+            MethodExists(_, ref name, pos) => {
+                vec![(pos, format!("method `{}` is first defined here.", name))]
+            }
+            VarPossiblyUnassigned(ref name, pos) |
+            FieldPossiblyUnassigned(_, ref name, pos) if pos != self.pos => {
+                vec![(pos, format!("`{}` is not assigned on this path.", name))]
+            }
+            _ => Vec::new(),
+        }
+    }
 }
 
 impl fmt::Display for MsgWithPos {
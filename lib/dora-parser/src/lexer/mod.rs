@@ -35,6 +35,10 @@ impl Lexer {
         self.reader.filename()
     }
 
+    pub fn src(&self) -> &str {
+        self.reader.src()
+    }
+
     pub fn read_token(&mut self) -> Result<Token, MsgWithPos> {
         loop {
             self.skip_white();
@@ -231,19 +235,19 @@ impl Lexer {
         let nnch = self.next().unwrap_or('x');
 
         tok.kind = match ch {
-            '+' => TokenKind::Add,
+            '+' => self.with_eq(TokenKind::Add, TokenKind::AddEq),
             '-' => {
                 if nch == '>' {
                     self.read_char();
                     TokenKind::Arrow
                 } else {
-                    TokenKind::Sub
+                    self.with_eq(TokenKind::Sub, TokenKind::SubEq)
                 }
             }
 
-            '*' => TokenKind::Mul,
-            '/' => TokenKind::Div,
-            '%' => TokenKind::Mod,
+            '*' => self.with_eq(TokenKind::Mul, TokenKind::MulEq),
+            '/' => self.with_eq(TokenKind::Div, TokenKind::DivEq),
+            '%' => self.with_eq(TokenKind::Mod, TokenKind::ModEq),
 
             '(' => TokenKind::LParen,
             ')' => TokenKind::RParen,
@@ -257,7 +261,7 @@ impl Lexer {
                     self.read_char();
                     TokenKind::Or
                 } else {
-                    TokenKind::BitOr
+                    self.with_eq(TokenKind::BitOr, TokenKind::BitOrEq)
                 }
             }
 
@@ -266,11 +270,11 @@ impl Lexer {
                     self.read_char();
                     TokenKind::And
                 } else {
-                    TokenKind::BitAnd
+                    self.with_eq(TokenKind::BitAnd, TokenKind::BitAndEq)
                 }
             }
 
-            '^' => TokenKind::Caret,
+            '^' => self.with_eq(TokenKind::Caret, TokenKind::CaretEq),
             '~' => TokenKind::Tilde,
             ',' => TokenKind::Comma,
             ';' => TokenKind::Semicolon,
@@ -293,6 +297,9 @@ impl Lexer {
                     } else {
                         TokenKind::EqEq
                     }
+                } else if nch == '>' {
+                    self.read_char();
+                    TokenKind::FatArrow
                 } else {
                     TokenKind::Eq
                 }
@@ -307,7 +314,7 @@ impl Lexer {
 
                     '<' => {
                         self.read_char();
-                        TokenKind::LtLt
+                        self.with_eq(TokenKind::LtLt, TokenKind::LtLtEq)
                     }
 
                     _ => TokenKind::Lt,
@@ -326,9 +333,9 @@ impl Lexer {
 
                         if nnch == '>' {
                             self.read_char();
-                            TokenKind::GtGtGt
+                            self.with_eq(TokenKind::GtGtGt, TokenKind::GtGtGtEq)
                         } else {
-                            TokenKind::GtGt
+                            self.with_eq(TokenKind::GtGt, TokenKind::GtGtEq)
                         }
                     }
 
@@ -359,6 +366,16 @@ impl Lexer {
         Ok(tok)
     }
 
+    // operators directly followed by `=` are compound assignments like `+=`
+    fn with_eq(&mut self, kind: TokenKind, assign_kind: TokenKind) -> TokenKind {
+        if self.cur() == Some('=') {
+            self.read_char();
+            assign_kind
+        } else {
+            kind
+        }
+    }
+
     fn read_number(&mut self) -> Result<Token, MsgWithPos> {
         let pos = self.reader.pos();
         let mut value = String::new();
@@ -560,6 +577,7 @@ fn keywords_in_map() -> HashMap<&'static str, TokenKind> {
     keywords.insert("nil", TokenKind::Nil);
     keywords.insert("enum", TokenKind::Enum);
     keywords.insert("type", TokenKind::Type);
+    keywords.insert("import", TokenKind::Import);
     keywords.insert("alias", TokenKind::Alias);
     keywords.insert("struct", TokenKind::Struct);
     keywords.insert("trait", TokenKind::Trait);
@@ -582,6 +600,7 @@ fn keywords_in_map() -> HashMap<&'static str, TokenKind> {
     keywords.insert("pub", TokenKind::Pub);
     keywords.insert("static", TokenKind::Static);
     keywords.insert("spawn", TokenKind::Spawn);
+    keywords.insert("match", TokenKind::Match);
     keywords.insert("const", TokenKind::Const);
 
     keywords
@@ -953,7 +972,10 @@ mod tests {
         assert_tok(&mut reader, TokenKind::Trait, 1, 24);
         assert_tok(&mut reader, TokenKind::Const, 1, 30);
 
-        let mut reader = Lexer::from_str("pub static for in impl Self spawn");
+        let mut reader = Lexer::from_str("import");
+        assert_tok(&mut reader, TokenKind::Import, 1, 1);
+
+        let mut reader = Lexer::from_str("pub static for in impl Self spawn match");
         assert_tok(&mut reader, TokenKind::Pub, 1, 1);
         assert_tok(&mut reader, TokenKind::Static, 1, 5);
         assert_tok(&mut reader, TokenKind::For, 1, 12);
@@ -961,6 +983,7 @@ mod tests {
         assert_tok(&mut reader, TokenKind::Impl, 1, 19);
         assert_tok(&mut reader, TokenKind::CapitalThis, 1, 24);
         assert_tok(&mut reader, TokenKind::Spawn, 1, 29);
+        assert_tok(&mut reader, TokenKind::Match, 1, 35);
 
         let mut reader = Lexer::from_str("abstract open override defer");
         assert_tok(&mut reader, TokenKind::Abstract, 1, 1);
@@ -973,8 +996,7 @@ mod tests {
     fn test_operators() {
         let mut reader = Lexer::from_str("==+=-*/%~.");
         assert_tok(&mut reader, TokenKind::EqEq, 1, 1);
-        assert_tok(&mut reader, TokenKind::Add, 1, 3);
-        assert_tok(&mut reader, TokenKind::Eq, 1, 4);
+        assert_tok(&mut reader, TokenKind::AddEq, 1, 3);
         assert_tok(&mut reader, TokenKind::Sub, 1, 5);
         assert_tok(&mut reader, TokenKind::Mul, 1, 6);
         assert_tok(&mut reader, TokenKind::Div, 1, 7);
@@ -1001,6 +1023,10 @@ mod tests {
         let mut reader = Lexer::from_str("->");
         assert_tok(&mut reader, TokenKind::Arrow, 1, 1);
 
+        let mut reader = Lexer::from_str("=>=");
+        assert_tok(&mut reader, TokenKind::FatArrow, 1, 1);
+        assert_tok(&mut reader, TokenKind::Eq, 1, 3);
+
         let mut reader = Lexer::from_str("try!try?1");
         assert_tok(&mut reader, TokenKind::TryForce, 1, 1);
         assert_tok(&mut reader, TokenKind::TryOpt, 1, 5);
@@ -1016,4 +1042,27 @@ mod tests {
         assert_tok(&mut reader, TokenKind::Underscore, 1, 8);
         assert_tok(&mut reader, TokenKind::Sep, 1, 9);
     }
+
+    #[test]
+    fn test_assign_operators() {
+        let mut reader = Lexer::from_str("+=-=*=/=%=");
+        assert_tok(&mut reader, TokenKind::AddEq, 1, 1);
+        assert_tok(&mut reader, TokenKind::SubEq, 1, 3);
+        assert_tok(&mut reader, TokenKind::MulEq, 1, 5);
+        assert_tok(&mut reader, TokenKind::DivEq, 1, 7);
+        assert_tok(&mut reader, TokenKind::ModEq, 1, 9);
+
+        let mut reader = Lexer::from_str("|=&=^=||=");
+        assert_tok(&mut reader, TokenKind::BitOrEq, 1, 1);
+        assert_tok(&mut reader, TokenKind::BitAndEq, 1, 3);
+        assert_tok(&mut reader, TokenKind::CaretEq, 1, 5);
+        assert_tok(&mut reader, TokenKind::Or, 1, 7);
+        assert_tok(&mut reader, TokenKind::Eq, 1, 9);
+
+        let mut reader = Lexer::from_str("<<=>>=>>>=<=");
+        assert_tok(&mut reader, TokenKind::LtLtEq, 1, 1);
+        assert_tok(&mut reader, TokenKind::GtGtEq, 1, 4);
+        assert_tok(&mut reader, TokenKind::GtGtGtEq, 1, 7);
+        assert_tok(&mut reader, TokenKind::Le, 1, 11);
+    }
 }
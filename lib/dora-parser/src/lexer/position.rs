@@ -1,8 +1,21 @@
 use std::fmt::{Formatter, Display, Error};
 use std::result::Result;
 
+// index of the source file in `Ast::files`
+#[derive(PartialEq,Eq,Debug,Copy,Clone)]
+pub struct FileId(pub u32);
+
+impl FileId {
+    pub fn idx(self) -> usize {
+        self.0 as usize
+    }
+}
+
 #[derive(PartialEq,Eq,Debug,Copy,Clone)]
 pub struct Position {
+    // positions without a file are synthesized or come from code
+    // that was parsed outside of a program, e.g. in unit tests
+    pub file: Option<FileId>,
     pub line: u32,
     pub column: u32,
 }
@@ -12,7 +25,11 @@ impl Position {
         assert!(l >= 1);
         assert!(c >= 1);
 
-        Position { line: l, column: c }
+        Position {
+            file: None,
+            line: l,
+            column: c,
+        }
     }
 }
 
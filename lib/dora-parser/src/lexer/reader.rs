@@ -1,10 +1,11 @@
 use std::fs::File;
 use std::io::{self, Read, Error};
 
-use lexer::position::Position;
+use lexer::position::{FileId, Position};
 
 pub struct Reader {
     filename: String,
+    file: Option<FileId>,
     src: String,
 
     pos: usize,
@@ -41,10 +42,18 @@ impl Reader {
         self.tabwidth = width;
     }
 
+    pub fn set_file(&mut self, file: FileId) {
+        self.file = Some(file);
+    }
+
     pub fn filename(&self) -> &str {
         &self.filename
     }
 
+    pub fn src(&self) -> &str {
+        &self.src
+    }
+
     pub fn advance(&mut self) -> Option<char> {
         match self.cur {
             Some('\n') => {
@@ -84,6 +93,7 @@ impl Reader {
 
     pub fn pos(&self) -> Position {
         Position {
+            file: self.file,
             line: self.line as u32,
             column: self.col as u32,
         }
@@ -103,6 +113,7 @@ impl Reader {
 fn common_init(name: String, src: String) -> Reader {
     let mut reader = Reader {
         filename: name,
+        file: None,
         src: src,
         pos: 0,
         next_pos: 0,
@@ -48,6 +48,7 @@ pub enum TokenKind {
     Pub,
     Static,
     Spawn,
+    Match,
 
     Enum,
     Type,
@@ -56,6 +57,7 @@ pub enum TokenKind {
     Trait,
     Impl,
     Const,
+    Import,
 
     Underscore,
     Defer,
@@ -85,6 +87,7 @@ pub enum TokenKind {
     Colon,
     Sep, // ::
     Arrow,
+    FatArrow,
     Tilde,
     BitOr,
     BitAnd,
@@ -108,6 +111,18 @@ pub enum TokenKind {
     GtGt,
     GtGtGt,
     LtLt,
+
+    AddEq,
+    SubEq,
+    MulEq,
+    DivEq,
+    ModEq,
+    BitOrEq,
+    BitAndEq,
+    CaretEq,
+    LtLtEq,
+    GtGtEq,
+    GtGtGtEq,
 }
 
 impl TokenKind {
@@ -170,6 +185,7 @@ impl TokenKind {
             TokenKind::Pub => "pub",
             TokenKind::Static => "static",
             TokenKind::Spawn => "spawn",
+            TokenKind::Match => "match",
 
             TokenKind::Enum => "enum",
             TokenKind::Type => "type",
@@ -178,6 +194,7 @@ impl TokenKind {
             TokenKind::Trait => "trait",
             TokenKind::Impl => "impl",
             TokenKind::Const => "const",
+            TokenKind::Import => "import",
 
             TokenKind::Underscore => "_",
             TokenKind::Defer => "defer",
@@ -207,6 +224,7 @@ impl TokenKind {
             TokenKind::Colon => ":",
             TokenKind::Sep => "::",
             TokenKind::Arrow => "=>",
+            TokenKind::FatArrow => "=>",
             TokenKind::Tilde => "~",
             TokenKind::BitOr => "|",
             TokenKind::BitAnd => "&",
@@ -227,6 +245,18 @@ impl TokenKind {
             TokenKind::GtGtGt => ">>>",
             TokenKind::LtLt => "<<",
 
+            TokenKind::AddEq => "+=",
+            TokenKind::SubEq => "-=",
+            TokenKind::MulEq => "*=",
+            TokenKind::DivEq => "/=",
+            TokenKind::ModEq => "%=",
+            TokenKind::BitOrEq => "|=",
+            TokenKind::BitAndEq => "&=",
+            TokenKind::CaretEq => "^=",
+            TokenKind::LtLtEq => "<<=",
+            TokenKind::GtGtEq => ">>=",
+            TokenKind::GtGtGtEq => ">>>=",
+
             TokenKind::EqEqEq => "===",
             TokenKind::NeEqEq => "!==",
             TokenKind::Is => "is",
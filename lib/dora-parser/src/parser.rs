@@ -4,6 +4,7 @@ use std::mem;
 use ast::*;
 use ast::Elem::*;
 use builder::Builder;
+use error::diag::Diagnostic;
 use error::msg::*;
 
 use interner::*;
@@ -23,6 +24,11 @@ pub struct Parser<'a> {
     field_idx: u32,
     in_class: bool,
 
+    // number of currently open braces, used for resynchronizing after errors
+    depth: u32,
+    errors: Vec<MsgWithPos>,
+    lexer_failed: bool,
+
     next_id: NodeId,
 }
 
@@ -47,6 +53,9 @@ impl<'a> Parser<'a> {
             field_idx: 0,
             in_class: false,
             ast: ast,
+            depth: 0,
+            errors: Vec::new(),
+            lexer_failed: false,
             next_id: NodeId(1),
         };
 
@@ -57,20 +66,110 @@ impl<'a> Parser<'a> {
         self.id_generator.next()
     }
 
-    pub fn parse(&mut self) -> Result<(), MsgWithPos> {
-        self.init()?;
+    // syntax errors are reported to `diag`, the parser recovers from them at
+    // statement and top-level element boundaries. The file is added to the AST
+    // even on errors, so that diagnostics can show its source.
+    pub fn parse(&mut self, diag: &mut Diagnostic) {
         let mut elements = vec![];
 
-        while !self.token.is_eof() {
-            self.parse_top_level_element(&mut elements)?;
+        if let Err(error) = self.parse_top_level_elements(&mut elements) {
+            self.errors.push(error);
+        }
+
+        for error in self.errors.drain(..) {
+            diag.report(error.pos, error.msg);
         }
 
         self.ast
             .files
             .push(File {
                       path: self.lexer.filename().to_string(),
+                      content: self.lexer.src().to_string(),
+                      module: Vec::new(),
                       elements: elements,
                   });
+    }
+
+    fn parse_top_level_elements(&mut self, elements: &mut Vec<Elem>) -> Result<(), MsgWithPos> {
+        self.init()?;
+
+        while !self.token.is_eof() {
+            let start = self.token.position;
+
+            if let Err(error) = self.parse_top_level_element(elements) {
+                self.recover(error)?;
+
+                if self.token.position == start {
+                    self.advance_token()?;
+                }
+
+                self.skip_top_level_element()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // records the error, errors from the lexer can't be recovered from
+    fn recover(&mut self, error: MsgWithPos) -> Result<(), MsgWithPos> {
+        if self.lexer_failed {
+            return Err(error);
+        }
+
+        self.errors.push(error);
+
+        Ok(())
+    }
+
+    fn skip_top_level_element(&mut self) -> Result<(), MsgWithPos> {
+        while !self.token.is_eof() {
+            if self.depth == 0 {
+                match self.token.kind {
+                    TokenKind::Fun | TokenKind::Class | TokenKind::Struct | TokenKind::Enum |
+                    TokenKind::Trait | TokenKind::Impl | TokenKind::Let | TokenKind::Var |
+                    TokenKind::Const | TokenKind::Type | TokenKind::Import |
+                    TokenKind::Abstract | TokenKind::Open | TokenKind::Override |
+                    TokenKind::Final | TokenKind::Internal | TokenKind::Pub |
+                    TokenKind::Static | TokenKind::Optimize => return Ok(()),
+                    _ => {}
+                }
+            }
+
+            self.advance_token()?;
+        }
+
+        Ok(())
+    }
+
+    // skips tokens until the start of the next statement in the block
+    // with the given depth
+    fn skip_statement(&mut self, depth: u32) -> Result<(), MsgWithPos> {
+        while !self.token.is_eof() && self.depth >= depth {
+            if self.depth == depth {
+                match self.token.kind {
+                    TokenKind::Semicolon => {
+                        self.advance_token()?;
+                        return Ok(());
+                    }
+
+                    TokenKind::RBrace | TokenKind::Let | TokenKind::Var | TokenKind::If |
+                    TokenKind::While | TokenKind::Loop | TokenKind::Break |
+                    TokenKind::Continue | TokenKind::Return | TokenKind::Throw |
+                    TokenKind::Defer | TokenKind::Do | TokenKind::Spawn | TokenKind::For => {
+                        return Ok(())
+                    }
+
+                    _ => {}
+                }
+            }
+
+            let closes_nested = self.token.is(TokenKind::RBrace) && self.depth == depth + 1;
+            self.advance_token()?;
+
+            if closes_nested {
+                return Ok(());
+            }
+        }
 
         Ok(())
     }
@@ -86,27 +185,37 @@ impl<'a> Parser<'a> {
 
         match self.token.kind {
             TokenKind::Fun => {
-                self.restrict_modifiers(&modifiers, &[Modifier::Internal, Modifier::Optimize])?;
+                self.restrict_modifiers(&modifiers,
+                                        &[Modifier::Internal, Modifier::Optimize, Modifier::Pub])?;
                 let fct = self.parse_function(&modifiers)?;
                 elements.push(ElemFunction(fct));
             }
 
             TokenKind::Class => {
                 self.restrict_modifiers(&modifiers,
-                                        &[Modifier::Abstract, Modifier::Open, Modifier::Internal])?;
+                                        &[Modifier::Abstract,
+                                          Modifier::Open,
+                                          Modifier::Internal,
+                                          Modifier::Pub])?;
                 let class = self.parse_class(&modifiers)?;
                 elements.push(ElemClass(class));
             }
 
             TokenKind::Struct => {
-                self.ban_modifiers(&modifiers)?;
-                let struc = self.parse_struct()?;
+                self.restrict_modifiers(&modifiers, &[Modifier::Pub])?;
+                let struc = self.parse_struct(&modifiers)?;
                 elements.push(ElemStruct(struc))
             }
 
+            TokenKind::Enum => {
+                self.restrict_modifiers(&modifiers, &[Modifier::Pub])?;
+                let xenum = self.parse_enum(&modifiers)?;
+                elements.push(ElemEnum(xenum));
+            }
+
             TokenKind::Trait => {
-                self.ban_modifiers(&modifiers)?;
-                let xtrait = self.parse_trait()?;
+                self.restrict_modifiers(&modifiers, &[Modifier::Pub])?;
+                let xtrait = self.parse_trait(&modifiers)?;
                 elements.push(ElemTrait(xtrait));
             }
 
@@ -117,16 +226,28 @@ impl<'a> Parser<'a> {
             }
 
             TokenKind::Let | TokenKind::Var => {
-                self.ban_modifiers(&modifiers)?;
-                self.parse_global(elements)?;
+                self.restrict_modifiers(&modifiers, &[Modifier::Pub])?;
+                self.parse_global(elements, &modifiers)?;
             }
 
             TokenKind::Const => {
-                self.ban_modifiers(&modifiers)?;
-                let xconst = self.parse_const()?;
+                self.restrict_modifiers(&modifiers, &[Modifier::Pub])?;
+                let xconst = self.parse_const(&modifiers)?;
                 elements.push(ElemConst(xconst));
             }
 
+            TokenKind::Type => {
+                self.restrict_modifiers(&modifiers, &[Modifier::Pub])?;
+                let alias = self.parse_type_alias(&modifiers)?;
+                elements.push(ElemTypeAlias(alias));
+            }
+
+            TokenKind::Import => {
+                self.ban_modifiers(&modifiers)?;
+                let import = self.parse_import()?;
+                elements.push(ElemImport(import));
+            }
+
             _ => {
                 let msg = Msg::ExpectedTopLevelElement(self.token.name());
                 return Err(MsgWithPos::new(self.token.position, msg));
@@ -136,7 +257,25 @@ impl<'a> Parser<'a> {
         Ok(())
     }
 
-    fn parse_const(&mut self) -> Result<Const, MsgWithPos> {
+    fn parse_import(&mut self) -> Result<Import, MsgWithPos> {
+        let pos = self.expect_token(TokenKind::Import)?.position;
+        let mut path = vec![self.expect_identifier()?];
+
+        while self.token.is(TokenKind::Sep) {
+            self.advance_token()?;
+            path.push(self.expect_identifier()?);
+        }
+
+        self.expect_semicolon()?;
+
+        Ok(Import {
+               id: self.generate_id(),
+               pos: pos,
+               path: path,
+           })
+    }
+
+    fn parse_const(&mut self, modifiers: &Modifiers) -> Result<Const, MsgWithPos> {
         let pos = self.expect_token(TokenKind::Const)?.position;
         let name = self.expect_identifier()?;
         self.expect_token(TokenKind::Colon)?;
@@ -149,11 +288,30 @@ impl<'a> Parser<'a> {
                id: self.generate_id(),
                pos: pos,
                name: name,
+               is_pub: modifiers.contains(Modifier::Pub),
                data_type: ty,
                expr: expr,
            })
     }
 
+    fn parse_type_alias(&mut self, modifiers: &Modifiers) -> Result<TypeAlias, MsgWithPos> {
+        let pos = self.expect_token(TokenKind::Type)?.position;
+        let name = self.expect_identifier()?;
+        let type_params = self.parse_type_params()?;
+        self.expect_token(TokenKind::Eq)?;
+        let ty = self.parse_type()?;
+        self.expect_semicolon()?;
+
+        Ok(TypeAlias {
+               id: self.generate_id(),
+               pos: pos,
+               name: name,
+               is_pub: modifiers.contains(Modifier::Pub),
+               type_params: type_params,
+               data_type: ty,
+           })
+    }
+
     fn parse_impl(&mut self) -> Result<Impl, MsgWithPos> {
         let pos = self.expect_token(TokenKind::Impl)?.position;
         let trait_name = self.expect_identifier()?;
@@ -183,7 +341,10 @@ impl<'a> Parser<'a> {
            })
     }
 
-    fn parse_global(&mut self, elements: &mut Vec<Elem>) -> Result<(), MsgWithPos> {
+    fn parse_global(&mut self,
+                    elements: &mut Vec<Elem>,
+                    modifiers: &Modifiers)
+                    -> Result<(), MsgWithPos> {
         let pos = self.token.position;
         let reassignable = self.token.is(TokenKind::Var);
 
@@ -209,6 +370,7 @@ impl<'a> Parser<'a> {
             id: self.generate_id(),
             name: name,
             pos: pos,
+            is_pub: modifiers.contains(Modifier::Pub),
             data_type: data_type,
             reassignable: reassignable,
             expr: expr,
@@ -219,7 +381,7 @@ impl<'a> Parser<'a> {
         Ok(())
     }
 
-    fn parse_trait(&mut self) -> Result<Trait, MsgWithPos> {
+    fn parse_trait(&mut self, modifiers: &Modifiers) -> Result<Trait, MsgWithPos> {
         let pos = self.expect_token(TokenKind::Trait)?.position;
         let ident = self.expect_identifier()?;
 
@@ -241,11 +403,12 @@ impl<'a> Parser<'a> {
                id: self.generate_id(),
                name: ident,
                pos: pos,
+               is_pub: modifiers.contains(Modifier::Pub),
                methods: methods,
            })
     }
 
-    fn parse_struct(&mut self) -> Result<Struct, MsgWithPos> {
+    fn parse_struct(&mut self, modifiers: &Modifiers) -> Result<Struct, MsgWithPos> {
         let pos = self.expect_token(TokenKind::Struct)?.position;
         let ident = self.expect_identifier()?;
 
@@ -256,6 +419,7 @@ impl<'a> Parser<'a> {
                id: self.generate_id(),
                name: ident,
                pos: pos,
+               is_pub: modifiers.contains(Modifier::Pub),
                fields: fields,
            })
     }
@@ -275,10 +439,46 @@ impl<'a> Parser<'a> {
            })
     }
 
+    fn parse_enum(&mut self, modifiers: &Modifiers) -> Result<Enum, MsgWithPos> {
+        let pos = self.expect_token(TokenKind::Enum)?.position;
+        let ident = self.expect_identifier()?;
+
+        self.expect_token(TokenKind::LBrace)?;
+        let variants = self.parse_comma_list(TokenKind::RBrace, |p| p.parse_enum_variant())?;
+
+        Ok(Enum {
+               id: self.generate_id(),
+               name: ident,
+               pos: pos,
+               is_pub: modifiers.contains(Modifier::Pub),
+               variants: variants,
+           })
+    }
+
+    fn parse_enum_variant(&mut self) -> Result<EnumVariant, MsgWithPos> {
+        let pos = self.token.position;
+        let ident = self.expect_identifier()?;
+
+        let types = if self.token.is(TokenKind::LParen) {
+            self.advance_token()?;
+            self.parse_comma_list(TokenKind::RParen, |p| p.parse_type())?
+        } else {
+            Vec::new()
+        };
+
+        Ok(EnumVariant {
+               id: self.generate_id(),
+               name: ident,
+               pos: pos,
+               types: types,
+           })
+    }
+
     fn parse_class(&mut self, modifiers: &Modifiers) -> Result<Class, MsgWithPos> {
         let has_open = modifiers.contains(Modifier::Open);
         let internal = modifiers.contains(Modifier::Internal);
         let is_abstract = modifiers.contains(Modifier::Abstract);
+        let is_pub = modifiers.contains(Modifier::Pub);
 
         let pos = self.expect_token(TokenKind::Class)?.position;
         let ident = self.expect_identifier()?;
@@ -291,6 +491,7 @@ impl<'a> Parser<'a> {
             has_open: has_open,
             internal: internal,
             is_abstract: is_abstract,
+            is_pub: is_pub,
             primary_ctor: false,
             parent_class: None,
             ctors: Vec::new(),
@@ -428,7 +629,16 @@ impl<'a> Parser<'a> {
     fn parse_primary_ctor_param(&mut self,
                                 cls: &mut Class)
                                 -> Result<PrimaryCtorParam, MsgWithPos> {
+        let modifiers = self.parse_modifiers()?;
         let field = self.token.is(TokenKind::Var) || self.token.is(TokenKind::Let);
+
+        // only parameters that define a field can be public
+        if field {
+            self.restrict_modifiers(&modifiers, &[Modifier::Pub])?;
+        } else {
+            self.ban_modifiers(&modifiers)?;
+        }
+
         let reassignable = self.token.is(TokenKind::Var);
 
         // consume var and let
@@ -452,6 +662,7 @@ impl<'a> Parser<'a> {
                           primary_ctor: true,
                           expr: None,
                           reassignable: reassignable,
+                          is_pub: modifiers.contains(Modifier::Pub),
                       })
         }
 
@@ -481,6 +692,7 @@ impl<'a> Parser<'a> {
                                  Modifier::Open,
                                  Modifier::Override,
                                  Modifier::Final,
+                                 Modifier::Optimize,
                                  Modifier::Pub,
                                  Modifier::Static];
                     self.restrict_modifiers(&modifiers, mods)?;
@@ -498,9 +710,9 @@ impl<'a> Parser<'a> {
                 }
 
                 TokenKind::Var | TokenKind::Let => {
-                    self.ban_modifiers(&modifiers)?;
+                    self.restrict_modifiers(&modifiers, &[Modifier::Pub])?;
 
-                    let field = self.parse_field()?;
+                    let field = self.parse_field(&modifiers)?;
                     cls.fields.push(field);
                 }
 
@@ -632,7 +844,7 @@ impl<'a> Parser<'a> {
                 }))
     }
 
-    fn parse_field(&mut self) -> Result<Field, MsgWithPos> {
+    fn parse_field(&mut self, modifiers: &Modifiers) -> Result<Field, MsgWithPos> {
         let pos = self.token.position;
         let reassignable = if self.token.is(TokenKind::Var) {
             self.expect_token(TokenKind::Var)?;
@@ -666,6 +878,7 @@ impl<'a> Parser<'a> {
                primary_ctor: false,
                expr: expr,
                reassignable: reassignable,
+               is_pub: modifiers.contains(Modifier::Pub),
            })
     }
 
@@ -813,16 +1026,23 @@ impl<'a> Parser<'a> {
 
             TokenKind::Identifier(_) => {
                 let pos = self.token.position;
-                let name = self.expect_identifier()?;
+                let mut path = Vec::new();
+                let mut name = self.expect_identifier()?;
+
+                while self.token.is(TokenKind::Sep) {
+                    self.advance_token()?;
+                    path.push(name);
+                    name = self.expect_identifier()?;
+                }
 
                 let params = if self.token.is(TokenKind::Lt) {
                     self.advance_token()?;
-                    self.parse_comma_list(TokenKind::Gt, |p| Ok(Box::new(p.parse_type()?)))?
+                    self.parse_type_args()?
                 } else {
                     Vec::new()
                 };
 
-                Ok(Type::create_basic(self.generate_id(), pos, name, params))
+                Ok(Type::create_basic(self.generate_id(), pos, path, name, params))
             }
 
             TokenKind::LParen => {
@@ -847,6 +1067,55 @@ impl<'a> Parser<'a> {
         }
     }
 
+    fn parse_type_args(&mut self) -> Result<Vec<Box<Type>>, MsgWithPos> {
+        let mut params = vec![];
+        let mut comma = true;
+
+        while !self.is_type_args_end() && !self.token.is_eof() {
+            if !comma {
+                return Err(MsgWithPos::new(self.token.position,
+                                           Msg::ExpectedToken(TokenKind::Comma.name().into(),
+                                                              self.token.name())));
+            }
+
+            params.push(Box::new(self.parse_type()?));
+
+            comma = self.token.is(TokenKind::Comma);
+            if comma {
+                self.advance_token()?;
+            }
+        }
+
+        self.expect_type_args_end()?;
+
+        Ok(params)
+    }
+
+    fn is_type_args_end(&self) -> bool {
+        match self.token.kind {
+            TokenKind::Gt | TokenKind::GtGt | TokenKind::GtGtGt => true,
+            _ => false,
+        }
+    }
+
+    // nested type arguments like `Array<Array<int>>` end with `>>`:
+    // only the first `>` is consumed, the rest is left for the outer list
+    fn expect_type_args_end(&mut self) -> Result<(), MsgWithPos> {
+        let rest = match self.token.kind {
+            TokenKind::GtGt => TokenKind::Gt,
+            TokenKind::GtGtGt => TokenKind::GtGt,
+            _ => {
+                self.expect_token(TokenKind::Gt)?;
+                return Ok(());
+            }
+        };
+
+        let pos = self.token.position;
+        self.token = Token::new(rest, Position::new(pos.line, pos.column + 1));
+
+        Ok(())
+    }
+
     fn parse_statement(&mut self) -> StmtResult {
         match self.token.kind {
             TokenKind::Let | TokenKind::Var => self.parse_var(),
@@ -869,9 +1138,18 @@ impl<'a> Parser<'a> {
 
     fn parse_spawn(&mut self) -> StmtResult {
         let pos = self.expect_token(TokenKind::Spawn)?.position;
-        let expr = self.parse_expression()?;
 
-        self.expect_semicolon()?;
+        let expr = if self.token.is(TokenKind::LBrace) {
+            // `spawn { ... }` runs the block as a lambda without params
+            let block = self.parse_block()?;
+            self.build_lambda(pos, Vec::new(), None, block)
+
+        } else {
+            let expr = self.parse_expression()?;
+            self.expect_semicolon()?;
+
+            expr
+        };
 
         Ok(Box::new(Stmt::create_spawn(self.generate_id(), pos, expr)))
     }
@@ -944,6 +1222,11 @@ impl<'a> Parser<'a> {
         };
 
         let pos = self.advance_token()?.position;
+
+        if self.token.is(TokenKind::LParen) {
+            return self.parse_let_tuple(pos, reassignable);
+        }
+
         let ident = self.expect_identifier()?;
         let data_type = self.parse_var_type()?;
         let expr = self.parse_var_assignment()?;
@@ -958,6 +1241,34 @@ impl<'a> Parser<'a> {
                                      expr)))
     }
 
+    fn parse_let_tuple(&mut self, pos: Position, reassignable: bool) -> StmtResult {
+        self.expect_token(TokenKind::LParen)?;
+
+        let bindings = self.parse_comma_list(TokenKind::RParen, |p| {
+                let pos = p.token.position;
+                let name = p.expect_identifier()?;
+
+                Ok(TupleBinding {
+                       id: p.generate_id(),
+                       pos: pos,
+                       name: name,
+                   })
+            })?;
+
+        let data_type = self.parse_var_type()?;
+        self.expect_token(TokenKind::Eq)?;
+        let expr = self.parse_expression()?;
+
+        self.expect_semicolon()?;
+
+        Ok(Box::new(Stmt::create_let_tuple(self.generate_id(),
+                                           pos,
+                                           reassignable,
+                                           bindings,
+                                           data_type,
+                                           expr)))
+    }
+
     fn parse_var_type(&mut self) -> Result<Option<Type>, MsgWithPos> {
         if self.token.is(TokenKind::Colon) {
             self.advance_token()?;
@@ -981,11 +1292,25 @@ impl<'a> Parser<'a> {
 
     fn parse_block(&mut self) -> StmtResult {
         let pos = self.expect_token(TokenKind::LBrace)?.position;
+        let depth = self.depth;
         let mut stmts = vec![];
 
         while !self.token.is(TokenKind::RBrace) && !self.token.is_eof() {
-            let stmt = self.parse_statement()?;
-            stmts.push(stmt);
+            let start = self.token.position;
+
+            match self.parse_statement() {
+                Ok(stmt) => stmts.push(stmt),
+
+                Err(error) => {
+                    self.recover(error)?;
+                    stmts.push(Box::new(Stmt::create_error(self.generate_id(), start)));
+                    self.skip_statement(depth)?;
+
+                    if self.token.position == start {
+                        self.advance_token()?;
+                    }
+                }
+            }
         }
 
         self.expect_token(TokenKind::RBrace)?;
@@ -1105,7 +1430,10 @@ impl<'a> Parser<'a> {
             let right_precedence = match self.token.kind {
                 TokenKind::Or => 1,
                 TokenKind::And => 2,
-                TokenKind::Eq => 3,
+                TokenKind::Eq | TokenKind::AddEq | TokenKind::SubEq | TokenKind::MulEq |
+                TokenKind::DivEq | TokenKind::ModEq | TokenKind::BitOrEq | TokenKind::BitAndEq |
+                TokenKind::CaretEq | TokenKind::LtLtEq | TokenKind::GtGtEq |
+                TokenKind::GtGtGtEq => 3,
                 TokenKind::EqEq | TokenKind::Ne | TokenKind::Lt | TokenKind::Le |
                 TokenKind::Gt | TokenKind::Ge => 4,
                 TokenKind::EqEqEq | TokenKind::NeEqEq => 5,
@@ -1170,6 +1498,12 @@ impl<'a> Parser<'a> {
             left = match self.token.kind {
                 TokenKind::Dot => {
                     let tok = self.advance_token()?;
+
+                    if self.is_tuple_index() {
+                        left = self.parse_tuple_element(tok.position, left)?;
+                        continue;
+                    }
+
                     let ident = self.expect_identifier()?;
 
                     let type_params = if self.token.is(TokenKind::Sep) {
@@ -1205,10 +1539,79 @@ impl<'a> Parser<'a> {
         }
     }
 
+    fn is_tuple_index(&self) -> bool {
+        match self.token.kind {
+            TokenKind::LitInt(_, _, _) | TokenKind::LitFloat(_, _) => true,
+            _ => false,
+        }
+    }
+
+    // `t.0.1` is lexed as `t`, `.` and the float number `0.1`
+    fn parse_tuple_element(&mut self, pos: Position, object: Box<Expr>) -> ExprResult {
+        let tok = self.advance_token()?;
+
+        let indices = match tok.kind {
+            TokenKind::LitInt(ref value, IntBase::Dec, IntSuffix::Int) => vec![value.clone()],
+            TokenKind::LitFloat(ref value, FloatSuffix::Double) if value
+                .chars()
+                .all(|c| c.is_digit(10) || c == '.') => {
+                value.split('.').map(|idx| idx.to_string()).collect()
+            }
+            _ => {
+                let msg = Msg::InvalidTupleIndex(tok.name());
+                return Err(MsgWithPos::new(tok.position, msg));
+            }
+        };
+
+        let mut expr = object;
+
+        for index in indices {
+            let index = match index.parse::<usize>() {
+                Ok(index) => index,
+                Err(_) => {
+                    let msg = Msg::InvalidTupleIndex(index);
+                    return Err(MsgWithPos::new(tok.position, msg));
+                }
+            };
+
+            expr = Box::new(Expr::create_tuple_element(self.generate_id(), pos, expr, index));
+        }
+
+        Ok(expr)
+    }
+
     fn create_binary(&mut self, tok: Token, left: Box<Expr>, right: Box<Expr>) -> Box<Expr> {
         let op = match tok.kind {
             TokenKind::Eq => {
-                return Box::new(Expr::create_assign(self.generate_id(), tok.position, left, right));
+                return Box::new(Expr::create_assign(self.generate_id(),
+                                                    tok.position,
+                                                    None,
+                                                    left,
+                                                    right));
+            }
+
+            TokenKind::AddEq => return self.create_compound_assign(tok, BinOp::Add, left, right),
+            TokenKind::SubEq => return self.create_compound_assign(tok, BinOp::Sub, left, right),
+            TokenKind::MulEq => return self.create_compound_assign(tok, BinOp::Mul, left, right),
+            TokenKind::DivEq => return self.create_compound_assign(tok, BinOp::Div, left, right),
+            TokenKind::ModEq => return self.create_compound_assign(tok, BinOp::Mod, left, right),
+            TokenKind::BitOrEq => {
+                return self.create_compound_assign(tok, BinOp::BitOr, left, right)
+            }
+            TokenKind::BitAndEq => {
+                return self.create_compound_assign(tok, BinOp::BitAnd, left, right)
+            }
+            TokenKind::CaretEq => {
+                return self.create_compound_assign(tok, BinOp::BitXor, left, right)
+            }
+            TokenKind::LtLtEq => {
+                return self.create_compound_assign(tok, BinOp::ShiftL, left, right)
+            }
+            TokenKind::GtGtEq => {
+                return self.create_compound_assign(tok, BinOp::ShiftR, left, right)
+            }
+            TokenKind::GtGtGtEq => {
+                return self.create_compound_assign(tok, BinOp::UnShiftR, left, right)
             }
 
             TokenKind::Or => BinOp::Or,
@@ -1238,6 +1641,21 @@ impl<'a> Parser<'a> {
         Box::new(Expr::create_bin(self.generate_id(), tok.position, op, left, right))
     }
 
+    fn create_compound_assign(&mut self,
+                              tok: Token,
+                              op: BinOp,
+                              left: Box<Expr>,
+                              right: Box<Expr>)
+                              -> Box<Expr> {
+        let id = self.generate_id();
+        let op = AssignOp {
+            id: self.generate_id(),
+            op: op,
+        };
+
+        Box::new(Expr::create_assign(id, tok.position, Some(op), left, right))
+    }
+
     fn parse_factor(&mut self, opts: &ExprParsingOpts) -> ExprResult {
         match self.token.kind {
             TokenKind::LParen => self.parse_parentheses(),
@@ -1254,6 +1672,7 @@ impl<'a> Parser<'a> {
             TokenKind::Try => self.parse_try(),
             TokenKind::TryForce | TokenKind::TryOpt => self.parse_try_op(),
             TokenKind::BitOr | TokenKind::Or => self.parse_lambda(),
+            TokenKind::Match => self.parse_match(),
             _ => {
                 Err(MsgWithPos::new(self.token.position,
                                     Msg::ExpectedFactor(self.token.name().clone())))
@@ -1290,14 +1709,133 @@ impl<'a> Parser<'a> {
             assert!(type_params.is_none());
             self.parse_lit_struct(pos, Path { path: path })
 
+        } else if path.len() > 1 {
+            assert!(type_params.is_none());
+            Ok(Box::new(Expr::create_path(self.generate_id(), pos, Path { path: path })))
+
             // if not we have a simple identifier
         } else {
-            assert_eq!(1, path.len());
             let name = path[0];
             Ok(Box::new(Expr::create_ident(self.generate_id(), pos, name, type_params)))
         }
     }
 
+    fn parse_match(&mut self) -> ExprResult {
+        let pos = self.expect_token(TokenKind::Match)?.position;
+
+        let mut opts = ExprParsingOpts::new();
+        opts.parse_struct_lit(false);
+        let expr = self.parse_expression_with_opts(&opts)?;
+
+        self.expect_token(TokenKind::LBrace)?;
+        let arms = self.parse_comma_list(TokenKind::RBrace, |p| p.parse_match_arm())?;
+
+        Ok(Box::new(Expr::create_match(self.generate_id(), pos, expr, arms)))
+    }
+
+    fn parse_match_arm(&mut self) -> Result<MatchArm, MsgWithPos> {
+        let pos = self.token.position;
+        let pattern = self.parse_match_pattern()?;
+        self.expect_token(TokenKind::FatArrow)?;
+        let value = self.parse_expression()?;
+
+        Ok(MatchArm {
+               id: self.generate_id(),
+               pos: pos,
+               pattern: pattern,
+               value: value,
+           })
+    }
+
+    fn parse_match_pattern(&mut self) -> Result<MatchPattern, MsgWithPos> {
+        match self.token.kind {
+            TokenKind::Underscore => {
+                self.advance_token()?;
+
+                if self.token.is(TokenKind::Colon) {
+                    self.advance_token()?;
+                    let ty = self.parse_type()?;
+
+                    Ok(MatchPattern::Type(None, Box::new(ty)))
+                } else {
+                    Ok(MatchPattern::Underscore)
+                }
+            }
+
+            TokenKind::Identifier(_) => {
+                let name = self.expect_identifier()?;
+
+                if self.token.is(TokenKind::Sep) {
+                    return self.parse_match_enum(name);
+                }
+
+                self.expect_token(TokenKind::Colon)?;
+                let ty = self.parse_type()?;
+
+                Ok(MatchPattern::Type(Some(name), Box::new(ty)))
+            }
+
+            TokenKind::Sub => {
+                let tok = self.advance_token()?;
+
+                if let TokenKind::LitInt(_, _, _) = self.token.kind {
+                    let lit = self.parse_lit_int()?;
+                    let expr = Expr::create_un(self.generate_id(), tok.position, UnOp::Neg, lit);
+
+                    Ok(MatchPattern::Lit(Box::new(expr)))
+                } else {
+                    Err(MsgWithPos::new(self.token.position,
+                                        Msg::ExpectedMatchPattern(self.token.name())))
+                }
+            }
+
+            TokenKind::LitInt(_, _, _) => Ok(MatchPattern::Lit(self.parse_lit_int()?)),
+            TokenKind::LitChar(_) => Ok(MatchPattern::Lit(self.parse_lit_char()?)),
+            TokenKind::String(_) => Ok(MatchPattern::Lit(self.parse_string()?)),
+            TokenKind::True | TokenKind::False => Ok(MatchPattern::Lit(self.parse_bool_literal()?)),
+
+            _ => {
+                Err(MsgWithPos::new(self.token.position,
+                                    Msg::ExpectedMatchPattern(self.token.name())))
+            }
+        }
+    }
+
+    fn parse_match_enum(&mut self, name: Name) -> Result<MatchPattern, MsgWithPos> {
+        let mut path = vec![name];
+
+        while self.token.is(TokenKind::Sep) {
+            self.advance_token()?;
+            path.push(self.expect_identifier()?);
+        }
+
+        let bindings = if self.token.is(TokenKind::LParen) {
+            self.advance_token()?;
+            self.parse_comma_list(TokenKind::RParen, |p| p.parse_match_binding())?
+        } else {
+            Vec::new()
+        };
+
+        Ok(MatchPattern::Enum(Path { path: path }, bindings))
+    }
+
+    fn parse_match_binding(&mut self) -> Result<MatchBinding, MsgWithPos> {
+        let pos = self.token.position;
+
+        let name = if self.token.is(TokenKind::Underscore) {
+            self.advance_token()?;
+            None
+        } else {
+            Some(self.expect_identifier()?)
+        };
+
+        Ok(MatchBinding {
+               id: self.generate_id(),
+               pos: pos,
+               name: name,
+           })
+    }
+
     fn parse_lit_struct(&mut self, pos: Position, path: Path) -> ExprResult {
         self.expect_token(TokenKind::LBrace)?;
         let args = self.parse_comma_list(TokenKind::RBrace, |p| p.parse_lit_struct_arg())?;
@@ -1335,8 +1873,23 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_parentheses(&mut self) -> ExprResult {
-        self.advance_token()?;
+        let pos = self.advance_token()?.position;
         let exp = self.parse_expression()?;
+
+        if self.token.is(TokenKind::Comma) {
+            self.advance_token()?;
+
+            let mut elements = vec![exp];
+            elements.extend(self.parse_comma_list(TokenKind::RParen, |p| p.parse_expression())?);
+
+            // `(a,)` is the same as `(a)`
+            if elements.len() > 1 {
+                return Ok(Box::new(Expr::create_tuple(self.generate_id(), pos, elements)));
+            }
+
+            return Ok(elements.pop().unwrap());
+        }
+
         self.expect_token(TokenKind::RParen)?;
 
         Ok(exp)
@@ -1486,17 +2039,41 @@ impl<'a> Parser<'a> {
                 })?
         };
 
-        let ret = if self.token.is(TokenKind::Arrow) {
-            self.advance_token()?;
-            Some(Box::new(self.parse_type()?))
+        let ret = self.parse_function_type()?;
+        let block = self.parse_block()?;
 
-        } else {
-            None
-        };
+        Ok(self.build_lambda(tok.position, params, ret, block))
+    }
 
-        let block = self.parse_block()?;
+    fn build_lambda(
+        &mut self,
+        pos: Position,
+        params: Vec<Param>,
+        ret: Option<Type>,
+        block: Box<Stmt>,
+    ) -> Box<Expr> {
+        let fct = Function {
+            id: self.generate_id(),
+            name: self.interner.intern("<lambda>"),
+            pos: pos,
+            method: false,
+            has_open: false,
+            has_override: false,
+            has_final: false,
+            has_optimize: false,
+            is_pub: false,
+            is_static: false,
+            internal: false,
+            is_abstract: false,
+            ctor: CtorType::None,
+            params: params,
+            throws: false,
+            return_type: ret,
+            block: Some(block),
+            type_params: None,
+        };
 
-        Ok(Box::new(Expr::create_lambda(self.generate_id(), tok.position, params, ret, block)))
+        Box::new(Expr::create_lambda(self.generate_id(), pos, Box::new(fct)))
     }
 
     fn expect_identifier(&mut self) -> Result<Name, MsgWithPos> {
@@ -1528,7 +2105,19 @@ impl<'a> Parser<'a> {
     }
 
     fn advance_token(&mut self) -> Result<Token, MsgWithPos> {
-        let tok = self.lexer.read_token()?;
+        let tok = match self.lexer.read_token() {
+            Ok(tok) => tok,
+            Err(error) => {
+                self.lexer_failed = true;
+                return Err(error);
+            }
+        };
+
+        match self.token.kind {
+            TokenKind::LBrace => self.depth += 1,
+            TokenKind::RBrace if self.depth > 0 => self.depth -= 1,
+            _ => {}
+        }
 
         Ok(mem::replace(&mut self.token, tok))
     }
@@ -1626,6 +2215,7 @@ mod tests {
     use ast::*;
     use interner::*;
 
+    use error::diag::Diagnostic;
     use error::msg::{Msg, MsgWithPos};
     use lexer::position::Position;
     use lexer::reader::Reader;
@@ -1712,23 +2302,30 @@ mod tests {
         let mut interner = Interner::new();
         let mut ast = Ast::new();
 
+        let mut diag = Diagnostic::new();
+
         let reader = Reader::from_string(code);
-        Parser::new(reader, &id_generator, &mut ast, &mut interner)
-            .parse()
-            .unwrap();
+        Parser::new(reader, &id_generator, &mut ast, &mut interner).parse(&mut diag);
+        assert!(!diag.has_errors());
 
         (ast, interner)
     }
 
-    fn parse_err(code: &'static str) -> MsgWithPos {
+    fn parse_errs(code: &'static str) -> (Ast, Vec<MsgWithPos>) {
         let id_generator = NodeIdGenerator::new();
         let mut interner = Interner::new();
         let mut ast = Ast::new();
+        let mut diag = Diagnostic::new();
 
         let reader = Reader::from_string(code);
-        Parser::new(reader, &id_generator, &mut ast, &mut interner)
-            .parse()
-            .unwrap_err()
+        Parser::new(reader, &id_generator, &mut ast, &mut interner).parse(&mut diag);
+
+        (ast, diag.errors().to_vec())
+    }
+
+    fn parse_err(code: &'static str) -> MsgWithPos {
+        let (_, errors) = parse_errs(code);
+        errors[0].clone()
     }
 
     #[test]
@@ -1797,7 +2394,41 @@ mod tests {
 
     #[test]
     fn parse_field_non_ident() {
-        err_expr("obj.12", Msg::ExpectedIdentifier("12".into()), 1, 5);
+        err_expr("obj.\"a\"", Msg::ExpectedIdentifier("\"a\"".into()), 1, 5);
+    }
+
+    #[test]
+    fn parse_tuple() {
+        let (expr, _) = parse_expr("(1, a, (2, 3))");
+        let tuple = expr.to_tuple().unwrap();
+
+        assert_eq!(3, tuple.elements.len());
+        assert!(tuple.elements[0].is_lit_int());
+        assert!(tuple.elements[1].is_ident());
+        assert!(tuple.elements[2].is_tuple());
+
+        let (expr, _) = parse_expr("(1,)");
+        assert!(expr.is_lit_int());
+    }
+
+    #[test]
+    fn parse_tuple_element() {
+        let (expr, interner) = parse_expr("t.1");
+        let element = expr.to_tuple_element().unwrap();
+
+        assert_eq!(1, element.index);
+        let ident = element.object.to_ident().unwrap();
+        assert_eq!("t", *interner.str(ident.name));
+
+        let (expr, _) = parse_expr("t.0.1");
+        let outer = expr.to_tuple_element().unwrap();
+        assert_eq!(1, outer.index);
+
+        let inner = outer.object.to_tuple_element().unwrap();
+        assert_eq!(0, inner.index);
+        assert!(inner.object.is_ident());
+
+        err_expr("t.1L", Msg::InvalidTupleIndex("1L".into()), 1, 3);
     }
 
     #[test]
@@ -2097,6 +2728,31 @@ mod tests {
         assert_eq!(4, assign.rhs.to_lit_int().unwrap().value);
     }
 
+    #[test]
+    fn parse_compound_assign() {
+        let (expr, _) = parse_expr("a+=4");
+
+        let assign = expr.to_assign().unwrap();
+        assert_eq!(BinOp::Add, assign.op.unwrap().op);
+        assert!(assign.lhs.is_ident());
+        assert_eq!(4, assign.rhs.to_lit_int().unwrap().value);
+
+        let (expr, _) = parse_expr("a.b[1] >>>= 2");
+
+        let assign = expr.to_assign().unwrap();
+        assert_eq!(BinOp::UnShiftR, assign.op.unwrap().op);
+        assert!(assign.lhs.is_array());
+
+        let (expr, _) = parse_expr("a <<= b + 1");
+
+        let assign = expr.to_assign().unwrap();
+        assert_eq!(BinOp::ShiftL, assign.op.unwrap().op);
+        assert!(assign.rhs.is_bin());
+
+        let (expr, _) = parse_expr("a = 1");
+        assert!(expr.to_assign().unwrap().op.is_none());
+    }
+
     #[test]
     fn parse_shift_right() {
         let (expr, _) = parse_expr("a>>4");
@@ -2246,6 +2902,26 @@ mod tests {
         assert!(var.expr.as_ref().unwrap().is_lit_int());
     }
 
+    #[test]
+    fn parse_let_tuple() {
+        let stmt = parse_stmt("let (a, b) = f();");
+        let tuple = stmt.to_let_tuple().unwrap();
+
+        assert_eq!(false, tuple.reassignable);
+        assert_eq!(2, tuple.bindings.len());
+        assert!(tuple.data_type.is_none());
+        assert!(tuple.expr.is_call());
+
+        let stmt = parse_stmt("var (a, b): (int, Str) = (1, \"x\");");
+        let tuple = stmt.to_let_tuple().unwrap();
+
+        assert_eq!(true, tuple.reassignable);
+        assert!(tuple.data_type.is_some());
+        assert!(tuple.expr.is_tuple());
+
+        err_stmt("let (a, b);", Msg::ExpectedToken("=".into(), ";".into()), 1, 11);
+    }
+
     #[test]
     fn parse_let_with_type_but_without_assignment() {
         let stmt = parse_stmt("let x : int;");
@@ -2562,6 +3238,18 @@ mod tests {
         assert_eq!(false, mtd2.is_abstract);
     }
 
+    #[test]
+    fn parse_optimize_method() {
+        let (prog, _) = parse("class Foo {
+            optimize fun foo() {}
+            fun bar() {}
+        }");
+
+        let cls = prog.cls0();
+        assert_eq!(true, cls.methods[0].has_optimize);
+        assert_eq!(false, cls.methods[1].has_optimize);
+    }
+
     #[test]
     fn parse_class() {
         let (prog, interner) = parse("class Foo");
@@ -2998,6 +3686,112 @@ mod tests {
         assert_eq!("fb", *interner.str(f2.name));
     }
 
+    #[test]
+    fn parse_enum() {
+        let (prog, interner) = parse("enum Color { Red, Green, Blue }");
+        let xenum = prog.enum0();
+        assert_eq!("Color", *interner.str(xenum.name));
+        assert_eq!(3, xenum.variants.len());
+
+        let green = &xenum.variants[1];
+        assert_eq!("Green", *interner.str(green.name));
+        assert_eq!(0, green.types.len());
+    }
+
+    #[test]
+    fn parse_enum_with_payload() {
+        let (prog, interner) = parse("enum Shape { Circle(int), Rect(int, int), Empty }");
+        let xenum = prog.enum0();
+        assert_eq!(3, xenum.variants.len());
+
+        let rect = &xenum.variants[1];
+        assert_eq!("Rect", *interner.str(rect.name));
+        assert_eq!(2, rect.types.len());
+        assert_eq!(0, xenum.variants[2].types.len());
+    }
+
+    #[test]
+    fn parse_path() {
+        let (expr, interner) = parse_expr("Color::Red");
+        let path = expr.to_path().unwrap();
+
+        assert_eq!(2, path.path.len());
+        assert_eq!("Color", *interner.str(path.path[0]));
+        assert_eq!("Red", *interner.str(path.path[1]));
+    }
+
+    #[test]
+    fn parse_match() {
+        let (expr, interner) = parse_expr("match x { 1 => a, -2 => b, c: Foo => c, _ => d }");
+        let xmatch = expr.to_match().unwrap();
+
+        assert!(xmatch.expr.is_ident());
+        assert_eq!(4, xmatch.arms.len());
+
+        assert!(xmatch.arms[0].pattern.is_lit());
+        assert!(xmatch.arms[1].pattern.is_lit());
+
+        match xmatch.arms[2].pattern {
+            MatchPattern::Type(Some(name), _) => assert_eq!("c", *interner.str(name)),
+            _ => unreachable!(),
+        }
+
+        assert!(xmatch.arms[3].pattern.is_underscore());
+    }
+
+    #[test]
+    fn parse_match_literals() {
+        let (expr, _) = parse_expr("match x { 'a' => 1, \"b\" => 2, true => 3, _: Foo => 4, }");
+        let xmatch = expr.to_match().unwrap();
+
+        assert_eq!(4, xmatch.arms.len());
+        assert!(xmatch.arms[0].pattern.is_lit());
+        assert!(xmatch.arms[1].pattern.is_lit());
+        assert!(xmatch.arms[2].pattern.is_lit());
+        assert!(xmatch.arms[3].pattern.is_type());
+    }
+
+    #[test]
+    fn parse_match_invalid_pattern() {
+        err_expr("match x { a + 1 => 1 }",
+                 Msg::ExpectedToken(":".into(), "+".into()),
+                 1,
+                 13);
+        err_expr("match x { nil => 1 }",
+                 Msg::ExpectedMatchPattern("nil".into()),
+                 1,
+                 11);
+    }
+
+    #[test]
+    fn parse_match_enum() {
+        let (expr, interner) =
+            parse_expr("match x { Shape::Rect(w, _) => w, Shape::Circle(r) => r, Shape::Empty => 0 }");
+        let xmatch = expr.to_match().unwrap();
+
+        assert_eq!(3, xmatch.arms.len());
+        assert!(xmatch.arms.iter().all(|arm| arm.pattern.is_enum()));
+
+        match xmatch.arms[0].pattern {
+            MatchPattern::Enum(ref path, ref bindings) => {
+                assert_eq!(2, path.len());
+                assert_eq!("Shape", *interner.str(path[0]));
+                assert_eq!("Rect", *interner.str(path[1]));
+
+                assert_eq!(2, bindings.len());
+                assert_eq!("w", *interner.str(bindings[0].name.unwrap()));
+                assert!(bindings[1].name.is_none());
+            }
+
+            _ => unreachable!(),
+        }
+
+        match xmatch.arms[2].pattern {
+            MatchPattern::Enum(_, ref bindings) => assert!(bindings.is_empty()),
+            _ => unreachable!(),
+        }
+    }
+
     #[test]
     fn parse_struct_lit() {
         let (expr, _) = parse_expr("Foo { a: 1, b: 2 }");
@@ -3155,6 +3949,17 @@ mod tests {
         assert!(spawn.expr.is_lit_int());
     }
 
+    #[test]
+    fn parse_spawn_block() {
+        let stmt = parse_stmt("spawn { foo(); }");
+        let spawn = stmt.to_spawn().unwrap();
+        let lambda = spawn.expr.to_lambda().unwrap();
+
+        assert!(lambda.fct.params.is_empty());
+        assert!(lambda.fct.return_type.is_none());
+        assert!(lambda.fct.block().is_block());
+    }
+
     #[test]
     fn parse_fct_call_with_type_param() {
         let (expr, _) = parse_expr("Array::<int>()");
@@ -3216,6 +4021,107 @@ mod tests {
         assert_eq!("x", *interner.str(xconst.name));
     }
 
+    #[test]
+    fn parse_type_alias() {
+        let (prog, interner) = parse("type Name = Str;");
+        let alias = prog.alias0();
+
+        assert_eq!("Name", *interner.str(alias.name));
+        assert!(alias.type_params.is_none());
+        assert_eq!("Str", *interner.str(alias.data_type.to_basic().unwrap().name));
+    }
+
+    #[test]
+    fn parse_generic_type_alias() {
+        let (prog, interner) = parse("type Grid<T> = Array<Array<T>>;");
+        let alias = prog.alias0();
+
+        assert_eq!("Grid", *interner.str(alias.name));
+        assert_eq!(1, alias.type_params.as_ref().unwrap().len());
+
+        let ty = alias.data_type.to_basic().unwrap();
+        assert_eq!("Array", *interner.str(ty.name));
+
+        let param = ty.params[0].to_basic().unwrap();
+        assert_eq!("Array", *interner.str(param.name));
+        assert_eq!("T", *interner.str(param.params[0].to_basic().unwrap().name));
+    }
+
+    #[test]
+    fn parse_qualified_type() {
+        let (ty, interner) = parse_type("foo::bar::Baz<A>");
+        let basic = ty.to_basic().unwrap();
+
+        assert_eq!(2, basic.path.len());
+        assert_eq!("foo", *interner.str(basic.path[0]));
+        assert_eq!("bar", *interner.str(basic.path[1]));
+        assert_eq!("Baz", *interner.str(basic.name));
+        assert_eq!(1, basic.params.len());
+    }
+
+    #[test]
+    fn parse_import() {
+        let (prog, interner) = parse("import foo::bar;");
+        let import = prog.files.last().unwrap().elements[0].to_import().unwrap();
+
+        assert_eq!(2, import.path.len());
+        assert_eq!("foo", *interner.str(import.path[0]));
+        assert_eq!("bar", *interner.str(import.path[1]));
+
+        let err = parse_err("import foo::;");
+        assert_eq!(Msg::ExpectedIdentifier(";".into()), err.msg);
+
+        let err = parse_err("pub import foo;");
+        assert_eq!(Msg::MisplacedModifier("pub".into()), err.msg);
+    }
+
+    #[test]
+    fn parse_pub_elements() {
+        let (prog, _) = parse("pub fun f() {} pub class A pub struct B {} pub enum C { X }
+            pub trait D {} pub let e: int = 0; pub const F: int = 0; pub type G = int;
+            fun h() {}");
+        let elements = &prog.files.last().unwrap().elements;
+
+        assert!(elements[0].to_function().unwrap().is_pub);
+        assert!(elements[1].to_class().unwrap().is_pub);
+        assert!(elements[2].to_struct().unwrap().is_pub);
+        assert!(elements[3].to_enum().unwrap().is_pub);
+        assert!(elements[4].to_trait().unwrap().is_pub);
+        assert!(elements[5].to_global().unwrap().is_pub);
+        assert!(elements[6].to_const().unwrap().is_pub);
+        assert!(elements[7].to_type_alias().unwrap().is_pub);
+        assert!(!elements[8].to_function().unwrap().is_pub);
+
+        let err = parse_err("pub impl Foo for Bar {}");
+        assert_eq!(Msg::MisplacedModifier("pub".into()), err.msg);
+    }
+
+    #[test]
+    fn parse_pub_fields() {
+        let (prog, _) = parse("class A(pub let a: int, let b: int) { pub var c: int; var d: int; }");
+        let cls = prog.cls0();
+
+        assert!(cls.fields[0].is_pub);
+        assert!(!cls.fields[1].is_pub);
+        assert!(cls.fields[2].is_pub);
+        assert!(!cls.fields[3].is_pub);
+
+        let err = parse_err("class A(pub a: int)");
+        assert_eq!(Msg::MisplacedModifier("pub".into()), err.msg);
+        assert_eq!(Position::new(1, 9), err.pos);
+    }
+
+    #[test]
+    fn parse_nested_type_args() {
+        let (ty, interner) = parse_type("A<B<C<int>>>");
+        let a = ty.to_basic().unwrap();
+        let b = a.params[0].to_basic().unwrap();
+        let c = b.params[0].to_basic().unwrap();
+
+        assert_eq!("C", *interner.str(c.name));
+        assert_eq!("int", *interner.str(c.params[0].to_basic().unwrap().name));
+    }
+
     #[test]
     fn parse_generic_with_bound() {
         let (prog, _) = parse("class A<T: Foo>");
@@ -3249,14 +4155,14 @@ mod tests {
         let (expr, _) = parse_expr("|| {}");
         let lambda = expr.to_lambda().unwrap();
 
-        assert!(lambda.ret.is_none());
+        assert!(lambda.fct.return_type.is_none());
     }
 
     #[test]
     fn parse_lambda_no_params_unit_as_return_value() {
         let (expr, _) = parse_expr("|| -> () {}");
         let lambda = expr.to_lambda().unwrap();
-        let ret = lambda.ret.as_ref().unwrap();
+        let ret = lambda.fct.return_type.as_ref().unwrap();
 
         assert!(ret.is_unit());
     }
@@ -3265,7 +4171,7 @@ mod tests {
     fn parse_lambda_no_params_with_return_value() {
         let (expr, interner) = parse_expr("|| -> A {}");
         let lambda = expr.to_lambda().unwrap();
-        let ret = lambda.ret.as_ref().unwrap();
+        let ret = lambda.fct.return_type.as_ref().unwrap();
         let basic = ret.to_basic().unwrap();
 
         assert_eq!("A", *interner.str(basic.name));
@@ -3276,14 +4182,14 @@ mod tests {
         let (expr, interner) = parse_expr("|a: A| -> B {}");
         let lambda = expr.to_lambda().unwrap();
 
-        assert_eq!(1, lambda.params.len());
+        assert_eq!(1, lambda.fct.params.len());
 
-        let param = &lambda.params[0];
+        let param = &lambda.fct.params[0];
         assert_eq!("a", *interner.str(param.name));
         let basic = param.data_type.to_basic().unwrap();
         assert_eq!("A", *interner.str(basic.name));
 
-        let ret = lambda.ret.as_ref().unwrap();
+        let ret = lambda.fct.return_type.as_ref().unwrap();
         let basic = ret.to_basic().unwrap();
 
         assert_eq!("B", *interner.str(basic.name));
@@ -3294,19 +4200,19 @@ mod tests {
         let (expr, interner) = parse_expr("|a: A, b: B| -> C {}");
         let lambda = expr.to_lambda().unwrap();
 
-        assert_eq!(2, lambda.params.len());
+        assert_eq!(2, lambda.fct.params.len());
 
-        let param = &lambda.params[0];
+        let param = &lambda.fct.params[0];
         assert_eq!("a", *interner.str(param.name));
         let basic = param.data_type.to_basic().unwrap();
         assert_eq!("A", *interner.str(basic.name));
 
-        let param = &lambda.params[1];
+        let param = &lambda.fct.params[1];
         assert_eq!("b", *interner.str(param.name));
         let basic = param.data_type.to_basic().unwrap();
         assert_eq!("B", *interner.str(basic.name));
 
-        let ret = lambda.ret.as_ref().unwrap();
+        let ret = lambda.fct.return_type.as_ref().unwrap();
         let basic = ret.to_basic().unwrap();
 
         assert_eq!("C", *interner.str(basic.name));
@@ -3317,4 +4223,53 @@ mod tests {
         let stmt = parse_stmt("for i in a+b {}");
         assert!(stmt.is_for());
     }
+
+    #[test]
+    fn recover_from_statement_errors() {
+        let (prog, errors) = parse_errs("fun f() {
+            let = 1;
+            var x = 2;
+            x = * 3;
+            if (x { 1; }
+            x = 4;
+        }");
+
+        assert_eq!(3, errors.len());
+        assert_eq!(Position::new(2, 17), errors[0].pos);
+        assert_eq!(Msg::ExpectedIdentifier("=".into()), errors[0].msg);
+        assert_eq!(Position::new(4, 17), errors[1].pos);
+        assert_eq!(Position::new(5, 21), errors[2].pos);
+
+        let block = prog.fct0().block().to_block().unwrap();
+        assert_eq!(5, block.stmts.len());
+        assert!(block.stmts[0].is_error());
+        assert!(block.stmts[1].is_var());
+        assert!(block.stmts[2].is_error());
+        assert!(block.stmts[3].is_error());
+        assert!(block.stmts[4].is_expr());
+    }
+
+    #[test]
+    fn recover_from_top_level_errors() {
+        let (prog, errors) = parse_errs("foo bar;
+            fun f( {}
+            class A { fun g() -> { } }
+            fun h() {}");
+
+        assert_eq!(3, errors.len());
+        assert_eq!(Position::new(1, 1), errors[0].pos);
+        assert_eq!(Position::new(2, 20), errors[1].pos);
+        assert_eq!(Position::new(3, 34), errors[2].pos);
+
+        let elements = &prog.files.last().unwrap().elements;
+        assert_eq!(1, elements.len());
+        assert!(elements[0].to_function().is_some());
+    }
+
+    #[test]
+    fn stop_at_lexer_error() {
+        let (_, errors) = parse_errs("fun f() { 1 # 2; } fun g() { let = 1; }");
+        assert_eq!(1, errors.len());
+        assert_eq!(Msg::UnknownChar('#'), errors[0].msg);
+    }
 }
@@ -1,5 +1,6 @@
 use std::mem;
 
+use dora_parser::ast::NodeId;
 use dora_parser::lexer::position::Position;
 
 use baseline::codegen::CondCode;
@@ -8,12 +9,15 @@ use baseline::expr::{ensure_native_stub, AllocationSize, ExprStore};
 use baseline::fct::{CatchType, Comment, GcPoint, JitBaselineFct, JitDescriptor};
 use baseline::info::JitInfo;
 use class::TypeParams;
-use cpu::{FReg, Mem, Reg, FREG_RESULT, REG_PARAMS, REG_RESULT, REG_THREAD, REG_TMP1};
+use cpu::{
+    FReg, Mem, Reg, FREG_RESULT, REG_FP, REG_PARAMS, REG_RESULT, REG_THREAD, REG_TMP1, REG_TMP2,
+};
 use ctxt::VM;
 use ctxt::{FctId, VarId};
 use gc::tlab::TLAB_OBJECT_SIZE;
 use gc::Address;
 use masm::{Label, MacroAssembler, ScratchReg};
+use opt::fct::JitOptFct;
 use os::signal::Trap;
 use stdlib;
 use threads::ThreadLocalData;
@@ -85,6 +89,10 @@ where
         self.masm.jump_if(cond, label);
     }
 
+    pub fn jump_table(&mut self, index: Reg, targets: &[Label]) {
+        self.masm.jump_table(index, targets);
+    }
+
     pub fn pos(&self) -> usize {
         self.masm.pos()
     }
@@ -180,6 +188,17 @@ where
         self.masm.add_addr(ptr)
     }
 
+    // adds a counter with the initial value to the data segment and
+    // decrements it, see MacroAssembler::decrement_counter
+    pub fn decrement_counter(&mut self, dest: Reg, initial: i32) {
+        let disp = self.masm.add_i32(initial);
+        self.masm.decrement_counter(dest, disp);
+    }
+
+    pub fn jump_reg(&mut self, reg: Reg) {
+        self.masm.jump_reg(reg);
+    }
+
     pub fn set(&mut self, dest: Reg, op: CondCode) {
         self.masm.set(dest, op);
     }
@@ -399,16 +418,68 @@ where
         self.masm.emit_lineno(lineno);
     }
 
+    pub fn set_inlined(&mut self, inlined: Vec<(FctId, i32)>) {
+        self.masm.set_inlined(inlined);
+    }
+
     pub fn var_store(&mut self, jit_info: &JitInfo, src: ExprStore, var_id: VarId) {
         let offset = jit_info.offset(var_id);
         let ty = jit_info.ty(var_id);
-        self.masm.store_mem(ty.mode(), Mem::Local(offset), src);
+
+        if ty.is_struct_or_tuple() {
+            self.copy_struct(ty, Mem::Local(offset), Mem::Base(src.reg(), 0));
+        } else {
+            self.masm.store_mem(ty.mode(), Mem::Local(offset), src);
+        }
     }
 
     pub fn var_load(&mut self, jit_info: &JitInfo, var_id: VarId, dest: ExprStore) {
         let offset = jit_info.offset(var_id);
         let ty = jit_info.ty(var_id);
-        self.masm.load_mem(ty.mode(), dest, Mem::Local(offset));
+
+        if ty.is_struct_or_tuple() {
+            self.masm.lea(dest.reg(), Mem::Local(offset));
+        } else {
+            self.masm.load_mem(ty.mode(), dest, Mem::Local(offset));
+        }
+    }
+
+    pub fn lea(&mut self, dest: Reg, mem: Mem) {
+        self.masm.lea(dest, mem);
+    }
+
+    pub fn emit_barrier(&mut self, base: Reg, card_table_offset: usize) {
+        self.masm.emit_barrier(base, card_table_offset);
+    }
+
+    // copies the struct value stored at `src` to `dest`, clobbers one of
+    // REG_RESULT, REG_TMP1 or REG_TMP2 that is not used as base register
+    pub fn copy_struct(&mut self, ty: BuiltinType, dest: Mem, src: Mem) {
+        let size = ty.size(self.vm);
+        let tmp = [REG_TMP1, REG_TMP2, REG_RESULT]
+            .iter()
+            .cloned()
+            .find(|&reg| Some(reg) != mem_base(dest) && Some(reg) != mem_base(src))
+            .unwrap();
+
+        let mut offset = 0;
+
+        while offset < size {
+            let mode = if size - offset >= 8 {
+                MachineMode::Int64
+            } else if size - offset >= 4 {
+                MachineMode::Int32
+            } else {
+                MachineMode::Int8
+            };
+
+            self.masm
+                .load_mem(mode, tmp.into(), mem_with_offset(src, offset));
+            self.masm
+                .store_mem(mode, mem_with_offset(dest, offset), tmp.into());
+
+            offset += mode.size();
+        }
     }
 
     pub fn jit(mut self, stacksize: i32, desc: JitDescriptor, throws: bool) -> JitBaselineFct {
@@ -416,6 +487,11 @@ where
         self.masm.jit(self.vm, stacksize, desc, throws)
     }
 
+    pub fn jit_opt(mut self, framesize: i32, fct_id: FctId, throws: bool) -> Option<JitOptFct> {
+        self.slow_paths();
+        self.masm.jit_opt(self.vm, framesize, fct_id, throws)
+    }
+
     pub fn native_call(
         &mut self,
         internal_fct: InternalFct,
@@ -430,6 +506,15 @@ where
         self.call_epilog(pos, ty, dest, gcpoint);
     }
 
+    // calls OSR code with the frame pointer of this function
+    pub fn osr_call(&mut self, fct_ptr: Reg, pos: Position, gcpoint: GcPoint) {
+        self.masm.copy_reg(MachineMode::Ptr, REG_TMP1, fct_ptr);
+        self.masm.copy_reg(MachineMode::Ptr, REG_PARAMS[0], REG_FP);
+        self.masm.call_reg(REG_TMP1);
+        self.masm.emit_lineno(pos.line as i32);
+        self.masm.emit_gcpoint(gcpoint);
+    }
+
     pub fn direct_call(
         &mut self,
         fct_id: FctId,
@@ -457,6 +542,23 @@ where
         self.call_epilog(pos, ty, dest, gcpoint);
     }
 
+    pub fn cached_call(
+        &mut self,
+        id: Option<NodeId>,
+        fct_id: FctId,
+        index: u32,
+        pos: Position,
+        gcpoint: GcPoint,
+        ty: BuiltinType,
+        dest: ExprStore,
+    ) {
+        let miss_thunk = self.vm.ic_miss_thunk();
+        let stats = self.vm.args.flag_emit_ic_stats;
+        self.masm
+            .cached_call(pos.line as i32, id, fct_id, index, miss_thunk, stats);
+        self.call_epilog(pos, ty, dest, gcpoint);
+    }
+
     fn call_epilog(&mut self, pos: Position, ty: BuiltinType, dest: ExprStore, gcpoint: GcPoint) {
         self.masm.emit_lineno(pos.line as i32);
         self.masm.emit_gcpoint(gcpoint);
@@ -662,3 +764,19 @@ where
 enum SlowPathKind {
     TlabAllocationFailure(Label, Label, Reg, AllocationSize, Position, bool, GcPoint),
 }
+
+fn mem_base(mem: Mem) -> Option<Reg> {
+    match mem {
+        Mem::Local(_) => None,
+        Mem::Base(base, _) => Some(base),
+        _ => unimplemented!(),
+    }
+}
+
+fn mem_with_offset(mem: Mem, offset: i32) -> Mem {
+    match mem {
+        Mem::Local(disp) => Mem::Local(disp + offset),
+        Mem::Base(base, disp) => Mem::Base(base, disp + offset),
+        _ => unimplemented!(),
+    }
+}
@@ -0,0 +1,559 @@
+use std::collections::{HashMap, HashSet};
+use std::mem;
+
+use baseline::info::JitInfo;
+use ctxt::{CallType, FctSrc, IdentType, Intrinsic, VarId, VM};
+use dora_parser::ast::visit::*;
+use dora_parser::ast::Expr::*;
+use dora_parser::ast::Stmt::*;
+use dora_parser::ast::*;
+use dora_parser::lexer::token::IntSuffix;
+use ty::BuiltinType;
+
+// Finds array accesses that are always in bounds, for these accesses no bounds
+// check needs to be emitted. The index needs to be the induction variable of
+// one of these loops:
+//
+//   while i < a.len() { ... a[i] ... i = i + 1; }
+//   for i in range(lower, a.len()) { ... a[i] ... }
+//
+// The array `a` is a local variable that is not assigned in the loop. In the while
+// loop `i` is known to be non-negative before the loop and incremented exactly once
+// at the top-level of the loop body, accesses after the increment are still checked.
+// The lower bound of the range needs to be non-negative.
+pub fn analyze<'ast>(
+    vm: &VM<'ast>,
+    src: &FctSrc,
+    jit_info: &JitInfo<'ast>,
+    ast: &'ast Function,
+) -> HashSet<NodeId> {
+    let mut bce = BoundsCheckElimination {
+        vm: vm,
+        src: src,
+        jit_info: jit_info,
+        non_negative: HashSet::new(),
+        in_bounds: Vec::new(),
+        result: HashSet::new(),
+    };
+
+    if let Some(ref block) = ast.block {
+        bce.visit_stmt(block);
+    }
+
+    bce.result
+}
+
+#[derive(Clone)]
+struct State {
+    non_negative: HashSet<VarId>,
+    in_bounds: Vec<(VarId, VarId)>,
+}
+
+impl State {
+    fn intersect(&mut self, other: &State) {
+        self.non_negative
+            .retain(|var| other.non_negative.contains(var));
+        self.in_bounds.retain(|pair| other.in_bounds.contains(pair));
+    }
+}
+
+struct BoundsCheckElimination<'a, 'ast: 'a> {
+    vm: &'a VM<'ast>,
+    src: &'a FctSrc,
+    jit_info: &'a JitInfo<'ast>,
+
+    // variables with a value >= 0
+    non_negative: HashSet<VarId>,
+
+    // pairs of index and array variable with 0 <= index < array.len()
+    in_bounds: Vec<(VarId, VarId)>,
+
+    result: HashSet<NodeId>,
+}
+
+impl<'a, 'ast> Visitor<'ast> for BoundsCheckElimination<'a, 'ast> {
+    fn visit_stmt(&mut self, s: &'ast Stmt) {
+        match *s {
+            StmtVar(ref stmt) => {
+                let var = *self.src.map_vars.get(stmt.id).unwrap();
+                let non_negative = match stmt.expr {
+                    Some(ref expr) => {
+                        self.visit_expr(expr);
+                        self.is_non_negative(expr)
+                    }
+
+                    None => false,
+                };
+
+                self.set_non_negative(var, non_negative);
+            }
+
+            StmtExpr(ref stmt) => match *stmt.expr {
+                ExprAssign(ref assign) if assign.op.is_none() => {
+                    let non_negative = self.is_non_negative(&assign.rhs);
+                    self.visit_expr(&stmt.expr);
+
+                    if let Some(var) = self.var(&assign.lhs) {
+                        self.set_non_negative(var, non_negative);
+                    }
+                }
+
+                _ => self.visit_expr(&stmt.expr),
+            },
+
+            StmtIf(ref stmt) => {
+                self.visit_expr(&stmt.cond);
+
+                let state = self.state();
+                self.visit_stmt(&stmt.then_block);
+                let mut then_state = self.replace_state(state);
+
+                if let Some(ref else_block) = stmt.else_block {
+                    self.visit_stmt(else_block);
+                }
+
+                then_state.intersect(&self.state());
+                self.replace_state(then_state);
+            }
+
+            StmtWhile(ref stmt) => self.stmt_while(stmt),
+            StmtFor(ref stmt) => self.stmt_for(stmt),
+
+            StmtLoop(ref stmt) => {
+                let assigned = self.assigned_vars(s, None);
+                self.enter_loop(&assigned);
+                let state = self.state();
+                self.visit_stmt(&stmt.block);
+                self.replace_state(state);
+            }
+
+            StmtBlock(_) | StmtReturn(_) | StmtThrow(_) | StmtBreak(_) | StmtContinue(_) => {
+                walk_stmt(self, s)
+            }
+
+            // the remaining statements do not execute their
+            // sub-statements and expressions in order
+            _ => {
+                let assigned = self.assigned_vars(s, None);
+                self.enter_loop(&assigned);
+                let state = self.replace_state(State {
+                    non_negative: HashSet::new(),
+                    in_bounds: Vec::new(),
+                });
+                walk_stmt(self, s);
+                self.replace_state(state);
+            }
+        }
+    }
+
+    fn visit_expr(&mut self, e: &'ast Expr) {
+        match *e {
+            ExprArray(ref array) => {
+                if let (Some(object), Some(index)) =
+                    (self.var(&array.object), self.var(&array.index))
+                {
+                    if self.in_bounds.contains(&(index, object)) {
+                        self.result.insert(array.id);
+                    }
+                }
+
+                walk_expr(self, e);
+            }
+
+            ExprAssign(ref assign) => {
+                walk_expr(self, e);
+
+                if let Some(var) = self.var(&assign.lhs) {
+                    self.set_non_negative(var, false);
+                }
+            }
+
+            _ => walk_expr(self, e),
+        }
+    }
+}
+
+impl<'a, 'ast> BoundsCheckElimination<'a, 'ast> {
+    fn stmt_while(&mut self, stmt: &'ast StmtWhileType) {
+        let assigned = self.assigned_vars(&stmt.block, Some(&stmt.cond));
+
+        let mut induction_vars = Vec::new();
+
+        for (index, object) in self.loop_conditions(&stmt.cond) {
+            if self.non_negative.contains(&index)
+                && !assigned.contains_key(&object)
+                && assigned.get(&index) == Some(&1)
+                && self.is_incremented(&stmt.block, index)
+            {
+                induction_vars.push((index, object));
+            }
+        }
+
+        self.enter_loop(&assigned);
+
+        for &(index, _) in &induction_vars {
+            self.non_negative.insert(index);
+        }
+
+        let state = self.state();
+        self.visit_expr(&stmt.cond);
+        self.in_bounds.extend(induction_vars);
+        self.visit_stmt(&stmt.block);
+        self.replace_state(state);
+    }
+
+    fn stmt_for(&mut self, stmt: &'ast StmtForType) {
+        self.visit_expr(&stmt.expr);
+
+        let range = self.range(&stmt.expr);
+        let assigned = self.assigned_vars(&stmt.block, None);
+        self.enter_loop(&assigned);
+
+        let state = self.state();
+
+        if let Some(object) = range {
+            if !assigned.contains_key(&object) {
+                let index = *self.src.map_vars.get(stmt.id).unwrap();
+                self.non_negative.insert(index);
+                self.in_bounds.push((index, object));
+            }
+        }
+
+        self.visit_stmt(&stmt.block);
+        self.replace_state(state);
+    }
+
+    fn assigned_vars(&self, stmt: &'ast Stmt, expr: Option<&'ast Expr>) -> HashMap<VarId, usize> {
+        let mut visitor = AssignedVars {
+            src: self.src,
+            assigned: HashMap::new(),
+        };
+
+        visitor.visit_stmt(stmt);
+
+        if let Some(expr) = expr {
+            visitor.visit_expr(expr);
+        }
+
+        visitor.assigned
+    }
+
+    // facts about variables assigned in a loop do not hold in the loop body
+    fn enter_loop(&mut self, assigned: &HashMap<VarId, usize>) {
+        self.non_negative.retain(|var| !assigned.contains_key(var));
+        self.in_bounds.retain(|&(index, object)| {
+            !assigned.contains_key(&index) && !assigned.contains_key(&object)
+        });
+    }
+
+    fn set_non_negative(&mut self, var: VarId, non_negative: bool) {
+        self.in_bounds
+            .retain(|&(index, object)| index != var && object != var);
+
+        if non_negative {
+            self.non_negative.insert(var);
+        } else {
+            self.non_negative.remove(&var);
+        }
+    }
+
+    fn state(&self) -> State {
+        State {
+            non_negative: self.non_negative.clone(),
+            in_bounds: self.in_bounds.clone(),
+        }
+    }
+
+    fn replace_state(&mut self, state: State) -> State {
+        State {
+            non_negative: mem::replace(&mut self.non_negative, state.non_negative),
+            in_bounds: mem::replace(&mut self.in_bounds, state.in_bounds),
+        }
+    }
+
+    // returns pairs of index and array for all conjuncts `i < a.len()`
+    fn loop_conditions(&self, cond: &Expr) -> Vec<(VarId, VarId)> {
+        let bin = match *cond {
+            ExprBin(ref bin) => bin,
+            _ => return Vec::new(),
+        };
+
+        match bin.op {
+            BinOp::And => {
+                let mut conditions = self.loop_conditions(&bin.lhs);
+                conditions.extend(self.loop_conditions(&bin.rhs));
+                conditions
+            }
+
+            BinOp::Cmp(CmpOp::Lt) => self.index_less_than_len(&bin.lhs, &bin.rhs),
+            BinOp::Cmp(CmpOp::Gt) => self.index_less_than_len(&bin.rhs, &bin.lhs),
+            _ => Vec::new(),
+        }
+    }
+
+    fn index_less_than_len(&self, index: &Expr, len: &Expr) -> Vec<(VarId, VarId)> {
+        match (self.int_var(index), self.len(len)) {
+            (Some(index), Some(object)) => vec![(index, object)],
+            _ => Vec::new(),
+        }
+    }
+
+    // checks for `i = i + 1` or `i += 1` at the top-level of the loop body
+    fn is_incremented(&self, block: &Stmt, var: VarId) -> bool {
+        let block = match *block {
+            StmtBlock(ref block) => block,
+            _ => return false,
+        };
+
+        block.stmts.iter().any(|stmt| {
+            let assign = match **stmt {
+                StmtExpr(ref stmt) => match *stmt.expr {
+                    ExprAssign(ref assign) => assign,
+                    _ => return false,
+                },
+
+                _ => return false,
+            };
+
+            if self.var(&assign.lhs) != Some(var) {
+                return false;
+            }
+
+            match assign.op {
+                Some(ref op) => op.op == BinOp::Add && is_one(&assign.rhs),
+
+                None => match *assign.rhs {
+                    ExprBin(ref bin) => {
+                        bin.op == BinOp::Add && self.var(&bin.lhs) == Some(var) && is_one(&bin.rhs)
+                    }
+
+                    _ => false,
+                },
+            }
+        })
+    }
+
+    // returns the array variable of `range(lower, a.len())` with lower >= 0
+    fn range(&self, expr: &Expr) -> Option<VarId> {
+        let call = match *expr {
+            ExprCall(ref call) => call,
+            _ => return None,
+        };
+
+        match self.src.map_calls.get(call.id).map(|call| &**call) {
+            Some(&CallType::Fct(fct_id, _, _)) if fct_id == self.vm.vips.range_fct => {}
+            _ => return None,
+        }
+
+        if call.args.len() != 2 || !self.is_non_negative(&call.args[0]) {
+            return None;
+        }
+
+        self.len(&call.args[1])
+    }
+
+    fn is_non_negative(&self, expr: &Expr) -> bool {
+        match *expr {
+            ExprLitInt(ref lit) => {
+                lit.suffix == IntSuffix::Int && lit.value <= i32::max_value() as u64
+            }
+
+            ExprIdent(_) => match self.var(expr) {
+                Some(var) => self.non_negative.contains(&var),
+                None => false,
+            },
+
+            _ => self.len(expr).is_some(),
+        }
+    }
+
+    // returns the array variable of `a.len()`
+    fn len(&self, expr: &Expr) -> Option<VarId> {
+        let call = match *expr {
+            ExprCall(ref call) if call.args.is_empty() => call,
+            _ => return None,
+        };
+
+        match self.jit_info.map_intrinsics.get(call.id) {
+            Some(&Intrinsic::GenericArrayLen) => {}
+            _ => return None,
+        }
+
+        match call.object {
+            Some(ref object) => self.var(object),
+            None => None,
+        }
+    }
+
+    fn int_var(&self, expr: &Expr) -> Option<VarId> {
+        let var = self.var(expr)?;
+
+        if self.src.vars[var].ty == BuiltinType::Int {
+            Some(var)
+        } else {
+            None
+        }
+    }
+
+    fn var(&self, expr: &Expr) -> Option<VarId> {
+        match *expr {
+            ExprIdent(ref ident) => match self.src.map_idents.get(ident.id) {
+                Some(&IdentType::Var(var)) => Some(var),
+                _ => None,
+            },
+
+            _ => None,
+        }
+    }
+}
+
+fn is_one(expr: &Expr) -> bool {
+    match *expr {
+        ExprLitInt(ref lit) => lit.value == 1 && lit.suffix == IntSuffix::Int,
+        _ => false,
+    }
+}
+
+// counts assignments of local variables
+struct AssignedVars<'a> {
+    src: &'a FctSrc,
+    assigned: HashMap<VarId, usize>,
+}
+
+impl<'a, 'ast> Visitor<'ast> for AssignedVars<'a> {
+    fn visit_expr(&mut self, e: &'ast Expr) {
+        if let ExprAssign(ref assign) = *e {
+            if let Some(&IdentType::Var(var)) = self.src.map_idents.get(assign.lhs.id()) {
+                *self.assigned.entry(var).or_insert(0) += 1;
+            }
+        }
+
+        walk_expr(self, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use baseline::info::{self, JitInfo};
+    use class::TypeParams;
+    use ctxt::*;
+    use os;
+    use test;
+
+    fn in_bounds(code: &'static str) -> usize {
+        os::init_page_size();
+
+        test::parse(code, |vm| {
+            let fid = vm.fct_by_name("f").unwrap();
+            let fct = vm.fcts.idx(fid);
+            let fct = fct.read();
+            let src = fct.src();
+            let mut src = src.write();
+            let mut jit_info = JitInfo::new();
+            let empty = TypeParams::empty();
+
+            info::generate(vm, &fct, &mut src, &mut jit_info, &empty, &empty);
+
+            jit_info.in_bounds.len()
+        })
+    }
+
+    #[test]
+    fn test_while() {
+        assert_eq!(
+            2,
+            in_bounds(
+                "fun f(a: Array<int>) -> int {
+                    var i = 0; var sum = 0;
+                    while i < a.len() { sum = sum + a[i]; a[i] = 0; i = i + 1; }
+                    return sum; }"
+            )
+        );
+        assert_eq!(
+            1,
+            in_bounds(
+                "fun f(a: Array<int>) {
+                    var i = 0;
+                    while a.len() > i && i < 10 { a[i] = i; i += 1; } }"
+            )
+        );
+    }
+
+    #[test]
+    fn test_while_not_in_bounds() {
+        // access after increment
+        assert_eq!(
+            0,
+            in_bounds(
+                "fun f(a: Array<int>) {
+                    var i = 0;
+                    while i < a.len() { i = i + 1; a[i] = 0; } }"
+            )
+        );
+
+        // negative start
+        assert_eq!(
+            0,
+            in_bounds(
+                "fun f(a: Array<int>) {
+                    var i = -1;
+                    while i < a.len() { a[i] = 0; i = i + 1; } }"
+            )
+        );
+
+        // array reassigned in loop
+        assert_eq!(
+            0,
+            in_bounds(
+                "fun f(a: Array<int>, b: Array<int>) {
+                    var i = 0; var x = a;
+                    while i < x.len() { x[i] = 0; x = b; i = i + 1; } }"
+            )
+        );
+
+        // index assigned twice
+        assert_eq!(
+            0,
+            in_bounds(
+                "fun f(a: Array<int>) {
+                    var i = 0;
+                    while i < a.len() { a[i] = 0; if i == 2 { i = 5; } i = i + 1; } }"
+            )
+        );
+
+        // condition not a conjunct
+        assert_eq!(
+            0,
+            in_bounds(
+                "fun f(a: Array<int>, c: bool) {
+                    var i = 0;
+                    while i < a.len() || c { a[i] = 0; i = i + 1; } }"
+            )
+        );
+    }
+
+    #[test]
+    fn test_for() {
+        assert_eq!(
+            1,
+            in_bounds(
+                "fun f(a: Array<int>) {
+                    for i in range(0, a.len()) { a[i] = i; } }"
+            )
+        );
+        assert_eq!(
+            0,
+            in_bounds(
+                "fun f(a: Array<int>, lower: int) {
+                    for i in range(lower, a.len()) { a[i] = i; } }"
+            )
+        );
+        assert_eq!(
+            0,
+            in_bounds(
+                "fun f(a: Array<int>) {
+                    for i in range(0, a.len() + 1) { a[i] = i; } }"
+            )
+        );
+    }
+}
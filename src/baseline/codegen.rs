@@ -15,24 +15,30 @@ use dora_parser::ast::*;
 use dora_parser::lexer::position::Position;
 
 use baseline::asm::BaselineAssembler;
+use baseline::dora_compile::osr_request;
+use baseline::dora_native::{InternalFct, InternalFctDescriptor};
 use baseline::expr::*;
 use baseline::fct::{
-    CatchType, Comment, CommentFormat, GcPoint, JitBaselineFct, JitDescriptor, JitFct,
+    CatchType, Comment, CommentFormat, GcPoint, JitBaselineFct, JitDescriptor, JitFct, JitFctId,
 };
 use baseline::info::{self, JitInfo};
 use baseline::map::CodeDescriptor;
 use class::{ClassDef, TypeParams};
-use cpu::{Mem, FREG_PARAMS, FREG_RESULT, REG_PARAMS, REG_RESULT};
+use cpu::{
+    next_param_offset, Mem, FREG_PARAMS, FREG_RESULT, PARAM_OFFSET, REG_PARAMS, REG_RESULT,
+    REG_TMP1, REG_TMP2,
+};
 use ctxt::VM;
 use ctxt::{CallSite, Fct, FctId, FctParent, FctSrc, VarId};
 use driver::cmd::AsmSyntax;
 use gc::Address;
 use masm::*;
 use mem;
+use opt;
 use os;
 use os::signal::Trap;
 use semck::always_returns;
-use semck::specialize::specialize_class_ty;
+use semck::specialize::{specialize_class_ty, specialize_struct_ty};
 use ty::{BuiltinType, MachineMode};
 
 pub fn generate<'ast>(
@@ -84,34 +90,160 @@ pub fn generate_fct<'ast>(
         cls_type_params,
         fct_type_params,
     );
-    let jit_fct = CodeGen {
-        vm: vm,
-        fct: &fct,
-        ast: ast,
-        asm: BaselineAssembler::new(vm),
-        scopes: Scopes::new(),
-        src: src,
-        jit_info: jit_info,
-
-        lbl_break: None,
-        lbl_continue: None,
-
-        active_finallys: Vec::new(),
-        active_upper: None,
-        active_loop: None,
-        lbl_return: None,
-
-        cls_type_params: cls_type_params,
-        fct_type_params: fct_type_params,
+
+    let jit_fct = if ast.has_optimize {
+        opt::compile(
+            vm,
+            fct,
+            src,
+            &jit_info,
+            cls_type_params,
+            fct_type_params,
+            None,
+        )
+    } else {
+        None
+    };
+
+    let jit_fct = match jit_fct {
+        Some(jit_fct) => JitFct::Opt(jit_fct),
+
+        None => JitFct::Base(
+            CodeGen {
+                vm: vm,
+                fct: &fct,
+                ast: ast,
+                asm: BaselineAssembler::new(vm),
+                scopes: Scopes::new(),
+                src: src,
+                jit_info: jit_info,
+
+                lbl_break: None,
+                lbl_continue: None,
+
+                active_finallys: Vec::new(),
+                active_upper: None,
+                active_loop: None,
+                lbl_return: None,
+
+                cls_type_params: cls_type_params,
+                fct_type_params: fct_type_params,
+            }
+            .generate(),
+        ),
+    };
+
+    let (jit_fct_id, fct_ptr) = install(vm, fct, src, jit_fct);
+
+    {
+        let mut specials = src.specializations.write();
+        let key = (cls_type_params.clone(), fct_type_params.clone());
+        specials.insert(key, jit_fct_id);
+    }
+
+    fct_ptr
+}
+
+// recompiles a hot function with the optimizing compiler, the optimized code
+// replaces the baseline code for all call sites that are patched afterwards
+pub fn generate_optimized<'ast>(
+    vm: &VM<'ast>,
+    id: FctId,
+    cls_type_params: &TypeParams,
+    fct_type_params: &TypeParams,
+) -> Option<Address> {
+    let fct = vm.fcts.idx(id);
+    let fct = fct.read();
+    let src = fct.src();
+    let mut src = src.write();
+    let key = (cls_type_params.clone(), fct_type_params.clone());
+
+    if let Some(&jit_fct_id) = src.specializations.read().get(&key) {
+        let jit_fct = vm.jit_fcts.idx(jit_fct_id);
+
+        if let JitFct::Opt(_) = *jit_fct {
+            return Some(jit_fct.fct_ptr());
+        }
+    }
+
+    let mut jit_info = JitInfo::new();
+    info::generate(
+        vm,
+        &fct,
+        &mut src,
+        &mut jit_info,
+        cls_type_params,
+        fct_type_params,
+    );
+
+    let jit_fct = opt::compile(
+        vm,
+        &fct,
+        &mut src,
+        &jit_info,
+        cls_type_params,
+        fct_type_params,
+        None,
+    )?;
+
+    let (jit_fct_id, fct_ptr) = install(vm, &fct, &src, JitFct::Opt(jit_fct));
+    src.specializations.write().insert(key, jit_fct_id);
+
+    Some(fct_ptr)
+}
+
+// compiles optimized code that continues the given loop of running baseline
+// code, the code expects the frame pointer of the baseline code as argument
+pub fn generate_osr<'ast>(vm: &VM<'ast>, id: FctId, loop_id: NodeId) -> Option<Address> {
+    let fct = vm.fcts.idx(id);
+    let fct = fct.read();
+    let src = fct.src();
+    let mut src = src.write();
+
+    if let Some(&jit_fct_id) = src.osr_entries.read().get(&loop_id) {
+        return Some(vm.jit_fcts.idx(jit_fct_id).fct_ptr());
+    }
+
+    let empty = TypeParams::empty();
+    let mut jit_info = JitInfo::new();
+    info::generate(vm, &fct, &mut src, &mut jit_info, &empty, &empty);
+
+    let jit_fct = opt::compile(
+        vm,
+        &fct,
+        &mut src,
+        &jit_info,
+        &empty,
+        &empty,
+        Some(loop_id),
+    )?;
+
+    let (jit_fct_id, fct_ptr) = install(vm, &fct, &src, JitFct::Opt(jit_fct));
+    src.osr_entries.write().insert(loop_id, jit_fct_id);
+
+    Some(fct_ptr)
+}
+
+fn install<'ast>(
+    vm: &VM<'ast>,
+    fct: &Fct<'ast>,
+    src: &FctSrc,
+    jit_fct: JitFct,
+) -> (JitFctId, Address) {
+    if vm.args.flag_enable_perf {
+        os::perf::register_with_perf(&jit_fct, vm, fct.ast.name);
+    }
+
+    if vm.args.flag_enable_gdb {
+        os::gdb::register_with_gdb(&jit_fct, vm, fct);
     }
-    .generate();
 
-    if should_emit_asm(vm, &*fct) {
+    if should_emit_asm(vm, fct) {
         dump_asm(
             vm,
-            &*fct,
+            fct,
             &jit_fct,
-            Some(&src),
+            Some(src),
             vm.args.flag_asm_syntax.unwrap_or(AsmSyntax::Att),
         );
     }
@@ -126,24 +258,18 @@ pub fn generate_fct<'ast>(
     let jit_fct_id = {
         let mut jit_fcts = vm.jit_fcts.lock();
         let jit_fct_id = jit_fcts.len().into();
-        jit_fcts.push(Arc::new(JitFct::Base(jit_fct)));
+        jit_fcts.push(Arc::new(jit_fct));
 
         jit_fct_id
     };
 
-    {
-        let mut specials = src.specializations.write();
-        let key = (cls_type_params.clone(), fct_type_params.clone());
-        specials.insert(key, jit_fct_id);
-    }
-
     {
         let mut code_map = vm.code_map.lock();
         let cdata = CodeDescriptor::DoraFct(jit_fct_id);
         code_map.insert(ptr_start, ptr_end, cdata);
     }
 
-    fct_ptr
+    (jit_fct_id, fct_ptr)
 }
 
 #[cfg(target_arch = "x86_64")]
@@ -163,7 +289,7 @@ fn get_engine() -> Result<Engine, Error> {
 pub fn dump_asm<'ast>(
     vm: &VM<'ast>,
     fct: &Fct<'ast>,
-    jit_fct: &JitBaselineFct,
+    jit_fct: &JitFct,
     fct_src: Option<&FctSrc>,
     asm_syntax: AsmSyntax,
 ) {
@@ -316,6 +442,10 @@ where
             self.asm.debug();
         }
 
+        if self.tiering() {
+            self.emit_invocation_counter();
+        }
+
         self.emit_prolog();
         self.store_register_params_on_stack();
         self.visit_fct(self.ast);
@@ -332,10 +462,6 @@ where
             self.ast.throws,
         );
 
-        if self.vm.args.flag_enable_perf {
-            os::perf::register_with_perf(&jit_fct, self.vm, self.ast.name);
-        }
-
         jit_fct
     }
 
@@ -367,6 +493,8 @@ where
             }
         }
 
+        let mut param_offset = PARAM_OFFSET;
+
         for p in &self.ast.params {
             let varid = *self.src.map_vars.get(p.id).unwrap();
             let ty = self.jit_info.ty(varid);
@@ -375,6 +503,12 @@ where
             if ty.reference_type() {
                 let offset = self.jit_info.offset(varid);
                 self.scopes.add_var(varid, offset);
+            } else if ty.is_struct_or_tuple() {
+                let offset = self.jit_info.offset(varid);
+
+                for ref_offset in struct_ref_fields(self.vm, ty) {
+                    self.scopes.add_var_offset(offset + ref_offset);
+                }
             }
 
             if is_float && freg_idx < FREG_PARAMS.len() {
@@ -392,11 +526,91 @@ where
 
                 reg_idx += 1;
             } else {
-                // ignore params not stored in register
+                if ty.is_struct_or_tuple() {
+                    // copy struct into local variable, only its address was passed
+                    self.asm.emit_comment(Comment::StoreParam(varid));
+                    self.asm
+                        .load_mem(MachineMode::Ptr, REG_TMP1.into(), Mem::Local(param_offset));
+                    self.asm.var_store(&self.jit_info, REG_TMP1.into(), varid);
+                }
+
+                // ignore other params not stored in register
+                param_offset = next_param_offset(param_offset, ty);
+            }
+        }
+
+        if let Some(offset) = self.jit_info.struct_return {
+            if reg_idx < REG_PARAMS.len() {
+                let reg = REG_PARAMS[reg_idx];
+                self.asm
+                    .store_mem(MachineMode::Ptr, Mem::Local(offset), reg.into());
             }
         }
     }
 
+    // baseline code of functions supported by the optimizing compiler counts
+    // invocations and loop iterations to find hot code
+    fn tiering(&self) -> bool {
+        !self.vm.args.flag_disable_tiering
+            && !self.ast.has_optimize
+            && opt::is_supported(self.fct, self.cls_type_params, self.fct_type_params)
+    }
+
+    // jumps to the tier-up thunk once the counter is exhausted, the thunk
+    // optimizes the function and continues in the optimized code
+    fn emit_invocation_counter(&mut self) {
+        let lbl_prolog = self.asm.create_label();
+        let threshold = self.vm.args.tier_threshold();
+
+        self.asm.decrement_counter(REG_TMP2, threshold);
+        self.asm.jump_if(CondCode::NotEqual, lbl_prolog);
+
+        let disp = self.asm.add_addr(self.vm.tier_up_thunk().to_ptr());
+        let pos = self.asm.pos() as i32;
+        self.asm.load_constpool(REG_TMP1, disp + pos);
+        self.asm.jump_reg(REG_TMP1);
+
+        self.asm.bind_label(lbl_prolog);
+    }
+
+    // transfers execution into optimized code at the loop header once the
+    // counter is exhausted, the optimized code continues the loop and its
+    // result is returned from this function
+    fn emit_back_edge_counter(&mut self, id: NodeId, pos: Position) {
+        if !self.tiering() {
+            return;
+        }
+
+        let lbl_loop = self.asm.create_label();
+        let threshold = self.vm.args.osr_threshold();
+
+        self.asm.decrement_counter(REG_PARAMS[1], threshold);
+        self.asm.jump_if(CondCode::NotEqual, lbl_loop);
+
+        self.asm
+            .load_int_const(MachineMode::Ptr, REG_PARAMS[0], id.0 as i64);
+
+        let internal_fct = InternalFct {
+            ptr: Address::from_ptr(osr_request as *const u8),
+            args: &[BuiltinType::Ptr, BuiltinType::Ptr],
+            return_type: BuiltinType::Ptr,
+            throws: false,
+            desc: InternalFctDescriptor::OsrThunk,
+        };
+
+        let gcpoint = create_gcpoint(&self.scopes, &TempOffsets::new());
+        self.asm
+            .native_call(internal_fct, pos, gcpoint, REG_RESULT.into());
+        self.asm
+            .test_and_jump_if(CondCode::Zero, REG_RESULT, lbl_loop);
+
+        // variables are not accessed by this function anymore
+        self.asm.osr_call(REG_RESULT, pos, GcPoint::new());
+        self.emit_epilog();
+
+        self.asm.bind_label(lbl_loop);
+    }
+
     fn emit_prolog(&mut self) {
         let stacksize = self.jit_info.stacksize();
         self.asm.prolog(stacksize);
@@ -420,6 +634,19 @@ where
         if let Some(ref expr) = s.expr {
             self.emit_expr(expr);
 
+            if return_type.is_struct_or_tuple() {
+                // copy struct into storage of caller and return its address
+                let offset = self.jit_info.struct_return.unwrap();
+                self.asm
+                    .load_mem(MachineMode::Ptr, REG_TMP1.into(), Mem::Local(offset));
+                self.asm.copy_struct(
+                    return_type,
+                    Mem::Base(REG_TMP1, 0),
+                    Mem::Base(REG_RESULT, 0),
+                );
+                self.asm.copy_reg(MachineMode::Ptr, REG_RESULT, REG_TMP1);
+            }
+
             if len > 0 {
                 let offset = self.jit_info.eh_return_value.unwrap();
                 let rmode = return_type.mode();
@@ -468,6 +695,7 @@ where
 
         self.active_loop = Some(self.active_finallys.len());
         self.asm.bind_label(lbl_start);
+        self.emit_back_edge_counter(s.id, s.pos);
 
         if s.cond.is_lit_true() {
             // always true => no condition evaluation
@@ -542,6 +770,7 @@ where
 
         self.active_loop = Some(self.active_finallys.len());
         self.asm.bind_label(lbl_start);
+        self.emit_back_edge_counter(s.id, s.pos);
 
         self.save_label_state(lbl_end, lbl_start, |this| {
             this.visit_stmt(&s.block);
@@ -653,12 +882,66 @@ where
 
     fn emit_stmt_block(&mut self, s: &'ast StmtBlockType) {
         self.scopes.push_scope();
+        self.emit_stmts(&s.stmts);
+        self.scopes.pop_scope();
+    }
+
+    fn emit_stmts(&mut self, stmts: &'ast [Box<Stmt>]) {
+        for (idx, stmt) in stmts.iter().enumerate() {
+            if let StmtDefer(ref defer) = **stmt {
+                // all following statements in this block are guarded by the defer
+                self.emit_stmts_with_defer(stmt, defer, &stmts[idx + 1..]);
+                return;
+            }
 
-        for stmt in &s.stmts {
             self.visit_stmt(stmt);
         }
+    }
+
+    fn emit_stmts_with_defer(
+        &mut self,
+        stmt: &'ast Stmt,
+        defer: &'ast StmtDeferType,
+        stmts: &'ast [Box<Stmt>],
+    ) {
+        let lbl_after = self.asm.create_label();
+
+        // deferred expression is executed like a finally-block
+        // on return, break and continue
+        self.active_finallys.push(stmt);
+
+        let start = self.asm.pos();
+        self.emit_stmts(stmts);
+        let end = self.asm.pos();
+
+        self.active_finallys.pop();
+
+        if !stmts.iter().any(|stmt| always_returns(stmt)) {
+            self.emit_expr(&defer.expr);
+            self.asm.jump(lbl_after);
+        }
+
+        // execute deferred expression when exception is thrown
+        // and rethrow exception afterwards
+        let defer_pos = self.asm.pos();
+
+        self.scopes.push_scope();
+
+        let offset = *self.jit_info.map_offsets.get(defer.id).unwrap();
+        self.scopes.add_var_offset(offset);
+
+        self.emit_expr(&defer.expr);
+
+        self.asm
+            .load_mem(MachineMode::Ptr, REG_RESULT.into(), Mem::Local(offset));
+        self.asm.throw(REG_RESULT, defer.pos);
 
         self.scopes.pop_scope();
+
+        self.asm.bind_label(lbl_after);
+
+        self.asm
+            .emit_exception_handler((start, end), defer_pos, Some(offset), CatchType::Any);
     }
 
     fn emit_stmt_var(&mut self, s: &'ast StmtVarType) {
@@ -672,9 +955,33 @@ where
             self.asm.var_store(&self.jit_info, value, var);
         }
 
-        let reference_type = {
-            let ty = self.jit_info.ty(var);
+        let ty = self.jit_info.ty(var);
 
+        if ty.is_struct_or_tuple() {
+            let offset = self.jit_info.offset(var);
+            let ref_fields = struct_ref_fields(self.vm, ty);
+
+            // same as below for references stored in the struct
+            if !ref_fields.is_empty() && !initialized {
+                self.asm.load_nil(REG_RESULT);
+            }
+
+            for ref_offset in ref_fields {
+                if !initialized {
+                    self.asm.store_mem(
+                        MachineMode::Ptr,
+                        Mem::Local(offset + ref_offset),
+                        REG_RESULT.into(),
+                    );
+                }
+
+                self.scopes.add_var_offset(offset + ref_offset);
+            }
+
+            return;
+        }
+
+        let reference_type = {
             if ty.reference_type() {
                 let offset = self.jit_info.offset(var);
                 self.scopes.add_var(var, offset);
@@ -691,6 +998,52 @@ where
         }
     }
 
+    fn emit_stmt_let_tuple(&mut self, s: &'ast StmtLetTupleType) {
+        let ty = self.specialize_type(self.src.ty(s.expr.id()));
+        self.emit_expr(&s.expr);
+
+        let tuple_id = specialize_struct_ty(self.vm, ty);
+        let fields = {
+            let tuple = self.vm.struct_defs.idx(tuple_id);
+            let tuple = tuple.lock();
+            tuple.fields.clone()
+        };
+
+        // REG_RESULT holds the address of the tuple, copy each element into its variable
+        for (binding, field) in s.bindings.iter().zip(&fields) {
+            let var = *self.src.map_vars.get(binding.id).unwrap();
+            let ty = self.jit_info.ty(var);
+            let offset = self.jit_info.offset(var);
+
+            if ty.is_unit() {
+                continue;
+            }
+
+            if ty.is_struct_or_tuple() {
+                self.asm
+                    .copy_struct(ty, Mem::Local(offset), Mem::Base(REG_RESULT, field.offset));
+
+                for ref_offset in struct_ref_fields(self.vm, ty) {
+                    self.scopes.add_var_offset(offset + ref_offset);
+                }
+            } else {
+                let value: ExprStore = if ty.is_float() {
+                    FREG_RESULT.into()
+                } else {
+                    REG_TMP1.into()
+                };
+
+                self.asm
+                    .load_mem(ty.mode(), value, Mem::Base(REG_RESULT, field.offset));
+                self.asm.store_mem(ty.mode(), Mem::Local(offset), value);
+
+                if ty.reference_type() {
+                    self.scopes.add_var(var, offset);
+                }
+            }
+        }
+    }
+
     fn emit_stmt_throw(&mut self, s: &'ast StmtThrowType) {
         self.emit_expr(&s.expr);
         self.asm.test_if_nil_bailout(s.pos, REG_RESULT, Trap::NIL);
@@ -698,6 +1051,11 @@ where
         self.asm.throw(REG_RESULT, s.pos);
     }
 
+    fn emit_stmt_spawn(&mut self, s: &'ast StmtSpawnType) {
+        let csite = self.jit_info.map_csites.get(s.id).unwrap().clone();
+        self.emit_call_site(&csite, s.pos);
+    }
+
     fn emit_stmt_do(&mut self, s: &'ast StmtDoType) {
         let lbl_after = self.asm.create_label();
 
@@ -850,7 +1208,11 @@ where
         let callee = callee.read();
         let return_type = self.specialize_type(callee.return_type);
 
-        let dest = register_for_mode(return_type.mode());
+        let dest = if return_type.is_unit() {
+            REG_RESULT.into()
+        } else {
+            register_for_mode(return_type.mode())
+        };
 
         let mut expr_gen = ExprGen::new(
             self.vm,
@@ -864,7 +1226,7 @@ where
             self.fct_type_params,
         );
 
-        expr_gen.emit_call_site(call_site, pos, dest);
+        expr_gen.emit_call_site(call_site, None, pos, dest);
 
         dest
     }
@@ -876,8 +1238,9 @@ where
                 self.cls_type_params[id.idx()]
             }
 
+            // lambdas use the type params of their enclosing function
             BuiltinType::FctTypeParam(fct_id, id) => {
-                debug_assert!(self.fct.id == fct_id);
+                debug_assert!(self.fct.id == fct_id || self.fct.is_lambda);
                 self.fct_type_params[id.idx()]
             }
 
@@ -891,7 +1254,21 @@ where
                 BuiltinType::Class(cls_id, list_id)
             }
 
-            BuiltinType::Lambda(_) => unimplemented!(),
+            BuiltinType::Lambda(lambda_id) => {
+                BuiltinType::Lambda(info::specialize_lambda(self.vm, lambda_id, |t| {
+                    self.specialize_type(t)
+                }))
+            }
+
+            BuiltinType::Tuple(tuple_id) => {
+                let elements = self.vm.tuple_types.lock().get(tuple_id);
+
+                let elements: Vec<_> = elements.iter().map(|&t| self.specialize_type(t)).collect();
+
+                let tuple_id = self.vm.tuple_types.lock().insert(elements);
+
+                BuiltinType::Tuple(tuple_id)
+            }
 
             _ => ty,
         }
@@ -919,10 +1296,16 @@ impl<'a, 'ast> visit::Visitor<'ast> for CodeGen<'a, 'ast> {
             StmtContinue(ref stmt) => self.emit_stmt_continue(stmt),
             StmtBlock(ref stmt) => self.emit_stmt_block(stmt),
             StmtVar(ref stmt) => self.emit_stmt_var(stmt),
+            StmtLetTuple(ref stmt) => self.emit_stmt_let_tuple(stmt),
             StmtThrow(ref stmt) => self.emit_stmt_throw(stmt),
-            StmtDefer(_) => unimplemented!(),
+            // only invoked for executing the deferred expression,
+            // see emit_stmts_with_defer
+            StmtDefer(ref stmt) => {
+                self.emit_expr(&stmt.expr);
+            }
             StmtDo(ref stmt) => self.emit_stmt_do(stmt),
-            StmtSpawn(_) => unimplemented!(),
+            StmtSpawn(ref stmt) => self.emit_stmt_spawn(stmt),
+            StmtError(_) => unreachable!(),
         }
     }
 
@@ -931,7 +1314,7 @@ impl<'a, 'ast> visit::Visitor<'ast> for CodeGen<'a, 'ast> {
     }
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
 pub enum CondCode {
     Zero,
     NonZero,
@@ -1016,6 +1399,15 @@ impl TempOffsets {
     }
 }
 
+// offsets of all references stored inside the given struct
+pub fn struct_ref_fields(vm: &VM, ty: BuiltinType) -> Vec<i32> {
+    let struct_id = specialize_struct_ty(vm, ty);
+    let struc = vm.struct_defs.idx(struct_id);
+    let struc = struc.lock();
+
+    struc.ref_fields.clone()
+}
+
 pub fn create_gcpoint(vars: &Scopes, temps: &TempOffsets) -> GcPoint {
     let mut offsets = Vec::new();
 
@@ -1056,7 +1448,7 @@ pub fn should_emit_asm(vm: &VM, fct: &Fct) -> bool {
     }
 }
 
-fn fct_pattern_match(vm: &VM, fct: &Fct, pattern: &str) -> bool {
+pub fn fct_pattern_match(vm: &VM, fct: &Fct, pattern: &str) -> bool {
     if pattern == "all" {
         return true;
     }
@@ -1,11 +1,18 @@
 use std::mem::size_of;
+use std::ptr;
+use std::sync::Arc;
+
+use dora_parser::ast::NodeId;
 
 use baseline;
 use baseline::dora_native::{finish_native_call, start_native_call};
 use baseline::fct::{BailoutInfo, JitBaselineFct, JitDescriptor, JitFct};
+use baseline::ic::InlineCache;
 use baseline::map::CodeDescriptor;
 use class::TypeParams;
-use cpu::{Mem, FREG_PARAMS, REG_FP, REG_PARAMS, REG_RESULT, REG_SP, REG_THREAD, REG_TMP1};
+use cpu::{
+    Mem, FREG_PARAMS, REG_FP, REG_PARAMS, REG_RESULT, REG_SP, REG_THREAD, REG_TMP1, REG_TMP2,
+};
 use ctxt::FctId;
 use ctxt::{get_vm, VM};
 use exception::DoraToNativeInfo;
@@ -24,10 +31,33 @@ use ty::MachineMode;
 // executed.
 
 pub fn generate<'a, 'ast: 'a>(vm: &'a VM<'ast>) -> Address {
+    generate_thunk(vm, compile_request as *const u8)
+}
+
+// This code generates the tier-up thunk. Baseline code of a hot function jumps
+// to this thunk with the address of its exhausted invocation counter in REG_TMP2.
+// The thunk recompiles the function with the optimizing compiler, patches the
+// call site and continues execution in the optimized function.
+
+pub fn generate_tier_up<'a, 'ast: 'a>(vm: &'a VM<'ast>) -> Address {
+    generate_thunk(vm, tier_up_request as *const u8)
+}
+
+// This code generates the thunk for misses of inline caches. Call sites with an
+// inline cache invoke this thunk with the address of the cache in REG_TMP2 when
+// the receiver's vtable is not cached yet. The thunk looks up the target for the
+// receiver, adds it to the inline cache and continues execution in the target.
+
+pub fn generate_ic_miss<'a, 'ast: 'a>(vm: &'a VM<'ast>) -> Address {
+    generate_thunk(vm, ic_miss_request as *const u8)
+}
+
+fn generate_thunk<'a, 'ast: 'a>(vm: &'a VM<'ast>, request: *const u8) -> Address {
     let ngen = DoraCompileGen {
         vm: vm,
         masm: MacroAssembler::new(),
         dbg: vm.args.flag_emit_debug_compile,
+        request: request,
     };
 
     let jit_fct = ngen.generate();
@@ -46,6 +76,7 @@ struct DoraCompileGen<'a, 'ast: 'a> {
     vm: &'a VM<'ast>,
     masm: MacroAssembler,
     dbg: bool,
+    request: *const u8,
 }
 
 impl<'a, 'ast> DoraCompileGen<'a, 'ast>
@@ -54,13 +85,14 @@ where
 {
     pub fn generate(mut self) -> JitBaselineFct {
         let framesize = size_of::<DoraToNativeInfo>() as i32
-            + (REG_PARAMS.len() + FREG_PARAMS.len() + 2) as i32 * mem::ptr_width();
+            + (REG_PARAMS.len() + FREG_PARAMS.len() + 3) as i32 * mem::ptr_width();
         let framesize = mem::align_i32(framesize, 16) as i32;
 
         let offset_params = 0;
         let offset_tmp =
             offset_params + (FREG_PARAMS.len() + REG_PARAMS.len()) as i32 * mem::ptr_width();
         let offset_thread = offset_tmp + mem::ptr_width();
+        let offset_counter = offset_thread + mem::ptr_width();
 
         if self.dbg {
             self.masm.debug();
@@ -82,6 +114,12 @@ where
             REG_TMP1.into(),
         );
 
+        self.masm.store_mem(
+            MachineMode::Ptr,
+            Mem::Base(REG_SP, offset_counter),
+            REG_TMP2.into(),
+        );
+
         // store params passed in registers on the stack
         self.store_params(offset_params);
 
@@ -101,7 +139,12 @@ where
             REG_PARAMS[1].into(),
             Mem::Base(REG_SP, offset_params),
         );
-        self.masm.raw_call(compile_request as *const u8);
+        self.masm.load_mem(
+            MachineMode::Ptr,
+            REG_PARAMS[2].into(),
+            Mem::Base(REG_SP, offset_counter),
+        );
+        self.masm.raw_call(self.request);
         self.masm.store_mem(
             MachineMode::Ptr,
             Mem::Base(REG_SP, offset_tmp),
@@ -175,28 +218,7 @@ where
 
 fn compile_request(ra: usize, receiver: Address) -> Address {
     let vm = get_vm();
-
-    let bailout = {
-        let data = {
-            let code_map = vm.code_map.lock();
-            code_map.get(ra.into()).expect("return address not found")
-        };
-
-        let fct_id = match data {
-            CodeDescriptor::DoraFct(fct_id) => fct_id,
-            _ => panic!("expected function for code"),
-        };
-
-        let jit_fct = vm.jit_fcts.idx(fct_id);
-
-        let offset = ra - jit_fct.fct_ptr().to_usize();
-        let jit_fct = jit_fct.to_base().expect("baseline expected");
-        jit_fct
-            .bailouts
-            .get(offset as i32)
-            .expect("bailout info not found")
-            .clone()
-    };
+    let bailout = bailout_for_ra(vm, ra).expect("bailout info not found");
 
     match bailout {
         BailoutInfo::Compile(fct_id, disp, ref cls_tps, ref fct_tps) => {
@@ -206,9 +228,129 @@ fn compile_request(ra: usize, receiver: Address) -> Address {
         BailoutInfo::VirtCompile(vtable_index, ref fct_tps) => {
             patch_vtable_call(vm, receiver, vtable_index, fct_tps)
         }
+
+        BailoutInfo::InlineCache(_, vtable_index, _, _) => {
+            patch_vtable_call(vm, receiver, vtable_index, &TypeParams::empty())
+        }
+    }
+}
+
+fn tier_up_request(ra: usize, receiver: Address, counter: Address) -> Address {
+    let vm = get_vm();
+    let jit_fct = jit_fct_for_counter(vm, counter);
+    let empty = TypeParams::empty();
+
+    let fct_ptr = match baseline::generate_optimized(vm, jit_fct.fct_id(), &empty, &empty) {
+        Some(fct_ptr) => fct_ptr,
+
+        // the counter is negative now and does not trigger again
+        None => return jit_fct.fct_ptr(),
+    };
+
+    // call sites that still invoke the baseline code get patched on their next call
+    reset_counter(counter, 1);
+
+    match bailout_for_ra(vm, ra) {
+        Some(BailoutInfo::Compile(fct_id, disp, ref cls_tps, ref fct_tps)) => {
+            patch_fct_call(vm, ra, fct_id, cls_tps, fct_tps, disp);
+        }
+
+        Some(BailoutInfo::VirtCompile(vtable_index, ref fct_tps)) => {
+            patch_vtable_call(vm, receiver, vtable_index, fct_tps);
+        }
+
+        Some(BailoutInfo::InlineCache(_, vtable_index, disp, _)) => {
+            patch_vtable_call(vm, receiver, vtable_index, &empty);
+
+            let ic = InlineCache::at(Address::from(ra).sub(disp as usize));
+            ic.update_target(vm, jit_fct.fct_ptr(), fct_ptr);
+        }
+
+        // function was not invoked from a patchable call site
+        None => {}
+    }
+
+    fct_ptr
+}
+
+fn ic_miss_request(ra: usize, receiver: Address, ic: Address) -> Address {
+    let vm = get_vm();
+
+    let vtable_index = match bailout_for_ra(vm, ra) {
+        Some(BailoutInfo::InlineCache(_, vtable_index, _, _)) => vtable_index,
+        _ => panic!("inline cache not found"),
+    };
+
+    let obj = unsafe { &*receiver.to_ptr::<Obj>() };
+    let vtable = obj.header().vtbl();
+    let mut fct_ptr = Address::from(vtable.table()[vtable_index as usize]);
+
+    if fct_ptr == vm.compiler_thunk() {
+        fct_ptr = patch_vtable_call(vm, receiver, vtable_index, &TypeParams::empty());
+    }
+
+    if !fct_ptr.is_null() {
+        InlineCache::at(ic).insert(vm, obj.header().vtblptr(), fct_ptr);
+    }
+
+    fct_ptr
+}
+
+// Invoked from baseline code when the back-edge counter of a loop is exhausted,
+// returns the entry point of optimized code that continues the running loop
+// or null if the function cannot be optimized.
+pub extern "C" fn osr_request(loop_id: usize, counter: usize) -> *const u8 {
+    let vm = get_vm();
+    let counter = Address::from(counter);
+    let jit_fct = jit_fct_for_counter(vm, counter);
+
+    match baseline::generate_osr(vm, jit_fct.fct_id(), NodeId(loop_id)) {
+        Some(fct_ptr) => {
+            // later invocations of the baseline code run the loop for another
+            // full threshold before entering the cached optimized code
+            reset_counter(counter, vm.args.osr_threshold());
+            fct_ptr.to_ptr()
+        }
+
+        None => ptr::null(),
+    }
+}
+
+fn jit_fct_for_counter(vm: &VM, counter: Address) -> Arc<JitFct> {
+    let data = {
+        let code_map = vm.code_map.lock();
+        code_map.get(counter).expect("counter not found")
+    };
+
+    match data {
+        CodeDescriptor::DoraFct(jit_fct_id) => vm.jit_fcts.idx(jit_fct_id),
+        _ => panic!("expected function for counter"),
+    }
+}
+
+fn reset_counter(counter: Address, value: i32) {
+    unsafe {
+        *counter.to_mut_ptr::<i32>() = value;
     }
 }
 
+fn bailout_for_ra(vm: &VM, ra: usize) -> Option<BailoutInfo> {
+    let data = {
+        let code_map = vm.code_map.lock();
+        code_map.get(ra.into())
+    };
+
+    let jit_fct_id = match data {
+        Some(CodeDescriptor::DoraFct(jit_fct_id)) => jit_fct_id,
+        _ => return None,
+    };
+
+    let jit_fct = vm.jit_fcts.idx(jit_fct_id);
+    let offset = ra - jit_fct.fct_ptr().to_usize();
+
+    jit_fct.bailout_for_offset(offset as i32).cloned()
+}
+
 fn patch_vtable_call(
     vm: &VM,
     receiver: Address,
@@ -229,7 +371,14 @@ fn patch_vtable_call(
 
         if Some(vtable_index) == fct.vtable_index {
             let empty = TypeParams::empty();
-            fct_ptr = baseline::generate(vm, fct_id, &empty, fct_tps);
+
+            // lambdas are compiled for the type params of their closure
+            fct_ptr = if fct.is_lambda {
+                let type_params = vtable.class().type_params.clone();
+                baseline::generate(vm, fct_id, &empty, &type_params)
+            } else {
+                baseline::generate(vm, fct_id, &empty, fct_tps)
+            };
             break;
         }
     }
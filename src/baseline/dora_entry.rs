@@ -1,7 +1,7 @@
-use baseline::fct::{JitBaselineFct, JitDescriptor, JitFct};
+use baseline::fct::{CatchType, JitBaselineFct, JitDescriptor, JitFct, JitFctId};
 use baseline::map::CodeDescriptor;
-use cpu::{Mem, REG_PARAMS, REG_SP, REG_THREAD, REG_TMP1};
-use ctxt::VM;
+use cpu::{Mem, CALLEE_SAVED, REG_PARAMS, REG_SP, REG_THREAD, REG_TMP1};
+use ctxt::{exception_set, VM};
 use gc::Address;
 use masm::MacroAssembler;
 use mem;
@@ -12,6 +12,7 @@ pub fn generate<'a, 'ast: 'a>(vm: &'a VM<'ast>) -> Address {
         vm: vm,
         masm: MacroAssembler::new(),
         dbg: vm.args.flag_emit_debug_entry,
+        catch: false,
     };
 
     let jit_fct = ngen.generate();
@@ -27,10 +28,37 @@ pub fn generate<'a, 'ast: 'a>(vm: &'a VM<'ast>) -> Address {
     ptr
 }
 
+// entry used by the interpreter to call compiled functions, an exception
+// escaping the compiled function is caught and left as pending exception
+// (see `exception_get_and_clear`) instead of terminating the program
+pub fn generate_interpreter_entry<'a, 'ast: 'a>(vm: &'a VM<'ast>) -> Address {
+    let ngen = DoraEntryGen {
+        vm: vm,
+        masm: MacroAssembler::new(),
+        dbg: vm.args.flag_emit_debug_entry,
+        catch: true,
+    };
+
+    let jit_fct = ngen.generate();
+    let ptr = jit_fct.fct_ptr();
+    let start = jit_fct.ptr_start();
+    let end = jit_fct.ptr_end();
+
+    let jit_fct_id: JitFctId = vm.jit_fcts.push(JitFct::Base(jit_fct)).into();
+    vm.insert_code_map(start, end, CodeDescriptor::InterpreterEntry(jit_fct_id));
+
+    ptr
+}
+
+extern "C" fn catch_exception(exception: *const u8) {
+    exception_set(exception);
+}
+
 struct DoraEntryGen<'a, 'ast: 'a> {
     vm: &'a VM<'ast>,
     masm: MacroAssembler,
     dbg: bool,
+    catch: bool,
 }
 
 impl<'a, 'ast> DoraEntryGen<'a, 'ast>
@@ -38,10 +66,11 @@ where
     'ast: 'a,
 {
     pub fn generate(mut self) -> JitBaselineFct {
-        let framesize = mem::ptr_width_usize();
+        let framesize = (2 + CALLEE_SAVED.len()) * mem::ptr_width_usize();
         let framesize = mem::align_usize(framesize, 16) as i32;
 
         let offset_thread = 0;
+        let offset_exception = (1 + CALLEE_SAVED.len() as i32) * mem::ptr_width();
 
         if self.dbg {
             self.masm.debug();
@@ -54,19 +83,60 @@ where
             REG_THREAD.into(),
         );
 
+        for (ind, &reg) in CALLEE_SAVED.iter().enumerate() {
+            let offset = (ind as i32 + 1) * mem::ptr_width();
+            self.masm
+                .store_mem(MachineMode::Ptr, Mem::Base(REG_SP, offset), reg.into());
+        }
+
         self.masm
             .copy_reg(MachineMode::Ptr, REG_THREAD, REG_PARAMS[0]);
         self.masm
             .copy_reg(MachineMode::Ptr, REG_TMP1, REG_PARAMS[1]);
-        self.masm
-            .copy_reg(MachineMode::Ptr, REG_PARAMS[0], REG_PARAMS[2]);
+
+        // the arguments of the called function follow the thread and the function
+        for idx in 2..REG_PARAMS.len() {
+            self.masm
+                .copy_reg(MachineMode::Ptr, REG_PARAMS[idx - 2], REG_PARAMS[idx]);
+        }
+
+        let try_start = self.masm.pos();
         self.masm.call_reg(REG_TMP1);
+        let try_end = self.masm.pos();
+
+        if self.catch {
+            let lbl_return = self.masm.create_label();
+            self.masm.jump(lbl_return);
+
+            let catch = self.masm.pos();
+            self.masm.load_mem(
+                MachineMode::Ptr,
+                REG_PARAMS[0].into(),
+                Mem::Base(REG_SP, offset_exception),
+            );
+            self.masm.raw_call(catch_exception as *const u8);
+            self.masm.bind_label(lbl_return);
+
+            self.masm.emit_exception_handler(
+                (try_start, try_end),
+                catch,
+                Some(offset_exception - framesize),
+                CatchType::Any,
+            );
+        }
 
         self.masm.load_mem(
             MachineMode::Ptr,
             REG_THREAD.into(),
             Mem::Base(REG_SP, offset_thread),
         );
+
+        for (ind, &reg) in CALLEE_SAVED.iter().enumerate() {
+            let offset = (ind as i32 + 1) * mem::ptr_width();
+            self.masm
+                .load_mem(MachineMode::Ptr, reg.into(), Mem::Base(REG_SP, offset));
+        }
+
         self.masm.epilog(framesize);
 
         self.masm
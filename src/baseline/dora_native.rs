@@ -5,7 +5,10 @@ use dora_parser::lexer::position::Position;
 
 use baseline::fct::{JitBaselineFct, JitDescriptor, JitFct, JitFctId};
 use baseline::map::CodeDescriptor;
-use cpu::{Mem, FREG_PARAMS, REG_FP, REG_PARAMS, REG_RESULT, REG_SP, REG_THREAD};
+use cpu::{
+    next_param_offset, Mem, FREG_PARAMS, PARAM_OFFSET, REG_FP, REG_PARAMS, REG_RESULT, REG_SP,
+    REG_THREAD, REG_TMP1,
+};
 use ctxt::VM;
 use ctxt::{exception_get_and_clear, FctId};
 use exception::DoraToNativeInfo;
@@ -40,6 +43,8 @@ pub enum InternalFctDescriptor {
     NativeThunk(FctId),
     AllocThunk,
     TrapThunk,
+    OsrThunk,
+    EnumEqualsThunk,
 }
 
 pub struct InternalFct<'a> {
@@ -69,6 +74,8 @@ pub fn generate<'a, 'ast: 'a>(vm: &'a VM<'ast>, fct: InternalFct, dbg: bool) ->
         InternalFctDescriptor::NativeThunk(_) => CodeDescriptor::NativeThunk(jit_fct_id),
         InternalFctDescriptor::TrapThunk => CodeDescriptor::TrapThunk,
         InternalFctDescriptor::AllocThunk => CodeDescriptor::AllocThunk,
+        InternalFctDescriptor::OsrThunk => CodeDescriptor::OsrThunk,
+        InternalFctDescriptor::EnumEqualsThunk => CodeDescriptor::EnumEqualsThunk,
     };
 
     vm.insert_code_map(jit_start, jit_end, code_desc);
@@ -90,16 +97,18 @@ where
 {
     pub fn generate(mut self) -> JitBaselineFct {
         let save_return = self.fct.return_type != BuiltinType::Unit;
-        let args = self.fct.args.len();
+        let (args, stack_args) = count_params(self.fct.args);
 
         let framesize = size_of::<DoraToNativeInfo>() as i32 +          // save d2n structure on stack
             mem::ptr_width() +                              // store thread register
             (args as i32 * mem::ptr_width()) +              // store arguments on stack
+            (stack_args as i32 * mem::ptr_width()) +        // pass arguments on stack
             if save_return { mem::ptr_width() } else { 0 }; // save return value on stack
 
         let framesize = mem::align_i32(framesize, 16);
 
-        let offset_return = 0;
+        let offset_stack_args = 0;
+        let offset_return = offset_stack_args + stack_args as i32 * mem::ptr_width();
         let offset_args = offset_return + if save_return { mem::ptr_width() } else { 0 };
         let offset_thread = offset_args + args as i32 * mem::ptr_width();
 
@@ -124,6 +133,7 @@ where
         self.masm.raw_call(start_native_call as *const u8);
 
         restore_params(&mut self.masm, self.fct.args, offset_args);
+        copy_stack_params(&mut self.masm, self.fct.args, offset_stack_args);
 
         self.masm.raw_call(self.fct.ptr.to_ptr());
 
@@ -164,12 +174,33 @@ where
             InternalFctDescriptor::NativeThunk(fid) => JitDescriptor::NativeThunk(fid),
             InternalFctDescriptor::AllocThunk => JitDescriptor::AllocThunk,
             InternalFctDescriptor::TrapThunk => JitDescriptor::TrapThunk,
+            InternalFctDescriptor::OsrThunk => JitDescriptor::OsrThunk,
+            InternalFctDescriptor::EnumEqualsThunk => JitDescriptor::EnumEqualsThunk,
         };
 
         self.masm.jit(self.vm, framesize, desc, self.fct.throws)
     }
 }
 
+// returns the number of arguments passed in registers and on the stack
+fn count_params(args: &[BuiltinType]) -> (usize, usize) {
+    let mut reg_idx = 0;
+    let mut freg_idx = 0;
+    let mut stack_args = 0;
+
+    for &ty in args {
+        if ty.mode().is_float() && freg_idx < FREG_PARAMS.len() {
+            freg_idx += 1;
+        } else if !ty.mode().is_float() && reg_idx < REG_PARAMS.len() {
+            reg_idx += 1;
+        } else {
+            stack_args += 1;
+        }
+    }
+
+    (reg_idx + freg_idx, stack_args)
+}
+
 fn save_params(masm: &mut MacroAssembler, args: &[BuiltinType], offset_args: i32) {
     let mut reg_idx = 0;
     let mut freg_idx = 0;
@@ -189,7 +220,8 @@ fn save_params(masm: &mut MacroAssembler, args: &[BuiltinType], offset_args: i32
             masm.store_mem(mode, Mem::Base(REG_SP, offset), reg);
             reg_idx += 1;
         } else {
-            panic!("parameter saved on stack");
+            // stays in the frame of the caller
+            continue;
         }
 
         idx += 1;
@@ -215,13 +247,43 @@ fn restore_params(masm: &mut MacroAssembler, args: &[BuiltinType], offset_args:
             masm.load_mem(mode, reg, Mem::Base(REG_SP, offset));
             reg_idx += 1;
         } else {
-            panic!("parameter saved on stack");
+            continue;
         }
 
         idx += 1;
     }
 }
 
+// Dora code passes the remaining arguments in 8-byte slots in argument
+// order like the SysV and AAPCS64 calling conventions, so they can be
+// copied from the caller's frame to the bottom of the native stub's frame
+fn copy_stack_params(masm: &mut MacroAssembler, args: &[BuiltinType], offset_stack_args: i32) {
+    let mut reg_idx = 0;
+    let mut freg_idx = 0;
+    let mut param_offset = PARAM_OFFSET;
+    let mut offset = offset_stack_args;
+
+    for &ty in args {
+        let is_float = ty.mode().is_float();
+
+        if is_float && freg_idx < FREG_PARAMS.len() {
+            freg_idx += 1;
+        } else if !is_float && reg_idx < REG_PARAMS.len() {
+            reg_idx += 1;
+        } else {
+            masm.load_mem(
+                MachineMode::Ptr,
+                REG_TMP1.into(),
+                Mem::Base(REG_FP, param_offset),
+            );
+            masm.store_mem(MachineMode::Ptr, Mem::Base(REG_SP, offset), REG_TMP1.into());
+
+            param_offset = next_param_offset(param_offset, ty);
+            offset += mem::ptr_width();
+        }
+    }
+}
+
 pub fn start_native_call(fp: *const u8, pc: usize) {
     unsafe {
         // fp is framepointer of native stub
@@ -1,12 +1,12 @@
 use baseline::asm::BaselineAssembler;
 use baseline::codegen::{
-    self, dump_asm, register_for_mode, should_emit_asm, should_emit_debug, CondCode, Scopes,
-    TempOffsets,
+    self, dump_asm, register_for_mode, should_emit_asm, should_emit_debug, struct_ref_fields,
+    CondCode, Scopes, TempOffsets,
 };
 use baseline::dora_native::{self, InternalFct, InternalFctDescriptor};
 use baseline::fct::{CatchType, Comment, GcPoint};
-use baseline::info::JitInfo;
-use class::{ClassDefId, ClassSize, FieldId, TypeParams};
+use baseline::info::{self, JitInfo};
+use class::{ClassDefId, ClassId, ClassSize, FieldId, TypeParams};
 use cpu::{
     FReg, Mem, Reg, FREG_PARAMS, FREG_RESULT, FREG_TMP1, REG_PARAMS, REG_RESULT, REG_TMP1, REG_TMP2,
 };
@@ -18,13 +18,18 @@ use dora_parser::lexer::position::Position;
 use dora_parser::lexer::token::{FloatSuffix, IntSuffix};
 use driver::cmd::AsmSyntax;
 use gc::Address;
+use masm::Label;
 use mem;
-use object::{Header, Str};
+use object::{offset_of_array_data, Header, Str};
 use os::signal::Trap;
-use semck::specialize::{specialize_class_id, specialize_class_ty};
-use ty::{BuiltinType, MachineMode};
+use semck::specialize::{specialize_class_id, specialize_class_ty, specialize_struct_ty};
+use stdlib;
+use ty::{BuiltinType, LambdaId, MachineMode};
 use vtable::{VTable, DISPLAY_SIZE};
 
+// minimum number of int arms before a match is lowered to a jump table
+const MIN_JUMP_TABLE_ARMS: usize = 4;
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum ExprStore {
     Reg(Reg),
@@ -140,19 +145,446 @@ where
             ExprLitStruct(ref expr) => self.emit_lit_struct(expr, dest),
             ExprUn(ref expr) => self.emit_un(expr, dest),
             ExprIdent(ref expr) => self.emit_ident(expr, dest),
+            ExprPath(ref expr) => self.emit_path(expr, dest),
             ExprAssign(ref expr) => self.emit_assign(expr),
             ExprBin(ref expr) => self.emit_bin(expr, dest),
             ExprCall(ref expr) => self.emit_call(expr, dest),
             ExprDelegation(ref expr) => self.emit_delegation(expr, dest),
             ExprField(ref expr) => self.emit_field(expr, dest),
-            ExprSelf(_) => self.emit_self(dest.reg()),
+            ExprSelf(ref expr) => self.emit_this(expr, dest),
             ExprSuper(_) => self.emit_self(dest.reg()),
             ExprNil(_) => self.emit_nil(dest.reg()),
             ExprArray(ref expr) => self.emit_array(expr, dest),
             ExprConv(ref expr) => self.emit_conv(expr, dest.reg()),
             ExprTry(ref expr) => self.emit_try(expr, dest),
-            ExprLambda(_) => unimplemented!(),
+            ExprLambda(ref expr) => self.emit_lambda(expr, dest.reg()),
+            ExprMatch(ref expr) => self.emit_match(expr, dest),
+            ExprTuple(ref expr) => self.emit_tuple(expr, dest),
+            ExprTupleElement(ref expr) => self.emit_tuple_element(expr, dest),
+        }
+    }
+
+    fn emit_this(&mut self, e: &'ast ExprSelfType, dest: ExprStore) {
+        // `self` in a lambda is loaded from the closure object
+        if let Some(&ident) = self.src.map_idents.get(e.id) {
+            self.emit_ident_type(e.pos, ident, dest);
+        } else {
+            self.emit_self(dest.reg());
+        }
+    }
+
+    fn emit_lambda(&mut self, e: &'ast ExprLambdaType, dest: Reg) {
+        let lambda = self.src.map_lambdas.get(e.id).unwrap().clone();
+        let cls_ty = self.vm.classes.idx(lambda.cls_id).read().ty;
+        let cls_id = specialize_class_ty(self.vm, self.specialize_type(cls_ty));
+        let offset = *self.jit_info.map_offsets.get(e.id).unwrap();
+
+        self.emit_allocation(e.pos, &[], cls_id, offset, REG_RESULT);
+
+        // after the allocation `offset` is initialized,
+        // add it to the set of temporaries such that it is part
+        // of the gc point
+        self.temps.insert(offset);
+
+        let fields = {
+            let cls = self.vm.class_defs.idx(cls_id);
+            let cls = cls.read();
+            cls.fields.clone()
+        };
+
+        // store captured values in the closure
+        for (idx, (field, &capture)) in fields.iter().zip(&lambda.captures).enumerate() {
+            let reg = result_reg(field.ty.mode());
+            self.emit_ident_type(e.pos, capture, reg);
+
+            self.asm
+                .load_mem(MachineMode::Ptr, REG_TMP1.into(), Mem::Local(offset));
+            self.asm
+                .emit_comment(Comment::StoreField(cls_id, FieldId::from(idx)));
+
+            self.emit_store_field(e.pos, field.ty, REG_TMP1, field.offset, reg);
+        }
+
+        self.asm
+            .load_mem(MachineMode::Ptr, dest.into(), Mem::Local(offset));
+        self.temps.remove(offset);
+    }
+
+    fn emit_path(&mut self, e: &'ast ExprPathType, dest: ExprStore) {
+        match *self.src.map_idents.get(e.id).unwrap() {
+            IdentType::EnumValue(enum_id, idx) => {
+                self.emit_enum_value(e.pos, e.id, enum_id, idx, &[], dest.reg());
+            }
+
+            IdentType::Struct(_) => {
+                let offset = -(self.jit_info.localsize + self.jit_info.get_store(e.id).offset());
+                self.asm.lea(dest.reg(), Mem::Local(offset));
+            }
+
+            ident => self.emit_ident_type(e.pos, ident, dest),
+        }
+    }
+
+    fn emit_enum_value(
+        &mut self,
+        pos: Position,
+        id: NodeId,
+        enum_id: EnumId,
+        idx: usize,
+        args: &'ast [Box<Expr>],
+        dest: Reg,
+    ) {
+        let cls_id = {
+            let xenum = self.vm.enums.idx(enum_id);
+            let xenum = xenum.lock();
+            xenum.variants[idx].cls_id
+        };
+
+        let cls_id = specialize_class_id(self.vm, cls_id);
+        let offset = -(self.jit_info.localsize + self.jit_info.get_store(id).offset());
+
+        self.emit_allocation(pos, &[], cls_id, offset, REG_RESULT);
+        self.temps.insert(offset);
+
+        let fields = {
+            let cls = self.vm.class_defs.idx(cls_id);
+            let cls = cls.read();
+            cls.fields.clone()
+        };
+
+        // the first field stores the tag of the variant
+        self.asm
+            .load_int_const(MachineMode::Int32, REG_TMP1, idx as i64);
+        self.asm.store_mem(
+            MachineMode::Int32,
+            Mem::Base(REG_RESULT, fields[0].offset),
+            REG_TMP1.into(),
+        );
+
+        for (field, arg) in fields[1..].iter().zip(args) {
+            if field.ty.is_unit() {
+                self.emit_expr(arg, REG_RESULT.into());
+                continue;
+            }
+
+            let reg = result_reg(field.ty.mode());
+            self.emit_expr(arg, reg);
+
+            self.asm
+                .load_mem(MachineMode::Ptr, REG_TMP1.into(), Mem::Local(offset));
+            self.emit_store_field(pos, field.ty, REG_TMP1, field.offset, reg);
+        }
+
+        self.asm
+            .load_mem(MachineMode::Ptr, dest.into(), Mem::Local(offset));
+        self.temps.remove(offset);
+    }
+
+    fn emit_match(&mut self, e: &'ast ExprMatchType, dest: ExprStore) {
+        let info = self.src.map_matches.get(e.id).unwrap().clone();
+        let ty = self.ty(e.expr.id());
+        let offset = *self.jit_info.map_offsets.get(e.id).unwrap();
+
+        self.emit_expr(&e.expr, REG_RESULT.into());
+        self.asm
+            .store_mem(ty.mode(), Mem::Local(offset), REG_RESULT.into());
+
+        // the value needs to survive the calls to Str::equals
+        if ty.reference_type() {
+            self.temps.insert(offset);
+        }
+
+        let lbl_arms = e
+            .arms
+            .iter()
+            .map(|_| self.asm.create_label())
+            .collect::<Vec<_>>();
+
+        if !self.emit_match_jump_table(&info, ty.mode(), offset, &lbl_arms) {
+            for (idx, arm) in e.arms.iter().enumerate() {
+                self.emit_match_test(arm, info.arms[idx], ty.mode(), offset, lbl_arms[idx]);
+            }
+        }
+
+        if ty.reference_type() {
+            self.temps.remove(offset);
+        }
+
+        let lbl_end = self.asm.create_label();
+
+        for (idx, arm) in e.arms.iter().enumerate() {
+            self.asm.bind_label(lbl_arms[idx]);
+            self.scopes.push_scope();
+
+            if let MatchPattern::Type(Some(_), _) = arm.pattern {
+                let var = *self.src.map_vars.get(arm.id).unwrap();
+                let var_offset = self.jit_info.offset(var);
+
+                self.asm
+                    .load_mem(MachineMode::Ptr, REG_RESULT.into(), Mem::Local(offset));
+                self.asm
+                    .store_mem(MachineMode::Ptr, Mem::Local(var_offset), REG_RESULT.into());
+                self.scopes.add_var(var, var_offset);
+            } else if arm.pattern.is_enum() {
+                self.emit_match_enum_bindings(arm, offset);
+            }
+
+            self.emit_expr(&arm.value, dest);
+
+            self.scopes.pop_scope();
+            self.asm.jump(lbl_end);
+        }
+
+        self.asm.bind_label(lbl_end);
+    }
+
+    // dense integer matches jump directly to the matching arm,
+    // returns false if no jump table was emitted
+    fn emit_match_jump_table(
+        &mut self,
+        info: &MatchInfo,
+        mode: MachineMode,
+        offset: i32,
+        lbl_arms: &[Label],
+    ) -> bool {
+        if !info.is_int_switch() || mode == MachineMode::Int64 {
+            return false;
+        }
+
+        let values = info
+            .arms
+            .iter()
+            .filter_map(|arm| match *arm {
+                MatchArmInfo::Int(value) => Some(value),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        if values.len() < MIN_JUMP_TABLE_ARMS {
+            return false;
+        }
+
+        let min = *values.iter().min().unwrap();
+        let max = *values.iter().max().unwrap();
+        let len = (max - min + 1) as usize;
+
+        if len > 3 * values.len() || min == i32::min_value() as i64 {
+            return false;
+        }
+
+        // values without an arm jump to the `_` arm
+        let lbl_default = *lbl_arms.last().unwrap();
+        let mut targets = vec![lbl_default; len];
+
+        for (idx, &value) in values.iter().enumerate() {
+            targets[(value - min) as usize] = lbl_arms[idx];
+        }
+
+        self.asm
+            .load_mem(mode, REG_RESULT.into(), Mem::Local(offset));
+
+        if min != 0 {
+            self.asm
+                .int_add_imm(MachineMode::Int32, REG_RESULT, REG_RESULT, -min);
         }
+
+        self.asm
+            .cmp_reg_imm(MachineMode::Int32, REG_RESULT, (len - 1) as i32);
+        self.asm.jump_if(CondCode::UnsignedGreater, lbl_default);
+        self.asm.jump_table(REG_RESULT, &targets);
+
+        true
+    }
+
+    fn emit_match_test(
+        &mut self,
+        arm: &'ast MatchArm,
+        info: MatchArmInfo,
+        mode: MachineMode,
+        offset: i32,
+        lbl: Label,
+    ) {
+        match info {
+            MatchArmInfo::Int(value) => {
+                self.asm
+                    .load_mem(mode, REG_RESULT.into(), Mem::Local(offset));
+
+                // bytes are zero-extended when loaded
+                let mode = if mode == MachineMode::Int64 {
+                    MachineMode::Int64
+                } else {
+                    MachineMode::Int32
+                };
+
+                self.asm.load_int_const(mode, REG_TMP1, value);
+                self.asm.cmp_reg(mode, REG_RESULT, REG_TMP1);
+                self.asm.jump_if(CondCode::Equal, lbl);
+            }
+
+            MatchArmInfo::Bool(value) => {
+                self.asm
+                    .load_mem(MachineMode::Int8, REG_RESULT.into(), Mem::Local(offset));
+                let cond = if value {
+                    CondCode::NonZero
+                } else {
+                    CondCode::Zero
+                };
+                self.asm.test_and_jump_if(cond, REG_RESULT, lbl);
+            }
+
+            MatchArmInfo::Str => {
+                self.emit_call_site_id(arm.id, arm.pos, REG_RESULT.into());
+                self.asm
+                    .test_and_jump_if(CondCode::NonZero, REG_RESULT, lbl);
+            }
+
+            MatchArmInfo::Class(conv) if !conv.valid => {
+                self.asm
+                    .load_mem(MachineMode::Ptr, REG_RESULT.into(), Mem::Local(offset));
+                self.emit_instance_of_jump(conv.cls_id, lbl);
+            }
+
+            MatchArmInfo::Enum(idx) => {
+                self.asm
+                    .load_mem(MachineMode::Ptr, REG_RESULT.into(), Mem::Local(offset));
+                self.asm.load_field(
+                    MachineMode::Int32,
+                    REG_RESULT.into(),
+                    REG_RESULT,
+                    Header::size(),
+                    arm.pos.line as i32,
+                );
+                self.asm
+                    .cmp_reg_imm(MachineMode::Int32, REG_RESULT, idx as i32);
+                self.asm.jump_if(CondCode::Equal, lbl);
+            }
+
+            MatchArmInfo::Class(_) | MatchArmInfo::Underscore => self.asm.jump(lbl),
+        }
+    }
+
+    // copies the payload of the matched variant into the variables of the bindings
+    fn emit_match_enum_bindings(&mut self, arm: &'ast MatchArm, offset: i32) {
+        let bindings = match arm.pattern {
+            MatchPattern::Enum(_, ref bindings) => bindings,
+            _ => unreachable!(),
+        };
+
+        let cls_id = match *self.src.map_idents.get(arm.id).unwrap() {
+            IdentType::EnumValue(enum_id, idx) => {
+                let xenum = self.vm.enums.idx(enum_id);
+                let xenum = xenum.lock();
+                xenum.variants[idx].cls_id
+            }
+
+            _ => unreachable!(),
+        };
+
+        let cls_id = specialize_class_id(self.vm, cls_id);
+        let fields = {
+            let cls = self.vm.class_defs.idx(cls_id);
+            let cls = cls.read();
+            cls.fields.clone()
+        };
+
+        self.asm
+            .load_mem(MachineMode::Ptr, REG_RESULT.into(), Mem::Local(offset));
+
+        // the first field stores the tag of the variant
+        for (binding, field) in bindings.iter().zip(&fields[1..]) {
+            let var = match self.src.map_vars.get(binding.id) {
+                Some(&var) => var,
+                None => continue,
+            };
+
+            let ty = self.jit_info.ty(var);
+            let var_offset = self.jit_info.offset(var);
+
+            if ty.is_unit() {
+                continue;
+            }
+
+            if ty.is_struct_or_tuple() {
+                self.asm.copy_struct(
+                    ty,
+                    Mem::Local(var_offset),
+                    Mem::Base(REG_RESULT, field.offset),
+                );
+
+                for ref_offset in struct_ref_fields(self.vm, ty) {
+                    self.scopes.add_var_offset(var_offset + ref_offset);
+                }
+            } else {
+                let value: ExprStore = if ty.is_float() {
+                    FREG_RESULT.into()
+                } else {
+                    REG_TMP1.into()
+                };
+
+                self.asm
+                    .load_mem(ty.mode(), value, Mem::Base(REG_RESULT, field.offset));
+                self.asm.store_mem(ty.mode(), Mem::Local(var_offset), value);
+
+                if ty.reference_type() {
+                    self.scopes.add_var(var, var_offset);
+                }
+            }
+        }
+    }
+
+    // jumps to lbl_match if the object in REG_RESULT is an instance of the class
+    fn emit_instance_of_jump(&mut self, cls_id: ClassId, lbl_match: Label) {
+        let lbl_next = self.asm.test_if_nil(REG_RESULT);
+
+        let cls_id = specialize_class_id(self.vm, cls_id);
+        let cls = self.vm.class_defs.idx(cls_id);
+        let cls = cls.read();
+
+        let vtable: &VTable = cls.vtable.as_ref().unwrap();
+
+        // tmp1 = <vtable of object>
+        self.asm
+            .load_mem(MachineMode::Ptr, REG_TMP1.into(), Mem::Base(REG_RESULT, 0));
+
+        let disp = self.asm.add_addr(vtable as *const _ as *mut u8);
+        let pos = self.asm.pos() as i32;
+
+        // tmp2 = <vtable of T>
+        self.asm.load_constpool(REG_TMP2, disp + pos);
+
+        if vtable.subtype_depth >= DISPLAY_SIZE as i32 {
+            self.asm.cmp_mem_imm(
+                MachineMode::Int32,
+                Mem::Base(REG_TMP1, VTable::offset_of_depth()),
+                vtable.subtype_depth,
+            );
+            self.asm.jump_if(CondCode::Less, lbl_next);
+
+            // tmp1 = tmp1.subtype_overflow
+            self.asm.load_mem(
+                MachineMode::Ptr,
+                REG_TMP1.into(),
+                Mem::Base(REG_TMP1, VTable::offset_of_overflow()),
+            );
+
+            let overflow_offset = mem::ptr_width() * (vtable.subtype_depth - DISPLAY_SIZE as i32);
+            self.asm.cmp_mem(
+                MachineMode::Ptr,
+                Mem::Base(REG_TMP1, overflow_offset),
+                REG_TMP2,
+            );
+        } else {
+            let display_entry =
+                VTable::offset_of_display() + vtable.subtype_depth * mem::ptr_width();
+            self.asm.cmp_mem(
+                MachineMode::Ptr,
+                Mem::Base(REG_TMP1, display_entry),
+                REG_TMP2,
+            );
+        }
+
+        self.asm.jump_if(CondCode::Equal, lbl_match);
+        self.asm.bind_label(lbl_next);
     }
 
     fn emit_try(&mut self, e: &'ast ExprTryType, dest: ExprStore) {
@@ -213,7 +645,37 @@ where
                 self.asm.bind_label(lbl_after);
             }
 
-            TryMode::Opt => panic!("unsupported"),
+            TryMode::Opt => {
+                let lbl_after = self.asm.create_label();
+                let ty = self.ty(e.id);
+
+                let try_span = {
+                    let start = self.asm.pos();
+                    self.emit_expr(&e.expr, dest);
+                    let end = self.asm.pos();
+
+                    self.asm.jump(lbl_after);
+
+                    (start, end)
+                };
+
+                // the exception is dropped and replaced with nil
+                let catch_span = {
+                    let start = self.asm.pos();
+
+                    if !ty.is_unit() {
+                        self.asm.load_nil(dest.reg());
+                    }
+
+                    let end = self.asm.pos();
+
+                    (start, end)
+                };
+
+                self.asm
+                    .emit_exception_handler(try_span, catch_span.0, None, CatchType::Any);
+                self.asm.bind_label(lbl_after);
+            }
         }
     }
 
@@ -362,11 +824,16 @@ where
             match intrinsic {
                 Intrinsic::GenericArrayGet => {
                     let ty = self.ty(e.id);
-                    self.emit_array_get(e.pos, ty.mode(), &e.object, &e.index, dest);
+
+                    if ty.is_struct_or_tuple() {
+                        self.emit_array_get_struct(e.id, e.pos, ty, &e.object, &e.index, dest);
+                    } else {
+                        self.emit_array_get(e.id, e.pos, ty.mode(), &e.object, &e.index, dest);
+                    }
                 }
 
                 Intrinsic::StrGet => {
-                    self.emit_array_get(e.pos, MachineMode::Int8, &e.object, &e.index, dest)
+                    self.emit_array_get(e.id, e.pos, MachineMode::Int8, &e.object, &e.index, dest)
                 }
 
                 _ => panic!("unexpected intrinsic {:?}", intrinsic),
@@ -381,9 +848,7 @@ where
         let ty = self.ty(id);
         let offset = -(self.jit_info.localsize + self.jit_info.get_store(id).offset());
 
-        if ty.reference_type() {
-            self.temps.insert(offset);
-        }
+        self.insert_temp_with_type(ty, offset);
 
         offset
     }
@@ -392,9 +857,7 @@ where
         let offset = -(self.jit_info.localsize + arg.offset());
         let ty = arg.ty();
 
-        if ty.reference_type() {
-            self.temps.insert(offset);
-        }
+        self.insert_temp_with_type(ty, offset);
 
         offset
     }
@@ -407,15 +870,26 @@ where
 
     fn free_temp_for_node(&mut self, expr: &Expr, offset: i32) {
         let ty = self.ty(expr.id());
+        self.free_temp_with_type(ty, offset);
+    }
 
+    fn insert_temp_with_type(&mut self, ty: BuiltinType, offset: i32) {
         if ty.reference_type() {
-            self.temps.remove(offset);
+            self.temps.insert(offset);
+        } else if ty.is_struct_or_tuple() {
+            for ref_offset in struct_ref_fields(self.vm, ty) {
+                self.temps.insert(offset + ref_offset);
+            }
         }
     }
 
     fn free_temp_with_type(&mut self, ty: BuiltinType, offset: i32) {
         if ty.reference_type() {
             self.temps.remove(offset);
+        } else if ty.is_struct_or_tuple() {
+            for ref_offset in struct_ref_fields(self.vm, ty) {
+                self.temps.remove(offset + ref_offset);
+            }
         }
     }
 
@@ -438,19 +912,46 @@ where
     }
 
     fn emit_field(&mut self, expr: &'ast ExprFieldType, dest: ExprStore) {
-        let (ty, field) = {
-            let ident_type = self.src.map_idents.get(expr.id).unwrap();
+        let &ident_type = self.src.map_idents.get(expr.id).unwrap();
+
+        match ident_type {
+            IdentType::Field(ty, field) => {
+                let ty = self.specialize_type(ty);
 
-            match ident_type {
-                &IdentType::Field(ty, field) => (ty, field),
-                _ => unreachable!(),
+                self.emit_expr(&expr.object, REG_RESULT.into());
+                self.emit_field_access(expr.pos, ty, field, REG_RESULT, dest);
             }
-        };
 
-        let ty = self.specialize_type(ty);
+            IdentType::StructField(ty, field) => {
+                let ty = self.specialize_type(ty);
 
-        self.emit_expr(&expr.object, REG_RESULT.into());
-        self.emit_field_access(expr.pos, ty, field, REG_RESULT, dest);
+                self.emit_expr(&expr.object, REG_RESULT.into());
+                self.emit_struct_field_access(ty, field, REG_RESULT, dest);
+            }
+
+            _ => unreachable!(),
+        }
+    }
+
+    fn emit_struct_field_access(
+        &mut self,
+        ty: BuiltinType,
+        fieldid: StructFieldId,
+        src: Reg,
+        dest: ExprStore,
+    ) {
+        let struct_id = specialize_struct_ty(self.vm, ty);
+        let struc = self.vm.struct_defs.idx(struct_id);
+        let struc = struc.lock();
+        let field = &struc.fields[fieldid.idx()];
+
+        // nested structs are accessed by their address
+        if field.ty.is_struct_or_tuple() {
+            self.asm.lea(dest.reg(), Mem::Base(src, field.offset));
+        } else {
+            self.asm
+                .load_mem(field.ty.mode(), dest, Mem::Base(src, field.offset));
+        }
     }
 
     fn emit_field_access(
@@ -467,8 +968,51 @@ where
         let field = &cls.fields[fieldid.idx()];
 
         self.asm.emit_comment(Comment::LoadField(cls_id, fieldid));
-        self.asm
-            .load_field(field.ty.mode(), dest, src, field.offset, pos.line as i32);
+
+        // structs are stored inline in the object, use their address
+        if field.ty.is_struct_or_tuple() {
+            self.asm.test_if_nil_bailout(pos, src, Trap::NIL);
+            self.asm.lea(dest.reg(), Mem::Base(src, field.offset));
+        } else {
+            self.asm
+                .load_field(field.ty.mode(), dest, src, field.offset, pos.line as i32);
+        }
+    }
+
+    fn emit_store_field(
+        &mut self,
+        pos: Position,
+        ty: BuiltinType,
+        base: Reg,
+        offset: i32,
+        src: ExprStore,
+    ) {
+        let card_table_offset = self.vm.gc.card_table_offset();
+
+        if ty.is_struct_or_tuple() {
+            let write_barrier =
+                self.vm.gc.needs_write_barrier() && !struct_ref_fields(self.vm, ty).is_empty();
+
+            self.asm.test_if_nil_bailout(pos, base, Trap::NIL);
+            self.asm
+                .copy_struct(ty, Mem::Base(base, offset), Mem::Base(src.reg(), 0));
+
+            if write_barrier {
+                self.asm.emit_barrier(base, card_table_offset);
+            }
+        } else {
+            let write_barrier = self.vm.gc.needs_write_barrier() && ty.reference_type();
+
+            self.asm.store_field(
+                ty.mode(),
+                base,
+                offset,
+                src,
+                pos.line as i32,
+                write_barrier,
+                card_table_offset,
+            );
+        }
     }
 
     fn emit_lit_char(&mut self, lit: &'ast ExprLitCharType, dest: Reg) {
@@ -513,13 +1057,141 @@ where
         self.asm.load_constpool(dest, disp + pos);
     }
 
-    fn emit_lit_struct(&mut self, _: &'ast ExprLitStructType, _: ExprStore) {
-        unimplemented!();
+    fn emit_lit_struct(&mut self, e: &'ast ExprLitStructType, dest: ExprStore) {
+        let ty = self.ty(e.id);
+        let struct_id = specialize_struct_ty(self.vm, ty);
+        let fields = {
+            let struc = self.vm.struct_defs.idx(struct_id);
+            let struc = struc.lock();
+            struc.fields.clone()
+        };
+
+        let names = {
+            let sid = self.src.map_idents.get(e.id).unwrap().struct_id();
+            let struc = self.vm.structs.idx(sid);
+            let struc = struc.lock();
+            struc.fields.iter().map(|f| f.name).collect::<Vec<_>>()
+        };
+
+        let offset = -(self.jit_info.localsize + self.jit_info.get_store(e.id).offset());
+        let ref_fields = struct_ref_fields(self.vm, ty);
+
+        // references need to be initialized before they become part of a gc point
+        if !ref_fields.is_empty() {
+            self.asm.load_nil(REG_RESULT);
+
+            for &ref_offset in &ref_fields {
+                self.asm.store_mem(
+                    MachineMode::Ptr,
+                    Mem::Local(offset + ref_offset),
+                    REG_RESULT.into(),
+                );
+            }
+        }
+
+        self.insert_temp_with_type(ty, offset);
+
+        for arg in &e.args {
+            let idx = names.iter().position(|&name| name == arg.name).unwrap();
+            let field = &fields[idx];
+
+            if field.ty.is_unit() {
+                self.emit_expr(&arg.expr, REG_RESULT.into());
+                continue;
+            }
+
+            let reg = result_reg(field.ty.mode());
+            self.emit_expr(&arg.expr, reg);
+
+            if field.ty.is_struct_or_tuple() {
+                self.asm.copy_struct(
+                    field.ty,
+                    Mem::Local(offset + field.offset),
+                    Mem::Base(reg.reg(), 0),
+                );
+            } else {
+                self.asm
+                    .store_mem(field.ty.mode(), Mem::Local(offset + field.offset), reg);
+            }
+        }
+
+        self.free_temp_with_type(ty, offset);
+        self.asm.lea(dest.reg(), Mem::Local(offset));
+    }
+
+    fn emit_tuple(&mut self, e: &'ast ExprTupleType, dest: ExprStore) {
+        let ty = self.ty(e.id);
+        let tuple_id = specialize_struct_ty(self.vm, ty);
+        let fields = {
+            let tuple = self.vm.struct_defs.idx(tuple_id);
+            let tuple = tuple.lock();
+            tuple.fields.clone()
+        };
+
+        let offset = -(self.jit_info.localsize + self.jit_info.get_store(e.id).offset());
+        let ref_fields = struct_ref_fields(self.vm, ty);
+
+        // references need to be initialized before they become part of a gc point
+        if !ref_fields.is_empty() {
+            self.asm.load_nil(REG_RESULT);
+
+            for &ref_offset in &ref_fields {
+                self.asm.store_mem(
+                    MachineMode::Ptr,
+                    Mem::Local(offset + ref_offset),
+                    REG_RESULT.into(),
+                );
+            }
+        }
+
+        self.insert_temp_with_type(ty, offset);
+
+        for (element, field) in e.elements.iter().zip(&fields) {
+            if field.ty.is_unit() {
+                self.emit_expr(element, REG_RESULT.into());
+                continue;
+            }
+
+            let reg = result_reg(field.ty.mode());
+            self.emit_expr(element, reg);
+
+            if field.ty.is_struct_or_tuple() {
+                self.asm.copy_struct(
+                    field.ty,
+                    Mem::Local(offset + field.offset),
+                    Mem::Base(reg.reg(), 0),
+                );
+            } else {
+                self.asm
+                    .store_mem(field.ty.mode(), Mem::Local(offset + field.offset), reg);
+            }
+        }
+
+        self.free_temp_with_type(ty, offset);
+        self.asm.lea(dest.reg(), Mem::Local(offset));
+    }
+
+    fn emit_tuple_element(&mut self, e: &'ast ExprTupleElementType, dest: ExprStore) {
+        let ty = self.ty(e.object.id());
+
+        self.emit_expr(&e.object, REG_RESULT.into());
+        self.emit_struct_field_access(ty, (e.index as u32).into(), REG_RESULT, dest);
     }
 
     fn emit_ident(&mut self, e: &'ast ExprIdentType, dest: ExprStore) {
         let &ident = self.src.map_idents.get(e.id).unwrap();
 
+        // struct without any fields, nothing to initialize
+        if let IdentType::Struct(_) = ident {
+            let offset = -(self.jit_info.localsize + self.jit_info.get_store(e.id).offset());
+            self.asm.lea(dest.reg(), Mem::Local(offset));
+            return;
+        }
+
+        self.emit_ident_type(e.pos, ident, dest);
+    }
+
+    fn emit_ident_type(&mut self, pos: Position, ident: IdentType, dest: ExprStore) {
         match ident {
             IdentType::Var(varid) => {
                 self.asm.emit_comment(Comment::LoadVar(varid));
@@ -536,17 +1208,23 @@ where
                 self.asm.emit_comment(Comment::LoadGlobal(gid));
                 self.asm.load_constpool(REG_TMP1, disp + pos);
 
-                self.asm
-                    .load_mem(glob.ty.mode(), dest, Mem::Base(REG_TMP1, 0));
+                if glob.ty.is_struct_or_tuple() {
+                    self.asm.copy_reg(MachineMode::Ptr, dest.reg(), REG_TMP1);
+                } else {
+                    self.asm
+                        .load_mem(glob.ty.mode(), dest, Mem::Base(REG_TMP1, 0));
+                }
             }
 
-            IdentType::Field(cls, field) => {
+            IdentType::Field(ty, field) => {
+                let ty = self.specialize_type(ty);
+
                 self.emit_self(REG_RESULT);
-                self.emit_field_access(e.pos, cls, field, REG_RESULT, dest);
+                self.emit_field_access(pos, ty, field, REG_RESULT, dest);
             }
 
-            IdentType::Struct(_) => {
-                unimplemented!();
+            IdentType::Struct(_) | IdentType::StructField(_, _) | IdentType::EnumValue(_, _) => {
+                unreachable!();
             }
 
             IdentType::Const(const_id) => {
@@ -655,6 +1333,11 @@ where
     }
 
     fn emit_assign(&mut self, e: &'ast ExprAssignType) {
+        if let Some(op) = e.op {
+            self.emit_assign_op(e, op);
+            return;
+        }
+
         if e.lhs.is_array() {
             let array = e.lhs.to_array().unwrap();
 
@@ -662,7 +1345,21 @@ where
                 match intrinsic {
                     Intrinsic::GenericArraySet => {
                         let ty = self.ty(array.id);
+
+                        if ty.is_struct_or_tuple() {
+                            self.emit_array_set_struct(
+                                array.id,
+                                e.pos,
+                                ty,
+                                &array.object,
+                                &array.index,
+                                &e.rhs,
+                            );
+                            return;
+                        }
+
                         self.emit_array_set(
+                            array.id,
                             e.pos,
                             ty,
                             ty.mode(),
@@ -673,6 +1370,7 @@ where
                     }
 
                     Intrinsic::StrSet => self.emit_array_set(
+                        array.id,
                         e.pos,
                         BuiltinType::Byte,
                         MachineMode::Int8,
@@ -718,7 +1416,12 @@ where
                 self.asm.emit_comment(Comment::StoreGlobal(gid));
                 self.asm.load_constpool(REG_TMP1, disp + pos);
 
-                self.asm.store_mem(ty.mode(), Mem::Base(REG_TMP1, 0), dest);
+                if ty.is_struct_or_tuple() {
+                    self.asm
+                        .copy_struct(ty, Mem::Base(REG_TMP1, 0), Mem::Base(dest.reg(), 0));
+                } else {
+                    self.asm.store_mem(ty.mode(), Mem::Base(REG_TMP1, 0), dest);
+                }
             }
 
             IdentType::Field(ty, fieldid) => {
@@ -749,28 +1452,358 @@ where
 
                 self.asm.emit_comment(Comment::StoreField(cls_id, fieldid));
 
-                let write_barrier = self.vm.gc.needs_write_barrier() && field.ty.reference_type();
-                let card_table_offset = self.vm.gc.card_table_offset();
-
-                self.asm.store_field(
-                    field.ty.mode(),
-                    REG_TMP1,
-                    field.offset,
-                    reg,
-                    e.pos.line as i32,
-                    write_barrier,
-                    card_table_offset,
-                );
+                self.emit_store_field(e.pos, field.ty, REG_TMP1, field.offset, reg);
                 self.free_temp_for_node(temp, temp_offset);
             }
 
-            IdentType::Struct(_) => {
-                unimplemented!();
+            IdentType::StructField(ty, fieldid) => {
+                let ty = self.specialize_type(ty);
+                let struct_id = specialize_struct_ty(self.vm, ty);
+                let field = {
+                    let struc = self.vm.struct_defs.idx(struct_id);
+                    let struc = struc.lock();
+                    struc.fields[fieldid.idx()].clone()
+                };
+
+                // evaluate value first, determining the address of the
+                // struct must not be interrupted by the GC
+                let reg = result_reg(field.ty.mode());
+                self.emit_expr(&e.rhs, reg);
+
+                let value_offset = self.reserve_temp_for_node(&e.rhs);
+
+                if field.ty.is_struct_or_tuple() {
+                    self.asm.copy_struct(
+                        field.ty,
+                        Mem::Local(value_offset),
+                        Mem::Base(reg.reg(), 0),
+                    );
+                } else {
+                    self.asm
+                        .store_mem(field.ty.mode(), Mem::Local(value_offset), reg);
+                }
+
+                let expr_field = e.lhs.to_field().unwrap();
+                self.emit_expr(&expr_field.object, REG_RESULT.into());
+
+                if field.ty.is_struct_or_tuple() {
+                    self.asm.copy_struct(
+                        field.ty,
+                        Mem::Base(REG_RESULT, field.offset),
+                        Mem::Local(value_offset),
+                    );
+                } else {
+                    let tmp = if field.ty.is_float() {
+                        FREG_TMP1.into()
+                    } else {
+                        REG_TMP1.into()
+                    };
+
+                    self.asm
+                        .load_mem(field.ty.mode(), tmp, Mem::Local(value_offset));
+                    self.asm
+                        .store_mem(field.ty.mode(), Mem::Base(REG_RESULT, field.offset), tmp);
+                }
+
+                let has_refs = field.ty.reference_type()
+                    || (field.ty.is_struct_or_tuple()
+                        && !struct_ref_fields(self.vm, field.ty).is_empty());
+
+                if self.vm.gc.needs_write_barrier()
+                    && has_refs
+                    && self.is_heap_struct(&expr_field.object)
+                {
+                    let card_table_offset = self.vm.gc.card_table_offset();
+                    self.asm
+                        .lea(REG_RESULT, Mem::Base(REG_RESULT, field.offset));
+                    self.asm.emit_barrier(REG_RESULT, card_table_offset);
+                }
+
+                self.free_temp_for_node(&e.rhs, value_offset);
+            }
+
+            IdentType::Struct(_) | IdentType::EnumValue(_, _) => {
+                unreachable!();
             }
 
-            IdentType::Const(_) => {
-                unreachable!();
+            IdentType::Const(_) => {
+                unreachable!();
+            }
+        }
+    }
+
+    // compound assignments keep the receiver and the index of the lhs
+    // in stack slots, such that both are only evaluated once
+    fn emit_assign_op(&mut self, e: &'ast ExprAssignType, op: AssignOp) {
+        let ty = self.ty(e.lhs.id());
+        let offset = *self.jit_info.map_offsets.get(e.id).unwrap();
+        let reg = result_reg(ty.mode());
+
+        if let Some(array) = e.lhs.to_array() {
+            self.emit_assign_op_array(e, op, array, ty, offset);
+            return;
+        }
+
+        let &ident_type = self.src.map_idents.get(e.lhs.id()).unwrap();
+
+        match ident_type {
+            IdentType::Var(varid) => {
+                self.emit_expr(&e.lhs, reg);
+                self.emit_assign_op_value(e, op, ty, offset, reg);
+
+                self.asm.emit_comment(Comment::StoreVar(varid));
+                self.asm.var_store(self.jit_info, reg, varid);
+            }
+
+            IdentType::Global(gid) => {
+                let address_value = self.vm.globals.idx(gid).lock().address_value;
+
+                self.emit_expr(&e.lhs, reg);
+                self.emit_assign_op_value(e, op, ty, offset, reg);
+
+                let disp = self.asm.add_addr(address_value.to_ptr());
+                let pos = self.asm.pos() as i32;
+
+                self.asm.emit_comment(Comment::StoreGlobal(gid));
+                self.asm.load_constpool(REG_TMP1, disp + pos);
+                self.asm.store_mem(ty.mode(), Mem::Base(REG_TMP1, 0), reg);
+            }
+
+            IdentType::Field(cls_ty, fieldid) => {
+                let cls_ty = self.specialize_type(cls_ty);
+                let cls_id = specialize_class_ty(self.vm, cls_ty);
+                let (field_ty, field_offset) = {
+                    let cls = self.vm.class_defs.idx(cls_id);
+                    let cls = cls.read();
+                    let field = &cls.fields[fieldid.idx()];
+
+                    (field.ty, field.offset)
+                };
+
+                let object_offset = *self.jit_info.map_offsets.get(e.lhs.id()).unwrap();
+
+                if let Some(expr_field) = e.lhs.to_field() {
+                    self.emit_expr(&expr_field.object, REG_RESULT.into());
+                } else {
+                    self.emit_self(REG_RESULT);
+                }
+
+                self.asm.store_mem(
+                    MachineMode::Ptr,
+                    Mem::Local(object_offset),
+                    REG_RESULT.into(),
+                );
+                self.temps.insert(object_offset);
+
+                self.emit_field_access(e.pos, cls_ty, fieldid, REG_RESULT, reg);
+                self.emit_assign_op_value(e, op, ty, offset, reg);
+
+                self.asm
+                    .load_mem(MachineMode::Ptr, REG_TMP1.into(), Mem::Local(object_offset));
+
+                self.asm.emit_comment(Comment::StoreField(cls_id, fieldid));
+                self.emit_store_field(e.pos, field_ty, REG_TMP1, field_offset, reg);
+                self.temps.remove(object_offset);
+            }
+
+            IdentType::StructField(struct_ty, fieldid) => {
+                let struct_ty = self.specialize_type(struct_ty);
+                let struct_id = specialize_struct_ty(self.vm, struct_ty);
+                let field = {
+                    let struc = self.vm.struct_defs.idx(struct_id);
+                    let struc = struc.lock();
+                    struc.fields[fieldid.idx()].clone()
+                };
+
+                self.emit_expr(&e.lhs, reg);
+                self.emit_assign_op_value(e, op, ty, offset, reg);
+
+                // the address of the struct can't be kept while the operator is
+                // applied: it might move during GC, so it is determined again
+                let expr_field = e.lhs.to_field().unwrap();
+                self.emit_expr(&expr_field.object, REG_RESULT.into());
+
+                let tmp = if field.ty.is_float() {
+                    FREG_TMP1.into()
+                } else {
+                    REG_TMP1.into()
+                };
+
+                self.asm.load_mem(field.ty.mode(), tmp, Mem::Local(offset));
+                self.asm
+                    .store_mem(field.ty.mode(), Mem::Base(REG_RESULT, field.offset), tmp);
+
+                if self.vm.gc.needs_write_barrier()
+                    && field.ty.reference_type()
+                    && self.is_heap_struct(&expr_field.object)
+                {
+                    let card_table_offset = self.vm.gc.card_table_offset();
+                    self.asm
+                        .lea(REG_RESULT, Mem::Base(REG_RESULT, field.offset));
+                    self.asm.emit_barrier(REG_RESULT, card_table_offset);
+                }
+            }
+
+            IdentType::Struct(_) | IdentType::EnumValue(_, _) | IdentType::Const(_) => {
+                unreachable!();
+            }
+        }
+    }
+
+    fn emit_assign_op_array(
+        &mut self,
+        e: &'ast ExprAssignType,
+        op: AssignOp,
+        array: &'ast ExprArrayType,
+        ty: BuiltinType,
+        offset: i32,
+    ) {
+        let object_offset = *self.jit_info.map_offsets.get(array.object.id()).unwrap();
+        let index_offset = *self.jit_info.map_offsets.get(array.index.id()).unwrap();
+        let index_type = self.ty(array.index.id());
+        let reg = result_reg(ty.mode());
+
+        self.emit_expr(&array.object, REG_RESULT.into());
+        self.asm.store_mem(
+            MachineMode::Ptr,
+            Mem::Local(object_offset),
+            REG_RESULT.into(),
+        );
+        self.temps.insert(object_offset);
+
+        let index_reg = result_reg(index_type.mode());
+        self.emit_expr(&array.index, index_reg);
+        self.asm
+            .store_mem(index_type.mode(), Mem::Local(index_offset), index_reg);
+
+        if index_type.reference_type() {
+            self.temps.insert(index_offset);
+        }
+
+        if let Some(intrinsic) = self.intrinsic(array.id) {
+            let mode = match intrinsic {
+                Intrinsic::GenericArrayGet => ty.mode(),
+                Intrinsic::StrGet => MachineMode::Int8,
+                _ => panic!("unexpected intrinsic {:?}", intrinsic),
+            };
+
+            self.asm.load_mem(
+                MachineMode::Ptr,
+                REG_RESULT.into(),
+                Mem::Local(object_offset),
+            );
+            self.asm.load_mem(
+                MachineMode::Int32,
+                REG_TMP1.into(),
+                Mem::Local(index_offset),
+            );
+
+            self.asm.test_if_nil_bailout(e.pos, REG_RESULT, Trap::NIL);
+
+            if self.needs_bounds_check(array.id) {
+                self.asm
+                    .check_index_out_of_bounds(e.pos, REG_RESULT, REG_TMP1);
+            }
+
+            self.asm.load_array_elem(mode, reg, REG_RESULT, REG_TMP1);
+        } else {
+            self.emit_call_site_id(array.id, e.pos, reg);
+        }
+
+        self.emit_assign_op_value(e, op, ty, offset, reg);
+
+        if let Some(intrinsic) = self.intrinsic(e.id) {
+            let mode = match intrinsic {
+                Intrinsic::GenericArraySet => ty.mode(),
+                Intrinsic::StrSet => MachineMode::Int8,
+                _ => panic!("unexpected intrinsic {:?}", intrinsic),
+            };
+
+            // nil and bounds were already checked when loading the element
+            self.asm
+                .load_mem(MachineMode::Ptr, REG_TMP1.into(), Mem::Local(object_offset));
+            self.asm.load_mem(
+                MachineMode::Int32,
+                REG_TMP2.into(),
+                Mem::Local(index_offset),
+            );
+
+            let write_barrier = self.vm.gc.needs_write_barrier() && ty.reference_type();
+            let card_table_offset = self.vm.gc.card_table_offset();
+
+            self.asm.store_array_elem(
+                mode,
+                REG_TMP1,
+                REG_TMP2,
+                reg,
+                write_barrier,
+                card_table_offset,
+            );
+        } else {
+            self.emit_call_site_id(e.id, e.pos, REG_RESULT.into());
+        }
+
+        self.temps.remove(object_offset);
+
+        if index_type.reference_type() {
+            self.temps.remove(index_offset);
+        }
+    }
+
+    // applies the operator to the current value of the lhs in `reg`, the
+    // result is returned in `reg` and also stored in the slot at `offset`
+    fn emit_assign_op_value(
+        &mut self,
+        e: &'ast ExprAssignType,
+        op: AssignOp,
+        ty: BuiltinType,
+        offset: i32,
+        reg: ExprStore,
+    ) {
+        let mode = ty.mode();
+        self.asm.store_mem(mode, Mem::Local(offset), reg);
+
+        if ty.reference_type() {
+            self.temps.insert(offset);
+        }
+
+        if let Some(intrinsic) = self.intrinsic(op.id) {
+            let rhs_reg = if mode.is_float() {
+                FREG_TMP1.into()
+            } else {
+                REG_TMP1.into()
+            };
+
+            self.emit_expr(&e.rhs, rhs_reg);
+            self.asm.load_mem(mode, reg, Mem::Local(offset));
+
+            if mode.is_float() {
+                let lhs_reg = reg.freg();
+                self.emit_intrinsic_float(reg, lhs_reg, rhs_reg.freg(), intrinsic, Some(op.op));
+            } else {
+                let lhs_reg = reg.reg();
+                self.emit_intrinsic_int(lhs_reg, lhs_reg, rhs_reg.reg(), intrinsic, Some(op.op));
+            }
+        } else {
+            self.emit_call_site_id(op.id, e.pos, reg);
+        }
+
+        self.asm.store_mem(mode, Mem::Local(offset), reg);
+
+        if ty.reference_type() {
+            self.temps.remove(offset);
+        }
+    }
+
+    // is the struct stored inline in an object on the heap?
+    fn is_heap_struct(&self, object: &'ast Expr) -> bool {
+        match self.src.map_idents.get(object.id()) {
+            Some(&IdentType::Field(_, _)) => true,
+            Some(&IdentType::StructField(_, _)) => {
+                let field = object.to_field().unwrap();
+                self.is_heap_struct(&field.object)
             }
+            _ => false,
         }
     }
 
@@ -796,6 +1829,8 @@ where
 
             self.asm.set(dest.reg(), op);
             self.free_temp_for_node(&e.lhs, offset);
+        } else if self.ty(e.lhs.id()).is_enum() {
+            self.emit_bin_enum(e, dest.reg());
         } else if e.op == BinOp::Or {
             self.emit_bin_or(e, dest.reg());
         } else if e.op == BinOp::And {
@@ -828,6 +1863,79 @@ where
         }
     }
 
+    fn emit_bin_enum(&mut self, e: &'ast ExprBinType, dest: Reg) {
+        self.emit_expr(&e.lhs, REG_RESULT.into());
+        let offset = self.reserve_temp_for_node(&e.lhs);
+        self.asm
+            .store_mem(MachineMode::Ptr, Mem::Local(offset), REG_RESULT.into());
+
+        let has_payload = match self.ty(e.lhs.id()) {
+            BuiltinType::Enum(enum_id) => self.vm.enums.idx(enum_id).lock().has_payload(),
+            _ => unreachable!(),
+        };
+
+        // payloads are compared by the runtime
+        if has_payload {
+            self.emit_expr(&e.rhs, REG_TMP1.into());
+            self.asm.copy_reg(MachineMode::Ptr, REG_PARAMS[1], REG_TMP1);
+            self.asm
+                .load_mem(MachineMode::Ptr, REG_PARAMS[0].into(), Mem::Local(offset));
+
+            let internal_fct = InternalFct {
+                ptr: Address::from_ptr(stdlib::enum_equals as *const u8),
+                args: &[BuiltinType::Ptr, BuiltinType::Ptr],
+                return_type: BuiltinType::Bool,
+                throws: false,
+                desc: InternalFctDescriptor::EnumEqualsThunk,
+            };
+
+            let gcpoint = self.create_gcpoint();
+            self.asm
+                .native_call(internal_fct, e.pos, gcpoint, dest.into());
+
+            // only the lowest byte of a bool returned by native code is defined
+            self.asm.extend_byte(MachineMode::Int32, dest, dest);
+
+            if e.op == BinOp::Cmp(CmpOp::Ne) {
+                self.asm.bool_not(dest, dest);
+            }
+
+            self.free_temp_for_node(&e.lhs, offset);
+            return;
+        }
+
+        self.emit_expr(&e.rhs, REG_TMP1.into());
+        self.asm
+            .load_mem(MachineMode::Ptr, REG_RESULT.into(), Mem::Local(offset));
+
+        // the tag is the first field in the object of every variant
+        let tag_offset = Header::size();
+        let line = e.pos.line as i32;
+        self.asm.load_field(
+            MachineMode::Int32,
+            REG_RESULT.into(),
+            REG_RESULT,
+            tag_offset,
+            line,
+        );
+        self.asm.load_field(
+            MachineMode::Int32,
+            REG_TMP1.into(),
+            REG_TMP1,
+            tag_offset,
+            line,
+        );
+        self.asm.cmp_reg(MachineMode::Int32, REG_RESULT, REG_TMP1);
+
+        let op = match e.op {
+            BinOp::Cmp(CmpOp::Eq) => CondCode::Equal,
+            _ => CondCode::NotEqual,
+        };
+
+        self.asm.set(dest, op);
+        self.free_temp_for_node(&e.lhs, offset);
+    }
+
     fn emit_bin_or(&mut self, e: &'ast ExprBinType, dest: Reg) {
         let lbl_true = self.asm.create_label();
         let lbl_false = self.asm.create_label();
@@ -880,40 +1988,31 @@ where
         cls_type_params: TypeParams,
         fct_type_params: TypeParams,
     ) -> Address {
-        if self.fct.id == fid {
-            // we want to recursively invoke the function we are compiling right now
-            ensure_jit_or_stub_ptr(self.src, self.vm, cls_type_params, fct_type_params)
-        } else {
-            let fct = self.vm.fcts.idx(fid);
-            let fct = fct.read();
-
-            match fct.kind {
-                FctKind::Source(_) => {
-                    let src = fct.src();
-                    let mut src = src.write();
-
-                    ensure_jit_or_stub_ptr(&mut src, self.vm, cls_type_params, fct_type_params)
-                }
-
-                FctKind::Native(ptr) => {
-                    let internal_fct = InternalFct {
-                        ptr: ptr,
-                        args: fct.params_with_self(),
-                        return_type: fct.return_type,
-                        throws: fct.ast.throws,
-                        desc: InternalFctDescriptor::NativeThunk(fid),
-                    };
+        ptr_for_fct_id(
+            self.vm,
+            self.fct,
+            self.src,
+            fid,
+            cls_type_params,
+            fct_type_params,
+        )
+    }
 
-                    ensure_native_stub(self.vm, fid, internal_fct)
-                }
+    fn emit_call(&mut self, e: &'ast ExprCallType, dest: ExprStore) {
+        match **self.src.map_calls.get(e.id).unwrap() {
+            CallType::Lambda(lambda_id) => {
+                self.emit_call_lambda(e, lambda_id, dest);
+                return;
+            }
 
-                FctKind::Definition => panic!("prototype for fct call"),
-                FctKind::Builtin(_) => panic!("intrinsic fct call"),
+            CallType::EnumValue(enum_id, idx) => {
+                self.emit_enum_value(e.pos, e.id, enum_id, idx, &e.args, dest.reg());
+                return;
             }
+
+            _ => {}
         }
-    }
 
-    fn emit_call(&mut self, e: &'ast ExprCallType, dest: ExprStore) {
         if let Some(intrinsic) = self.intrinsic(e.id) {
             match intrinsic {
                 Intrinsic::GenericArrayLen => self.emit_intrinsic_len(e, dest.reg()),
@@ -923,6 +2022,7 @@ where
                 Intrinsic::SetUint8 => self.emit_set_uint8(e, dest.reg()),
                 Intrinsic::StrLen => self.emit_intrinsic_len(e, dest.reg()),
                 Intrinsic::StrGet => self.emit_array_get(
+                    e.id,
                     e.pos,
                     MachineMode::Int8,
                     e.object.as_ref().unwrap(),
@@ -1041,6 +2141,95 @@ where
         }
     }
 
+    fn emit_call_lambda(&mut self, e: &'ast ExprCallType, lambda_id: LambdaId, dest: ExprStore) {
+        let lambda_id = info::specialize_lambda(self.vm, lambda_id, |t| self.specialize_type(t));
+        let lambda = self.vm.lambda_types.lock().get(lambda_id);
+        let &ident = self.src.map_idents.get(e.id).unwrap();
+
+        // the closure object is passed as first argument
+        let closure_offset = *self.jit_info.map_offsets.get(e.id).unwrap();
+        self.emit_ident_type(e.pos, ident, REG_RESULT.into());
+        self.asm.store_mem(
+            MachineMode::Ptr,
+            Mem::Local(closure_offset),
+            REG_RESULT.into(),
+        );
+        self.temps.insert(closure_offset);
+
+        let mut temps = Vec::with_capacity(e.args.len());
+
+        for (arg, &ty) in e.args.iter().zip(lambda.params()) {
+            let reg = register_for_mode(ty.mode());
+            self.emit_expr(arg, reg);
+
+            let offset = *self.jit_info.map_offsets.get(arg.id()).unwrap();
+            self.asm.store_mem(ty.mode(), Mem::Local(offset), reg);
+
+            if ty.reference_type() {
+                self.temps.insert(offset);
+            }
+
+            temps.push((ty, offset));
+        }
+
+        self.asm.load_mem(
+            MachineMode::Ptr,
+            REG_PARAMS[0].into(),
+            Mem::Local(closure_offset),
+        );
+
+        let mut arg_offset = -self.jit_info.stacksize();
+        let mut reg_idx = 1;
+        let mut freg_idx = 0;
+
+        for &(ty, offset) in &temps {
+            let mode = ty.mode();
+
+            if mode.is_float() {
+                if freg_idx < FREG_PARAMS.len() {
+                    let freg = FREG_PARAMS[freg_idx];
+                    self.asm.load_mem(mode, freg.into(), Mem::Local(offset));
+
+                    freg_idx += 1;
+                } else {
+                    // FREG_TMP1 might already hold an argument, copy the
+                    // bits through an integer register instead
+                    let mode = float_bits_mode(mode);
+                    self.asm.load_mem(mode, REG_TMP1.into(), Mem::Local(offset));
+                    self.asm
+                        .store_mem(mode, Mem::Local(arg_offset), REG_TMP1.into());
+
+                    arg_offset += 8;
+                }
+            } else {
+                if reg_idx < REG_PARAMS.len() {
+                    let reg = REG_PARAMS[reg_idx];
+                    self.asm.load_mem(mode, reg.into(), Mem::Local(offset));
+
+                    reg_idx += 1;
+                } else {
+                    self.asm.load_mem(mode, REG_TMP1.into(), Mem::Local(offset));
+                    self.asm
+                        .store_mem(mode, Mem::Local(arg_offset), REG_TMP1.into());
+
+                    arg_offset += 8;
+                }
+            }
+        }
+
+        // the lambda body is the only method in the vtable of the closure
+        self.asm.emit_comment(Comment::Lit("call lambda"));
+        let gcpoint = self.create_gcpoint();
+        self.asm
+            .indirect_call(0, e.pos, gcpoint, lambda.ret(), dest);
+
+        self.temps.remove(closure_offset);
+
+        for (ty, offset) in temps {
+            self.free_temp_with_type(ty, offset);
+        }
+    }
+
     fn emit_intrinsic_default_value(&mut self, e: &'ast ExprCallType, dest: ExprStore) {
         let ty = self.ty(e.id);
 
@@ -1071,6 +2260,7 @@ where
 
     fn emit_array_set(
         &mut self,
+        id: NodeId,
         pos: Position,
         element_type: BuiltinType,
         mode: MachineMode,
@@ -1110,7 +2300,7 @@ where
 
         self.asm.test_if_nil_bailout(pos, REG_TMP1, Trap::NIL);
 
-        if !self.vm.args.flag_omit_bounds_check {
+        if self.needs_bounds_check(id) {
             self.asm.check_index_out_of_bounds(pos, REG_TMP1, REG_TMP2);
         }
 
@@ -1135,6 +2325,7 @@ where
 
     fn emit_array_get(
         &mut self,
+        id: NodeId,
         pos: Position,
         mode: MachineMode,
         object: &'ast Expr,
@@ -1152,7 +2343,7 @@ where
 
         self.asm.test_if_nil_bailout(pos, REG_RESULT, Trap::NIL);
 
-        if !self.vm.args.flag_omit_bounds_check {
+        if self.needs_bounds_check(id) {
             self.asm
                 .check_index_out_of_bounds(pos, REG_RESULT, REG_TMP1);
         }
@@ -1168,6 +2359,128 @@ where
         }
     }
 
+    fn emit_array_set_struct(
+        &mut self,
+        id: NodeId,
+        pos: Position,
+        element_type: BuiltinType,
+        object: &'ast Expr,
+        index: &'ast Expr,
+        rhs: &'ast Expr,
+    ) {
+        self.emit_expr(object, REG_RESULT.into());
+        let offset_object = self.reserve_temp_for_node(object);
+        self.asm.store_mem(
+            MachineMode::Ptr,
+            Mem::Local(offset_object),
+            REG_RESULT.into(),
+        );
+
+        self.emit_expr(index, REG_RESULT.into());
+        let offset_index = self.reserve_temp_for_node(index);
+        self.asm.store_mem(
+            MachineMode::Int32,
+            Mem::Local(offset_index),
+            REG_RESULT.into(),
+        );
+
+        self.emit_expr(rhs, REG_RESULT.into());
+        let offset_value = self.reserve_temp_for_node(rhs);
+        self.asm.copy_struct(
+            element_type,
+            Mem::Local(offset_value),
+            Mem::Base(REG_RESULT, 0),
+        );
+
+        self.asm
+            .load_mem(MachineMode::Ptr, REG_TMP1.into(), Mem::Local(offset_object));
+        self.asm.load_mem(
+            MachineMode::Int32,
+            REG_TMP2.into(),
+            Mem::Local(offset_index),
+        );
+
+        self.asm.test_if_nil_bailout(pos, REG_TMP1, Trap::NIL);
+
+        if self.needs_bounds_check(id) {
+            self.asm.check_index_out_of_bounds(pos, REG_TMP1, REG_TMP2);
+        }
+
+        self.emit_array_elem_address(element_type, REG_TMP1, REG_TMP2, REG_RESULT, REG_TMP1);
+        self.asm.copy_struct(
+            element_type,
+            Mem::Base(REG_TMP1, 0),
+            Mem::Local(offset_value),
+        );
+
+        let write_barrier = self.vm.gc.needs_write_barrier()
+            && !struct_ref_fields(self.vm, element_type).is_empty();
+
+        // the GC visits all elements of an array whose header card is dirty
+        if write_barrier {
+            let card_table_offset = self.vm.gc.card_table_offset();
+            self.asm
+                .load_mem(MachineMode::Ptr, REG_TMP1.into(), Mem::Local(offset_object));
+            self.asm.emit_barrier(REG_TMP1, card_table_offset);
+        }
+
+        self.free_temp_for_node(object, offset_object);
+        self.free_temp_for_node(index, offset_index);
+        self.free_temp_for_node(rhs, offset_value);
+    }
+
+    fn emit_array_get_struct(
+        &mut self,
+        id: NodeId,
+        pos: Position,
+        element_type: BuiltinType,
+        object: &'ast Expr,
+        index: &'ast Expr,
+        dest: ExprStore,
+    ) {
+        self.emit_expr(object, REG_RESULT.into());
+        let offset = self.reserve_temp_for_node(object);
+        self.asm
+            .store_mem(MachineMode::Ptr, Mem::Local(offset), REG_RESULT.into());
+
+        self.emit_expr(index, REG_TMP1.into());
+        self.asm
+            .load_mem(MachineMode::Ptr, REG_RESULT.into(), Mem::Local(offset));
+
+        self.asm.test_if_nil_bailout(pos, REG_RESULT, Trap::NIL);
+
+        if self.needs_bounds_check(id) {
+            self.asm
+                .check_index_out_of_bounds(pos, REG_RESULT, REG_TMP1);
+        }
+
+        self.emit_array_elem_address(element_type, REG_RESULT, REG_TMP1, REG_TMP2, dest.reg());
+        self.free_temp_for_node(object, offset);
+    }
+
+    fn needs_bounds_check(&self, id: NodeId) -> bool {
+        !self.vm.args.flag_omit_bounds_check && !self.jit_info.in_bounds.contains(&id)
+    }
+
+    // computes address of array element, clobbers `index` and `tmp`
+    fn emit_array_elem_address(
+        &mut self,
+        element_type: BuiltinType,
+        array: Reg,
+        index: Reg,
+        tmp: Reg,
+        dest: Reg,
+    ) {
+        let size = element_type.size(self.vm);
+
+        self.asm.extend_int_long(index, index);
+        self.asm
+            .load_int_const(MachineMode::Int64, tmp, size as i64);
+        self.asm.int_mul(MachineMode::Int64, index, index, tmp);
+        self.asm.int_add(MachineMode::Ptr, index, index, array);
+        self.asm.lea(dest, Mem::Base(index, offset_of_array_data()));
+    }
+
     fn emit_set_uint8(&mut self, e: &'ast ExprCallType, _: Reg) {
         self.emit_expr(&e.args[0], REG_RESULT.into());
         let offset = self.reserve_temp_for_node(&e.args[0]);
@@ -1501,10 +2814,16 @@ where
 
     fn emit_call_site_id(&mut self, id: NodeId, pos: Position, dest: ExprStore) {
         let csite = self.jit_info.map_csites.get(id).unwrap().clone();
-        self.emit_call_site(&csite, pos, dest);
+        self.emit_call_site(&csite, Some(id), pos, dest);
     }
 
-    pub fn emit_call_site(&mut self, csite: &CallSite<'ast>, pos: Position, dest: ExprStore) {
+    pub fn emit_call_site(
+        &mut self,
+        csite: &CallSite<'ast>,
+        id: Option<NodeId>,
+        pos: Position,
+        dest: ExprStore,
+    ) {
         let mut temps: Vec<(BuiltinType, i32, Option<ClassDefId>)> = Vec::new();
 
         let fid = csite.callee;
@@ -1536,6 +2855,10 @@ where
 
                 Arg::Stack(soffset, ty, _) => {
                     self.asm.load_mem(ty.mode(), dest, Mem::Local(soffset));
+
+                    if idx == 0 && fct.has_self() && check_for_nil(ty) && !fct.is_virtual() {
+                        self.asm.test_if_nil_bailout(pos, dest.reg(), Trap::NIL);
+                    }
                 }
 
                 Arg::Selfie(_, _) => {
@@ -1555,8 +2878,16 @@ where
             }
 
             let offset = self.reserve_temp_for_arg(arg);
-            self.asm
-                .store_mem(arg.ty().mode(), Mem::Local(offset), dest);
+
+            // structs are copied, the callee gets the address of the copy
+            if arg.ty().is_struct_or_tuple() {
+                self.asm
+                    .copy_struct(arg.ty(), Mem::Local(offset), Mem::Base(dest.reg(), 0));
+            } else {
+                self.asm
+                    .store_mem(arg.ty().mode(), Mem::Local(offset), dest);
+            }
+
             temps.push((arg.ty(), offset, None));
         }
 
@@ -1594,10 +2925,25 @@ where
 
                     freg_idx += 1;
                 } else {
+                    // FREG_TMP1 might already hold an argument, copy the
+                    // bits through an integer register instead
+                    let mode = float_bits_mode(mode);
+                    self.asm.load_mem(mode, REG_TMP1.into(), Mem::Local(offset));
                     self.asm
-                        .load_mem(mode, FREG_TMP1.into(), Mem::Local(offset));
+                        .store_mem(mode, Mem::Local(arg_offset), REG_TMP1.into());
+
+                    arg_offset += 8;
+                }
+            } else if ty.is_struct_or_tuple() {
+                if reg_idx < REG_PARAMS.len() {
+                    let reg = REG_PARAMS[reg_idx];
+                    self.asm.lea(reg, Mem::Local(offset));
+
+                    reg_idx += 1;
+                } else {
+                    self.asm.lea(REG_TMP1, Mem::Local(offset));
                     self.asm
-                        .store_mem(mode, Mem::Local(arg_offset), FREG_TMP1.into());
+                        .store_mem(mode, Mem::Local(arg_offset), REG_TMP1.into());
 
                     arg_offset += 8;
                 }
@@ -1619,6 +2965,19 @@ where
             idx += 1;
         }
 
+        // pass address of storage for returned struct as last argument
+        if let Some(offset) = csite.struct_return {
+            let offset = -(self.jit_info.localsize + offset);
+
+            if reg_idx < REG_PARAMS.len() {
+                self.asm.lea(REG_PARAMS[reg_idx], Mem::Local(offset));
+            } else {
+                self.asm.lea(REG_TMP1, Mem::Local(offset));
+                self.asm
+                    .store_mem(MachineMode::Ptr, Mem::Local(arg_offset), REG_TMP1.into());
+            }
+        }
+
         let return_type = self.specialize_type(csite.return_type);
         let cls_type_params: TypeParams = csite
             .cls_type_params
@@ -1659,7 +3018,7 @@ where
             self.asm.emit_comment(Comment::CallVirtual(fid));
             let gcpoint = self.create_gcpoint();
             self.asm
-                .indirect_call(vtable_index, pos, gcpoint, return_type, dest);
+                .cached_call(id, fid, vtable_index, pos, gcpoint, return_type, dest);
         } else {
             let ptr = self.ptr_for_fct_id(fid, cls_type_params.clone(), fct_type_params.clone());
             self.asm.emit_comment(Comment::CallDirect(fid));
@@ -1712,7 +3071,7 @@ where
                 alloc_size = AllocationSize::Fixed(size as usize);
             }
 
-            ClassSize::Array(esize) if temps.len() > 1 => {
+            ClassSize::Array(esize) | ClassSize::StructArray(esize) if temps.len() > 1 => {
                 self.asm
                     .load_mem(MachineMode::Int32, REG_TMP1.into(), Mem::Local(temps[1].1));
 
@@ -1745,7 +3104,10 @@ where
                 alloc_size = AllocationSize::Dynamic(REG_PARAMS[0]);
             }
 
-            ClassSize::Array(_) | ClassSize::ObjArray | ClassSize::Str => {
+            ClassSize::Array(_)
+            | ClassSize::ObjArray
+            | ClassSize::StructArray(_)
+            | ClassSize::Str => {
                 let size = Header::size() as usize + mem::ptr_width_usize();
                 self.asm
                     .load_int_const(MachineMode::Int32, REG_PARAMS[0], size as i64);
@@ -1818,7 +3180,7 @@ where
                 );
 
                 let element_size = match cls.size {
-                    ClassSize::Array(esize) => esize,
+                    ClassSize::Array(esize) | ClassSize::StructArray(esize) => esize,
                     ClassSize::ObjArray => mem::ptr_width(),
                     ClassSize::Str => 1,
                     ClassSize::Fixed(_) => unreachable!(),
@@ -1845,8 +3207,9 @@ where
                 self.cls_type_params[id.idx()]
             }
 
+            // lambdas use the type params of their enclosing function
             BuiltinType::FctTypeParam(fct_id, id) => {
-                assert!(self.fct.id == fct_id);
+                assert!(self.fct.id == fct_id || self.fct.is_lambda);
                 self.fct_type_params[id.idx()]
             }
 
@@ -1860,7 +3223,21 @@ where
                 BuiltinType::Class(cls_id, list_id)
             }
 
-            BuiltinType::Lambda(_) => unimplemented!(),
+            BuiltinType::Lambda(lambda_id) => {
+                BuiltinType::Lambda(info::specialize_lambda(self.vm, lambda_id, |t| {
+                    self.specialize_type(t)
+                }))
+            }
+
+            BuiltinType::Tuple(tuple_id) => {
+                let elements = self.vm.tuple_types.lock().get(tuple_id);
+
+                let elements: Vec<_> = elements.iter().map(|&t| self.specialize_type(t)).collect();
+
+                let tuple_id = self.vm.tuple_types.lock().insert(elements);
+
+                BuiltinType::Tuple(tuple_id)
+            }
 
             _ => ty,
         }
@@ -1884,7 +3261,16 @@ fn result_reg(mode: MachineMode) -> ExprStore {
     }
 }
 
-fn check_for_nil(ty: BuiltinType) -> bool {
+// integer mode of the same size as the given float mode
+fn float_bits_mode(mode: MachineMode) -> MachineMode {
+    match mode {
+        MachineMode::Float32 => MachineMode::Int32,
+        MachineMode::Float64 => MachineMode::Int64,
+        _ => unreachable!(),
+    }
+}
+
+pub fn check_for_nil(ty: BuiltinType) -> bool {
     match ty {
         BuiltinType::Error => panic!("error shouldn't occur in code generation."),
         BuiltinType::Unit => false,
@@ -1897,7 +3283,8 @@ fn check_for_nil(ty: BuiltinType) -> bool {
         | BuiltinType::Bool => false,
         BuiltinType::Nil | BuiltinType::Ptr => true,
         BuiltinType::Class(_, _) => true,
-        BuiltinType::Struct(_, _) => false,
+        BuiltinType::Struct(_, _) | BuiltinType::Tuple(_) => false,
+        BuiltinType::Enum(_) => false,
         BuiltinType::Trait(_) => false,
         BuiltinType::This => unreachable!(),
         BuiltinType::ClassTypeParam(_, _) => unreachable!(),
@@ -1920,15 +3307,14 @@ pub fn ensure_native_stub(vm: &VM, fct_id: FctId, internal_fct: InternalFct) ->
 
         let jit_fct_id = dora_native::generate(vm, internal_fct, dbg);
         let jit_fct = vm.jit_fcts.idx(jit_fct_id);
-        let jit_fct = jit_fct.to_base().expect("baseline expected");
 
-        let fct_start = jit_fct.fct_start;
+        let fct_start = jit_fct.fct_ptr();
 
         if should_emit_asm(vm, &*fct) {
             dump_asm(
                 vm,
                 &*fct,
-                &jit_fct,
+                &*jit_fct,
                 None,
                 vm.args.flag_asm_syntax.unwrap_or(AsmSyntax::Att),
             );
@@ -1939,6 +3325,47 @@ pub fn ensure_native_stub(vm: &VM, fct_id: FctId, internal_fct: InternalFct) ->
     }
 }
 
+pub fn ptr_for_fct_id(
+    vm: &VM,
+    caller: &Fct,
+    caller_src: &mut FctSrc,
+    fid: FctId,
+    cls_type_params: TypeParams,
+    fct_type_params: TypeParams,
+) -> Address {
+    if caller.id == fid {
+        // we want to recursively invoke the function we are compiling right now
+        ensure_jit_or_stub_ptr(caller_src, vm, cls_type_params, fct_type_params)
+    } else {
+        let fct = vm.fcts.idx(fid);
+        let fct = fct.read();
+
+        match fct.kind {
+            FctKind::Source(_) => {
+                let src = fct.src();
+                let mut src = src.write();
+
+                ensure_jit_or_stub_ptr(&mut src, vm, cls_type_params, fct_type_params)
+            }
+
+            FctKind::Native(ptr) => {
+                let internal_fct = InternalFct {
+                    ptr: ptr,
+                    args: fct.params_with_self(),
+                    return_type: fct.return_type,
+                    throws: fct.ast.throws,
+                    desc: InternalFctDescriptor::NativeThunk(fid),
+                };
+
+                ensure_native_stub(vm, fid, internal_fct)
+            }
+
+            FctKind::Definition => panic!("prototype for fct call"),
+            FctKind::Builtin(_) => panic!("intrinsic fct call"),
+        }
+    }
+}
+
 fn ensure_jit_or_stub_ptr<'ast>(
     src: &mut FctSrc,
     vm: &VM,
@@ -1956,7 +3383,7 @@ fn ensure_jit_or_stub_ptr<'ast>(
     vm.compiler_thunk()
 }
 
-fn to_cond_code(cmp: CmpOp) -> CondCode {
+pub fn to_cond_code(cmp: CmpOp) -> CondCode {
     match cmp {
         CmpOp::Eq => CondCode::Equal,
         CmpOp::Ne => CondCode::NotEqual,
@@ -1,3 +1,4 @@
+use std::collections::hash_map;
 use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::ptr;
@@ -7,13 +8,14 @@ use class::{ClassDef, ClassDefId, FieldId, TypeParams};
 use cpu::flush_icache;
 use ctxt::VM;
 use ctxt::{FctId, FctSrc, GlobalId, VarId};
+use dora_parser::ast::NodeId;
 use dseg::DSeg;
-use gc::Address;
+use gc::{Address, GcReason};
 use object::{Ref, Str};
 use opt::fct::JitOptFct;
 use utils::GrowableVec;
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct JitFctId(usize);
 
 impl JitFctId {
@@ -58,10 +60,102 @@ impl JitFct {
         }
     }
 
-    pub fn to_base(&self) -> Option<&JitBaselineFct> {
+    pub fn ptr_start(&self) -> Address {
+        match self {
+            &JitFct::Base(ref base) => base.ptr_start(),
+            &JitFct::Opt(ref opt) => opt.ptr_start(),
+        }
+    }
+
+    pub fn ptr_end(&self) -> Address {
+        match self {
+            &JitFct::Base(ref base) => base.ptr_end(),
+            &JitFct::Opt(ref opt) => opt.ptr_end(),
+        }
+    }
+
+    pub fn fct_end(&self) -> Address {
+        self.fct_ptr().offset(self.fct_len())
+    }
+
+    pub fn fct_len(&self) -> usize {
+        match self {
+            &JitFct::Base(ref base) => base.fct_len(),
+            &JitFct::Opt(ref opt) => opt.fct_len(),
+        }
+    }
+
+    pub fn framesize(&self) -> i32 {
+        match self {
+            &JitFct::Base(ref base) => base.framesize,
+            &JitFct::Opt(ref opt) => opt.framesize,
+        }
+    }
+
+    pub fn throws(&self) -> bool {
+        match self {
+            &JitFct::Base(ref base) => base.throws,
+            &JitFct::Opt(ref opt) => opt.throws,
+        }
+    }
+
+    pub fn exception_handlers(&self) -> &[ExHandler] {
+        match self {
+            &JitFct::Base(ref base) => &base.exception_handlers,
+            &JitFct::Opt(_) => &[],
+        }
+    }
+
+    pub fn lineno_for_offset(&self, offset: i32) -> i32 {
+        match self {
+            &JitFct::Base(ref base) => base.lineno_for_offset(offset),
+            &JitFct::Opt(ref opt) => opt.lineno_for_offset(offset),
+        }
+    }
+
+    pub fn linenos(&self) -> &LineNumberTable {
+        match self {
+            &JitFct::Base(ref base) => &base.linenos,
+            &JitFct::Opt(ref opt) => opt.linenos(),
+        }
+    }
+
+    pub fn inlined_for_offset(&self, offset: i32) -> &[(FctId, i32)] {
+        match self {
+            &JitFct::Base(_) => &[],
+            &JitFct::Opt(ref opt) => opt.inlined_for_offset(offset),
+        }
+    }
+
+    pub fn gcpoint_for_offset(&self, offset: i32) -> Option<&GcPoint> {
+        match self {
+            &JitFct::Base(ref base) => base.gcpoint_for_offset(offset),
+            &JitFct::Opt(ref opt) => opt.gcpoint_for_offset(offset),
+        }
+    }
+
+    pub fn nil_check_for_offset(&self, offset: i32) -> bool {
+        match self {
+            &JitFct::Base(ref base) => base.nil_check_for_offset(offset),
+            &JitFct::Opt(ref opt) => opt.nil_check_for_offset(offset),
+        }
+    }
+
+    pub fn bailout_for_offset(&self, offset: i32) -> Option<&BailoutInfo> {
+        self.bailouts().get(offset)
+    }
+
+    pub fn bailouts(&self) -> &Bailouts {
+        match self {
+            &JitFct::Base(ref base) => &base.bailouts,
+            &JitFct::Opt(ref opt) => &opt.bailouts,
+        }
+    }
+
+    pub fn get_comment(&self, pos: i32) -> Option<&[Comment]> {
         match self {
-            &JitFct::Base(ref base) => Some(base),
-            _ => None,
+            &JitFct::Base(ref base) => base.get_comment(pos),
+            &JitFct::Opt(ref opt) => opt.get_comment(pos),
         }
     }
 }
@@ -73,6 +167,8 @@ pub enum JitDescriptor {
     ThrowThunk,
     TrapThunk,
     AllocThunk,
+    OsrThunk,
+    EnumEqualsThunk,
     NativeThunk(FctId),
     DoraEntry,
 }
@@ -114,22 +210,8 @@ impl JitBaselineFct {
         throws: bool,
         mut exception_handlers: Vec<ExHandler>,
     ) -> JitBaselineFct {
+        let (ptr, fct_start) = install_code(vm, dseg, buffer);
         let size = dseg.size() as usize + buffer.len();
-        let ptr = vm.gc.alloc_code(size);
-
-        if ptr.is_null() {
-            panic!("out of memory: not enough executable memory left!");
-        }
-
-        dseg.finish(ptr.to_ptr());
-
-        let fct_start = ptr.offset(dseg.size() as usize);
-
-        unsafe {
-            ptr::copy_nonoverlapping(buffer.as_ptr(), fct_start.to_mut_ptr(), buffer.len());
-        }
-
-        flush_icache(ptr.to_ptr(), size);
 
         for handler in &mut exception_handlers {
             handler.try_start = fct_start.offset(handler.try_start).to_usize();
@@ -199,6 +281,43 @@ impl JitBaselineFct {
     }
 }
 
+// copies data segment and machine code into executable memory, returns
+// the start of the allocation and the start of the function
+pub fn install_code(vm: &VM, dseg: &DSeg, buffer: &[u8]) -> (Address, Address) {
+    match try_install_code(vm, dseg, buffer) {
+        Some(res) => res,
+        None => panic!("out of memory: not enough executable memory left!"),
+    }
+}
+
+// like `install_code` but returns None when the code doesn't fit into the code space
+// even after a code collection, the freed memory might be too fragmented for the code
+pub fn try_install_code(vm: &VM, dseg: &DSeg, buffer: &[u8]) -> Option<(Address, Address)> {
+    let size = dseg.size() as usize + buffer.len();
+    let mut ptr = vm.gc.alloc_code(size);
+
+    if ptr.is_null() {
+        vm.gc.collect_code(vm, GcReason::AllocationFailure);
+        ptr = vm.gc.alloc_code(size);
+    }
+
+    if ptr.is_null() {
+        return None;
+    }
+
+    dseg.finish(ptr.to_ptr());
+
+    let fct_start = ptr.offset(dseg.size() as usize);
+
+    unsafe {
+        ptr::copy_nonoverlapping(buffer.as_ptr(), fct_start.to_mut_ptr(), buffer.len());
+    }
+
+    flush_icache(ptr.to_ptr(), size);
+
+    Some((ptr, fct_start))
+}
+
 impl fmt::Debug for JitBaselineFct {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
@@ -424,17 +543,36 @@ impl<'a, 'ast> fmt::Display for CommentFormat<'a, 'ast> {
 #[derive(Debug)]
 pub struct LineNumberTable {
     map: HashMap<i32, i32>,
+
+    // frames of inlined functions, innermost first
+    inlined: HashMap<i32, Vec<(FctId, i32)>>,
 }
 
 impl LineNumberTable {
     pub fn new() -> LineNumberTable {
         LineNumberTable {
             map: HashMap::new(),
+            inlined: HashMap::new(),
         }
     }
 
-    pub fn insert(&mut self, offset: i32, lineno: i32) {
+    // `inlined` contains the functions inlined at the program point, innermost
+    // first, together with the line of their call site. The line of each frame
+    // is the line of the call site of the frame before.
+    pub fn insert(&mut self, offset: i32, lineno: i32, inlined: &[(FctId, i32)]) {
+        let mut lineno = lineno;
+        let mut frames = Vec::with_capacity(inlined.len());
+
+        for &(fct_id, call_lineno) in inlined {
+            frames.push((fct_id, lineno));
+            lineno = call_lineno;
+        }
+
         assert!(self.map.insert(offset, lineno).is_none());
+
+        if !frames.is_empty() {
+            self.inlined.insert(offset, frames);
+        }
     }
 
     pub fn get(&self, offset: i32) -> i32 {
@@ -444,6 +582,17 @@ impl LineNumberTable {
             0
         }
     }
+
+    pub fn inlined(&self, offset: i32) -> &[(FctId, i32)] {
+        match self.inlined.get(&offset) {
+            Some(frames) => frames,
+            None => &[],
+        }
+    }
+
+    pub fn iter(&self) -> hash_map::Iter<i32, i32> {
+        self.map.iter()
+    }
 }
 
 #[derive(Debug)]
@@ -480,10 +629,15 @@ impl Bailouts {
     pub fn get(&self, offset: i32) -> Option<&BailoutInfo> {
         self.map.get(&offset)
     }
+
+    pub fn iter(&self) -> hash_map::Iter<i32, BailoutInfo> {
+        self.map.iter()
+    }
 }
 
 #[derive(Clone, Debug)]
 pub enum BailoutInfo {
     Compile(FctId, i32, TypeParams, TypeParams),
     VirtCompile(u32, TypeParams),
+    InlineCache(FctId, u32, i32, Option<NodeId>),
 }
@@ -0,0 +1,202 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use baseline::fct::{BailoutInfo, JitFct};
+use baseline::map::CodeDescriptor;
+use ctxt::VM;
+use dora_parser::ast::NodeId;
+use gc::Address;
+use mem::ptr_width;
+
+// number of receiver classes cached at a call site before it becomes megamorphic
+pub const IC_ENTRIES: i32 = 4;
+
+// An inline cache is stored in the data segment of the calling function. It starts
+// with IC_ENTRIES pairs of vtable and target address, followed by a flag that marks
+// the call site as megamorphic and the hit and miss counters (only updated with
+// --emit-ic-stats). Entries are only added and never removed, unused entries are null.
+pub const IC_SIZE: i32 = 2 * IC_ENTRIES + 3;
+
+pub fn offset_of_vtable(entry: i32) -> i32 {
+    2 * entry * ptr_width()
+}
+
+pub fn offset_of_target(entry: i32) -> i32 {
+    (2 * entry + 1) * ptr_width()
+}
+
+pub fn offset_of_megamorphic() -> i32 {
+    2 * IC_ENTRIES * ptr_width()
+}
+
+pub fn offset_of_hits() -> i32 {
+    (2 * IC_ENTRIES + 1) * ptr_width()
+}
+
+pub fn offset_of_misses() -> i32 {
+    (2 * IC_ENTRIES + 2) * ptr_width()
+}
+
+pub struct InlineCache {
+    start: Address,
+}
+
+impl InlineCache {
+    pub fn at(start: Address) -> InlineCache {
+        InlineCache { start: start }
+    }
+
+    // inline cache of the call returning to `offset`
+    fn for_call(jit_fct: &JitFct, offset: i32, disp: i32) -> InlineCache {
+        let ra = jit_fct.fct_ptr().offset(offset as usize);
+        InlineCache::at(ra.sub(disp as usize))
+    }
+
+    // caches the target for the given vtable, the call site becomes
+    // megamorphic when all entries are in use
+    pub fn insert(&self, vm: &VM, vtable: Address, target: Address) {
+        let _lock = vm.ic_lock.lock();
+
+        for entry in 0..IC_ENTRIES {
+            let cached = self.load(offset_of_vtable(entry));
+
+            if cached == vtable.to_usize() {
+                return;
+            }
+
+            if cached == 0 {
+                // generated code reads the target after matching the vtable,
+                // so the target needs to be stored first
+                self.store(offset_of_target(entry), target.to_usize());
+                self.store(offset_of_vtable(entry), vtable.to_usize());
+                return;
+            }
+        }
+
+        self.store(offset_of_megamorphic(), 1);
+    }
+
+    // replaces all cached targets `old` with `new`, used after
+    // a function was recompiled
+    pub fn update_target(&self, vm: &VM, old: Address, new: Address) {
+        let _lock = vm.ic_lock.lock();
+
+        for entry in 0..IC_ENTRIES {
+            if self.load(offset_of_target(entry)) == old.to_usize() {
+                self.store(offset_of_target(entry), new.to_usize());
+            }
+        }
+    }
+
+    fn entries(&self) -> i32 {
+        let mut entries = 0;
+
+        for entry in 0..IC_ENTRIES {
+            if self.load(offset_of_vtable(entry)) != 0 {
+                entries += 1;
+            }
+        }
+
+        entries
+    }
+
+    fn state(&self) -> &'static str {
+        if self.load(offset_of_megamorphic()) != 0 {
+            return "megamorphic";
+        }
+
+        match self.entries() {
+            0 => "uninitialized",
+            1 => "monomorphic",
+            _ => "polymorphic",
+        }
+    }
+
+    fn load(&self, offset: i32) -> usize {
+        self.word(offset).load(Ordering::Acquire)
+    }
+
+    fn store(&self, offset: i32, value: usize) {
+        self.word(offset).store(value, Ordering::Release);
+    }
+
+    fn word(&self, offset: i32) -> &AtomicUsize {
+        unsafe { &*self.start.offset(offset as usize).to_ptr::<AtomicUsize>() }
+    }
+}
+
+// returns the receiver vtable and the target of the call site `id` in the
+// given code if its inline cache only saw a single receiver class
+pub fn monomorphic_target(jit_fct: &JitFct, id: NodeId) -> Option<(Address, Address)> {
+    for (&offset, info) in jit_fct.bailouts().iter() {
+        match *info {
+            BailoutInfo::InlineCache(_, _, disp, Some(call_id)) if call_id == id => {
+                let ic = InlineCache::for_call(jit_fct, offset, disp);
+
+                if ic.load(offset_of_megamorphic()) != 0 || ic.entries() != 1 {
+                    return None;
+                }
+
+                let vtable = ic.load(offset_of_vtable(0));
+                let target = ic.load(offset_of_target(0));
+
+                return Some((vtable.into(), target.into()));
+            }
+
+            _ => {}
+        }
+    }
+
+    None
+}
+
+pub fn dump_stats(vm: &VM) {
+    let mut sites = Vec::new();
+
+    for (idx, jit_fct) in vm.jit_fcts.iter().enumerate() {
+        let desc = vm.code_map.lock().get(jit_fct.ptr_start());
+
+        // skip thunks and freed code
+        if desc != Some(CodeDescriptor::DoraFct(idx.into())) {
+            continue;
+        }
+
+        let optimized = match *jit_fct {
+            JitFct::Base(_) => false,
+            JitFct::Opt(_) => true,
+        };
+
+        for (&offset, info) in jit_fct.bailouts().iter() {
+            if let &BailoutInfo::InlineCache(callee_id, _, disp, _) = info {
+                let fct = vm.fcts.idx(jit_fct.fct_id());
+                let fct = fct.read();
+                let callee = vm.fcts.idx(callee_id);
+                let callee = callee.read();
+
+                let ic = InlineCache::for_call(&jit_fct, offset, disp);
+
+                sites.push((
+                    fct.full_name(vm),
+                    jit_fct.lineno_for_offset(offset),
+                    optimized,
+                    callee.full_name(vm),
+                    ic.state(),
+                    ic.load(offset_of_hits()),
+                    ic.load(offset_of_misses()),
+                ));
+            }
+        }
+    }
+
+    sites.sort();
+
+    println!("inline caches:");
+
+    for (fct, line, optimized, callee, state, hits, misses) in sites {
+        let tier = if optimized { " (opt)" } else { "" };
+
+        println!(
+            "  {}{} line {}: {} {} ({} hits, {} misses)",
+            fct, tier, line, callee, state, hits, misses
+        );
+    }
+}
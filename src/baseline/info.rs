@@ -1,19 +1,20 @@
 use std::cmp::max;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
+use baseline::bounds;
 use class::TypeParams;
 use cpu::*;
 use ctxt::VM;
 use ctxt::{
-    Arg, CallSite, CallType, Fct, FctId, FctKind, FctParent, FctSrc, Intrinsic, NodeMap, Store,
-    TraitId, VarId,
+    Arg, CallSite, CallType, Fct, FctId, FctKind, FctParent, FctSrc, IdentType, Intrinsic, NodeMap,
+    Store, TraitId, VarId,
 };
 use dora_parser::ast::visit::*;
 use dora_parser::ast::Expr::*;
 use dora_parser::ast::Stmt::*;
 use dora_parser::ast::*;
 use mem;
-use ty::BuiltinType;
+use ty::{BuiltinType, LambdaId};
 
 pub fn generate<'a, 'ast: 'a>(
     vm: &'a VM<'ast>,
@@ -58,6 +59,7 @@ pub struct JitInfo<'ast> {
     pub argsize: i32,                 // size of arguments on stack (need to be on bottom)
     pub leaf: bool,                   // false if fct calls other functions
     pub eh_return_value: Option<i32>, // stack slot for return value storage
+    pub struct_return: Option<i32>,   // stack slot for address of returned struct
 
     pub map_stores: NodeMap<Store>,
     pub map_csites: NodeMap<CallSite<'ast>>,
@@ -66,6 +68,7 @@ pub struct JitInfo<'ast> {
     pub map_var_types: HashMap<VarId, BuiltinType>,
     pub map_intrinsics: NodeMap<Intrinsic>,
     pub map_fors: NodeMap<ForInfo<'ast>>,
+    pub in_bounds: HashSet<NodeId>, // array accesses without bounds check
 }
 
 impl<'ast> JitInfo<'ast> {
@@ -101,6 +104,7 @@ impl<'ast> JitInfo<'ast> {
             argsize: 0,
             leaf: false,
             eh_return_value: None,
+            struct_return: None,
 
             map_stores: NodeMap::new(),
             map_csites: NodeMap::new(),
@@ -109,6 +113,7 @@ impl<'ast> JitInfo<'ast> {
             map_var_types: HashMap::new(),
             map_intrinsics: NodeMap::new(),
             map_fors: NodeMap::new(),
+            in_bounds: HashSet::new(),
         }
     }
 }
@@ -158,13 +163,16 @@ impl<'a, 'ast> Visitor<'ast> for InfoGenerator<'a, 'ast> {
         // the rest of the parameters are already stored on the stack
         // just use the current offset
         } else {
-            let var = &self.src.vars[var];
-            self.jit_info
-                .map_var_offsets
-                .insert(var.id, self.param_offset);
+            if ty.is_struct_or_tuple() {
+                // only the address of the struct is passed on the stack,
+                // the struct is copied into a local variable
+                self.reserve_stack_for_var(var);
+            } else {
+                self.jit_info.map_var_offsets.insert(var, self.param_offset);
+            }
 
             // determine next `param_offset`
-            self.param_offset = next_param_offset(self.param_offset, var.ty);
+            self.param_offset = next_param_offset(self.param_offset, ty);
         }
     }
 
@@ -175,12 +183,31 @@ impl<'a, 'ast> Visitor<'ast> for InfoGenerator<'a, 'ast> {
                 self.reserve_stack_for_var(var);
             }
 
+            &StmtLetTuple(ref tuple) => {
+                for binding in &tuple.bindings {
+                    let var = *self.src.map_vars.get(binding.id).unwrap();
+                    self.reserve_stack_for_var(var);
+                }
+            }
+
             &StmtDo(ref try) => {
                 self.reserve_stmt_do(try);
             }
 
             &StmtFor(ref sfor) => {
+                // iterated expression is already visited as argument of makeIterator()
                 self.reserve_stmt_for(sfor);
+                self.visit_stmt(&sfor.block);
+                return;
+            }
+
+            &StmtDefer(ref defer) => {
+                self.reserve_stmt_defer(defer);
+            }
+
+            &StmtSpawn(ref spawn) => {
+                self.reserve_stmt_spawn(spawn);
+                return;
             }
 
             _ => {}
@@ -205,6 +232,11 @@ impl<'a, 'ast> Visitor<'ast> for InfoGenerator<'a, 'ast> {
             ExprUn(ref expr) => self.expr_un(expr),
             ExprConv(ref expr) => self.expr_conv(expr),
             ExprLitStruct(ref expr) => self.expr_lit_struct(expr),
+            ExprTuple(ref expr) => self.expr_tuple(expr),
+            ExprLambda(ref expr) => self.expr_lambda(expr),
+            ExprIdent(ref expr) => self.expr_ident(expr),
+            ExprPath(ref expr) => self.expr_path(expr),
+            ExprMatch(ref expr) => self.expr_match(expr),
 
             _ => visit::walk_expr(self, e),
         }
@@ -218,24 +250,61 @@ impl<'a, 'ast> InfoGenerator<'a, 'ast> {
         }
 
         self.visit_fct(self.ast);
+        self.reserve_struct_return();
 
         self.jit_info.localsize = self.localsize;
         self.jit_info.tempsize = self.max_tempsize;
         self.jit_info.argsize = self.argsize;
         self.jit_info.leaf = self.leaf;
         self.jit_info.eh_return_value = self.eh_return_value;
+        self.jit_info.in_bounds = bounds::analyze(self.vm, self.src, self.jit_info, self.ast);
     }
 
-    fn reserve_stmt_do(&mut self, try: &'ast StmtDoType) {
-        let ret = self.fct.return_type;
+    fn reserve_struct_return(&mut self) {
+        let ret = self.specialize_type(self.fct.return_type);
 
-        if !ret.is_unit() {
-            self.eh_return_value = Some(
-                self.eh_return_value
-                    .unwrap_or_else(|| self.reserve_stack_for_type(ret)),
-            );
+        if !ret.is_struct_or_tuple() {
+            return;
         }
 
+        // the address for the returned struct is passed as last argument
+        let offset = if self.param_reg_idx < REG_PARAMS.len() {
+            self.reserve_stack_for_type(BuiltinType::Ptr)
+        } else {
+            self.param_offset
+        };
+
+        self.jit_info.struct_return = Some(offset);
+    }
+
+    fn reserve_stmt_spawn(&mut self, spawn: &'ast StmtSpawnType) {
+        self.cur_tempsize = 0;
+
+        // spawn calls the internal function spawnThread with the lambda as argument
+        let ty = self.ty(spawn.expr.id());
+        let args = vec![Arg::Expr(&spawn.expr, ty, 0)];
+        self.universal_call(spawn.id, args, None);
+
+        self.max_tempsize = max(self.cur_tempsize, self.max_tempsize);
+    }
+
+    fn reserve_eh_return_value(&mut self) {
+        let ret = self.specialize_type(self.fct.return_type);
+
+        if !ret.is_unit() && self.eh_return_value.is_none() {
+            // structs are returned by address
+            let ty = if ret.is_struct_or_tuple() {
+                BuiltinType::Ptr
+            } else {
+                ret
+            };
+            self.eh_return_value = Some(self.reserve_stack_for_type(ty));
+        }
+    }
+
+    fn reserve_stmt_do(&mut self, try: &'ast StmtDoType) {
+        self.reserve_eh_return_value();
+
         // we also need space for catch block parameters
         for catch in &try.catch_blocks {
             let var = *self.src.map_vars.get(catch.id).unwrap();
@@ -248,6 +317,14 @@ impl<'a, 'ast> InfoGenerator<'a, 'ast> {
         }
     }
 
+    fn reserve_stmt_defer(&mut self, defer: &'ast StmtDeferType) {
+        self.reserve_eh_return_value();
+
+        // stack slot for exception when unwinding
+        let offset = self.reserve_stack_for_type(BuiltinType::Ptr);
+        self.jit_info.map_offsets.insert(defer.id, offset);
+    }
+
     fn reserve_stmt_for(&mut self, stmt: &'ast StmtForType) {
         let for_type_info = self.src.map_fors.get(stmt.id).unwrap();
 
@@ -322,6 +399,7 @@ impl<'a, 'ast> InfoGenerator<'a, 'ast> {
 
         let id = self.src.var_self().id;
         self.jit_info.map_var_offsets.insert(id, offset);
+        self.jit_info.map_var_types.insert(id, ty);
     }
 
     fn reserve_stack_for_var(&mut self, id: VarId) -> i32 {
@@ -337,7 +415,8 @@ impl<'a, 'ast> InfoGenerator<'a, 'ast> {
 
     fn reserve_stack_for_type(&mut self, ty: BuiltinType) -> i32 {
         let ty_size = ty.size(self.vm);
-        self.localsize = mem::align_i32(self.localsize + ty_size, ty_size);
+        let ty_align = ty.align(self.vm);
+        self.localsize = mem::align_i32(self.localsize + ty_size, ty_align);
 
         -self.localsize
     }
@@ -376,6 +455,21 @@ impl<'a, 'ast> InfoGenerator<'a, 'ast> {
         }
     }
 
+    fn expr_tuple(&mut self, e: &'ast ExprTupleType) {
+        self.reserve_temp_for_node_id(e.id);
+
+        for element in &e.elements {
+            self.visit_expr(element);
+        }
+    }
+
+    fn expr_ident(&mut self, e: &'ast ExprIdentType) {
+        // struct without any fields
+        if let Some(&IdentType::Struct(_)) = self.src.map_idents.get(e.id) {
+            self.reserve_temp_for_node_id(e.id);
+        }
+    }
+
     fn get_intrinsic(&self, id: NodeId) -> Option<Intrinsic> {
         let fid = self.src.map_calls.get(id).unwrap().fct_id();
 
@@ -394,6 +488,25 @@ impl<'a, 'ast> InfoGenerator<'a, 'ast> {
     }
 
     fn expr_call(&mut self, expr: &'ast ExprCallType) {
+        match **self.src.map_calls.get(expr.id).unwrap() {
+            CallType::Lambda(lambda_id) => {
+                self.expr_call_lambda(expr, lambda_id);
+                return;
+            }
+
+            CallType::EnumValue(_, _) => {
+                self.expr_enum_value(expr.id);
+
+                for arg in &expr.args {
+                    self.visit_expr(arg);
+                }
+
+                return;
+            }
+
+            _ => {}
+        }
+
         if let Some(intrinsic) = self.get_intrinsic(expr.id) {
             self.reserve_args(expr);
             self.jit_info.map_intrinsics.insert(expr.id, intrinsic);
@@ -434,6 +547,8 @@ impl<'a, 'ast> InfoGenerator<'a, 'ast> {
             CallType::Fct(fid, _, _) => {
                 fct_id = fid;
             }
+
+            CallType::Lambda(_) | CallType::EnumValue(_, _) => unreachable!(),
         }
 
         let fct = self.vm.fcts.idx(fct_id);
@@ -465,6 +580,92 @@ impl<'a, 'ast> InfoGenerator<'a, 'ast> {
         self.universal_call(expr.id, args, Some(callee_id));
     }
 
+    fn expr_call_lambda(&mut self, expr: &'ast ExprCallType, lambda_id: LambdaId) {
+        let lambda_id = specialize_lambda(self.vm, lambda_id, |t| self.specialize_type(t));
+        let lambda = self.vm.lambda_types.lock().get(lambda_id);
+
+        // the closure object is passed as first argument, the
+        // call node might already have a temp of its result type
+        let offset = self.reserve_stack_for_type(BuiltinType::Ptr);
+        self.jit_info.map_offsets.insert(expr.id, offset);
+        let mut args = vec![Arg::Stack(offset, BuiltinType::Ptr, offset)];
+
+        for (arg, &ty) in expr.args.iter().zip(lambda.params()) {
+            let offset = self.reserve_stack_for_type(ty);
+            self.jit_info.map_offsets.insert(arg.id(), offset);
+            args.push(Arg::Expr(arg, ty, offset));
+        }
+
+        // function invokes another function
+        self.leaf = false;
+
+        self.determine_call_stack(&args, false);
+    }
+
+    fn expr_match(&mut self, expr: &'ast ExprMatchType) {
+        self.visit_expr(&expr.expr);
+
+        // the value is kept on the stack while the arms are tested
+        let ty = self.ty(expr.expr.id());
+        let offset = self.reserve_stack_for_type(ty);
+        self.jit_info.map_offsets.insert(expr.id, offset);
+
+        let info = self.src.map_matches.get(expr.id).unwrap().clone();
+
+        for (arm, arm_info) in expr.arms.iter().zip(&info.arms) {
+            match arm.pattern {
+                MatchPattern::Lit(ref lit) if arm_info.is_str() => {
+                    let args = vec![Arg::Stack(offset, ty, 0), Arg::Expr(lit, ty, 0)];
+                    self.universal_call(arm.id, args, None);
+                }
+
+                MatchPattern::Type(Some(_), _) => {
+                    let var = *self.src.map_vars.get(arm.id).unwrap();
+                    self.reserve_stack_for_var(var);
+                }
+
+                MatchPattern::Enum(_, ref bindings) => {
+                    for binding in bindings.iter().filter(|binding| binding.name.is_some()) {
+                        let var = *self.src.map_vars.get(binding.id).unwrap();
+                        self.reserve_stack_for_var(var);
+                    }
+                }
+
+                _ => {}
+            }
+
+            self.visit_expr(&arm.value);
+        }
+    }
+
+    fn expr_path(&mut self, expr: &'ast ExprPathType) {
+        match self.src.map_idents.get(expr.id) {
+            Some(&IdentType::EnumValue(_, _)) => self.expr_enum_value(expr.id),
+
+            // struct without any fields
+            Some(&IdentType::Struct(_)) => {
+                self.reserve_temp_for_node_id(expr.id);
+            }
+
+            _ => {}
+        }
+    }
+
+    fn expr_enum_value(&mut self, id: NodeId) {
+        // enum values are allocated on the heap
+        self.leaf = false;
+
+        self.reserve_temp_for_node_with_type(id, BuiltinType::Ptr);
+    }
+
+    fn expr_lambda(&mut self, expr: &'ast ExprLambdaType) {
+        // allocating the closure object invokes the runtime
+        self.leaf = false;
+
+        let offset = self.reserve_stack_for_type(BuiltinType::Ptr);
+        self.jit_info.map_offsets.insert(expr.id, offset);
+    }
+
     fn reserve_args(&mut self, expr: &'ast ExprCallType) {
         for arg in &expr.args {
             self.visit_expr(arg);
@@ -546,7 +747,14 @@ impl<'a, 'ast> InfoGenerator<'a, 'ast> {
             self.determine_call_args_and_types(&*call_type, &*callee, args);
         let (cls_type_params, fct_type_params) = self.determine_call_type_params(&*call_type);
 
-        self.determine_call_stack(&args);
+        // caller reserves storage for returned struct
+        let struct_return = if return_type.is_struct_or_tuple() {
+            Some(self.reserve_temp_for_type(return_type))
+        } else {
+            None
+        };
+
+        self.determine_call_stack(&args, struct_return.is_some());
 
         CallSite {
             callee: callee_id,
@@ -555,6 +763,7 @@ impl<'a, 'ast> InfoGenerator<'a, 'ast> {
             fct_type_params: fct_type_params,
             super_call: super_call,
             return_type: return_type,
+            struct_return: struct_return,
         }
     }
 
@@ -618,15 +827,22 @@ impl<'a, 'ast> InfoGenerator<'a, 'ast> {
                 cls_type_params = cls_tps.clone();
                 fct_type_params = fct_tps.clone();
             }
+
+            CallType::Lambda(_) | CallType::EnumValue(_, _) => unreachable!(),
         }
 
         (cls_type_params, fct_type_params)
     }
 
-    fn determine_call_stack(&mut self, args: &[Arg<'ast>]) {
+    fn determine_call_stack(&mut self, args: &[Arg<'ast>], struct_return: bool) {
         let mut reg_args: i32 = 0;
         let mut freg_args: i32 = 0;
 
+        // address of returned struct is passed as additional argument
+        if struct_return {
+            reg_args += 1;
+        }
+
         for arg in args {
             match *arg {
                 Arg::Expr(ast, ty, _) => {
@@ -661,14 +877,19 @@ impl<'a, 'ast> InfoGenerator<'a, 'ast> {
     }
 
     fn expr_assign(&mut self, e: &'ast ExprAssignType) {
-        if e.lhs.is_ident() {
+        if let Some(op) = e.op {
+            self.expr_assign_op(e, op);
+            return;
+        }
+
+        if e.lhs.is_ident() || e.lhs.is_path() {
             self.visit_expr(&e.rhs);
 
-            let lhs = e.lhs.to_ident().unwrap();
-            let field = self.src.map_idents.get(lhs.id).unwrap().is_field();
+            let lhs_id = e.lhs.id();
+            let field = self.src.map_idents.get(lhs_id).unwrap().is_field();
 
             if field {
-                self.reserve_temp_for_node_with_type(lhs.id, BuiltinType::Ptr);
+                self.reserve_temp_for_node_with_type(lhs_id, BuiltinType::Ptr);
             }
         } else if e.lhs.is_field() {
             let lhs = e.lhs.to_field().unwrap();
@@ -676,7 +897,12 @@ impl<'a, 'ast> InfoGenerator<'a, 'ast> {
             self.visit_expr(&lhs.object);
             self.visit_expr(&e.rhs);
 
-            self.reserve_temp_for_node(&lhs.object);
+            // the value is evaluated before the address of the struct
+            if self.src.map_idents.get(lhs.id).unwrap().is_struct_field() {
+                self.reserve_temp_for_node(&e.rhs);
+            } else {
+                self.reserve_temp_for_node(&lhs.object);
+            }
         } else {
             assert!(e.lhs.is_array());
             let array = e.lhs.to_array().unwrap();
@@ -705,6 +931,76 @@ impl<'a, 'ast> InfoGenerator<'a, 'ast> {
         }
     }
 
+    // the receiver and the index of the lhs are kept in stack slots,
+    // such that they are only evaluated once
+    fn expr_assign_op(&mut self, e: &'ast ExprAssignType, op: AssignOp) {
+        let ty = self.ty(e.lhs.id());
+
+        // holds the current value of the lhs and then the result
+        let offset = self.reserve_stack_for_type(ty);
+        self.jit_info.map_offsets.insert(e.id, offset);
+
+        if let Some(array) = e.lhs.to_array() {
+            self.visit_expr(&array.object);
+            self.visit_expr(&array.index);
+
+            let object_offset = self.reserve_stack_for_type(BuiltinType::Ptr);
+            self.jit_info
+                .map_offsets
+                .insert(array.object.id(), object_offset);
+
+            let index_type = self.ty(array.index.id());
+            let index_offset = self.reserve_stack_for_type(index_type);
+            self.jit_info
+                .map_offsets
+                .insert(array.index.id(), index_offset);
+
+            if let Some(intrinsic) = self.get_intrinsic(array.id) {
+                self.jit_info.map_intrinsics.insert(array.id, intrinsic);
+            } else {
+                let args = vec![
+                    Arg::Stack(object_offset, BuiltinType::Unit, 0),
+                    Arg::Stack(index_offset, BuiltinType::Unit, 0),
+                ];
+
+                self.universal_call(array.id, args, None);
+            }
+
+            if let Some(intrinsic) = self.get_intrinsic(e.id) {
+                self.jit_info.map_intrinsics.insert(e.id, intrinsic);
+            } else {
+                let args = vec![
+                    Arg::Stack(object_offset, BuiltinType::Unit, 0),
+                    Arg::Stack(index_offset, BuiltinType::Unit, 0),
+                    Arg::Stack(offset, BuiltinType::Unit, 0),
+                ];
+
+                self.universal_call(e.id, args, None);
+            }
+        } else if self.src.map_idents.get(e.lhs.id()).unwrap().is_field() {
+            if let Some(field) = e.lhs.to_field() {
+                self.visit_expr(&field.object);
+            }
+
+            let object_offset = self.reserve_stack_for_type(BuiltinType::Ptr);
+            self.jit_info.map_offsets.insert(e.lhs.id(), object_offset);
+        } else {
+            self.visit_expr(&e.lhs);
+        }
+
+        if let Some(intrinsic) = self.get_intrinsic(op.id) {
+            self.visit_expr(&e.rhs);
+            self.jit_info.map_intrinsics.insert(op.id, intrinsic);
+        } else {
+            let args = vec![
+                Arg::Stack(offset, BuiltinType::Unit, 0),
+                Arg::Expr(&e.rhs, BuiltinType::Unit, 0),
+            ];
+
+            self.universal_call(op.id, args, None);
+        }
+    }
+
     fn expr_bin(&mut self, expr: &'ast ExprBinType) {
         let lhs_ty = self.ty(expr.lhs.id());
         let rhs_ty = self.ty(expr.rhs.id());
@@ -713,6 +1009,11 @@ impl<'a, 'ast> InfoGenerator<'a, 'ast> {
             self.visit_expr(&expr.lhs);
             self.visit_expr(&expr.rhs);
 
+            self.reserve_temp_for_node_with_type(expr.lhs.id(), BuiltinType::Ptr);
+        } else if lhs_ty.is_enum() {
+            self.visit_expr(&expr.lhs);
+            self.visit_expr(&expr.rhs);
+
             self.reserve_temp_for_node_with_type(expr.lhs.id(), BuiltinType::Ptr);
         } else if expr.op == BinOp::Or || expr.op == BinOp::And {
             self.visit_expr(&expr.lhs);
@@ -774,7 +1075,8 @@ impl<'a, 'ast> InfoGenerator<'a, 'ast> {
 
     fn reserve_temp_for_type(&mut self, ty: BuiltinType) -> i32 {
         let ty_size = ty.size(self.vm);
-        self.cur_tempsize = mem::align_i32(self.cur_tempsize + ty_size, ty_size);
+        let ty_align = ty.align(self.vm);
+        self.cur_tempsize = mem::align_i32(self.cur_tempsize + ty_size, ty_align);
 
         self.cur_tempsize
     }
@@ -803,6 +1105,8 @@ impl<'a, 'ast> InfoGenerator<'a, 'ast> {
                 let empty = TypeParams::empty();
                 specialize_type(self.vm, ty, type_params, &empty)
             }
+
+            CallType::Lambda(_) | CallType::EnumValue(_, _) => ty,
         };
 
         self.specialize_type(ty)
@@ -815,8 +1119,9 @@ impl<'a, 'ast> InfoGenerator<'a, 'ast> {
                 self.cls_type_params[id.idx()]
             }
 
+            // lambdas use the type params of their enclosing function
             BuiltinType::FctTypeParam(fct_id, id) => {
-                debug_assert!(self.fct.id == fct_id);
+                debug_assert!(self.fct.id == fct_id || self.fct.is_lambda);
                 self.fct_type_params[id.idx()]
             }
 
@@ -829,14 +1134,28 @@ impl<'a, 'ast> InfoGenerator<'a, 'ast> {
                 BuiltinType::Class(cls_id, list_id)
             }
 
-            BuiltinType::Lambda(_) => unimplemented!(),
+            BuiltinType::Lambda(lambda_id) => {
+                BuiltinType::Lambda(specialize_lambda(self.vm, lambda_id, |t| {
+                    self.specialize_type(t)
+                }))
+            }
+
+            BuiltinType::Tuple(tuple_id) => {
+                let elements = self.vm.tuple_types.lock().get(tuple_id);
+
+                let elements: Vec<_> = elements.iter().map(|&t| self.specialize_type(t)).collect();
+
+                let tuple_id = self.vm.tuple_types.lock().insert(elements);
+
+                BuiltinType::Tuple(tuple_id)
+            }
 
             _ => ty,
         }
     }
 }
 
-fn specialize_type(
+pub fn specialize_type(
     vm: &VM,
     ty: BuiltinType,
     cls_type_params: &TypeParams,
@@ -860,12 +1179,42 @@ fn specialize_type(
             BuiltinType::Class(cls_id, list_id)
         }
 
-        BuiltinType::Lambda(_) => unimplemented!(),
+        BuiltinType::Lambda(lambda_id) => {
+            BuiltinType::Lambda(specialize_lambda(vm, lambda_id, |t| {
+                specialize_type(vm, t, cls_type_params, fct_type_params)
+            }))
+        }
+
+        BuiltinType::Tuple(tuple_id) => {
+            let elements = vm.tuple_types.lock().get(tuple_id);
+
+            let elements: Vec<_> = elements
+                .iter()
+                .map(|&t| specialize_type(vm, t, cls_type_params, fct_type_params))
+                .collect();
+
+            let tuple_id = vm.tuple_types.lock().insert(elements);
+
+            BuiltinType::Tuple(tuple_id)
+        }
 
         _ => ty,
     }
 }
 
+// specializes the parameter and return types of a lambda type
+pub fn specialize_lambda<F>(vm: &VM, lambda_id: LambdaId, mut specialize: F) -> LambdaId
+where
+    F: FnMut(BuiltinType) -> BuiltinType,
+{
+    let lambda = vm.lambda_types.lock().get(lambda_id);
+
+    let params: Vec<_> = lambda.params().iter().map(|&t| specialize(t)).collect();
+    let ret = specialize(lambda.ret());
+
+    vm.lambda_types.lock().insert(params, ret)
+}
+
 #[derive(Clone)]
 pub struct ForInfo<'ast> {
     pub make_iterator: CallSite<'ast>,
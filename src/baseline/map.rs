@@ -34,6 +34,8 @@ impl CodeMap {
                 &CodeDescriptor::ThrowThunk => println!("throw_thunk"),
                 &CodeDescriptor::TrapThunk => println!("trap_thunk"),
                 &CodeDescriptor::AllocThunk => println!("alloc_thunk"),
+                &CodeDescriptor::OsrThunk => println!("osr_thunk"),
+                &CodeDescriptor::EnumEqualsThunk => println!("enum_equals_thunk"),
                 &CodeDescriptor::NativeThunk(jit_fct_id) => {
                     let jit_fct = vm.jit_fcts.idx(jit_fct_id);
                     let fct = vm.fcts.idx(jit_fct.fct_id());
@@ -42,6 +44,7 @@ impl CodeMap {
                     println!("native {}", fct.full_name(vm));
                 }
                 &CodeDescriptor::DoraEntry => println!("dora_entry"),
+                &CodeDescriptor::InterpreterEntry(_) => println!("interpreter_entry"),
             }
         }
 
@@ -58,6 +61,23 @@ impl CodeMap {
 
         self.tree.get(&span).map(|el| *el)
     }
+
+    pub fn remove(&mut self, ptr: Address) -> Option<CodeDescriptor> {
+        let span = CodeSpan::new(ptr, ptr.offset(1));
+
+        self.tree.remove(&span)
+    }
+
+    // returns all compiled Dora functions
+    pub fn dora_fcts(&self) -> Vec<JitFctId> {
+        self.tree
+            .values()
+            .filter_map(|data| match *data {
+                CodeDescriptor::DoraFct(jit_fct_id) => Some(jit_fct_id),
+                _ => None,
+            })
+            .collect()
+    }
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
@@ -67,8 +87,11 @@ pub enum CodeDescriptor {
     ThrowThunk,
     TrapThunk,
     AllocThunk,
+    OsrThunk,
+    EnumEqualsThunk,
     NativeThunk(JitFctId),
     DoraEntry,
+    InterpreterEntry(JitFctId),
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -1,6 +1,7 @@
-pub use baseline::codegen::{generate, generate_fct};
+pub use baseline::codegen::{generate, generate_fct, generate_optimized, generate_osr};
 
-mod asm;
+pub mod asm;
+pub mod bounds;
 pub mod codegen;
 pub mod dora_compile;
 pub mod dora_entry;
@@ -8,5 +9,6 @@ pub mod dora_native;
 pub mod dora_throw;
 pub mod expr;
 pub mod fct;
+pub mod ic;
 pub mod info;
 pub mod map;
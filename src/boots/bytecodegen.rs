@@ -1,16 +1,23 @@
 use std::collections::HashMap;
 
-use dora_parser::ast::visit::Visitor;
+use class::{ClassDefId, ClassSize, FieldId};
+use ctxt::{CallType, ConstId, ConstValue, Fct, FctId, FctKind, FctParent, FctSrc};
+use ctxt::{IdentType, Intrinsic, VarId, VM};
 use dora_parser::ast::Expr::*;
 use dora_parser::ast::Stmt::*;
 use dora_parser::ast::*;
-use dora_parser::interner::Name;
+use dora_parser::lexer::position::Position;
+use object::{Ref, Str};
+use os::signal::Trap;
+use semck::specialize::{specialize_class_id, specialize_class_ty};
+use ty::BuiltinType;
 
-#[derive(PartialEq, Debug)]
-pub struct Register(usize);
-#[derive(PartialEq, Debug, Eq, Hash)]
-pub struct Label(usize);
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Register(pub usize);
+#[derive(Copy, Clone, PartialEq, Debug, Eq, Hash)]
+pub struct Label(pub usize);
 
+#[cfg(test)]
 macro_rules! hashmap {
     ($( $key: expr => $val: expr ),*) => {{
          let mut map = ::std::collections::HashMap::new();
@@ -20,7 +27,7 @@ macro_rules! hashmap {
 }
 
 pub struct Context {
-    var_map: HashMap<Name, Register>,
+    var_map: HashMap<VarId, Register>,
 }
 
 impl Context {
@@ -30,11 +37,11 @@ impl Context {
         }
     }
 
-    pub fn new_var(&mut self, var: Name, reg: Register) {
+    pub fn new_var(&mut self, var: VarId, reg: Register) {
         self.var_map.insert(var, reg);
     }
 
-    pub fn get_reg(&self, var: Name) -> Option<&Register> {
+    pub fn get_reg(&self, var: VarId) -> Option<&Register> {
         let reg = self.var_map.get(&var);
         reg
     }
@@ -45,23 +52,93 @@ pub struct LoopLabels {
     end: Label,
 }
 
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum BytecodeType {
+    Bool,
+    Byte,
+    Char,
+    Int,
+    Long,
+    Float,
+    Double,
+    Ptr,
+}
+
+impl BytecodeType {
+    pub fn from_ty(ty: BuiltinType) -> Option<BytecodeType> {
+        match ty {
+            BuiltinType::Bool => Some(BytecodeType::Bool),
+            BuiltinType::Byte => Some(BytecodeType::Byte),
+            BuiltinType::Char => Some(BytecodeType::Char),
+            BuiltinType::Int => Some(BytecodeType::Int),
+            BuiltinType::Long => Some(BytecodeType::Long),
+            BuiltinType::Float => Some(BytecodeType::Float),
+            BuiltinType::Double => Some(BytecodeType::Double),
+            BuiltinType::Nil
+            | BuiltinType::Ptr
+            | BuiltinType::This
+            | BuiltinType::Class(_, _)
+            | BuiltinType::Trait(_) => Some(BytecodeType::Ptr),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BytecodeType::Bool => "Bool",
+            BytecodeType::Byte => "Byte",
+            BytecodeType::Char => "Char",
+            BytecodeType::Int => "Int",
+            BytecodeType::Long => "Long",
+            BytecodeType::Float => "Float",
+            BytecodeType::Double => "Double",
+            BytecodeType::Ptr => "Ptr",
+        }
+    }
+}
+
+// binary operations and tests take their left operand from the given
+// register and their right operand from the accumulator
 #[derive(PartialEq, Debug)]
 pub enum Bytecode {
     Add(Register),
+    ArrayLength,
+    Assert,
     BitwiseAnd(Register),
     BitwiseOr(Register),
     BitwiseXor(Register),
+    CheckedCast(ClassDefId),
+    Conv(BytecodeType),
     Div(Register),
+    InstanceOf(ClassDefId),
+    InvokeStatic(FctId, Register, usize),
+    InvokeVirtual(FctId, Register, usize),
     Ldar(Register),
+    LdaArray(Register, BytecodeType),
+    LdaByte(u8),
+    LdaChar(char),
+    LdaDouble(f64),
+    LdaFalse,
+    LdaField(Register, ClassDefId, FieldId),
+    LdaFloat(f32),
     LdaInt(u64),
+    LdaLong(u64),
+    LdaNil,
+    LdaStr(usize),
+    LdaTrue,
     LdaZero,
     LogicalNot,
     Star(Register),
+    StaArray(Register, Register, BytecodeType),
+    StaField(Register, ClassDefId, FieldId),
     JumpIfFalse(Label),
+    JumpIfTrue(Label),
     Jump(Label),
     Mod(Register),
     Mul(Register),
     Neg,
+    NewArray(ClassDefId, Register, BytecodeType),
+    NewObject(ClassDefId),
     ShiftLeft(Register),
     ShiftRight(Register),
     Sub(Register),
@@ -73,118 +150,262 @@ pub enum Bytecode {
     TestLessThan(Register),
     TestLessThanOrEqual(Register),
     TestNotEqual(Register),
+    Throw,
+    Trap(Trap),
+    UnsignedShiftRight(Register),
 }
 
-pub struct BytecodeGen {
-    code: Vec<Bytecode>,
-    ctxs: Vec<Context>,
-    loops: Vec<LoopLabels>,
-    labels: HashMap<Label, usize>,
-    regs: usize,
+// exceptions thrown in `try_start..try_end` continue at `catch`, the
+// exception is stored in `register` when the catch block binds it
+#[derive(Debug)]
+pub struct ExceptionHandler {
+    pub try_start: usize,
+    pub try_end: usize,
+    pub catch: usize,
+    pub catch_type: Option<ClassDefId>,
+    pub register: Option<Register>,
 }
 
-impl<'ast> visit::Visitor<'ast> for BytecodeGen {
-    fn visit_fct(&mut self, f: &'ast Function) {
-        if !f.has_optimize {
-            return;
-        }
+pub struct BytecodeFunction {
+    pub fct_id: FctId,
+    pub code: Vec<Bytecode>,
+    pub labels: HashMap<Label, usize>,
+    pub handlers: Vec<ExceptionHandler>,
+    pub strings: Vec<Ref<Str>>,
+    pub arguments: usize,
+    pub registers: usize,
+}
 
-        for p in &f.params {
-            self.visit_param(p);
-        }
+impl BytecodeFunction {
+    pub fn label(&self, label: Label) -> usize {
+        *self.labels.get(&label).unwrap()
+    }
+
+    pub fn dump(&self, vm: &VM) {
+        let fct = vm.fcts.idx(self.fct_id);
+        let fct = fct.read();
+
+        println!(
+            "fun {} (arguments: {}, registers: {})",
+            fct.full_name(vm),
+            self.arguments,
+            self.registers
+        );
 
-        if let Some(ref ty) = f.return_type {
-            self.visit_type(ty);
+        for (btidx, btcode) in self.code.iter().enumerate() {
+            println!("{:4}: {}", btidx, self.format(vm, btcode));
         }
 
-        if let Some(ref block) = f.block {
-            self.visit_stmt(block);
+        for handler in &self.handlers {
+            let catch_type = match handler.catch_type {
+                Some(cls_id) => vm.class_defs.idx(cls_id).read().name(vm),
+                None => "*".into(),
+            };
+
+            print!(
+                "catch {}..{} => {} ({})",
+                handler.try_start, handler.try_end, handler.catch, catch_type
+            );
+
+            if let Some(Register(reg)) = handler.register {
+                print!(" r{}", reg);
+            }
+
+            println!();
         }
 
-        if self.code.len() == 0 || self.code.last().unwrap() != &Bytecode::Return {
-            self.code.push(Bytecode::ReturnVoid);
+        println!();
+    }
+
+    fn format(&self, vm: &VM, btcode: &Bytecode) -> String {
+        let cls_name = |cls_id: ClassDefId| vm.class_defs.idx(cls_id).read().name(vm);
+        let fct_name = |fct_id: FctId| vm.fcts.idx(fct_id).read().full_name(vm);
+
+        match *btcode {
+            Bytecode::Add(Register(register)) => format!("Add r{}", register),
+            Bytecode::ArrayLength => format!("ArrayLength"),
+            Bytecode::Assert => format!("Assert"),
+            Bytecode::BitwiseAnd(Register(register)) => format!("BitwiseAnd r{}", register),
+            Bytecode::BitwiseOr(Register(register)) => format!("BitwiseOr r{}", register),
+            Bytecode::BitwiseXor(Register(register)) => format!("BitwiseXor r{}", register),
+            Bytecode::CheckedCast(cls_id) => format!("CheckedCast {}", cls_name(cls_id)),
+            Bytecode::Conv(ty) => format!("Conv {}", ty.name()),
+            Bytecode::Div(Register(register)) => format!("Div r{}", register),
+            Bytecode::InstanceOf(cls_id) => format!("InstanceOf {}", cls_name(cls_id)),
+            Bytecode::InvokeStatic(fct_id, Register(start), count) => {
+                format!("InvokeStatic {} r{}, {}", fct_name(fct_id), start, count)
+            }
+            Bytecode::InvokeVirtual(fct_id, Register(start), count) => {
+                format!("InvokeVirtual {} r{}, {}", fct_name(fct_id), start, count)
+            }
+            Bytecode::Ldar(Register(register)) => format!("Ldar r{}", register),
+            Bytecode::LdaArray(Register(register), ty) => {
+                format!("LdaArray r{}, {}", register, ty.name())
+            }
+            Bytecode::LdaByte(value) => format!("LdaByte {}", value),
+            Bytecode::LdaChar(value) => format!("LdaChar {:?}", value),
+            Bytecode::LdaDouble(value) => format!("LdaDouble {}", value),
+            Bytecode::LdaFalse => format!("LdaFalse"),
+            Bytecode::LdaField(Register(register), cls_id, field_id) => format!(
+                "LdaField r{}, {}.{}",
+                register,
+                cls_name(cls_id),
+                field_id.idx()
+            ),
+            Bytecode::LdaFloat(value) => format!("LdaFloat {}", value),
+            Bytecode::LdaInt(value) => format!("LdaInt {}", value),
+            Bytecode::LdaLong(value) => format!("LdaLong {}", value),
+            Bytecode::LdaNil => format!("LdaNil"),
+            Bytecode::LdaStr(idx) => format!("LdaStr {:?}", self.strings[idx].to_cstring()),
+            Bytecode::LdaTrue => format!("LdaTrue"),
+            Bytecode::LdaZero => format!("LdaZero"),
+            Bytecode::LogicalNot => format!("LogicalNot"),
+            Bytecode::Star(Register(register)) => format!("Star r{}", register),
+            Bytecode::StaArray(Register(array), Register(index), ty) => {
+                format!("StaArray r{}, r{}, {}", array, index, ty.name())
+            }
+            Bytecode::StaField(Register(register), cls_id, field_id) => format!(
+                "StaField r{}, {}.{}",
+                register,
+                cls_name(cls_id),
+                field_id.idx()
+            ),
+            Bytecode::JumpIfFalse(label) => format!("JumpIfFalse {}", self.label(label)),
+            Bytecode::JumpIfTrue(label) => format!("JumpIfTrue {}", self.label(label)),
+            Bytecode::Jump(label) => format!("Jump {}", self.label(label)),
+            Bytecode::Mod(Register(register)) => format!("Mod r{}", register),
+            Bytecode::Mul(Register(register)) => format!("Mul r{}", register),
+            Bytecode::Neg => format!("Neg"),
+            Bytecode::NewArray(cls_id, Register(length), ty) => {
+                format!("NewArray {} r{}, {}", cls_name(cls_id), length, ty.name())
+            }
+            Bytecode::NewObject(cls_id) => format!("NewObject {}", cls_name(cls_id)),
+            Bytecode::ShiftLeft(Register(register)) => format!("ShiftLeft r{}", register),
+            Bytecode::ShiftRight(Register(register)) => format!("ShiftRight r{}", register),
+            Bytecode::Sub(Register(register)) => format!("Sub r{}", register),
+            Bytecode::Return => format!("Return"),
+            Bytecode::ReturnVoid => format!("ReturnVoid"),
+            Bytecode::TestEqual(Register(register)) => format!("TestEqual r{}", register),
+            Bytecode::TestGreatherThan(Register(register)) => {
+                format!("TestGreaterThan r{}", register)
+            }
+            Bytecode::TestGreatherThanOrEqual(Register(register)) => {
+                format!("TestGreatherThanOrEqual r{}", register)
+            }
+            Bytecode::TestLessThan(Register(register)) => format!("TestLessThan r{}", register),
+            Bytecode::TestLessThanOrEqual(Register(register)) => {
+                format!("TestLessThanOrEqual r{}", register)
+            }
+            Bytecode::TestNotEqual(Register(register)) => {
+                format!("TestNotEqual r{}", register)
+            }
+            Bytecode::Throw => format!("Throw"),
+            Bytecode::Trap(trap) => format!("Trap {:?}", trap),
+            Bytecode::UnsignedShiftRight(Register(register)) => {
+                format!("UnsignedShiftRight r{}", register)
+            }
         }
     }
 }
 
-impl BytecodeGen {
-    pub fn new() -> BytecodeGen {
+// generates bytecode for a type checked function, returns the position
+// of the first construct the bytecode does not support
+pub fn generate<'ast>(vm: &VM<'ast>, id: FctId) -> Result<BytecodeFunction, Position> {
+    let fct = vm.fcts.idx(id);
+    let fct = fct.read();
+
+    let src = match fct.kind {
+        FctKind::Source(ref src) => src,
+        _ => panic!("source function expected"),
+    };
+    let src = src.read();
+
+    BytecodeGen::new(vm, &fct, &src).generate()
+}
+
+pub struct BytecodeGen<'a, 'ast: 'a> {
+    vm: &'a VM<'ast>,
+    fct: &'a Fct<'ast>,
+    src: &'a FctSrc,
+
+    code: Vec<Bytecode>,
+    ctxs: Vec<Context>,
+    loops: Vec<LoopLabels>,
+    labels: HashMap<Label, usize>,
+    handlers: Vec<ExceptionHandler>,
+    strings: Vec<Ref<Str>>,
+    regs: usize,
+    max_regs: usize,
+    unsupported: Option<Position>,
+}
+
+impl<'a, 'ast> BytecodeGen<'a, 'ast> {
+    pub fn new(vm: &'a VM<'ast>, fct: &'a Fct<'ast>, src: &'a FctSrc) -> BytecodeGen<'a, 'ast> {
         BytecodeGen {
+            vm: vm,
+            fct: fct,
+            src: src,
+
             code: Vec::new(),
             ctxs: Vec::new(),
             loops: Vec::new(),
             labels: HashMap::new(),
+            handlers: Vec::new(),
+            strings: Vec::new(),
             regs: 0,
+            max_regs: 0,
+            unsupported: None,
         }
     }
 
-    pub fn gen(&mut self, ast: &Ast) {
-        self.visit_ast(ast);
-    }
+    pub fn generate(mut self) -> Result<BytecodeFunction, Position> {
+        let fct = self.fct;
 
-    pub fn dump(&self) {
-        let mut btidx = 0;
-        for btcode in self.code.iter() {
-            match btcode {
-                Bytecode::Add(Register(register)) => println!("{}: Add {}", btidx, register),
-                Bytecode::BitwiseAnd(Register(register)) => {
-                    println!("{}: BitwiseAnd {}", btidx, register)
-                }
-                Bytecode::BitwiseOr(Register(register)) => {
-                    println!("{}: BitwiseOr {}", btidx, register)
-                }
-                Bytecode::BitwiseXor(Register(register)) => {
-                    println!("{}: BitwiseXor {}", btidx, register)
-                }
-                Bytecode::Div(Register(register)) => println!("{}: Div {}", btidx, register),
-                Bytecode::Ldar(Register(register)) => println!("{}: Ldar {}", btidx, register),
-                Bytecode::LdaInt(value) => println!("{}: LdaInt {}", btidx, value),
-                Bytecode::LdaZero => println!("{}: LdaZero", btidx),
-                Bytecode::LogicalNot => println!("{}: LogicalNot", btidx),
-                Bytecode::Star(Register(register)) => println!("{}: Star {}", btidx, register),
-                Bytecode::JumpIfFalse(label) => {
-                    println!("{}: JumpIfFalse {}", btidx, self.labels.get(label).unwrap())
-                }
-                Bytecode::Jump(label) => {
-                    println!("{}: Jump {}", btidx, self.labels.get(label).unwrap())
-                }
-                Bytecode::Mod(Register(register)) => println!("{}: Mod {}", btidx, register),
-                Bytecode::Mul(Register(register)) => println!("{}: Mul {}", btidx, register),
-                Bytecode::Neg => println!("{}: Neg", btidx),
-                Bytecode::ShiftLeft(Register(register)) => {
-                    println!("{}: ShiftLeft {}", btidx, register)
-                }
-                Bytecode::ShiftRight(Register(register)) => {
-                    println!("{}: ShiftRight {}", btidx, register)
-                }
-                Bytecode::Sub(Register(register)) => println!("{}: Sub {}", btidx, register),
-                Bytecode::Return => println!("{}: Return", btidx),
-                Bytecode::ReturnVoid => println!("{}: ReturnVoid", btidx),
-                Bytecode::TestEqual(Register(register)) => {
-                    println!("{}: TestEqual {}", btidx, register)
-                }
-                Bytecode::TestGreatherThan(Register(register)) => {
-                    println!("{}: TestGreaterThan {}", btidx, register)
-                }
-                Bytecode::TestGreatherThanOrEqual(Register(register)) => {
-                    println!("{}: TestGreatherThanOrEqual {}", btidx, register)
-                }
-                Bytecode::TestLessThan(Register(register)) => {
-                    println!("{}: TestLessThan {}", btidx, register)
-                }
-                Bytecode::TestLessThanOrEqual(Register(register)) => {
-                    println!("{}: TestLessThanOrEqual {}", btidx, register)
-                }
-                Bytecode::TestNotEqual(Register(register)) => {
-                    println!("{}: TestNotEqual {}", btidx, register)
-                }
-            }
-            btidx = btidx + 1;
+        if !is_supported_fct(self.vm, fct) {
+            return Err(fct.pos);
+        }
+
+        self.ctxs.push(Context::new());
+
+        if fct.has_self() {
+            let var = self.src.var_self().id;
+            self.new_var(var);
+        }
+
+        for p in &fct.ast.params {
+            let var = *self.src.map_vars.get(p.id).unwrap();
+            self.new_var(var);
+        }
+
+        let arguments = self.regs;
+
+        if let Some(ref block) = fct.ast.block {
+            self.visit_stmt(block);
         }
+
+        // some label might still point behind the last instruction
+        let end = self.code.len();
+        if self.code.last() != Some(&Bytecode::Return) || self.labels.values().any(|&l| l == end) {
+            self.code.push(Bytecode::ReturnVoid);
+        }
+
+        if let Some(pos) = self.unsupported {
+            return Err(pos);
+        }
+
+        Ok(BytecodeFunction {
+            fct_id: fct.id,
+            code: self.code,
+            labels: self.labels,
+            handlers: self.handlers,
+            strings: self.strings,
+            arguments: arguments,
+            registers: self.max_regs,
+        })
     }
 
-    pub fn get_reg(&self, var: Name) -> Option<&Register> {
-        for ctx in self.ctxs.iter() {
+    pub fn get_reg(&self, var: VarId) -> Option<&Register> {
+        for ctx in self.ctxs.iter().rev() {
             let opt = ctx.get_reg(var);
             if opt.is_some() {
                 return opt;
@@ -193,7 +414,75 @@ impl BytecodeGen {
         None
     }
 
-    // TODO - implement other statements
+    // variables declared by unsupported statements have no register
+    fn var_reg(&self, var: VarId) -> Option<Register> {
+        self.get_reg(var).cloned()
+    }
+
+    fn self_reg(&self) -> Register {
+        self.var_reg(self.src.var_self().id).unwrap()
+    }
+
+    fn new_var(&mut self, var: VarId) -> Register {
+        let reg = self.alloc_regs(1);
+        self.ctxs.last_mut().unwrap().new_var(var, reg);
+
+        reg
+    }
+
+    // registers are allocated like a stack, `self.regs` is reset to free them again
+    fn alloc_regs(&mut self, count: usize) -> Register {
+        let reg = Register(self.regs);
+        self.regs += count;
+
+        if self.regs > self.max_regs {
+            self.max_regs = self.regs;
+        }
+
+        reg
+    }
+
+    fn create_label(&mut self) -> Label {
+        let label = Label(self.labels.len());
+        self.labels.insert(label, 0); // Just a place holder
+
+        label
+    }
+
+    fn bind_label(&mut self, label: Label) {
+        self.labels.insert(label, self.code.len());
+    }
+
+    fn unsupported(&mut self, pos: Position) {
+        if self.unsupported.is_none() {
+            self.unsupported = Some(pos);
+        }
+    }
+
+    fn ty(&self, id: NodeId) -> BuiltinType {
+        self.src.ty(id)
+    }
+
+    fn bytecode_type(&mut self, ty: BuiltinType, pos: Position) -> BytecodeType {
+        match BytecodeType::from_ty(ty) {
+            Some(ty) => ty,
+            None => {
+                self.unsupported(pos);
+                BytecodeType::Ptr
+            }
+        }
+    }
+
+    fn intrinsic(&self, fct_id: FctId) -> Option<Intrinsic> {
+        let fct = self.vm.fcts.idx(fct_id);
+        let fct = fct.read();
+
+        match fct.kind {
+            FctKind::Builtin(intrinsic) => Some(intrinsic),
+            _ => None,
+        }
+    }
+
     fn visit_stmt(&mut self, stmt: &Stmt) {
         match *stmt {
             StmtBlock(ref block) => self.visit_block(block),
@@ -204,71 +493,89 @@ impl BytecodeGen {
             StmtIf(ref stmt) => self.visit_stmt_if(stmt),
             StmtVar(ref stmt) => self.visit_stmt_var(stmt),
             StmtWhile(ref stmt) => self.visit_stmt_while(stmt),
-            // StmtLoop(ref stmt) => {},
-            // StmtThrow(ref stmt) => {},
-            // StmtDefer(ref stmt) => {},
-            // StmtDo(ref stmt) => {},
-            // StmtSpawn(ref stmt) => {},
-            // StmtFor(ref stmt) => {},
-            _ => unimplemented!(),
+            StmtLoop(ref stmt) => self.visit_stmt_loop(stmt),
+            StmtThrow(ref stmt) => self.visit_stmt_throw(stmt),
+            StmtDo(ref stmt) => self.visit_stmt_do(stmt),
+            _ => self.unsupported(stmt.pos()),
         }
     }
 
     fn visit_stmt_var(&mut self, stmt: &StmtVarType) {
-        let reg = self.regs;
-        let varid = stmt.name;
-        self.regs += 1;
-        self.ctxs
-            .last_mut()
-            .unwrap()
-            .new_var(varid as Name, Register(reg));
+        let var = *self.src.map_vars.get(stmt.id).unwrap();
+        let ty = self.src.vars[var].ty;
+        let reg = self.new_var(var);
 
         if let Some(ref expr) = stmt.expr {
             self.visit_expr(expr);
         } else {
-            self.code.push(Bytecode::LdaZero);
+            self.visit_default_value(ty, stmt.pos);
+        };
+        self.code.push(Bytecode::Star(reg));
+    }
+
+    fn visit_default_value(&mut self, ty: BuiltinType, pos: Position) {
+        let bytecode = match self.bytecode_type(ty, pos) {
+            BytecodeType::Bool => Bytecode::LdaFalse,
+            BytecodeType::Byte => Bytecode::LdaByte(0),
+            BytecodeType::Char => Bytecode::LdaChar('\0'),
+            BytecodeType::Int => Bytecode::LdaZero,
+            BytecodeType::Long => Bytecode::LdaLong(0),
+            BytecodeType::Float => Bytecode::LdaFloat(0.0),
+            BytecodeType::Double => Bytecode::LdaDouble(0.0),
+            BytecodeType::Ptr => Bytecode::LdaNil,
         };
-        self.code.push(Bytecode::Star(Register(reg)));
+
+        self.code.push(bytecode);
     }
 
     fn visit_stmt_while(&mut self, stmt: &StmtWhileType) {
-        let cond_lbl = self.labels.len();
-        let end_lbl = cond_lbl + 1;
+        let cond_lbl = self.create_label();
+        let end_lbl = self.create_label();
         self.loops.push(LoopLabels {
-            cond: Label(cond_lbl),
-            end: Label(end_lbl),
+            cond: cond_lbl,
+            end: end_lbl,
         });
 
-        self.labels.insert(Label(cond_lbl), self.code.len());
-        self.labels.insert(Label(end_lbl), 0); // Just a place holder
-
+        self.bind_label(cond_lbl);
         self.visit_expr(&stmt.cond);
-        self.code.push(Bytecode::JumpIfFalse(Label(end_lbl)));
+        self.code.push(Bytecode::JumpIfFalse(end_lbl));
         self.visit_stmt(&stmt.block);
-        self.code.push(Bytecode::Jump(Label(cond_lbl)));
-        self.labels.insert(Label(end_lbl), self.code.len());
+        self.code.push(Bytecode::Jump(cond_lbl));
+        self.bind_label(end_lbl);
         self.loops.pop();
     }
 
-    fn visit_stmt_if(&mut self, stmt: &StmtIfType) {
-        let else_lbl = self.labels.len();
-        let end_lbl = else_lbl + 1;
+    fn visit_stmt_loop(&mut self, stmt: &StmtLoopType) {
+        let cond_lbl = self.create_label();
+        let end_lbl = self.create_label();
+        self.loops.push(LoopLabels {
+            cond: cond_lbl,
+            end: end_lbl,
+        });
+
+        self.bind_label(cond_lbl);
+        self.visit_stmt(&stmt.block);
+        self.code.push(Bytecode::Jump(cond_lbl));
+        self.bind_label(end_lbl);
+        self.loops.pop();
+    }
 
-        self.labels.insert(Label(else_lbl), 0); // Just a place holder
-        self.labels.insert(Label(end_lbl), 0); // Just a place holder
+    fn visit_stmt_if(&mut self, stmt: &StmtIfType) {
+        let else_lbl = self.create_label();
+        let end_lbl = self.create_label();
 
         self.visit_expr(&stmt.cond);
-        self.code.push(Bytecode::JumpIfFalse(Label(else_lbl)));
+        self.code.push(Bytecode::JumpIfFalse(else_lbl));
         self.visit_stmt(&stmt.then_block);
-        self.code.push(Bytecode::Jump(Label(end_lbl)));
-        self.labels.insert(Label(else_lbl), self.code.len());
+        self.code.push(Bytecode::Jump(end_lbl));
+        self.bind_label(else_lbl);
         match &stmt.else_block {
             Some(else_block) => {
                 self.visit_stmt(&else_block);
             }
             _ => {}
         }
-        self.labels.insert(Label(end_lbl), self.code.len());
+        self.bind_label(end_lbl);
     }
 
     fn visit_stmt_expr(&mut self, stmt: &StmtExprType) {
@@ -288,122 +595,875 @@ impl BytecodeGen {
     fn visit_stmt_return(&mut self, ret: &StmtReturnType) {
         if let Some(ref expr) = ret.expr {
             self.visit_expr(expr);
+            self.code.push(Bytecode::Return);
+        } else {
+            self.code.push(Bytecode::ReturnVoid);
+        }
+    }
+
+    fn visit_stmt_break(&mut self, _stmt: &StmtBreakType) {
+        let end = self.loops.last().unwrap().end;
+        self.code.push(Bytecode::Jump(end));
+    }
+
+    fn visit_stmt_continue(&mut self, _stmt: &StmtContinueType) {
+        let cond = self.loops.last().unwrap().cond;
+        self.code.push(Bytecode::Jump(cond));
+    }
+
+    fn visit_stmt_throw(&mut self, stmt: &StmtThrowType) {
+        self.visit_expr(&stmt.expr);
+        self.code.push(Bytecode::Throw);
+    }
+
+    fn visit_stmt_do(&mut self, stmt: &StmtDoType) {
+        if stmt.finally_block.is_some() {
+            self.unsupported(stmt.pos);
+            return;
+        }
+
+        let end_lbl = self.create_label();
+
+        let try_start = self.code.len();
+        self.visit_stmt(&stmt.do_block);
+        let try_end = self.code.len();
+        self.code.push(Bytecode::Jump(end_lbl));
+
+        for catch in &stmt.catch_blocks {
+            let regs = self.regs;
+            self.ctxs.push(Context::new());
+
+            let var = *self.src.map_vars.get(catch.id).unwrap();
+            let reg = self.new_var(var);
+            let ty = self.ty(catch.data_type.id());
+            let cls_id = specialize_class_ty(self.vm, ty);
+
+            self.handlers.push(ExceptionHandler {
+                try_start: try_start,
+                try_end: try_end,
+                catch: self.code.len(),
+                catch_type: Some(cls_id),
+                register: Some(reg),
+            });
+
+            self.visit_stmt(&catch.block);
+            self.code.push(Bytecode::Jump(end_lbl));
+
+            self.ctxs.pop();
+            self.regs = regs;
+        }
+
+        self.bind_label(end_lbl);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        match *expr {
+            ExprUn(ref un) => self.visit_expr_un(un),
+            ExprBin(ref bin) => self.visit_expr_bin(bin),
+            ExprField(ref field) => self.visit_expr_field(field),
+            ExprArray(ref array) => self.visit_expr_array(array),
+            ExprLitChar(ref lit) => self.code.push(Bytecode::LdaChar(lit.value)),
+            ExprLitInt(ref lit) => self.visit_expr_lit_int(lit),
+            ExprLitFloat(ref lit) => self.visit_expr_lit_float(lit),
+            ExprLitStr(ref lit) => self.visit_expr_lit_str(lit),
+            ExprLitBool(ref lit) => self.visit_expr_lit_bool(lit),
+            ExprIdent(ref ident) => self.visit_expr_ident(ident),
+            ExprAssign(ref assign) => self.visit_expr_assign(assign),
+            ExprCall(ref call) => self.visit_expr_call(call),
+            ExprDelegation(ref call) => self.visit_expr_delegation(call),
+            ExprSelf(_) | ExprSuper(_) => {
+                let reg = self.self_reg();
+                self.code.push(Bytecode::Ldar(reg));
+            }
+            ExprNil(_) => self.code.push(Bytecode::LdaNil),
+            ExprConv(ref expr) => self.visit_expr_conv(expr),
+            ExprTry(ref expr) => self.visit_expr_try(expr),
+            _ => self.unsupported(expr.pos()),
+        }
+    }
+
+    fn visit_expr_lit_int(&mut self, lit: &ExprLitIntType) {
+        match self.ty(lit.id) {
+            BuiltinType::Byte => self.code.push(Bytecode::LdaByte(lit.value as u8)),
+            BuiltinType::Long => self.code.push(Bytecode::LdaLong(lit.value)),
+            _ if lit.value == 0 => self.code.push(Bytecode::LdaZero),
+            _ => self.code.push(Bytecode::LdaInt(lit.value)),
+        }
+    }
+
+    fn visit_expr_lit_float(&mut self, lit: &ExprLitFloatType) {
+        match self.ty(lit.id) {
+            BuiltinType::Float => self.code.push(Bytecode::LdaFloat(lit.value as f32)),
+            _ => self.code.push(Bytecode::LdaDouble(lit.value)),
+        }
+    }
+
+    fn visit_expr_lit_str(&mut self, lit: &ExprLitStrType) {
+        let handle = Str::from_buffer_in_perm(self.vm, lit.value.as_bytes());
+        let idx = self.strings.len();
+        self.strings.push(handle);
+
+        self.code.push(Bytecode::LdaStr(idx));
+    }
+
+    fn visit_expr_lit_bool(&mut self, lit: &ExprLitBoolType) {
+        if lit.value {
+            self.code.push(Bytecode::LdaTrue);
+        } else {
+            self.code.push(Bytecode::LdaFalse);
+        }
+    }
+
+    fn visit_const(&mut self, const_id: ConstId) {
+        let xconst = self.vm.consts.idx(const_id);
+        let xconst = xconst.lock();
+
+        let bytecode = match (xconst.ty, &xconst.value) {
+            (BuiltinType::Bool, &ConstValue::Bool(true)) => Bytecode::LdaTrue,
+            (BuiltinType::Bool, &ConstValue::Bool(false)) => Bytecode::LdaFalse,
+            (BuiltinType::Char, &ConstValue::Char(value)) => Bytecode::LdaChar(value),
+            (BuiltinType::Byte, &ConstValue::Int(value)) => Bytecode::LdaByte(value as u8),
+            (BuiltinType::Int, &ConstValue::Int(value)) => Bytecode::LdaInt(value as u32 as u64),
+            (BuiltinType::Long, &ConstValue::Int(value)) => Bytecode::LdaLong(value as u64),
+            (BuiltinType::Float, &ConstValue::Float(value)) => Bytecode::LdaFloat(value as f32),
+            (BuiltinType::Double, &ConstValue::Float(value)) => Bytecode::LdaDouble(value),
+            _ => unreachable!(),
+        };
+
+        self.code.push(bytecode);
+    }
+
+    fn visit_expr_un(&mut self, expr: &ExprUnType) {
+        let fct_id = self.src.map_calls.get(expr.id).unwrap().fct_id();
+
+        if self.intrinsic(fct_id).is_none() {
+            self.visit_call(expr.id, expr.pos, fct_id, Some(&*expr.opnd), &[], false);
+            return;
+        }
+
+        self.visit_expr(&expr.opnd);
+        match expr.op {
+            UnOp::Plus => {}
+            UnOp::Neg => self.code.push(Bytecode::Neg),
+            UnOp::Not => self.code.push(Bytecode::LogicalNot),
+        }
+    }
+
+    fn visit_expr_bin(&mut self, expr: &ExprBinType) {
+        match expr.op {
+            BinOp::Or => {
+                self.visit_expr_bin_cond(expr, true);
+                return;
+            }
+            BinOp::And => {
+                self.visit_expr_bin_cond(expr, false);
+                return;
+            }
+            BinOp::Cmp(CmpOp::Is) | BinOp::Cmp(CmpOp::IsNot) => {}
+            _ if self.ty(expr.lhs.id()).is_enum() => {
+                self.unsupported(expr.pos);
+                return;
+            }
+            _ => {
+                let fct_id = self.src.map_calls.get(expr.id).unwrap().fct_id();
+
+                if self.intrinsic(fct_id).is_none() {
+                    self.visit_expr_bin_call(expr, fct_id);
+                    return;
+                }
+            }
+        }
+
+        self.visit_expr(&expr.lhs);
+        let lhs_reg = self.alloc_regs(1);
+        self.code.push(Bytecode::Star(lhs_reg));
+        self.visit_expr(&expr.rhs);
+        let bytecode = match expr.op {
+            BinOp::Cmp(op) => cmp_op(op, lhs_reg),
+            op => arith_op(op, lhs_reg),
+        };
+        self.code.push(bytecode);
+        self.regs -= 1;
+    }
+
+    // `a || b` evaluates `b` only if `a` is false, `a && b` only if `a` is true
+    fn visit_expr_bin_cond(&mut self, expr: &ExprBinType, is_or: bool) {
+        let end_lbl = self.create_label();
+
+        self.visit_expr(&expr.lhs);
+        if is_or {
+            self.code.push(Bytecode::JumpIfTrue(end_lbl));
+        } else {
+            self.code.push(Bytecode::JumpIfFalse(end_lbl));
+        }
+        self.visit_expr(&expr.rhs);
+        self.bind_label(end_lbl);
+    }
+
+    // operators implemented in Dora return the result of `equals` or `compareTo`
+    fn visit_expr_bin_call(&mut self, expr: &ExprBinType, fct_id: FctId) {
+        self.visit_call(
+            expr.id,
+            expr.pos,
+            fct_id,
+            Some(&*expr.lhs),
+            &[&*expr.rhs],
+            false,
+        );
+
+        match expr.op {
+            BinOp::Cmp(CmpOp::Eq) => {}
+            BinOp::Cmp(CmpOp::Ne) => self.code.push(Bytecode::LogicalNot),
+            BinOp::Cmp(op) => {
+                let result_reg = self.alloc_regs(1);
+                self.code.push(Bytecode::Star(result_reg));
+                self.code.push(Bytecode::LdaZero);
+                self.code.push(cmp_op(op, result_reg));
+                self.regs -= 1;
+            }
+            _ => {}
+        }
+    }
+
+    fn visit_expr_field(&mut self, expr: &ExprFieldType) {
+        let (ty, field_id) = match *self.src.map_idents.get(expr.id).unwrap() {
+            IdentType::Field(ty, field_id) => (ty, field_id),
+            _ => {
+                self.unsupported(expr.pos);
+                return;
+            }
+        };
+
+        let cls_id = self.field_class(ty, field_id, expr.pos);
+        self.visit_expr(&expr.object);
+        let obj_reg = self.alloc_regs(1);
+        self.code.push(Bytecode::Star(obj_reg));
+        self.code
+            .push(Bytecode::LdaField(obj_reg, cls_id, field_id));
+        self.regs -= 1;
+    }
+
+    // fields of value types other than primitives are stored inline
+    fn field_class(&mut self, ty: BuiltinType, field_id: FieldId, pos: Position) -> ClassDefId {
+        let cls_id = specialize_class_ty(self.vm, ty);
+        let cls = self.vm.class_defs.idx(cls_id);
+        let field_ty = cls.read().fields[field_id.idx()].ty;
+        self.bytecode_type(field_ty, pos);
+
+        cls_id
+    }
+
+    fn visit_expr_array(&mut self, expr: &ExprArrayType) {
+        let fct_id = self.src.map_calls.get(expr.id).unwrap().fct_id();
+
+        if self.intrinsic(fct_id).is_none() {
+            self.visit_call(
+                expr.id,
+                expr.pos,
+                fct_id,
+                Some(&*expr.object),
+                &[&*expr.index],
+                false,
+            );
+            return;
+        }
+
+        self.visit_expr(&expr.object);
+        let array_reg = self.alloc_regs(1);
+        self.code.push(Bytecode::Star(array_reg));
+        self.visit_expr(&expr.index);
+        let ty = self.ty(expr.id);
+        let ty = self.bytecode_type(ty, expr.pos);
+        self.code.push(Bytecode::LdaArray(array_reg, ty));
+        self.regs -= 1;
+    }
+
+    fn visit_expr_ident(&mut self, ident: &ExprIdentType) {
+        match *self.src.map_idents.get(ident.id).unwrap() {
+            IdentType::Var(var) => match self.var_reg(var) {
+                Some(reg) => self.code.push(Bytecode::Ldar(reg)),
+                None => self.unsupported(ident.pos),
+            },
+
+            IdentType::Field(ty, field_id) => {
+                let cls_id = self.field_class(ty, field_id, ident.pos);
+                let reg = self.self_reg();
+                self.code.push(Bytecode::LdaField(reg, cls_id, field_id));
+            }
+
+            IdentType::Const(const_id) => self.visit_const(const_id),
+
+            _ => self.unsupported(ident.pos),
+        }
+    }
+
+    fn visit_expr_assign(&mut self, expr: &ExprAssignType) {
+        match *expr.lhs {
+            ExprIdent(ref ident) => match *self.src.map_idents.get(ident.id).unwrap() {
+                IdentType::Var(var) => match self.var_reg(var) {
+                    Some(reg) => {
+                        self.visit_expr(&expr.rhs);
+
+                        if let Some(op) = expr.op {
+                            self.visit_assign_op(op, reg, expr.pos);
+                        }
+
+                        self.code.push(Bytecode::Star(reg));
+                    }
+
+                    None => self.unsupported(expr.pos),
+                },
+
+                IdentType::Field(ty, field_id) => {
+                    let reg = self.self_reg();
+                    self.visit_assign_field(expr, reg, ty, field_id);
+                }
+
+                _ => self.unsupported(expr.pos),
+            },
+
+            ExprField(ref field) => match *self.src.map_idents.get(field.id).unwrap() {
+                IdentType::Field(ty, field_id) => {
+                    self.visit_expr(&field.object);
+                    let obj_reg = self.alloc_regs(1);
+                    self.code.push(Bytecode::Star(obj_reg));
+                    self.visit_assign_field(expr, obj_reg, ty, field_id);
+                    self.regs -= 1;
+                }
+
+                _ => self.unsupported(expr.pos),
+            },
+
+            ExprArray(ref array) => self.visit_assign_array(expr, array),
+
+            _ => self.unsupported(expr.pos),
+        }
+    }
+
+    fn visit_assign_field(
+        &mut self,
+        expr: &ExprAssignType,
+        obj_reg: Register,
+        ty: BuiltinType,
+        field_id: FieldId,
+    ) {
+        let cls_id = self.field_class(ty, field_id, expr.pos);
+
+        if let Some(op) = expr.op {
+            let current_reg = self.alloc_regs(1);
+            self.code
+                .push(Bytecode::LdaField(obj_reg, cls_id, field_id));
+            self.code.push(Bytecode::Star(current_reg));
+            self.visit_expr(&expr.rhs);
+            self.visit_assign_op(op, current_reg, expr.pos);
+            self.regs -= 1;
+        } else {
+            self.visit_expr(&expr.rhs);
+        }
+
+        self.code
+            .push(Bytecode::StaField(obj_reg, cls_id, field_id));
+    }
+
+    fn visit_assign_array(&mut self, expr: &ExprAssignType, array: &ExprArrayType) {
+        let set_id = self.src.map_calls.get(expr.id).unwrap().fct_id();
+
+        // array, index and value are the arguments of `set`
+        let start = self.regs;
+        let array_reg = self.alloc_regs(3);
+        let index_reg = Register(start + 1);
+        let value_reg = Register(start + 2);
+
+        self.visit_expr(&array.object);
+        self.code.push(Bytecode::Star(array_reg));
+        self.visit_expr(&array.index);
+        self.code.push(Bytecode::Star(index_reg));
+
+        let ty = self.ty(array.id);
+
+        if let Some(op) = expr.op {
+            let get_id = self.src.map_calls.get(array.id).unwrap().fct_id();
+
+            if self.intrinsic(get_id).is_some() {
+                let element_ty = self.bytecode_type(ty, array.pos);
+                self.code.push(Bytecode::Ldar(index_reg));
+                self.code.push(Bytecode::LdaArray(array_reg, element_ty));
+            } else {
+                let args = self.alloc_regs(2);
+                self.code.push(Bytecode::Ldar(array_reg));
+                self.code.push(Bytecode::Star(args));
+                self.code.push(Bytecode::Ldar(index_reg));
+                self.code.push(Bytecode::Star(Register(args.0 + 1)));
+                self.emit_invoke(get_id, args, 2, false, array.pos);
+                self.regs -= 2;
+            }
+
+            let current_reg = self.alloc_regs(1);
+            self.code.push(Bytecode::Star(current_reg));
+            self.visit_expr(&expr.rhs);
+            self.visit_assign_op(op, current_reg, expr.pos);
+            self.regs -= 1;
+        } else {
+            self.visit_expr(&expr.rhs);
+        }
+
+        if self.intrinsic(set_id).is_some() {
+            let element_ty = self.bytecode_type(ty, expr.pos);
+            self.code
+                .push(Bytecode::StaArray(array_reg, index_reg, element_ty));
+        } else {
+            self.code.push(Bytecode::Star(value_reg));
+            self.emit_invoke(set_id, array_reg, 3, false, expr.pos);
+        }
+
+        self.regs = start;
+    }
+
+    // expects the right-hand side in the accumulator and the current value
+    // of the assigned location in `current_reg`
+    fn visit_assign_op(&mut self, op: AssignOp, current_reg: Register, pos: Position) {
+        let fct_id = self.src.map_calls.get(op.id).unwrap().fct_id();
+
+        if self.intrinsic(fct_id).is_some() {
+            self.code.push(arith_op(op.op, current_reg));
+        } else {
+            let args = self.alloc_regs(2);
+            self.code.push(Bytecode::Star(Register(args.0 + 1)));
+            self.code.push(Bytecode::Ldar(current_reg));
+            self.code.push(Bytecode::Star(args));
+            self.emit_invoke(fct_id, args, 2, false, pos);
+            self.regs -= 2;
+        }
+    }
+
+    fn visit_expr_call(&mut self, expr: &ExprCallType) {
+        let call_type = self.src.map_calls.get(expr.id).unwrap().clone();
+        let args = expr.args.iter().map(|arg| &**arg).collect::<Vec<_>>();
+
+        match *call_type {
+            CallType::CtorNew(cls_id, fct_id, _) => {
+                let is_array = self.vm.classes.idx(cls_id).read().is_array;
+
+                if is_array {
+                    self.visit_array_ctor(expr);
+                } else {
+                    self.visit_ctor_new(expr, fct_id);
+                }
+            }
+
+            CallType::Method(_, fct_id, _) => {
+                let object = expr.object.as_ref().unwrap();
+                let is_super = match **object {
+                    ExprSuper(_) => true,
+                    _ => false,
+                };
+
+                self.visit_call(expr.id, expr.pos, fct_id, Some(&**object), &args, is_super);
+            }
+
+            CallType::Fct(fct_id, _, _) => {
+                self.visit_call(expr.id, expr.pos, fct_id, None, &args, false);
+            }
+
+            CallType::Ctor(_, _, _) | CallType::Lambda(_) | CallType::EnumValue(_, _) => {
+                self.unsupported(expr.pos);
+            }
+        }
+    }
+
+    fn visit_ctor_new(&mut self, expr: &ExprCallType, fct_id: FctId) {
+        let cls_id = specialize_class_ty(self.vm, self.ty(expr.id));
+
+        match self.vm.class_defs.idx(cls_id).read().size {
+            ClassSize::Fixed(_) => {}
+            _ => {
+                self.unsupported(expr.pos);
+                return;
+            }
+        }
+
+        // the new object is passed as first argument and is the result of the call
+        let start = self.regs;
+        let obj_reg = self.alloc_regs(1 + expr.args.len());
+        self.code.push(Bytecode::NewObject(cls_id));
+        self.code.push(Bytecode::Star(obj_reg));
+
+        for (idx, arg) in expr.args.iter().enumerate() {
+            self.visit_expr(arg);
+            self.code.push(Bytecode::Star(Register(start + 1 + idx)));
+        }
+
+        self.emit_invoke(fct_id, obj_reg, 1 + expr.args.len(), true, expr.pos);
+        self.code.push(Bytecode::Ldar(obj_reg));
+        self.regs = start;
+    }
+
+    // arrays are allocated with their length, all elements are initialized
+    // with the given value or the default value of the element type
+    fn visit_array_ctor(&mut self, expr: &ExprCallType) {
+        let ty = self.ty(expr.id);
+        let cls_id = specialize_class_ty(self.vm, ty);
+        let element_ty = ty.type_params(self.vm)[0];
+        let bytecode_ty = self.bytecode_type(element_ty, expr.pos);
+
+        let length_reg = self.alloc_regs(1);
+
+        if expr.args.is_empty() {
+            self.code.push(Bytecode::LdaZero);
+        } else {
+            self.visit_expr(&expr.args[0]);
+        }
+
+        self.code.push(Bytecode::Star(length_reg));
+
+        if expr.args.len() > 1 {
+            self.visit_expr(&expr.args[1]);
+        } else {
+            self.visit_default_value(element_ty, expr.pos);
+        }
+
+        self.code
+            .push(Bytecode::NewArray(cls_id, length_reg, bytecode_ty));
+        self.regs -= 1;
+    }
+
+    fn visit_expr_delegation(&mut self, expr: &ExprDelegationType) {
+        let fct_id = self.src.map_calls.get(expr.id).unwrap().fct_id();
+
+        let start = self.regs;
+        let obj_reg = self.alloc_regs(1 + expr.args.len());
+        let self_reg = self.self_reg();
+        self.code.push(Bytecode::Ldar(self_reg));
+        self.code.push(Bytecode::Star(obj_reg));
+
+        for (idx, arg) in expr.args.iter().enumerate() {
+            self.visit_expr(arg);
+            self.code.push(Bytecode::Star(Register(start + 1 + idx)));
+        }
+
+        self.emit_invoke(fct_id, obj_reg, 1 + expr.args.len(), true, expr.pos);
+        self.regs = start;
+    }
+
+    fn visit_call(
+        &mut self,
+        id: NodeId,
+        pos: Position,
+        fct_id: FctId,
+        object: Option<&Expr>,
+        args: &[&Expr],
+        is_super: bool,
+    ) {
+        if let Some(intrinsic) = self.intrinsic(fct_id) {
+            let mut operands = Vec::new();
+            operands.extend(object);
+            operands.extend_from_slice(args);
+
+            self.visit_intrinsic(id, pos, intrinsic, &operands);
+            return;
+        }
+
+        // all arguments are stored in consecutive registers
+        let start = self.regs;
+        let count = object.iter().count() + args.len();
+        let args_reg = self.alloc_regs(count);
+
+        for (idx, arg) in object.into_iter().chain(args.iter().cloned()).enumerate() {
+            self.visit_expr(arg);
+            self.code.push(Bytecode::Star(Register(start + idx)));
+        }
+
+        self.emit_invoke(fct_id, args_reg, count, is_super, pos);
+        self.regs = start;
+    }
+
+    fn emit_invoke(
+        &mut self,
+        fct_id: FctId,
+        start: Register,
+        count: usize,
+        is_static: bool,
+        pos: Position,
+    ) {
+        let fct = self.vm.fcts.idx(fct_id);
+        let fct = fct.read();
+
+        match fct.kind {
+            FctKind::Source(_) | FctKind::Native(_) if is_supported_fct(self.vm, &fct) => {}
+            FctKind::Definition if fct.is_virtual() && !is_static => {}
+            _ => {
+                self.unsupported(pos);
+                return;
+            }
+        }
+
+        if fct.is_virtual() && !is_static {
+            self.code
+                .push(Bytecode::InvokeVirtual(fct_id, start, count));
+        } else {
+            self.code.push(Bytecode::InvokeStatic(fct_id, start, count));
+        }
+    }
+
+    fn visit_intrinsic(&mut self, id: NodeId, pos: Position, intrinsic: Intrinsic, ops: &[&Expr]) {
+        match intrinsic {
+            Intrinsic::Assert => {
+                self.visit_expr(ops[0]);
+                self.code.push(Bytecode::Assert);
+            }
+
+            Intrinsic::IntPlus
+            | Intrinsic::LongPlus
+            | Intrinsic::FloatPlus
+            | Intrinsic::DoublePlus => self.visit_expr(ops[0]),
+
+            Intrinsic::IntNeg | Intrinsic::LongNeg | Intrinsic::FloatNeg | Intrinsic::DoubleNeg => {
+                self.visit_expr(ops[0]);
+                self.code.push(Bytecode::Neg);
+            }
+
+            Intrinsic::IntNot | Intrinsic::LongNot | Intrinsic::BoolNot | Intrinsic::ByteNot => {
+                self.visit_expr(ops[0]);
+                self.code.push(Bytecode::LogicalNot);
+            }
+
+            Intrinsic::GenericArrayLen
+            | Intrinsic::StrLen
+            | Intrinsic::FloatArrayLen
+            | Intrinsic::DoubleArrayLen => {
+                self.visit_expr(ops[0]);
+                self.code.push(Bytecode::ArrayLength);
+            }
+
+            Intrinsic::GenericArrayGet
+            | Intrinsic::StrGet
+            | Intrinsic::FloatArrayGet
+            | Intrinsic::DoubleArrayGet => {
+                self.visit_expr(ops[0]);
+                let array_reg = self.alloc_regs(1);
+                self.code.push(Bytecode::Star(array_reg));
+                self.visit_expr(ops[1]);
+                let ty = self.ty(id);
+                let ty = self.bytecode_type(ty, pos);
+                self.code.push(Bytecode::LdaArray(array_reg, ty));
+                self.regs -= 1;
+            }
+
+            Intrinsic::GenericArraySet
+            | Intrinsic::StrSet
+            | Intrinsic::FloatArraySet
+            | Intrinsic::DoubleArraySet => {
+                let array_reg = self.alloc_regs(2);
+                let index_reg = Register(array_reg.0 + 1);
+                self.visit_expr(ops[0]);
+                self.code.push(Bytecode::Star(array_reg));
+                self.visit_expr(ops[1]);
+                self.code.push(Bytecode::Star(index_reg));
+                self.visit_expr(ops[2]);
+                let ty = self.ty(ops[2].id());
+                let ty = self.bytecode_type(ty, pos);
+                self.code.push(Bytecode::StaArray(array_reg, index_reg, ty));
+                self.regs -= 2;
+            }
+
+            _ => {
+                if let Some(ty) = conv_type(intrinsic) {
+                    self.visit_expr(ops[0]);
+                    self.code.push(Bytecode::Conv(ty));
+                } else if ops.len() == 2 && intrinsic_op(intrinsic, Register(0)).is_some() {
+                    self.visit_expr(ops[0]);
+                    let lhs_reg = self.alloc_regs(1);
+                    self.code.push(Bytecode::Star(lhs_reg));
+                    self.visit_expr(ops[1]);
+                    self.code.push(intrinsic_op(intrinsic, lhs_reg).unwrap());
+                    self.regs -= 1;
+                } else {
+                    self.unsupported(pos);
+                }
+            }
         }
-        self.code.push(Bytecode::Return);
     }
 
-    fn visit_stmt_break(&mut self, _stmt: &StmtBreakType) {
-        let Label(end) = self.loops.pop().unwrap().end;
-        self.code.push(Bytecode::Jump(Label(end)));
-    }
+    fn visit_expr_conv(&mut self, expr: &ExprConvType) {
+        let conv = *self.src.map_convs.get(expr.id).unwrap();
+        self.visit_expr(&expr.object);
 
-    fn visit_stmt_continue(&mut self, _stmt: &StmtContinueType) {
-        let Label(cond) = self.loops.last().unwrap().cond;
-        self.code.push(Bytecode::Jump(Label(cond)));
-    }
+        if conv.valid && !expr.is {
+            return;
+        }
 
-    // TODO - implement other expressions
-    fn visit_expr(&mut self, expr: &Expr) {
-        match *expr {
-            ExprUn(ref un) => self.visit_expr_un(un),
-            ExprBin(ref bin) => self.visit_expr_bin(bin),
-            // ExprField(ref field) => {},
-            // ExprArray(ref array) => {},
-            // ExprLitChar(ref lit) => {},
-            ExprLitInt(ref lit) => self.visit_expr_lit_int(lit),
-            // ExprLitFloat(ref lit) => {},
-            // ExprLitStr(ref lit) => {},
-            // ExprLitStruct(ref lit) => {},
-            // ExprLitBool(ref lit) => {},
-            ExprIdent(ref ident) => self.visit_expr_ident(ident),
-            ExprAssign(ref assign) => self.visit_expr_assign(assign),
-            // ExprCall(ref call) => {},
-            // ExprDelegation(ref call) => {},
-            // ExprSelf(ref selfie) => {},
-            // ExprSuper(ref expr) => {},
-            // ExprNil(ref nil) => {},
-            // ExprConv(ref expr) => {},
-            // ExprTry(ref expr) => {},
-            // ExprLambda(ref expr) => {},
-            _ => unimplemented!(),
+        if self.vm.classes.idx(conv.cls_id).read().is_generic() {
+            self.unsupported(expr.pos);
+            return;
         }
-    }
 
-    fn visit_expr_lit_int(&mut self, lit: &ExprLitIntType) {
-        if lit.value == 0 {
-            self.code.push(Bytecode::LdaZero);
+        let cls_id = specialize_class_id(self.vm, conv.cls_id);
+
+        if expr.is {
+            self.code.push(Bytecode::InstanceOf(cls_id));
         } else {
-            self.code.push(Bytecode::LdaInt(lit.value));
+            self.code.push(Bytecode::CheckedCast(cls_id));
         }
     }
 
-    fn visit_expr_un(&mut self, expr: &ExprUnType) {
-        self.visit_expr(&expr.opnd);
-        match expr.op {
-            UnOp::Plus => {}
-            UnOp::Neg => self.code.push(Bytecode::Neg),
-            UnOp::Not => self.code.push(Bytecode::LogicalNot),
+    fn visit_expr_try(&mut self, expr: &ExprTryType) {
+        if expr.mode.is_normal() {
+            self.visit_expr(&expr.expr);
+            return;
         }
-    }
 
-    fn visit_expr_bin(&mut self, expr: &ExprBinType) {
-        self.visit_expr(&expr.rhs);
-        let rhs_reg = self.regs;
-        self.regs += 1;
-        self.code.push(Bytecode::Star(Register(rhs_reg)));
-        self.visit_expr(&expr.lhs);
-        match expr.op {
-            BinOp::Add => self.code.push(Bytecode::Add(Register(rhs_reg))),
-            BinOp::Sub => self.code.push(Bytecode::Sub(Register(rhs_reg))),
-            BinOp::Mul => self.code.push(Bytecode::Mul(Register(rhs_reg))),
-            BinOp::Div => self.code.push(Bytecode::Div(Register(rhs_reg))),
-            BinOp::Mod => self.code.push(Bytecode::Mod(Register(rhs_reg))),
-            BinOp::BitOr => self.code.push(Bytecode::BitwiseOr(Register(rhs_reg))),
-            BinOp::BitAnd => self.code.push(Bytecode::BitwiseAnd(Register(rhs_reg))),
-            BinOp::BitXor => self.code.push(Bytecode::BitwiseXor(Register(rhs_reg))),
-            BinOp::ShiftL => self.code.push(Bytecode::ShiftLeft(Register(rhs_reg))),
-            BinOp::ShiftR => self.code.push(Bytecode::ShiftRight(Register(rhs_reg))),
-            // BinOp::Or => { },
-            // BinOp::And => { },
-            // BinOp::UnShiftR => { },
-            BinOp::Cmp(op) => {
-                match op {
-                    CmpOp::Eq => self.code.push(Bytecode::TestEqual(Register(rhs_reg))),
-                    CmpOp::Ne => self.code.push(Bytecode::TestNotEqual(Register(rhs_reg))),
-                    CmpOp::Lt => self.code.push(Bytecode::TestLessThan(Register(rhs_reg))),
-                    CmpOp::Le => self
-                        .code
-                        .push(Bytecode::TestLessThanOrEqual(Register(rhs_reg))),
-                    CmpOp::Gt => self
-                        .code
-                        .push(Bytecode::TestGreatherThan(Register(rhs_reg))),
-                    CmpOp::Ge => self
-                        .code
-                        .push(Bytecode::TestGreatherThanOrEqual(Register(rhs_reg))),
-                    // CmpOp::Is => { },
-                    // CmpOp::IsNot => { },
-                    _ => unimplemented!(),
+        let end_lbl = self.create_label();
+
+        let try_start = self.code.len();
+        self.visit_expr(&expr.expr);
+        let try_end = self.code.len();
+        self.code.push(Bytecode::Jump(end_lbl));
+
+        self.handlers.push(ExceptionHandler {
+            try_start: try_start,
+            try_end: try_end,
+            catch: self.code.len(),
+            catch_type: None,
+            register: None,
+        });
+
+        match expr.mode {
+            TryMode::Else(ref alt_expr) => self.visit_expr(alt_expr),
+            TryMode::Force => self.code.push(Bytecode::Trap(Trap::UNEXPECTED)),
+            TryMode::Opt => {
+                // the exception is dropped and replaced with nil
+                if !self.ty(expr.id).is_unit() {
+                    self.code.push(Bytecode::LdaNil);
                 }
             }
-            _ => unimplemented!(),
+            TryMode::Normal => unreachable!(),
         }
-        self.regs -= 1;
+
+        self.bind_label(end_lbl);
     }
+}
 
-    fn visit_expr_assign(&mut self, expr: &ExprAssignType) {
-        self.visit_expr(&expr.rhs);
-        match *expr.lhs {
-            ExprIdent(ref assign) => {
-                let Register(reg) = *self.get_reg(assign.name).unwrap();
-                self.code.push(Bytecode::Star(Register(reg)));
+// generic functions and lambdas are not supported, natives can only
+// be invoked when all arguments are passed in general purpose registers
+pub fn is_supported_fct(vm: &VM, fct: &Fct) -> bool {
+    if !fct.type_params.is_empty() || fct.is_lambda {
+        return false;
+    }
+
+    match fct.parent {
+        FctParent::Class(cls_id) => {
+            if vm.classes.idx(cls_id).read().is_generic() {
+                return false;
             }
-            _ => unimplemented!(),
         }
+
+        FctParent::Trait(_) => return false,
+        _ => {}
     }
 
-    fn visit_expr_ident(&mut self, ident: &ExprIdentType) {
-        let Register(reg) = *self.get_reg(ident.name).unwrap();
-        self.code.push(Bytecode::Ldar(Register(reg)));
+    match fct.kind {
+        FctKind::Native(_) => {
+            fct.params_with_self().len() <= 6
+                && fct
+                    .params_with_self()
+                    .iter()
+                    .chain(Some(&fct.return_type))
+                    .all(|&ty| match BytecodeType::from_ty(ty) {
+                        Some(BytecodeType::Float) | Some(BytecodeType::Double) => false,
+                        Some(_) => true,
+                        None => ty.is_unit(),
+                    })
+        }
+
+        _ => true,
+    }
+}
+
+fn arith_op(op: BinOp, lhs_reg: Register) -> Bytecode {
+    match op {
+        BinOp::Add => Bytecode::Add(lhs_reg),
+        BinOp::Sub => Bytecode::Sub(lhs_reg),
+        BinOp::Mul => Bytecode::Mul(lhs_reg),
+        BinOp::Div => Bytecode::Div(lhs_reg),
+        BinOp::Mod => Bytecode::Mod(lhs_reg),
+        BinOp::BitOr => Bytecode::BitwiseOr(lhs_reg),
+        BinOp::BitAnd => Bytecode::BitwiseAnd(lhs_reg),
+        BinOp::BitXor => Bytecode::BitwiseXor(lhs_reg),
+        BinOp::ShiftL => Bytecode::ShiftLeft(lhs_reg),
+        BinOp::ShiftR => Bytecode::ShiftRight(lhs_reg),
+        BinOp::UnShiftR => Bytecode::UnsignedShiftRight(lhs_reg),
+        BinOp::Cmp(_) | BinOp::Or | BinOp::And => unreachable!(),
+    }
+}
+
+fn cmp_op(op: CmpOp, lhs_reg: Register) -> Bytecode {
+    match op {
+        CmpOp::Eq | CmpOp::Is => Bytecode::TestEqual(lhs_reg),
+        CmpOp::Ne | CmpOp::IsNot => Bytecode::TestNotEqual(lhs_reg),
+        CmpOp::Lt => Bytecode::TestLessThan(lhs_reg),
+        CmpOp::Le => Bytecode::TestLessThanOrEqual(lhs_reg),
+        CmpOp::Gt => Bytecode::TestGreatherThan(lhs_reg),
+        CmpOp::Ge => Bytecode::TestGreatherThanOrEqual(lhs_reg),
+    }
+}
+
+fn intrinsic_op(intrinsic: Intrinsic, lhs_reg: Register) -> Option<Bytecode> {
+    let bytecode = match intrinsic {
+        Intrinsic::IntAdd | Intrinsic::LongAdd | Intrinsic::FloatAdd | Intrinsic::DoubleAdd => {
+            Bytecode::Add(lhs_reg)
+        }
+        Intrinsic::IntSub | Intrinsic::LongSub | Intrinsic::FloatSub | Intrinsic::DoubleSub => {
+            Bytecode::Sub(lhs_reg)
+        }
+        Intrinsic::IntMul | Intrinsic::LongMul | Intrinsic::FloatMul | Intrinsic::DoubleMul => {
+            Bytecode::Mul(lhs_reg)
+        }
+        Intrinsic::IntDiv | Intrinsic::LongDiv | Intrinsic::FloatDiv | Intrinsic::DoubleDiv => {
+            Bytecode::Div(lhs_reg)
+        }
+        Intrinsic::IntMod | Intrinsic::LongMod => Bytecode::Mod(lhs_reg),
+        Intrinsic::IntOr | Intrinsic::LongOr => Bytecode::BitwiseOr(lhs_reg),
+        Intrinsic::IntAnd | Intrinsic::LongAnd => Bytecode::BitwiseAnd(lhs_reg),
+        Intrinsic::IntXor | Intrinsic::LongXor => Bytecode::BitwiseXor(lhs_reg),
+        Intrinsic::IntShl | Intrinsic::LongShl => Bytecode::ShiftLeft(lhs_reg),
+        Intrinsic::IntSar | Intrinsic::LongSar => Bytecode::ShiftRight(lhs_reg),
+        Intrinsic::IntShr | Intrinsic::LongShr => Bytecode::UnsignedShiftRight(lhs_reg),
+        Intrinsic::BoolEq
+        | Intrinsic::ByteEq
+        | Intrinsic::CharEq
+        | Intrinsic::IntEq
+        | Intrinsic::LongEq
+        | Intrinsic::FloatEq
+        | Intrinsic::DoubleEq => Bytecode::TestEqual(lhs_reg),
+        _ => return None,
+    };
+
+    Some(bytecode)
+}
+
+fn conv_type(intrinsic: Intrinsic) -> Option<BytecodeType> {
+    match intrinsic {
+        Intrinsic::IntToByte | Intrinsic::LongToByte => Some(BytecodeType::Byte),
+        Intrinsic::IntToChar | Intrinsic::LongToChar => Some(BytecodeType::Char),
+        Intrinsic::BoolToInt
+        | Intrinsic::ByteToInt
+        | Intrinsic::CharToInt
+        | Intrinsic::LongToInt
+        | Intrinsic::FloatToInt
+        | Intrinsic::DoubleToInt => Some(BytecodeType::Int),
+        Intrinsic::BoolToLong
+        | Intrinsic::ByteToLong
+        | Intrinsic::CharToLong
+        | Intrinsic::IntToLong
+        | Intrinsic::FloatToLong
+        | Intrinsic::DoubleToLong => Some(BytecodeType::Long),
+        Intrinsic::IntToFloat | Intrinsic::LongToFloat | Intrinsic::DoubleToFloat => {
+            Some(BytecodeType::Float)
+        }
+        Intrinsic::IntToDouble | Intrinsic::LongToDouble | Intrinsic::FloatToDouble => {
+            Some(BytecodeType::Double)
+        }
+        _ => None,
     }
 }
 
@@ -411,179 +1471,128 @@ impl BytecodeGen {
 mod tests {
     use boots::bytecodegen::Bytecode::*;
     use boots::bytecodegen::*;
-    use dora_parser::interner::Interner;
-    use dora_parser::lexer::reader::Reader;
-    use dora_parser::parser::{NodeIdGenerator, Parser};
+    use test;
 
-    fn parse(code: &'static str) -> (Ast, Interner) {
-        let id_generator = NodeIdGenerator::new();
-        let mut interner = Interner::new();
-        let mut ast = Ast::new();
+    fn code(code: &'static str) -> Vec<Bytecode> {
+        code_with_labels(code).0
+    }
 
-        let reader = Reader::from_string(code);
-        Parser::new(reader, &id_generator, &mut ast, &mut interner)
-            .parse()
-            .unwrap();
+    fn code_with_labels(code: &'static str) -> (Vec<Bytecode>, HashMap<Label, usize>) {
+        test::parse(code, |vm| {
+            let fct_id = vm.fct_by_name("f").expect("no function `f`.");
+            let fct = generate(vm, fct_id).expect("bytecode not supported");
 
-        (ast, interner)
+            (fct.code, fct.labels)
+        })
     }
 
-    #[test]
-    fn gen_nooptimize() {
-        let (ast, _) = parse("fun f() {1 + 2;}");
-        let mut bytecodegen = BytecodeGen::new();
-        bytecodegen.gen(&ast);
-        assert!(bytecodegen.code.is_empty());
+    fn unsupported(code: &'static str) -> Position {
+        test::parse(code, |vm| {
+            let fct_id = vm.fct_by_name("f").expect("no function `f`.");
+
+            match generate(vm, fct_id) {
+                Ok(_) => panic!("bytecode unexpectedly supported"),
+                Err(pos) => pos,
+            }
+        })
     }
 
     #[test]
     fn gen_add() {
-        let (ast, _) = parse(
-            "
-            optimize fun f() {1 + 2;}",
-        );
-        let mut bytecodegen = BytecodeGen::new();
         let expected = vec![
-            LdaInt(2),
-            Star(Register(0)),
             LdaInt(1),
+            Star(Register(0)),
+            LdaInt(2),
             Add(Register(0)),
             ReturnVoid,
         ];
-        bytecodegen.gen(&ast);
-        assert_eq!(expected, bytecodegen.code);
+        assert_eq!(expected, code("fun f() {1 + 2;}"));
     }
 
     #[test]
     fn gen_sub() {
-        let (ast, _) = parse(
-            "
-            optimize fun f() {1 - 2;}",
-        );
-        let mut bytecodegen = BytecodeGen::new();
         let expected = vec![
-            LdaInt(2),
-            Star(Register(0)),
             LdaInt(1),
+            Star(Register(0)),
+            LdaInt(2),
             Sub(Register(0)),
             ReturnVoid,
         ];
-        bytecodegen.gen(&ast);
-        assert_eq!(expected, bytecodegen.code);
+        assert_eq!(expected, code("fun f() {1 - 2;}"));
     }
 
     #[test]
     fn gen_div() {
-        let (ast, _) = parse(
-            "
-            optimize fun f() {1 / 2;}",
-        );
-        let mut bytecodegen = BytecodeGen::new();
         let expected = vec![
-            LdaInt(2),
-            Star(Register(0)),
             LdaInt(1),
+            Star(Register(0)),
+            LdaInt(2),
             Div(Register(0)),
             ReturnVoid,
         ];
-        bytecodegen.gen(&ast);
-        assert_eq!(expected, bytecodegen.code);
+        assert_eq!(expected, code("fun f() {1 / 2;}"));
     }
 
     #[test]
     fn gen_mul() {
-        let (ast, _) = parse(
-            "
-            optimize fun f() {1 * 2;}",
-        );
-        let mut bytecodegen = BytecodeGen::new();
         let expected = vec![
-            LdaInt(2),
-            Star(Register(0)),
             LdaInt(1),
+            Star(Register(0)),
+            LdaInt(2),
             Mul(Register(0)),
             ReturnVoid,
         ];
-        bytecodegen.gen(&ast);
-        assert_eq!(expected, bytecodegen.code);
+        assert_eq!(expected, code("fun f() {1 * 2;}"));
     }
 
     #[test]
     fn gen_stmt_var_noinit() {
-        let (ast, _) = parse(
-            "
-            optimize fun f() { let x; }",
-        );
-        let mut bytecodegen = BytecodeGen::new();
-        let mut expected = Vec::new();
-        expected.push(Bytecode::LdaZero);
-        expected.push(Bytecode::Star(Register(0)));
-        expected.push(Bytecode::ReturnVoid);
-        bytecodegen.gen(&ast);
-        assert_eq!(expected, bytecodegen.code);
+        let expected = vec![LdaZero, Star(Register(0)), ReturnVoid];
+        assert_eq!(expected, code("fun f() { var x: int; }"));
     }
 
     #[test]
     fn gen_stmt_var_init() {
-        let (ast, _) = parse(
-            "
-            optimize fun f() { let x = 1; }",
-        );
-        let mut bytecodegen = BytecodeGen::new();
         let expected = vec![LdaInt(1), Star(Register(0)), ReturnVoid];
-        bytecodegen.gen(&ast);
-        assert_eq!(expected, bytecodegen.code);
+        assert_eq!(expected, code("fun f() { let x = 1; }"));
     }
 
     #[test]
     fn gen_stmt_while() {
-        let (ast, _) = parse(
-            "
-            optimize fun f() { while 1 { 0; } }",
-        );
-        let mut bytecodegen = BytecodeGen::new();
-        let code = vec![
-            LdaInt(1),
+        let expected = vec![
+            LdaTrue,
             JumpIfFalse(Label(1)),
             LdaZero,
             Jump(Label(0)),
             ReturnVoid,
         ];
         let labels = hashmap![Label(0) => 0, Label(1) => 4];
-        bytecodegen.gen(&ast);
-        assert_eq!(code, bytecodegen.code);
-        assert_eq!(labels, bytecodegen.labels);
+        assert_eq!(
+            (expected, labels),
+            code_with_labels("fun f() { while true { 0; } }")
+        );
     }
 
     #[test]
     fn gen_stmt_if() {
-        let (ast, _) = parse(
-            "
-            optimize fun f() { if 0 { 1; } }",
-        );
-        let mut bytecodegen = BytecodeGen::new();
         let expected = vec![
-            LdaZero,
+            LdaTrue,
             JumpIfFalse(Label(0)),
             LdaInt(1),
             Jump(Label(1)),
             ReturnVoid,
         ];
         let labels = hashmap![Label(0) => 4, Label(1) => 4];
-        bytecodegen.gen(&ast);
-        assert_eq!(expected, bytecodegen.code);
-        assert_eq!(labels, bytecodegen.labels);
+        assert_eq!(
+            (expected, labels),
+            code_with_labels("fun f() { if true { 1; } }")
+        );
     }
 
     #[test]
     fn gen_stmt_if_else() {
-        let (ast, _) = parse(
-            "
-            optimize fun f() { if 0 { 1; } else { 2; } }",
-        );
-        let mut bytecodegen = BytecodeGen::new();
         let expected = vec![
-            LdaZero,
+            LdaTrue,
             JumpIfFalse(Label(0)),
             LdaInt(1),
             Jump(Label(1)),
@@ -591,378 +1600,462 @@ mod tests {
             ReturnVoid,
         ];
         let labels = hashmap![Label(0) => 4, Label(1) => 5];
-        bytecodegen.gen(&ast);
-        assert_eq!(expected, bytecodegen.code);
-        assert_eq!(labels, bytecodegen.labels);
+        assert_eq!(
+            (expected, labels),
+            code_with_labels("fun f() { if true { 1; } else { 2; } }")
+        );
     }
 
     #[test]
     fn gen_stmt_break() {
-        let (ast, _) = parse(
-            "
-            optimize fun f() { while 1 { break; } }",
-        );
-        let mut bytecodegen = BytecodeGen::new();
         let expected = vec![
-            LdaInt(1),
+            LdaTrue,
             JumpIfFalse(Label(1)),
             Jump(Label(1)),
             Jump(Label(0)),
             ReturnVoid,
         ];
         let labels = hashmap![Label(0) => 0, Label(1) => 4];
-        bytecodegen.gen(&ast);
-        assert_eq!(expected, bytecodegen.code);
-        assert_eq!(labels, bytecodegen.labels);
+        assert_eq!(
+            (expected, labels),
+            code_with_labels("fun f() { while true { break; } }")
+        );
     }
 
     #[test]
     fn gen_stmt_continue() {
-        let (ast, _) = parse(
-            "
-            optimize fun f() { while 1 { continue; } }",
-        );
-        let mut bytecodegen = BytecodeGen::new();
         let expected = vec![
-            LdaInt(1),
+            LdaTrue,
             JumpIfFalse(Label(1)),
             Jump(Label(0)),
             Jump(Label(0)),
             ReturnVoid,
         ];
         let labels = hashmap![Label(0) => 0, Label(1) => 4];
-        bytecodegen.gen(&ast);
-        assert_eq!(expected, bytecodegen.code);
-        assert_eq!(labels, bytecodegen.labels);
+        assert_eq!(
+            (expected, labels),
+            code_with_labels("fun f() { while true { continue; } }")
+        );
     }
 
     #[test]
-    fn gen_expr_lit_int() {
-        let (ast, _) = parse(
-            "
-            optimize fun f() { 1; }",
+    fn gen_stmt_nested_break() {
+        let expected = vec![
+            LdaTrue,
+            JumpIfFalse(Label(1)),
+            LdaTrue,
+            JumpIfFalse(Label(3)),
+            Jump(Label(3)),
+            Jump(Label(2)),
+            Jump(Label(1)),
+            Jump(Label(0)),
+            ReturnVoid,
+        ];
+        let labels = hashmap![Label(0) => 0, Label(1) => 8, Label(2) => 2, Label(3) => 6];
+        assert_eq!(
+            (expected, labels),
+            code_with_labels("fun f() { while true { while true { break; } break; } }")
         );
-        let mut bytecodegen = BytecodeGen::new();
+    }
+
+    #[test]
+    fn gen_expr_lit_int() {
         let expected = vec![LdaInt(1), ReturnVoid];
-        bytecodegen.gen(&ast);
-        assert_eq!(expected, bytecodegen.code);
+        assert_eq!(expected, code("fun f() { 1; }"));
     }
 
     #[test]
     fn gen_expr_lit_zero() {
-        let (ast, _) = parse(
-            "
-            optimize fun f() { 0; }",
-        );
-        let mut bytecodegen = BytecodeGen::new();
         let expected = vec![LdaZero, ReturnVoid];
-        bytecodegen.gen(&ast);
-        assert_eq!(expected, bytecodegen.code);
+        assert_eq!(expected, code("fun f() { 0; }"));
+    }
+
+    #[test]
+    fn gen_expr_lit_typed() {
+        let expected = vec![
+            LdaLong(1),
+            LdaByte(2),
+            LdaDouble(1.5),
+            LdaChar('a'),
+            ReturnVoid,
+        ];
+        assert_eq!(expected, code("fun f() { 1L; 2Y; 1.5; 'a'; }"));
     }
 
     #[test]
     fn gen_expr_puls() {
-        let (ast, _) = parse(
-            "
-            optimize fun f() { +1; }",
-        );
-        let mut bytecodegen = BytecodeGen::new();
         let expected = vec![LdaInt(1), ReturnVoid];
-        bytecodegen.gen(&ast);
-        assert_eq!(expected, bytecodegen.code);
+        assert_eq!(expected, code("fun f() { +1; }"));
     }
 
     #[test]
     fn gen_expr_neg() {
-        let (ast, _) = parse(
-            "
-            optimize fun f() { -1; }",
-        );
-        let mut bytecodegen = BytecodeGen::new();
         let expected = vec![LdaInt(1), Neg, ReturnVoid];
-        bytecodegen.gen(&ast);
-        assert_eq!(expected, bytecodegen.code);
+        assert_eq!(expected, code("fun f() { -1; }"));
     }
 
     #[test]
     fn gen_expr_not() {
-        let (ast, _) = parse(
-            "
-            optimize fun f() { !1; }",
-        );
-        let mut bytecodegen = BytecodeGen::new();
-        let expected = vec![LdaInt(1), LogicalNot, ReturnVoid];
-        bytecodegen.gen(&ast);
-        assert_eq!(expected, bytecodegen.code);
+        let expected = vec![LdaTrue, LogicalNot, ReturnVoid];
+        assert_eq!(expected, code("fun f() { !true; }"));
     }
 
     #[test]
     fn gen_expr_mod() {
-        let (ast, _) = parse(
-            "
-            optimize fun f() { 1 % 2; }",
-        );
-        let mut bytecodegen = BytecodeGen::new();
         let expected = vec![
-            LdaInt(2),
-            Star(Register(0)),
             LdaInt(1),
+            Star(Register(0)),
+            LdaInt(2),
             Mod(Register(0)),
             ReturnVoid,
         ];
-        bytecodegen.gen(&ast);
-        assert_eq!(expected, bytecodegen.code);
+        assert_eq!(expected, code("fun f() { 1 % 2; }"));
     }
 
     #[test]
     fn gen_expr_bit_or() {
-        let (ast, _) = parse(
-            "
-            optimize fun f() { 1 | 2; }",
-        );
-        let mut bytecodegen = BytecodeGen::new();
         let expected = vec![
-            LdaInt(2),
-            Star(Register(0)),
             LdaInt(1),
+            Star(Register(0)),
+            LdaInt(2),
             BitwiseOr(Register(0)),
             ReturnVoid,
         ];
-        bytecodegen.gen(&ast);
-        assert_eq!(expected, bytecodegen.code);
+        assert_eq!(expected, code("fun f() { 1 | 2; }"));
     }
 
     #[test]
     fn gen_expr_bit_and() {
-        let (ast, _) = parse(
-            "
-            optimize fun f() { 1 & 2; }",
-        );
-        let mut bytecodegen = BytecodeGen::new();
         let expected = vec![
-            LdaInt(2),
-            Star(Register(0)),
             LdaInt(1),
+            Star(Register(0)),
+            LdaInt(2),
             BitwiseAnd(Register(0)),
             ReturnVoid,
         ];
-        bytecodegen.gen(&ast);
-        assert_eq!(expected, bytecodegen.code);
+        assert_eq!(expected, code("fun f() { 1 & 2; }"));
     }
 
     #[test]
     fn gen_expr_bit_xor() {
-        let (ast, _) = parse(
-            "
-            optimize fun f() { 1 ^ 2; }",
-        );
-        let mut bytecodegen = BytecodeGen::new();
         let expected = vec![
-            LdaInt(2),
-            Star(Register(0)),
             LdaInt(1),
+            Star(Register(0)),
+            LdaInt(2),
             BitwiseXor(Register(0)),
             ReturnVoid,
         ];
-        bytecodegen.gen(&ast);
-        assert_eq!(expected, bytecodegen.code);
+        assert_eq!(expected, code("fun f() { 1 ^ 2; }"));
     }
 
     #[test]
     fn gen_expr_bit_shiftl() {
-        let (ast, _) = parse(
-            "
-            optimize fun f() { 1 << 2; }",
-        );
-        let mut bytecodegen = BytecodeGen::new();
         let expected = vec![
-            LdaInt(2),
-            Star(Register(0)),
             LdaInt(1),
+            Star(Register(0)),
+            LdaInt(2),
             ShiftLeft(Register(0)),
             ReturnVoid,
         ];
-        bytecodegen.gen(&ast);
-        assert_eq!(expected, bytecodegen.code);
+        assert_eq!(expected, code("fun f() { 1 << 2; }"));
     }
 
     #[test]
     fn gen_expr_bit_shiftr() {
-        let (ast, _) = parse(
-            "
-            optimize fun f() { 1 >> 2; }",
-        );
-        let mut bytecodegen = BytecodeGen::new();
         let expected = vec![
-            LdaInt(2),
-            Star(Register(0)),
             LdaInt(1),
+            Star(Register(0)),
+            LdaInt(2),
             ShiftRight(Register(0)),
             ReturnVoid,
         ];
-        bytecodegen.gen(&ast);
-        assert_eq!(expected, bytecodegen.code);
+        assert_eq!(expected, code("fun f() { 1 >> 2; }"));
     }
 
     #[test]
-    fn gen_expr_test_equal() {
-        let (ast, _) = parse(
-            "
-            optimize fun f() { 1 == 2; }",
-        );
-        let mut bytecodegen = BytecodeGen::new();
+    fn gen_expr_bit_ushiftr() {
         let expected = vec![
-            LdaInt(2),
+            LdaInt(1),
             Star(Register(0)),
+            LdaInt(2),
+            UnsignedShiftRight(Register(0)),
+            ReturnVoid,
+        ];
+        assert_eq!(expected, code("fun f() { 1 >>> 2; }"));
+    }
+
+    #[test]
+    fn gen_expr_test_equal() {
+        let expected = vec![
             LdaInt(1),
+            Star(Register(0)),
+            LdaInt(2),
             TestEqual(Register(0)),
             ReturnVoid,
         ];
-        bytecodegen.gen(&ast);
-        assert_eq!(expected, bytecodegen.code);
+        assert_eq!(expected, code("fun f() { 1 == 2; }"));
     }
 
     #[test]
     fn gen_expr_test_notequal() {
-        let (ast, _) = parse(
-            "
-            optimize fun f() { 1 != 2; }",
-        );
-        let mut bytecodegen = BytecodeGen::new();
         let expected = vec![
-            LdaInt(2),
-            Star(Register(0)),
             LdaInt(1),
+            Star(Register(0)),
+            LdaInt(2),
             TestNotEqual(Register(0)),
             ReturnVoid,
         ];
-        bytecodegen.gen(&ast);
-        assert_eq!(expected, bytecodegen.code);
+        assert_eq!(expected, code("fun f() { 1 != 2; }"));
     }
 
     #[test]
     fn gen_expr_test_lessthan() {
-        let (ast, _) = parse(
-            "
-            optimize fun f() { 1 < 2; }",
-        );
-        let mut bytecodegen = BytecodeGen::new();
         let expected = vec![
-            LdaInt(2),
-            Star(Register(0)),
             LdaInt(1),
+            Star(Register(0)),
+            LdaInt(2),
             TestLessThan(Register(0)),
             ReturnVoid,
         ];
-        bytecodegen.gen(&ast);
-        assert_eq!(expected, bytecodegen.code);
+        assert_eq!(expected, code("fun f() { 1 < 2; }"));
     }
 
     #[test]
     fn gen_expr_test_lessthanequal() {
-        let (ast, _) = parse(
-            "
-            optimize fun f() { 1 <= 2; }",
-        );
-        let mut bytecodegen = BytecodeGen::new();
         let expected = vec![
-            LdaInt(2),
-            Star(Register(0)),
             LdaInt(1),
+            Star(Register(0)),
+            LdaInt(2),
             TestLessThanOrEqual(Register(0)),
             ReturnVoid,
         ];
-        bytecodegen.gen(&ast);
-        assert_eq!(expected, bytecodegen.code);
+        assert_eq!(expected, code("fun f() { 1 <= 2; }"));
     }
 
     #[test]
     fn gen_expr_test_greaterthan() {
-        let (ast, _) = parse(
-            "
-            optimize fun f() { 1 > 2; }",
-        );
-        let mut bytecodegen = BytecodeGen::new();
         let expected = vec![
-            LdaInt(2),
-            Star(Register(0)),
             LdaInt(1),
+            Star(Register(0)),
+            LdaInt(2),
             TestGreatherThan(Register(0)),
             ReturnVoid,
         ];
-        bytecodegen.gen(&ast);
-        assert_eq!(expected, bytecodegen.code);
+        assert_eq!(expected, code("fun f() { 1 > 2; }"));
     }
 
     #[test]
     fn gen_expr_test_greaterthanequall() {
-        let (ast, _) = parse(
-            "
-            optimize fun f() { 1 >= 2; }",
-        );
-        let mut bytecodegen = BytecodeGen::new();
         let expected = vec![
-            LdaInt(2),
-            Star(Register(0)),
             LdaInt(1),
+            Star(Register(0)),
+            LdaInt(2),
             TestGreatherThanOrEqual(Register(0)),
             ReturnVoid,
         ];
-        bytecodegen.gen(&ast);
-        assert_eq!(expected, bytecodegen.code);
+        assert_eq!(expected, code("fun f() { 1 >= 2; }"));
+    }
+
+    #[test]
+    fn gen_expr_or() {
+        let expected = vec![LdaTrue, JumpIfTrue(Label(0)), LdaFalse, ReturnVoid];
+        assert_eq!(expected, code("fun f() { true || false; }"));
+    }
+
+    #[test]
+    fn gen_expr_and() {
+        let expected = vec![LdaTrue, JumpIfFalse(Label(0)), LdaFalse, ReturnVoid];
+        assert_eq!(expected, code("fun f() { true && false; }"));
     }
 
     #[test]
     fn gen_expr_ident() {
-        let (ast, _) = parse(
-            "
-            optimize fun f() { let x = 1; x; }",
-        );
-        let mut bytecodegen = BytecodeGen::new();
         let expected = vec![LdaInt(1), Star(Register(0)), Ldar(Register(0)), ReturnVoid];
-        bytecodegen.gen(&ast);
-        assert_eq!(expected, bytecodegen.code);
+        assert_eq!(expected, code("fun f() { let x = 1; x; }"));
     }
 
     #[test]
-    fn gen_expr_assign() {
-        let (ast, _) = parse(
-            "
-            optimize fun f() { var x = 1; x = 2; }",
+    fn gen_expr_param() {
+        let expected = vec![
+            Ldar(Register(0)),
+            Star(Register(2)),
+            Ldar(Register(1)),
+            Add(Register(2)),
+            Return,
+        ];
+        assert_eq!(
+            expected,
+            code("fun f(a: int, b: int) -> int { return a + b; }")
         );
-        let mut bytecodegen = BytecodeGen::new();
+    }
+
+    #[test]
+    fn gen_expr_assign() {
+        let expected = vec![
+            LdaInt(1),
+            Star(Register(0)),
+            LdaInt(2),
+            Star(Register(0)),
+            ReturnVoid,
+        ];
+        assert_eq!(expected, code("fun f() { var x = 1; x = 2; }"));
+    }
+
+    #[test]
+    fn gen_expr_assign_op() {
         let expected = vec![
             LdaInt(1),
             Star(Register(0)),
             LdaInt(2),
+            Add(Register(0)),
             Star(Register(0)),
             ReturnVoid,
         ];
-        bytecodegen.gen(&ast);
-        assert_eq!(expected, bytecodegen.code);
+        assert_eq!(expected, code("fun f() { var x = 1; x += 2; }"));
     }
 
     #[test]
     fn gen_expr_return() {
-        let (ast, _) = parse(
-            "
-            optimize fun f() { return 1; }",
-        );
-        let mut bytecodegen = BytecodeGen::new();
         let expected = vec![LdaInt(1), Return];
-        bytecodegen.gen(&ast);
-        assert_eq!(expected, bytecodegen.code);
+        assert_eq!(expected, code("fun f() -> int { return 1; }"));
     }
 
     #[test]
     fn gen_expr_returnvoid() {
-        let (ast, _) = parse(
-            "
-            optimize fun f() { }",
-        );
-        let mut bytecodegen = BytecodeGen::new();
         let expected = vec![ReturnVoid];
-        bytecodegen.gen(&ast);
-        assert_eq!(expected, bytecodegen.code);
+        assert_eq!(expected, code("fun f() { }"));
+    }
+
+    #[test]
+    fn gen_expr_call() {
+        test::parse(
+            "fun f() -> int { return g(1); } fun g(a: int) -> int { return a; }",
+            |vm| {
+                let g = vm.fct_by_name("g").unwrap();
+                let fct = generate(vm, vm.fct_by_name("f").unwrap()).unwrap();
+                let expected = vec![
+                    LdaInt(1),
+                    Star(Register(0)),
+                    InvokeStatic(g, Register(0), 1),
+                    Return,
+                ];
+                assert_eq!(expected, fct.code);
+            },
+        );
+    }
+
+    #[test]
+    fn gen_expr_field() {
+        let expected = vec![
+            NewObject(ClassDefId::from(0)),
+            Star(Register(1)),
+            LdaInt(1),
+            Star(Register(2)),
+            InvokeStatic(FctId(0), Register(1), 2),
+            Ldar(Register(1)),
+            Star(Register(0)),
+            Ldar(Register(0)),
+            Star(Register(1)),
+            LdaField(Register(1), ClassDefId::from(0), FieldId::from(0)),
+            Return,
+        ];
+
+        test::parse(
+            "class Foo(let a: int) fun f() -> int { let x = Foo(1); return x.a; }",
+            |vm| {
+                let fct = generate(vm, vm.fct_by_name("f").unwrap()).unwrap();
+                let cls_id = match fct.code[0] {
+                    NewObject(cls_id) => cls_id,
+                    _ => unreachable!(),
+                };
+                let ctor_id = match fct.code[4] {
+                    InvokeStatic(ctor_id, _, _) => ctor_id,
+                    _ => unreachable!(),
+                };
+                let expected = expected
+                    .into_iter()
+                    .map(|btcode| match btcode {
+                        NewObject(_) => NewObject(cls_id),
+                        InvokeStatic(_, start, count) => InvokeStatic(ctor_id, start, count),
+                        LdaField(reg, _, field_id) => LdaField(reg, cls_id, field_id),
+                        btcode => btcode,
+                    })
+                    .collect::<Vec<_>>();
+                assert_eq!(expected, fct.code);
+            },
+        );
+    }
+
+    #[test]
+    fn gen_expr_array() {
+        let expected = vec![
+            LdaInt(10),
+            Star(Register(1)),
+            LdaZero,
+            NewArray(ClassDefId::from(0), Register(1), BytecodeType::Int),
+            Star(Register(0)),
+            Ldar(Register(0)),
+            Star(Register(1)),
+            LdaInt(2),
+            Star(Register(2)),
+            LdaInt(3),
+            StaArray(Register(1), Register(2), BytecodeType::Int),
+            Ldar(Register(0)),
+            Star(Register(1)),
+            LdaInt(2),
+            LdaArray(Register(1), BytecodeType::Int),
+            Return,
+        ];
+
+        test::parse(
+            "fun f() -> int { let x = Array::<int>(10, 0); x[2] = 3; return x[2]; }",
+            |vm| {
+                let fct = generate(vm, vm.fct_by_name("f").unwrap()).unwrap();
+                let cls_id = match fct.code[3] {
+                    NewArray(cls_id, _, _) => cls_id,
+                    _ => unreachable!(),
+                };
+                let expected = expected
+                    .into_iter()
+                    .map(|btcode| match btcode {
+                        NewArray(_, reg, ty) => NewArray(cls_id, reg, ty),
+                        btcode => btcode,
+                    })
+                    .collect::<Vec<_>>();
+                assert_eq!(expected, fct.code);
+            },
+        );
+    }
+
+    #[test]
+    fn gen_stmt_do_catch() {
+        test::parse(
+            "fun f() -> int { do { throw Exception(); } catch x: Exception { return 1; } return 0; }",
+            |vm| {
+                let fct = generate(vm, vm.fct_by_name("f").unwrap()).unwrap();
+                assert_eq!(1, fct.handlers.len());
+
+                let handler = &fct.handlers[0];
+                assert_eq!(0, handler.try_start);
+                assert_eq!(Throw, fct.code[handler.try_end - 1]);
+                assert_eq!(Jump(Label(0)), fct.code[handler.try_end]);
+                assert_eq!(handler.try_end + 1, handler.catch);
+                assert_eq!(Some(Register(0)), handler.register);
+                assert_eq!(LdaInt(1), fct.code[handler.catch]);
+            },
+        );
+    }
+
+    #[test]
+    fn gen_unsupported() {
+        assert_eq!(
+            Position::new(1, 11),
+            unsupported("fun f() { for x in range(0, 1) {} }")
+        );
+        assert_eq!(Position::new(1, 1), unsupported("fun f<T>() { }"));
+        assert_eq!(
+            Position::new(1, 11),
+            unsupported("fun f() { let (a, b) = (1, 2); a; }")
+        );
     }
 }
@@ -0,0 +1,962 @@
+use parking_lot::Mutex;
+use std::collections::{HashMap, HashSet};
+use std::mem::transmute;
+use std::sync::Arc;
+
+use boots::bytecodegen::{self, Bytecode, BytecodeFunction, BytecodeType, Register};
+use class::{ClassDefId, FieldId};
+use ctxt::{exception_get_and_clear, CallType, Fct, FctId, FctKind, VM};
+use gc::root::Slot;
+use gc::swiper::CARD_SIZE_BITS;
+use gc::Address;
+use mem;
+use object::{self, offset_of_array_data, offset_of_array_length, Array, Ref, Str};
+use os::signal::Trap;
+use stdlib;
+use threads::{DoraThread, THREAD};
+use ty::BuiltinType;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Value {
+    Bool(bool),
+    Byte(u8),
+    Char(u32),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    Ptr(Address),
+}
+
+impl Value {
+    fn bool(self) -> bool {
+        match self {
+            Value::Bool(value) => value,
+            _ => unreachable!(),
+        }
+    }
+
+    fn int(self) -> i32 {
+        match self {
+            Value::Int(value) => value,
+            _ => unreachable!(),
+        }
+    }
+
+    fn ptr(self) -> Address {
+        match self {
+            Value::Ptr(value) => value,
+            _ => unreachable!(),
+        }
+    }
+
+    fn to_usize(self) -> usize {
+        match self {
+            Value::Bool(value) => value as usize,
+            Value::Byte(value) => value as usize,
+            Value::Char(value) => value as usize,
+            Value::Int(value) => value as u32 as usize,
+            Value::Long(value) => value as usize,
+            Value::Ptr(value) => value.to_usize(),
+            Value::Float(_) | Value::Double(_) => unreachable!(),
+        }
+    }
+
+    fn from_usize(value: usize, ty: BuiltinType) -> Value {
+        match BytecodeType::from_ty(ty) {
+            Some(BytecodeType::Bool) => Value::Bool(value as u8 != 0),
+            Some(BytecodeType::Byte) => Value::Byte(value as u8),
+            Some(BytecodeType::Char) => Value::Char(value as u32),
+            Some(BytecodeType::Int) => Value::Int(value as i32),
+            Some(BytecodeType::Long) => Value::Long(value as i64),
+            Some(BytecodeType::Ptr) => Value::Ptr(value.into()),
+            _ => Value::Int(0),
+        }
+    }
+}
+
+// the registers of all active frames are roots for the garbage collector
+pub struct Frame {
+    registers: Vec<Value>,
+    acc: Value,
+}
+
+pub struct InterpreterFrames {
+    frames: Mutex<Vec<*mut Frame>>,
+}
+
+impl InterpreterFrames {
+    pub fn new() -> InterpreterFrames {
+        InterpreterFrames {
+            frames: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn push(&self, frame: *mut Frame) {
+        self.frames.lock().push(frame);
+    }
+
+    fn pop(&self) {
+        self.frames.lock().pop();
+    }
+
+    pub fn roots(&self, rootset: &mut Vec<Slot>) {
+        let frames = self.frames.lock();
+
+        for &frame in frames.iter() {
+            let frame = unsafe { &mut *frame };
+
+            for value in frame.registers.iter_mut().chain(Some(&mut frame.acc)) {
+                if let Value::Ptr(ref mut addr) = *value {
+                    rootset.push(Slot::at(Address::from_ptr(addr as *const Address)));
+                }
+            }
+        }
+    }
+}
+
+// compiled functions are called with all arguments in registers
+const COMPILED_INT_ARGS: usize = 4;
+const COMPILED_FLOAT_ARGS: usize = 8;
+
+// thread, function and the arguments of the called function
+type CompiledEntry<R> = extern "C" fn(
+    Address,
+    Address,
+    usize,
+    usize,
+    usize,
+    usize,
+    f64,
+    f64,
+    f64,
+    f64,
+    f64,
+    f64,
+    f64,
+    f64,
+) -> R;
+
+// runs `main` in the interpreter, functions that can't be translated into
+// bytecode are compiled and called through the interpreter entry. Returns
+// `None` if the program needs to run in the JIT as a whole (see `prepare`).
+pub fn run<'ast>(vm: &VM<'ast>, main: FctId) -> Option<i32> {
+    let fcts = match prepare(vm, main) {
+        Some(fcts) => fcts,
+        None => return None,
+    };
+
+    let thread = THREAD.with(|thread| thread.borrow().clone());
+    let interpreter = Interpreter {
+        vm: vm,
+        fcts: fcts,
+        thread: thread,
+    };
+
+    match interpreter.invoke(main, &[]) {
+        Ok(Value::Int(value)) => Some(value),
+        Ok(_) => Some(0),
+        Err(_) => {
+            trap(Trap::THROW);
+        }
+    }
+}
+
+// translates the functions reachable from `main` into bytecode. Spawned
+// threads would wait forever for the interpreter to reach a safepoint, so
+// programs that spawn threads aren't interpreted. The same holds for
+// compiled functions that can't be called from the interpreter.
+fn prepare<'ast>(vm: &VM<'ast>, main: FctId) -> Option<HashMap<FctId, BytecodeFunction>> {
+    let mut fcts = HashMap::new();
+    let mut compiled = HashSet::new();
+    let mut worklist = vec![main];
+
+    while let Some(fct_id) = worklist.pop() {
+        if fcts.contains_key(&fct_id) || compiled.contains(&fct_id) {
+            continue;
+        }
+
+        {
+            let fct = vm.fcts.idx(fct_id);
+            let fct = fct.read();
+
+            match fct.kind {
+                FctKind::Source(_) => {}
+                FctKind::Native(_) | FctKind::Definition if !is_spawn(&fct) => continue,
+                _ => return None,
+            }
+        }
+
+        let bytecode = match bytecodegen::generate(vm, fct_id) {
+            Ok(bytecode) => bytecode,
+            Err(_) => {
+                if !can_call_compiled(vm, fct_id) || may_spawn(vm, fct_id) {
+                    return None;
+                }
+
+                compiled.insert(fct_id);
+                continue;
+            }
+        };
+
+        for btcode in &bytecode.code {
+            match *btcode {
+                Bytecode::InvokeStatic(callee, _, _) => worklist.push(callee),
+                Bytecode::InvokeVirtual(callee, _, _) => {
+                    worklist.push(callee);
+                    worklist.extend(overriders(vm, callee));
+                }
+                _ => {}
+            }
+        }
+
+        fcts.insert(fct_id, bytecode);
+    }
+
+    Some(fcts)
+}
+
+// the interpreter only calls functions that aren't generic
+// (see `bytecodegen::is_supported_fct`)
+fn can_call_compiled(vm: &VM, fct_id: FctId) -> bool {
+    let fct = vm.fcts.idx(fct_id);
+    let fct = fct.read();
+
+    let params = fct.params_with_self();
+    let floats = params.iter().filter(|ty| ty.is_float()).count();
+
+    params.len() - floats <= COMPILED_INT_ARGS && floats <= COMPILED_FLOAT_ARGS
+}
+
+// follows all calls of the compiled function, which are compiled as well
+fn may_spawn(vm: &VM, fct_id: FctId) -> bool {
+    let mut visited = HashSet::new();
+    let mut worklist = vec![fct_id];
+
+    while let Some(fct_id) = worklist.pop() {
+        if !visited.insert(fct_id) {
+            continue;
+        }
+
+        let fct = vm.fcts.idx(fct_id);
+        let fct = fct.read();
+
+        if is_spawn(&fct) {
+            return true;
+        }
+
+        if !fct.kind.is_src() {
+            continue;
+        }
+
+        let src = fct.src();
+        let src = src.read();
+
+        for (_, call_type) in src.map_calls.iter() {
+            match **call_type {
+                CallType::Lambda(_) | CallType::EnumValue(_, _) => {}
+                _ => {
+                    let callee = call_type.fct_id();
+                    worklist.push(callee);
+                    worklist.extend(overriders(vm, callee));
+                }
+            }
+        }
+
+        worklist.extend(src.map_lambdas.iter().map(|(_, lambda)| lambda.fct_id));
+    }
+
+    false
+}
+
+fn is_spawn(fct: &Fct) -> bool {
+    match fct.kind {
+        FctKind::Native(ptr) => {
+            ptr == Address::from_ptr(stdlib::spawn_thread as *const u8)
+                || ptr == Address::from_ptr(stdlib::spawn_lambda as *const u8)
+        }
+
+        _ => false,
+    }
+}
+
+fn overriders(vm: &VM, fct_id: FctId) -> Vec<FctId> {
+    vm.fcts
+        .iter()
+        .filter_map(|fct| {
+            let fct = fct.read();
+
+            if overrides(vm, &fct, fct_id) {
+                Some(fct.id)
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+fn overrides(vm: &VM, fct: &Fct, target: FctId) -> bool {
+    let mut overrides = fct.overrides;
+
+    while let Some(fct_id) = overrides {
+        if fct_id == target {
+            return true;
+        }
+
+        overrides = vm.fcts.idx(fct_id).read().overrides;
+    }
+
+    false
+}
+
+fn trap(trap: Trap) -> ! {
+    stdlib::trap(trap.int());
+    unreachable!();
+}
+
+struct Interpreter<'a, 'ast: 'a> {
+    vm: &'a VM<'ast>,
+    fcts: HashMap<FctId, BytecodeFunction>,
+    thread: Arc<DoraThread>,
+}
+
+impl<'a, 'ast> Interpreter<'a, 'ast> {
+    // exceptions are returned as `Err` with the thrown object
+    fn invoke(&self, fct_id: FctId, args: &[Value]) -> Result<Value, Address> {
+        let native = {
+            let fct = self.vm.fcts.idx(fct_id);
+            let fct = fct.read();
+
+            match fct.kind {
+                FctKind::Native(ptr) => Some((ptr, fct.return_type)),
+                _ => None,
+            }
+        };
+
+        if let Some((ptr, return_type)) = native {
+            return self.invoke_native(ptr, return_type, args);
+        }
+
+        let bytecode = match self.fcts.get(&fct_id) {
+            Some(bytecode) => bytecode,
+            None => return self.invoke_compiled(fct_id, args),
+        };
+        let mut frame = Box::new(Frame {
+            registers: vec![Value::Int(0); bytecode.registers],
+            acc: Value::Int(0),
+        });
+        frame.registers[..args.len()].copy_from_slice(args);
+
+        self.thread.interpreter.push(&mut *frame as *mut Frame);
+        let result = self.execute(bytecode, &mut frame);
+        self.thread.interpreter.pop();
+
+        result
+    }
+
+    fn invoke_native(
+        &self,
+        ptr: Address,
+        return_type: BuiltinType,
+        args: &[Value],
+    ) -> Result<Value, Address> {
+        let a = |idx: usize| args.get(idx).map(|arg| arg.to_usize()).unwrap_or(0);
+        let ptr = ptr.to_ptr::<u8>();
+
+        let result =
+            unsafe {
+                match args.len() {
+                    0 => transmute::<_, extern "C" fn() -> usize>(ptr)(),
+                    1 => transmute::<_, extern "C" fn(usize) -> usize>(ptr)(a(0)),
+                    2 => transmute::<_, extern "C" fn(usize, usize) -> usize>(ptr)(a(0), a(1)),
+                    3 => transmute::<_, extern "C" fn(usize, usize, usize) -> usize>(ptr)(
+                        a(0),
+                        a(1),
+                        a(2),
+                    ),
+                    4 => transmute::<_, extern "C" fn(usize, usize, usize, usize) -> usize>(ptr)(
+                        a(0),
+                        a(1),
+                        a(2),
+                        a(3),
+                    ),
+                    5 => transmute::<_, extern "C" fn(usize, usize, usize, usize, usize) -> usize>(
+                        ptr,
+                    )(a(0), a(1), a(2), a(3), a(4)),
+                    6 => transmute::<
+                        _,
+                        extern "C" fn(usize, usize, usize, usize, usize, usize) -> usize,
+                    >(ptr)(a(0), a(1), a(2), a(3), a(4), a(5)),
+                    _ => unreachable!(),
+                }
+            };
+
+        let exception = exception_get_and_clear();
+
+        if !exception.is_null() {
+            return Err(Address::from_ptr(exception));
+        }
+
+        Ok(Value::from_usize(result, return_type))
+    }
+
+    fn invoke_compiled(&self, fct_id: FctId, args: &[Value]) -> Result<Value, Address> {
+        let return_type = self.vm.fcts.idx(fct_id).read().return_type;
+        let ptr = self.vm.ensure_compiled(fct_id);
+        let entry = self.vm.interpreter_entry_thunk().to_ptr::<u8>();
+        let tld = Address::from_ptr(&self.thread.tld as *const _);
+
+        let mut ints = [0usize; COMPILED_INT_ARGS];
+        let mut floats = [0f64; COMPILED_FLOAT_ARGS];
+        let mut int_idx = 0;
+        let mut float_idx = 0;
+
+        for &arg in args {
+            match arg {
+                // the callee only reads the lower half of the register
+                Value::Float(value) => {
+                    floats[float_idx] = f64::from_bits(value.to_bits() as u64);
+                    float_idx += 1;
+                }
+
+                Value::Double(value) => {
+                    floats[float_idx] = value;
+                    float_idx += 1;
+                }
+
+                _ => {
+                    ints[int_idx] = arg.to_usize();
+                    int_idx += 1;
+                }
+            }
+        }
+
+        let i = ints;
+        let f = floats;
+
+        let result = unsafe {
+            if return_type.is_float() {
+                let entry = transmute::<_, CompiledEntry<f64>>(entry);
+                let result = entry(
+                    tld, ptr, i[0], i[1], i[2], i[3], f[0], f[1], f[2], f[3], f[4], f[5], f[6],
+                    f[7],
+                );
+
+                if return_type == BuiltinType::Float {
+                    Value::Float(f32::from_bits(result.to_bits() as u32))
+                } else {
+                    Value::Double(result)
+                }
+            } else {
+                let entry = transmute::<_, CompiledEntry<usize>>(entry);
+                let result = entry(
+                    tld, ptr, i[0], i[1], i[2], i[3], f[0], f[1], f[2], f[3], f[4], f[5], f[6],
+                    f[7],
+                );
+
+                Value::from_usize(result, return_type)
+            }
+        };
+
+        let exception = exception_get_and_clear();
+
+        if !exception.is_null() {
+            return Err(Address::from_ptr(exception));
+        }
+
+        Ok(result)
+    }
+
+    fn execute(&self, bytecode: &BytecodeFunction, frame: &mut Frame) -> Result<Value, Address> {
+        let mut pc = 0;
+
+        loop {
+            let btcode = &bytecode.code[pc];
+            pc += 1;
+
+            match *btcode {
+                Bytecode::Ldar(Register(reg)) => frame.acc = frame.registers[reg],
+                Bytecode::Star(Register(reg)) => frame.registers[reg] = frame.acc,
+
+                Bytecode::LdaZero => frame.acc = Value::Int(0),
+                Bytecode::LdaInt(value) => frame.acc = Value::Int(value as i32),
+                Bytecode::LdaLong(value) => frame.acc = Value::Long(value as i64),
+                Bytecode::LdaByte(value) => frame.acc = Value::Byte(value),
+                Bytecode::LdaChar(value) => frame.acc = Value::Char(value as u32),
+                Bytecode::LdaFloat(value) => frame.acc = Value::Float(value),
+                Bytecode::LdaDouble(value) => frame.acc = Value::Double(value),
+                Bytecode::LdaTrue => frame.acc = Value::Bool(true),
+                Bytecode::LdaFalse => frame.acc = Value::Bool(false),
+                Bytecode::LdaNil => frame.acc = Value::Ptr(Address::null()),
+                Bytecode::LdaStr(idx) => {
+                    frame.acc = Value::Ptr(Address::from_ptr(bytecode.strings[idx].raw()))
+                }
+
+                Bytecode::Add(Register(reg))
+                | Bytecode::Sub(Register(reg))
+                | Bytecode::Mul(Register(reg))
+                | Bytecode::Div(Register(reg))
+                | Bytecode::Mod(Register(reg))
+                | Bytecode::BitwiseAnd(Register(reg))
+                | Bytecode::BitwiseOr(Register(reg))
+                | Bytecode::BitwiseXor(Register(reg))
+                | Bytecode::ShiftLeft(Register(reg))
+                | Bytecode::ShiftRight(Register(reg))
+                | Bytecode::UnsignedShiftRight(Register(reg)) => {
+                    frame.acc = binary(btcode, frame.registers[reg], frame.acc);
+                }
+
+                Bytecode::TestEqual(Register(reg))
+                | Bytecode::TestNotEqual(Register(reg))
+                | Bytecode::TestLessThan(Register(reg))
+                | Bytecode::TestLessThanOrEqual(Register(reg))
+                | Bytecode::TestGreatherThan(Register(reg))
+                | Bytecode::TestGreatherThanOrEqual(Register(reg)) => {
+                    frame.acc = Value::Bool(compare(btcode, frame.registers[reg], frame.acc));
+                }
+
+                Bytecode::Neg => {
+                    frame.acc = match frame.acc {
+                        Value::Int(value) => Value::Int(value.wrapping_neg()),
+                        Value::Long(value) => Value::Long(value.wrapping_neg()),
+                        Value::Float(value) => Value::Float(-value),
+                        Value::Double(value) => Value::Double(-value),
+                        _ => unreachable!(),
+                    }
+                }
+
+                Bytecode::LogicalNot => {
+                    frame.acc = match frame.acc {
+                        Value::Bool(value) => Value::Bool(!value),
+                        Value::Byte(value) => Value::Byte(!value),
+                        Value::Int(value) => Value::Int(!value),
+                        Value::Long(value) => Value::Long(!value),
+                        _ => unreachable!(),
+                    }
+                }
+
+                Bytecode::Conv(ty) => frame.acc = convert(frame.acc, ty),
+
+                Bytecode::Jump(label) => pc = bytecode.label(label),
+                Bytecode::JumpIfFalse(label) => {
+                    if !frame.acc.bool() {
+                        pc = bytecode.label(label);
+                    }
+                }
+                Bytecode::JumpIfTrue(label) => {
+                    if frame.acc.bool() {
+                        pc = bytecode.label(label);
+                    }
+                }
+
+                Bytecode::Return => return Ok(frame.acc),
+                Bytecode::ReturnVoid => return Ok(Value::Int(0)),
+
+                Bytecode::LdaField(Register(reg), cls_id, field_id) => {
+                    let obj = nil_check(frame.registers[reg].ptr());
+                    let (offset, ty) = self.field(cls_id, field_id);
+                    frame.acc = load(obj.offset(offset), ty);
+                }
+
+                Bytecode::StaField(Register(reg), cls_id, field_id) => {
+                    let obj = nil_check(frame.registers[reg].ptr());
+                    let (offset, ty) = self.field(cls_id, field_id);
+                    self.store(obj.offset(offset), ty, frame.acc);
+                }
+
+                Bytecode::ArrayLength => {
+                    let array = nil_check(frame.acc.ptr());
+                    frame.acc = Value::Int(array_length(array) as i32);
+                }
+
+                Bytecode::LdaArray(Register(reg), ty) => {
+                    let array = nil_check(frame.registers[reg].ptr());
+                    let element = self.array_element(array, frame.acc.int(), ty);
+                    frame.acc = load(element, ty);
+                }
+
+                Bytecode::StaArray(Register(array), Register(index), ty) => {
+                    let array = nil_check(frame.registers[array].ptr());
+                    let element = self.array_element(array, frame.registers[index].int(), ty);
+                    self.store(element, ty, frame.acc);
+                }
+
+                Bytecode::NewObject(cls_id) => {
+                    let obj = object::alloc(self.vm, cls_id);
+                    frame.acc = Value::Ptr(Address::from_ptr(obj.raw()));
+                }
+
+                Bytecode::NewArray(cls_id, Register(reg), ty) => {
+                    let length = frame.registers[reg].int();
+
+                    if length < 0 {
+                        trap(Trap::INDEX_OUT_OF_BOUNDS);
+                    }
+
+                    frame.acc = self.alloc_array(cls_id, length as usize, frame, ty);
+                }
+
+                Bytecode::InvokeStatic(fct_id, Register(start), count)
+                | Bytecode::InvokeVirtual(fct_id, Register(start), count) => {
+                    let fct_id = match *btcode {
+                        Bytecode::InvokeVirtual(_, _, _) => {
+                            let receiver = nil_check(frame.registers[start].ptr());
+                            self.find_override(receiver, fct_id)
+                        }
+
+                        _ => fct_id,
+                    };
+
+                    let args = frame.registers[start..start + count].to_vec();
+
+                    match self.invoke(fct_id, &args) {
+                        Ok(value) => frame.acc = value,
+                        Err(exception) => pc = self.handle(bytecode, frame, pc - 1, exception)?,
+                    }
+                }
+
+                Bytecode::Throw => {
+                    let exception = nil_check(frame.acc.ptr());
+                    pc = self.handle(bytecode, frame, pc - 1, exception)?;
+                }
+
+                Bytecode::Trap(trap_id) => trap(trap_id),
+
+                Bytecode::Assert => {
+                    if !frame.acc.bool() {
+                        trap(Trap::ASSERT);
+                    }
+                }
+
+                Bytecode::InstanceOf(cls_id) => {
+                    let obj = frame.acc.ptr();
+                    frame.acc = Value::Bool(!obj.is_null() && is_subclass(obj, cls_id));
+                }
+
+                Bytecode::CheckedCast(cls_id) => {
+                    let obj = frame.acc.ptr();
+
+                    if !obj.is_null() && !is_subclass(obj, cls_id) {
+                        trap(Trap::CAST);
+                    }
+                }
+            }
+        }
+    }
+
+    // returns the position of the matching catch block, the exception
+    // is propagated to the caller if no handler matches
+    fn handle(
+        &self,
+        bytecode: &BytecodeFunction,
+        frame: &mut Frame,
+        pc: usize,
+        exception: Address,
+    ) -> Result<usize, Address> {
+        let cls_id = class_of(exception);
+
+        for handler in &bytecode.handlers {
+            if pc < handler.try_start || pc >= handler.try_end {
+                continue;
+            }
+
+            if handler
+                .catch_type
+                .map_or(true, |catch_type| catch_type == cls_id)
+            {
+                if let Some(Register(reg)) = handler.register {
+                    frame.registers[reg] = Value::Ptr(exception);
+                }
+
+                return Ok(handler.catch);
+            }
+        }
+
+        Err(exception)
+    }
+
+    fn find_override(&self, receiver: Address, fct_id: FctId) -> FctId {
+        let cls_id = self.vm.class_defs.idx(class_of(receiver)).read().cls_id;
+        let mut cls_id = Some(cls_id);
+
+        while let Some(id) = cls_id {
+            let cls = self.vm.classes.idx(id);
+            let cls = cls.read();
+
+            for &method in &cls.methods {
+                if method == fct_id || overrides(self.vm, &self.vm.fcts.idx(method).read(), fct_id)
+                {
+                    return method;
+                }
+            }
+
+            cls_id = cls.parent_class;
+        }
+
+        fct_id
+    }
+
+    fn field(&self, cls_id: ClassDefId, field_id: FieldId) -> (usize, BytecodeType) {
+        let cls = self.vm.class_defs.idx(cls_id);
+        let cls = cls.read();
+        let field = &cls.fields[field_id.idx()];
+
+        (
+            field.offset as usize,
+            BytecodeType::from_ty(field.ty).unwrap(),
+        )
+    }
+
+    fn array_element(&self, array: Address, index: i32, ty: BytecodeType) -> Address {
+        if !self.vm.args.flag_omit_bounds_check
+            && (index < 0 || index as usize >= array_length(array))
+        {
+            trap(Trap::INDEX_OUT_OF_BOUNDS);
+        }
+
+        array
+            .offset(offset_of_array_data() as usize)
+            .offset(index as usize * size(ty))
+    }
+
+    // the element is read from the accumulator after the allocation, since
+    // the garbage collector might have moved it
+    fn alloc_array(
+        &self,
+        cls_id: ClassDefId,
+        len: usize,
+        frame: &Frame,
+        ty: BytecodeType,
+    ) -> Value {
+        let array = match (ty, frame.acc) {
+            (BytecodeType::Bool, Value::Bool(elem)) => {
+                Array::<bool>::alloc(self.vm, len, elem, cls_id).cast::<u8>()
+            }
+            (BytecodeType::Byte, Value::Byte(elem)) => {
+                Array::<u8>::alloc(self.vm, len, elem, cls_id).cast::<u8>()
+            }
+            (BytecodeType::Char, Value::Char(elem)) => {
+                Array::<i32>::alloc(self.vm, len, elem as i32, cls_id).cast::<u8>()
+            }
+            (BytecodeType::Int, Value::Int(elem)) => {
+                Array::<i32>::alloc(self.vm, len, elem, cls_id).cast::<u8>()
+            }
+            (BytecodeType::Long, Value::Long(elem)) => {
+                Array::<i64>::alloc(self.vm, len, elem, cls_id).cast::<u8>()
+            }
+            (BytecodeType::Float, Value::Float(elem)) => {
+                Array::<f32>::alloc(self.vm, len, elem, cls_id).cast::<u8>()
+            }
+            (BytecodeType::Double, Value::Double(elem)) => {
+                Array::<f64>::alloc(self.vm, len, elem, cls_id).cast::<u8>()
+            }
+            (BytecodeType::Ptr, Value::Ptr(_)) => {
+                let array = Array::<Ref<Str>>::alloc(self.vm, len, Ref::null(), cls_id);
+                let data = Address::from_ptr(array.data());
+
+                for idx in 0..len {
+                    self.store(data.offset(idx * mem::ptr_width() as usize), ty, frame.acc);
+                }
+
+                array.cast::<u8>()
+            }
+            _ => unreachable!(),
+        };
+
+        Value::Ptr(Address::from_ptr(array.raw()))
+    }
+
+    fn store(&self, addr: Address, ty: BytecodeType, value: Value) {
+        unsafe {
+            match value {
+                Value::Bool(value) => *addr.to_mut_ptr::<bool>() = value,
+                Value::Byte(value) => *addr.to_mut_ptr::<u8>() = value,
+                Value::Char(value) => *addr.to_mut_ptr::<u32>() = value,
+                Value::Int(value) => *addr.to_mut_ptr::<i32>() = value,
+                Value::Long(value) => *addr.to_mut_ptr::<i64>() = value,
+                Value::Float(value) => *addr.to_mut_ptr::<f32>() = value,
+                Value::Double(value) => *addr.to_mut_ptr::<f64>() = value,
+                Value::Ptr(value) => *addr.to_mut_ptr::<Address>() = value,
+            }
+        }
+
+        if ty == BytecodeType::Ptr && self.vm.gc.needs_write_barrier() {
+            let card = self.vm.gc.card_table_offset() + (addr.to_usize() >> CARD_SIZE_BITS);
+            unsafe {
+                *(card as *mut u8) = 0;
+            }
+        }
+    }
+}
+
+fn binary(btcode: &Bytecode, lhs: Value, rhs: Value) -> Value {
+    match (lhs, rhs) {
+        (Value::Int(lhs), Value::Int(rhs)) => Value::Int(match *btcode {
+            Bytecode::Add(_) => lhs.wrapping_add(rhs),
+            Bytecode::Sub(_) => lhs.wrapping_sub(rhs),
+            Bytecode::Mul(_) => lhs.wrapping_mul(rhs),
+            Bytecode::Div(_) => lhs.wrapping_div(div_check(rhs as i64) as i32),
+            Bytecode::Mod(_) => lhs.wrapping_rem(div_check(rhs as i64) as i32),
+            Bytecode::BitwiseAnd(_) => lhs & rhs,
+            Bytecode::BitwiseOr(_) => lhs | rhs,
+            Bytecode::BitwiseXor(_) => lhs ^ rhs,
+            Bytecode::ShiftLeft(_) => lhs.wrapping_shl(rhs as u32),
+            Bytecode::ShiftRight(_) => lhs.wrapping_shr(rhs as u32),
+            Bytecode::UnsignedShiftRight(_) => (lhs as u32).wrapping_shr(rhs as u32) as i32,
+            _ => unreachable!(),
+        }),
+
+        (Value::Long(lhs), Value::Long(rhs)) => Value::Long(match *btcode {
+            Bytecode::Add(_) => lhs.wrapping_add(rhs),
+            Bytecode::Sub(_) => lhs.wrapping_sub(rhs),
+            Bytecode::Mul(_) => lhs.wrapping_mul(rhs),
+            Bytecode::Div(_) => lhs.wrapping_div(div_check(rhs)),
+            Bytecode::Mod(_) => lhs.wrapping_rem(div_check(rhs)),
+            Bytecode::BitwiseAnd(_) => lhs & rhs,
+            Bytecode::BitwiseOr(_) => lhs | rhs,
+            Bytecode::BitwiseXor(_) => lhs ^ rhs,
+            Bytecode::ShiftLeft(_) => lhs.wrapping_shl(rhs as u32),
+            Bytecode::ShiftRight(_) => lhs.wrapping_shr(rhs as u32),
+            Bytecode::UnsignedShiftRight(_) => (lhs as u64).wrapping_shr(rhs as u32) as i64,
+            _ => unreachable!(),
+        }),
+
+        (Value::Float(lhs), Value::Float(rhs)) => Value::Float(match *btcode {
+            Bytecode::Add(_) => lhs + rhs,
+            Bytecode::Sub(_) => lhs - rhs,
+            Bytecode::Mul(_) => lhs * rhs,
+            Bytecode::Div(_) => lhs / rhs,
+            _ => unreachable!(),
+        }),
+
+        (Value::Double(lhs), Value::Double(rhs)) => Value::Double(match *btcode {
+            Bytecode::Add(_) => lhs + rhs,
+            Bytecode::Sub(_) => lhs - rhs,
+            Bytecode::Mul(_) => lhs * rhs,
+            Bytecode::Div(_) => lhs / rhs,
+            _ => unreachable!(),
+        }),
+
+        _ => unreachable!(),
+    }
+}
+
+fn div_check(value: i64) -> i64 {
+    if value == 0 {
+        trap(Trap::DIV0);
+    }
+
+    value
+}
+
+fn compare(btcode: &Bytecode, lhs: Value, rhs: Value) -> bool {
+    match *btcode {
+        Bytecode::TestEqual(_) => lhs == rhs,
+        Bytecode::TestNotEqual(_) => lhs != rhs,
+        Bytecode::TestLessThan(_) => lt(lhs, rhs),
+        Bytecode::TestLessThanOrEqual(_) => lt(lhs, rhs) || lhs == rhs,
+        Bytecode::TestGreatherThan(_) => lt(rhs, lhs),
+        Bytecode::TestGreatherThanOrEqual(_) => lt(rhs, lhs) || lhs == rhs,
+        _ => unreachable!(),
+    }
+}
+
+fn lt(lhs: Value, rhs: Value) -> bool {
+    match (lhs, rhs) {
+        (Value::Byte(lhs), Value::Byte(rhs)) => lhs < rhs,
+        (Value::Char(lhs), Value::Char(rhs)) => lhs < rhs,
+        (Value::Int(lhs), Value::Int(rhs)) => lhs < rhs,
+        (Value::Long(lhs), Value::Long(rhs)) => lhs < rhs,
+        (Value::Float(lhs), Value::Float(rhs)) => lhs < rhs,
+        (Value::Double(lhs), Value::Double(rhs)) => lhs < rhs,
+        _ => unreachable!(),
+    }
+}
+
+fn convert(value: Value, ty: BytecodeType) -> Value {
+    let (long, double) = match value {
+        Value::Bool(value) => (value as i64, 0.0),
+        Value::Byte(value) => (value as i64, 0.0),
+        Value::Char(value) => (value as i64, 0.0),
+        Value::Int(value) => (value as i64, value as f64),
+        Value::Long(value) => (value, value as f64),
+        Value::Float(value) => (value as i64, value as f64),
+        Value::Double(value) => (value as i64, value),
+        Value::Ptr(_) => unreachable!(),
+    };
+
+    match ty {
+        BytecodeType::Byte => Value::Byte(long as u8),
+        BytecodeType::Char => Value::Char(long as u32),
+        BytecodeType::Int => match value {
+            Value::Float(value) => Value::Int(value as i32),
+            Value::Double(value) => Value::Int(value as i32),
+            _ => Value::Int(long as i32),
+        },
+        BytecodeType::Long => Value::Long(long),
+        BytecodeType::Float => match value {
+            Value::Long(value) => Value::Float(value as f32),
+            _ => Value::Float(double as f32),
+        },
+        BytecodeType::Double => Value::Double(double),
+        BytecodeType::Bool | BytecodeType::Ptr => unreachable!(),
+    }
+}
+
+fn nil_check(obj: Address) -> Address {
+    if obj.is_null() {
+        trap(Trap::NIL);
+    }
+
+    obj
+}
+
+fn class_of(obj: Address) -> ClassDefId {
+    obj.to_obj().header().vtbl().class().id
+}
+
+fn is_subclass(obj: Address, cls_id: ClassDefId) -> bool {
+    let mut cls = Some(obj.to_obj().header().vtbl().class().id);
+    let vm = ::ctxt::get_vm();
+
+    while let Some(id) = cls {
+        if id == cls_id {
+            return true;
+        }
+
+        cls = vm.class_defs.idx(id).read().parent_id;
+    }
+
+    false
+}
+
+fn array_length(array: Address) -> usize {
+    unsafe {
+        *array
+            .offset(offset_of_array_length() as usize)
+            .to_ptr::<usize>()
+    }
+}
+
+fn load(addr: Address, ty: BytecodeType) -> Value {
+    unsafe {
+        match ty {
+            BytecodeType::Bool => Value::Bool(*addr.to_ptr::<bool>()),
+            BytecodeType::Byte => Value::Byte(*addr.to_ptr::<u8>()),
+            BytecodeType::Char => Value::Char(*addr.to_ptr::<u32>()),
+            BytecodeType::Int => Value::Int(*addr.to_ptr::<i32>()),
+            BytecodeType::Long => Value::Long(*addr.to_ptr::<i64>()),
+            BytecodeType::Float => Value::Float(*addr.to_ptr::<f32>()),
+            BytecodeType::Double => Value::Double(*addr.to_ptr::<f64>()),
+            BytecodeType::Ptr => Value::Ptr(*addr.to_ptr::<Address>()),
+        }
+    }
+}
+
+fn size(ty: BytecodeType) -> usize {
+    match ty {
+        BytecodeType::Bool | BytecodeType::Byte => 1,
+        BytecodeType::Char | BytecodeType::Int | BytecodeType::Float => 4,
+        BytecodeType::Long | BytecodeType::Double | BytecodeType::Ptr => 8,
+    }
+}
@@ -1 +1,2 @@
 pub mod bytecodegen;
+pub mod interpreter;
@@ -6,7 +6,7 @@ use std::ops::{Index, IndexMut};
 use std::sync::Arc;
 
 use ctxt::VM;
-use ctxt::{FctId, ImplId, TraitId, TypeParam};
+use ctxt::{FctId, ImplId, ModuleId, TraitId, TypeParam};
 use dora_parser::interner::Name;
 use dora_parser::lexer::position::Position;
 use ty::BuiltinType;
@@ -47,6 +47,7 @@ pub struct Class {
     pub id: ClassId,
     pub pos: Position,
     pub name: Name,
+    pub module: Option<ModuleId>,
     pub ty: BuiltinType,
     pub parent_class: Option<ClassId>,
     pub has_open: bool,
@@ -249,7 +250,7 @@ impl From<usize> for TypeParamId {
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub struct FieldId(usize);
 
 impl FieldId {
@@ -271,6 +272,7 @@ pub struct Field {
     pub ty: BuiltinType,
     pub offset: i32,
     pub reassignable: bool,
+    pub is_pub: bool,
 }
 
 impl Index<FieldId> for Vec<Field> {
@@ -287,7 +289,7 @@ impl IndexMut<FieldId> for Vec<Field> {
     }
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct ClassDefId(usize);
 
 impl From<usize> for ClassDefId {
@@ -307,6 +309,9 @@ pub enum ClassSize {
     Fixed(i32),
     Array(i32),
     ObjArray,
+    // array of structs or tuples with references, `ref_fields`
+    // stores the offsets of the references within an element
+    StructArray(i32),
     Str,
 }
 
@@ -4,6 +4,9 @@ pub const REG_COUNT: usize = 31;
 pub static REG_PARAMS: [Reg; 8] = [R0, R1, R2, R3, R4, R5, R6, R7];
 pub static SCRATCH: [Reg; 5] = [R9, R12, R13, R14, R15];
 
+// optimized code is only generated on x64
+pub static CALLEE_SAVED: [Reg; 0] = [];
+
 pub const REG_RESULT: Reg = R0;
 pub const REG_TMP1: Reg = R10;
 pub const REG_TMP2: Reg = R11;
@@ -50,6 +53,9 @@ pub const FREG_RESULT: FReg = F0;
 pub const FREG_TMP1: FReg = F1;
 pub static FREG_PARAMS: [FReg; 8] = [F0, F1, F2, F3, F4, F5, F6, F7];
 
+// optimized code is only generated on x64
+pub static FREG_OPT: [FReg; 0] = [];
+
 pub const F0: FReg = FReg(0);
 pub const F1: FReg = FReg(1);
 pub const F2: FReg = FReg(2);
@@ -30,6 +30,7 @@ impl From<FReg> for u32 {
     }
 }
 
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub enum Mem {
     // rbp + val1
     Local(i32),
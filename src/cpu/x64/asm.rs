@@ -462,6 +462,14 @@ pub fn emit_jmp(buf: &mut MacroAssembler, lbl: Label) {
     buf.emit_label(lbl);
 }
 
+// lea dest, [rip + <label>]
+pub fn lea_label(buf: &mut MacroAssembler, dest: Reg, lbl: Label) {
+    emit_rex(buf, 1, dest.msb(), 0, 0);
+    emit_op(buf, 0x8D);
+    emit_modrm(buf, 0b00, dest.and7(), 0b101);
+    buf.emit_label(lbl);
+}
+
 pub fn emit_jmp_reg(buf: &mut MacroAssembler, reg: Reg) {
     if reg.msb() != 0 {
         emit_rex(buf, 0, 0, 0, reg.msb());
@@ -711,6 +719,37 @@ pub fn emit_cmp_mem_imm(
     }
 }
 
+pub fn emit_add_mem_imm(
+    buf: &mut MacroAssembler,
+    mode: MachineMode,
+    base: Reg,
+    disp: i32,
+    imm: i32,
+) {
+    let base_msb = if base == RIP { 0 } else { base.msb() };
+
+    let opcode = if fits_i8(imm) { 0x83 } else { 0x81 };
+
+    let x64 = match mode {
+        MachineMode::Int32 => 0,
+        MachineMode::Ptr => 1,
+        _ => unimplemented!(),
+    };
+
+    if x64 != 0 || base_msb != 0 {
+        emit_rex(buf, x64, 0, 0, base_msb);
+    }
+
+    emit_op(buf, opcode);
+    emit_membase(buf, base, disp, RAX);
+
+    if fits_i8(imm) {
+        emit_u8(buf, imm as u8);
+    } else {
+        emit_u32(buf, imm as u32);
+    }
+}
+
 pub fn emit_cmp_memindex_reg(
     buf: &mut MacroAssembler,
     mode: MachineMode,
@@ -1435,6 +1474,22 @@ mod tests {
         assert_eq!(vec![0xe9, 1, 0, 0, 0, 0x90], buf.data());
     }
 
+    #[test]
+    fn test_lea_label() {
+        let mut buf = MacroAssembler::new();
+        let lbl = buf.create_label();
+        lea_label(&mut buf, RAX, lbl);
+        emit_nop(&mut buf);
+        buf.bind_label(lbl);
+        assert_eq!(vec![0x48, 0x8d, 0x05, 1, 0, 0, 0, 0x90], buf.data());
+
+        let mut buf = MacroAssembler::new();
+        let lbl = buf.create_label();
+        buf.bind_label(lbl);
+        lea_label(&mut buf, R9, lbl);
+        assert_eq!(vec![0x4c, 0x8d, 0x0d, 0xf9, 0xff, 0xff, 0xff], buf.data());
+    }
+
     #[test]
     fn test_emit_movl_memq_reg() {
         assert_emit!(0x8b, 0x44, 0x24, 1; emit_movl_memq_reg(RSP, 1, RAX));
@@ -1860,6 +1915,22 @@ mod tests {
         assert_emit!(0x41, 0x80, 0xBF, 0, 1, 0, 0, 2; emit_cmp_mem_imm(b, R15, 256, 2));
     }
 
+    #[test]
+    fn test_add_mem_imm() {
+        let p = MachineMode::Ptr;
+
+        // add [r11+80], 1
+        assert_emit!(0x49, 0x83, 0x43, 80, 1; emit_add_mem_imm(p, R11, 80, 1));
+
+        // add [rbx+1], 256
+        assert_emit!(0x48, 0x81, 0x43, 1, 0, 1, 0, 0; emit_add_mem_imm(p, RBX, 1, 256));
+
+        let i = MachineMode::Int32;
+
+        // add [rbx+256], 2
+        assert_emit!(0x83, 0x83, 0, 1, 0, 0, 2; emit_add_mem_imm(i, RBX, 256, 2));
+    }
+
     #[test]
     #[should_panic]
     fn test_cmp_mem_imm_i32_for_i8() {
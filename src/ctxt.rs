@@ -30,7 +30,7 @@ use stdlib;
 use sym::Sym::*;
 use sym::*;
 use threads::{Threads, THREAD};
-use ty::{BuiltinType, LambdaTypes, TypeLists};
+use ty::{BuiltinType, LambdaId, LambdaTypes, TupleTypes, TypeLists};
 use utils::GrowableVec;
 
 pub static mut EXCEPTION_OBJECT: *const u8 = 0 as *const u8;
@@ -83,6 +83,9 @@ pub struct SemContext<'ast> {
     pub consts: GrowableVec<Mutex<ConstData<'ast>>>, // stores all const definitions
     pub structs: GrowableVec<Mutex<StructData>>,     // stores all struct source definitions
     pub struct_defs: GrowableVec<Mutex<StructDef>>,  // stores all struct definitions
+    pub enums: GrowableVec<Mutex<EnumData>>,         // stores all enum definitions
+    pub type_aliases: Vec<TypeAliasData<'ast>>,      // stores all type alias definitions
+    pub modules: Vec<ModuleData>,                    // stores all modules
     pub classes: GrowableVec<RwLock<Class>>,         // stores all class source definitions
     pub class_defs: GrowableVec<RwLock<ClassDef>>,   // stores all class definitions
     pub fcts: GrowableVec<RwLock<Fct<'ast>>>,        // stores all function definitions
@@ -96,8 +99,15 @@ pub struct SemContext<'ast> {
     pub polling_page: PollingPage,
     pub lists: Mutex<TypeLists>,
     pub lambda_types: Mutex<LambdaTypes>,
+    pub tuple_types: Mutex<TupleTypes>,
+    pub type_alias_uses: Mutex<HashSet<ast::NodeId>>, // types that name a type alias
     pub compiler_thunk: Mutex<Address>,
+    pub tier_up_thunk: Mutex<Address>,
+    pub ic_miss_thunk: Mutex<Address>,
+    pub ic_lock: Mutex<()>, // serializes updates of inline caches
+    pub gdb_lock: Mutex<()>, // serializes updates of the GDB JIT descriptor
     pub dora_entry: Mutex<Address>,
+    pub interpreter_entry: Mutex<Address>,
     pub trap_thunk: Mutex<Address>,
     pub throw_thunk: Mutex<Address>,
     pub threads: Threads,
@@ -115,6 +125,9 @@ impl<'ast> SemContext<'ast> {
             consts: GrowableVec::new(),
             structs: GrowableVec::new(),
             struct_defs: GrowableVec::new(),
+            enums: GrowableVec::new(),
+            type_aliases: Vec::new(),
+            modules: Vec::new(),
             classes: GrowableVec::new(),
             class_defs: GrowableVec::new(),
             traits: Vec::new(),
@@ -142,6 +155,8 @@ impl<'ast> SemContext<'ast> {
                 comparable_trait: empty_trait_id,
                 iterator_trait: Mutex::new(None),
 
+                range_fct: 0.into(),
+
                 int_array_def: Mutex::new(None),
                 str_class_def: Mutex::new(None),
                 obj_class_def: Mutex::new(None),
@@ -158,9 +173,16 @@ impl<'ast> SemContext<'ast> {
             polling_page: PollingPage::new(),
             lists: Mutex::new(TypeLists::new()),
             lambda_types: Mutex::new(LambdaTypes::new()),
+            tuple_types: Mutex::new(TupleTypes::new()),
+            type_alias_uses: Mutex::new(HashSet::new()),
             native_thunks: Mutex::new(NativeThunks::new()),
             compiler_thunk: Mutex::new(Address::null()),
+            tier_up_thunk: Mutex::new(Address::null()),
+            ic_miss_thunk: Mutex::new(Address::null()),
+            ic_lock: Mutex::new(()),
+            gdb_lock: Mutex::new(()),
             dora_entry: Mutex::new(Address::null()),
+            interpreter_entry: Mutex::new(Address::null()),
             trap_thunk: Mutex::new(Address::null()),
             throw_thunk: Mutex::new(Address::null()),
             threads: Threads::new(),
@@ -200,7 +222,7 @@ impl<'ast> SemContext<'ast> {
         fct(tld, ptr, testing);
     }
 
-    fn ensure_compiled(&self, fct_id: FctId) -> Address {
+    pub fn ensure_compiled(&self, fct_id: FctId) -> Address {
         let mut dtn = DoraToNativeInfo::new();
         let type_params = TypeParams::empty();
 
@@ -236,8 +258,9 @@ impl<'ast> SemContext<'ast> {
         let fctid = self.add_fct(fct);
 
         let mut sym = self.sym.lock();
+        let module = sym.module();
 
-        match sym.get(name) {
+        match sym.get_in_module(module, name) {
             Some(sym) => Err(sym),
             None => {
                 assert!(sym.insert(name, SymFct(fctid)).is_none());
@@ -247,6 +270,45 @@ impl<'ast> SemContext<'ast> {
         }
     }
 
+    // returns the module a file belongs to, None for the root module
+    pub fn file_module(&self, file: &ast::File) -> Option<ModuleId> {
+        let sym = self.sym.lock();
+        let mut module = None;
+
+        for name in &file.module {
+            let name = self.interner.intern(name);
+            module = sym
+                .get_in_module(module, name)
+                .and_then(|sym| sym.to_module());
+            assert!(module.is_some());
+        }
+
+        module
+    }
+
+    // qualified name of a top-level symbol in a module
+    pub fn module_path(&self, module: Option<ModuleId>, name: Name) -> String {
+        let name = self.interner.str(name);
+
+        match module {
+            Some(module) => format!("{}::{}", self.modules[module].path, name),
+            None => name.to_string(),
+        }
+    }
+
+    // finds a function of the stdlib module `internal`, programs can't
+    // name this module and its functions are only called by the compiler
+    pub fn internal_module_fct(&self, name: &str) -> Option<FctId> {
+        let sym = self.sym.lock();
+        let module = sym
+            .get_in_module(None, self.interner.intern("internal"))
+            .and_then(|sym| sym.to_module());
+
+        module
+            .and_then(|module| sym.get_in_module(Some(module), self.interner.intern(name)))
+            .and_then(|sym| sym.to_fct())
+    }
+
     #[cfg(test)]
     pub fn cls_by_name(&self, name: &'static str) -> ClassId {
         let name = self.interner.intern(name);
@@ -274,6 +336,16 @@ impl<'ast> SemContext<'ast> {
         *dora_entry_thunk
     }
 
+    pub fn interpreter_entry_thunk(&self) -> Address {
+        let mut interpreter_entry_thunk = self.interpreter_entry.lock();
+
+        if interpreter_entry_thunk.is_null() {
+            *interpreter_entry_thunk = dora_entry::generate_interpreter_entry(self);
+        }
+
+        *interpreter_entry_thunk
+    }
+
     pub fn throw_thunk(&self) -> Address {
         let mut throw_thunk = self.throw_thunk.lock();
 
@@ -294,6 +366,26 @@ impl<'ast> SemContext<'ast> {
         *compiler_thunk
     }
 
+    pub fn tier_up_thunk(&self) -> Address {
+        let mut tier_up_thunk = self.tier_up_thunk.lock();
+
+        if tier_up_thunk.is_null() {
+            *tier_up_thunk = dora_compile::generate_tier_up(self);
+        }
+
+        *tier_up_thunk
+    }
+
+    pub fn ic_miss_thunk(&self) -> Address {
+        let mut ic_miss_thunk = self.ic_miss_thunk.lock();
+
+        if ic_miss_thunk.is_null() {
+            *ic_miss_thunk = dora_compile::generate_ic_miss(self);
+        }
+
+        *ic_miss_thunk
+    }
+
     pub fn trap_thunk(&self) -> Address {
         let mut trap_thunk = self.trap_thunk.lock();
 
@@ -531,6 +623,12 @@ pub struct StructData {
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct StructFieldId(u32);
 
+impl StructFieldId {
+    pub fn idx(self) -> usize {
+        self.0 as usize
+    }
+}
+
 impl From<u32> for StructFieldId {
     fn from(data: u32) -> StructFieldId {
         StructFieldId(data)
@@ -546,6 +644,110 @@ pub struct StructFieldData {
     pub offset: i32,
 }
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct EnumId(u32);
+
+impl GrowableVec<Mutex<EnumData>> {
+    pub fn idx(&self, index: EnumId) -> Arc<Mutex<EnumData>> {
+        self.idx_usize(index.0 as usize)
+    }
+}
+
+impl From<u32> for EnumId {
+    fn from(data: u32) -> EnumId {
+        EnumId(data)
+    }
+}
+
+#[derive(Debug)]
+pub struct EnumData {
+    pub id: EnumId,
+    pub pos: Position,
+    pub name: Name,
+    pub variants: Vec<EnumVariant>,
+}
+
+impl EnumData {
+    pub fn variant_idx(&self, name: Name) -> Option<usize> {
+        self.variants.iter().position(|v| v.name == name)
+    }
+
+    pub fn has_payload(&self) -> bool {
+        self.variants.iter().any(|v| !v.types.is_empty())
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TypeAliasId(u32);
+
+impl From<u32> for TypeAliasId {
+    fn from(data: u32) -> TypeAliasId {
+        TypeAliasId(data)
+    }
+}
+
+#[derive(Debug)]
+pub struct TypeAliasData<'ast> {
+    pub id: TypeAliasId,
+    pub ast: &'ast ast::TypeAlias,
+    pub pos: Position,
+    pub name: Name,
+    pub module: Option<ModuleId>,
+    pub type_params: Vec<Name>,
+}
+
+impl<'ast> Index<TypeAliasId> for Vec<TypeAliasData<'ast>> {
+    type Output = TypeAliasData<'ast>;
+
+    fn index(&self, index: TypeAliasId) -> &TypeAliasData<'ast> {
+        &self[index.0 as usize]
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ModuleId(u32);
+
+impl ModuleId {
+    pub fn idx(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl From<usize> for ModuleId {
+    fn from(data: usize) -> ModuleId {
+        ModuleId(data as u32)
+    }
+}
+
+#[derive(Debug)]
+pub struct ModuleData {
+    pub id: ModuleId,
+    pub name: Name,
+    pub parent: Option<ModuleId>,
+
+    // qualified name, e.g. `foo::bar`
+    pub path: String,
+}
+
+impl Index<ModuleId> for Vec<ModuleData> {
+    type Output = ModuleData;
+
+    fn index(&self, index: ModuleId) -> &ModuleData {
+        &self[index.0 as usize]
+    }
+}
+
+#[derive(Debug)]
+pub struct EnumVariant {
+    pub pos: Position,
+    pub name: Name,
+    pub types: Vec<BuiltinType>,
+
+    // class describing the object layout of this variant,
+    // the first field stores the tag
+    pub cls_id: ClassId,
+}
+
 #[derive(Debug)]
 pub struct KnownElements {
     pub bool_class: ClassId,
@@ -567,6 +769,8 @@ pub struct KnownElements {
     pub comparable_trait: TraitId,
     pub iterator_trait: Mutex<Option<TraitId>>,
 
+    pub range_fct: FctId,
+
     int_array_def: Mutex<Option<ClassDefId>>,
     str_class_def: Mutex<Option<ClassDefId>>,
     obj_class_def: Mutex<Option<ClassDefId>>,
@@ -710,6 +914,7 @@ pub struct Fct<'ast> {
     pub ast: &'ast ast::Function,
     pub pos: Position,
     pub name: Name,
+    pub module: Option<ModuleId>,
     pub parent: FctParent,
     pub has_open: bool,
     pub has_override: bool,
@@ -728,6 +933,7 @@ pub struct Fct<'ast> {
     pub impl_for: Option<FctId>,
     pub initialized: bool,
     pub throws: bool,
+    pub is_lambda: bool,
 
     pub type_params: Vec<TypeParam>,
     pub kind: FctKind,
@@ -1027,10 +1233,13 @@ pub struct FctSrc {
     pub map_convs: NodeMap<ConvInfo>,
     pub map_cls: NodeMap<ClassId>,
     pub map_fors: NodeMap<ForTypeInfo>,
+    pub map_lambdas: NodeMap<LambdaInfo>,
+    pub map_matches: NodeMap<MatchInfo>,
 
     pub always_returns: bool, // true if function is always exited via return statement
     // false if function execution could reach the closing } of this function
     pub specializations: RwLock<HashMap<(TypeParams, TypeParams), JitFctId>>,
+    pub osr_entries: RwLock<HashMap<ast::NodeId, JitFctId>>, // maps loops to OSR code
     pub vars: Vec<Var>, // variables in functions
 }
 
@@ -1044,10 +1253,13 @@ impl Clone for FctSrc {
             map_convs: self.map_convs.clone(),
             map_cls: self.map_cls.clone(),
             map_fors: self.map_fors.clone(),
+            map_lambdas: self.map_lambdas.clone(),
+            map_matches: self.map_matches.clone(),
 
             vars: self.vars.clone(),
             always_returns: self.always_returns,
             specializations: RwLock::new(HashMap::new()),
+            osr_entries: RwLock::new(HashMap::new()),
         }
     }
 }
@@ -1062,10 +1274,13 @@ impl FctSrc {
             map_convs: NodeMap::new(),
             map_cls: NodeMap::new(),
             map_fors: NodeMap::new(),
+            map_lambdas: NodeMap::new(),
+            map_matches: NodeMap::new(),
 
             vars: Vec::new(),
             always_returns: false,
             specializations: RwLock::new(HashMap::new()),
+            osr_entries: RwLock::new(HashMap::new()),
         }
     }
 
@@ -1141,6 +1356,61 @@ pub struct ConvInfo {
     pub valid: bool,
 }
 
+#[derive(Debug, Clone)]
+pub struct MatchInfo {
+    pub arms: Vec<MatchArmInfo>,
+}
+
+impl MatchInfo {
+    // all arms compare against integer values, except for the
+    // last arm which matches all remaining values
+    pub fn is_int_switch(&self) -> bool {
+        let (last, rest) = self.arms.split_last().unwrap();
+
+        last.is_underscore() && rest.iter().all(|arm| arm.is_int())
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum MatchArmInfo {
+    // values of int, long, byte and char
+    Int(i64),
+    Bool(bool),
+
+    // strings are compared with Str::equals, the call is stored for the arm
+    Str,
+
+    // valid means that every value matches
+    Class(ConvInfo),
+
+    // variants are compared by their tag
+    Enum(usize),
+    Underscore,
+}
+
+impl MatchArmInfo {
+    pub fn is_int(&self) -> bool {
+        match *self {
+            MatchArmInfo::Int(_) => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_str(&self) -> bool {
+        match *self {
+            MatchArmInfo::Str => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_underscore(&self) -> bool {
+        match *self {
+            MatchArmInfo::Underscore => true,
+            _ => false,
+        }
+    }
+}
+
 #[derive(Debug, Copy, Clone)]
 pub enum Store {
     Reg,
@@ -1162,7 +1432,9 @@ pub enum IdentType {
     Global(GlobalId),
     Field(BuiltinType, FieldId),
     Struct(StructId),
+    StructField(BuiltinType, StructFieldId),
     Const(ConstId),
+    EnumValue(EnumId, usize),
 }
 
 impl IdentType {
@@ -1193,6 +1465,13 @@ impl IdentType {
             _ => false,
         }
     }
+
+    pub fn is_struct_field(&self) -> bool {
+        match *self {
+            IdentType::StructField(_, _) => true,
+            _ => false,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -1203,12 +1482,26 @@ pub struct ForTypeInfo {
     pub iterator_type: BuiltinType,
 }
 
+#[derive(Debug, Clone)]
+pub struct LambdaInfo {
+    pub fct_id: FctId,
+
+    // closure class, captured values are stored in its fields
+    pub cls_id: ClassId,
+
+    // how to load each captured value in the enclosing function,
+    // the index of a capture is the FieldId of its field
+    pub captures: Vec<IdentType>,
+}
+
 #[derive(Debug, Clone)]
 pub enum CallType {
     Fct(FctId, TypeParams, TypeParams),
     Method(BuiltinType, FctId, TypeParams),
     CtorNew(ClassId, FctId, TypeParams),
     Ctor(ClassId, FctId, TypeParams),
+    Lambda(LambdaId),
+    EnumValue(EnumId, usize),
 }
 
 impl CallType {
@@ -1233,12 +1526,21 @@ impl CallType {
         }
     }
 
+    pub fn is_lambda(&self) -> bool {
+        match *self {
+            CallType::Lambda(_) => true,
+            _ => false,
+        }
+    }
+
     pub fn fct_id(&self) -> FctId {
         match *self {
             CallType::Fct(fctid, _, _) => fctid,
             CallType::Method(_, fctid, _) => fctid,
             CallType::CtorNew(_, fctid, _) => fctid,
             CallType::Ctor(_, fctid, _) => fctid,
+            CallType::Lambda(_) => panic!("lambda call has no fct"),
+            CallType::EnumValue(_, _) => panic!("enum value has no fct"),
         }
     }
 }
@@ -1251,6 +1553,7 @@ pub struct CallSite<'ast> {
     pub args: Vec<Arg<'ast>>,
     pub super_call: bool,
     pub return_type: BuiltinType,
+    pub struct_return: Option<i32>,
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
@@ -1273,6 +1576,7 @@ pub struct ConstData<'ast> {
     pub id: ConstId,
     pub pos: Position,
     pub name: Name,
+    pub module: Option<ModuleId>,
     pub ty: BuiltinType,
     pub expr: &'ast ast::Expr,
     pub value: ConstValue,
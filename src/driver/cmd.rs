@@ -28,16 +28,32 @@ Options:
     --emit-llvm             Emits initial LLVM IR to stdout.
     --emit-asm=<fct>        Emits assembly code to stdout.
     --emit-asm-file         Emits assembly code into file `dora-<pid>.asm`.
+    --emit-bytecode=<fct>   Emits bytecode to stdout.
+    --emit-opt-ir=<fct>     Emits IR of optimized functions to stdout.
     --emit-stubs            Emits generated stubs.
+    --emit-ic-stats         Print hit and miss counters of inline caches.
     --emit-debug=<fct>      Emits debug instruction at beginning of functions.
     --emit-debug-compile    Emits debug instruction at beginning of compile thunk.
     --emit-debug-throw      Emits debug instruction at beginning of throw thunk.
     --emit-debug-entry      Emits debug instruction at beginning of entry thunk.
     --omit-bounds-check     Omit array index out of bounds checks.
     --check                 Only type check given program.
+    --deny-warnings         Treat all warnings as errors.
+    --allow=<lints>         Comma-separated list of lints to ignore.
+    --deny=<lints>          Comma-separated list of lints to treat as errors.
+                            Lints: unused-variable, unused-assignment,
+                            unreachable-code, unnecessary-var,
+                            unused-import.
+    --error-format=<fmt>    Print compiler errors for humans or as JSON.
+                            Allowed values: human, json.
+    --interpreter           Run functions in the bytecode interpreter if possible.
+    --disable-tiering       Disable recompilation of hot functions.
+    --tier-threshold=<num>  Invocations before a function is recompiled.
+    --osr-threshold=<num>   Loop iterations before a running loop is recompiled.
     --asm-syntax TYPE       Emits assembly with Intel or AT&T syntax.
                             Allowed values: intel, att.
     --enable-perf           Enable dump for perf.
+    --enable-gdb            Register compiled code with the GDB JIT interface.
     --gc-events             Dump GC events.
     --gc-stress             Collect garbage at every allocation.
     --gc-stress-minor       Minor collection at every allocation.
@@ -70,9 +86,13 @@ pub struct Args {
     pub flag_emit_ast: bool,
     pub flag_emit_asm: Option<String>,
     pub flag_emit_asm_file: bool,
+    pub flag_emit_bytecode: Option<String>,
+    pub flag_emit_opt_ir: Option<String>,
     pub flag_emit_llvm: bool,
     pub flag_emit_stubs: bool,
+    pub flag_emit_ic_stats: bool,
     pub flag_enable_perf: bool,
+    pub flag_enable_gdb: bool,
     pub flag_omit_bounds_check: bool,
     pub flag_version: bool,
     pub flag_emit_debug: Option<String>,
@@ -100,6 +120,14 @@ pub struct Args {
     pub flag_code_size: Option<MemSize>,
     pub flag_perm_size: Option<MemSize>,
     pub flag_check: bool,
+    pub flag_deny_warnings: bool,
+    pub flag_allow: Option<String>,
+    pub flag_deny: Option<String>,
+    pub flag_error_format: Option<ErrorFormat>,
+    pub flag_interpreter: bool,
+    pub flag_disable_tiering: bool,
+    pub flag_tier_threshold: Option<u32>,
+    pub flag_osr_threshold: Option<u32>,
     pub flag_disable_tlab: bool,
 
     pub cmd_test: bool,
@@ -151,6 +179,16 @@ impl Args {
     pub fn young_appel(&self) -> bool {
         self.flag_gc_young_appel
     }
+
+    pub fn tier_threshold(&self) -> i32 {
+        let threshold = self.flag_tier_threshold.unwrap_or(1000);
+        min(max(threshold, 1), i32::max_value() as u32) as i32
+    }
+
+    pub fn osr_threshold(&self) -> i32 {
+        let threshold = self.flag_osr_threshold.unwrap_or(10000);
+        min(max(threshold, 1), i32::max_value() as u32) as i32
+    }
 }
 
 impl Default for Args {
@@ -162,13 +200,17 @@ impl Default for Args {
             flag_emit_ast: false,
             flag_emit_asm: None,
             flag_emit_asm_file: false,
+            flag_emit_bytecode: None,
+            flag_emit_opt_ir: None,
             flag_emit_llvm: false,
             flag_emit_stubs: false,
+            flag_emit_ic_stats: false,
             flag_emit_debug: None,
             flag_emit_debug_compile: false,
             flag_emit_debug_throw: false,
             flag_emit_debug_entry: false,
             flag_enable_perf: false,
+            flag_enable_gdb: false,
             flag_omit_bounds_check: false,
             flag_version: false,
             flag_asm_syntax: None,
@@ -192,6 +234,14 @@ impl Default for Args {
             flag_code_size: None,
             flag_perm_size: None,
             flag_check: false,
+            flag_deny_warnings: false,
+            flag_allow: None,
+            flag_deny: None,
+            flag_error_format: None,
+            flag_interpreter: false,
+            flag_disable_tiering: false,
+            flag_tier_threshold: None,
+            flag_osr_threshold: None,
             flag_disable_tlab: false,
 
             cmd_test: false,
@@ -213,6 +263,12 @@ pub enum AsmSyntax {
     Att,
 }
 
+#[derive(Copy, Clone, Debug, RustcDecodable)]
+pub enum ErrorFormat {
+    Human,
+    Json,
+}
+
 #[derive(Copy, Clone, Debug)]
 pub struct MemSize(usize);
 
@@ -1,22 +1,28 @@
+use rustc_serialize::json::Json;
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::Path;
 
 use ctxt::VM;
 use ctxt::{exception_get_and_clear, Fct, FctId};
 use dora_parser::ast::{self, Ast};
-use dora_parser::error::msg::Msg;
+use dora_parser::error::diag::{self, Diagnostic};
+use dora_parser::error::msg::{Msg, MsgWithPos};
 
-use boots::bytecodegen::BytecodeGen;
+use baseline::codegen::fct_pattern_match;
+use baseline::ic;
+use boots::{bytecodegen, interpreter};
 use dora_parser::interner::Interner;
-use dora_parser::lexer::position::Position;
+use dora_parser::lexer::position::{FileId, Position};
 use dora_parser::lexer::reader::Reader;
-use driver::cmd;
+use driver::cmd::{self, ErrorFormat};
 use object;
 use os;
 use timer::Timer;
 
 use dora_parser::parser::{NodeIdGenerator, Parser};
 use semck;
+use semck::lintck;
 use semck::specialize::specialize_class_id;
 use ty::BuiltinType;
 
@@ -35,26 +41,52 @@ pub fn start(content: Option<&str>) -> i32 {
         return 0;
     }
 
+    if let Err(name) = lintck::check_args(&args) {
+        println!("unknown lint `{}`.", name);
+        return 1;
+    }
+
     let mut interner = Interner::new();
     let id_generator = NodeIdGenerator::new();
     let mut ast = Ast::new();
+    let mut diag = Diagnostic::new();
+    let error_format = args.flag_error_format.unwrap_or(ErrorFormat::Human);
+
+    if let Err(code) = parse_dir("stdlib", &id_generator, &mut ast, &mut interner, &mut diag)
+        .and_then(|_| {
+            if fuzzing {
+                return parse_str(
+                    content.unwrap(),
+                    &id_generator,
+                    &mut ast,
+                    &mut interner,
+                    &mut diag,
+                );
+            }
 
-    if let Err(code) = parse_dir("stdlib", &id_generator, &mut ast, &mut interner).and_then(|_| {
-        if fuzzing {
-            return parse_str(content.unwrap(), &id_generator, &mut ast, &mut interner);
+            let path = Path::new(&args.arg_file);
+
+            if path.is_file() {
+                parse_file(
+                    &args.arg_file,
+                    Vec::new(),
+                    &id_generator,
+                    &mut ast,
+                    &mut interner,
+                    &mut diag,
+                )
+            } else if path.is_dir() {
+                parse_modules(path, &[], &id_generator, &mut ast, &mut interner, &mut diag)
+            } else {
+                println!("file or directory `{}` does not exist.", &args.arg_file);
+                Err(1)
+            }
+        })
+    {
+        if diag.has_errors() {
+            report_errors(&diag, &ast, error_format);
         }
 
-        let path = Path::new(&args.arg_file);
-
-        if path.is_file() {
-            parse_file(&args.arg_file, &id_generator, &mut ast, &mut interner)
-        } else if path.is_dir() {
-            parse_dir(&args.arg_file, &id_generator, &mut ast, &mut interner)
-        } else {
-            println!("file or directory `{}` does not exist.", &args.arg_file);
-            Err(1)
-        }
-    }) {
         return code;
     }
 
@@ -62,37 +94,43 @@ pub fn start(content: Option<&str>) -> i32 {
         ast::dump::dump(&ast, &interner);
     }
 
-    {
-        let mut bytecodegen = BytecodeGen::new();
-        bytecodegen.gen(&ast);
-    }
-
     let mut vm = VM::new(args, &ast, interner);
 
+    // semck runs on the recovered AST even after syntax errors,
+    // its errors are reported after the syntax errors
     semck::check(&mut vm);
 
+    // warn about unused variables and assignments and unreachable code,
+    // lints for a program with syntax errors would be misleading
+    if !diag.has_errors() && !vm.diag.lock().has_errors() {
+        lintck::check(&vm);
+    }
+
     // register signal handler
     os::register_signals();
 
-    let main = if vm.args.cmd_test {
+    // a syntax error may have dropped main
+    let main = if vm.args.cmd_test || diag.has_errors() {
         None
     } else {
         find_main(&vm)
     };
 
-    if vm.diag.lock().has_errors() {
-        vm.diag.lock().dump();
-        let no_errors = vm.diag.lock().errors().len();
+    for error in vm.diag.lock().errors() {
+        diag.report(error.pos, error.msg.clone());
+    }
 
-        if no_errors == 1 {
-            println!("{} error found.", no_errors);
-        } else {
-            println!("{} errors found.", no_errors);
-        }
+    report_warnings(&vm.diag.lock(), vm.ast, error_format);
 
+    if diag.has_errors() {
+        report_errors(&diag, vm.ast, error_format);
         return 1;
     }
 
+    if vm.args.flag_emit_bytecode.is_some() {
+        emit_bytecode(&vm);
+    }
+
     // if --check given, stop after type/semantic check
     if vm.args.flag_check {
         return 0;
@@ -113,6 +151,10 @@ pub fn start(content: Option<&str>) -> i32 {
 
     os::unregister_signals();
 
+    if vm.args.flag_emit_ic_stats {
+        ic::dump_stats(&vm);
+    }
+
     if vm.args.flag_gc_verbose {
         vm.dump_gc_summary(timer.stop());
     }
@@ -120,6 +162,56 @@ pub fn start(content: Option<&str>) -> i32 {
     code
 }
 
+fn report_errors(diag: &Diagnostic, ast: &Ast, format: ErrorFormat) {
+    match format {
+        ErrorFormat::Human => {
+            diag.dump(ast);
+            let no_errors = diag.errors().len();
+
+            if no_errors == 1 {
+                println!("{} error found.", no_errors);
+            } else {
+                println!("{} errors found.", no_errors);
+            }
+        }
+
+        ErrorFormat::Json => {
+            for err in diag.errors() {
+                println!("{}", error_to_json(ast, err, "error"));
+            }
+        }
+    }
+}
+
+// warnings go to stderr, so they don't mix with the program output
+fn report_warnings(diag: &Diagnostic, ast: &Ast, format: ErrorFormat) {
+    for warning in diag.warnings() {
+        match format {
+            ErrorFormat::Human => eprint!("{}", diag::render_warning(ast, warning)),
+            ErrorFormat::Json => eprintln!("{}", error_to_json(ast, warning, "warning")),
+        }
+    }
+}
+
+// one JSON object per error, the file is null for positions outside
+// of source files
+fn error_to_json(ast: &Ast, err: &MsgWithPos, severity: &str) -> String {
+    let mut object = BTreeMap::new();
+    let file = match err.pos.file {
+        Some(file) => Json::String(ast.files[file.idx()].path.clone()),
+        None => Json::Null,
+    };
+
+    object.insert("code".to_string(), Json::String(err.msg.code().into()));
+    object.insert("file".to_string(), file);
+    object.insert("line".to_string(), Json::U64(err.pos.line as u64));
+    object.insert("column".to_string(), Json::U64(err.pos.column as u64));
+    object.insert("message".to_string(), Json::String(err.msg.message()));
+    object.insert("severity".to_string(), Json::String(severity.into()));
+
+    Json::Object(object).to_string()
+}
+
 fn run_tests<'ast>(vm: &VM<'ast>) -> i32 {
     let mut tests = 0;
     let mut passed = 0;
@@ -187,8 +279,34 @@ fn is_test_fct<'ast>(vm: &VM<'ast>, fct: &Fct<'ast>) -> bool {
     fct_name.starts_with("test")
 }
 
+fn emit_bytecode<'ast>(vm: &VM<'ast>) {
+    let pattern = vm.args.flag_emit_bytecode.as_ref().unwrap();
+
+    for fct in vm.fcts.iter() {
+        let fct = fct.read();
+
+        if !fct.is_src() || !fct_pattern_match(vm, &*fct, pattern) {
+            continue;
+        }
+
+        match bytecodegen::generate(vm, fct.id) {
+            Ok(bytecode) => bytecode.dump(vm),
+            Err(pos) => println!(
+                "fun {}: bytecode not supported at {}\n",
+                fct.full_name(vm),
+                pos
+            ),
+        }
+    }
+}
+
 fn run_main<'ast>(vm: &VM<'ast>, main: FctId) -> i32 {
-    let res = vm.run(main);
+    // falls back to the compiler if the program isn't supported by the interpreter
+    let res = if vm.args.flag_interpreter {
+        interpreter::run(vm, main).unwrap_or_else(|| vm.run(main))
+    } else {
+        vm.run(main)
+    };
     let fct = vm.fcts.idx(main);
     let fct = fct.read();
     let is_unit = fct.return_type.is_unit();
@@ -208,19 +326,12 @@ fn parse_dir(
     id_generator: &NodeIdGenerator,
     ast: &mut Ast,
     interner: &mut Interner,
+    diag: &mut Diagnostic,
 ) -> Result<(), i32> {
     let path = Path::new(dirname);
 
     if path.is_dir() {
-        for entry in fs::read_dir(path).unwrap() {
-            let path = entry.unwrap().path();
-
-            if path.is_file() && path.extension().unwrap() == "dora" {
-                parse_file(path.to_str().unwrap(), id_generator, ast, interner)?;
-            }
-        }
-
-        Ok(())
+        parse_dir_files(path, &[], id_generator, ast, interner, diag)
     } else {
         println!("directory `{}` does not exist.", dirname);
 
@@ -228,13 +339,103 @@ fn parse_dir(
     }
 }
 
+// all files of a stdlib subdirectory belong to the module named after the
+// directory, the module `internal` can't be named in programs since
+// `internal` is a keyword
+fn parse_dir_files(
+    path: &Path,
+    module: &[String],
+    id_generator: &NodeIdGenerator,
+    ast: &mut Ast,
+    interner: &mut Interner,
+    diag: &mut Diagnostic,
+) -> Result<(), i32> {
+    for entry in fs::read_dir(path).unwrap() {
+        let path = entry.unwrap().path();
+
+        if path.is_dir() {
+            let mut module = module.to_vec();
+            module.push(path.file_name().unwrap().to_str().unwrap().to_string());
+
+            parse_dir_files(&path, &module, id_generator, ast, interner, diag)?;
+        } else if path.is_file() && path.extension().unwrap() == "dora" {
+            parse_file(
+                path.to_str().unwrap(),
+                module.to_vec(),
+                id_generator,
+                ast,
+                interner,
+                diag,
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+// every file of a program directory defines a module named after the file,
+// only `main.dora` at the top belongs to the root module; subdirectories
+// contain the submodules of the module with the same name
+fn parse_modules(
+    path: &Path,
+    module: &[String],
+    id_generator: &NodeIdGenerator,
+    ast: &mut Ast,
+    interner: &mut Interner,
+    diag: &mut Diagnostic,
+) -> Result<(), i32> {
+    let entries = match fs::read_dir(path) {
+        Err(_) => {
+            println!("unable to read directory `{}`", path.display());
+            return Err(1);
+        }
+
+        Ok(entries) => entries,
+    };
+
+    for entry in entries {
+        let path = match entry {
+            Err(_) => {
+                println!("unable to read directory `{}`", path.display());
+                return Err(1);
+            }
+
+            Ok(entry) => entry.path(),
+        };
+        let name = path.file_stem().unwrap().to_str().unwrap().to_string();
+        let mut module = module.to_vec();
+
+        if path.is_dir() {
+            module.push(name);
+            parse_modules(&path, &module, id_generator, ast, interner, diag)?;
+        } else if path.is_file() && path.extension().map_or(false, |ext| ext == "dora") {
+            if !module.is_empty() || name != "main" {
+                module.push(name);
+            }
+
+            parse_file(
+                path.to_str().unwrap(),
+                module,
+                id_generator,
+                ast,
+                interner,
+                diag,
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
 fn parse_file(
     filename: &str,
+    module: Vec<String>,
     id_generator: &NodeIdGenerator,
     ast: &mut Ast,
     interner: &mut Interner,
+    diag: &mut Diagnostic,
 ) -> Result<(), i32> {
-    let reader = if filename == "-" {
+    let mut reader = if filename == "-" {
         match Reader::from_input() {
             Err(_) => {
                 println!("unable to read from stdin.");
@@ -254,11 +455,11 @@ fn parse_file(
         }
     };
 
-    if let Err(error) = Parser::new(reader, id_generator, ast, interner).parse() {
-        println!("{}", error);
-        println!("1 error found.");
-        return Err(1);
-    }
+    reader.set_file(FileId(ast.files.len() as u32));
+
+    Parser::new(reader, id_generator, ast, interner).parse(diag);
+
+    ast.files.last_mut().unwrap().module = module;
 
     Ok(())
 }
@@ -268,14 +469,12 @@ fn parse_str(
     id_generator: &NodeIdGenerator,
     ast: &mut Ast,
     interner: &mut Interner,
+    diag: &mut Diagnostic,
 ) -> Result<(), i32> {
-    let reader = Reader::from_string(file);
+    let mut reader = Reader::from_string(file);
+    reader.set_file(FileId(ast.files.len() as u32));
 
-    if let Err(error) = Parser::new(reader, id_generator, ast, interner).parse() {
-        println!("{}", error);
-        println!("1 error found.");
-        return Err(1);
-    }
+    Parser::new(reader, id_generator, ast, interner).parse(diag);
 
     Ok(())
 }
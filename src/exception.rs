@@ -1,9 +1,9 @@
 use std::ptr;
 
-use baseline::fct::{CatchType, JitFctId};
+use baseline::fct::CatchType;
 use baseline::map::CodeDescriptor;
 use cpu::fp_from_execstate;
-use ctxt::{get_vm, VM};
+use ctxt::{get_vm, FctId, VM};
 use execstate::ExecState;
 use handle::root;
 use object::{alloc, Array, Exception, IntArray, Obj, Ref, StackTraceElement, Str};
@@ -24,7 +24,7 @@ impl Stacktrace {
         self.elems.len()
     }
 
-    pub fn push_entry(&mut self, fct_id: JitFctId, lineno: i32) {
+    pub fn push_entry(&mut self, fct_id: FctId, lineno: i32) {
         self.elems.push(StackElem {
             fct_id: fct_id,
             lineno: lineno,
@@ -33,9 +33,7 @@ impl Stacktrace {
 
     pub fn dump(&self, vm: &VM) {
         for (ind, elem) in self.elems.iter().enumerate() {
-            let jit_fct = vm.jit_fcts.idx(elem.fct_id);
-            let fct_id = jit_fct.fct_id();
-            let fct = vm.fcts.idx(fct_id);
+            let fct = vm.fcts.idx(elem.fct_id);
             let fct = fct.read();
             let name = fct.full_name(vm);
             print!("{}: {}: ", ind, name);
@@ -50,7 +48,7 @@ impl Stacktrace {
 }
 
 struct StackElem {
-    fct_id: JitFctId,
+    fct_id: FctId,
     lineno: i32,
 }
 
@@ -134,14 +132,17 @@ fn determine_stack_entry(stacktrace: &mut Stacktrace, vm: &VM, pc: usize) -> boo
             let jit_fct = vm.jit_fcts.idx(fct_id);
 
             let offset = pc - jit_fct.fct_ptr().to_usize();
-            let jit_fct = jit_fct.to_base().expect("baseline expected");
             let lineno = jit_fct.lineno_for_offset(offset as i32);
 
             if lineno == 0 {
                 panic!("lineno not found for program point");
             }
 
-            stacktrace.push_entry(fct_id, lineno);
+            for &(fct_id, lineno) in jit_fct.inlined_for_offset(offset as i32) {
+                stacktrace.push_entry(fct_id, lineno);
+            }
+
+            stacktrace.push_entry(jit_fct.fct_id(), lineno);
 
             true
         }
@@ -151,7 +152,7 @@ fn determine_stack_entry(stacktrace: &mut Stacktrace, vm: &VM, pc: usize) -> boo
             let fct = vm.fcts.idx(jit_fct.fct_id());
             let fct = fct.read();
 
-            stacktrace.push_entry(fct_id, fct.ast.pos.line as i32);
+            stacktrace.push_entry(fct.id, fct.ast.pos.line as i32);
 
             true
         }
@@ -159,7 +160,10 @@ fn determine_stack_entry(stacktrace: &mut Stacktrace, vm: &VM, pc: usize) -> boo
         Some(CodeDescriptor::TrapThunk) => true,
         Some(CodeDescriptor::ThrowThunk) => true,
         Some(CodeDescriptor::AllocThunk) => true,
+        Some(CodeDescriptor::OsrThunk) => true,
+        Some(CodeDescriptor::EnumEqualsThunk) => true,
         Some(CodeDescriptor::DoraEntry) => false,
+        Some(CodeDescriptor::InterpreterEntry(_)) => false,
 
         _ => {
             println!("data = {:?}", data);
@@ -232,12 +236,15 @@ fn find_handler(
     };
 
     match data {
-        Some(CodeDescriptor::DoraFct(fct_id)) | Some(CodeDescriptor::NativeThunk(fct_id)) => {
+        Some(CodeDescriptor::DoraFct(fct_id))
+        | Some(CodeDescriptor::NativeThunk(fct_id))
+        | Some(CodeDescriptor::InterpreterEntry(fct_id)) => {
             let jit_fct = vm.jit_fcts.idx(fct_id);
-            let jit_fct = jit_fct.to_base().expect("baseline expected");
             let clsptr = exception.header().vtbl().classptr();
 
-            for entry in &jit_fct.exception_handlers {
+            // handlers of nested try ranges precede the enclosing ones,
+            // handlers of sequential ranges are not sorted by pc
+            for entry in jit_fct.exception_handlers() {
                 // println!("entry = {:x} to {:x} for {:?}",
                 //          entry.try_start, entry.try_end, entry.catch_type);
 
@@ -246,7 +253,7 @@ fn find_handler(
                     && (entry.catch_type == CatchType::Any
                         || entry.catch_type == CatchType::Class(clsptr))
                 {
-                    let stacksize = jit_fct.framesize as usize;
+                    let stacksize = jit_fct.framesize() as usize;
 
                     if let Some(offset) = entry.offset {
                         let arg = (fp as isize + offset as isize) as usize;
@@ -261,17 +268,12 @@ fn find_handler(
                     resume.fp = fp;
 
                     return HandlerFound::Yes;
-                } else if pc > entry.try_end {
-                    // exception handlers are sorted, no more possible handlers
-                    // in this function
-
-                    return HandlerFound::No;
                 }
             }
 
             // exception can only bubble up in stacktrace if current function
             // is allowed to throw exceptions
-            if !jit_fct.throws {
+            if !jit_fct.throws() {
                 return HandlerFound::Stop;
             }
 
@@ -308,9 +310,7 @@ pub extern "C" fn stack_element(obj: Ref<Exception>, ind: i32) -> Ref<StackTrace
     let mut ste = root(ste);
     ste.line = lineno;
 
-    let jit_fct_id = JitFctId::from(fct_id as usize);
-    let jit_fct = vm.jit_fcts.idx(jit_fct_id);
-    let fct = vm.fcts.idx(jit_fct.fct_id());
+    let fct = vm.fcts.idx(FctId::from(fct_id as usize));
     let fct = fct.read();
     let name = fct.full_name(vm);
     ste.name = Str::from_buffer(vm, name.as_bytes());
@@ -344,7 +344,7 @@ fn set_exception_backtrace(vm: &VM, obj: Ref<Exception>, via_retrieve: bool) {
     // ignore first element of stack trace (ctor of Exception)
     for elem in stacktrace.elems.iter().skip(skip) {
         array.set_at(i, elem.lineno);
-        array.set_at(i + 1, elem.fct_id.idx() as i32);
+        array.set_at(i + 1, elem.fct_id.0 as i32);
 
         i += 2;
     }
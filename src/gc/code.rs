@@ -0,0 +1,174 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use baseline::fct::JitFctId;
+use baseline::map::CodeDescriptor;
+use ctxt::{FctKind, VM};
+use gc::space::Space;
+use gc::Address;
+use mem;
+use os;
+use threads::DoraThread;
+
+// Frees code of Dora functions that can't be invoked anymore, e.g. baseline
+// code replaced by optimized code or specializations only used by other
+// freed code. Code is reachable from stack frames and vtables, machine code
+// references other functions through its data segment and inline caches.
+// All words of reachable code are scanned conservatively for such references.
+// Returns the number of freed functions.
+pub fn collect(vm: &VM, threads: &[Arc<DoraThread>], code_space: &Space) -> usize {
+    let mut marked = HashSet::new();
+    let mut worklist = Vec::new();
+
+    mark_stacks(vm, threads, &mut marked, &mut worklist);
+    mark_vtables(vm, code_space, &mut marked, &mut worklist);
+
+    while let Some(jit_fct_id) = worklist.pop() {
+        let jit_fct = vm.jit_fcts.idx(jit_fct_id);
+        let mut addr = mem::align_usize(jit_fct.ptr_start().to_usize(), mem::ptr_width_usize());
+        let end = jit_fct.ptr_end().to_usize();
+
+        while addr + mem::ptr_width_usize() <= end {
+            let value = unsafe { *(addr as *const usize) };
+            mark_code(vm, code_space, value.into(), &mut marked, &mut worklist);
+
+            addr += mem::ptr_width_usize();
+        }
+    }
+
+    let dora_fcts = vm.code_map.lock().dora_fcts();
+    let mut freed = 0;
+
+    for jit_fct_id in dora_fcts {
+        if marked.contains(&jit_fct_id) || !remove_specialization(vm, jit_fct_id) {
+            continue;
+        }
+
+        let jit_fct = vm.jit_fcts.idx(jit_fct_id);
+        let start = jit_fct.ptr_start();
+        let size = jit_fct.ptr_end().offset_from(start);
+
+        if vm.args.flag_enable_gdb {
+            os::gdb::unregister_with_gdb(&jit_fct, vm);
+        }
+
+        vm.code_map.lock().remove(start);
+        code_space.free(start, size);
+        freed += 1;
+    }
+
+    freed
+}
+
+fn mark_code(
+    vm: &VM,
+    code_space: &Space,
+    addr: Address,
+    marked: &mut HashSet<JitFctId>,
+    worklist: &mut Vec<JitFctId>,
+) {
+    if !code_space.contains(addr) {
+        return;
+    }
+
+    let data = vm.code_map.lock().get(addr);
+
+    if let Some(CodeDescriptor::DoraFct(jit_fct_id)) = data {
+        if marked.insert(jit_fct_id) {
+            worklist.push(jit_fct_id);
+        }
+    }
+}
+
+fn mark_stacks(
+    vm: &VM,
+    threads: &[Arc<DoraThread>],
+    marked: &mut HashSet<JitFctId>,
+    worklist: &mut Vec<JitFctId>,
+) {
+    for thread in threads {
+        let mut dtn = thread.dtn();
+
+        while !dtn.is_null() {
+            let current = unsafe { &*dtn };
+            let mut pc = current.pc;
+            let mut fp = current.fp;
+
+            while fp != 0 {
+                let data = vm.code_map.lock().get(pc.into());
+
+                match data {
+                    Some(CodeDescriptor::DoraFct(jit_fct_id)) => {
+                        if marked.insert(jit_fct_id) {
+                            worklist.push(jit_fct_id);
+                        }
+                    }
+
+                    Some(CodeDescriptor::DoraEntry)
+                    | Some(CodeDescriptor::InterpreterEntry(_))
+                    | None => break,
+                    Some(_) => {}
+                }
+
+                pc = unsafe { *((fp + 8) as *const usize) };
+                fp = unsafe { *(fp as *const usize) };
+            }
+
+            dtn = current.last;
+        }
+    }
+}
+
+fn mark_vtables(
+    vm: &VM,
+    code_space: &Space,
+    marked: &mut HashSet<JitFctId>,
+    worklist: &mut Vec<JitFctId>,
+) {
+    for cls in vm.class_defs.iter() {
+        let cls = cls.read();
+
+        if let Some(ref vtable) = cls.vtable {
+            for &entry in vtable.table() {
+                mark_code(vm, code_space, entry.into(), marked, worklist);
+            }
+        }
+    }
+}
+
+// removes the freed code from the specializations and OSR entries of its
+// function, code of functions that are currently compiled is kept
+fn remove_specialization(vm: &VM, jit_fct_id: JitFctId) -> bool {
+    let jit_fct = vm.jit_fcts.idx(jit_fct_id);
+    let fct = vm.fcts.idx(jit_fct.fct_id());
+
+    let fct = match fct.try_read() {
+        Some(fct) => fct,
+        None => return false,
+    };
+
+    let src = match fct.kind {
+        FctKind::Source(ref src) => src,
+        _ => return false,
+    };
+
+    let src = match src.try_read() {
+        Some(src) => src,
+        None => return false,
+    };
+
+    let mut specializations = match src.specializations.try_write() {
+        Some(specializations) => specializations,
+        None => return false,
+    };
+
+    let mut osr_entries = match src.osr_entries.try_write() {
+        Some(osr_entries) => osr_entries,
+        None => return false,
+    };
+
+    specializations.retain(|_, &mut id| id != jit_fct_id);
+    osr_entries.retain(|_, &mut id| id != jit_fct_id);
+
+    true
+}
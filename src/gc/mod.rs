@@ -12,10 +12,13 @@ use gc::zero::ZeroCollector;
 use mem;
 use object::{Header, Obj};
 use os;
+use safepoint;
+use timer::Timer;
 use vtable::VTable;
 
 pub mod arena;
 pub mod bump;
+pub mod code;
 pub mod compact;
 pub mod copy;
 pub mod root;
@@ -30,7 +33,9 @@ pub const M: usize = K * K;
 const LARGE_OBJECT_SIZE: usize = 64 * K;
 
 const CHUNK_SIZE: usize = 8 * K;
-pub const DEFAULT_CODE_SPACE_LIMIT: usize = 128 * K;
+// the code space only reserves its limit and commits chunks on demand,
+// unreachable code is collected before the limit is reached
+pub const DEFAULT_CODE_SPACE_LIMIT: usize = 64 * M;
 pub const DEFAULT_PERM_SPACE_LIMIT: usize = 64 * K;
 
 // young/old gen are aligned to at least this size
@@ -89,6 +94,30 @@ impl Gc {
         self.code_space.alloc(size)
     }
 
+    // frees code of functions that can't be invoked anymore
+    pub fn collect_code(&self, vm: &VM, reason: GcReason) {
+        let timer = Timer::new(vm.args.flag_gc_verbose);
+        let start_used = self.code_space.used();
+        let start_committed = self.code_space.committed();
+
+        let freed = safepoint::stop_the_world(vm, |threads| {
+            code::collect(vm, threads, &self.code_space)
+        });
+
+        timer.stop_with(|dur| {
+            println!(
+                "GC: Code ({}) {}/{} -> {}/{}; {:.2} ms; {} functions freed",
+                reason,
+                formatted_size(start_used),
+                formatted_size(start_committed),
+                formatted_size(self.code_space.used()),
+                formatted_size(self.code_space.committed()),
+                dur,
+                freed,
+            );
+        });
+    }
+
     pub fn alloc_perm(&self, size: usize) -> Address {
         self.perm_space.alloc(size)
     }
@@ -149,6 +178,13 @@ impl Gc {
 
     pub fn dump_summary(&self, runtime: f32) {
         self.collector.dump_summary(runtime);
+
+        println!(
+            "Code space: {} used, {} committed, {} limit",
+            formatted_size(self.code_space.used()),
+            formatted_size(self.code_space.committed()),
+            formatted_size(self.code_space.total().size()),
+        );
     }
 }
 
@@ -4,6 +4,7 @@ use baseline::map::CodeDescriptor;
 use ctxt::VM;
 use exception::DoraToNativeInfo;
 use gc::Address;
+use semck::specialize::specialize_struct_ty;
 use threads::DoraThread;
 
 pub fn get_rootset(vm: &VM, threads: &[Arc<DoraThread>]) -> Vec<Slot> {
@@ -11,6 +12,7 @@ pub fn get_rootset(vm: &VM, threads: &[Arc<DoraThread>]) -> Vec<Slot> {
 
     determine_rootset_from_stack(&mut rootset, vm, threads);
     determine_rootset_from_handles(&mut rootset, threads);
+    determine_rootset_from_interpreter(&mut rootset, threads);
 
     determine_rootset_from_globals(&mut rootset, vm);
 
@@ -26,10 +28,29 @@ fn determine_rootset_from_handles(rootset: &mut Vec<Slot>, threads: &[Arc<DoraTh
     }
 }
 
+fn determine_rootset_from_interpreter(rootset: &mut Vec<Slot>, threads: &[Arc<DoraThread>]) {
+    for thread in threads {
+        thread.interpreter.roots(rootset);
+    }
+}
+
 fn determine_rootset_from_globals(rootset: &mut Vec<Slot>, vm: &VM) {
     for glob in vm.globals.iter() {
         let glob = glob.lock();
 
+        if glob.ty.is_struct_or_tuple() {
+            let struct_id = specialize_struct_ty(vm, glob.ty);
+            let struc = vm.struct_defs.idx(struct_id);
+            let struc = struc.lock();
+
+            for &offset in &struc.ref_fields {
+                let slot = Slot::at(glob.address_value.offset(offset as usize));
+                rootset.push(slot);
+            }
+
+            continue;
+        }
+
         if !glob.ty.reference_type() {
             continue;
         }
@@ -85,7 +106,6 @@ fn determine_rootset(rootset: &mut Vec<Slot>, vm: &VM, fp: usize, pc: usize) ->
             let jit_fct = vm.jit_fcts.idx(fct_id);
 
             let offset = pc - jit_fct.fct_ptr().to_usize();
-            let jit_fct = jit_fct.to_base().expect("baseline expected");
             let gcpoint = jit_fct
                 .gcpoint_for_offset(offset as i32)
                 .expect("no gcpoint");
@@ -99,8 +119,11 @@ fn determine_rootset(rootset: &mut Vec<Slot>, vm: &VM, fp: usize, pc: usize) ->
         }
 
         Some(CodeDescriptor::AllocThunk) => true,
+        Some(CodeDescriptor::OsrThunk) => true,
+        Some(CodeDescriptor::EnumEqualsThunk) => true,
         Some(CodeDescriptor::NativeThunk(_)) => true,
         Some(CodeDescriptor::DoraEntry) => false,
+        Some(CodeDescriptor::InterpreterEntry(_)) => false,
 
         _ => {
             println!("data = {:?}", data);
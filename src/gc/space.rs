@@ -1,4 +1,5 @@
 use parking_lot::Mutex;
+use std::cmp::min;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
 use gc::arena;
@@ -35,6 +36,9 @@ pub struct Space {
     end: AtomicUsize,
 
     allocate: Mutex<()>,
+
+    // regions returned by `free`, sorted by address
+    free_list: Mutex<Vec<Region>>,
 }
 
 impl Space {
@@ -57,16 +61,22 @@ impl Space {
             end: AtomicUsize::new(end.to_usize()),
 
             allocate: Mutex::new(()),
+            free_list: Mutex::new(Vec::new()),
         }
     }
 
-    /// allocate memory in this space. This first tries to allocate space
-    /// in the current chunk. If this fails a new chunk is allocated.
-    /// Doesn't use a freelist right now so memory at the end of a chunk
-    /// is probably lost.
+    /// allocate memory in this space. This first tries to reuse memory
+    /// returned by `free`, then tries to allocate space in the current
+    /// chunk. If this fails a new chunk is allocated.
+    /// Memory at the end of a chunk is probably lost.
     pub fn alloc(&self, size: usize) -> Address {
         let size = mem::align_usize(size, self.config.align);
 
+        let ptr = self.alloc_free_list(size);
+        if !ptr.is_null() {
+            return ptr;
+        }
+
         loop {
             let ptr = self.raw_alloc(size);
             if !ptr.is_null() {
@@ -79,6 +89,64 @@ impl Space {
         }
     }
 
+    fn alloc_free_list(&self, size: usize) -> Address {
+        let mut free_list = self.free_list.lock();
+
+        for idx in 0..free_list.len() {
+            let region = free_list[idx];
+
+            if region.size() >= size {
+                if region.size() == size {
+                    free_list.remove(idx);
+                } else {
+                    free_list[idx].start = region.start.offset(size);
+                }
+
+                return region.start;
+            }
+        }
+
+        Address::null()
+    }
+
+    /// returns memory allocated with `alloc` to this space,
+    /// adjacent free regions are merged. A free region at the end
+    /// of the used memory is given back to the current chunk.
+    pub fn free(&self, start: Address, size: usize) {
+        let size = mem::align_usize(size, self.config.align);
+        let region = Region::new(start, start.offset(size));
+        let mut free_list = self.free_list.lock();
+
+        let idx = match free_list.binary_search_by_key(&start, |region| region.start) {
+            Ok(_) => panic!("region freed twice"),
+            Err(idx) => idx,
+        };
+
+        free_list.insert(idx, region);
+
+        if idx + 1 < free_list.len() && free_list[idx].end == free_list[idx + 1].start {
+            free_list[idx].end = free_list[idx + 1].end;
+            free_list.remove(idx + 1);
+        }
+
+        if idx > 0 && free_list[idx - 1].end == free_list[idx].start {
+            free_list[idx - 1].end = free_list[idx].end;
+            free_list.remove(idx);
+        }
+
+        let last = *free_list.last().unwrap();
+        let res = self.top.compare_exchange(
+            last.end.to_usize(),
+            last.start.to_usize(),
+            Ordering::SeqCst,
+            Ordering::Relaxed,
+        );
+
+        if res.is_ok() {
+            free_list.pop();
+        }
+    }
+
     fn raw_alloc(&self, size: usize) -> Address {
         let mut old = self.top.load(Ordering::Relaxed);
         let mut new;
@@ -114,18 +182,19 @@ impl Space {
         }
 
         let size = size - (end - top);
-        let size = mem::align_usize(size, self.config.chunk);
 
-        let new_end = end + size;
+        if end + size > self.total.end.to_usize() {
+            return false;
+        }
+
+        // the last chunk may be smaller when the limit isn't a multiple of the chunk size
+        let size = mem::align_usize(size, self.config.chunk);
+        let new_end = min(end + size, self.total.end.to_usize());
 
-        if new_end <= self.total.end.to_usize() {
-            arena::commit(end.into(), size, self.config.executable);
-            self.end.store(new_end, Ordering::SeqCst);
+        arena::commit(end.into(), new_end - end, self.config.executable);
+        self.end.store(new_end, Ordering::SeqCst);
 
-            true
-        } else {
-            false
-        }
+        true
     }
 
     pub fn contains(&self, addr: Address) -> bool {
@@ -136,6 +205,18 @@ impl Space {
         self.total.clone()
     }
 
+    /// size of allocated memory that wasn't freed yet
+    pub fn used(&self) -> usize {
+        let free: usize = self.free_list.lock().iter().map(|r| r.size()).sum();
+
+        self.used_region().size() - free
+    }
+
+    /// size of memory committed so far
+    pub fn committed(&self) -> usize {
+        self.end.load(Ordering::Relaxed) - self.total.start.to_usize()
+    }
+
     pub fn used_region(&self) -> Region {
         let start = self.total.start;
         let end = self.top.load(Ordering::Relaxed).into();
@@ -9,6 +9,7 @@ use cpu::asm::*;
 use cpu::reg::*;
 use cpu::{FReg, Mem, Reg};
 use ctxt::{get_vm, FctId};
+use dora_parser::ast::NodeId;
 use dora_parser::lexer::position::Position;
 use gc::swiper::CARD_SIZE_BITS;
 use gc::Address;
@@ -139,6 +140,19 @@ impl MacroAssembler {
         self.emit_bailout_info(BailoutInfo::VirtCompile(index, TypeParams::empty()));
     }
 
+    // inline caches are not implemented on arm64 yet, always dispatch through the vtable
+    pub fn cached_call(
+        &mut self,
+        line: i32,
+        _id: Option<NodeId>,
+        _fct_id: FctId,
+        index: u32,
+        _miss_thunk: Address,
+        _stats: bool,
+    ) {
+        self.indirect_call(line, index);
+    }
+
     pub fn load_array_elem(&mut self, mode: MachineMode, dest: ExprStore, array: Reg, index: Reg) {
         self.load_mem(
             mode,
@@ -283,6 +297,21 @@ impl MacroAssembler {
         self.emit_u32(asm::br(reg));
     }
 
+    // jumps to targets[index], index needs to be in range.
+    // the table consists of one branch instruction per target
+    pub fn jump_table(&mut self, index: Reg, targets: &[Label]) {
+        let scratch = self.get_scratch();
+
+        // table starts right after adr, add and br
+        self.emit_u32(asm::adr(*scratch, 12));
+        self.emit_u32(asm::add_shreg(1, *scratch, *scratch, index, Shift::LSL, 2));
+        self.emit_u32(asm::br(*scratch));
+
+        for &target in targets {
+            self.jump(target);
+        }
+    }
+
     pub fn int_div(&mut self, mode: MachineMode, dest: Reg, lhs: Reg, rhs: Reg) {
         let x64 = match mode {
             MachineMode::Int32 => 0,
@@ -586,8 +615,6 @@ impl MacroAssembler {
         element_size: i32,
         with_header: bool,
     ) {
-        assert!(element_size == 1 || element_size == 2 || element_size == 4 || element_size == 8);
-
         let header_size = if with_header {
             Header::size() + ptr_width()
         } else {
@@ -606,10 +633,17 @@ impl MacroAssembler {
                 2 => 1,
                 4 => 2,
                 8 => 3,
-                _ => unreachable!(),
+                _ => 0,
             };
 
-            self.emit_u32(asm::lsl_imm(1, dest, length, shift));
+            if shift > 0 {
+                self.emit_u32(asm::lsl_imm(1, dest, length, shift));
+            } else {
+                // elements of struct arrays can have any size
+                let scratch = self.get_scratch();
+                self.load_int_const(MachineMode::Int64, *scratch, element_size as i64);
+                self.int_mul(MachineMode::Int64, dest, length, *scratch);
+            }
         } else {
             self.copy_reg(MachineMode::Ptr, dest, length);
         }
@@ -764,7 +798,7 @@ impl MacroAssembler {
         }
     }
 
-    fn emit_barrier(&mut self, src: Reg, card_table_offset: usize) {
+    pub fn emit_barrier(&mut self, src: Reg, card_table_offset: usize) {
         self.emit_u32(asm::lsr_imm(1, src, src, CARD_SIZE_BITS as u32));
         let scratch = self.get_scratch();
         self.load_int_const(MachineMode::Ptr, *scratch, card_table_offset as i64);
@@ -892,6 +926,24 @@ impl MacroAssembler {
         self.emit_u32(asm::adr(dest, 0));
     }
 
+    pub fn lea(&mut self, dest: Reg, mem: Mem) {
+        match mem {
+            Mem::Local(offset) => {
+                self.int_add_imm(MachineMode::Ptr, dest, REG_FP, offset as i64);
+            }
+
+            Mem::Base(base, disp) => {
+                self.int_add_imm(MachineMode::Ptr, dest, base, disp as i64);
+            }
+
+            _ => unimplemented!(),
+        }
+    }
+
+    pub fn decrement_counter(&mut self, _dest: Reg, _disp: i32) {
+        unimplemented!();
+    }
+
     pub fn copy_ra(&mut self, dest: Reg) {
         self.copy_reg(MachineMode::Ptr, dest, REG_LR);
     }
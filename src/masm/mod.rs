@@ -1,6 +1,7 @@
 use std::cell::Cell;
 use std::collections::HashSet;
 use std::ops::Deref;
+use std::ptr;
 use std::rc::Rc;
 
 use baseline::codegen::CondCode;
@@ -9,13 +10,15 @@ use baseline::fct::{
     BailoutInfo, Bailouts, CatchType, Comment, Comments, ExHandler, GcPoint, GcPoints,
     JitBaselineFct, JitDescriptor, LineNumberTable,
 };
+use baseline::ic;
 use byteorder::{ByteOrder, LittleEndian, WriteBytesExt};
 use cpu::{Mem, Reg, SCRATCH};
-use ctxt::VM;
+use ctxt::{FctId, VM};
 use dora_parser::lexer::position::Position;
 use dseg::DSeg;
 use mem;
 use object::Header;
+use opt::fct::JitOptFct;
 use os::signal::Trap;
 use ty::MachineMode;
 
@@ -35,13 +38,14 @@ pub struct MacroAssembler {
     data: Vec<u8>,
     labels: Vec<Option<usize>>,
     jumps: Vec<ForwardJump>,
-    bailouts: Vec<(Label, Trap, Position)>,
+    bailouts: Vec<(Label, Trap, Position, Vec<(FctId, i32)>)>,
     bailout_infos: Bailouts,
     nil_checks: HashSet<i32>,
     dseg: DSeg,
     gcpoints: GcPoints,
     comments: Comments,
     linenos: LineNumberTable,
+    inlined: Vec<(FctId, i32)>,
     exception_handlers: Vec<ExHandler>,
     scratch_registers: ScratchRegisters,
 }
@@ -59,6 +63,7 @@ impl MacroAssembler {
             gcpoints: GcPoints::new(),
             comments: Comments::new(),
             linenos: LineNumberTable::new(),
+            inlined: Vec::new(),
             exception_handlers: Vec::new(),
             scratch_registers: ScratchRegisters::new(),
         }
@@ -93,6 +98,25 @@ impl MacroAssembler {
         )
     }
 
+    pub fn jit_opt(mut self, vm: &VM, framesize: i32, fct_id: FctId, throws: bool) -> Option<JitOptFct> {
+        self.finish();
+        self.dseg.align(16);
+
+        JitOptFct::from_buffer(
+            vm,
+            &self.dseg,
+            &self.data,
+            self.bailout_infos,
+            self.nil_checks,
+            self.gcpoints,
+            framesize,
+            self.comments,
+            self.linenos,
+            fct_id,
+            throws,
+        )
+    }
+
     #[cfg(test)]
     pub fn buffer(&self) -> &[u8] {
         &self.data
@@ -107,10 +131,9 @@ impl MacroAssembler {
     fn finish(&mut self) {
         let bailouts = self.bailouts.drain(0..).collect::<Vec<_>>();
 
-        for bailout in &bailouts {
-            let (lbl, trap, pos) = *bailout;
-
+        for &(lbl, trap, pos, ref inlined) in &bailouts {
             self.bind_label(lbl);
+            self.inlined = inlined.clone();
             self.trap(trap, pos);
         }
 
@@ -127,6 +150,22 @@ impl MacroAssembler {
         self.dseg.add_addr(ptr)
     }
 
+    pub fn add_i32(&mut self, value: i32) -> i32 {
+        self.dseg.add_i32(value)
+    }
+
+    // reserves an empty inline cache in the data segment,
+    // returns the displacement of its first word
+    pub fn add_inline_cache(&mut self) -> i32 {
+        let mut disp = 0;
+
+        for _ in 0..ic::IC_SIZE {
+            disp = self.dseg.add_addr(ptr::null());
+        }
+
+        disp
+    }
+
     pub fn pos(&self) -> usize {
         self.data.len()
     }
@@ -156,17 +195,23 @@ impl MacroAssembler {
 
     pub fn emit_lineno(&mut self, lineno: i32) {
         let pos = self.pos() as i32;
-        self.linenos.insert(pos, lineno);
+        self.linenos.insert(pos, lineno, &self.inlined);
     }
 
     pub fn emit_lineno_if_missing(&mut self, lineno: i32) {
         let pos = self.pos() as i32;
 
         if self.linenos.get(pos) == 0 {
-            self.linenos.insert(pos, lineno);
+            self.linenos.insert(pos, lineno, &self.inlined);
         }
     }
 
+    // functions inlined at the code emitted next, innermost first, together
+    // with the line of their call site
+    pub fn set_inlined(&mut self, inlined: Vec<(FctId, i32)>) {
+        self.inlined = inlined;
+    }
+
     pub fn emit_gcpoint(&mut self, gcpoint: GcPoint) {
         let pos = self.pos() as i32;
         self.gcpoints.insert(pos, gcpoint);
@@ -202,7 +247,8 @@ impl MacroAssembler {
     }
 
     pub fn emit_bailout(&mut self, lbl: Label, trap: Trap, pos: Position) {
-        self.bailouts.push((lbl, trap, pos));
+        let inlined = self.inlined.clone();
+        self.bailouts.push((lbl, trap, pos, inlined));
     }
 
     pub fn emit_bailout_inplace(&mut self, trap: Trap, pos: Position) {
@@ -2,11 +2,13 @@ use baseline::codegen::CondCode;
 use baseline::expr::ExprStore;
 use baseline::fct::BailoutInfo;
 use baseline::fct::GcPoint;
+use baseline::ic;
 use byteorder::{LittleEndian, WriteBytesExt};
 use class::TypeParams;
 use cpu::*;
 use ctxt::get_vm;
 use ctxt::FctId;
+use dora_parser::ast::NodeId;
 use dora_parser::lexer::position::Position;
 use gc::swiper::CARD_SIZE_BITS;
 use gc::Address;
@@ -99,6 +101,87 @@ impl MacroAssembler {
         self.emit_bailout_info(BailoutInfo::VirtCompile(index, TypeParams::empty()));
     }
 
+    // virtual call through an inline cache: the receiver's vtable is compared against
+    // the cached vtables, on a hit the cached target is invoked directly. Misses
+    // invoke `miss_thunk`, which updates the cache. Megamorphic call sites fall
+    // back to the vtable.
+    pub fn cached_call(
+        &mut self,
+        line: i32,
+        id: Option<NodeId>,
+        fct_id: FctId,
+        index: u32,
+        miss_thunk: Address,
+        stats: bool,
+    ) {
+        let obj = REG_PARAMS[0];
+        let vtable = REG_RESULT;
+        let cache = REG_TMP2;
+        let target = REG_TMP1;
+
+        self.emit_lineno(line);
+        self.emit_nil_check();
+
+        // REG_RESULT = [obj] (load vtable)
+        self.load_mem(MachineMode::Ptr, vtable.into(), Mem::Base(obj, 0));
+
+        let disp = self.add_inline_cache();
+        let pos = self.pos() as i32;
+        let inst_size = 7;
+        asm::lea(self, cache, Mem::Base(RIP, -(disp + pos + inst_size)));
+
+        let lbl_call = self.create_label();
+
+        for entry in 0..ic::IC_ENTRIES {
+            let lbl_next = self.create_label();
+            let entry_vtable = Mem::Base(cache, ic::offset_of_vtable(entry));
+            self.cmp_mem(MachineMode::Ptr, entry_vtable, vtable);
+            self.jump_if(CondCode::NotEqual, lbl_next);
+
+            let entry_target = Mem::Base(cache, ic::offset_of_target(entry));
+            self.load_mem(MachineMode::Ptr, target.into(), entry_target);
+
+            if stats {
+                self.increment_counter(Mem::Base(cache, ic::offset_of_hits()));
+            }
+
+            self.jump(lbl_call);
+            self.bind_label(lbl_next);
+        }
+
+        if stats {
+            self.increment_counter(Mem::Base(cache, ic::offset_of_misses()));
+        }
+
+        let lbl_miss = self.create_label();
+        self.cmp_mem_imm(
+            MachineMode::Ptr,
+            Mem::Base(cache, ic::offset_of_megamorphic()),
+            0,
+        );
+        self.jump_if(CondCode::Equal, lbl_miss);
+
+        // megamorphic: load vtable entry
+        let disp_method = VTable::offset_of_method_table() + (index as i32) * ptr_width();
+        self.load_mem(
+            MachineMode::Ptr,
+            target.into(),
+            Mem::Base(vtable, disp_method),
+        );
+        self.jump(lbl_call);
+
+        self.bind_label(lbl_miss);
+        let disp_thunk = self.add_addr(miss_thunk.to_ptr());
+        let pos = self.pos() as i32;
+        self.load_constpool(target, disp_thunk + pos);
+
+        self.bind_label(lbl_call);
+        self.call_reg(target);
+
+        let pos = self.pos() as i32;
+        self.emit_bailout_info(BailoutInfo::InlineCache(fct_id, index, disp + pos, id));
+    }
+
     pub fn load_array_elem(&mut self, mode: MachineMode, dest: ExprStore, array: Reg, index: Reg) {
         self.load_mem(
             mode,
@@ -276,6 +359,26 @@ impl MacroAssembler {
         asm::emit_jmp_reg(self, reg);
     }
 
+    // jumps to targets[index], index needs to be in range.
+    // every table entry stores the distance from its end to the target
+    pub fn jump_table(&mut self, index: Reg, targets: &[Label]) {
+        let table = self.create_label();
+        let scratch = self.get_scratch();
+
+        asm::lea_label(self, *scratch, table);
+        asm::lea(self, *scratch, Mem::Index(*scratch, index, 4, 4));
+        asm::emit_movl_memq_reg(self, *scratch, -4, index);
+        asm::emit_movsx(self, index, index);
+        asm::emit_add_reg_reg(self, 1, index, *scratch);
+        asm::emit_jmp_reg(self, *scratch);
+
+        self.bind_label(table);
+
+        for &target in targets {
+            self.emit_label(target);
+        }
+    }
+
     pub fn int_div(&mut self, mode: MachineMode, dest: Reg, lhs: Reg, rhs: Reg) {
         self.div_common(mode, dest, lhs, rhs, RAX);
     }
@@ -528,8 +631,6 @@ impl MacroAssembler {
         element_size: i32,
         with_header: bool,
     ) {
-        assert!(element_size == 1 || element_size == 2 || element_size == 4 || element_size == 8);
-
         let header_size = if with_header {
             Header::size() + ptr_width()
         } else {
@@ -543,7 +644,15 @@ impl MacroAssembler {
                 0
             };
 
-        asm::lea(self, dest, Mem::Offset(length, element_size, size));
+        if element_size == 1 || element_size == 2 || element_size == 4 || element_size == 8 {
+            asm::lea(self, dest, Mem::Offset(length, element_size, size));
+        } else {
+            // elements of struct arrays can have any size
+            let scratch = self.get_scratch();
+            self.load_int_const(MachineMode::Int64, *scratch, element_size as i64);
+            self.int_mul(MachineMode::Int64, dest, length, *scratch);
+            asm::lea(self, dest, Mem::Base(dest, size));
+        }
 
         if element_size != ptr_width() {
             asm::emit_andq_imm_reg(self, -ptr_width(), dest);
@@ -640,7 +749,7 @@ impl MacroAssembler {
         }
     }
 
-    fn emit_barrier(&mut self, src: Reg, card_table_offset: usize) {
+    pub fn emit_barrier(&mut self, src: Reg, card_table_offset: usize) {
         asm::emit_shr_reg_imm(self, 1, src, CARD_SIZE_BITS as u8);
 
         // test if card table offset fits into displacement of memory store
@@ -703,6 +812,28 @@ impl MacroAssembler {
         asm::lea(self, dest, Mem::Base(RIP, 0));
     }
 
+    pub fn lea(&mut self, dest: Reg, mem: Mem) {
+        asm::lea(self, dest, mem);
+    }
+
+    // decrements the counter at `disp` in the data segment, `dest` is loaded
+    // with the address of the counter and flags are set by the subtraction
+    pub fn decrement_counter(&mut self, dest: Reg, disp: i32) {
+        assert!(dest.and7() != 4 && dest.and7() != 5);
+        let pos = self.pos() as i32;
+        let inst_size = 7;
+
+        asm::lea(self, dest, Mem::Base(RIP, -(disp + pos + inst_size)));
+        asm::emit_sub_imm_mem(self, MachineMode::Int32, dest, 1);
+    }
+
+    pub fn increment_counter(&mut self, mem: Mem) {
+        match mem {
+            Mem::Base(base, disp) => asm::emit_add_mem_imm(self, MachineMode::Ptr, base, disp, 1),
+            _ => unimplemented!(),
+        }
+    }
+
     pub fn copy_ra(&mut self, dest: Reg) {
         self.load_mem(MachineMode::Ptr, dest.into(), Mem::Base(REG_SP, 0));
     }
@@ -772,16 +903,18 @@ impl MacroAssembler {
 
     pub fn load_float_const(&mut self, mode: MachineMode, dest: FReg, imm: f64) {
         let pos = self.pos() as i32;
+        // instruction needs REX prefix for xmm8-xmm15
+        let inst_size = 8 + dest.msb() as i32;
 
         match mode {
             MachineMode::Float32 => {
                 let off = self.dseg.add_f32(imm as f32);
-                asm::movss_load(self, dest, Mem::Base(RIP, -(off + pos + 8)));
+                asm::movss_load(self, dest, Mem::Base(RIP, -(off + pos + inst_size)));
             }
 
             MachineMode::Float64 => {
                 let off = self.dseg.add_f64(imm);
-                asm::movsd_load(self, dest, Mem::Base(RIP, -(off + pos + 8)));
+                asm::movsd_load(self, dest, Mem::Base(RIP, -(off + pos + inst_size)));
             }
 
             _ => unreachable!(),
@@ -250,7 +250,9 @@ impl Obj {
 
             ClassSize::ObjArray => determine_array_size(self, mem::ptr_width()),
 
-            ClassSize::Array(element_size) => determine_array_size(self, element_size),
+            ClassSize::Array(element_size) | ClassSize::StructArray(element_size) => {
+                determine_array_size(self, element_size)
+            }
 
             ClassSize::Str => {
                 let handle: Ref<Str> = Ref {
@@ -287,6 +289,11 @@ impl Obj {
             return;
         }
 
+        if let ClassSize::StructArray(element_size) = cls.size {
+            visit_struct_array_elements(self, element_size, &cls.ref_fields, f);
+            return;
+        }
+
         let addr = self.address();
 
         for &offset in &cls.ref_fields {
@@ -320,6 +327,13 @@ impl Obj {
             return;
         }
 
+        // the write barrier marks the card of the array header for
+        // arrays of structs, so the whole array is visited
+        if let ClassSize::StructArray(element_size) = cls.size {
+            visit_struct_array_elements(self, element_size, &cls.ref_fields, f);
+            return;
+        }
+
         let addr = self.address();
 
         // visit the whole object all the time
@@ -340,6 +354,26 @@ impl Obj {
     }
 }
 
+fn visit_struct_array_elements<F>(obj: &Obj, element_size: i32, ref_fields: &[i32], mut f: F)
+where
+    F: FnMut(Slot),
+{
+    let handle: Ref<ByteArray> = Ref {
+        ptr: obj as *const Obj as *const ByteArray,
+    };
+
+    let mut element = Address::from_ptr(handle.data());
+    let last = element.offset(element_size as usize * handle.len() as usize);
+
+    while element < last {
+        for &offset in ref_fields {
+            f(Slot::at(element.offset(offset as usize)));
+        }
+
+        element = element.offset(element_size as usize);
+    }
+}
+
 fn determine_array_size(obj: &Obj, element_size: i32) -> usize {
     let handle: Ref<ByteArray> = Ref {
         ptr: obj as *const Obj as *const ByteArray,
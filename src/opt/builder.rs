@@ -0,0 +1,1690 @@
+use std::collections::{HashMap, HashSet};
+
+use baseline::codegen::CondCode;
+use baseline::expr::{check_for_nil, to_cond_code};
+use baseline::fct::JitFct;
+use baseline::ic;
+use baseline::info::{self, specialize_type, JitInfo};
+use baseline::map::CodeDescriptor;
+use class::{ClassSize, FieldId, TypeParams};
+use ctxt::*;
+use dora_parser::ast::Expr::*;
+use dora_parser::ast::Stmt::*;
+use dora_parser::ast::*;
+use dora_parser::lexer::position::Position;
+use dora_parser::lexer::token::{FloatSuffix, IntSuffix};
+use gc::Address;
+use object::Str;
+use opt::ir::{BlockId, CallInfo, Graph, InlinedFct, InstId, Op};
+use semck::specialize::specialize_class_ty;
+use ty::{BuiltinType, MachineMode};
+
+// an error means that the function can't be optimized and
+// falls back to the baseline compiler
+pub type BuildResult<T> = Result<T, &'static str>;
+
+// callees with more instructions are not inlined
+const MAX_INLINE_SIZE: usize = 30;
+
+// maximum number of nested inlined functions
+const MAX_INLINE_DEPTH: usize = 3;
+
+// with `osr` the graph is entered at the header of the given loop with the
+// local variables of the running baseline code
+pub fn build<'ast>(
+    vm: &VM<'ast>,
+    fct: &Fct<'ast>,
+    src: &FctSrc,
+    jit_info: &JitInfo<'ast>,
+    osr: Option<NodeId>,
+) -> BuildResult<Graph> {
+    let empty = TypeParams::empty();
+    let mut builder = Builder::new(vm, fct, src, jit_info, &empty, &empty, vec![fct.id]);
+    builder.osr = osr;
+
+    builder.finish()
+}
+
+struct Builder<'a, 'ast: 'a> {
+    vm: &'a VM<'ast>,
+    fct: &'a Fct<'ast>,
+    src: &'a FctSrc,
+    jit_info: &'a JitInfo<'ast>,
+    cls_type_params: &'a TypeParams,
+    fct_type_params: &'a TypeParams,
+    graph: Graph,
+    current: BlockId,
+
+    // SSA construction, see "Simple and Efficient Construction of
+    // Static Single Assignment Form" by Braun et al.
+    defs: HashMap<(VarId, BlockId), InstId>,
+    incomplete_phis: HashMap<BlockId, Vec<(VarId, InstId)>>,
+    sealed: HashSet<BlockId>,
+
+    consts: HashMap<(Op, MachineMode), InstId>,
+
+    // break and continue targets of surrounding loops
+    loops: Vec<(BlockId, BlockId)>,
+
+    // loop entered by OSR code, reset once the entry is connected
+    osr: Option<NodeId>,
+
+    // the compiled function followed by the functions inlined into
+    // each other down to this function
+    inline_stack: Vec<FctId>,
+}
+
+impl<'a, 'ast> Builder<'a, 'ast>
+where
+    'ast: 'a,
+{
+    fn new(
+        vm: &'a VM<'ast>,
+        fct: &'a Fct<'ast>,
+        src: &'a FctSrc,
+        jit_info: &'a JitInfo<'ast>,
+        cls_type_params: &'a TypeParams,
+        fct_type_params: &'a TypeParams,
+        inline_stack: Vec<FctId>,
+    ) -> Builder<'a, 'ast> {
+        Builder {
+            vm: vm,
+            fct: fct,
+            src: src,
+            jit_info: jit_info,
+            cls_type_params: cls_type_params,
+            fct_type_params: fct_type_params,
+            graph: Graph::new(),
+            current: BlockId(0),
+            defs: HashMap::new(),
+            incomplete_phis: HashMap::new(),
+            sealed: HashSet::new(),
+            consts: HashMap::new(),
+            loops: Vec::new(),
+            osr: None,
+            inline_stack: inline_stack,
+        }
+    }
+
+    fn finish(mut self) -> BuildResult<Graph> {
+        self.build()?;
+
+        let mut graph = self.graph;
+        graph.remove_unreachable();
+        while graph.remove_trivial_phis() {}
+
+        Ok(graph)
+    }
+
+    fn build(&mut self) -> BuildResult<()> {
+        let entry = self.graph.entry();
+        self.sealed.insert(entry);
+
+        let ast = self.fct.ast;
+        let pos = ast.pos;
+
+        if self.osr.is_some() {
+            self.osr_entry()?;
+
+            // code before the loop is never executed
+            self.unreachable();
+        } else {
+            self.params()?;
+
+            let block = self.graph.new_block();
+            self.goto(block, pos);
+            self.seal(block);
+            self.current = block;
+        }
+
+        self.stmt(ast.block())?;
+
+        if self.osr.is_some() {
+            return Err("loop not found");
+        }
+
+        if !self.graph.is_terminated(self.current) {
+            self.add(Op::Return, None, Vec::new(), pos);
+        }
+
+        Ok(())
+    }
+
+    fn params(&mut self) -> BuildResult<()> {
+        let entry = self.graph.entry();
+        let ast = self.fct.ast;
+        let mut idx = 0;
+
+        if self.fct.has_self() {
+            let var = self.src.var_self();
+            let mode = mode_of(self.specialize(var.ty))?;
+            let value = self
+                .graph
+                .add_inst(entry, Op::Param(idx), Some(mode), Vec::new(), ast.pos);
+            self.write_var(var.id, entry, value);
+            idx += 1;
+        }
+
+        for param in &ast.params {
+            let var = *self.src.map_vars.get(param.id).unwrap();
+            let mode = mode_of(self.jit_info.ty(var))?;
+            let value =
+                self.graph
+                    .add_inst(entry, Op::Param(idx), Some(mode), Vec::new(), param.pos);
+            self.write_var(var, entry, value);
+            idx += 1;
+        }
+
+        check_arg_count(self.fct.params_with_self())
+    }
+
+    // loads all variables from the stack frame of the baseline code,
+    // loads of variables that are not live in the loop are removed later
+    fn osr_entry(&mut self) -> BuildResult<()> {
+        let entry = self.graph.entry();
+        let pos = self.fct.ast.pos;
+        let frame = self.graph.add_inst(
+            entry,
+            Op::OsrFrame,
+            Some(MachineMode::Int64),
+            Vec::new(),
+            pos,
+        );
+
+        for var in &self.src.vars {
+            let offset = match self.jit_info.map_var_offsets.get(&var.id) {
+                Some(&offset) => offset,
+                None => continue,
+            };
+
+            let mode = mode_of(self.jit_info.ty(var.id))?;
+            let value =
+                self.graph
+                    .add_inst(entry, Op::OsrLoad(offset), Some(mode), vec![frame], pos);
+            self.write_var(var.id, entry, value);
+        }
+
+        Ok(())
+    }
+
+    // connects the entry block of OSR code to the header of the loop
+    fn enter_loop(&mut self, id: NodeId, header: BlockId, pos: Position) {
+        if self.osr != Some(id) {
+            return;
+        }
+
+        let current = self.current;
+        self.current = self.graph.entry();
+        self.goto(header, pos);
+        self.current = current;
+        self.osr = None;
+    }
+
+    fn stmt(&mut self, s: &'ast Stmt) -> BuildResult<()> {
+        match *s {
+            StmtExpr(ref stmt) => {
+                self.expr(&stmt.expr)?;
+            }
+
+            StmtBlock(ref block) => {
+                for stmt in &block.stmts {
+                    self.stmt(stmt)?;
+                }
+            }
+
+            StmtVar(ref stmt) => {
+                let var = *self.src.map_vars.get(stmt.id).unwrap();
+                let mode = mode_of(self.jit_info.ty(var))?;
+
+                let value = match stmt.expr {
+                    Some(ref expr) => self.value(expr)?,
+                    None => self.zero(mode, stmt.pos),
+                };
+
+                let current = self.current;
+                self.write_var(var, current, value);
+            }
+
+            StmtIf(ref stmt) => {
+                let then_block = self.graph.new_block();
+                let else_block = self.graph.new_block();
+                let join = self.graph.new_block();
+
+                self.cond(&stmt.cond, then_block, else_block)?;
+                self.seal(then_block);
+                self.seal(else_block);
+
+                self.current = then_block;
+                self.stmt(&stmt.then_block)?;
+                self.goto(join, stmt.pos);
+
+                self.current = else_block;
+
+                if let Some(ref else_stmt) = stmt.else_block {
+                    self.stmt(else_stmt)?;
+                }
+
+                self.goto(join, stmt.pos);
+                self.seal(join);
+                self.current = join;
+            }
+
+            StmtWhile(ref stmt) => {
+                let header = self.graph.new_block();
+                let body = self.graph.new_block();
+                let exit = self.graph.new_block();
+
+                self.goto(header, stmt.pos);
+                self.enter_loop(stmt.id, header, stmt.pos);
+                self.current = header;
+                self.cond(&stmt.cond, body, exit)?;
+                self.seal(body);
+
+                self.current = body;
+                self.loop_body(&stmt.block, header, exit, stmt.pos)?;
+            }
+
+            StmtLoop(ref stmt) => {
+                let header = self.graph.new_block();
+                let exit = self.graph.new_block();
+
+                self.goto(header, stmt.pos);
+                self.enter_loop(stmt.id, header, stmt.pos);
+                self.current = header;
+                self.loop_body(&stmt.block, header, exit, stmt.pos)?;
+            }
+
+            StmtBreak(ref stmt) => {
+                let (target, _) = *self.loops.last().unwrap();
+                self.goto(target, stmt.pos);
+                self.unreachable();
+            }
+
+            StmtContinue(ref stmt) => {
+                let (_, target) = *self.loops.last().unwrap();
+                self.goto(target, stmt.pos);
+                self.unreachable();
+            }
+
+            StmtReturn(ref stmt) => {
+                let args = match stmt.expr {
+                    Some(ref expr) => match self.expr(expr)? {
+                        Some(value) => vec![value],
+                        None => Vec::new(),
+                    },
+                    None => Vec::new(),
+                };
+
+                self.add(Op::Return, None, args, stmt.pos);
+                self.unreachable();
+            }
+
+            StmtThrow(_) => return Err("throw"),
+            StmtDefer(_) => return Err("defer"),
+            StmtDo(_) => return Err("do"),
+            StmtSpawn(_) => return Err("spawn"),
+            StmtFor(_) => return Err("for"),
+            StmtLetTuple(_) => return Err("tuple"),
+            StmtError(_) => unreachable!(),
+        }
+
+        Ok(())
+    }
+
+    // the loop body is entered with the current block, the back edge
+    // goes through a latch that polls for safepoints
+    fn loop_body(
+        &mut self,
+        block: &'ast Stmt,
+        header: BlockId,
+        exit: BlockId,
+        pos: Position,
+    ) -> BuildResult<()> {
+        let latch = self.graph.new_block();
+
+        self.loops.push((exit, latch));
+        self.stmt(block)?;
+        self.loops.pop();
+
+        self.goto(latch, pos);
+        self.seal(latch);
+
+        self.current = latch;
+        self.add(Op::Safepoint, None, Vec::new(), pos);
+        self.goto(header, pos);
+        self.seal(header);
+
+        self.seal(exit);
+        self.current = exit;
+
+        Ok(())
+    }
+
+    // branches to `then_block` or `else_block` depending on the
+    // condition, short-circuit operators don't materialize a value
+    fn cond(&mut self, e: &'ast Expr, then_block: BlockId, else_block: BlockId) -> BuildResult<()> {
+        match *e {
+            ExprLitBool(ref lit) => {
+                let target = if lit.value { then_block } else { else_block };
+                self.goto(target, lit.pos);
+                self.unreachable();
+            }
+
+            ExprBin(ref bin) if bin.op == BinOp::And || bin.op == BinOp::Or => {
+                self.cond_bin(bin, then_block, else_block)?;
+            }
+
+            ExprUn(ref un) if self.intrinsic(un.id) == Some(Intrinsic::BoolNot) => {
+                self.cond(&un.opnd, else_block, then_block)?;
+            }
+
+            _ => {
+                let value = self.value(e)?;
+                let current = self.current;
+
+                self.add(Op::If, None, vec![value], e.pos());
+                self.graph.add_edge(current, then_block);
+                self.graph.add_edge(current, else_block);
+                self.unreachable();
+            }
+        }
+
+        Ok(())
+    }
+
+    fn cond_bin(
+        &mut self,
+        e: &'ast ExprBinType,
+        then_block: BlockId,
+        else_block: BlockId,
+    ) -> BuildResult<()> {
+        let rhs_block = self.graph.new_block();
+
+        if e.op == BinOp::And {
+            self.cond(&e.lhs, rhs_block, else_block)?;
+        } else {
+            self.cond(&e.lhs, then_block, rhs_block)?;
+        }
+
+        self.seal(rhs_block);
+        self.current = rhs_block;
+        self.cond(&e.rhs, then_block, else_block)
+    }
+
+    fn value(&mut self, e: &'ast Expr) -> BuildResult<InstId> {
+        match self.expr(e)? {
+            Some(value) => Ok(value),
+            None => Err("unit value"),
+        }
+    }
+
+    fn expr(&mut self, e: &'ast Expr) -> BuildResult<Option<InstId>> {
+        let value = match *e {
+            ExprLitChar(ref lit) => {
+                self.constant(Op::Int(lit.value as i64), MachineMode::Int32, lit.pos)
+            }
+
+            ExprLitInt(ref lit) => {
+                let (value, mode) = match lit.suffix {
+                    IntSuffix::Byte => (lit.value as u8 as i64, MachineMode::Int8),
+                    IntSuffix::Int => (lit.value as i32 as i64, MachineMode::Int32),
+                    IntSuffix::Long => (lit.value as i64, MachineMode::Int64),
+                };
+
+                self.constant(Op::Int(value), mode, lit.pos)
+            }
+
+            ExprLitFloat(ref lit) => {
+                let (value, mode) = match lit.suffix {
+                    FloatSuffix::Float => (lit.value as f32 as f64, MachineMode::Float32),
+                    FloatSuffix::Double => (lit.value, MachineMode::Float64),
+                };
+
+                self.constant(Op::float(value), mode, lit.pos)
+            }
+
+            ExprLitBool(ref lit) => {
+                self.constant(Op::Int(lit.value as i64), MachineMode::Int8, lit.pos)
+            }
+
+            ExprLitStr(ref lit) => {
+                let handle = Str::from_buffer_in_perm(self.vm, lit.value.as_bytes());
+                self.constant(Op::Addr(handle.raw() as usize), MachineMode::Ptr, lit.pos)
+            }
+
+            ExprNil(ref nil) => self.constant(Op::Nil, MachineMode::Ptr, nil.pos),
+
+            ExprSelf(_) | ExprSuper(_) => {
+                let var = self.src.var_self().id;
+                let current = self.current;
+                self.read_var(var, current)
+            }
+
+            ExprIdent(ref ident) => {
+                let &ident_type = self.src.map_idents.get(ident.id).unwrap();
+                self.ident(ident_type, ident.pos)?
+            }
+
+            ExprPath(ref path) => {
+                let &ident_type = self.src.map_idents.get(path.id).unwrap();
+                self.ident(ident_type, path.pos)?
+            }
+
+            ExprField(ref field) => match *self.src.map_idents.get(field.id).unwrap() {
+                IdentType::Field(ty, field_id) => {
+                    let object = self.value(&field.object)?;
+                    let (offset, mode) = self.field(ty, field_id)?;
+
+                    self.add(Op::LoadField(offset), Some(mode), vec![object], field.pos)
+                }
+
+                _ => return Err("struct field"),
+            },
+
+            ExprUn(ref un) => return self.un(un),
+            ExprBin(ref bin) => return self.bin(bin),
+            ExprCall(ref call) => return self.call(call),
+            ExprAssign(ref assign) => {
+                self.assign(assign)?;
+                return Ok(None);
+            }
+
+            ExprArray(ref array) => match self.intrinsic(array.id) {
+                Some(Intrinsic::GenericArrayGet) => {
+                    let mode = mode_of(self.ty(array.id))?;
+                    self.array_get(array.id, &array.object, &array.index, mode, array.pos)?
+                }
+
+                Some(Intrinsic::StrGet) => self.array_get(
+                    array.id,
+                    &array.object,
+                    &array.index,
+                    MachineMode::Int8,
+                    array.pos,
+                )?,
+
+                Some(_) => return Err("intrinsic"),
+                None => return self.call_site(array.id, array.pos),
+            },
+
+            ExprTry(ref expr) => {
+                if !expr.mode.is_normal() {
+                    return Err("try");
+                }
+
+                return self.expr(&expr.expr);
+            }
+
+            ExprLitStruct(_) => return Err("struct"),
+            ExprDelegation(_) => return Err("delegation"),
+            ExprConv(_) => return Err("conv"),
+            ExprLambda(_) => return Err("lambda"),
+            ExprMatch(_) => return Err("match"),
+            ExprTuple(_) | ExprTupleElement(_) => return Err("tuple"),
+        };
+
+        Ok(Some(value))
+    }
+
+    fn ident(&mut self, ident: IdentType, pos: Position) -> BuildResult<InstId> {
+        let value = match ident {
+            IdentType::Var(var) => {
+                let current = self.current;
+                self.read_var(var, current)
+            }
+
+            IdentType::Global(global_id) => {
+                let (address, mode) = self.global(global_id)?;
+                self.add(Op::LoadGlobal(address), Some(mode), Vec::new(), pos)
+            }
+
+            IdentType::Field(ty, field_id) => {
+                let var = self.src.var_self().id;
+                let current = self.current;
+                let object = self.read_var(var, current);
+                let (offset, mode) = self.field(ty, field_id)?;
+
+                self.add(Op::LoadField(offset), Some(mode), vec![object], pos)
+            }
+
+            IdentType::Const(const_id) => {
+                let xconst = self.vm.consts.idx(const_id);
+                let xconst = xconst.lock();
+
+                let op = match xconst.ty {
+                    BuiltinType::Bool => Op::Int(xconst.value.to_bool() as i64),
+                    BuiltinType::Char => Op::Int(xconst.value.to_char() as i64),
+                    BuiltinType::Byte => Op::Int(xconst.value.to_int() as u8 as i64),
+                    BuiltinType::Int => Op::Int(xconst.value.to_int() as i32 as i64),
+                    BuiltinType::Long => Op::Int(xconst.value.to_int()),
+                    BuiltinType::Float => Op::float(xconst.value.to_float() as f32 as f64),
+                    BuiltinType::Double => Op::float(xconst.value.to_float()),
+                    _ => return Err("const"),
+                };
+
+                self.constant(op, xconst.ty.mode(), pos)
+            }
+
+            IdentType::Struct(_) | IdentType::StructField(_, _) => return Err("struct"),
+            IdentType::EnumValue(_, _) => return Err("enum"),
+        };
+
+        Ok(value)
+    }
+
+    fn un(&mut self, e: &'ast ExprUnType) -> BuildResult<Option<InstId>> {
+        let intrinsic = match self.intrinsic(e.id) {
+            Some(intrinsic) => intrinsic,
+            None => return self.call_site(e.id, e.pos),
+        };
+
+        let opnd = self.value(&e.opnd)?;
+
+        let (op, mode) = match intrinsic {
+            Intrinsic::IntPlus
+            | Intrinsic::LongPlus
+            | Intrinsic::FloatPlus
+            | Intrinsic::DoublePlus => return Ok(Some(opnd)),
+
+            Intrinsic::IntNeg => (Op::Neg, MachineMode::Int32),
+            Intrinsic::LongNeg => (Op::Neg, MachineMode::Int64),
+            Intrinsic::FloatNeg => (Op::Neg, MachineMode::Float32),
+            Intrinsic::DoubleNeg => (Op::Neg, MachineMode::Float64),
+
+            Intrinsic::ByteNot => (Op::Not, MachineMode::Int8),
+            Intrinsic::IntNot => (Op::Not, MachineMode::Int32),
+            Intrinsic::LongNot => (Op::Not, MachineMode::Int64),
+            Intrinsic::BoolNot => (Op::BoolNot, MachineMode::Int8),
+
+            _ => return Err("intrinsic"),
+        };
+
+        Ok(Some(self.add(op, Some(mode), vec![opnd], e.pos)))
+    }
+
+    fn bin(&mut self, e: &'ast ExprBinType) -> BuildResult<Option<InstId>> {
+        if let Some(intrinsic) = self.intrinsic(e.id) {
+            let lhs = self.value(&e.lhs)?;
+            let rhs = self.value(&e.rhs)?;
+
+            return self
+                .intrinsic_bin(intrinsic, Some(e.op), lhs, rhs, e.pos)
+                .map(Some);
+        }
+
+        match e.op {
+            BinOp::Cmp(CmpOp::Is) | BinOp::Cmp(CmpOp::IsNot) => {
+                let lhs = self.value(&e.lhs)?;
+                let rhs = self.value(&e.rhs)?;
+
+                let cond = match e.op {
+                    BinOp::Cmp(CmpOp::Is) => CondCode::Equal,
+                    _ => CondCode::NotEqual,
+                };
+
+                let value = self.add(
+                    Op::Cmp(cond),
+                    Some(MachineMode::Int8),
+                    vec![lhs, rhs],
+                    e.pos,
+                );
+                return Ok(Some(value));
+            }
+
+            _ if self.ty(e.lhs.id()).is_enum() => return Err("enum"),
+
+            BinOp::And | BinOp::Or => {
+                let then_block = self.graph.new_block();
+                let else_block = self.graph.new_block();
+                let join = self.graph.new_block();
+
+                self.cond_bin(e, then_block, else_block)?;
+                self.seal(then_block);
+                self.seal(else_block);
+
+                let one = self.constant(Op::Int(1), MachineMode::Int8, e.pos);
+                let zero = self.constant(Op::Int(0), MachineMode::Int8, e.pos);
+
+                self.current = then_block;
+                self.goto(join, e.pos);
+                self.current = else_block;
+                self.goto(join, e.pos);
+                self.seal(join);
+                self.current = join;
+
+                let phi = self.graph.add_phi(join, MachineMode::Int8, e.pos);
+                self.graph.inst_mut(phi).args = vec![one, zero];
+
+                return Ok(Some(phi));
+            }
+
+            _ => {}
+        }
+
+        let value = match self.call_site(e.id, e.pos)? {
+            Some(value) => value,
+            None => return Err("unit value"),
+        };
+
+        let value = match e.op {
+            BinOp::Cmp(CmpOp::Ne) => {
+                self.add(Op::BoolNot, Some(MachineMode::Int8), vec![value], e.pos)
+            }
+
+            BinOp::Cmp(CmpOp::Eq) => value,
+
+            BinOp::Cmp(op) => {
+                let zero = self.constant(Op::Int(0), MachineMode::Int32, e.pos);
+                self.add(
+                    Op::Cmp(to_cond_code(op)),
+                    Some(MachineMode::Int8),
+                    vec![value, zero],
+                    e.pos,
+                )
+            }
+
+            _ => value,
+        };
+
+        Ok(Some(value))
+    }
+
+    fn intrinsic_bin(
+        &mut self,
+        intrinsic: Intrinsic,
+        op: Option<BinOp>,
+        lhs: InstId,
+        rhs: InstId,
+        pos: Position,
+    ) -> BuildResult<InstId> {
+        use ty::MachineMode::{Float32, Float64, Int32, Int64, Int8};
+
+        let cond = match op {
+            Some(BinOp::Cmp(cmp)) => Some(to_cond_code(cmp)),
+            _ => None,
+        };
+
+        let (op, mode) = match intrinsic {
+            Intrinsic::ByteEq
+            | Intrinsic::BoolEq
+            | Intrinsic::CharEq
+            | Intrinsic::IntEq
+            | Intrinsic::LongEq
+            | Intrinsic::FloatEq
+            | Intrinsic::DoubleEq => (Op::Cmp(cond.unwrap_or(CondCode::Equal)), Int8),
+
+            Intrinsic::ByteCmp
+            | Intrinsic::CharCmp
+            | Intrinsic::IntCmp
+            | Intrinsic::LongCmp
+            | Intrinsic::FloatCmp
+            | Intrinsic::DoubleCmp
+                if cond.is_some() =>
+            {
+                (Op::Cmp(cond.unwrap()), Int8)
+            }
+
+            Intrinsic::ByteCmp | Intrinsic::CharCmp | Intrinsic::IntCmp => (Op::Sub, Int32),
+
+            Intrinsic::IntAdd => (Op::Add, Int32),
+            Intrinsic::IntSub => (Op::Sub, Int32),
+            Intrinsic::IntMul => (Op::Mul, Int32),
+            Intrinsic::IntDiv => (Op::Div, Int32),
+            Intrinsic::IntMod => (Op::Mod, Int32),
+            Intrinsic::IntOr => (Op::Or, Int32),
+            Intrinsic::IntAnd => (Op::And, Int32),
+            Intrinsic::IntXor => (Op::Xor, Int32),
+            Intrinsic::IntShl => (Op::Shl, Int32),
+            Intrinsic::IntSar => (Op::Sar, Int32),
+            Intrinsic::IntShr => (Op::Shr, Int32),
+
+            Intrinsic::LongAdd => (Op::Add, Int64),
+            Intrinsic::LongSub => (Op::Sub, Int64),
+            Intrinsic::LongMul => (Op::Mul, Int64),
+            Intrinsic::LongDiv => (Op::Div, Int64),
+            Intrinsic::LongMod => (Op::Mod, Int64),
+            Intrinsic::LongOr => (Op::Or, Int64),
+            Intrinsic::LongAnd => (Op::And, Int64),
+            Intrinsic::LongXor => (Op::Xor, Int64),
+            Intrinsic::LongShl => (Op::Shl, Int64),
+            Intrinsic::LongSar => (Op::Sar, Int64),
+            Intrinsic::LongShr => (Op::Shr, Int64),
+
+            Intrinsic::FloatAdd => (Op::Add, Float32),
+            Intrinsic::FloatSub => (Op::Sub, Float32),
+            Intrinsic::FloatMul => (Op::Mul, Float32),
+            Intrinsic::FloatDiv => (Op::Div, Float32),
+
+            Intrinsic::DoubleAdd => (Op::Add, Float64),
+            Intrinsic::DoubleSub => (Op::Sub, Float64),
+            Intrinsic::DoubleMul => (Op::Mul, Float64),
+            Intrinsic::DoubleDiv => (Op::Div, Float64),
+
+            _ => return Err("intrinsic"),
+        };
+
+        Ok(self.add(op, Some(mode), vec![lhs, rhs], pos))
+    }
+
+    fn call(&mut self, e: &'ast ExprCallType) -> BuildResult<Option<InstId>> {
+        match **self.src.map_calls.get(e.id).unwrap() {
+            CallType::Lambda(_) => return Err("lambda"),
+            CallType::EnumValue(_, _) => return Err("enum"),
+            _ => {}
+        }
+
+        let intrinsic = match self.intrinsic(e.id) {
+            Some(intrinsic) => intrinsic,
+            None => return self.call_site(e.id, e.pos),
+        };
+
+        use ty::MachineMode::{Float32, Float64, Int32, Int64, Int8};
+
+        let value = match intrinsic {
+            Intrinsic::GenericArrayLen | Intrinsic::StrLen => {
+                let object = self.value(e.object.as_ref().unwrap())?;
+                self.add(Op::NilCheck, None, vec![object], e.pos);
+                self.add(Op::ArrayLength, Some(Int32), vec![object], e.pos)
+            }
+
+            Intrinsic::StrGet => {
+                let object = e.object.as_ref().unwrap();
+                self.array_get(e.id, object, &e.args[0], Int8, e.pos)?
+            }
+
+            Intrinsic::Assert => {
+                let value = self.value(&e.args[0])?;
+                self.add(Op::Assert, None, vec![value], e.pos);
+                return Ok(None);
+            }
+
+            Intrinsic::Shl => {
+                let lhs = self.value(&e.args[0])?;
+                let rhs = self.value(&e.args[1])?;
+                self.add(Op::Shl, Some(Int32), vec![lhs, rhs], e.pos)
+            }
+
+            Intrinsic::CharToInt | Intrinsic::IntToChar => {
+                self.value(e.object.as_ref().unwrap())?
+            }
+
+            Intrinsic::BoolToInt | Intrinsic::ByteToInt => self.convert(e, Int32)?,
+            Intrinsic::BoolToLong | Intrinsic::ByteToLong => self.convert(e, Int64)?,
+            Intrinsic::LongToByte | Intrinsic::IntToByte => self.convert(e, Int8)?,
+            Intrinsic::LongToChar | Intrinsic::LongToInt => self.convert(e, Int32)?,
+            Intrinsic::CharToLong | Intrinsic::IntToLong => self.convert(e, Int64)?,
+
+            Intrinsic::IntToFloat | Intrinsic::LongToFloat | Intrinsic::DoubleToFloat => {
+                self.convert(e, Float32)?
+            }
+
+            Intrinsic::IntToDouble | Intrinsic::LongToDouble | Intrinsic::FloatToDouble => {
+                self.convert(e, Float64)?
+            }
+
+            Intrinsic::FloatToInt | Intrinsic::DoubleToInt => self.convert(e, Int32)?,
+            Intrinsic::FloatToLong | Intrinsic::DoubleToLong => self.convert(e, Int64)?,
+
+            Intrinsic::FloatIsNan | Intrinsic::DoubleIsNan => {
+                let object = self.value(e.object.as_ref().unwrap())?;
+                self.add(Op::IsNan, Some(Int8), vec![object], e.pos)
+            }
+
+            Intrinsic::FloatSqrt => {
+                let object = self.value(e.object.as_ref().unwrap())?;
+                self.add(Op::Sqrt, Some(Float32), vec![object], e.pos)
+            }
+
+            Intrinsic::DoubleSqrt => {
+                let object = self.value(e.object.as_ref().unwrap())?;
+                self.add(Op::Sqrt, Some(Float64), vec![object], e.pos)
+            }
+
+            Intrinsic::DefaultValue => {
+                let mode = mode_of(self.ty(e.id))?;
+                self.zero(mode, e.pos)
+            }
+
+            Intrinsic::ByteEq
+            | Intrinsic::ByteCmp
+            | Intrinsic::BoolEq
+            | Intrinsic::CharEq
+            | Intrinsic::CharCmp
+            | Intrinsic::IntEq
+            | Intrinsic::IntCmp
+            | Intrinsic::IntAdd
+            | Intrinsic::IntSub
+            | Intrinsic::IntMul
+            | Intrinsic::IntDiv
+            | Intrinsic::IntMod
+            | Intrinsic::IntOr
+            | Intrinsic::IntAnd
+            | Intrinsic::IntXor
+            | Intrinsic::IntShl
+            | Intrinsic::IntSar
+            | Intrinsic::IntShr
+            | Intrinsic::LongEq
+            | Intrinsic::LongCmp
+            | Intrinsic::LongAdd
+            | Intrinsic::LongSub
+            | Intrinsic::LongMul
+            | Intrinsic::LongDiv
+            | Intrinsic::LongMod
+            | Intrinsic::LongOr
+            | Intrinsic::LongAnd
+            | Intrinsic::LongXor
+            | Intrinsic::LongShl
+            | Intrinsic::LongSar
+            | Intrinsic::LongShr
+            | Intrinsic::FloatAdd
+            | Intrinsic::FloatSub
+            | Intrinsic::FloatMul
+            | Intrinsic::FloatDiv
+            | Intrinsic::DoubleAdd
+            | Intrinsic::DoubleSub
+            | Intrinsic::DoubleMul
+            | Intrinsic::DoubleDiv => {
+                let lhs = self.value(e.object.as_ref().unwrap())?;
+                let rhs = self.value(&e.args[0])?;
+                self.intrinsic_bin(intrinsic, None, lhs, rhs, e.pos)?
+            }
+
+            Intrinsic::ByteNot | Intrinsic::BoolNot => {
+                let (op, mode) = if intrinsic == Intrinsic::ByteNot {
+                    (Op::Not, Int8)
+                } else {
+                    (Op::BoolNot, Int8)
+                };
+
+                let object = self.value(e.object.as_ref().unwrap())?;
+                self.add(op, Some(mode), vec![object], e.pos)
+            }
+
+            _ => return Err("intrinsic"),
+        };
+
+        Ok(Some(value))
+    }
+
+    fn convert(&mut self, e: &'ast ExprCallType, mode: MachineMode) -> BuildResult<InstId> {
+        let object = self.value(e.object.as_ref().unwrap())?;
+        Ok(self.add(Op::Convert, Some(mode), vec![object], e.pos))
+    }
+
+    fn call_site(&mut self, id: NodeId, pos: Position) -> BuildResult<Option<InstId>> {
+        let csite = self.jit_info.map_csites.get(id).unwrap();
+
+        if csite.struct_return.is_some() {
+            return Err("struct return");
+        }
+
+        let fct = self.vm.fcts.idx(csite.callee);
+        let fct = fct.read();
+
+        let is_virtual = fct.is_virtual() && !csite.super_call;
+
+        if !is_virtual {
+            match fct.kind {
+                FctKind::Source(_) | FctKind::Native(_) => {}
+                FctKind::Definition | FctKind::Builtin(_) => return Err("callee"),
+            }
+        }
+
+        let types = csite
+            .args
+            .iter()
+            .map(|arg| self.specialize(arg.ty()))
+            .collect::<Vec<_>>();
+        check_arg_count(&types)?;
+
+        let mut args = Vec::with_capacity(csite.args.len());
+        let mut alloc_cls = None;
+
+        for (idx, arg) in csite.args.iter().enumerate() {
+            match *arg {
+                Arg::Expr(ast, ty, _) => {
+                    let ty = self.specialize(ty);
+                    mode_of(ty)?;
+                    let value = self.value(ast)?;
+
+                    if idx == 0
+                        && fct.has_self()
+                        && check_for_nil(ty)
+                        && !csite.super_call
+                        && !fct.is_virtual()
+                    {
+                        self.add(Op::NilCheck, None, vec![value], pos);
+                    }
+
+                    args.push(value);
+                }
+
+                Arg::Selfie(_, _) => {
+                    let var = self.src.var_self().id;
+                    let current = self.current;
+                    let value = self.read_var(var, current);
+                    args.push(value);
+                }
+
+                Arg::SelfieNew(ty, _) => {
+                    alloc_cls = Some(specialize_class_ty(self.vm, self.specialize(ty)));
+                    args.push(InstId(0));
+                }
+
+                Arg::Stack(_, _, _) => return Err("stack argument"),
+            }
+        }
+
+        // objects are allocated after evaluating the arguments of the
+        // constructor, arrays use the first argument as length
+        let object = if let Some(cls_id) = alloc_cls {
+            let cls = self.vm.class_defs.idx(cls_id);
+            let cls = cls.read();
+
+            let alloc_args = match cls.size {
+                ClassSize::Fixed(_) => Vec::new(),
+                _ if args.len() > 1 => vec![args[1]],
+                _ => Vec::new(),
+            };
+
+            let object = self.add(Op::Alloc(cls_id), Some(MachineMode::Ptr), alloc_args, pos);
+            args[0] = object;
+
+            Some(object)
+        } else {
+            None
+        };
+
+        let return_type = self.specialize(csite.return_type);
+
+        let mode = if return_type.is_unit() {
+            None
+        } else {
+            Some(mode_of(return_type)?)
+        };
+
+        let call = CallInfo {
+            fct_id: csite.callee,
+            cls_type_params: self.specialize_params(&csite.cls_type_params),
+            fct_type_params: self.specialize_params(&csite.fct_type_params),
+            vtable_index: if is_virtual { fct.vtable_index } else { None },
+            super_call: csite.super_call,
+        };
+
+        if object.is_some() {
+            self.emit_call(call, args, mode, pos);
+            return Ok(object);
+        }
+
+        if !is_virtual {
+            let graph =
+                self.inline_graph(call.fct_id, &call.cls_type_params, &call.fct_type_params);
+
+            if let Some(graph) = graph {
+                return Ok(self.splice(call.fct_id, graph, &args, mode, pos));
+            }
+        } else if call.fct_type_params.len() == 0 {
+            if let Some((vtable, fct_id)) = self.monomorphic_target(id) {
+                let empty = TypeParams::empty();
+
+                if let Some(graph) = self.inline_graph(fct_id, &empty, &empty) {
+                    return Ok(self.guarded_splice(vtable, fct_id, graph, call, args, mode, pos));
+                }
+            }
+        }
+
+        Ok(self.emit_call(call, args, mode, pos))
+    }
+
+    fn emit_call(
+        &mut self,
+        call: CallInfo,
+        args: Vec<InstId>,
+        mode: Option<MachineMode>,
+        pos: Position,
+    ) -> Option<InstId> {
+        let call_idx = self.graph.calls.len();
+        self.graph.calls.push(call);
+
+        let result = self.add(Op::Call(call_idx), mode, args, pos);
+        mode.map(|_| result)
+    }
+
+    // builds the graph of a callee that is small enough to be inlined
+    fn inline_graph(
+        &self,
+        fct_id: FctId,
+        cls_type_params: &TypeParams,
+        fct_type_params: &TypeParams,
+    ) -> Option<Graph> {
+        if self.inline_stack.len() > MAX_INLINE_DEPTH || self.inline_stack.contains(&fct_id) {
+            return None;
+        }
+
+        let fct = self.vm.fcts.idx(fct_id);
+        let fct = fct.read();
+
+        let src = match fct.kind {
+            FctKind::Source(ref src) => src,
+            _ => return None,
+        };
+
+        if fct.ctor != CtorType::None || fct.is_lambda || fct.ast.throws {
+            return None;
+        }
+
+        // the callee is compiled by another thread
+        let mut src = src.try_write()?;
+
+        let mut jit_info = JitInfo::new();
+        info::generate(
+            self.vm,
+            &fct,
+            &mut src,
+            &mut jit_info,
+            cls_type_params,
+            fct_type_params,
+        );
+
+        let mut inline_stack = self.inline_stack.clone();
+        inline_stack.push(fct_id);
+
+        let builder = Builder::new(
+            self.vm,
+            &fct,
+            &src,
+            &jit_info,
+            cls_type_params,
+            fct_type_params,
+            inline_stack,
+        );
+
+        let graph = builder.finish().ok()?;
+
+        if inline_size(&graph) > MAX_INLINE_SIZE {
+            return None;
+        }
+
+        Some(graph)
+    }
+
+    // receiver vtable and target of a virtual call that only saw
+    // a single receiver class in baseline code
+    fn monomorphic_target(&self, id: NodeId) -> Option<(Address, FctId)> {
+        let key = (self.cls_type_params.clone(), self.fct_type_params.clone());
+        let jit_fct_id = *self.src.specializations.read().get(&key)?;
+        let jit_fct = self.vm.jit_fcts.idx(jit_fct_id);
+
+        let (vtable, target) = match *jit_fct {
+            JitFct::Base(_) => ic::monomorphic_target(&jit_fct, id)?,
+            JitFct::Opt(_) => return None,
+        };
+
+        let data = self.vm.code_map.lock().get(target);
+
+        let fct_id = match data {
+            Some(CodeDescriptor::DoraFct(jit_fct_id)) => self.vm.jit_fcts.idx(jit_fct_id).fct_id(),
+            _ => return None,
+        };
+
+        let fct = self.vm.fcts.idx(fct_id);
+        let fct = fct.read();
+
+        match fct.parent {
+            FctParent::Class(cls_id) if !self.vm.classes.idx(cls_id).read().is_generic() => {
+                Some((vtable, fct_id))
+            }
+
+            _ => None,
+        }
+    }
+
+    // copies the graph of an inlined function into the current block, its
+    // parameters are replaced by the arguments and its returns continue in
+    // a new block that merges the returned values
+    fn splice(
+        &mut self,
+        fct_id: FctId,
+        callee: Graph,
+        args: &[InstId],
+        mode: Option<MachineMode>,
+        pos: Position,
+    ) -> Option<InstId> {
+        let inlined = self.graph.inlined.len();
+        self.graph.inlined.push(InlinedFct {
+            fct_id: fct_id,
+            pos: pos,
+            caller: None,
+        });
+
+        for fct in &callee.inlined {
+            self.graph.inlined.push(InlinedFct {
+                fct_id: fct.fct_id,
+                pos: fct.pos,
+                caller: Some(fct.caller.map_or(inlined, |caller| inlined + 1 + caller)),
+            });
+        }
+
+        let calls = self.graph.calls.len();
+        self.graph.calls.extend(callee.calls.iter().cloned());
+
+        let rpo = callee.rpo();
+        let mut blocks = vec![None; callee.blocks.len()];
+
+        for &block in &rpo {
+            let new_block = self.graph.new_block();
+            self.sealed.insert(new_block);
+            blocks[block.idx()] = Some(new_block);
+        }
+
+        let block_for = |block: BlockId| blocks[block.idx()].unwrap();
+        let exit = self.graph.new_block();
+        let mut values = vec![None; callee.insts.len()];
+        let mut copies = Vec::new();
+        let mut returned = Vec::new();
+
+        for &block in &rpo {
+            let new_block = block_for(block);
+
+            for &id in &callee.block(block).phis {
+                let inst = callee.inst(id);
+                let phi = self.graph.add_phi(new_block, inst.mode.unwrap(), inst.pos);
+                values[id.idx()] = Some(phi);
+                copies.push((id, phi));
+            }
+
+            for &id in &callee.block(block).insts {
+                let inst = callee.inst(id);
+
+                let op = match inst.op {
+                    Op::Param(idx) => {
+                        values[id.idx()] = Some(args[idx]);
+                        continue;
+                    }
+
+                    op if op.is_const() => {
+                        values[id.idx()] = Some(self.constant(op, inst.mode.unwrap(), inst.pos));
+                        continue;
+                    }
+
+                    Op::Return => {
+                        if let Some(&value) = inst.args.get(0) {
+                            returned.push(value);
+                        }
+
+                        Op::Goto
+                    }
+
+                    Op::Call(idx) => Op::Call(calls + idx),
+                    op => op,
+                };
+
+                let new_inst = self
+                    .graph
+                    .add_inst(new_block, op, inst.mode, Vec::new(), inst.pos);
+                values[id.idx()] = Some(new_inst);
+                copies.push((id, new_inst));
+            }
+
+            let succs = if callee.block(block).succs.is_empty() {
+                vec![exit]
+            } else {
+                callee
+                    .block(block)
+                    .succs
+                    .iter()
+                    .map(|&succ| block_for(succ))
+                    .collect()
+            };
+
+            let preds = callee
+                .block(block)
+                .preds
+                .iter()
+                .map(|&pred| block_for(pred))
+                .collect();
+
+            self.graph.blocks[new_block.idx()].preds = preds;
+
+            for succ in succs {
+                self.graph.blocks[new_block.idx()].succs.push(succ);
+
+                if succ == exit {
+                    self.graph.blocks[exit.idx()].preds.push(new_block);
+                }
+            }
+        }
+
+        for (id, new_inst) in copies {
+            let inst = callee.inst(id);
+
+            let args = match inst.op {
+                Op::Return => Vec::new(),
+                _ => inst
+                    .args
+                    .iter()
+                    .map(|arg| values[arg.idx()].unwrap())
+                    .collect(),
+            };
+
+            let new_inst = self.graph.inst_mut(new_inst);
+            new_inst.args = args;
+            new_inst.inlined = Some(inst.inlined.map_or(inlined, |idx| inlined + 1 + idx));
+        }
+
+        self.goto(block_for(callee.entry()), pos);
+        self.sealed.insert(exit);
+        self.current = exit;
+
+        let mode = mode?;
+
+        if returned.is_empty() {
+            return Some(self.zero(mode, pos));
+        }
+
+        let phi = self.graph.add_phi(exit, mode, pos);
+        self.graph.inst_mut(phi).args = returned
+            .into_iter()
+            .map(|value| values[value.idx()].unwrap())
+            .collect();
+
+        Some(phi)
+    }
+
+    // inlines the target of a virtual call for the receiver class seen
+    // before, other receivers invoke the function through the vtable
+    fn guarded_splice(
+        &mut self,
+        vtable: Address,
+        fct_id: FctId,
+        callee: Graph,
+        call: CallInfo,
+        args: Vec<InstId>,
+        mode: Option<MachineMode>,
+        pos: Position,
+    ) -> Option<InstId> {
+        use ty::MachineMode::{Int64, Int8};
+
+        // the first word of the object header is the vtable, the
+        // load also checks the receiver for nil
+        let receiver_vtable = self.add(Op::LoadField(0), Some(Int64), vec![args[0]], pos);
+        let expected = self.constant(Op::Int(vtable.to_usize() as i64), Int64, pos);
+        let cond = self.add(
+            Op::Cmp(CondCode::Equal),
+            Some(Int8),
+            vec![receiver_vtable, expected],
+            pos,
+        );
+
+        let inline_block = self.graph.new_block();
+        let call_block = self.graph.new_block();
+        let join = self.graph.new_block();
+
+        let current = self.current;
+        self.add(Op::If, None, vec![cond], pos);
+        self.graph.add_edge(current, inline_block);
+        self.graph.add_edge(current, call_block);
+        self.seal(inline_block);
+        self.seal(call_block);
+
+        self.current = inline_block;
+        let inlined = self.splice(fct_id, callee, &args, mode, pos);
+        self.goto(join, pos);
+
+        self.current = call_block;
+        let called = self.emit_call(call, args, mode, pos);
+        self.goto(join, pos);
+
+        self.seal(join);
+        self.current = join;
+
+        let mode = mode?;
+        let phi = self.graph.add_phi(join, mode, pos);
+        self.graph.inst_mut(phi).args = vec![inlined.unwrap(), called.unwrap()];
+
+        Some(phi)
+    }
+
+    fn assign(&mut self, e: &'ast ExprAssignType) -> BuildResult<()> {
+        if let Some(array) = e.lhs.to_array() {
+            return self.assign_array(e, array);
+        }
+
+        let &ident_type = self.src.map_idents.get(e.lhs.id()).unwrap();
+
+        match ident_type {
+            IdentType::Var(var) => {
+                let value = if let Some(op) = e.op {
+                    let current = self.current;
+                    let lhs = self.read_var(var, current);
+                    self.assign_op(e, op, lhs)?
+                } else {
+                    self.value(&e.rhs)?
+                };
+
+                let current = self.current;
+                self.write_var(var, current, value);
+            }
+
+            IdentType::Global(global_id) => {
+                let (address, mode) = self.global(global_id)?;
+
+                let value = if let Some(op) = e.op {
+                    let lhs = self.add(Op::LoadGlobal(address), Some(mode), Vec::new(), e.pos);
+                    self.assign_op(e, op, lhs)?
+                } else {
+                    self.value(&e.rhs)?
+                };
+
+                self.add(Op::StoreGlobal(address), None, vec![value], e.pos);
+            }
+
+            IdentType::Field(ty, field_id) => {
+                let object = match e.lhs.to_field() {
+                    Some(field) => self.value(&field.object)?,
+                    None => {
+                        let var = self.src.var_self().id;
+                        let current = self.current;
+                        self.read_var(var, current)
+                    }
+                };
+
+                let (offset, mode) = self.field(ty, field_id)?;
+
+                let value = if let Some(op) = e.op {
+                    let lhs = self.add(Op::LoadField(offset), Some(mode), vec![object], e.pos);
+                    self.assign_op(e, op, lhs)?
+                } else {
+                    self.value(&e.rhs)?
+                };
+
+                let barrier = self.needs_barrier(mode);
+                self.add(
+                    Op::StoreField(offset, barrier),
+                    None,
+                    vec![object, value],
+                    e.pos,
+                );
+            }
+
+            _ => return Err("assignment"),
+        }
+
+        Ok(())
+    }
+
+    fn assign_array(
+        &mut self,
+        e: &'ast ExprAssignType,
+        array: &'ast ExprArrayType,
+    ) -> BuildResult<()> {
+        let mode = match self.intrinsic(e.id) {
+            Some(Intrinsic::GenericArraySet) => mode_of(self.ty(array.id))?,
+            Some(Intrinsic::StrSet) => MachineMode::Int8,
+            _ => return Err("array assignment"),
+        };
+
+        let object = self.value(&array.object)?;
+        let index = self.value(&array.index)?;
+
+        let value = if let Some(op) = e.op {
+            match self.intrinsic(array.id) {
+                Some(Intrinsic::GenericArrayGet) | Some(Intrinsic::StrGet) => {}
+                _ => return Err("array assignment"),
+            }
+
+            self.array_check(array.id, object, index, array.pos);
+            let lhs = self.add(Op::LoadArray, Some(mode), vec![object, index], e.pos);
+            self.assign_op(e, op, lhs)?
+        } else {
+            let value = self.value(&e.rhs)?;
+            self.array_check(array.id, object, index, e.pos);
+            value
+        };
+
+        let barrier = self.needs_barrier(mode);
+        self.add(
+            Op::StoreArray(barrier),
+            None,
+            vec![object, index, value],
+            e.pos,
+        );
+
+        Ok(())
+    }
+
+    fn assign_op(
+        &mut self,
+        e: &'ast ExprAssignType,
+        op: AssignOp,
+        lhs: InstId,
+    ) -> BuildResult<InstId> {
+        let intrinsic = match self.intrinsic(op.id) {
+            Some(intrinsic) => intrinsic,
+            None => return Err("assignment operator"),
+        };
+
+        let rhs = self.value(&e.rhs)?;
+        self.intrinsic_bin(intrinsic, Some(op.op), lhs, rhs, e.pos)
+    }
+
+    fn array_get(
+        &mut self,
+        id: NodeId,
+        object: &'ast Expr,
+        index: &'ast Expr,
+        mode: MachineMode,
+        pos: Position,
+    ) -> BuildResult<InstId> {
+        let object = self.value(object)?;
+        let index = self.value(index)?;
+        self.array_check(id, object, index, pos);
+
+        Ok(self.add(Op::LoadArray, Some(mode), vec![object, index], pos))
+    }
+
+    fn array_check(&mut self, id: NodeId, object: InstId, index: InstId, pos: Position) {
+        self.add(Op::NilCheck, None, vec![object], pos);
+
+        if !self.vm.args.flag_omit_bounds_check && !self.jit_info.in_bounds.contains(&id) {
+            self.add(Op::BoundsCheck, None, vec![object, index], pos);
+        }
+    }
+
+    fn field(&self, ty: BuiltinType, field_id: FieldId) -> BuildResult<(i32, MachineMode)> {
+        let cls_id = specialize_class_ty(self.vm, self.specialize(ty));
+        let cls = self.vm.class_defs.idx(cls_id);
+        let cls = cls.read();
+        let field = &cls.fields[field_id.idx()];
+
+        Ok((field.offset, mode_of(field.ty)?))
+    }
+
+    fn global(&self, global_id: GlobalId) -> BuildResult<(usize, MachineMode)> {
+        let glob = self.vm.globals.idx(global_id);
+        let glob = glob.lock();
+
+        if glob.getter.is_some() {
+            return Err("global initializer");
+        }
+
+        Ok((glob.address_value.to_usize(), mode_of(glob.ty)?))
+    }
+
+    fn needs_barrier(&self, mode: MachineMode) -> bool {
+        self.vm.gc.needs_write_barrier() && mode == MachineMode::Ptr
+    }
+
+    fn intrinsic(&self, id: NodeId) -> Option<Intrinsic> {
+        self.jit_info.map_intrinsics.get(id).map(|&intr| intr)
+    }
+
+    fn ty(&self, id: NodeId) -> BuiltinType {
+        self.specialize(*self.src.map_tys.get(id).unwrap())
+    }
+
+    fn specialize(&self, ty: BuiltinType) -> BuiltinType {
+        specialize_type(self.vm, ty, self.cls_type_params, self.fct_type_params)
+    }
+
+    fn specialize_params(&self, params: &TypeParams) -> TypeParams {
+        params
+            .iter()
+            .map(|ty| self.specialize(ty))
+            .collect::<Vec<_>>()
+            .into()
+    }
+
+    fn add(
+        &mut self,
+        op: Op,
+        mode: Option<MachineMode>,
+        args: Vec<InstId>,
+        pos: Position,
+    ) -> InstId {
+        let current = self.current;
+        self.graph.add_inst(current, op, mode, args, pos)
+    }
+
+    fn constant(&mut self, op: Op, mode: MachineMode, pos: Position) -> InstId {
+        if let Some(&id) = self.consts.get(&(op, mode)) {
+            return id;
+        }
+
+        let id = self.graph.add_const(op, mode, pos);
+        self.consts.insert((op, mode), id);
+
+        id
+    }
+
+    fn zero(&mut self, mode: MachineMode, pos: Position) -> InstId {
+        let op = match mode {
+            MachineMode::Int8 | MachineMode::Int32 | MachineMode::Int64 => Op::Int(0),
+            MachineMode::Float32 | MachineMode::Float64 => Op::float(0.0),
+            MachineMode::Ptr => Op::Nil,
+        };
+
+        self.constant(op, mode, pos)
+    }
+
+    fn goto(&mut self, target: BlockId, pos: Position) {
+        if self.graph.is_terminated(self.current) {
+            return;
+        }
+
+        let current = self.current;
+        self.add(Op::Goto, None, Vec::new(), pos);
+        self.graph.add_edge(current, target);
+    }
+
+    // continues building in a block without predecessors, code after
+    // return, break and continue is removed later
+    fn unreachable(&mut self) {
+        let block = self.graph.new_block();
+        self.sealed.insert(block);
+        self.current = block;
+    }
+
+    fn write_var(&mut self, var: VarId, block: BlockId, value: InstId) {
+        self.defs.insert((var, block), value);
+    }
+
+    fn read_var(&mut self, var: VarId, block: BlockId) -> InstId {
+        if let Some(&value) = self.defs.get(&(var, block)) {
+            return value;
+        }
+
+        let mode = self.jit_info.ty(var).mode();
+        let pos = self.fct.ast.pos;
+
+        let value = if !self.sealed.contains(&block) {
+            let phi = self.graph.add_phi(block, mode, pos);
+            self.incomplete_phis
+                .entry(block)
+                .or_insert_with(Vec::new)
+                .push((var, phi));
+            phi
+        } else if self.graph.block(block).preds.len() == 1 {
+            let pred = self.graph.block(block).preds[0];
+            self.read_var(var, pred)
+        } else if self.graph.block(block).preds.is_empty() {
+            // only happens in unreachable code
+            self.zero(mode, pos)
+        } else {
+            let phi = self.graph.add_phi(block, mode, pos);
+            self.write_var(var, block, phi);
+            self.add_phi_operands(var, phi);
+            phi
+        };
+
+        self.write_var(var, block, value);
+        value
+    }
+
+    fn add_phi_operands(&mut self, var: VarId, phi: InstId) {
+        let block = self.graph.inst(phi).block;
+        let preds = self.graph.block(block).preds.clone();
+
+        for pred in preds {
+            let value = self.read_var(var, pred);
+            self.graph.inst_mut(phi).args.push(value);
+        }
+    }
+
+    // all predecessors of the block are known
+    fn seal(&mut self, block: BlockId) {
+        if let Some(phis) = self.incomplete_phis.remove(&block) {
+            for (var, phi) in phis {
+                self.add_phi_operands(var, phi);
+            }
+        }
+
+        self.sealed.insert(block);
+    }
+}
+
+fn mode_of(ty: BuiltinType) -> BuildResult<MachineMode> {
+    match ty {
+        BuiltinType::Nil => Ok(MachineMode::Ptr),
+
+        BuiltinType::Bool
+        | BuiltinType::Byte
+        | BuiltinType::Char
+        | BuiltinType::Int
+        | BuiltinType::Long
+        | BuiltinType::Float
+        | BuiltinType::Double
+        | BuiltinType::Class(_, _)
+        | BuiltinType::Enum(_)
+        | BuiltinType::Ptr => Ok(ty.mode()),
+
+        _ => Err("unsupported type"),
+    }
+}
+
+// number of instructions emitted for an inlined graph
+fn inline_size(graph: &Graph) -> usize {
+    graph
+        .rpo()
+        .into_iter()
+        .flat_map(|block| graph.block(block).insts.iter())
+        .filter(|&&inst| match graph.inst(inst).op {
+            Op::Param(_) | Op::Goto => false,
+            op => !op.is_const(),
+        })
+        .count()
+}
+
+// all arguments need to be passed in registers
+fn check_arg_count(types: &[BuiltinType]) -> BuildResult<()> {
+    use cpu::{FREG_PARAMS, REG_PARAMS};
+
+    let floats = types.iter().filter(|ty| ty.is_float()).count();
+    let ints = types.len() - floats;
+
+    if ints > REG_PARAMS.len() || floats > FREG_PARAMS.len() {
+        return Err("too many arguments");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,984 @@
+use std::collections::HashSet;
+
+use baseline::asm::BaselineAssembler;
+use baseline::codegen::CondCode;
+use baseline::expr::{ptr_for_fct_id, AllocationSize};
+use baseline::fct::GcPoint;
+use class::ClassSize;
+use cpu::{
+    FReg, Mem, Reg, FREG_PARAMS, FREG_RESULT, FREG_TMP1, REG_PARAMS, REG_RESULT, REG_TMP1, REG_TMP2,
+};
+use ctxt::{Fct, FctSrc, VM};
+use masm::Label;
+use mem;
+use object::{offset_of_array_length, Header};
+use opt::fct::JitOptFct;
+use opt::ir::{BlockId, Graph, InstId, Op};
+use opt::regalloc::{Allocation, Location};
+use os::signal::Trap;
+use ty::{BuiltinType, MachineMode};
+use vtable::VTable;
+
+pub fn generate<'a, 'ast: 'a>(
+    vm: &'a VM<'ast>,
+    fct: &Fct<'ast>,
+    src: &mut FctSrc,
+    graph: &Graph,
+    allocation: &Allocation,
+) -> Option<JitOptFct> {
+    let mut asm = BaselineAssembler::new(vm);
+    let labels = (0..graph.blocks.len())
+        .map(|_| asm.create_label())
+        .collect();
+
+    let mut cg = CodeGen {
+        vm: vm,
+        fct: fct,
+        src: src,
+        graph: graph,
+        allocation: allocation,
+        asm: asm,
+        labels: labels,
+        uses: graph.uses(),
+        fused: HashSet::new(),
+        inlined: None,
+    };
+
+    cg.generate();
+
+    cg.asm.jit_opt(allocation.framesize, fct.id, fct.ast.throws)
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum Source {
+    Const(InstId),
+    Loc(Location),
+}
+
+struct CodeGen<'a, 'ast: 'a> {
+    vm: &'a VM<'ast>,
+    fct: &'a Fct<'ast>,
+    src: &'a mut FctSrc,
+    graph: &'a Graph,
+    allocation: &'a Allocation,
+    asm: BaselineAssembler<'a, 'ast>,
+    labels: Vec<Label>,
+    uses: Vec<usize>,
+
+    // comparisons that are emitted together with the following branch
+    fused: HashSet<InstId>,
+
+    // inlined function of the instructions emitted last
+    inlined: Option<usize>,
+}
+
+impl<'a, 'ast> CodeGen<'a, 'ast>
+where
+    'ast: 'a,
+{
+    fn generate(&mut self) {
+        self.asm.prolog(self.allocation.framesize);
+
+        if !self.allocation.ptr_slots.is_empty() {
+            self.asm.load_nil(REG_TMP1);
+
+            for &offset in &self.allocation.ptr_slots {
+                self.asm
+                    .store_mem(MachineMode::Ptr, Mem::Local(offset), REG_TMP1.into());
+            }
+        }
+
+        let order = &self.allocation.order;
+
+        for (idx, &block) in order.iter().enumerate() {
+            self.asm.bind_label(self.labels[block.idx()]);
+            let next = order.get(idx + 1).cloned();
+            let insts = &self.graph.block(block).insts;
+
+            for (inst_idx, &inst) in insts.iter().enumerate() {
+                let succ = insts.get(inst_idx + 1).cloned();
+                self.set_inlined(self.graph.inst(inst).inlined);
+                self.emit_inst(block, inst, succ, next);
+            }
+        }
+    }
+
+    // line number tables record the frames of inlined functions
+    // for stack traces
+    fn set_inlined(&mut self, inlined: Option<usize>) {
+        if self.inlined == inlined {
+            return;
+        }
+
+        let mut frames = Vec::new();
+        let mut current = inlined;
+
+        while let Some(idx) = current {
+            let fct = &self.graph.inlined[idx];
+            frames.push((fct.fct_id, fct.pos.line as i32));
+            current = fct.caller;
+        }
+
+        self.asm.set_inlined(frames);
+        self.inlined = inlined;
+    }
+
+    fn emit_inst(
+        &mut self,
+        block: BlockId,
+        id: InstId,
+        succ: Option<InstId>,
+        next: Option<BlockId>,
+    ) {
+        let inst = self.graph.inst(id);
+        let args = &inst.args;
+
+        match inst.op {
+            Op::Int(_) | Op::Float(_) | Op::Nil | Op::Addr(_) => {}
+            Op::Phi => unreachable!(),
+
+            Op::Param(idx) => self.emit_param(id, idx),
+
+            Op::OsrFrame => self.store_reg(id, REG_PARAMS[0]),
+
+            Op::OsrLoad(offset) => {
+                let mode = self.mode(id);
+                let frame = self.use_reg(args[0], REG_TMP1);
+
+                if mode.is_float() {
+                    let dest = self.dest_freg(id, FREG_RESULT);
+                    self.asm
+                        .load_mem(mode, dest.into(), Mem::Base(frame, offset));
+                    self.store_freg(id, dest);
+                } else {
+                    let dest = self.dest_reg(id, REG_TMP2);
+                    self.asm
+                        .load_mem(mode, dest.into(), Mem::Base(frame, offset));
+                    self.store_reg(id, dest);
+                }
+            }
+
+            Op::Add
+            | Op::Sub
+            | Op::Mul
+            | Op::Div
+            | Op::Mod
+            | Op::And
+            | Op::Or
+            | Op::Xor
+            | Op::Shl
+            | Op::Shr
+            | Op::Sar => {
+                if self.mode(id).is_float() {
+                    self.emit_float_bin(id, args[0], args[1]);
+                } else {
+                    self.emit_int_bin(id, args[0], args[1]);
+                }
+            }
+
+            Op::Neg if self.mode(id).is_float() => {
+                let mode = self.mode(id);
+                let dest = self.dest_freg(id, FREG_RESULT);
+                self.load_freg(args[0], dest);
+                self.asm.float_neg(mode, dest, dest);
+                self.store_freg(id, dest);
+            }
+
+            Op::Neg | Op::Not | Op::BoolNot => {
+                let mode = self.mode(id);
+                let dest = self.dest_reg(id, REG_TMP1);
+                self.load_reg(args[0], dest);
+
+                match inst.op {
+                    Op::Neg => self.asm.int_neg(mode, dest, dest),
+                    Op::Not => self.asm.int_not(mode, dest, dest),
+                    _ => self.asm.bool_not(dest, dest),
+                }
+
+                self.store_reg(id, dest);
+            }
+
+            Op::Cmp(cond) => {
+                if self.is_fusable(id, succ) {
+                    self.fused.insert(id);
+                } else {
+                    self.emit_cmp(id, cond);
+                }
+            }
+
+            Op::Convert => self.emit_convert(id, args[0]),
+
+            Op::Sqrt => {
+                let src = self.use_freg(args[0], FREG_TMP1);
+                let dest = self.dest_freg(id, FREG_RESULT);
+                self.asm.float_sqrt(self.mode(id), dest, src);
+                self.store_freg(id, dest);
+            }
+
+            Op::IsNan => {
+                let src = self.use_freg(args[0], FREG_RESULT);
+                let dest = self.dest_reg(id, REG_TMP1);
+                self.asm.float_cmp_nan(self.mode(args[0]), dest, src);
+                self.store_reg(id, dest);
+            }
+
+            Op::LoadGlobal(address) => {
+                let mode = self.mode(id);
+                self.load_addr(address, REG_TMP1);
+
+                if mode.is_float() {
+                    let dest = self.dest_freg(id, FREG_RESULT);
+                    self.asm.load_mem(mode, dest.into(), Mem::Base(REG_TMP1, 0));
+                    self.store_freg(id, dest);
+                } else {
+                    let dest = self.dest_reg(id, REG_TMP2);
+                    self.asm.load_mem(mode, dest.into(), Mem::Base(REG_TMP1, 0));
+                    self.store_reg(id, dest);
+                }
+            }
+
+            Op::StoreGlobal(address) => {
+                let mode = self.mode(args[0]);
+                let value = self.use_any(args[0], REG_TMP2, FREG_RESULT);
+                self.load_addr(address, REG_TMP1);
+                self.asm.store_mem(mode, Mem::Base(REG_TMP1, 0), value);
+            }
+
+            Op::LoadField(offset) => {
+                let mode = self.mode(id);
+                let line = inst.pos.line as i32;
+                let base = self.use_reg(args[0], REG_TMP1);
+
+                if mode.is_float() {
+                    let dest = self.dest_freg(id, FREG_RESULT);
+                    self.asm.load_field(mode, dest.into(), base, offset, line);
+                    self.store_freg(id, dest);
+                } else {
+                    let dest = self.dest_reg(id, REG_TMP2);
+                    self.asm.load_field(mode, dest.into(), base, offset, line);
+                    self.store_reg(id, dest);
+                }
+            }
+
+            Op::StoreField(offset, write_barrier) => {
+                let mode = self.mode(args[1]);
+
+                // the write barrier destroys the base register
+                let base = if write_barrier {
+                    self.load_reg(args[0], REG_TMP1);
+                    REG_TMP1
+                } else {
+                    self.use_reg(args[0], REG_TMP1)
+                };
+
+                let value = self.use_any(args[1], REG_TMP2, FREG_RESULT);
+
+                self.asm.store_field(
+                    mode,
+                    base,
+                    offset,
+                    value,
+                    inst.pos.line as i32,
+                    write_barrier,
+                    self.vm.gc.card_table_offset(),
+                );
+            }
+
+            Op::NilCheck => {
+                let reg = self.use_reg(args[0], REG_TMP1);
+                self.asm.test_if_nil_bailout(inst.pos, reg, Trap::NIL);
+            }
+
+            Op::BoundsCheck => {
+                let array = self.use_reg(args[0], REG_TMP1);
+                let index = self.use_reg(args[1], REG_TMP2);
+                self.asm.check_index_out_of_bounds(inst.pos, array, index);
+            }
+
+            Op::ArrayLength => {
+                let array = self.use_reg(args[0], REG_TMP1);
+                let dest = self.dest_reg(id, REG_TMP1);
+                self.asm.load_mem(
+                    MachineMode::Int32,
+                    dest.into(),
+                    Mem::Base(array, offset_of_array_length()),
+                );
+                self.store_reg(id, dest);
+            }
+
+            Op::LoadArray => {
+                let mode = self.mode(id);
+                let array = self.use_reg(args[0], REG_TMP1);
+                let index = self.use_reg(args[1], REG_TMP2);
+
+                if mode.is_float() {
+                    let dest = self.dest_freg(id, FREG_RESULT);
+                    self.asm.load_array_elem(mode, dest.into(), array, index);
+                    self.store_freg(id, dest);
+                } else {
+                    let dest = self.dest_reg(id, REG_RESULT);
+                    self.asm.load_array_elem(mode, dest.into(), array, index);
+                    self.store_reg(id, dest);
+                }
+            }
+
+            Op::StoreArray(write_barrier) => {
+                let mode = self.mode(args[2]);
+                let array = self.use_reg(args[0], REG_TMP1);
+                let index = self.use_reg(args[1], REG_TMP2);
+                let value = self.use_any(args[2], REG_RESULT, FREG_RESULT);
+
+                self.asm.store_array_elem(
+                    mode,
+                    array,
+                    index,
+                    value,
+                    write_barrier,
+                    self.vm.gc.card_table_offset(),
+                );
+            }
+
+            Op::Alloc(cls_id) => {
+                let cls = self.vm.class_defs.idx(cls_id);
+                let cls = cls.read();
+                let length = args.get(0).cloned();
+
+                let size = match cls.size {
+                    ClassSize::Fixed(size) => AllocationSize::Fixed(size as usize),
+
+                    _ if length.is_some() => {
+                        self.load_reg(length.unwrap(), REG_TMP1);
+                        let element_size = element_size(&cls.size);
+                        self.asm
+                            .determine_array_size(REG_PARAMS[0], REG_TMP1, element_size, true);
+
+                        AllocationSize::Dynamic(REG_PARAMS[0])
+                    }
+
+                    _ => AllocationSize::Fixed(Header::size() as usize + mem::ptr_width_usize()),
+                };
+
+                let array_ref = match cls.size {
+                    ClassSize::ObjArray => true,
+                    _ => false,
+                };
+
+                let gcpoint = self.gcpoint(id);
+                self.asm
+                    .allocate(REG_RESULT, size, inst.pos, array_ref, gcpoint);
+
+                // store classptr and clear mark/fwdptr word in header
+                let cptr = (&**cls.vtable.as_ref().unwrap()) as *const VTable as *const u8;
+                let disp = self.asm.add_addr(cptr);
+                let pos = self.asm.pos() as i32;
+                self.asm.load_constpool(REG_TMP1, disp + pos);
+                self.asm
+                    .store_mem(MachineMode::Ptr, Mem::Base(REG_RESULT, 0), REG_TMP1.into());
+
+                self.asm.load_int_const(MachineMode::Ptr, REG_TMP1, 0);
+                self.asm.store_mem(
+                    MachineMode::Ptr,
+                    Mem::Base(REG_RESULT, mem::ptr_width()),
+                    REG_TMP1.into(),
+                );
+
+                match cls.size {
+                    ClassSize::Fixed(size) => {
+                        self.asm.copy_reg(MachineMode::Ptr, REG_TMP2, REG_RESULT);
+                        self.asm.fill_zero(REG_TMP2, size as usize);
+                    }
+
+                    _ => {
+                        // store length in object, arrays with length 0 do
+                        // not need to clear any data
+                        match length {
+                            Some(length) => self.load_reg(length, REG_TMP1),
+                            None => self.asm.load_int_const(MachineMode::Ptr, REG_TMP1, 0),
+                        }
+
+                        self.asm.store_mem(
+                            MachineMode::Ptr,
+                            Mem::Base(REG_RESULT, offset_of_array_length()),
+                            REG_TMP1.into(),
+                        );
+
+                        if length.is_some() {
+                            self.asm.copy_reg(MachineMode::Ptr, REG_TMP2, REG_RESULT);
+                            self.asm.int_add_imm(
+                                MachineMode::Ptr,
+                                REG_TMP2,
+                                REG_TMP2,
+                                (Header::size() + mem::ptr_width()) as i64,
+                            );
+                            self.asm.determine_array_size(
+                                REG_TMP1,
+                                REG_TMP1,
+                                element_size(&cls.size),
+                                false,
+                            );
+                            self.asm
+                                .int_add(MachineMode::Ptr, REG_TMP1, REG_TMP1, REG_TMP2);
+                            self.asm.fill_zero_dynamic(REG_TMP2, REG_TMP1);
+                        }
+                    }
+                }
+
+                self.store_reg(id, REG_RESULT);
+            }
+
+            Op::Call(idx) => self.emit_call(id, idx),
+
+            Op::Assert => {
+                let reg = self.use_reg(args[0], REG_TMP1);
+                let lbl = self.asm.create_label();
+                self.asm.test_and_jump_if(CondCode::Zero, reg, lbl);
+                self.asm.emit_bailout(lbl, Trap::ASSERT, inst.pos);
+            }
+
+            Op::Safepoint => {
+                self.asm.check_polling_page(self.vm.polling_page.addr());
+                let gcpoint = self.gcpoint(id);
+                self.asm.emit_gcpoint(gcpoint);
+            }
+
+            Op::Goto => {
+                let target = self.graph.block(block).succs[0];
+                self.emit_phi_moves(block, target);
+
+                if next != Some(target) {
+                    self.asm.jump(self.labels[target.idx()]);
+                }
+            }
+
+            Op::If => self.emit_if(block, args[0], next),
+
+            Op::Return => {
+                if let Some(&value) = args.get(0) {
+                    if self.mode(value).is_float() {
+                        self.load_freg(value, FREG_RESULT);
+                    } else {
+                        self.load_reg(value, REG_RESULT);
+                    }
+                }
+
+                let polling_page = self.vm.polling_page.addr();
+                self.asm
+                    .epilog_with_polling(self.allocation.framesize, polling_page);
+            }
+        }
+    }
+
+    fn emit_param(&mut self, id: InstId, idx: usize) {
+        let params = self.fct.params_with_self();
+        let is_float = params[idx].is_float();
+        let reg_idx = params[..idx]
+            .iter()
+            .filter(|ty| ty.is_float() == is_float)
+            .count();
+
+        if is_float {
+            self.store_freg(id, FREG_PARAMS[reg_idx]);
+        } else {
+            self.store_reg(id, REG_PARAMS[reg_idx]);
+        }
+    }
+
+    fn emit_int_bin(&mut self, id: InstId, lhs: InstId, rhs: InstId) {
+        let op = self.graph.inst(id).op;
+        let mode = self.mode(id);
+
+        if op == Op::Div || op == Op::Mod {
+            let rhs = self.use_reg(rhs, REG_TMP2);
+            self.load_reg(lhs, REG_RESULT);
+            let dest = self.dest_reg(id, REG_RESULT);
+
+            if op == Op::Div {
+                self.asm.int_div(mode, dest, REG_RESULT, rhs);
+            } else {
+                self.asm.int_mod(mode, dest, REG_RESULT, rhs);
+            }
+
+            self.store_reg(id, dest);
+            return;
+        }
+
+        // instructions overwrite the left operand
+        let rhs = self.use_reg(rhs, REG_TMP2);
+        let dest = match self.allocation.location(id) {
+            Some(Location::Reg(reg)) if reg != rhs => reg,
+            _ => REG_TMP1,
+        };
+        self.load_reg(lhs, dest);
+
+        match op {
+            Op::Add => self.asm.int_add(mode, dest, dest, rhs),
+            Op::Sub => self.asm.int_sub(mode, dest, dest, rhs),
+            Op::Mul => self.asm.int_mul(mode, dest, dest, rhs),
+            Op::And => self.asm.int_and(mode, dest, dest, rhs),
+            Op::Or => self.asm.int_or(mode, dest, dest, rhs),
+            Op::Xor => self.asm.int_xor(mode, dest, dest, rhs),
+            Op::Shl => self.asm.int_shl(mode, dest, dest, rhs),
+            Op::Shr => self.asm.int_shr(mode, dest, dest, rhs),
+            Op::Sar => self.asm.int_sar(mode, dest, dest, rhs),
+            _ => unreachable!(),
+        }
+
+        self.store_reg(id, dest);
+    }
+
+    fn emit_float_bin(&mut self, id: InstId, lhs: InstId, rhs: InstId) {
+        let op = self.graph.inst(id).op;
+        let mode = self.mode(id);
+
+        let rhs = self.use_freg(rhs, FREG_TMP1);
+        let dest = match self.allocation.location(id) {
+            Some(Location::FReg(reg)) if reg != rhs => reg,
+            _ => FREG_RESULT,
+        };
+        self.load_freg(lhs, dest);
+
+        match op {
+            Op::Add => self.asm.float_add(mode, dest, dest, rhs),
+            Op::Sub => self.asm.float_sub(mode, dest, dest, rhs),
+            Op::Mul => self.asm.float_mul(mode, dest, dest, rhs),
+            Op::Div => self.asm.float_div(mode, dest, dest, rhs),
+            _ => unreachable!(),
+        }
+
+        self.store_freg(id, dest);
+    }
+
+    // an integer comparison only used by the directly following branch
+    // sets the flags for the branch
+    fn is_fusable(&self, id: InstId, succ: Option<InstId>) -> bool {
+        let arg = self.graph.inst(id).args[0];
+
+        match succ {
+            Some(succ) => {
+                let succ = self.graph.inst(succ);
+
+                succ.op == Op::If
+                    && succ.args[0] == id
+                    && self.uses[id.idx()] == 1
+                    && !self.mode(arg).is_float()
+            }
+
+            None => false,
+        }
+    }
+
+    fn emit_cmp(&mut self, id: InstId, cond: CondCode) {
+        let inst = self.graph.inst(id);
+        let mode = self.mode(inst.args[0]);
+
+        if mode.is_float() {
+            let lhs = self.use_freg(inst.args[0], FREG_RESULT);
+            let rhs = self.use_freg(inst.args[1], FREG_TMP1);
+            let dest = self.dest_reg(id, REG_TMP1);
+            self.asm.float_cmp(mode, dest, lhs, rhs, cond);
+            self.store_reg(id, dest);
+        } else {
+            self.emit_int_cmp(inst.args[0], inst.args[1]);
+            let dest = self.dest_reg(id, REG_TMP1);
+            self.asm.set(dest, cond);
+            self.store_reg(id, dest);
+        }
+    }
+
+    fn emit_int_cmp(&mut self, lhs: InstId, rhs: InstId) {
+        let mode = self.mode(lhs);
+        let lhs = self.use_reg(lhs, REG_TMP1);
+        let rhs = self.use_reg(rhs, REG_TMP2);
+        self.asm.cmp_reg(mode, lhs, rhs);
+    }
+
+    fn emit_convert(&mut self, id: InstId, arg: InstId) {
+        let dest_mode = self.mode(id);
+        let src_mode = self.mode(arg);
+
+        match (src_mode.is_float(), dest_mode.is_float()) {
+            (false, false) => {
+                let src = self.use_reg(arg, REG_TMP1);
+                let dest = self.dest_reg(id, REG_TMP1);
+
+                match (src_mode, dest_mode) {
+                    (MachineMode::Int8, _) => self.asm.extend_byte(dest_mode, dest, src),
+                    (_, MachineMode::Int8) => self.asm.extend_byte(MachineMode::Int32, dest, src),
+                    (MachineMode::Int32, MachineMode::Int64) => self.asm.extend_int_long(dest, src),
+                    (MachineMode::Int64, MachineMode::Int32) => {
+                        self.asm.copy_reg(MachineMode::Int32, dest, src)
+                    }
+                    _ => unreachable!(),
+                }
+
+                self.store_reg(id, dest);
+            }
+
+            (false, true) => {
+                let src = self.use_reg(arg, REG_TMP1);
+                let dest = self.dest_freg(id, FREG_RESULT);
+                self.asm.int_to_float(dest_mode, dest, src_mode, src);
+                self.store_freg(id, dest);
+            }
+
+            (true, false) => {
+                let src = self.use_freg(arg, FREG_RESULT);
+                let dest = self.dest_reg(id, REG_TMP1);
+                self.asm.float_to_int(dest_mode, dest, src_mode, src);
+                self.store_reg(id, dest);
+            }
+
+            (true, true) => {
+                let src = self.use_freg(arg, FREG_RESULT);
+                let dest = self.dest_freg(id, FREG_TMP1);
+
+                if dest_mode == MachineMode::Float64 {
+                    self.asm.float_to_double(dest, src);
+                } else {
+                    self.asm.double_to_float(dest, src);
+                }
+
+                self.store_freg(id, dest);
+            }
+        }
+    }
+
+    fn emit_call(&mut self, id: InstId, idx: usize) {
+        let inst = self.graph.inst(id);
+        let mut reg_idx = 0;
+        let mut freg_idx = 0;
+
+        // registers of values are never used for arguments
+        for &arg in &inst.args {
+            if self.mode(arg).is_float() {
+                self.load_freg(arg, FREG_PARAMS[freg_idx]);
+                freg_idx += 1;
+            } else {
+                self.load_reg(arg, REG_PARAMS[reg_idx]);
+                reg_idx += 1;
+            }
+        }
+
+        let info = &self.graph.calls[idx];
+        let gcpoint = self.gcpoint(id);
+
+        if let Some(vtable_index) = info.vtable_index {
+            self.asm.cached_call(
+                None,
+                info.fct_id,
+                vtable_index,
+                inst.pos,
+                gcpoint,
+                BuiltinType::Unit,
+                REG_RESULT.into(),
+            );
+        } else {
+            let ptr = ptr_for_fct_id(
+                self.vm,
+                self.fct,
+                self.src,
+                info.fct_id,
+                info.cls_type_params.clone(),
+                info.fct_type_params.clone(),
+            );
+
+            self.asm.direct_call(
+                info.fct_id,
+                ptr.to_ptr(),
+                info.cls_type_params.clone(),
+                info.fct_type_params.clone(),
+                inst.pos,
+                gcpoint,
+                BuiltinType::Unit,
+                REG_RESULT.into(),
+            );
+        }
+
+        match inst.mode {
+            Some(mode) if mode.is_float() => self.store_freg(id, FREG_RESULT),
+            Some(_) => self.store_reg(id, REG_RESULT),
+            None => {}
+        }
+    }
+
+    fn emit_if(&mut self, block: BlockId, cond: InstId, next: Option<BlockId>) {
+        let succs = &self.graph.block(block).succs;
+        let (then_block, else_block) = (succs[0], succs[1]);
+        let then_lbl = self.labels[then_block.idx()];
+        let else_lbl = self.labels[else_block.idx()];
+
+        // critical edges are split, successors with phis have a single
+        // predecessor and therefore no phis
+        debug_assert!(self.graph.block(then_block).phis.is_empty());
+        debug_assert!(self.graph.block(else_block).phis.is_empty());
+
+        if self.fused.contains(&cond) {
+            let inst = self.graph.inst(cond);
+            let cond_code = match inst.op {
+                Op::Cmp(cond_code) => cond_code,
+                _ => unreachable!(),
+            };
+
+            self.emit_int_cmp(inst.args[0], inst.args[1]);
+            self.asm.jump_if(cond_code, then_lbl);
+
+            if next != Some(else_block) {
+                self.asm.jump(else_lbl);
+            }
+
+            return;
+        }
+
+        let reg = self.use_reg(cond, REG_TMP1);
+
+        if next == Some(then_block) {
+            self.asm.test_and_jump_if(CondCode::Zero, reg, else_lbl);
+        } else {
+            self.asm.test_and_jump_if(CondCode::NonZero, reg, then_lbl);
+
+            if next != Some(else_block) {
+                self.asm.jump(else_lbl);
+            }
+        }
+    }
+
+    // phis of the successor are assigned in parallel, moves are ordered
+    // such that no source is overwritten before it is read, cycles are
+    // broken with a temporary register
+    fn emit_phi_moves(&mut self, block: BlockId, succ: BlockId) {
+        let pred_idx = self
+            .graph
+            .block(succ)
+            .preds
+            .iter()
+            .position(|&pred| pred == block)
+            .unwrap();
+
+        let mut moves = Vec::new();
+
+        for &phi in &self.graph.block(succ).phis {
+            let src = self.source(self.graph.inst(phi).args[pred_idx]);
+            let dest = self.allocation.location(phi).unwrap();
+
+            if src != Source::Loc(dest) {
+                moves.push((src, dest, self.mode(phi)));
+            }
+        }
+
+        while !moves.is_empty() {
+            let ready = moves.iter().position(|&(_, dest, _)| {
+                moves.iter().all(|&(src, _, _)| src != Source::Loc(dest))
+            });
+
+            if let Some(idx) = ready {
+                let (src, dest, mode) = moves.remove(idx);
+                self.emit_move(mode, src, dest);
+                continue;
+            }
+
+            let (_, dest, mode) = moves[0];
+            let tmp = if mode.is_float() {
+                Location::FReg(FREG_PARAMS[2])
+            } else {
+                Location::Reg(REG_TMP2)
+            };
+
+            self.emit_move(mode, Source::Loc(dest), tmp);
+
+            for mov in &mut moves {
+                if mov.0 == Source::Loc(dest) {
+                    mov.0 = Source::Loc(tmp);
+                }
+            }
+        }
+    }
+
+    fn emit_move(&mut self, mode: MachineMode, src: Source, dest: Location) {
+        match (src, dest) {
+            (Source::Const(id), Location::Reg(reg)) => self.load_const(id, reg),
+            (Source::Const(id), Location::FReg(reg)) => self.load_float_const(id, reg),
+
+            (Source::Const(id), Location::Stack(_)) => {
+                let tmp = if mode.is_float() {
+                    self.load_float_const(id, FREG_RESULT);
+                    Location::FReg(FREG_RESULT)
+                } else {
+                    self.load_const(id, REG_TMP1);
+                    Location::Reg(REG_TMP1)
+                };
+
+                self.emit_move(mode, Source::Loc(tmp), dest);
+            }
+
+            (Source::Loc(Location::Reg(src)), Location::Reg(dest)) => {
+                self.asm.copy_reg(mode, dest, src)
+            }
+
+            (Source::Loc(Location::FReg(src)), Location::FReg(dest)) => {
+                self.asm.copy_freg(mode, dest, src)
+            }
+
+            (Source::Loc(Location::Reg(src)), Location::Stack(offset)) => {
+                self.asm.store_mem(mode, Mem::Local(offset), src.into())
+            }
+
+            (Source::Loc(Location::FReg(src)), Location::Stack(offset)) => {
+                self.asm.store_mem(mode, Mem::Local(offset), src.into())
+            }
+
+            (Source::Loc(Location::Stack(offset)), Location::Reg(dest)) => {
+                self.asm.load_mem(mode, dest.into(), Mem::Local(offset))
+            }
+
+            (Source::Loc(Location::Stack(offset)), Location::FReg(dest)) => {
+                self.asm.load_mem(mode, dest.into(), Mem::Local(offset))
+            }
+
+            (Source::Loc(Location::Stack(offset)), Location::Stack(_)) => {
+                let tmp = if mode.is_float() {
+                    Location::FReg(FREG_RESULT)
+                } else {
+                    Location::Reg(REG_TMP1)
+                };
+
+                self.emit_move(mode, Source::Loc(Location::Stack(offset)), tmp);
+                self.emit_move(mode, Source::Loc(tmp), dest);
+            }
+
+            _ => unreachable!(),
+        }
+    }
+
+    fn load_const(&mut self, id: InstId, dest: Reg) {
+        match self.graph.inst(id).op {
+            Op::Int(value) => self.asm.load_int_const(self.mode(id), dest, value),
+            Op::Nil => self.asm.load_nil(dest),
+            Op::Addr(address) => self.load_addr(address, dest),
+            _ => unreachable!(),
+        }
+    }
+
+    fn load_float_const(&mut self, id: InstId, dest: FReg) {
+        let value = self.graph.float_value(id).unwrap();
+        self.asm.load_float_const(self.mode(id), dest, value);
+    }
+
+    fn load_addr(&mut self, address: usize, dest: Reg) {
+        let disp = self.asm.add_addr(address as *const u8);
+        let pos = self.asm.pos() as i32;
+        self.asm.load_constpool(dest, disp + pos);
+    }
+
+    fn source(&self, id: InstId) -> Source {
+        if self.graph.inst(id).op.is_const() {
+            Source::Const(id)
+        } else {
+            Source::Loc(self.allocation.location(id).unwrap())
+        }
+    }
+
+    fn mode(&self, id: InstId) -> MachineMode {
+        self.graph.inst(id).mode.unwrap()
+    }
+
+    // register holding the value, values not in a register are loaded
+    // into the temporary register
+    fn use_reg(&mut self, id: InstId, tmp: Reg) -> Reg {
+        match self.source(id) {
+            Source::Loc(Location::Reg(reg)) => reg,
+
+            src => {
+                self.emit_move(self.mode(id), src, Location::Reg(tmp));
+                tmp
+            }
+        }
+    }
+
+    fn use_freg(&mut self, id: InstId, tmp: FReg) -> FReg {
+        match self.source(id) {
+            Source::Loc(Location::FReg(reg)) => reg,
+
+            src => {
+                self.emit_move(self.mode(id), src, Location::FReg(tmp));
+                tmp
+            }
+        }
+    }
+
+    fn use_any(&mut self, id: InstId, tmp: Reg, ftmp: FReg) -> ::baseline::expr::ExprStore {
+        if self.mode(id).is_float() {
+            self.use_freg(id, ftmp).into()
+        } else {
+            self.use_reg(id, tmp).into()
+        }
+    }
+
+    fn load_reg(&mut self, id: InstId, dest: Reg) {
+        let src = self.source(id);
+
+        if src != Source::Loc(Location::Reg(dest)) {
+            self.emit_move(self.mode(id), src, Location::Reg(dest));
+        }
+    }
+
+    fn load_freg(&mut self, id: InstId, dest: FReg) {
+        let src = self.source(id);
+
+        if src != Source::Loc(Location::FReg(dest)) {
+            self.emit_move(self.mode(id), src, Location::FReg(dest));
+        }
+    }
+
+    // register the result is computed into
+    fn dest_reg(&self, id: InstId, tmp: Reg) -> Reg {
+        match self.allocation.location(id) {
+            Some(Location::Reg(reg)) => reg,
+            _ => tmp,
+        }
+    }
+
+    fn dest_freg(&self, id: InstId, tmp: FReg) -> FReg {
+        match self.allocation.location(id) {
+            Some(Location::FReg(reg)) => reg,
+            _ => tmp,
+        }
+    }
+
+    fn store_reg(&mut self, id: InstId, src: Reg) {
+        let src = Location::Reg(src);
+
+        match self.allocation.location(id) {
+            Some(dest) if dest != src => self.emit_move(self.mode(id), Source::Loc(src), dest),
+            _ => {}
+        }
+    }
+
+    fn store_freg(&mut self, id: InstId, src: FReg) {
+        let src = Location::FReg(src);
+
+        match self.allocation.location(id) {
+            Some(dest) if dest != src => self.emit_move(self.mode(id), Source::Loc(src), dest),
+            _ => {}
+        }
+    }
+
+    fn gcpoint(&self, id: InstId) -> GcPoint {
+        let offsets = self
+            .allocation
+            .gcpoints
+            .get(&id)
+            .cloned()
+            .unwrap_or_else(Vec::new);
+
+        GcPoint::from_offsets(offsets)
+    }
+}
+
+fn element_size(size: &ClassSize) -> i32 {
+    match size {
+        ClassSize::Array(element_size) | ClassSize::StructArray(element_size) => *element_size,
+        ClassSize::ObjArray => mem::ptr_width(),
+        ClassSize::Str => 1,
+        ClassSize::Fixed(_) => unreachable!(),
+    }
+}
@@ -0,0 +1,58 @@
+use opt::ir::Graph;
+
+// removes instructions and phis whose values are never used by an
+// instruction with side effects
+pub fn dce(graph: &mut Graph) {
+    let mut live = vec![false; graph.insts.len()];
+    let mut worklist = Vec::new();
+
+    for block in graph.rpo() {
+        for &inst in &graph.block(block).insts {
+            if graph.inst(inst).op.has_side_effect() {
+                live[inst.idx()] = true;
+                worklist.push(inst);
+            }
+        }
+    }
+
+    while let Some(inst) = worklist.pop() {
+        for &arg in &graph.inst(inst).args {
+            if !live[arg.idx()] {
+                live[arg.idx()] = true;
+                worklist.push(arg);
+            }
+        }
+    }
+
+    for block in &mut graph.blocks {
+        block.insts.retain(|inst| live[inst.idx()]);
+        block.phis.retain(|inst| live[inst.idx()]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use opt::ir::Op;
+    use opt::tests::optimized_graph_for;
+
+    #[test]
+    fn dce_unused_values() {
+        let graph = optimized_graph_for(
+            "optimize fun f(a: int) -> int {
+                var x = a * 3;
+                var i = 0;
+                while i < 10 { x = x * a; i = i + 1; }
+                return a;
+            }",
+            "f",
+        );
+
+        for block in graph.rpo() {
+            assert!(graph.block(block).phis.len() <= 1);
+
+            for &inst in &graph.block(block).insts {
+                assert!(graph.inst(inst).op != Op::Mul);
+            }
+        }
+    }
+}
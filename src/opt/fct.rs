@@ -1,12 +1,94 @@
-use ctxt::FctId;
+use std::collections::HashSet;
+
+use baseline::fct::{
+    try_install_code, Bailouts, Comment, Comments, GcPoint, GcPoints, LineNumberTable,
+};
+use ctxt::{FctId, VM};
+use dseg::DSeg;
 use gc::Address;
 
 pub struct JitOptFct {
+    code_start: Address,
+    code_end: Address,
+
     pub fct_id: FctId,
+    pub throws: bool,
+
+    // pointer to beginning of function
     pub fct_start: Address,
+
+    // machine code length in bytes
+    fct_len: usize,
+
+    pub framesize: i32,
+    pub bailouts: Bailouts,
+    pub nil_checks: HashSet<i32>,
+    gcpoints: GcPoints,
+    comments: Comments,
+    linenos: LineNumberTable,
 }
 
 impl JitOptFct {
+    pub fn from_buffer(
+        vm: &VM,
+        dseg: &DSeg,
+        buffer: &[u8],
+        bailouts: Bailouts,
+        nil_checks: HashSet<i32>,
+        gcpoints: GcPoints,
+        framesize: i32,
+        comments: Comments,
+        linenos: LineNumberTable,
+        fct_id: FctId,
+        throws: bool,
+    ) -> Option<JitOptFct> {
+        let (ptr, fct_start) = try_install_code(vm, dseg, buffer)?;
+        let size = dseg.size() as usize + buffer.len();
+
+        Some(JitOptFct {
+            code_start: ptr,
+            code_end: ptr.offset(size),
+            fct_id: fct_id,
+            throws: throws,
+            fct_start: fct_start,
+            fct_len: buffer.len(),
+            framesize: framesize,
+            bailouts: bailouts,
+            nil_checks: nil_checks,
+            gcpoints: gcpoints,
+            comments: comments,
+            linenos: linenos,
+        })
+    }
+
+    pub fn lineno_for_offset(&self, offset: i32) -> i32 {
+        self.linenos.get(offset)
+    }
+
+    pub fn linenos(&self) -> &LineNumberTable {
+        &self.linenos
+    }
+
+    pub fn inlined_for_offset(&self, offset: i32) -> &[(FctId, i32)] {
+        self.linenos.inlined(offset)
+    }
+
+    pub fn gcpoint_for_offset(&self, offset: i32) -> Option<&GcPoint> {
+        self.gcpoints.get(offset)
+    }
+
+    pub fn nil_check_for_offset(&self, offset: i32) -> bool {
+        self.nil_checks.contains(&offset)
+    }
+
+    pub fn ptr_start(&self) -> Address {
+        self.code_start
+    }
+
+    pub fn ptr_end(&self) -> Address {
+        self.code_end
+    }
+
     pub fn fct_id(&self) -> FctId {
         self.fct_id
     }
@@ -14,4 +96,12 @@ impl JitOptFct {
     pub fn fct_ptr(&self) -> Address {
         self.fct_start
     }
+
+    pub fn fct_len(&self) -> usize {
+        self.fct_len
+    }
+
+    pub fn get_comment(&self, pos: i32) -> Option<&[Comment]> {
+        self.comments.get(pos)
+    }
 }
@@ -0,0 +1,392 @@
+use std::collections::HashMap;
+
+use baseline::codegen::CondCode;
+use opt::ir::{resolve, Graph, InstId, Op};
+use ty::MachineMode;
+
+// constant folding, algebraic simplification and removal of
+// branches with constant conditions, repeated until nothing changes
+pub fn fold(graph: &mut Graph) {
+    loop {
+        let mut changed = fold_insts(graph);
+        changed |= fold_branches(graph);
+        changed |= graph.remove_unreachable();
+        changed |= graph.remove_trivial_phis();
+
+        if !changed {
+            break;
+        }
+    }
+}
+
+fn fold_insts(graph: &mut Graph) -> bool {
+    let mut aliases = HashMap::new();
+    let mut changed = false;
+
+    for block in graph.rpo() {
+        let insts = graph.block(block).insts.clone();
+
+        for id in insts {
+            let args = graph
+                .inst(id)
+                .args
+                .iter()
+                .map(|&arg| resolve(&aliases, arg))
+                .collect::<Vec<_>>();
+
+            match simplify(graph, id, &args) {
+                Simplified::Const(op) => {
+                    let inst = graph.inst_mut(id);
+                    inst.op = op;
+                    inst.args.clear();
+                    changed = true;
+                }
+
+                Simplified::Alias(alias) => {
+                    aliases.insert(id, alias);
+                }
+
+                Simplified::Remove => {
+                    graph.blocks[block.idx()].insts.retain(|&inst| inst != id);
+                    changed = true;
+                }
+
+                Simplified::None => {}
+            }
+        }
+    }
+
+    if !aliases.is_empty() {
+        graph.replace_uses(&aliases);
+        changed = true;
+
+        // aliased instructions are pure and not used anymore
+        for block in &mut graph.blocks {
+            block.insts.retain(|inst| !aliases.contains_key(inst));
+        }
+    }
+
+    changed
+}
+
+fn fold_branches(graph: &mut Graph) -> bool {
+    let mut changed = false;
+
+    for block in graph.rpo() {
+        let term = graph.terminator(block);
+
+        if graph.inst(term).op != Op::If {
+            continue;
+        }
+
+        let cond = match graph.int_value(graph.inst(term).args[0]) {
+            Some(value) => value != 0,
+            None => continue,
+        };
+
+        let not_taken = graph.block(block).succs[if cond { 1 } else { 0 }];
+
+        let inst = graph.inst_mut(term);
+        inst.op = Op::Goto;
+        inst.args.clear();
+
+        graph.remove_edge(block, not_taken);
+        changed = true;
+    }
+
+    changed
+}
+
+enum Simplified {
+    None,
+    Const(Op),
+    Alias(InstId),
+    Remove,
+}
+
+fn simplify(graph: &Graph, id: InstId, args: &[InstId]) -> Simplified {
+    let inst = graph.inst(id);
+
+    let mode = match inst.mode {
+        Some(mode) => mode,
+
+        None => {
+            // objects are never nil after allocation
+            if inst.op == Op::NilCheck {
+                match graph.inst(args[0]).op {
+                    Op::Addr(_) | Op::Alloc(_) => return Simplified::Remove,
+                    _ => {}
+                }
+            }
+
+            return Simplified::None;
+        }
+    };
+
+    if let Some(op) = fold_const(graph, inst.op, mode, args) {
+        return Simplified::Const(op);
+    }
+
+    if !mode.is_float() && args.len() == 2 {
+        let rhs = graph.int_value(args[1]);
+        let lhs = graph.int_value(args[0]);
+
+        let alias = match (inst.op, lhs, rhs) {
+            (Op::Add, _, Some(0))
+            | (Op::Sub, _, Some(0))
+            | (Op::Or, _, Some(0))
+            | (Op::Xor, _, Some(0))
+            | (Op::Shl, _, Some(0))
+            | (Op::Shr, _, Some(0))
+            | (Op::Sar, _, Some(0))
+            | (Op::Mul, _, Some(1))
+            | (Op::Div, _, Some(1)) => Some(args[0]),
+
+            (Op::Add, Some(0), _) | (Op::Or, Some(0), _) | (Op::Mul, Some(1), _) => Some(args[1]),
+
+            (Op::And, _, Some(value)) if value == canonical(mode, -1) => Some(args[0]),
+            (Op::And, Some(value), _) if value == canonical(mode, -1) => Some(args[1]),
+
+            _ => None,
+        };
+
+        if let Some(alias) = alias {
+            return Simplified::Alias(alias);
+        }
+
+        match (inst.op, lhs, rhs) {
+            (Op::Mul, _, Some(0)) | (Op::Mul, Some(0), _) | (Op::And, _, Some(0)) => {
+                return Simplified::Const(Op::Int(0));
+            }
+
+            _ => {}
+        }
+    }
+
+    if inst.op == Op::BoolNot {
+        let arg = graph.inst(args[0]);
+
+        if arg.op == Op::BoolNot {
+            return Simplified::Alias(arg.args[0]);
+        }
+    }
+
+    Simplified::None
+}
+
+fn fold_const(graph: &Graph, op: Op, mode: MachineMode, args: &[InstId]) -> Option<Op> {
+    if args.is_empty() || !args.iter().all(|&arg| graph.inst(arg).op.is_const()) {
+        return None;
+    }
+
+    let arg_mode = graph.inst(args[0]).mode.unwrap();
+
+    if arg_mode.is_float() {
+        let lhs = graph.float_value(args[0])?;
+
+        if args.len() == 1 {
+            return fold_float_un(op, mode, arg_mode, lhs);
+        }
+
+        let rhs = graph.float_value(args[1])?;
+
+        if let Op::Cmp(cond) = op {
+            // leave unordered comparisons to the machine
+            if lhs.is_nan() || rhs.is_nan() {
+                return None;
+            }
+
+            return Some(Op::Int(compare(cond, lhs, rhs) as i64));
+        }
+
+        let value = match op {
+            Op::Add => lhs + rhs,
+            Op::Sub => lhs - rhs,
+            Op::Mul => lhs * rhs,
+            Op::Div => lhs / rhs,
+            _ => return None,
+        };
+
+        return Some(Op::float(canonical_float(mode, value)));
+    }
+
+    let lhs = graph.int_value(args[0])?;
+
+    if args.len() == 1 {
+        return fold_int_un(op, mode, arg_mode, lhs);
+    }
+
+    let rhs = graph.int_value(args[1])?;
+
+    if let Op::Cmp(cond) = op {
+        return Some(Op::Int(compare(cond, lhs, rhs) as i64));
+    }
+
+    let shift = if mode == MachineMode::Int64 { 63 } else { 31 };
+
+    let value = match op {
+        Op::Add => lhs.wrapping_add(rhs),
+        Op::Sub => lhs.wrapping_sub(rhs),
+        Op::Mul => lhs.wrapping_mul(rhs),
+
+        // division by zero and overflow trap at runtime
+        Op::Div if mode == MachineMode::Int32 => (lhs as i32).checked_div(rhs as i32)? as i64,
+        Op::Mod if mode == MachineMode::Int32 => (lhs as i32).checked_rem(rhs as i32)? as i64,
+        Op::Div => lhs.checked_div(rhs)?,
+        Op::Mod => lhs.checked_rem(rhs)?,
+
+        Op::And => lhs & rhs,
+        Op::Or => lhs | rhs,
+        Op::Xor => lhs ^ rhs,
+
+        Op::Shl => lhs.wrapping_shl((rhs & shift) as u32),
+        Op::Sar if mode == MachineMode::Int32 => ((lhs as i32) >> (rhs & shift)) as i64,
+        Op::Sar => lhs >> (rhs & shift),
+        Op::Shr if mode == MachineMode::Int32 => ((lhs as u32) >> (rhs & shift)) as i64,
+        Op::Shr => ((lhs as u64) >> (rhs & shift)) as i64,
+
+        _ => return None,
+    };
+
+    Some(Op::Int(canonical(mode, value)))
+}
+
+fn fold_int_un(op: Op, mode: MachineMode, arg_mode: MachineMode, value: i64) -> Option<Op> {
+    let value = match op {
+        Op::Neg => value.wrapping_neg(),
+        Op::Not => !value,
+        Op::BoolNot => value ^ 1,
+
+        Op::Convert if mode == MachineMode::Float32 => {
+            return Some(Op::float(value as f32 as f64));
+        }
+
+        Op::Convert if mode == MachineMode::Float64 => return Some(Op::float(value as f64)),
+
+        Op::Convert => value,
+
+        _ => return None,
+    };
+
+    // Int32 values are sign-extended, conversion to Int64 keeps the sign
+    let value = if arg_mode == MachineMode::Int8 {
+        value & 0xFF
+    } else {
+        value
+    };
+
+    Some(Op::Int(canonical(mode, value)))
+}
+
+fn fold_float_un(op: Op, mode: MachineMode, arg_mode: MachineMode, value: f64) -> Option<Op> {
+    let value = match op {
+        Op::Neg => -value,
+        Op::Sqrt if arg_mode == MachineMode::Float32 => (value as f32).sqrt() as f64,
+        Op::Sqrt => value.sqrt(),
+        Op::IsNan => return Some(Op::Int(value.is_nan() as i64)),
+
+        Op::Convert if mode.is_float() => value,
+
+        // out of range values are handled by the machine
+        Op::Convert => {
+            let truncated = value.trunc();
+
+            let (min, max) = match mode {
+                MachineMode::Int32 => (i32::min_value() as f64, i32::max_value() as f64),
+                MachineMode::Int64 => (-9.2e18, 9.2e18),
+                _ => return None,
+            };
+
+            if !(truncated >= min && truncated <= max) {
+                return None;
+            }
+
+            return Some(Op::Int(truncated as i64));
+        }
+
+        _ => return None,
+    };
+
+    Some(Op::float(canonical_float(mode, value)))
+}
+
+fn compare<T: PartialOrd>(cond: CondCode, lhs: T, rhs: T) -> bool {
+    match cond {
+        CondCode::Equal => lhs == rhs,
+        CondCode::NotEqual => lhs != rhs,
+        CondCode::Greater => lhs > rhs,
+        CondCode::GreaterEq => lhs >= rhs,
+        CondCode::Less => lhs < rhs,
+        CondCode::LessEq => lhs <= rhs,
+        _ => unreachable!(),
+    }
+}
+
+// integer constants are stored sign-extended, bytes are unsigned
+pub fn canonical(mode: MachineMode, value: i64) -> i64 {
+    match mode {
+        MachineMode::Int8 => value & 0xFF,
+        MachineMode::Int32 => value as i32 as i64,
+        _ => value,
+    }
+}
+
+fn canonical_float(mode: MachineMode, value: f64) -> f64 {
+    match mode {
+        MachineMode::Float32 => value as f32 as f64,
+        _ => value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use opt::fold::fold;
+    use opt::ir::{Graph, Op};
+    use opt::tests::{graph_for, returned_value};
+
+    fn folded(code: &'static str) -> Graph {
+        let mut graph = graph_for(code, "f");
+        fold(&mut graph);
+
+        graph
+    }
+
+    #[test]
+    fn fold_arithmetic() {
+        let graph = folded("optimize fun f() -> int { return (1 + 2) * 3; }");
+        assert_eq!(Op::Int(9), graph.inst(returned_value(&graph)).op);
+    }
+
+    #[test]
+    fn fold_overflow() {
+        let graph = folded("optimize fun f() -> int { return 2147483647 + 1; }");
+        assert_eq!(Op::Int(-2147483648), graph.inst(returned_value(&graph)).op);
+    }
+
+    #[test]
+    fn fold_keeps_division_by_zero() {
+        let graph = folded("optimize fun f() -> int { return 1 / 0; }");
+        assert_eq!(Op::Div, graph.inst(returned_value(&graph)).op);
+    }
+
+    #[test]
+    fn fold_identities() {
+        let graph = folded("optimize fun f(a: int) -> int { return (a + 0) * 1; }");
+        assert_eq!(Op::Param(0), graph.inst(returned_value(&graph)).op);
+    }
+
+    #[test]
+    fn fold_branch() {
+        let graph = folded(
+            "optimize fun f(a: int) -> int {
+                if 1 < 2 { return a; } else { return 0; }
+            }",
+        );
+
+        assert!(graph
+            .rpo()
+            .iter()
+            .all(|&block| graph.inst(graph.terminator(block)).op != Op::If));
+        assert_eq!(Op::Param(0), graph.inst(returned_value(&graph)).op);
+    }
+}
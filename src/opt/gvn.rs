@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+
+use baseline::codegen::CondCode;
+use opt::ir::{resolve, BlockId, Graph, InstId, Op};
+use ty::MachineMode;
+
+type Key = (Op, Option<MachineMode>, Vec<InstId>);
+
+// dominator-based global value numbering: an instruction is replaced by an
+// equal instruction in a dominating block, checks are removed when an equal
+// check already dominates them
+pub fn gvn(graph: &mut Graph) {
+    let rpo = graph.rpo();
+    let idom = graph.dominators(&rpo);
+    let mut children = vec![Vec::new(); graph.blocks.len()];
+
+    for &block in &rpo[1..] {
+        children[idom[block.idx()].unwrap().idx()].push(block);
+    }
+
+    let mut gvn = Gvn {
+        graph: graph,
+        children: children,
+        table: HashMap::new(),
+        aliases: HashMap::new(),
+    };
+
+    let entry = gvn.graph.entry();
+    gvn.visit(entry);
+
+    let aliases = gvn.aliases;
+
+    if !aliases.is_empty() {
+        graph.replace_uses(&aliases);
+
+        for block in &mut graph.blocks {
+            block.insts.retain(|inst| !aliases.contains_key(inst));
+        }
+    }
+}
+
+struct Gvn<'a> {
+    graph: &'a Graph,
+    children: Vec<Vec<BlockId>>,
+    table: HashMap<Key, InstId>,
+    aliases: HashMap<InstId, InstId>,
+}
+
+impl<'a> Gvn<'a> {
+    fn visit(&mut self, block: BlockId) {
+        let mut added = Vec::new();
+
+        for id in self.graph.block(block).insts.clone() {
+            let key = match self.key(id) {
+                Some(key) => key,
+                None => continue,
+            };
+
+            if let Some(&existing) = self.table.get(&key) {
+                self.aliases.insert(id, existing);
+            } else {
+                self.table.insert(key.clone(), id);
+                added.push(key);
+            }
+        }
+
+        for child in self.children[block.idx()].clone() {
+            self.visit(child);
+        }
+
+        for key in added {
+            self.table.remove(&key);
+        }
+    }
+
+    fn key(&self, id: InstId) -> Option<Key> {
+        let inst = self.graph.inst(id);
+
+        if !is_pure(inst.op) && inst.op != Op::NilCheck && inst.op != Op::BoundsCheck {
+            return None;
+        }
+
+        let mut args = inst
+            .args
+            .iter()
+            .map(|&arg| resolve(&self.aliases, arg))
+            .collect::<Vec<_>>();
+
+        if is_commutative(inst.op) && args[0] > args[1] {
+            args.swap(0, 1);
+        }
+
+        Some((inst.op, inst.mode, args))
+    }
+}
+
+// instructions that only depend on their arguments, a dominated copy
+// computes the same value (a trapping copy is dominated by the
+// original trap)
+fn is_pure(op: Op) -> bool {
+    match op {
+        Op::Int(_)
+        | Op::Float(_)
+        | Op::Nil
+        | Op::Addr(_)
+        | Op::Add
+        | Op::Sub
+        | Op::Mul
+        | Op::Div
+        | Op::Mod
+        | Op::And
+        | Op::Or
+        | Op::Xor
+        | Op::Shl
+        | Op::Shr
+        | Op::Sar
+        | Op::Neg
+        | Op::Not
+        | Op::BoolNot
+        | Op::Cmp(_)
+        | Op::Convert
+        | Op::Sqrt
+        | Op::IsNan
+        | Op::ArrayLength => true,
+        _ => false,
+    }
+}
+
+fn is_commutative(op: Op) -> bool {
+    match op {
+        Op::Add | Op::Mul | Op::And | Op::Or | Op::Xor => true,
+        Op::Cmp(CondCode::Equal) | Op::Cmp(CondCode::NotEqual) => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use opt::ir::Op;
+    use opt::tests::optimized_graph_for;
+
+    fn count(code: &'static str, op: Op) -> usize {
+        let graph = optimized_graph_for(code, "f");
+
+        graph
+            .rpo()
+            .iter()
+            .flat_map(|&block| graph.block(block).insts.iter())
+            .filter(|&&inst| graph.inst(inst).op == op)
+            .count()
+    }
+
+    #[test]
+    fn gvn_arithmetic() {
+        let code = "optimize fun f(a: int, b: int) -> int { return (a + b) * (b + a); }";
+        assert_eq!(1, count(code, Op::Add));
+    }
+
+    #[test]
+    fn gvn_dominated_checks() {
+        let code = "optimize fun f(a: Array<int>, i: int) -> int { return a[i] + a[i]; }";
+        assert_eq!(1, count(code, Op::BoundsCheck));
+        assert_eq!(1, count(code, Op::NilCheck));
+    }
+
+    #[test]
+    fn gvn_keeps_checks_in_branches() {
+        let code = "optimize fun f(a: Array<int>, c: bool) -> int {
+            if c { return a[0]; }
+            return a[0];
+        }";
+        assert_eq!(2, count(code, Op::BoundsCheck));
+    }
+}
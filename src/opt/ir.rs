@@ -0,0 +1,619 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use baseline::codegen::CondCode;
+use class::{ClassDefId, TypeParams};
+use ctxt::FctId;
+use dora_parser::lexer::position::Position;
+use ty::MachineMode;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BlockId(pub usize);
+
+impl BlockId {
+    pub fn idx(self) -> usize {
+        self.0
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct InstId(pub usize);
+
+impl InstId {
+    pub fn idx(self) -> usize {
+        self.0
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Op {
+    Param(usize),
+    // frame pointer of the baseline code continued by OSR code and loads of
+    // local variables from this frame
+    OsrFrame,
+    OsrLoad(i32),
+    Int(i64),
+    // bit pattern of the f64 value, allows hashing of constants
+    Float(u64),
+    Nil,
+    // address of object in perm space (e.g. string literal)
+    Addr(usize),
+    Phi,
+
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    And,
+    Or,
+    Xor,
+    Shl,
+    Shr,
+    Sar,
+    Neg,
+    Not,
+    BoolNot,
+    Cmp(CondCode),
+    Convert,
+    Sqrt,
+    IsNan,
+
+    LoadGlobal(usize),
+    StoreGlobal(usize),
+    LoadField(i32),
+    StoreField(i32, bool),
+    NilCheck,
+    BoundsCheck,
+    ArrayLength,
+    LoadArray,
+    StoreArray(bool),
+    Alloc(ClassDefId),
+    Call(usize),
+    Assert,
+    Safepoint,
+
+    Goto,
+    If,
+    Return,
+}
+
+impl Op {
+    pub fn float(value: f64) -> Op {
+        Op::Float(value.to_bits())
+    }
+
+    pub fn is_const(&self) -> bool {
+        match *self {
+            Op::Int(_) | Op::Float(_) | Op::Nil | Op::Addr(_) => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_terminator(&self) -> bool {
+        match *self {
+            Op::Goto | Op::If | Op::Return => true,
+            _ => false,
+        }
+    }
+
+    // instructions that call into the runtime or other functions,
+    // all registers are clobbered
+    pub fn is_call(&self) -> bool {
+        match *self {
+            Op::Call(_) | Op::Alloc(_) => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_safepoint(&self) -> bool {
+        self.is_call() || *self == Op::Safepoint
+    }
+
+    // instructions that can neither be removed nor reordered
+    pub fn has_side_effect(&self) -> bool {
+        match *self {
+            Op::Div
+            | Op::Mod
+            | Op::StoreGlobal(_)
+            | Op::LoadField(_)
+            | Op::StoreField(_, _)
+            | Op::NilCheck
+            | Op::BoundsCheck
+            | Op::StoreArray(_)
+            | Op::Alloc(_)
+            | Op::Call(_)
+            | Op::Assert
+            | Op::Safepoint => true,
+            _ => self.is_terminator(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Inst {
+    pub op: Op,
+    pub mode: Option<MachineMode>,
+    pub args: Vec<InstId>,
+    pub block: BlockId,
+    pub pos: Position,
+    // index of the inlined function this instruction belongs to
+    pub inlined: Option<usize>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Block {
+    pub phis: Vec<InstId>,
+    // last instruction is always the terminator
+    pub insts: Vec<InstId>,
+    pub preds: Vec<BlockId>,
+    // `If` jumps to the first successor when condition is true
+    pub succs: Vec<BlockId>,
+}
+
+#[derive(Clone, Debug)]
+pub struct CallInfo {
+    pub fct_id: FctId,
+    pub cls_type_params: TypeParams,
+    pub fct_type_params: TypeParams,
+    pub vtable_index: Option<u32>,
+    pub super_call: bool,
+}
+
+// call site of an inlined function
+#[derive(Clone, Debug)]
+pub struct InlinedFct {
+    pub fct_id: FctId,
+    pub pos: Position,
+    // inlined function containing the call site, None for the compiled function
+    pub caller: Option<usize>,
+}
+
+pub struct Graph {
+    pub blocks: Vec<Block>,
+    pub insts: Vec<Inst>,
+    pub calls: Vec<CallInfo>,
+    pub inlined: Vec<InlinedFct>,
+}
+
+impl Graph {
+    pub fn new() -> Graph {
+        let mut graph = Graph {
+            blocks: Vec::new(),
+            insts: Vec::new(),
+            calls: Vec::new(),
+            inlined: Vec::new(),
+        };
+
+        graph.new_block();
+        graph
+    }
+
+    pub fn entry(&self) -> BlockId {
+        BlockId(0)
+    }
+
+    pub fn new_block(&mut self) -> BlockId {
+        let id = BlockId(self.blocks.len());
+
+        self.blocks.push(Block {
+            phis: Vec::new(),
+            insts: Vec::new(),
+            preds: Vec::new(),
+            succs: Vec::new(),
+        });
+
+        id
+    }
+
+    pub fn block(&self, id: BlockId) -> &Block {
+        &self.blocks[id.idx()]
+    }
+
+    pub fn inst(&self, id: InstId) -> &Inst {
+        &self.insts[id.idx()]
+    }
+
+    pub fn inst_mut(&mut self, id: InstId) -> &mut Inst {
+        &mut self.insts[id.idx()]
+    }
+
+    fn create_inst(
+        &mut self,
+        block: BlockId,
+        op: Op,
+        mode: Option<MachineMode>,
+        args: Vec<InstId>,
+        pos: Position,
+    ) -> InstId {
+        let id = InstId(self.insts.len());
+
+        self.insts.push(Inst {
+            op: op,
+            mode: mode,
+            args: args,
+            block: block,
+            pos: pos,
+            inlined: None,
+        });
+
+        id
+    }
+
+    pub fn add_inst(
+        &mut self,
+        block: BlockId,
+        op: Op,
+        mode: Option<MachineMode>,
+        args: Vec<InstId>,
+        pos: Position,
+    ) -> InstId {
+        debug_assert!(!self.is_terminated(block));
+        let id = self.create_inst(block, op, mode, args, pos);
+        self.blocks[block.idx()].insts.push(id);
+
+        id
+    }
+
+    pub fn add_phi(&mut self, block: BlockId, mode: MachineMode, pos: Position) -> InstId {
+        let id = self.create_inst(block, Op::Phi, Some(mode), Vec::new(), pos);
+        self.blocks[block.idx()].phis.push(id);
+
+        id
+    }
+
+    // constants are placed at the beginning of the entry block, they
+    // dominate all other instructions
+    pub fn add_const(&mut self, op: Op, mode: MachineMode, pos: Position) -> InstId {
+        debug_assert!(op.is_const());
+        let entry = self.entry();
+        let id = self.create_inst(entry, op, Some(mode), Vec::new(), pos);
+        self.blocks[entry.idx()].insts.insert(0, id);
+
+        id
+    }
+
+    pub fn add_edge(&mut self, from: BlockId, to: BlockId) {
+        self.blocks[from.idx()].succs.push(to);
+        self.blocks[to.idx()].preds.push(from);
+    }
+
+    pub fn is_terminated(&self, block: BlockId) -> bool {
+        match self.block(block).insts.last() {
+            Some(&inst) => self.inst(inst).op.is_terminator(),
+            None => false,
+        }
+    }
+
+    pub fn terminator(&self, block: BlockId) -> InstId {
+        let inst = *self.block(block).insts.last().expect("empty block");
+        debug_assert!(self.inst(inst).op.is_terminator());
+
+        inst
+    }
+
+    pub fn int_value(&self, id: InstId) -> Option<i64> {
+        match self.inst(id).op {
+            Op::Int(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn float_value(&self, id: InstId) -> Option<f64> {
+        match self.inst(id).op {
+            Op::Float(bits) => Some(f64::from_bits(bits)),
+            _ => None,
+        }
+    }
+
+    // reverse postorder, the first successor is visited first
+    pub fn rpo(&self) -> Vec<BlockId> {
+        let mut visited = vec![false; self.blocks.len()];
+        let mut postorder = Vec::with_capacity(self.blocks.len());
+        let mut stack = vec![(self.entry(), 0)];
+        visited[0] = true;
+
+        while let Some(&(block, next)) = stack.last() {
+            let succs = &self.block(block).succs;
+
+            // visit successors in reverse order, so that the first successor
+            // ends up directly after its predecessor
+            if next < succs.len() {
+                let succ = succs[succs.len() - 1 - next];
+                stack.last_mut().unwrap().1 += 1;
+
+                if !visited[succ.idx()] {
+                    visited[succ.idx()] = true;
+                    stack.push((succ, 0));
+                }
+            } else {
+                postorder.push(block);
+                stack.pop();
+            }
+        }
+
+        postorder.reverse();
+        postorder
+    }
+
+    // immediate dominator of each reachable block (the entry block is its
+    // own dominator), see Cooper, Harvey & Kennedy: "A Simple, Fast
+    // Dominance Algorithm"
+    pub fn dominators(&self, rpo: &[BlockId]) -> Vec<Option<BlockId>> {
+        let mut order = vec![usize::max_value(); self.blocks.len()];
+
+        for (idx, &block) in rpo.iter().enumerate() {
+            order[block.idx()] = idx;
+        }
+
+        let mut idom = vec![None; self.blocks.len()];
+        idom[self.entry().idx()] = Some(self.entry());
+        let mut changed = true;
+
+        while changed {
+            changed = false;
+
+            for &block in &rpo[1..] {
+                let mut new_idom = None;
+
+                for &pred in &self.block(block).preds {
+                    if idom[pred.idx()].is_none() {
+                        continue;
+                    }
+
+                    new_idom = Some(match new_idom {
+                        Some(other) => intersect(&idom, &order, pred, other),
+                        None => pred,
+                    });
+                }
+
+                if new_idom != idom[block.idx()] {
+                    idom[block.idx()] = new_idom;
+                    changed = true;
+                }
+            }
+        }
+
+        idom
+    }
+
+    pub fn remove_edge(&mut self, from: BlockId, to: BlockId) {
+        let succ_idx = self
+            .block(from)
+            .succs
+            .iter()
+            .position(|&b| b == to)
+            .expect("edge not found");
+        self.blocks[from.idx()].succs.remove(succ_idx);
+
+        let pred_idx = self
+            .block(to)
+            .preds
+            .iter()
+            .position(|&b| b == from)
+            .expect("edge not found");
+        self.blocks[to.idx()].preds.remove(pred_idx);
+
+        for phi in self.block(to).phis.clone() {
+            self.insts[phi.idx()].args.remove(pred_idx);
+        }
+    }
+
+    // detaches blocks that are not reachable from the entry block
+    pub fn remove_unreachable(&mut self) -> bool {
+        let mut reachable = vec![false; self.blocks.len()];
+
+        for block in self.rpo() {
+            reachable[block.idx()] = true;
+        }
+
+        let mut changed = false;
+
+        for idx in 0..self.blocks.len() {
+            if reachable[idx] {
+                continue;
+            }
+
+            let block = BlockId(idx);
+
+            for succ in self.block(block).succs.clone() {
+                if reachable[succ.idx()] {
+                    self.remove_edge(block, succ);
+                }
+            }
+
+            let block = &mut self.blocks[idx];
+
+            if !block.insts.is_empty() || !block.phis.is_empty() || !block.preds.is_empty() {
+                changed = true;
+            }
+
+            block.insts.clear();
+            block.phis.clear();
+            block.preds.clear();
+            block.succs.clear();
+        }
+
+        changed
+    }
+
+    // inserts empty blocks on edges from blocks with multiple successors to
+    // blocks with multiple predecessors, gives phi moves a place to live
+    pub fn split_critical_edges(&mut self) {
+        for block in self.rpo() {
+            if self.block(block).succs.len() < 2 {
+                continue;
+            }
+
+            for succ_idx in 0..self.block(block).succs.len() {
+                let succ = self.block(block).succs[succ_idx];
+
+                if self.block(succ).preds.len() < 2 {
+                    continue;
+                }
+
+                let pos = self.inst(self.terminator(block)).pos;
+                let split = self.new_block();
+                self.add_inst(split, Op::Goto, None, Vec::new(), pos);
+
+                let pred_idx = self
+                    .block(succ)
+                    .preds
+                    .iter()
+                    .position(|&b| b == block)
+                    .unwrap();
+
+                self.blocks[block.idx()].succs[succ_idx] = split;
+                self.blocks[succ.idx()].preds[pred_idx] = split;
+                self.blocks[split.idx()].preds.push(block);
+                self.blocks[split.idx()].succs.push(succ);
+            }
+        }
+    }
+
+    // replaces all uses of instructions with their aliases
+    pub fn replace_uses(&mut self, aliases: &HashMap<InstId, InstId>) {
+        if aliases.is_empty() {
+            return;
+        }
+
+        for inst in &mut self.insts {
+            for arg in &mut inst.args {
+                *arg = resolve(aliases, *arg);
+            }
+        }
+    }
+
+    // removes phis that merge only a single value (besides themselves)
+    pub fn remove_trivial_phis(&mut self) -> bool {
+        let mut aliases = HashMap::new();
+
+        for block in self.rpo() {
+            let phis = self.block(block).phis.clone();
+
+            for phi in phis {
+                let mut same = None;
+                let mut trivial = true;
+
+                for &arg in &self.inst(phi).args {
+                    let arg = resolve(&aliases, arg);
+
+                    if arg == phi || Some(arg) == same {
+                        continue;
+                    }
+
+                    if same.is_some() {
+                        trivial = false;
+                        break;
+                    }
+
+                    same = Some(arg);
+                }
+
+                if !trivial {
+                    continue;
+                }
+
+                if let Some(same) = same {
+                    aliases.insert(phi, same);
+                    self.blocks[block.idx()].phis.retain(|&p| p != phi);
+                }
+            }
+        }
+
+        self.replace_uses(&aliases);
+
+        !aliases.is_empty()
+    }
+
+    pub fn uses(&self) -> Vec<usize> {
+        let mut uses = vec![0; self.insts.len()];
+
+        for block in self.rpo() {
+            let block = self.block(block);
+
+            for &inst in block.phis.iter().chain(block.insts.iter()) {
+                for &arg in &self.inst(inst).args {
+                    uses[arg.idx()] += 1;
+                }
+            }
+        }
+
+        uses
+    }
+}
+
+fn intersect(
+    idom: &[Option<BlockId>],
+    order: &[usize],
+    mut lhs: BlockId,
+    mut rhs: BlockId,
+) -> BlockId {
+    while lhs != rhs {
+        while order[lhs.idx()] > order[rhs.idx()] {
+            lhs = idom[lhs.idx()].unwrap();
+        }
+
+        while order[rhs.idx()] > order[lhs.idx()] {
+            rhs = idom[rhs.idx()].unwrap();
+        }
+    }
+
+    lhs
+}
+
+pub fn resolve(aliases: &HashMap<InstId, InstId>, mut id: InstId) -> InstId {
+    while let Some(&alias) = aliases.get(&id) {
+        id = alias;
+    }
+
+    id
+}
+
+impl fmt::Display for Graph {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for block_id in self.rpo() {
+            let block = self.block(block_id);
+            write!(f, "b{}:", block_id.idx())?;
+
+            if !block.preds.is_empty() {
+                write!(f, " preds")?;
+
+                for pred in &block.preds {
+                    write!(f, " b{}", pred.idx())?;
+                }
+            }
+
+            writeln!(f)?;
+
+            for &id in block.phis.iter().chain(block.insts.iter()) {
+                let inst = self.inst(id);
+                write!(f, "  ")?;
+
+                if let Some(mode) = inst.mode {
+                    write!(f, "v{}: {:?} = ", id.idx(), mode)?;
+                }
+
+                match inst.op {
+                    Op::Float(bits) => write!(f, "Float({})", f64::from_bits(bits))?,
+                    ref op => write!(f, "{:?}", op)?,
+                }
+
+                for (ind, arg) in inst.args.iter().enumerate() {
+                    let sep = if ind == 0 { " " } else { ", " };
+                    write!(f, "{}v{}", sep, arg.idx())?;
+                }
+
+                if inst.op.is_terminator() {
+                    for succ in &block.succs {
+                        write!(f, " b{}", succ.idx())?;
+                    }
+                }
+
+                writeln!(f)?;
+            }
+        }
+
+        Ok(())
+    }
+}
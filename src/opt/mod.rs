@@ -1 +1,222 @@
+use baseline::codegen::fct_pattern_match;
+use baseline::info::JitInfo;
+use class::TypeParams;
+use ctxt::{Fct, FctSrc, VM};
+use dora_parser::ast::{CtorType, NodeId};
+use opt::fct::JitOptFct;
+use opt::ir::Graph;
+
+pub mod builder;
+pub mod codegen;
+pub mod dce;
 pub mod fct;
+pub mod fold;
+pub mod gvn;
+pub mod ir;
+pub mod regalloc;
+
+// compiles functions marked with `optimize` and hot functions, returns None
+// for functions that use features not supported by the optimizing compiler
+// and when the code space is exhausted, these are compiled by the baseline
+// compiler instead. With `osr` the code continues the given loop of running
+// baseline code.
+pub fn compile<'ast>(
+    vm: &VM<'ast>,
+    fct: &Fct<'ast>,
+    src: &mut FctSrc,
+    jit_info: &JitInfo<'ast>,
+    cls_type_params: &TypeParams,
+    fct_type_params: &TypeParams,
+    osr: Option<NodeId>,
+) -> Option<JitOptFct> {
+    if !is_supported(fct, cls_type_params, fct_type_params) {
+        return None;
+    }
+
+    let emit_ir = should_emit_ir(vm, fct);
+    let name = vm.interner.str(fct.name);
+    let name = if osr.is_some() {
+        format!("{} (osr)", name)
+    } else {
+        name.to_string()
+    };
+
+    let mut graph = match builder::build(vm, fct, src, jit_info, osr) {
+        Ok(graph) => graph,
+
+        Err(msg) => {
+            if emit_ir {
+                println!("fun {}: not optimized ({})", name, msg);
+            }
+
+            return None;
+        }
+    };
+
+    optimize(&mut graph);
+    graph.split_critical_edges();
+
+    if emit_ir {
+        println!("fun {}:\n{}", name, graph);
+    }
+
+    let allocation = regalloc::allocate(&graph);
+
+    codegen::generate(vm, fct, src, &graph, &allocation)
+}
+
+// the optimizing compiler only handles non-generic functions and methods
+// on x64, constructors and lambda bodies are always compiled by the baseline
+// compiler. Functions that pass this check can still be rejected while
+// building the graph (see the errors returned by `builder::build`) when they
+// contain:
+//
+// * `for`, `do`, `defer`, `throw` and `spawn` statements, `try!` and `try?`
+// * `match` expressions and enum values
+// * lambda expressions and calls of lambdas
+// * tuples and structs (literals, fields and struct return values)
+// * conversions (`as`/`is`) and delegation to super constructors
+// * calls with stack arguments and calls of definitions without a body
+//
+// --emit-opt-ir prints the reason for functions that are not optimized.
+pub fn is_supported(fct: &Fct, cls_type_params: &TypeParams, fct_type_params: &TypeParams) -> bool {
+    cfg!(target_arch = "x86_64")
+        && cls_type_params.len() == 0
+        && fct_type_params.len() == 0
+        && fct.ctor == CtorType::None
+        && !fct.is_lambda
+}
+
+pub fn optimize(graph: &mut Graph) {
+    fold::fold(graph);
+    gvn::gvn(graph);
+    dce::dce(graph);
+}
+
+fn should_emit_ir(vm: &VM, fct: &Fct) -> bool {
+    if let Some(ref pattern) = vm.args.flag_emit_opt_ir {
+        fct_pattern_match(vm, fct, pattern)
+    } else {
+        false
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use baseline::info::{self, JitInfo};
+    use class::TypeParams;
+    use opt::builder;
+    use opt::ir::{Graph, InstId, Op};
+    use opt::optimize;
+    use test;
+
+    pub fn graph_for(code: &'static str, name: &str) -> Graph {
+        test::parse(code, |vm| {
+            let fid = vm.fct_by_name(name).unwrap();
+            let fct = vm.fcts.idx(fid);
+            let fct = fct.read();
+            let src = fct.src();
+            let mut src = src.write();
+            let mut jit_info = JitInfo::new();
+            let empty = TypeParams::empty();
+
+            info::generate(vm, &fct, &mut src, &mut jit_info, &empty, &empty);
+
+            builder::build(vm, &fct, &src, &jit_info, None).expect("not supported")
+        })
+    }
+
+    // value returned by the single return instruction
+    pub fn returned_value(graph: &Graph) -> InstId {
+        let returns = graph
+            .rpo()
+            .into_iter()
+            .map(|block| graph.terminator(block))
+            .filter(|&inst| graph.inst(inst).op == Op::Return)
+            .collect::<Vec<_>>();
+
+        assert_eq!(1, returns.len());
+        graph.inst(returns[0]).args[0]
+    }
+
+    pub fn optimized_graph_for(code: &'static str, name: &str) -> Graph {
+        let mut graph = graph_for(code, name);
+        optimize(&mut graph);
+
+        graph
+    }
+
+    #[test]
+    fn build_loop() {
+        let graph = graph_for(
+            "optimize fun f(n: int) -> int {
+                var i = 0;
+                var sum = 0;
+                while i < n { sum = sum + i; i = i + 1; }
+                return sum;
+            }",
+            "f",
+        );
+
+        let phis = graph
+            .rpo()
+            .iter()
+            .map(|&block| graph.block(block).phis.len())
+            .sum::<usize>();
+        assert_eq!(2, phis);
+    }
+
+    #[test]
+    fn build_unsupported() {
+        test::parse("optimize fun f() throws { throw \"abc\"; }", |vm| {
+            let fid = vm.fct_by_name("f").unwrap();
+            let fct = vm.fcts.idx(fid);
+            let fct = fct.read();
+            let src = fct.src();
+            let mut src = src.write();
+            let mut jit_info = JitInfo::new();
+            let empty = TypeParams::empty();
+
+            info::generate(vm, &fct, &mut src, &mut jit_info, &empty, &empty);
+
+            assert!(builder::build(vm, &fct, &src, &jit_info, None).is_err());
+        });
+    }
+
+    fn calls(graph: &Graph) -> usize {
+        graph
+            .rpo()
+            .into_iter()
+            .flat_map(|block| graph.block(block).insts.iter())
+            .filter(|&&inst| graph.inst(inst).op.is_call())
+            .count()
+    }
+
+    #[test]
+    fn build_inlined() {
+        let graph = graph_for(
+            "optimize fun f(a: int, b: int) -> int {
+                return int::min(a, b).abs() + twice(a);
+            }
+            fun twice(x: int) -> int { return x + x; }",
+            "f",
+        );
+
+        assert_eq!(0, calls(&graph));
+        assert_eq!(3, graph.inlined.len());
+    }
+
+    #[test]
+    fn build_inlined_recursion() {
+        let graph = graph_for(
+            "optimize fun f(n: int) -> int {
+                if n <= 1 { return n; }
+                return f(n - 1) + f(n - 2);
+            }",
+            "f",
+        );
+
+        assert_eq!(2, calls(&graph));
+        assert!(graph.inlined.is_empty());
+    }
+}
@@ -0,0 +1,425 @@
+use std::collections::{HashMap, HashSet};
+
+use cpu::{FReg, Reg, CALLEE_SAVED, FREG_OPT};
+use mem;
+use opt::ir::{BlockId, Graph, InstId};
+use ty::MachineMode;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Location {
+    Reg(Reg),
+    FReg(FReg),
+    // offset relative to frame pointer
+    Stack(i32),
+}
+
+pub struct Allocation {
+    // blocks in the order they are emitted
+    pub order: Vec<BlockId>,
+    // constants have no location, they are materialized on every use
+    pub locations: Vec<Option<Location>>,
+    pub framesize: i32,
+    // slots of spilled pointers, cleared in the prolog so that the GC never
+    // sees uninitialized slots
+    pub ptr_slots: Vec<i32>,
+    // slots with pointers that are live across a call or safepoint
+    pub gcpoints: HashMap<InstId, Vec<i32>>,
+}
+
+impl Allocation {
+    pub fn location(&self, id: InstId) -> Option<Location> {
+        self.locations[id.idx()]
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Interval {
+    inst: InstId,
+    start: usize,
+    end: usize,
+}
+
+impl Interval {
+    fn covers(&self, pos: usize) -> bool {
+        self.start < pos && pos < self.end
+    }
+}
+
+// linear scan register allocation on the live ranges of values, ranges are
+// approximated by a single interval from the first definition to the last
+// use in the linear block order. Registers are not preserved across calls,
+// values live across calls are spilled into their own stack slot for the
+// whole range. See Poletto & Sarkar: "Linear Scan Register Allocation"
+pub fn allocate(graph: &Graph) -> Allocation {
+    let order = graph.rpo();
+    let positions = number(graph, &order);
+    let (live_in, live_out) = liveness(graph, &order);
+    let intervals = intervals(graph, &order, &positions, &live_in, &live_out);
+
+    let mut calls = Vec::new();
+    let mut safepoints = Vec::new();
+
+    for &block in &order {
+        for &inst in &graph.block(block).insts {
+            let op = graph.inst(inst).op;
+
+            if op.is_call() {
+                calls.push(positions.inst[inst.idx()]);
+            }
+
+            if op.is_safepoint() {
+                safepoints.push((positions.inst[inst.idx()], inst));
+            }
+        }
+    }
+
+    let mut scan = Scan {
+        graph: graph,
+        locations: vec![None; graph.insts.len()],
+        slots: 0,
+        ptr_slots: Vec::new(),
+    };
+
+    let (floats, ints): (Vec<Interval>, Vec<Interval>) = intervals
+        .into_iter()
+        .partition(|interval| graph.inst(interval.inst).mode.unwrap().is_float());
+
+    let must_spill = |interval: &Interval| {
+        let is_ptr = graph.inst(interval.inst).mode == Some(MachineMode::Ptr);
+
+        calls.iter().any(|&pos| interval.covers(pos))
+            || (is_ptr && safepoints.iter().any(|&(pos, _)| interval.covers(pos)))
+    };
+
+    let int_regs = CALLEE_SAVED
+        .iter()
+        .map(|&reg| Location::Reg(reg))
+        .collect::<Vec<_>>();
+    let float_regs = FREG_OPT
+        .iter()
+        .map(|&reg| Location::FReg(reg))
+        .collect::<Vec<_>>();
+
+    let spilled_ints = scan.run(ints, &int_regs, &must_spill);
+    scan.run(floats, &float_regs, &must_spill);
+
+    let mut gcpoints = HashMap::new();
+
+    for &(pos, inst) in &safepoints {
+        let slots = spilled_ints
+            .iter()
+            .filter(|interval| interval.covers(pos))
+            .filter(|interval| graph.inst(interval.inst).mode == Some(MachineMode::Ptr))
+            .map(|interval| match scan.locations[interval.inst.idx()] {
+                Some(Location::Stack(offset)) => offset,
+                _ => unreachable!(),
+            })
+            .collect();
+
+        gcpoints.insert(inst, slots);
+    }
+
+    let framesize = mem::align_i32(scan.slots * mem::ptr_width(), 16);
+
+    Allocation {
+        order: order,
+        locations: scan.locations,
+        framesize: framesize,
+        ptr_slots: scan.ptr_slots,
+        gcpoints: gcpoints,
+    }
+}
+
+struct Scan<'a> {
+    graph: &'a Graph,
+    locations: Vec<Option<Location>>,
+    slots: i32,
+    ptr_slots: Vec<i32>,
+}
+
+impl<'a> Scan<'a> {
+    // returns all spilled intervals
+    fn run<F>(
+        &mut self,
+        mut intervals: Vec<Interval>,
+        regs: &[Location],
+        must_spill: &F,
+    ) -> Vec<Interval>
+    where
+        F: Fn(&Interval) -> bool,
+    {
+        intervals.sort_by_key(|interval| (interval.start, interval.inst));
+
+        let mut free = regs.iter().rev().cloned().collect::<Vec<_>>();
+        let mut active: Vec<Interval> = Vec::new();
+        let mut spilled = Vec::new();
+
+        for interval in intervals {
+            // operands are read before the result is written, a register
+            // can be reused by a value defined at the last use
+            let locations = &self.locations;
+            active.retain(|active| {
+                if active.end <= interval.start {
+                    free.push(locations[active.inst.idx()].unwrap());
+                    false
+                } else {
+                    true
+                }
+            });
+
+            if must_spill(&interval) {
+                self.spill(interval);
+                spilled.push(interval);
+                continue;
+            }
+
+            if let Some(reg) = free.pop() {
+                self.locations[interval.inst.idx()] = Some(reg);
+                active.push(interval);
+                continue;
+            }
+
+            let (idx, furthest) = active
+                .iter()
+                .cloned()
+                .enumerate()
+                .max_by_key(|&(_, active)| active.end)
+                .expect("no registers");
+
+            if furthest.end > interval.end {
+                let reg = self.locations[furthest.inst.idx()].unwrap();
+                self.spill(furthest);
+                spilled.push(furthest);
+
+                self.locations[interval.inst.idx()] = Some(reg);
+                active[idx] = interval;
+            } else {
+                self.spill(interval);
+                spilled.push(interval);
+            }
+        }
+
+        spilled
+    }
+
+    fn spill(&mut self, interval: Interval) {
+        self.slots += 1;
+        let offset = -self.slots * mem::ptr_width();
+
+        if self.graph.inst(interval.inst).mode == Some(MachineMode::Ptr) {
+            self.ptr_slots.push(offset);
+        }
+
+        self.locations[interval.inst.idx()] = Some(Location::Stack(offset));
+    }
+}
+
+struct Positions {
+    // phis are defined at the start of the block
+    block_start: Vec<usize>,
+    block_end: Vec<usize>,
+    inst: Vec<usize>,
+}
+
+fn number(graph: &Graph, order: &[BlockId]) -> Positions {
+    let mut positions = Positions {
+        block_start: vec![0; graph.blocks.len()],
+        block_end: vec![0; graph.blocks.len()],
+        inst: vec![0; graph.insts.len()],
+    };
+
+    let mut pos = 0;
+
+    for &block in order {
+        positions.block_start[block.idx()] = pos;
+
+        for &inst in &graph.block(block).insts {
+            pos += 2;
+            positions.inst[inst.idx()] = pos;
+        }
+
+        // phi moves happen at the end of the predecessor, the gap separates
+        // them from the phis in the successor
+        pos += 2;
+        positions.block_end[block.idx()] = pos;
+        pos += 2;
+    }
+
+    positions
+}
+
+fn has_location(graph: &Graph, id: InstId) -> bool {
+    let inst = graph.inst(id);
+    inst.mode.is_some() && !inst.op.is_const()
+}
+
+// values live at the start and end of each block, arguments of phis are
+// live at the end of the corresponding predecessor
+fn liveness(graph: &Graph, order: &[BlockId]) -> (Vec<HashSet<InstId>>, Vec<HashSet<InstId>>) {
+    let mut live_in = vec![HashSet::new(); graph.blocks.len()];
+    let mut live_out = vec![HashSet::new(); graph.blocks.len()];
+    let mut changed = true;
+
+    while changed {
+        changed = false;
+
+        for &block in order.iter().rev() {
+            let mut live = HashSet::new();
+
+            for &succ in &graph.block(block).succs {
+                live.extend(live_in[succ.idx()].iter().cloned());
+
+                let pred_idx = graph
+                    .block(succ)
+                    .preds
+                    .iter()
+                    .position(|&pred| pred == block)
+                    .unwrap();
+
+                for &phi in &graph.block(succ).phis {
+                    let arg = graph.inst(phi).args[pred_idx];
+
+                    if has_location(graph, arg) {
+                        live.insert(arg);
+                    }
+                }
+            }
+
+            live_out[block.idx()] = live.clone();
+
+            for &inst in graph.block(block).insts.iter().rev() {
+                live.remove(&inst);
+
+                for &arg in &graph.inst(inst).args {
+                    if has_location(graph, arg) {
+                        live.insert(arg);
+                    }
+                }
+            }
+
+            for phi in &graph.block(block).phis {
+                live.remove(phi);
+            }
+
+            if live != live_in[block.idx()] {
+                live_in[block.idx()] = live;
+                changed = true;
+            }
+        }
+    }
+
+    (live_in, live_out)
+}
+
+fn intervals(
+    graph: &Graph,
+    order: &[BlockId],
+    positions: &Positions,
+    live_in: &[HashSet<InstId>],
+    live_out: &[HashSet<InstId>],
+) -> Vec<Interval> {
+    let mut ranges: Vec<Option<(usize, usize)>> = vec![None; graph.insts.len()];
+
+    {
+        let mut extend = |id: InstId, pos: usize| {
+            let range = ranges[id.idx()].get_or_insert((pos, pos));
+            range.0 = range.0.min(pos);
+            range.1 = range.1.max(pos);
+        };
+
+        for &block in order {
+            let start = positions.block_start[block.idx()];
+            let end = positions.block_end[block.idx()];
+
+            for &phi in &graph.block(block).phis {
+                extend(phi, start);
+            }
+
+            for &inst in &graph.block(block).insts {
+                let pos = positions.inst[inst.idx()];
+
+                if has_location(graph, inst) {
+                    extend(inst, pos);
+                }
+
+                for &arg in &graph.inst(inst).args {
+                    if has_location(graph, arg) {
+                        extend(arg, pos);
+                    }
+                }
+            }
+
+            for &value in &live_in[block.idx()] {
+                extend(value, start);
+            }
+
+            for &value in &live_out[block.idx()] {
+                extend(value, end);
+            }
+        }
+    }
+
+    ranges
+        .into_iter()
+        .enumerate()
+        .filter_map(|(idx, range)| {
+            range.map(|(start, end)| Interval {
+                inst: InstId(idx),
+                start: start,
+                end: end,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use opt::ir::Op;
+    use opt::regalloc::{allocate, Location};
+    use opt::tests::optimized_graph_for;
+
+    #[test]
+    fn regalloc_spills_across_calls() {
+        let mut graph = optimized_graph_for(
+            "optimize fun f(a: int) -> int {
+                println(\"g\");
+                return a;
+            }",
+            "f",
+        );
+        graph.split_critical_edges();
+        let allocation = allocate(&graph);
+
+        let param = graph
+            .block(graph.entry())
+            .insts
+            .iter()
+            .cloned()
+            .find(|&inst| graph.inst(inst).op == Op::Param(0))
+            .unwrap();
+
+        assert_eq!(Some(Location::Stack(-8)), allocation.location(param));
+        assert_eq!(16, allocation.framesize);
+    }
+
+    #[test]
+    fn regalloc_uses_registers() {
+        let mut graph = optimized_graph_for(
+            "optimize fun f(a: int, b: int) -> int { return a * b + a; }",
+            "f",
+        );
+        graph.split_critical_edges();
+        let allocation = allocate(&graph);
+
+        assert_eq!(0, allocation.framesize);
+
+        for &block in &allocation.order {
+            for &inst in &graph.block(block).insts {
+                match allocation.location(inst) {
+                    Some(Location::Stack(_)) => panic!("spilled value"),
+                    _ => {}
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,627 @@
+use byteorder::{LittleEndian, WriteBytesExt};
+use std::ptr;
+
+use baseline::fct::JitFct;
+use ctxt::{Fct, VM};
+use dora_parser::ast::Elem::*;
+use dora_parser::ast::{self, Elem};
+
+// Implementation of the GDB JIT interface: every compiled function is
+// described by an in-memory ELF object that contains the function symbol,
+// DWARF line information and call frame information. GDB sets a breakpoint
+// in `__jit_debug_register_code` and reads the objects from the list in
+// `__jit_debug_descriptor`.
+
+const JIT_NOACTION: u32 = 0;
+const JIT_REGISTER_FN: u32 = 1;
+const JIT_UNREGISTER_FN: u32 = 2;
+
+#[repr(C)]
+pub struct JitCodeEntry {
+    next_entry: *mut JitCodeEntry,
+    prev_entry: *mut JitCodeEntry,
+    symfile_addr: *const u8,
+    symfile_size: u64,
+
+    // not read by GDB
+    code_start: usize,
+    symfile: Vec<u8>,
+}
+
+#[repr(C)]
+pub struct JitDescriptor {
+    version: u32,
+    action_flag: u32,
+    relevant_entry: *mut JitCodeEntry,
+    first_entry: *mut JitCodeEntry,
+}
+
+#[no_mangle]
+pub static mut __jit_debug_descriptor: JitDescriptor = JitDescriptor {
+    version: 1,
+    action_flag: JIT_NOACTION,
+    relevant_entry: 0 as *mut JitCodeEntry,
+    first_entry: 0 as *mut JitCodeEntry,
+};
+
+// GDB places a breakpoint in this function, it must not be inlined or
+// removed by the compiler.
+#[no_mangle]
+#[inline(never)]
+pub extern "C" fn __jit_debug_register_code() {
+    let action = 0;
+    unsafe {
+        ptr::read_volatile(&action);
+    }
+}
+
+pub fn register_with_gdb(jit_fct: &JitFct, vm: &VM, fct: &Fct) {
+    let name = fct.full_name(vm);
+    let file = file_for(vm, fct);
+
+    let mut lines: Vec<(u64, u64)> = jit_fct
+        .linenos()
+        .iter()
+        .filter(|&(_, &line)| line > 0)
+        .map(|(&offset, &line)| (offset as u64, line as u64))
+        .collect();
+    lines.sort();
+
+    let object = build_object(
+        &name,
+        &file,
+        jit_fct.fct_ptr().to_usize() as u64,
+        jit_fct.fct_len() as u64,
+        &lines,
+    );
+
+    let _lock = vm.gdb_lock.lock();
+
+    let entry = Box::into_raw(Box::new(JitCodeEntry {
+        next_entry: ptr::null_mut(),
+        prev_entry: ptr::null_mut(),
+        symfile_addr: object.as_ptr(),
+        symfile_size: object.len() as u64,
+        code_start: jit_fct.fct_ptr().to_usize(),
+        symfile: object,
+    }));
+
+    unsafe {
+        let first = __jit_debug_descriptor.first_entry;
+        (*entry).next_entry = first;
+
+        if !first.is_null() {
+            (*first).prev_entry = entry;
+        }
+
+        __jit_debug_descriptor.first_entry = entry;
+        __jit_debug_descriptor.relevant_entry = entry;
+        __jit_debug_descriptor.action_flag = JIT_REGISTER_FN;
+        __jit_debug_register_code();
+        __jit_debug_descriptor.action_flag = JIT_NOACTION;
+    }
+}
+
+pub fn unregister_with_gdb(jit_fct: &JitFct, vm: &VM) {
+    let code_start = jit_fct.fct_ptr().to_usize();
+    let _lock = vm.gdb_lock.lock();
+
+    unsafe {
+        let mut entry = __jit_debug_descriptor.first_entry;
+
+        while !entry.is_null() && (*entry).code_start != code_start {
+            entry = (*entry).next_entry;
+        }
+
+        if entry.is_null() {
+            return;
+        }
+
+        let prev = (*entry).prev_entry;
+        let next = (*entry).next_entry;
+
+        if prev.is_null() {
+            __jit_debug_descriptor.first_entry = next;
+        } else {
+            (*prev).next_entry = next;
+        }
+
+        if !next.is_null() {
+            (*next).prev_entry = prev;
+        }
+
+        __jit_debug_descriptor.relevant_entry = entry;
+        __jit_debug_descriptor.action_flag = JIT_UNREGISTER_FN;
+        __jit_debug_register_code();
+        __jit_debug_descriptor.action_flag = JIT_NOACTION;
+        __jit_debug_descriptor.relevant_entry = ptr::null_mut();
+
+        drop(Box::from_raw(entry));
+    }
+}
+
+// finds the source file of a function, lambdas are not part of the
+// elements of a file and fall back to the program file
+fn file_for(vm: &VM, fct: &Fct) -> String {
+    for file in &vm.ast.files {
+        for elem in &file.elements {
+            if defines(elem, fct.ast.id) {
+                return file.path.clone();
+            }
+        }
+    }
+
+    vm.args.arg_file.clone()
+}
+
+fn defines(elem: &Elem, id: ast::NodeId) -> bool {
+    match elem {
+        &ElemFunction(ref fct) => fct.id == id,
+        &ElemClass(ref cls) => cls
+            .ctors
+            .iter()
+            .chain(cls.methods.iter())
+            .any(|m| m.id == id),
+        &ElemTrait(ref t) => t.methods.iter().any(|m| m.id == id),
+        &ElemImpl(ref i) => i.methods.iter().any(|m| m.id == id),
+        _ => false,
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod arch {
+    pub const EM_MACHINE: u16 = 62; // EM_X86_64
+    pub const DWARF_FP: u8 = 6; // rbp
+    pub const DWARF_RA: u8 = 16; // return address
+}
+
+#[cfg(target_arch = "aarch64")]
+mod arch {
+    pub const EM_MACHINE: u16 = 183; // EM_AARCH64
+    pub const DWARF_FP: u8 = 29; // x29
+    pub const DWARF_RA: u8 = 30; // x30
+}
+
+const SHT_PROGBITS: u32 = 1;
+const SHT_SYMTAB: u32 = 2;
+const SHT_STRTAB: u32 = 3;
+const SHT_NOBITS: u32 = 8;
+
+const SHF_ALLOC: u64 = 2;
+const SHF_EXECINSTR: u64 = 4;
+
+const TEXT_SECTION: u16 = 1;
+
+struct Section {
+    name: &'static str,
+    kind: u32,
+    flags: u64,
+    addr: u64,
+    size: u64,
+    data: Vec<u8>,
+    link: u32,
+    info: u32,
+    align: u64,
+    entsize: u64,
+}
+
+impl Section {
+    fn new(name: &'static str, kind: u32, data: Vec<u8>) -> Section {
+        Section {
+            name: name,
+            kind: kind,
+            flags: 0,
+            addr: 0,
+            size: data.len() as u64,
+            data: data,
+            link: 0,
+            info: 0,
+            align: 1,
+            entsize: 0,
+        }
+    }
+}
+
+// builds a relocatable ELF object for the machine code at `code_start`,
+// `lines` contains pairs of code offset and line number sorted by offset
+pub fn build_object(
+    name: &str,
+    file: &str,
+    code_start: u64,
+    code_len: u64,
+    lines: &[(u64, u64)],
+) -> Vec<u8> {
+    let mut text = Section::new(".text", SHT_NOBITS, Vec::new());
+    text.flags = SHF_ALLOC | SHF_EXECINSTR;
+    text.addr = code_start;
+    text.size = code_len;
+    text.align = 16;
+
+    let mut strtab = vec![0];
+    let mut symtab = vec![0; 24];
+    symtab
+        .write_u32::<LittleEndian>(strtab.len() as u32)
+        .unwrap();
+    symtab.push(0x12); // STB_GLOBAL, STT_FUNC
+    symtab.push(0);
+    symtab.write_u16::<LittleEndian>(TEXT_SECTION).unwrap();
+    symtab.write_u64::<LittleEndian>(code_start).unwrap();
+    symtab.write_u64::<LittleEndian>(code_len).unwrap();
+    write_str(&mut strtab, name);
+
+    let mut symtab = Section::new(".symtab", SHT_SYMTAB, symtab);
+    symtab.link = 3;
+    symtab.info = 1;
+    symtab.align = 8;
+    symtab.entsize = 24;
+
+    let sections = vec![
+        text,
+        Section::new(".shstrtab", SHT_STRTAB, Vec::new()),
+        Section::new(".strtab", SHT_STRTAB, strtab),
+        symtab,
+        Section::new(".debug_abbrev", SHT_PROGBITS, debug_abbrev()),
+        Section::new(
+            ".debug_info",
+            SHT_PROGBITS,
+            debug_info(name, file, code_start, code_len),
+        ),
+        Section::new(
+            ".debug_line",
+            SHT_PROGBITS,
+            debug_line(file, code_start, code_len, lines),
+        ),
+        Section::new(
+            ".debug_frame",
+            SHT_PROGBITS,
+            debug_frame(code_start, code_len),
+        ),
+    ];
+
+    write_elf(sections)
+}
+
+fn write_elf(mut sections: Vec<Section>) -> Vec<u8> {
+    let mut shstrtab = vec![0];
+    let mut names = Vec::with_capacity(sections.len());
+
+    for section in &sections {
+        names.push(shstrtab.len() as u32);
+        write_str(&mut shstrtab, section.name);
+    }
+
+    sections[1].size = shstrtab.len() as u64;
+    sections[1].data = shstrtab;
+
+    let mut buf = Vec::new();
+    buf.extend_from_slice(&[0x7f, b'E', b'L', b'F']);
+    buf.push(2); // ELFCLASS64
+    buf.push(1); // ELFDATA2LSB
+    buf.push(1); // EV_CURRENT
+    buf.resize(16, 0);
+    buf.write_u16::<LittleEndian>(1).unwrap(); // ET_REL
+    buf.write_u16::<LittleEndian>(arch::EM_MACHINE).unwrap();
+    buf.write_u32::<LittleEndian>(1).unwrap();
+    buf.write_u64::<LittleEndian>(0).unwrap(); // e_entry
+    buf.write_u64::<LittleEndian>(0).unwrap(); // e_phoff
+    let shoff_pos = buf.len();
+    buf.write_u64::<LittleEndian>(0).unwrap(); // e_shoff
+    buf.write_u32::<LittleEndian>(0).unwrap(); // e_flags
+    buf.write_u16::<LittleEndian>(64).unwrap(); // e_ehsize
+    buf.write_u16::<LittleEndian>(0).unwrap(); // e_phentsize
+    buf.write_u16::<LittleEndian>(0).unwrap(); // e_phnum
+    buf.write_u16::<LittleEndian>(64).unwrap(); // e_shentsize
+    buf.write_u16::<LittleEndian>(sections.len() as u16 + 1)
+        .unwrap();
+    buf.write_u16::<LittleEndian>(2).unwrap(); // e_shstrndx
+
+    let mut offsets = Vec::with_capacity(sections.len());
+
+    for section in &sections {
+        align(&mut buf, 8);
+        offsets.push(buf.len() as u64);
+        buf.extend_from_slice(&section.data);
+    }
+
+    align(&mut buf, 8);
+    let shoff = buf.len() as u64;
+    (&mut buf[shoff_pos..shoff_pos + 8])
+        .write_u64::<LittleEndian>(shoff)
+        .unwrap();
+
+    buf.resize(buf.len() + 64, 0);
+
+    for (i, section) in sections.iter().enumerate() {
+        buf.write_u32::<LittleEndian>(names[i]).unwrap();
+        buf.write_u32::<LittleEndian>(section.kind).unwrap();
+        buf.write_u64::<LittleEndian>(section.flags).unwrap();
+        buf.write_u64::<LittleEndian>(section.addr).unwrap();
+        buf.write_u64::<LittleEndian>(offsets[i]).unwrap();
+        buf.write_u64::<LittleEndian>(section.size).unwrap();
+        buf.write_u32::<LittleEndian>(section.link).unwrap();
+        buf.write_u32::<LittleEndian>(section.info).unwrap();
+        buf.write_u64::<LittleEndian>(section.align).unwrap();
+        buf.write_u64::<LittleEndian>(section.entsize).unwrap();
+    }
+
+    buf
+}
+
+const DW_TAG_COMPILE_UNIT: u8 = 0x11;
+const DW_TAG_SUBPROGRAM: u8 = 0x2e;
+
+const DW_AT_NAME: u8 = 0x03;
+const DW_AT_STMT_LIST: u8 = 0x10;
+const DW_AT_LOW_PC: u8 = 0x11;
+const DW_AT_HIGH_PC: u8 = 0x12;
+const DW_AT_EXTERNAL: u8 = 0x3f;
+
+const DW_FORM_ADDR: u8 = 0x01;
+const DW_FORM_DATA4: u8 = 0x06;
+const DW_FORM_STRING: u8 = 0x08;
+const DW_FORM_FLAG: u8 = 0x0c;
+
+fn debug_abbrev() -> Vec<u8> {
+    vec![
+        1,
+        DW_TAG_COMPILE_UNIT,
+        1, // has children
+        DW_AT_NAME,
+        DW_FORM_STRING,
+        DW_AT_LOW_PC,
+        DW_FORM_ADDR,
+        DW_AT_HIGH_PC,
+        DW_FORM_ADDR,
+        DW_AT_STMT_LIST,
+        DW_FORM_DATA4,
+        0,
+        0,
+        2,
+        DW_TAG_SUBPROGRAM,
+        0, // no children
+        DW_AT_NAME,
+        DW_FORM_STRING,
+        DW_AT_EXTERNAL,
+        DW_FORM_FLAG,
+        DW_AT_LOW_PC,
+        DW_FORM_ADDR,
+        DW_AT_HIGH_PC,
+        DW_FORM_ADDR,
+        0,
+        0,
+        0,
+    ]
+}
+
+fn debug_info(name: &str, file: &str, code_start: u64, code_len: u64) -> Vec<u8> {
+    let mut buf = Vec::new();
+    buf.write_u32::<LittleEndian>(0).unwrap(); // unit_length
+    buf.write_u16::<LittleEndian>(2).unwrap(); // version
+    buf.write_u32::<LittleEndian>(0).unwrap(); // debug_abbrev_offset
+    buf.push(8); // address_size
+
+    write_uleb(&mut buf, 1);
+    write_str(&mut buf, file);
+    buf.write_u64::<LittleEndian>(code_start).unwrap();
+    buf.write_u64::<LittleEndian>(code_start + code_len)
+        .unwrap();
+    buf.write_u32::<LittleEndian>(0).unwrap();
+
+    write_uleb(&mut buf, 2);
+    write_str(&mut buf, name);
+    buf.push(1);
+    buf.write_u64::<LittleEndian>(code_start).unwrap();
+    buf.write_u64::<LittleEndian>(code_start + code_len)
+        .unwrap();
+
+    buf.push(0);
+
+    patch_length(&mut buf);
+    buf
+}
+
+const DW_LNS_COPY: u8 = 0x01;
+const DW_LNS_ADVANCE_PC: u8 = 0x02;
+const DW_LNS_ADVANCE_LINE: u8 = 0x03;
+const DW_LNE_END_SEQUENCE: u8 = 0x01;
+const DW_LNE_SET_ADDRESS: u8 = 0x02;
+
+fn debug_line(file: &str, code_start: u64, code_len: u64, lines: &[(u64, u64)]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    buf.write_u32::<LittleEndian>(0).unwrap(); // unit_length
+    buf.write_u16::<LittleEndian>(2).unwrap(); // version
+    buf.write_u32::<LittleEndian>(0).unwrap(); // header_length
+    let header_start = buf.len();
+
+    buf.push(1); // minimum_instruction_length
+    buf.push(1); // default_is_stmt
+    buf.push(-5i8 as u8); // line_base
+    buf.push(14); // line_range
+    buf.push(13); // opcode_base
+    buf.extend_from_slice(&[0, 1, 1, 1, 1, 0, 0, 0, 1, 0, 0, 1]);
+    buf.push(0); // no include_directories
+
+    write_str(&mut buf, file);
+    write_uleb(&mut buf, 0); // directory
+    write_uleb(&mut buf, 0); // modification time
+    write_uleb(&mut buf, 0); // length
+    buf.push(0);
+
+    let header_length = (buf.len() - header_start) as u32;
+    (&mut buf[6..10])
+        .write_u32::<LittleEndian>(header_length)
+        .unwrap();
+
+    buf.push(0);
+    write_uleb(&mut buf, 9);
+    buf.push(DW_LNE_SET_ADDRESS);
+    buf.write_u64::<LittleEndian>(code_start).unwrap();
+
+    // offsets in the line number table are return addresses of calls
+    // and positions of traps, the line of an offset therefore holds for
+    // all code since the previous offset
+    let mut address = 0;
+    let mut line = 1;
+    let mut start = 0;
+
+    for &(offset, lineno) in lines {
+        emit_row(&mut buf, &mut address, &mut line, start, lineno);
+        start = offset;
+    }
+
+    if code_len > address {
+        buf.push(DW_LNS_ADVANCE_PC);
+        write_uleb(&mut buf, code_len - address);
+    }
+
+    buf.push(0);
+    write_uleb(&mut buf, 1);
+    buf.push(DW_LNE_END_SEQUENCE);
+
+    patch_length(&mut buf);
+    buf
+}
+
+fn emit_row(buf: &mut Vec<u8>, address: &mut u64, line: &mut u64, row_address: u64, row_line: u64) {
+    if row_address > *address {
+        buf.push(DW_LNS_ADVANCE_PC);
+        write_uleb(buf, row_address - *address);
+        *address = row_address;
+    }
+
+    if row_line != *line {
+        buf.push(DW_LNS_ADVANCE_LINE);
+        write_sleb(buf, row_line as i64 - *line as i64);
+        *line = row_line;
+    }
+
+    buf.push(DW_LNS_COPY);
+}
+
+const DW_CFA_DEF_CFA: u8 = 0x0c;
+const DW_CFA_OFFSET: u8 = 0x80;
+
+// the CFA is always relative to the frame pointer, this is only wrong in
+// the prolog and epilog of a function
+fn debug_frame(code_start: u64, code_len: u64) -> Vec<u8> {
+    let mut buf = Vec::new();
+
+    buf.write_u32::<LittleEndian>(0).unwrap(); // length
+    buf.write_u32::<LittleEndian>(0xffff_ffff).unwrap(); // CIE_id
+    buf.push(1); // version
+    buf.push(0); // augmentation
+    write_uleb(&mut buf, 1); // code_alignment_factor
+    write_sleb(&mut buf, -8); // data_alignment_factor
+    buf.push(arch::DWARF_RA);
+
+    buf.push(DW_CFA_DEF_CFA);
+    write_uleb(&mut buf, arch::DWARF_FP as u64);
+    write_uleb(&mut buf, 16);
+    buf.push(DW_CFA_OFFSET | arch::DWARF_RA);
+    write_uleb(&mut buf, 1);
+    buf.push(DW_CFA_OFFSET | arch::DWARF_FP);
+    write_uleb(&mut buf, 2);
+
+    align(&mut buf, 8);
+    patch_length(&mut buf);
+
+    let fde_start = buf.len();
+    buf.write_u32::<LittleEndian>(0).unwrap(); // length
+    buf.write_u32::<LittleEndian>(0).unwrap(); // CIE_pointer
+    buf.write_u64::<LittleEndian>(code_start).unwrap();
+    buf.write_u64::<LittleEndian>(code_len).unwrap();
+
+    let fde_length = (buf.len() - fde_start - 4) as u32;
+    (&mut buf[fde_start..fde_start + 4])
+        .write_u32::<LittleEndian>(fde_length)
+        .unwrap();
+
+    buf
+}
+
+// stores the length of the unit without the length field itself
+fn patch_length(buf: &mut Vec<u8>) {
+    let length = (buf.len() - 4) as u32;
+    (&mut buf[0..4]).write_u32::<LittleEndian>(length).unwrap();
+}
+
+fn align(buf: &mut Vec<u8>, alignment: usize) {
+    while buf.len() % alignment != 0 {
+        buf.push(0);
+    }
+}
+
+fn write_str(buf: &mut Vec<u8>, value: &str) {
+    buf.extend_from_slice(value.as_bytes());
+    buf.push(0);
+}
+
+fn write_uleb(buf: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+
+        if value == 0 {
+            buf.push(byte);
+            break;
+        }
+
+        buf.push(byte | 0x80);
+    }
+}
+
+fn write_sleb(buf: &mut Vec<u8>, mut value: i64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+
+        if (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0) {
+            buf.push(byte);
+            break;
+        }
+
+        buf.push(byte | 0x80);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use byteorder::ByteOrder;
+
+    #[test]
+    fn test_leb128() {
+        let mut buf = Vec::new();
+        write_uleb(&mut buf, 624485);
+        assert_eq!(vec![0xe5, 0x8e, 0x26], buf);
+
+        let mut buf = Vec::new();
+        write_sleb(&mut buf, -123456);
+        assert_eq!(vec![0xc0, 0xbb, 0x78], buf);
+
+        let mut buf = Vec::new();
+        write_sleb(&mut buf, 63);
+        write_sleb(&mut buf, 64);
+        assert_eq!(vec![0x3f, 0xc0, 0x00], buf);
+    }
+
+    #[test]
+    fn test_build_object() {
+        let object = build_object("Foo.bar", "foo.dora", 0x1000, 0x40, &[(8, 3), (20, 5)]);
+
+        assert_eq!(&[0x7f, b'E', b'L', b'F'], &object[0..4]);
+        assert_eq!(0, object.len() % 8);
+
+        // section header table is at the end of the object
+        let shnum = LittleEndian::read_u16(&object[60..62]) as usize;
+        assert_eq!(9, shnum);
+        let shoff = LittleEndian::read_u64(&object[40..48]) as usize;
+        assert_eq!(object.len(), shoff + shnum * 64);
+
+        // .text describes the machine code
+        let text = &object[shoff + 64..shoff + 128];
+        assert_eq!(SHT_NOBITS, LittleEndian::read_u32(&text[4..8]));
+        assert_eq!(0x1000, LittleEndian::read_u64(&text[16..24]));
+        assert_eq!(0x40, LittleEndian::read_u64(&text[32..40]));
+    }
+}
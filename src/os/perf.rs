@@ -1,9 +1,9 @@
-use baseline::fct::JitBaselineFct;
+use baseline::fct::JitFct;
 use ctxt::VM;
 use dora_parser::interner::Name;
 
 #[cfg(target_os = "linux")]
-pub fn register_with_perf(jit_fct: &JitBaselineFct, vm: &VM, name: Name) {
+pub fn register_with_perf(jit_fct: &JitFct, vm: &VM, name: Name) {
     use libc;
     use std::fs::OpenOptions;
     use std::io::prelude::*;
@@ -28,6 +28,6 @@ pub fn register_with_perf(jit_fct: &JitBaselineFct, vm: &VM, name: Name) {
 }
 
 #[cfg(not(target_os = "linux"))]
-pub fn register_with_perf(_: &JitBaselineFct, _: &VM, _: Name) {
+pub fn register_with_perf(_: &JitFct, _: &VM, _: Name) {
     // nothing to do
 }
@@ -148,8 +148,6 @@ fn detect_nil_check(vm: &VM, pc: usize, signo: libc::c_int, addr: *const u8) ->
     if let Some(CodeDescriptor::DoraFct(fid)) = code_map.get(pc.into()) {
         let jit_fct = vm.jit_fcts.idx(fid);
         let offset = pc - jit_fct.fct_ptr().to_usize();
-
-        let jit_fct = jit_fct.to_base().expect("baseline expected");
         jit_fct.nil_check_for_offset(offset as i32)
     } else {
         false
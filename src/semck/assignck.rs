@@ -0,0 +1,607 @@
+use std::collections::{HashMap, HashSet};
+
+use class::{ClassId, FieldId};
+use ctxt::{Fct, FctParent, FctSrc, IdentType, SemContext, VarId};
+use dora_parser::ast::visit::*;
+use dora_parser::ast::Expr::*;
+use dora_parser::ast::Stmt::*;
+use dora_parser::ast::*;
+use dora_parser::error::msg::Msg;
+use dora_parser::lexer::position::Position;
+use ty::BuiltinType;
+
+pub fn check<'ast>(ctxt: &SemContext<'ast>) {
+    for fct in ctxt.fcts.iter() {
+        let fct = fct.read();
+
+        if !fct.is_src() {
+            continue;
+        }
+
+        let src = fct.src();
+        let src = src.read();
+
+        let mut assignck = AssignCheck {
+            ctxt: ctxt,
+            fct: &fct,
+            src: &src,
+            state: Some(HashMap::new()),
+            loops: Vec::new(),
+            reported: HashSet::new(),
+        };
+
+        assignck.check();
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+enum Slot {
+    Var(VarId),
+    Field(FieldId),
+}
+
+// slots that are possibly unassigned at the current point, mapped to the
+// position of the path that misses the assignment, None if unreachable
+type State = Option<HashMap<Slot, Position>>;
+
+struct AssignCheck<'a, 'ast: 'a> {
+    ctxt: &'a SemContext<'ast>,
+    fct: &'a Fct<'ast>,
+    src: &'a FctSrc,
+
+    state: State,
+
+    // states and positions of break statements in enclosing loops
+    loops: Vec<Vec<(Position, State)>>,
+
+    // only the first error for every slot is reported
+    reported: HashSet<Slot>,
+}
+
+impl<'a, 'ast> AssignCheck<'a, 'ast> {
+    fn check(&mut self) {
+        let ast = self.fct.ast;
+        let mut pos = ast.pos;
+
+        if let Some(cls_id) = self.ctor_cls_id() {
+            let cls = self.ctxt.classes.idx(cls_id);
+            let cls = cls.read();
+
+            // the generated primary constructor has no position of its own
+            if self.fct.ctor.is_primary() {
+                pos = cls.pos;
+            }
+
+            // other fields start out as nil or zero
+            for field in &cls.fields {
+                if !has_default(self.ctxt, field.ty) {
+                    self.insert(Slot::Field(field.id), pos);
+                }
+            }
+        }
+
+        self.visit_stmt(ast.block());
+        self.check_fields(pos);
+    }
+
+    fn ctor_cls_id(&self) -> Option<ClassId> {
+        match self.fct.parent {
+            FctParent::Class(cls_id) if self.fct.ctor.is() => Some(cls_id),
+            _ => None,
+        }
+    }
+
+    fn insert(&mut self, slot: Slot, pos: Position) {
+        if let Some(ref mut state) = self.state {
+            state.insert(slot, pos);
+        }
+    }
+
+    fn assign(&mut self, slot: Slot) {
+        if let Some(ref mut state) = self.state {
+            state.remove(&slot);
+        }
+    }
+
+    fn read(&mut self, var: VarId, pos: Position) {
+        let path = match self.state {
+            Some(ref state) => state.get(&Slot::Var(var)).cloned(),
+            None => None,
+        };
+
+        if let Some(path) = path {
+            if self.reported.insert(Slot::Var(var)) {
+                let name = self.src.vars[var].name;
+                let name = self.ctxt.interner.str(name).to_string();
+                let msg = Msg::VarPossiblyUnassigned(name, path);
+                self.ctxt.diag.lock().report(pos, msg);
+            }
+
+            self.assign(Slot::Var(var));
+        }
+    }
+
+    // the constructor returns at `pos`, all fields need to be assigned
+    fn check_fields(&mut self, pos: Position) {
+        let cls_id = match self.ctor_cls_id() {
+            Some(cls_id) => cls_id,
+            None => return,
+        };
+
+        let mut fields = match self.state {
+            Some(ref state) => state
+                .iter()
+                .filter_map(|(&slot, &path)| match slot {
+                    Slot::Field(field_id) => Some((field_id, path)),
+                    Slot::Var(_) => None,
+                })
+                .collect::<Vec<_>>(),
+            None => return,
+        };
+
+        fields.sort_by_key(|&(field_id, _)| field_id.idx());
+
+        let cls = self.ctxt.classes.idx(cls_id);
+        let cls = cls.read();
+
+        for (field_id, path) in fields {
+            if !self.reported.insert(Slot::Field(field_id)) {
+                continue;
+            }
+
+            let cls_name = self.ctxt.interner.str(cls.name).to_string();
+            let field_name = self
+                .ctxt
+                .interner
+                .str(cls.fields[field_id].name)
+                .to_string();
+            let msg = Msg::FieldPossiblyUnassigned(cls_name, field_name, path);
+            self.ctxt.diag.lock().report(pos, msg);
+        }
+    }
+
+    // field of the constructed object that is assigned by `expr`
+    fn field(&self, expr: &Expr) -> Option<FieldId> {
+        let cls_id = self.ctor_cls_id()?;
+
+        let id = match *expr {
+            ExprField(ref field) if field.object.is_this() => field.id,
+            _ => return None,
+        };
+
+        match self.src.map_idents.get(id) {
+            Some(&IdentType::Field(BuiltinType::Class(id, _), field_id)) if id == cls_id => {
+                Some(field_id)
+            }
+            _ => None,
+        }
+    }
+
+    // a slot that is unassigned after the loop although the loop body
+    // assigns it is missed when the body is not executed
+    fn skip_loop(&mut self, entry: &State, body: &State, pos: Position) {
+        let (entry, body) = match (entry, body) {
+            (&Some(ref entry), &Some(ref body)) => (entry, body),
+            _ => return,
+        };
+
+        if let Some(ref mut state) = self.state {
+            for (slot, path) in state.iter_mut() {
+                if entry.get(slot) == Some(path) && !body.contains_key(slot) {
+                    *path = pos;
+                }
+            }
+        }
+    }
+
+    fn visit_if(&mut self, stmt: &'ast StmtIfType) {
+        self.visit_expr(&stmt.cond);
+        let entry = self.state.clone();
+
+        self.visit_stmt(&stmt.then_block);
+        let then_state = self.state.take();
+
+        self.state = entry.clone();
+
+        let else_pos = match stmt.else_block {
+            Some(ref else_block) => {
+                self.visit_stmt(else_block);
+                else_block.pos()
+            }
+            None => stmt.pos,
+        };
+
+        let paths = vec![
+            (stmt.then_block.pos(), then_state),
+            (else_pos, self.state.take()),
+        ];
+
+        self.state = merge(&entry, paths);
+    }
+
+    // assignments in the loop body only add assigned slots, so the state at
+    // the loop header is the state before the loop and needs no iteration,
+    // `exits` is false for loops only left through break
+    fn visit_loop(
+        &mut self,
+        pos: Position,
+        cond: Option<&'ast Expr>,
+        exits: bool,
+        block: &'ast Stmt,
+    ) {
+        if let Some(cond) = cond {
+            self.visit_expr(cond);
+        }
+
+        let entry = self.state.clone();
+
+        self.loops.push(Vec::new());
+        self.visit_stmt(block);
+        let body = self.state.take();
+        let mut paths = self.loops.pop().unwrap();
+
+        if exits {
+            paths.insert(0, (pos, entry.clone()));
+        }
+
+        self.state = merge(&entry, paths);
+        self.skip_loop(&entry, &body, pos);
+    }
+
+    fn visit_do(&mut self, stmt: &'ast StmtDoType) {
+        // any statement of the do-block may throw, so catch and finally
+        // blocks only see the assignments before the do-statement
+        let entry = self.state.clone();
+
+        self.visit_stmt(&stmt.do_block);
+        let mut paths = vec![(stmt.do_block.pos(), self.state.take())];
+
+        for catch in &stmt.catch_blocks {
+            self.state = entry.clone();
+            self.visit_stmt(&catch.block);
+            paths.push((catch.pos, self.state.take()));
+        }
+
+        self.state = merge(&entry, paths);
+
+        if let Some(ref finally_block) = stmt.finally_block {
+            let normal = self.state.take();
+
+            self.state = entry;
+            self.visit_stmt(&finally_block.block);
+            let finally = self.state.take();
+
+            self.state = match (normal, finally) {
+                (Some(mut normal), Some(finally)) => {
+                    normal.retain(|slot, _| finally.contains_key(slot));
+                    Some(normal)
+                }
+
+                _ => None,
+            };
+        }
+    }
+
+    fn visit_var(&mut self, stmt: &'ast StmtVarType) {
+        let var = match self.src.map_vars.get(stmt.id) {
+            Some(&var) => var,
+            None => return,
+        };
+
+        match stmt.expr {
+            Some(ref expr) => {
+                self.visit_expr(expr);
+                self.assign(Slot::Var(var));
+            }
+
+            None => self.insert(Slot::Var(var), stmt.pos),
+        }
+    }
+
+    fn visit_assign(&mut self, expr: &'ast ExprAssignType) {
+        if let Some(field_id) = self.field(&expr.lhs) {
+            self.visit_expr(&expr.rhs);
+            self.assign(Slot::Field(field_id));
+            return;
+        }
+
+        let var = match *expr.lhs {
+            ExprIdent(ref ident) => match self.src.map_idents.get(ident.id) {
+                Some(&IdentType::Var(var)) => Some(var),
+                _ => None,
+            },
+            _ => None,
+        };
+
+        let var = match var {
+            Some(var) => var,
+            None => {
+                self.visit_expr(&expr.lhs);
+                self.visit_expr(&expr.rhs);
+                return;
+            }
+        };
+
+        // compound assignments read the variable first
+        if expr.op.is_some() {
+            self.read(var, expr.lhs.pos());
+        }
+
+        self.visit_expr(&expr.rhs);
+        self.assign(Slot::Var(var));
+    }
+
+    fn visit_lambda(&mut self, expr: &'ast ExprLambdaType) {
+        let info = match self.src.map_lambdas.get(expr.id) {
+            Some(info) => info,
+            None => return,
+        };
+
+        // captured values are copied into the closure when it is created
+        for capture in &info.captures {
+            if let IdentType::Var(var) = *capture {
+                self.read(var, expr.pos);
+            }
+        }
+    }
+}
+
+// values of enums and lambdas are objects, a zeroed field would be an
+// invalid null reference
+fn has_default(ctxt: &SemContext, ty: BuiltinType) -> bool {
+    match ty {
+        BuiltinType::Enum(_) | BuiltinType::Lambda(_) => false,
+
+        BuiltinType::Struct(sid, _) => {
+            let struc = ctxt.structs.idx(sid);
+            let struc = struc.lock();
+
+            struc.fields.iter().all(|field| has_default(ctxt, field.ty))
+        }
+
+        BuiltinType::Tuple(tuple_id) => {
+            let elements = ctxt.tuple_types.lock().get(tuple_id);
+
+            elements.iter().all(|&ty| has_default(ctxt, ty))
+        }
+
+        _ => true,
+    }
+}
+
+// joins the states of paths starting at the same point with state `entry`,
+// a slot missed by only some paths is reported with the position of the
+// first such path unless the path itself is more specific
+fn merge(entry: &State, paths: Vec<(Position, State)>) -> State {
+    let paths = paths
+        .into_iter()
+        .filter_map(|(pos, state)| state.map(|state| (pos, state)))
+        .collect::<Vec<_>>();
+
+    if paths.is_empty() {
+        return None;
+    }
+
+    let mut result = HashMap::new();
+
+    for &(_, ref state) in &paths {
+        for &slot in state.keys() {
+            if result.contains_key(&slot) {
+                continue;
+            }
+
+            let all = paths
+                .iter()
+                .all(|&(_, ref state)| state.contains_key(&slot));
+            let &(pos, ref state) = paths
+                .iter()
+                .find(|&&(_, ref state)| state.contains_key(&slot))
+                .unwrap();
+            let path = state[&slot];
+
+            let inherited = match *entry {
+                Some(ref entry) => entry.get(&slot) == Some(&path),
+                None => false,
+            };
+
+            result.insert(slot, if !all && inherited { pos } else { path });
+        }
+    }
+
+    Some(result)
+}
+
+impl<'a, 'ast> Visitor<'ast> for AssignCheck<'a, 'ast> {
+    fn visit_stmt(&mut self, s: &'ast Stmt) {
+        match *s {
+            StmtIf(ref stmt) => self.visit_if(stmt),
+            StmtWhile(ref stmt) => self.visit_loop(stmt.pos, Some(&stmt.cond), true, &stmt.block),
+            StmtLoop(ref stmt) => self.visit_loop(stmt.pos, None, false, &stmt.block),
+            StmtFor(ref stmt) => {
+                self.visit_expr(&stmt.expr);
+                self.visit_loop(stmt.pos, None, true, &stmt.block);
+            }
+            StmtDo(ref stmt) => self.visit_do(stmt),
+            StmtVar(ref stmt) => self.visit_var(stmt),
+
+            StmtBreak(ref stmt) => {
+                let state = self.state.take();
+
+                // flowck already reported breaks outside of loops
+                if let Some(breaks) = self.loops.last_mut() {
+                    breaks.push((stmt.pos, state));
+                }
+            }
+
+            StmtContinue(_) => {
+                self.state = None;
+            }
+
+            StmtReturn(ref stmt) => {
+                visit::walk_stmt(self, s);
+                self.check_fields(stmt.pos);
+                self.state = None;
+            }
+
+            StmtThrow(_) => {
+                visit::walk_stmt(self, s);
+                self.state = None;
+            }
+
+            _ => visit::walk_stmt(self, s),
+        }
+    }
+
+    fn visit_expr(&mut self, e: &'ast Expr) {
+        match *e {
+            ExprIdent(ref ident) => {
+                if let Some(&IdentType::Var(var)) = self.src.map_idents.get(ident.id) {
+                    self.read(var, ident.pos);
+                }
+            }
+
+            ExprCall(ref call) => {
+                // call of lambda stored in variable
+                if let Some(&IdentType::Var(var)) = self.src.map_idents.get(call.id) {
+                    self.read(var, call.pos);
+                }
+
+                visit::walk_expr(self, e);
+            }
+
+            ExprBin(ref expr) if expr.op == BinOp::And || expr.op == BinOp::Or => {
+                // rhs is only evaluated depending on the value of lhs
+                self.visit_expr(&expr.lhs);
+                let lhs = self.state.clone();
+                self.visit_expr(&expr.rhs);
+
+                let paths = vec![(expr.pos, lhs.clone()), (expr.rhs.pos(), self.state.take())];
+                self.state = merge(&lhs, paths);
+            }
+
+            ExprMatch(ref expr) => {
+                self.visit_expr(&expr.expr);
+
+                if !expr.arms.is_empty() {
+                    let entry = self.state.take();
+                    let mut paths = Vec::new();
+
+                    for arm in &expr.arms {
+                        self.state = entry.clone();
+                        self.visit_expr(&arm.value);
+                        paths.push((arm.pos, self.state.take()));
+                    }
+
+                    self.state = merge(&entry, paths);
+                }
+            }
+
+            ExprDelegation(ref expr) => {
+                visit::walk_expr(self, e);
+
+                // the delegated constructor assigns all fields
+                if expr.ty.is_this() {
+                    if let Some(ref mut state) = self.state {
+                        state.retain(|slot, _| match *slot {
+                            Slot::Field(_) => false,
+                            Slot::Var(_) => true,
+                        });
+                    }
+                }
+            }
+
+            ExprAssign(ref expr) => self.visit_assign(expr),
+            ExprLambda(ref expr) => self.visit_lambda(expr),
+
+            _ => visit::walk_expr(self, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dora_parser::error::msg::Msg;
+    use semck::tests::*;
+
+    #[test]
+    fn assign_var_before_read() {
+        ok("fun f() -> int { var x: int; x = 1; return x; }");
+        ok("fun f(a: bool) -> int { var x: int; if a { x = 1; } else { x = 2; } return x; }");
+        ok("fun f(a: bool) -> int { var x: int; if a { return 1; } else { x = 2; } return x; }");
+        ok("fun f() -> int { var x: int; loop { x = 1; break; } return x; }");
+        ok("fun f() { var x: int; if true { } x = 1; x = x + 1; }");
+
+        err(
+            "fun f() -> int { var x: int; return x; }",
+            pos(1, 37),
+            Msg::VarPossiblyUnassigned("x".into(), pos(1, 18)),
+        );
+        err(
+            "fun f() { var x: int; x += 1; }",
+            pos(1, 23),
+            Msg::VarPossiblyUnassigned("x".into(), pos(1, 11)),
+        );
+    }
+
+    #[test]
+    fn assign_var_on_some_paths() {
+        err(
+            "fun f(a: bool) -> int { var x: int; if a { x = 1; } return x; }",
+            pos(1, 60),
+            Msg::VarPossiblyUnassigned("x".into(), pos(1, 37)),
+        );
+        err(
+            "fun f(a: bool) -> int { var x: int; if a { x = 1; } else { } return x; }",
+            pos(1, 69),
+            Msg::VarPossiblyUnassigned("x".into(), pos(1, 58)),
+        );
+        err(
+            "fun f(a: bool) -> int { var x: int; while a { x = 1; } return x; }",
+            pos(1, 63),
+            Msg::VarPossiblyUnassigned("x".into(), pos(1, 37)),
+        );
+        err(
+            "fun f() -> int { var x: int; do { x = try g(); } catch e: Exception { } return x; }
+            fun g() throws -> int { return 1; }",
+            pos(1, 80),
+            Msg::VarPossiblyUnassigned("x".into(), pos(1, 50)),
+        );
+        err(
+            "fun f() -> int { var x: int; let y = || -> int { return x; }; return y(); }",
+            pos(1, 38),
+            Msg::VarPossiblyUnassigned("x".into(), pos(1, 18)),
+        );
+    }
+
+    #[test]
+    fn assign_fields_in_ctor() {
+        ok("class A { var a: int; var b: A; var c: (int, A); }");
+        ok("class A(let a: E) { var b: E = E::X; } enum E { X, Y }");
+        ok("class A { var a: E; init() { self.a = E::X; } init(b: bool) : self() { } }
+            enum E { X, Y }");
+        ok("class A { var a: E; init(b: bool) { if b { self.a = E::X; } else { self.a = E::Y; } } }
+            enum E { X, Y }");
+
+        err(
+            "class A { var a: E; } enum E { X, Y }",
+            pos(1, 1),
+            Msg::FieldPossiblyUnassigned("A".into(), "a".into(), pos(1, 1)),
+        );
+        err(
+            "class A { var a: () -> int; } ",
+            pos(1, 1),
+            Msg::FieldPossiblyUnassigned("A".into(), "a".into(), pos(1, 1)),
+        );
+        err(
+            "class A { var a: E; init(b: bool) { if b { self.a = E::X; } } } enum E { X, Y }",
+            pos(1, 21),
+            Msg::FieldPossiblyUnassigned("A".into(), "a".into(), pos(1, 37)),
+        );
+        err(
+            "class A { var a: E; init(b: bool) { if b { return; } self.a = E::X; } }
+            enum E { X, Y }",
+            pos(1, 44),
+            Msg::FieldPossiblyUnassigned("A".into(), "a".into(), pos(1, 21)),
+        );
+    }
+}
@@ -36,7 +36,14 @@ impl<'x, 'ast> ClsCheck<'x, 'ast> {
         self.visit_ast(self.ast);
     }
 
-    fn add_field(&mut self, pos: Position, name: Name, ty: BuiltinType, reassignable: bool) {
+    fn add_field(
+        &mut self,
+        pos: Position,
+        name: Name,
+        ty: BuiltinType,
+        reassignable: bool,
+        is_pub: bool,
+    ) {
         let cls = self.ctxt.classes.idx(self.cls_id.unwrap());
         let mut cls = cls.write();
 
@@ -53,6 +60,7 @@ impl<'x, 'ast> ClsCheck<'x, 'ast> {
             ty: ty,
             offset: 0,
             reassignable: reassignable,
+            is_pub: is_pub,
         };
 
         cls.fields.push(field);
@@ -60,6 +68,14 @@ impl<'x, 'ast> ClsCheck<'x, 'ast> {
 }
 
 impl<'x, 'ast> Visitor<'ast> for ClsCheck<'x, 'ast> {
+    fn visit_file(&mut self, f: &'ast ast::File) {
+        let module = self.ctxt.file_module(f);
+
+        self.ctxt.sym.lock().set_module(module);
+        visit::walk_file(self, f);
+        self.ctxt.sym.lock().set_module(None);
+    }
+
     fn visit_class(&mut self, c: &'ast ast::Class) {
         self.cls_id = Some(*self.map_cls_defs.get(c.id).unwrap());
 
@@ -183,7 +199,7 @@ impl<'x, 'ast> Visitor<'ast> for ClsCheck<'x, 'ast> {
 
     fn visit_field(&mut self, f: &'ast ast::Field) {
         let ty = semck::read_type(self.ctxt, &f.data_type).unwrap_or(BuiltinType::Unit);
-        self.add_field(f.pos, f.name, ty, f.reassignable);
+        self.add_field(f.pos, f.name, ty, f.reassignable, f.is_pub);
 
         if !f.reassignable && !f.primary_ctor && f.expr.is_none() {
             self.ctxt
@@ -207,6 +223,7 @@ impl<'x, 'ast> Visitor<'ast> for ClsCheck<'x, 'ast> {
             pos: f.pos,
             ast: f,
             name: f.name,
+            module: self.ctxt.sym.lock().module(),
             param_types: Vec::new(),
             return_type: BuiltinType::Unit,
             parent: FctParent::Class(clsid),
@@ -220,6 +237,7 @@ impl<'x, 'ast> Visitor<'ast> for ClsCheck<'x, 'ast> {
             internal_resolved: false,
             overrides: None,
             throws: f.throws,
+            is_lambda: false,
             ctor: f.ctor,
             vtable_index: None,
             initialized: false,
@@ -252,6 +270,7 @@ impl<'x, 'ast> Visitor<'ast> for ClsCheck<'x, 'ast> {
             ast: f,
             pos: f.pos,
             name: f.name,
+            module: self.ctxt.sym.lock().module(),
             param_types: Vec::new(),
             return_type: BuiltinType::Unit,
             parent: FctParent::Class(self.cls_id.unwrap()),
@@ -268,6 +287,7 @@ impl<'x, 'ast> Visitor<'ast> for ClsCheck<'x, 'ast> {
             internal_resolved: false,
             overrides: None,
             throws: f.throws,
+            is_lambda: false,
             ctor: ast::CtorType::None,
             vtable_index: None,
             initialized: false,
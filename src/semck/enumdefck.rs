@@ -0,0 +1,169 @@
+use parking_lot::RwLock;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use class::{self, ClassId, Field, FieldId};
+use ctxt::{EnumId, EnumVariant, NodeMap, SemContext};
+use dora_parser::ast;
+use dora_parser::ast::visit::{self, Visitor};
+use dora_parser::error::msg::Msg;
+use dora_parser::interner::Name;
+use dora_parser::lexer::position::Position;
+use semck;
+use ty::BuiltinType;
+
+pub fn check<'ast>(ctxt: &mut SemContext<'ast>, map_enum_defs: &NodeMap<EnumId>) {
+    let mut enumck = EnumCheck {
+        ctxt: ctxt,
+        ast: ctxt.ast,
+        map_enum_defs: map_enum_defs,
+    };
+
+    enumck.check();
+}
+
+struct EnumCheck<'x, 'ast: 'x> {
+    ctxt: &'x mut SemContext<'ast>,
+    ast: &'ast ast::Ast,
+    map_enum_defs: &'x NodeMap<EnumId>,
+}
+
+impl<'x, 'ast> EnumCheck<'x, 'ast> {
+    fn check(&mut self) {
+        self.visit_ast(self.ast);
+    }
+
+    // every variant gets its own class: the first field stores the tag,
+    // the payload is stored in the remaining fields
+    fn add_variant_class(&mut self, name: Name, pos: Position, types: &[BuiltinType]) -> ClassId {
+        let mut fields = Vec::with_capacity(types.len() + 1);
+        let tag_name = self.ctxt.interner.intern("tag");
+
+        fields.push(Field {
+            id: FieldId::from(0),
+            name: tag_name,
+            ty: BuiltinType::Int,
+            offset: 0,
+            reassignable: false,
+            is_pub: false,
+        });
+
+        for (idx, &ty) in types.iter().enumerate() {
+            let field_name = self.ctxt.interner.intern(&idx.to_string());
+
+            fields.push(Field {
+                id: FieldId::from(idx + 1),
+                name: field_name,
+                ty: ty,
+                offset: 0,
+                reassignable: false,
+                is_pub: false,
+            });
+        }
+
+        let mut classes = self.ctxt.classes.lock();
+        let id: ClassId = classes.len().into();
+
+        let cls = class::Class {
+            id: id,
+            name: name,
+            pos: pos,
+            module: self.ctxt.sym.lock().module(),
+            ty: self.ctxt.cls(id),
+            parent_class: None,
+            has_open: false,
+            is_abstract: false,
+            internal: false,
+            internal_resolved: false,
+            primary_ctor: false,
+
+            ctors: Vec::new(),
+            fields: fields,
+            methods: Vec::new(),
+
+            traits: Vec::new(),
+            impls: Vec::new(),
+
+            type_params: Vec::new(),
+            specializations: RwLock::new(HashMap::new()),
+            vtable_len: 0,
+
+            is_array: false,
+            is_str: false,
+        };
+
+        classes.push(Arc::new(RwLock::new(cls)));
+
+        id
+    }
+}
+
+impl<'x, 'ast> Visitor<'ast> for EnumCheck<'x, 'ast> {
+    fn visit_file(&mut self, f: &'ast ast::File) {
+        let module = self.ctxt.file_module(f);
+
+        self.ctxt.sym.lock().set_module(module);
+        visit::walk_file(self, f);
+        self.ctxt.sym.lock().set_module(None);
+    }
+
+    fn visit_enum(&mut self, e: &'ast ast::Enum) {
+        let id = *self.map_enum_defs.get(e.id).unwrap();
+        let mut variants: Vec<EnumVariant> = Vec::with_capacity(e.variants.len());
+
+        for variant in &e.variants {
+            if variants.iter().any(|v| v.name == variant.name) {
+                let name = self.ctxt.interner.str(variant.name).to_string();
+                report(self.ctxt, variant.pos, Msg::ShadowEnumValue(name));
+                continue;
+            }
+
+            let types = variant
+                .types
+                .iter()
+                .map(|ty| semck::read_type(self.ctxt, ty).unwrap_or(BuiltinType::Error))
+                .collect::<Vec<_>>();
+
+            let cls_id = self.add_variant_class(e.name, variant.pos, &types);
+
+            variants.push(EnumVariant {
+                pos: variant.pos,
+                name: variant.name,
+                types: types,
+                cls_id: cls_id,
+            });
+        }
+
+        let xenum = self.ctxt.enums.idx(id);
+        xenum.lock().variants = variants;
+    }
+}
+
+fn report(ctxt: &SemContext, pos: Position, msg: Msg) {
+    ctxt.diag.lock().report(pos, msg);
+}
+
+#[cfg(test)]
+mod tests {
+    use dora_parser::error::msg::Msg;
+    use semck::tests::*;
+
+    #[test]
+    fn enum_definitions() {
+        ok("enum Color { Red, Green, Blue }");
+        ok("enum Shape { Circle(int), Rect(int, int), Empty }");
+        ok("class Foo enum Wrapper { Value(Foo), Nothing }");
+        ok("enum Color { Red, Green } fun f(c: Color) -> Color { return c; }");
+
+        err(
+            "enum Color { Red, Red }",
+            pos(1, 19),
+            Msg::ShadowEnumValue("Red".into()),
+        );
+        err(
+            "enum Shape { Circle(Foo) }",
+            pos(1, 21),
+            Msg::UnknownType("Foo".into()),
+        );
+    }
+}
@@ -16,6 +16,8 @@ pub fn check<'a, 'ast>(ctxt: &SemContext<'ast>) {
         let mut fct = fct.write();
         let ast = fct.ast;
 
+        ctxt.sym.lock().set_module(fct.module);
+
         // check modifiers for function
         check_abstract(ctxt, &*fct);
         check_static(ctxt, &*fct);
@@ -27,6 +29,29 @@ pub fn check<'a, 'ast>(ctxt: &SemContext<'ast>) {
         ctxt.sym.lock().push_level();
 
         match fct.parent {
+            FctParent::Class(owner_class) if fct.is_lambda => {
+                let cls = ctxt.classes.idx(owner_class);
+                let cls = cls.read();
+
+                // type params of a closure class are the type params
+                // of the function enclosing the lambda
+                let params = match cls.ty {
+                    BuiltinType::Class(_, list_id) => ctxt.lists.lock().get(list_id),
+                    _ => unreachable!(),
+                };
+
+                for (param, ty) in cls.type_params.iter().zip(params.iter()) {
+                    if let BuiltinType::FctTypeParam(fct_id, type_param_id) = ty {
+                        let sym = Sym::SymFctTypeParam(fct_id, type_param_id);
+                        ctxt.sym.lock().insert(param.name, sym);
+                    }
+                }
+
+                if fct.has_self() {
+                    fct.param_types.push(cls.ty);
+                }
+            }
+
             FctParent::Class(owner_class) => {
                 let cls = ctxt.classes.idx(owner_class);
                 let cls = cls.read();
@@ -192,6 +217,7 @@ pub fn check<'a, 'ast>(ctxt: &SemContext<'ast>) {
     }
 
     debug_assert!(ctxt.sym.lock().levels() == 1);
+    ctxt.sym.lock().set_module(None);
 }
 
 fn check_abstract<'ast>(ctxt: &SemContext<'ast>, fct: &Fct<'ast>) {
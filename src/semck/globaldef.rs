@@ -11,13 +11,17 @@ use dora_parser::error::msg::Msg;
 use dora_parser::interner::Name;
 use dora_parser::lexer::position::Position;
 use gc::Address;
-use sym::Sym::{self, SymClass, SymConst, SymFct, SymGlobal, SymStruct, SymTrait};
+use sym::Sym::{
+    self, SymClass, SymConst, SymEnum, SymFct, SymGlobal, SymModule, SymStruct, SymTrait,
+    SymTypeAlias,
+};
 use ty::BuiltinType;
 
 pub fn check<'ast>(
     ctxt: &mut SemContext<'ast>,
     map_cls_defs: &mut NodeMap<ClassId>,
     map_struct_defs: &mut NodeMap<StructId>,
+    map_enum_defs: &mut NodeMap<EnumId>,
     map_trait_defs: &mut NodeMap<TraitId>,
     map_impl_defs: &mut NodeMap<ImplId>,
     map_global_defs: &mut NodeMap<GlobalId>,
@@ -27,6 +31,7 @@ pub fn check<'ast>(
         ctxt: ctxt,
         map_cls_defs: map_cls_defs,
         map_struct_defs: map_struct_defs,
+        map_enum_defs: map_enum_defs,
         map_trait_defs: map_trait_defs,
         map_impl_defs: map_impl_defs,
         map_global_defs: map_global_defs,
@@ -40,13 +45,70 @@ struct GlobalDef<'x, 'ast: 'x> {
     ctxt: &'x mut SemContext<'ast>,
     map_cls_defs: &'x mut NodeMap<ClassId>,
     map_struct_defs: &'x mut NodeMap<StructId>,
+    map_enum_defs: &'x mut NodeMap<EnumId>,
     map_trait_defs: &'x mut NodeMap<TraitId>,
     map_impl_defs: &'x mut NodeMap<ImplId>,
     map_global_defs: &'x mut NodeMap<GlobalId>,
     map_const_defs: &'x mut NodeMap<ConstId>,
 }
 
+impl<'x, 'ast> GlobalDef<'x, 'ast> {
+    // creates the module of the file and all of its parent modules
+    fn define_module(&mut self, f: &'ast File) -> Option<ModuleId> {
+        let mut module = None;
+
+        for name in &f.module {
+            let name = self.ctxt.interner.intern(name);
+            let sym = self.ctxt.sym.lock().get_in_module(module, name);
+
+            if let Some(SymModule(id)) = sym {
+                module = Some(id);
+                continue;
+            }
+
+            let id: ModuleId = self.ctxt.modules.len().into();
+            let path = self.ctxt.module_path(module, name);
+
+            self.ctxt.modules.push(ModuleData {
+                id: id,
+                name: name,
+                parent: module,
+                path: path,
+            });
+
+            let mut sym = self.ctxt.sym.lock();
+            sym.add_module(id);
+
+            if let Some(sym) = sym.insert_in_module(module, name, SymModule(id)) {
+                report(self.ctxt, name, Position::new(1, 1), sym);
+            }
+
+            module = Some(id);
+        }
+
+        module
+    }
+
+    fn insert(&mut self, name: Name, pos: Position, sym: Sym, is_pub: bool) {
+        let mut table = self.ctxt.sym.lock();
+
+        if let Some(sym) = table.insert(name, sym) {
+            report(self.ctxt, name, pos, sym);
+        } else if is_pub {
+            table.set_public(name);
+        }
+    }
+}
+
 impl<'x, 'ast> Visitor<'ast> for GlobalDef<'x, 'ast> {
+    fn visit_file(&mut self, f: &'ast File) {
+        let module = self.define_module(f);
+
+        self.ctxt.sym.lock().set_module(module);
+        walk_file(self, f);
+        self.ctxt.sym.lock().set_module(None);
+    }
+
     fn visit_trait(&mut self, t: &'ast Trait) {
         let id: TraitId = (self.ctxt.traits.len() as u32).into();
         let xtrait = TraitData {
@@ -61,9 +123,7 @@ impl<'x, 'ast> Visitor<'ast> for GlobalDef<'x, 'ast> {
 
         self.map_trait_defs.insert(t.id, id);
 
-        if let Some(sym) = self.ctxt.sym.lock().insert(t.name, sym) {
-            report(self.ctxt, t.name, t.pos, sym);
-        }
+        self.insert(t.name, t.pos, sym, t.is_pub);
     }
 
     fn visit_global(&mut self, g: &'ast Global) {
@@ -90,9 +150,7 @@ impl<'x, 'ast> Visitor<'ast> for GlobalDef<'x, 'ast> {
         let sym = SymGlobal(id);
         self.map_global_defs.insert(g.id, id);
 
-        if let Some(sym) = self.ctxt.sym.lock().insert(g.name, sym) {
-            report(self.ctxt, g.name, g.pos, sym);
-        }
+        self.insert(g.name, g.pos, sym, g.is_pub);
     }
 
     fn visit_impl(&mut self, i: &'ast Impl) {
@@ -117,6 +175,7 @@ impl<'x, 'ast> Visitor<'ast> for GlobalDef<'x, 'ast> {
                 id: id,
                 pos: c.pos,
                 name: c.name,
+                module: self.ctxt.sym.lock().module(),
                 ty: BuiltinType::Unit,
                 expr: &c.expr,
                 value: ConstValue::None,
@@ -131,9 +190,7 @@ impl<'x, 'ast> Visitor<'ast> for GlobalDef<'x, 'ast> {
 
         let sym = SymConst(id);
 
-        if let Some(sym) = self.ctxt.sym.lock().insert(c.name, sym) {
-            report(self.ctxt, c.name, c.pos, sym);
-        }
+        self.insert(c.name, c.pos, sym, c.is_pub);
     }
 
     fn visit_class(&mut self, c: &'ast Class) {
@@ -145,6 +202,7 @@ impl<'x, 'ast> Visitor<'ast> for GlobalDef<'x, 'ast> {
                 id: id,
                 name: c.name,
                 pos: c.pos,
+                module: self.ctxt.sym.lock().module(),
                 ty: self.ctxt.cls(id),
                 parent_class: None,
                 has_open: c.has_open,
@@ -183,9 +241,7 @@ impl<'x, 'ast> Visitor<'ast> for GlobalDef<'x, 'ast> {
 
         self.map_cls_defs.insert(c.id, id);
 
-        if let Some(sym) = self.ctxt.sym.lock().insert(c.name, sym) {
-            report(self.ctxt, c.name, c.pos, sym);
-        }
+        self.insert(c.name, c.pos, sym, c.is_pub);
     }
 
     fn visit_struct(&mut self, s: &'ast Struct) {
@@ -209,9 +265,52 @@ impl<'x, 'ast> Visitor<'ast> for GlobalDef<'x, 'ast> {
 
         self.map_struct_defs.insert(s.id, id);
 
-        if let Some(sym) = self.ctxt.sym.lock().insert(s.name, sym) {
-            report(self.ctxt, s.name, s.pos, sym);
-        }
+        self.insert(s.name, s.pos, sym, s.is_pub);
+    }
+
+    fn visit_enum(&mut self, e: &'ast Enum) {
+        let id = {
+            let mut enums = self.ctxt.enums.lock();
+            let id: EnumId = (enums.len() as u32).into();
+            let xenum = EnumData {
+                id: id,
+                pos: e.pos,
+                name: e.name,
+                variants: Vec::new(),
+            };
+
+            enums.push(Arc::new(Mutex::new(xenum)));
+
+            id
+        };
+
+        let sym = SymEnum(id);
+
+        self.map_enum_defs.insert(e.id, id);
+
+        self.insert(e.name, e.pos, sym, e.is_pub);
+    }
+
+    fn visit_type_alias(&mut self, a: &'ast TypeAlias) {
+        let id: TypeAliasId = (self.ctxt.type_aliases.len() as u32).into();
+        let type_params = match a.type_params {
+            Some(ref type_params) => type_params.iter().map(|p| p.name).collect(),
+            None => Vec::new(),
+        };
+
+        let alias = TypeAliasData {
+            id: id,
+            ast: a,
+            pos: a.pos,
+            name: a.name,
+            module: self.ctxt.sym.lock().module(),
+            type_params: type_params,
+        };
+
+        self.ctxt.type_aliases.push(alias);
+        let sym = SymTypeAlias(id);
+
+        self.insert(a.name, a.pos, sym, a.is_pub);
     }
 
     fn visit_fct(&mut self, f: &'ast Function) {
@@ -226,19 +325,21 @@ impl<'x, 'ast> Visitor<'ast> for GlobalDef<'x, 'ast> {
             pos: f.pos,
             ast: f,
             name: f.name,
+            module: self.ctxt.sym.lock().module(),
             param_types: Vec::new(),
             return_type: BuiltinType::Unit,
             parent: FctParent::None,
             has_override: f.has_override,
             has_open: f.has_open,
             has_final: f.has_final,
-            is_pub: true,
+            is_pub: f.is_pub,
             is_static: false,
             is_abstract: false,
             internal: f.internal,
             internal_resolved: false,
             overrides: None,
             throws: f.throws,
+            is_lambda: false,
             ctor: CtorType::None,
             vtable_index: None,
             initialized: false,
@@ -248,22 +349,27 @@ impl<'x, 'ast> Visitor<'ast> for GlobalDef<'x, 'ast> {
             kind: kind,
         };
 
-        if let Err(sym) = self.ctxt.add_fct_to_sym(fct) {
-            report(self.ctxt, f.name, f.pos, sym);
+        match self.ctxt.add_fct_to_sym(fct) {
+            Ok(_) if f.is_pub => self.ctxt.sym.lock().set_public(f.name),
+            Ok(_) => {}
+            Err(sym) => report(self.ctxt, f.name, f.pos, sym),
         }
     }
 }
 
-fn report(ctxt: &SemContext, name: Name, pos: Position, sym: Sym) {
+pub fn report(ctxt: &SemContext, name: Name, pos: Position, sym: Sym) {
     let name = ctxt.interner.str(name).to_string();
 
     let msg = match sym {
         SymClass(_) => Msg::ShadowClass(name),
         SymStruct(_) => Msg::ShadowStruct(name),
+        SymEnum(_) => Msg::ShadowEnum(name),
         SymFct(_) => Msg::ShadowFunction(name),
         SymTrait(_) => Msg::ShadowTrait(name),
         SymGlobal(_) => Msg::ShadowGlobal(name),
         SymConst(_) => Msg::ShadowConst(name),
+        SymTypeAlias(_) => Msg::ShadowTypeAlias(name),
+        SymModule(_) => Msg::ShadowModule(name),
         _ => unimplemented!(),
     };
 
@@ -295,6 +401,36 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_type_alias() {
+        ok("type Foo = int;");
+        err(
+            "type Foo = int; class Foo",
+            pos(1, 17),
+            Msg::ShadowTypeAlias("Foo".into()),
+        );
+        err(
+            "class Foo type Foo = int;",
+            pos(1, 11),
+            Msg::ShadowClass("Foo".into()),
+        );
+    }
+
+    #[test]
+    fn test_enum() {
+        ok("enum Foo { A, B }");
+        err(
+            "enum Foo { A } enum Foo { B }",
+            pos(1, 16),
+            Msg::ShadowEnum("Foo".into()),
+        );
+        err(
+            "enum Foo { A } class Foo",
+            pos(1, 16),
+            Msg::ShadowEnum("Foo".into()),
+        );
+    }
+
     #[test]
     fn test_trait() {
         ok("trait Foo {}");
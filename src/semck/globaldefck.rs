@@ -24,12 +24,17 @@ struct GlobalDefCheck<'a, 'ast: 'a> {
 
 impl<'a, 'ast> Visitor<'ast> for GlobalDefCheck<'a, 'ast> {
     fn visit_file(&mut self, f: &'ast File) {
+        let module = self.ctxt.file_module(f);
+        self.ctxt.sym.lock().set_module(module);
+
         for e in &f.elements {
             match *e {
                 ElemGlobal(ref g) => self.visit_global(g),
                 _ => {}
             }
         }
+
+        self.ctxt.sym.lock().set_module(None);
     }
 
     fn visit_global(&mut self, g: &'ast Global) {
@@ -34,6 +34,14 @@ impl<'x, 'ast> ImplCheck<'x, 'ast> {
 }
 
 impl<'x, 'ast> Visitor<'ast> for ImplCheck<'x, 'ast> {
+    fn visit_file(&mut self, f: &'ast ast::File) {
+        let module = self.ctxt.file_module(f);
+
+        self.ctxt.sym.lock().set_module(module);
+        visit::walk_file(self, f);
+        self.ctxt.sym.lock().set_module(None);
+    }
+
     fn visit_impl(&mut self, i: &'ast ast::Impl) {
         self.impl_id = Some(*self.map_impl_defs.get(i.id).unwrap());
 
@@ -85,6 +93,7 @@ impl<'x, 'ast> Visitor<'ast> for ImplCheck<'x, 'ast> {
             ast: f,
             pos: f.pos,
             name: f.name,
+            module: self.ctxt.sym.lock().module(),
             param_types: Vec::new(),
             return_type: BuiltinType::Unit,
             parent: FctParent::Impl(self.impl_id.unwrap()),
@@ -98,6 +107,7 @@ impl<'x, 'ast> Visitor<'ast> for ImplCheck<'x, 'ast> {
             internal_resolved: false,
             overrides: None,
             throws: f.throws,
+            is_lambda: false,
             ctor: ast::CtorType::None,
             vtable_index: None,
             initialized: false,
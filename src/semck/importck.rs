@@ -0,0 +1,120 @@
+use ctxt::SemContext;
+use dora_parser::ast::visit::*;
+use dora_parser::ast::*;
+use dora_parser::error::msg::Msg;
+use semck;
+use semck::globaldef::report;
+
+pub fn check<'ast>(ctxt: &SemContext<'ast>) {
+    let mut importck = ImportCheck { ctxt: ctxt };
+
+    importck.visit_ast(ctxt.ast);
+}
+
+struct ImportCheck<'x, 'ast: 'x> {
+    ctxt: &'x SemContext<'ast>,
+}
+
+impl<'x, 'ast> Visitor<'ast> for ImportCheck<'x, 'ast> {
+    fn visit_file(&mut self, f: &'ast File) {
+        let module = self.ctxt.file_module(f);
+
+        self.ctxt.sym.lock().set_module(module);
+        walk_file(self, f);
+        self.ctxt.sym.lock().set_module(None);
+    }
+
+    fn visit_import(&mut self, import: &'ast Import) {
+        let (module, idx) = semck::module_prefix(self.ctxt, &import.path);
+        let name = *import.path.last().unwrap();
+
+        // all segments but the last one need to name modules
+        let sym = if idx + 1 == import.path.len() {
+            semck::module_item(self.ctxt, module, name, import.pos)
+        } else {
+            None
+        };
+
+        let sym = match sym {
+            Some(sym) => sym,
+            None => {
+                let path = import
+                    .path
+                    .iter()
+                    .map(|&name| self.ctxt.interner.str(name).to_string())
+                    .collect::<Vec<_>>()
+                    .join("::");
+                let msg = Msg::UnknownImport(path);
+                self.ctxt.diag.lock().report(import.pos, msg);
+                return;
+            }
+        };
+
+        if let Some(sym) = self.ctxt.sym.lock().insert_import(name, sym) {
+            report(self.ctxt, name, import.pos, sym);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dora_parser::error::msg::Msg;
+    use semck::tests::*;
+
+    #[test]
+    fn import_fct() {
+        ok_modules(&[
+            ("", "import util::f; fun main() { f(); util::f(); }"),
+            ("util", "pub fun f() {}"),
+        ]);
+        ok_modules(&[
+            (
+                "",
+                "import foo::bar; fun main() { bar::f(); foo::bar::f(); }",
+            ),
+            ("foo::bar", "pub fun f() {}"),
+        ]);
+    }
+
+    #[test]
+    fn import_unknown() {
+        err_modules(
+            &[
+                ("", "import util::g; fun main() {}"),
+                ("util", "pub fun f() {}"),
+            ],
+            pos(1, 1),
+            Msg::UnknownImport("util::g".into()),
+        );
+        err_modules(
+            &[("", "import foo::f; fun main() {}")],
+            pos(1, 1),
+            Msg::UnknownImport("foo::f".into()),
+        );
+    }
+
+    #[test]
+    fn import_private() {
+        err_modules(
+            &[
+                ("", "import util::f; fun main() {}"),
+                ("util", "fun f() {}"),
+            ],
+            pos(1, 1),
+            Msg::NotAccessible("util::f".into()),
+        );
+    }
+
+    #[test]
+    fn import_shadows_item() {
+        err_modules(
+            &[
+                ("", "fun main() {}"),
+                ("a", "import b::f; fun f() {}"),
+                ("b", "pub fun f() {}"),
+            ],
+            pos(1, 1),
+            Msg::ShadowFunction("f".into()),
+        );
+    }
+}
@@ -0,0 +1,856 @@
+use std::collections::{HashMap, HashSet};
+
+use ctxt::{Fct, FctId, FctParent, FctSrc, IdentType, SemContext, VarId};
+use dora_parser::ast::visit::*;
+use dora_parser::ast::Expr::*;
+use dora_parser::ast::Stmt::*;
+use dora_parser::ast::*;
+use dora_parser::error::msg::Msg;
+use dora_parser::lexer::position::Position;
+use driver::cmd::Args;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Lint {
+    UnusedVariable,
+    UnusedAssignment,
+    UnreachableCode,
+    UnnecessaryVar,
+    UnusedImport,
+}
+
+impl Lint {
+    pub fn name(self) -> &'static str {
+        match self {
+            Lint::UnusedVariable => "unused-variable",
+            Lint::UnusedAssignment => "unused-assignment",
+            Lint::UnreachableCode => "unreachable-code",
+            Lint::UnnecessaryVar => "unnecessary-var",
+            Lint::UnusedImport => "unused-import",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Lint> {
+        match name {
+            "unused-variable" => Some(Lint::UnusedVariable),
+            "unused-assignment" => Some(Lint::UnusedAssignment),
+            "unreachable-code" => Some(Lint::UnreachableCode),
+            "unnecessary-var" => Some(Lint::UnnecessaryVar),
+            "unused-import" => Some(Lint::UnusedImport),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum Level {
+    Allow,
+    Warn,
+    Deny,
+}
+
+// lints given to --allow and --deny as comma-separated list
+fn lint_names(list: &Option<String>) -> Vec<&str> {
+    match *list {
+        Some(ref list) => list.split(',').map(|name| name.trim()).collect(),
+        None => Vec::new(),
+    }
+}
+
+// returns the first unknown lint name given on the command line
+pub fn check_args(args: &Args) -> Result<(), String> {
+    let names = lint_names(&args.flag_allow)
+        .into_iter()
+        .chain(lint_names(&args.flag_deny));
+
+    for name in names {
+        if Lint::from_name(name).is_none() {
+            return Err(name.to_string());
+        }
+    }
+
+    Ok(())
+}
+
+fn level(args: &Args, lint: Lint) -> Level {
+    if lint_names(&args.flag_deny).contains(&lint.name()) {
+        Level::Deny
+    } else if lint_names(&args.flag_allow).contains(&lint.name()) {
+        Level::Allow
+    } else if args.flag_deny_warnings {
+        Level::Deny
+    } else {
+        Level::Warn
+    }
+}
+
+fn report_lint<'ast>(ctxt: &SemContext<'ast>, lint: Lint, pos: Position, msg: Msg) {
+    match level(&ctxt.args, lint) {
+        Level::Allow => {}
+        Level::Warn => ctxt.diag.lock().warn(pos, msg),
+        Level::Deny => ctxt.diag.lock().report(pos, msg),
+    }
+}
+
+pub fn check<'ast>(ctxt: &SemContext<'ast>) {
+    let mut user_fcts = UserFcts {
+        ids: HashSet::new(),
+    };
+
+    // the standard library is not linted
+    for file in &ctxt.ast.files {
+        if !file.path.starts_with("stdlib/") {
+            user_fcts.visit_file(file);
+            check_imports(ctxt, file);
+        }
+    }
+
+    for fct in ctxt.fcts.iter() {
+        let fct = fct.read();
+
+        // lambdas are checked together with their enclosing function
+        if !fct.is_src() || fct.is_lambda || !user_fcts.ids.contains(&fct.ast.id) {
+            continue;
+        }
+
+        check_fct(ctxt, &fct);
+    }
+}
+
+// imported names are marked as used when the symbol table finds them
+fn check_imports<'ast>(ctxt: &SemContext<'ast>, file: &'ast File) {
+    let module = ctxt.file_module(file);
+
+    for import in file.elements.iter().filter_map(|elem| elem.to_import()) {
+        let name = *import.path.last().unwrap();
+
+        if !ctxt.sym.lock().import_used(module, name) {
+            let path = import
+                .path
+                .iter()
+                .map(|&name| ctxt.interner.str(name).to_string())
+                .collect::<Vec<_>>()
+                .join("::");
+            report_lint(
+                ctxt,
+                Lint::UnusedImport,
+                import.pos,
+                Msg::UnusedImport(path),
+            );
+        }
+    }
+}
+
+fn check_fct<'ast>(ctxt: &SemContext<'ast>, fct: &Fct<'ast>) {
+    let src = fct.src();
+    let src = src.read();
+
+    let mut lintck = LintCheck {
+        ctxt: ctxt,
+        fct: fct,
+        src: &src,
+        state: Some(HashSet::new()),
+        writes: Vec::new(),
+        write_ids: HashMap::new(),
+        reads: vec![false; src.vars.len()],
+        reassigned: vec![false; src.vars.len()],
+        deferred: HashSet::new(),
+        decls: Vec::new(),
+        loops: Vec::new(),
+        tries: Vec::new(),
+        reached: HashSet::new(),
+        unreachable: Vec::new(),
+        lambdas: Vec::new(),
+    };
+
+    lintck.check();
+
+    for fct_id in lintck.lambdas {
+        let lambda = ctxt.fcts.idx(fct_id);
+        let lambda = lambda.read();
+
+        check_fct(ctxt, &lambda);
+    }
+}
+
+struct UserFcts {
+    ids: HashSet<NodeId>,
+}
+
+impl<'ast> Visitor<'ast> for UserFcts {
+    fn visit_fct(&mut self, f: &'ast Function) {
+        self.ids.insert(f.id);
+    }
+
+    fn visit_ctor(&mut self, f: &'ast Function) {
+        self.ids.insert(f.id);
+    }
+
+    fn visit_method(&mut self, f: &'ast Function) {
+        self.ids.insert(f.id);
+    }
+}
+
+// assignments that may reach the current point without being read,
+// None if the current point is unreachable
+type State = Option<HashSet<usize>>;
+
+enum DeclKind {
+    Local(bool),
+    Param,
+    Loop,
+}
+
+struct Decl {
+    var: VarId,
+    pos: Position,
+    kind: DeclKind,
+}
+
+struct Write {
+    var: VarId,
+    pos: Position,
+    used: bool,
+}
+
+struct LoopState {
+    breaks: State,
+    continues: State,
+}
+
+struct LintCheck<'a, 'ast: 'a> {
+    ctxt: &'a SemContext<'ast>,
+    fct: &'a Fct<'ast>,
+    src: &'a FctSrc,
+
+    state: State,
+    writes: Vec<Write>,
+    write_ids: HashMap<NodeId, usize>,
+
+    reads: Vec<bool>,
+    reassigned: Vec<bool>,
+
+    // variables read in defer, these are read when leaving the function
+    deferred: HashSet<VarId>,
+
+    decls: Vec<Decl>,
+    loops: Vec<LoopState>,
+
+    // states that can reach the catch and finally blocks of enclosing do-statements
+    tries: Vec<State>,
+
+    // statements that are reachable in some iteration of the analysis
+    reached: HashSet<NodeId>,
+    unreachable: Vec<(NodeId, Position)>,
+
+    lambdas: Vec<FctId>,
+}
+
+impl<'a, 'ast> LintCheck<'a, 'ast> {
+    fn check(&mut self) {
+        let ast = self.fct.ast;
+
+        for param in &ast.params {
+            if let Some(&var) = self.src.map_vars.get(param.id) {
+                self.decl(var, param.pos, DeclKind::Param);
+            }
+        }
+
+        self.visit_stmt(ast.block());
+        self.report();
+    }
+
+    fn report(&self) {
+        let mut lints = Vec::new();
+
+        for &(id, pos) in &self.unreachable {
+            if !self.reached.contains(&id) {
+                lints.push((pos, Lint::UnreachableCode, Msg::UnreachableCode));
+            }
+        }
+
+        for decl in &self.decls {
+            let var = &self.src.vars[decl.var];
+            let name = self.ctxt.interner.str(var.name).to_string();
+
+            if name.starts_with('_') || name == "self" {
+                continue;
+            }
+
+            if !self.reads[decl.var.0] {
+                match decl.kind {
+                    DeclKind::Param if !self.check_params() => {}
+                    DeclKind::Param => {
+                        lints.push((decl.pos, Lint::UnusedVariable, Msg::UnusedParam(name)))
+                    }
+                    _ => lints.push((decl.pos, Lint::UnusedVariable, Msg::UnusedVariable(name))),
+                }
+
+                continue;
+            }
+
+            if let DeclKind::Local(true) = decl.kind {
+                if var.reassignable && !self.reassigned[decl.var.0] {
+                    let msg = Msg::VarNeverReassigned(name.clone());
+                    lints.push((decl.pos, Lint::UnnecessaryVar, msg));
+                }
+            }
+
+            if self.deferred.contains(&decl.var) {
+                continue;
+            }
+
+            for write in self.writes.iter().filter(|w| w.var == decl.var && !w.used) {
+                let msg = Msg::UnusedAssignment(name.clone());
+                lints.push((write.pos, Lint::UnusedAssignment, msg));
+            }
+        }
+
+        lints.sort_by_key(|&(pos, _, _)| (pos.line, pos.column));
+
+        for (pos, lint, msg) in lints {
+            self.lint(lint, pos, msg);
+        }
+    }
+
+    // the signature of lambdas and methods implementing or overriding
+    // other methods is given, their params may be unused
+    fn check_params(&self) -> bool {
+        let fct = self.fct;
+
+        match fct.parent {
+            FctParent::Impl(_) | FctParent::Trait(_) => false,
+            _ => !fct.is_lambda && !fct.has_open && !fct.has_override && !fct.is_abstract,
+        }
+    }
+
+    fn lint(&self, lint: Lint, pos: Position, msg: Msg) {
+        report_lint(self.ctxt, lint, pos, msg);
+    }
+
+    fn decl(&mut self, var: VarId, pos: Position, kind: DeclKind) {
+        // loop bodies are visited more than once
+        if self.decls.iter().any(|decl| decl.var == var) {
+            return;
+        }
+
+        self.decls.push(Decl {
+            var: var,
+            pos: pos,
+            kind: kind,
+        });
+    }
+
+    fn read(&mut self, var: VarId) {
+        self.reads[var.0] = true;
+
+        if let Some(ref state) = self.state {
+            for &idx in state {
+                if self.writes[idx].var == var {
+                    self.writes[idx].used = true;
+                }
+            }
+        }
+    }
+
+    fn write(&mut self, id: NodeId, var: VarId, pos: Position) {
+        let writes = &mut self.writes;
+        let idx = *self.write_ids.entry(id).or_insert_with(|| {
+            writes.push(Write {
+                var: var,
+                pos: pos,
+                used: false,
+            });
+
+            writes.len() - 1
+        });
+
+        if let Some(ref mut state) = self.state {
+            state.retain(|&other| writes[other].var != var);
+            state.insert(idx);
+        }
+    }
+
+    // the current state can reach catch and finally blocks
+    fn may_throw(&mut self) {
+        for state in self.tries.iter_mut() {
+            *state = join(state.take(), self.state.clone());
+        }
+    }
+
+    fn visit_block(&mut self, block: &'ast StmtBlockType) {
+        let mut reported = false;
+
+        for stmt in &block.stmts {
+            if self.state.is_some() {
+                self.reached.insert(stmt.id());
+            } else if !reported {
+                if !self.unreachable.iter().any(|&(id, _)| id == stmt.id()) {
+                    self.unreachable.push((stmt.id(), stmt.pos()));
+                }
+
+                reported = true;
+            }
+
+            self.visit_stmt(stmt);
+        }
+    }
+
+    fn visit_if(&mut self, stmt: &'ast StmtIfType) {
+        self.visit_expr(&stmt.cond);
+        let cond = self.state.clone();
+
+        self.visit_stmt(&stmt.then_block);
+        let then_state = self.state.take();
+
+        self.state = cond;
+
+        if let Some(ref else_block) = stmt.else_block {
+            self.visit_stmt(else_block);
+        }
+
+        self.state = join(then_state, self.state.take());
+    }
+
+    // iterates the loop until the state at the loop header does not change,
+    // `exits` is false for loops only left through break
+    fn visit_loop(&mut self, cond: Option<&'ast Expr>, exits: bool, block: &'ast Stmt) {
+        let entry = self.state.clone();
+        let mut header = entry.clone();
+
+        loop {
+            self.state = header.clone();
+
+            if let Some(cond) = cond {
+                self.visit_expr(cond);
+            }
+
+            let exit = self.state.clone();
+
+            self.loops.push(LoopState {
+                breaks: None,
+                continues: None,
+            });
+
+            self.visit_stmt(block);
+
+            let loop_state = self.loops.pop().unwrap();
+            let next = join(join(entry.clone(), self.state.take()), loop_state.continues);
+
+            if next == header {
+                self.state = if exits {
+                    join(exit, loop_state.breaks)
+                } else {
+                    loop_state.breaks
+                };
+
+                break;
+            }
+
+            header = next;
+        }
+    }
+
+    fn visit_for(&mut self, stmt: &'ast StmtForType) {
+        self.visit_expr(&stmt.expr);
+
+        if let Some(&var) = self.src.map_vars.get(stmt.id) {
+            self.decl(var, stmt.pos, DeclKind::Loop);
+        }
+
+        self.visit_loop(None, true, &stmt.block);
+    }
+
+    fn visit_do(&mut self, stmt: &'ast StmtDoType) {
+        self.tries.push(self.state.clone());
+        self.visit_stmt(&stmt.do_block);
+        let do_throws = self.tries.pop().unwrap();
+
+        let mut state = self.state.take();
+
+        self.tries.push(None);
+
+        for catch in &stmt.catch_blocks {
+            self.state = do_throws.clone();
+            self.visit_stmt(&catch.block);
+            state = join(state, self.state.take());
+        }
+
+        let catch_throws = self.tries.pop().unwrap();
+
+        self.state = state;
+
+        if let Some(ref finally_block) = stmt.finally_block {
+            let normal = self.state.take();
+            let throws = join(do_throws, catch_throws);
+
+            // finally is also executed when leaving with an exception
+            self.state = join(normal, throws);
+            self.visit_stmt(&finally_block.block);
+        }
+    }
+
+    fn visit_var(&mut self, stmt: &'ast StmtVarType) {
+        let var = match self.src.map_vars.get(stmt.id) {
+            Some(&var) => var,
+            None => return,
+        };
+
+        self.decl(var, stmt.pos, DeclKind::Local(stmt.expr.is_some()));
+
+        if let Some(ref expr) = stmt.expr {
+            self.visit_expr(expr);
+            self.write(stmt.id, var, stmt.pos);
+        }
+    }
+
+    fn visit_let_tuple(&mut self, stmt: &'ast StmtLetTupleType) {
+        self.visit_expr(&stmt.expr);
+
+        for binding in &stmt.bindings {
+            if let Some(&var) = self.src.map_vars.get(binding.id) {
+                // a tuple is always destructured into all its bindings
+                self.decl(var, binding.pos, DeclKind::Local(false));
+                self.write(binding.id, var, binding.pos);
+            }
+        }
+    }
+
+    fn visit_defer(&mut self, stmt: &'ast StmtDeferType) {
+        let mut reads = VarReads {
+            src: self.src,
+            vars: HashSet::new(),
+        };
+
+        reads.visit_expr(&stmt.expr);
+        self.deferred.extend(reads.vars);
+
+        self.visit_expr(&stmt.expr);
+    }
+
+    fn visit_assign(&mut self, expr: &'ast ExprAssignType) {
+        let var = match *expr.lhs {
+            ExprIdent(ref ident) => match self.src.map_idents.get(ident.id) {
+                Some(&IdentType::Var(var)) => Some(var),
+                _ => None,
+            },
+            _ => None,
+        };
+
+        let var = match var {
+            Some(var) => var,
+            None => {
+                // storing into a field of a struct or tuple modifies the variable
+                if let Some(var) = self.value_var(&expr.lhs) {
+                    self.reassigned[var.0] = true;
+                }
+
+                self.visit_expr(&expr.lhs);
+                self.visit_expr(&expr.rhs);
+                return;
+            }
+        };
+
+        // compound assignments read the variable first
+        if expr.op.is_some() {
+            self.read(var);
+        }
+
+        self.visit_expr(&expr.rhs);
+        self.reassigned[var.0] = true;
+        self.write(expr.id, var, expr.pos);
+    }
+
+    // variable of struct or tuple type the field access is based on
+    fn value_var(&self, expr: &Expr) -> Option<VarId> {
+        let object = match *expr {
+            ExprField(ref field) => &field.object,
+            ExprTupleElement(ref element) => &element.object,
+            ExprIdent(ref ident) => {
+                return match self.src.map_idents.get(ident.id) {
+                    Some(&IdentType::Var(var)) => Some(var),
+                    _ => None,
+                };
+            }
+            _ => return None,
+        };
+
+        let var = self.value_var(object)?;
+
+        if self.src.vars[var].ty.is_struct_or_tuple() {
+            Some(var)
+        } else {
+            None
+        }
+    }
+
+    fn visit_lambda(&mut self, expr: &'ast ExprLambdaType) {
+        let info = match self.src.map_lambdas.get(expr.id) {
+            Some(info) => info,
+            None => return,
+        };
+
+        // captured values are copied into the closure when it is created
+        for capture in &info.captures {
+            if let IdentType::Var(var) = *capture {
+                self.read(var);
+            }
+        }
+
+        self.lambdas.push(info.fct_id);
+    }
+}
+
+struct VarReads<'a> {
+    src: &'a FctSrc,
+    vars: HashSet<VarId>,
+}
+
+impl<'a, 'ast> Visitor<'ast> for VarReads<'a> {
+    fn visit_expr(&mut self, e: &'ast Expr) {
+        if let ExprIdent(ref ident) = *e {
+            if let Some(&IdentType::Var(var)) = self.src.map_idents.get(ident.id) {
+                self.vars.insert(var);
+            }
+        }
+
+        visit::walk_expr(self, e);
+    }
+}
+
+fn join(lhs: State, rhs: State) -> State {
+    match (lhs, rhs) {
+        (Some(mut lhs), Some(rhs)) => {
+            lhs.extend(rhs);
+            Some(lhs)
+        }
+
+        (Some(lhs), None) => Some(lhs),
+        (None, rhs) => rhs,
+    }
+}
+
+impl<'a, 'ast> Visitor<'ast> for LintCheck<'a, 'ast> {
+    fn visit_stmt(&mut self, s: &'ast Stmt) {
+        match *s {
+            StmtBlock(ref stmt) => self.visit_block(stmt),
+            StmtIf(ref stmt) => self.visit_if(stmt),
+            StmtWhile(ref stmt) => self.visit_loop(Some(&stmt.cond), true, &stmt.block),
+            StmtLoop(ref stmt) => self.visit_loop(None, false, &stmt.block),
+            StmtFor(ref stmt) => self.visit_for(stmt),
+            StmtDo(ref stmt) => self.visit_do(stmt),
+            StmtVar(ref stmt) => self.visit_var(stmt),
+            StmtLetTuple(ref stmt) => self.visit_let_tuple(stmt),
+            StmtDefer(ref stmt) => self.visit_defer(stmt),
+
+            StmtBreak(_) => {
+                self.may_throw();
+                let state = self.state.take();
+                let current = self.loops.last_mut().unwrap();
+                current.breaks = join(current.breaks.take(), state);
+            }
+
+            StmtContinue(_) => {
+                self.may_throw();
+                let state = self.state.take();
+                let current = self.loops.last_mut().unwrap();
+                current.continues = join(current.continues.take(), state);
+            }
+
+            StmtReturn(_) | StmtThrow(_) => {
+                visit::walk_stmt(self, s);
+                self.may_throw();
+                self.state = None;
+            }
+
+            StmtExpr(_) | StmtSpawn(_) | StmtError(_) => visit::walk_stmt(self, s),
+        }
+    }
+
+    fn visit_expr(&mut self, e: &'ast Expr) {
+        match *e {
+            ExprIdent(ref ident) => {
+                if let Some(&IdentType::Var(var)) = self.src.map_idents.get(ident.id) {
+                    self.read(var);
+                }
+            }
+
+            ExprCall(ref call) => {
+                // call of lambda stored in variable
+                if let Some(&IdentType::Var(var)) = self.src.map_idents.get(call.id) {
+                    self.read(var);
+                }
+
+                visit::walk_expr(self, e);
+                self.may_throw();
+            }
+
+            ExprBin(ref expr) if expr.op == BinOp::And || expr.op == BinOp::Or => {
+                // rhs is only evaluated depending on the value of lhs
+                self.visit_expr(&expr.lhs);
+                let lhs = self.state.clone();
+                self.visit_expr(&expr.rhs);
+                self.state = join(lhs, self.state.take());
+            }
+
+            ExprMatch(ref expr) => {
+                self.visit_expr(&expr.expr);
+
+                if !expr.arms.is_empty() {
+                    let entry = self.state.take();
+
+                    for arm in &expr.arms {
+                        let state = self.state.take();
+                        self.state = entry.clone();
+                        self.visit_expr(&arm.value);
+                        self.state = join(state, self.state.take());
+                    }
+                }
+            }
+
+            ExprAssign(ref expr) => self.visit_assign(expr),
+            ExprLambda(ref expr) => self.visit_lambda(expr),
+
+            ExprDelegation(_) | ExprTry(_) => {
+                visit::walk_expr(self, e);
+                self.may_throw();
+            }
+
+            _ => visit::walk_expr(self, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dora_parser::error::msg::Msg;
+    use semck::tests::*;
+
+    #[test]
+    fn lint_unused_variable() {
+        warnings(
+            "fun f(a: int, _b: int) { let x = 1; let _y = 2; }",
+            &[
+                (pos(1, 7), Msg::UnusedParam("a".into())),
+                (pos(1, 26), Msg::UnusedVariable("x".into())),
+            ],
+        );
+        warnings("fun f(a: int) -> int { let x = a; return x; }", &[]);
+        warnings(
+            "fun f() { for i in range(0, 10) { } }",
+            &[(pos(1, 11), Msg::UnusedVariable("i".into()))],
+        );
+    }
+
+    #[test]
+    fn lint_unused_param_in_method() {
+        warnings(
+            "open class A { open fun f(a: int) {} }
+            trait T { fun g(a: int); }
+            class B { fun h(a: int) {} }
+            impl T for B { fun g(a: int) {} }",
+            &[(pos(3, 29), Msg::UnusedParam("a".into()))],
+        );
+    }
+
+    #[test]
+    fn lint_captured_variable() {
+        warnings(
+            "fun f() -> int { let x = 1; let g = || -> int { return x; }; return g(); }",
+            &[],
+        );
+    }
+
+    #[test]
+    fn lint_unused_assignment() {
+        warnings(
+            "fun f() -> int { var x = 1; x = 2; return x; }",
+            &[(pos(1, 18), Msg::UnusedAssignment("x".into()))],
+        );
+        warnings(
+            "fun f(a: bool) -> int { var x = 1; if a { x = 2; } return x; }",
+            &[],
+        );
+        warnings(
+            "fun f() -> int { var x = 0; var i = 0; while i < 10 { x = x + i; i = i + 1; } return x; }",
+            &[],
+        );
+        warnings(
+            "fun f() -> int { var x = 1; x = 2; x = 3; return x; }",
+            &[
+                (pos(1, 18), Msg::UnusedAssignment("x".into())),
+                (pos(1, 31), Msg::UnusedAssignment("x".into())),
+            ],
+        );
+        warnings("fun f() -> int { var x = 1; x += 2; return x; }", &[]);
+    }
+
+    #[test]
+    fn lint_unused_assignment_with_exceptions() {
+        warnings(
+            "fun g() throws {} fun f() -> int {
+                var x = 1;
+                do { x = 2; try g(); x = 3; } catch e: Str { return x; }
+                return x;
+            }",
+            &[],
+        );
+        warnings(
+            "fun f() { var x = 1; defer println(x.toString()); x = 2; }",
+            &[],
+        );
+    }
+
+    #[test]
+    fn lint_unreachable_code() {
+        warnings(
+            "fun f() -> int { return 1; println(\"a\"); }",
+            &[(pos(1, 28), Msg::UnreachableCode)],
+        );
+        warnings(
+            "fun f() { throw \"a\"; println(\"a\"); println(\"b\"); }",
+            &[(pos(1, 22), Msg::UnreachableCode)],
+        );
+        warnings(
+            "fun f(a: bool) { if a { return; } else { return; } println(\"a\"); }",
+            &[(pos(1, 52), Msg::UnreachableCode)],
+        );
+        warnings("fun f() { while true { break; } println(\"a\"); }", &[]);
+        warnings(
+            "fun f() { loop { } println(\"a\"); }",
+            &[(pos(1, 20), Msg::UnreachableCode)],
+        );
+    }
+
+    #[test]
+    fn lint_unused_import() {
+        warnings_modules(
+            &[
+                (
+                    "",
+                    "import util::f; import util::g; import util::A; import foo::bar;
+                    fun main() { g(); bar::h(); }
+                    fun id(a: A) -> A { return a; }",
+                ),
+                (
+                    "util",
+                    "import foo::bar::h; pub fun f() {} pub fun g() { h(); } pub class A",
+                ),
+                ("foo::bar", "pub fun h() {}"),
+            ],
+            &[(pos(1, 1), Msg::UnusedImport("util::f".into()))],
+        );
+    }
+
+    #[test]
+    fn lint_var_never_reassigned() {
+        warnings(
+            "fun f() -> int { var x = 1; return x; }",
+            &[(pos(1, 18), Msg::VarNeverReassigned("x".into()))],
+        );
+        warnings("fun f() -> int { var x: int; x = 1; return x; }", &[]);
+        warnings(
+            "struct Foo { a: int } fun f() -> int { var x = Foo { a: 1 }; x.a = 2; return x.a; }",
+            &[],
+        );
+    }
+}
@@ -1,21 +1,30 @@
 use class::TypeParams;
-use ctxt::{NodeMap, SemContext};
+use ctxt::{ModuleId, NodeMap, SemContext, TypeAliasId};
 use dora_parser::ast::Type::{TypeBasic, TypeLambda, TypeSelf, TypeTuple};
-use dora_parser::ast::{Stmt, Type};
+use dora_parser::ast::{Stmt, Type, TypeBasicType};
 use dora_parser::error::msg::Msg;
+use dora_parser::interner::Name;
+use dora_parser::lexer::position::Position;
 use mem;
-use sym::Sym::{SymClass, SymClassTypeParam, SymFctTypeParam, SymStruct, SymTrait};
+use sym::Sym::{
+    self, SymClass, SymClassTypeParam, SymEnum, SymFctTypeParam, SymModule, SymStruct, SymTrait,
+    SymTypeAlias,
+};
 use ty::BuiltinType;
 
 mod abstractck;
+mod assignck;
 mod clsdefck;
 mod constdefck;
+mod enumdefck;
 mod fctdefck;
 mod flowck;
 mod globaldef;
 mod globaldefck;
 mod implck;
 mod impldefck;
+mod importck;
+pub mod lintck;
 mod nameck;
 mod prelude;
 mod returnck;
@@ -23,6 +32,7 @@ pub mod specialize;
 mod structdefck;
 mod superck;
 mod traitdefck;
+mod typealiasck;
 mod typeck;
 
 macro_rules! return_on_error {
@@ -36,6 +46,7 @@ macro_rules! return_on_error {
 pub fn check<'ast>(ctxt: &mut SemContext<'ast>) {
     let mut map_cls_defs = NodeMap::new(); // get ClassId from ast node
     let mut map_struct_defs = NodeMap::new(); // get StructId from ast node
+    let mut map_enum_defs = NodeMap::new(); // get EnumId from ast node
     let mut map_trait_defs = NodeMap::new(); // get TraitId from ast node
     let mut map_impl_defs = NodeMap::new(); // get ImplId from ast node
     let mut map_global_defs = NodeMap::new(); // get GlobalId from ast node
@@ -47,6 +58,7 @@ pub fn check<'ast>(ctxt: &mut SemContext<'ast>) {
         ctxt,
         &mut map_cls_defs,
         &mut map_struct_defs,
+        &mut map_enum_defs,
         &mut map_trait_defs,
         &mut map_impl_defs,
         &mut map_global_defs,
@@ -54,16 +66,27 @@ pub fn check<'ast>(ctxt: &mut SemContext<'ast>) {
     );
     return_on_error!(ctxt);
 
+    // resolve imports, all modules and their symbols are known now
+    importck::check(ctxt);
+    return_on_error!(ctxt);
+
+    // type aliases are expanded on every use,
+    // so cycles need to be rejected before any type is read
+    typealiasck::check_cycles(ctxt);
+    return_on_error!(ctxt);
+
     // define internal classes
     prelude::internal_classes(ctxt);
 
-    // checks class/struct/trait definitions/bodies
+    // checks class/struct/enum/trait definitions/bodies
     clsdefck::check(ctxt, &map_cls_defs);
     structdefck::check(ctxt, &map_struct_defs);
+    enumdefck::check(ctxt, &map_enum_defs);
     traitdefck::check(ctxt, &map_trait_defs);
     impldefck::check(ctxt, &map_impl_defs);
     globaldefck::check(ctxt, &map_global_defs);
     constdefck::check(ctxt, &map_const_defs);
+    typealiasck::check(ctxt);
     return_on_error!(ctxt);
 
     // check names/identifiers of local variables
@@ -96,6 +119,9 @@ pub fn check<'ast>(ctxt: &mut SemContext<'ast>) {
     // checks if function has a return value
     returnck::check(ctxt);
 
+    // are variables and fields assigned before they are used?
+    assignck::check(ctxt);
+
     // add size of super classes to field offsets
     superck::check(ctxt);
     return_on_error!(ctxt);
@@ -175,14 +201,124 @@ fn init_global_addresses<'ast>(ctxt: &SemContext<'ast>) {
     }
 }
 
+// follows the leading segments of a qualified path that name modules,
+// returns the innermost module and the index of the first other segment
+pub fn module_prefix<'ast>(ctxt: &SemContext<'ast>, path: &[Name]) -> (Option<ModuleId>, usize) {
+    let sym = ctxt.sym.lock();
+    let mut module = None;
+    let mut idx = 0;
+
+    while idx + 1 < path.len() {
+        let next = if module.is_none() {
+            sym.get(path[idx])
+        } else {
+            sym.get_in_module(module, path[idx])
+        };
+
+        match next {
+            Some(SymModule(id)) => {
+                module = Some(id);
+                idx += 1;
+            }
+
+            _ => break,
+        }
+    }
+
+    (module, idx)
+}
+
+// looks up a name in a module returned by module_prefix, symbols
+// of other modules can only be used when they are public
+pub fn module_item<'ast>(
+    ctxt: &SemContext<'ast>,
+    module: Option<ModuleId>,
+    name: Name,
+    pos: Position,
+) -> Option<Sym> {
+    let module = match module {
+        Some(module) => module,
+        None => return ctxt.sym.lock().get(name),
+    };
+
+    let (sym, accessible) = {
+        let table = ctxt.sym.lock();
+        let accessible = table.module() == Some(module) || table.is_public(module, name);
+
+        (table.get_in_module(Some(module), name), accessible)
+    };
+
+    if let Some(ref sym) = sym {
+        if !accessible && !sym.is_module() {
+            let msg = Msg::NotAccessible(ctxt.module_path(Some(module), name));
+            ctxt.diag.lock().report(pos, msg);
+        }
+    }
+
+    sym
+}
+
+// fields and methods of a class in another module can only be used when
+// they are public, members of the root module are accessible everywhere
+pub fn check_member_access<'ast>(
+    ctxt: &SemContext<'ast>,
+    cls_name: Name,
+    module: Option<ModuleId>,
+    is_pub: bool,
+    name: Name,
+    pos: Position,
+) {
+    if is_pub || module.is_none() || ctxt.sym.lock().module() == module {
+        return;
+    }
+
+    let path = format!(
+        "{}::{}",
+        ctxt.module_path(module, cls_name),
+        ctxt.interner.str(name)
+    );
+
+    ctxt.diag.lock().report(pos, Msg::NotAccessible(path));
+}
+
 pub fn read_type<'ast>(ctxt: &SemContext<'ast>, t: &'ast Type) -> Option<BuiltinType> {
+    read_type_with_alias_params(ctxt, t, &[])
+}
+
+// alias_params maps the type params of the type alias currently
+// being expanded to the types given at the use site
+fn read_type_with_alias_params<'ast>(
+    ctxt: &SemContext<'ast>,
+    t: &'ast Type,
+    alias_params: &[(Name, BuiltinType)],
+) -> Option<BuiltinType> {
     match *t {
         TypeSelf(_) => {
             return Some(BuiltinType::This);
         }
 
         TypeBasic(ref basic) => {
-            let sym = ctxt.sym.lock().get(basic.name);
+            let alias_param = if basic.path.is_empty() {
+                alias_params.iter().find(|p| p.0 == basic.name)
+            } else {
+                None
+            };
+
+            if let Some(&(_, ty)) = alias_param {
+                if basic.params.len() > 0 {
+                    let msg = Msg::NoTypeParamsExpected;
+                    ctxt.diag.lock().report(basic.pos, msg);
+                }
+
+                return Some(ty);
+            }
+
+            let sym = if basic.path.is_empty() {
+                ctxt.sym.lock().get(basic.name)
+            } else {
+                read_qualified_type_sym(ctxt, basic)
+            };
+
             if let Some(sym) = sym {
                 match sym {
                     SymClass(cls_id) => {
@@ -190,7 +326,7 @@ pub fn read_type<'ast>(ctxt: &SemContext<'ast>, t: &'ast Type) -> Option<Builtin
                             let mut type_params = Vec::new();
 
                             for param in &basic.params {
-                                let param = read_type(ctxt, param);
+                                let param = read_type_with_alias_params(ctxt, param, alias_params);
 
                                 if let Some(param) = param {
                                     type_params.push(param);
@@ -212,6 +348,11 @@ pub fn read_type<'ast>(ctxt: &SemContext<'ast>, t: &'ast Type) -> Option<Builtin
                             }
 
                             for (tp, ty) in cls.type_params.iter().zip(type_params.iter()) {
+                                // error types were already reported
+                                if ty.is_error() {
+                                    continue;
+                                }
+
                                 if let Some(cls_id) = tp.class_bound {
                                     let cls = ctxt.cls(cls_id);
 
@@ -275,6 +416,19 @@ pub fn read_type<'ast>(ctxt: &SemContext<'ast>, t: &'ast Type) -> Option<Builtin
                         return Some(BuiltinType::Struct(struct_id, list_id));
                     }
 
+                    SymEnum(enum_id) => {
+                        if basic.params.len() > 0 {
+                            let msg = Msg::NoTypeParamsExpected;
+                            ctxt.diag.lock().report(basic.pos, msg);
+                        }
+
+                        return Some(BuiltinType::Enum(enum_id));
+                    }
+
+                    SymTypeAlias(alias_id) => {
+                        return read_type_alias(ctxt, basic, alias_id, alias_params);
+                    }
+
                     SymClassTypeParam(cls_id, type_param_id) => {
                         if basic.params.len() > 0 {
                             let msg = Msg::NoTypeParamsExpected;
@@ -294,13 +448,13 @@ pub fn read_type<'ast>(ctxt: &SemContext<'ast>, t: &'ast Type) -> Option<Builtin
                     }
 
                     _ => {
-                        let name = ctxt.interner.str(basic.name).to_string();
+                        let name = t.to_string(&ctxt.interner);
                         let msg = Msg::ExpectedType(name);
                         ctxt.diag.lock().report(basic.pos, msg);
                     }
                 }
             } else {
-                let name = ctxt.interner.str(basic.name).to_string();
+                let name = t.to_string(&ctxt.interner);
                 let msg = Msg::UnknownType(name);
                 ctxt.diag.lock().report(basic.pos, msg);
             }
@@ -310,22 +464,43 @@ pub fn read_type<'ast>(ctxt: &SemContext<'ast>, t: &'ast Type) -> Option<Builtin
             return Some(BuiltinType::Unit);
         }
 
+        TypeTuple(ref tuple) if tuple.subtypes.len() == 1 => {
+            return read_type_with_alias_params(ctxt, &tuple.subtypes[0], alias_params);
+        }
+
+        TypeTuple(ref tuple) => {
+            let mut elements = Vec::with_capacity(tuple.subtypes.len());
+
+            for subtype in &tuple.subtypes {
+                if let Some(ty) = read_type_with_alias_params(ctxt, subtype, alias_params) {
+                    elements.push(ty);
+                } else {
+                    return None;
+                }
+            }
+
+            let tuple_id = ctxt.tuple_types.lock().insert(elements);
+
+            return Some(BuiltinType::Tuple(tuple_id));
+        }
+
         TypeLambda(ref lambda) => {
             let mut params = vec![];
 
             for param in &lambda.params {
-                if let Some(p) = read_type(ctxt, param) {
+                if let Some(p) = read_type_with_alias_params(ctxt, param, alias_params) {
                     params.push(p);
                 } else {
                     return None;
                 }
             }
 
-            let ret = if let Some(ret) = read_type(ctxt, &lambda.ret) {
-                ret
-            } else {
-                return None;
-            };
+            let ret =
+                if let Some(ret) = read_type_with_alias_params(ctxt, &lambda.ret, alias_params) {
+                    ret
+                } else {
+                    return None;
+                };
 
             let ty = ctxt.lambda_types.lock().insert(params, ret);
             let ty = BuiltinType::Lambda(ty);
@@ -339,6 +514,85 @@ pub fn read_type<'ast>(ctxt: &SemContext<'ast>, t: &'ast Type) -> Option<Builtin
     None
 }
 
+fn read_qualified_type_sym<'ast>(
+    ctxt: &SemContext<'ast>,
+    basic: &'ast TypeBasicType,
+) -> Option<Sym> {
+    let mut path = basic.path.clone();
+    path.push(basic.name);
+
+    // every segment but the type name needs to name a module
+    match module_prefix(ctxt, &path) {
+        (Some(module), idx) if idx == basic.path.len() => {
+            module_item(ctxt, Some(module), basic.name, basic.pos)
+        }
+
+        _ => None,
+    }
+}
+
+fn read_type_alias<'ast>(
+    ctxt: &SemContext<'ast>,
+    basic: &'ast TypeBasicType,
+    alias_id: TypeAliasId,
+    alias_params: &[(Name, BuiltinType)],
+) -> Option<BuiltinType> {
+    let alias = &ctxt.type_aliases[alias_id];
+    ctxt.type_alias_uses.lock().insert(basic.id);
+
+    if alias.type_params.len() != basic.params.len() {
+        let name = ctxt.interner.str(alias.name).to_string();
+        let msg =
+            Msg::WrongNumberTypeAliasParams(name, alias.type_params.len(), basic.params.len());
+        ctxt.diag.lock().report(basic.pos, msg);
+        return None;
+    }
+
+    let mut params = Vec::with_capacity(basic.params.len());
+
+    for (&name, param) in alias.type_params.iter().zip(&basic.params) {
+        if let Some(ty) = read_type_with_alias_params(ctxt, param, alias_params) {
+            params.push((name, ty));
+        } else {
+            return None;
+        }
+    }
+
+    // the aliased type is resolved in the module of the alias
+    let module = ctxt.sym.lock().module();
+    ctxt.sym.lock().set_module(alias.module);
+    let ty = read_type_with_alias_params(ctxt, &alias.ast.data_type, &params);
+    ctxt.sym.lock().set_module(module);
+
+    ty
+}
+
+// name of the type t resolved to ty for diagnostics, types that mention
+// a type alias are printed as written instead of the expanded type
+pub fn type_name<'ast>(ctxt: &SemContext<'ast>, t: &'ast Type, ty: BuiltinType) -> String {
+    if mentions_type_alias(ctxt, t) {
+        t.to_string(&ctxt.interner)
+    } else {
+        ty.name(ctxt)
+    }
+}
+
+fn mentions_type_alias<'ast>(ctxt: &SemContext<'ast>, t: &'ast Type) -> bool {
+    match *t {
+        TypeSelf(_) => false,
+        TypeBasic(ref basic) => {
+            ctxt.type_alias_uses.lock().contains(&basic.id)
+                || basic.params.iter().any(|p| mentions_type_alias(ctxt, p))
+        }
+        TypeTuple(ref tuple) => tuple.subtypes.iter().any(|t| mentions_type_alias(ctxt, t)),
+        TypeLambda(ref lambda) => {
+            lambda.params.iter().any(|p| mentions_type_alias(ctxt, p))
+                || mentions_type_alias(ctxt, &lambda.ret)
+        }
+        _ => false,
+    }
+}
+
 pub fn always_returns(s: &Stmt) -> bool {
     match returnck::returns_value(s) {
         Ok(_) => true,
@@ -388,6 +642,34 @@ mod tests {
         })
     }
 
+    pub fn ok_modules(files: &[(&'static str, &'static str)]) {
+        test::parse_modules_with_errors(files, |ctxt| {
+            let diag = ctxt.diag.lock();
+            let errors = diag.errors();
+
+            println!("errors = {:?}", errors);
+
+            for e in errors {
+                println!("{}", e.message());
+            }
+
+            assert!(!diag.has_errors());
+        });
+    }
+
+    pub fn err_modules(files: &[(&'static str, &'static str)], pos: Position, msg: Msg) {
+        test::parse_modules_with_errors(files, |ctxt| {
+            let diag = ctxt.diag.lock();
+            let errors = diag.errors();
+
+            println!("errors = {:?}", errors);
+
+            assert_eq!(1, errors.len());
+            assert_eq!(pos, errors[0].pos);
+            assert_eq!(msg, errors[0].msg);
+        });
+    }
+
     pub fn err(code: &'static str, pos: Position, msg: Msg) {
         test::parse_with_errors(code, |ctxt| {
             let diag = ctxt.diag.lock();
@@ -416,6 +698,26 @@ mod tests {
         });
     }
 
+    pub fn warnings(code: &'static str, vec: &[(Position, Msg)]) {
+        warnings_modules(&[("", code)], vec);
+    }
+
+    pub fn warnings_modules(files: &[(&'static str, &'static str)], vec: &[(Position, Msg)]) {
+        test::parse_modules_with_errors(files, |ctxt| {
+            let diag = ctxt.diag.lock();
+            let warnings = diag.warnings();
+
+            println!("warnings = {:?}", warnings);
+            assert!(!diag.has_errors());
+            assert_eq!(vec.len(), warnings.len());
+
+            for (ind, warning) in warnings.iter().enumerate() {
+                assert_eq!(vec[ind].0, warning.pos);
+                assert_eq!(vec[ind].1, warning.msg);
+            }
+        });
+    }
+
     pub fn pos(line: u32, col: u32) -> Position {
         Position::new(line, col)
     }
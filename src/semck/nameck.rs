@@ -1,7 +1,9 @@
+use parking_lot::RwLock;
+use std::collections::HashMap;
 use std::sync::Arc;
 
-use class::TypeParams;
-use ctxt::*;
+use class::{self, ClassId, Field, FieldId, TypeParams};
+use ctxt::{self, *};
 use dora_parser::error::msg::Msg;
 
 use dora_parser::ast::visit::*;
@@ -11,6 +13,7 @@ use dora_parser::ast::*;
 use dora_parser::interner::Name;
 use dora_parser::lexer::position::Position;
 
+use semck;
 use sym::Sym;
 use sym::Sym::*;
 use ty::BuiltinType;
@@ -19,7 +22,8 @@ pub fn check<'ast>(ctxt: &SemContext<'ast>) {
     for fct in ctxt.fcts.iter() {
         let fct = fct.read();
 
-        if !fct.is_src() {
+        // lambdas are checked together with their enclosing function
+        if !fct.is_src() || fct.is_lambda {
             continue;
         }
 
@@ -32,10 +36,15 @@ pub fn check<'ast>(ctxt: &SemContext<'ast>) {
             fct: &fct,
             src: &mut src,
             ast: ast,
+            level: 0,
+            outer_self: false,
         };
 
+        ctxt.sym.lock().set_module(fct.module);
         nameck.check();
     }
+
+    ctxt.sym.lock().set_module(None);
 }
 
 struct NameCheck<'a, 'ast: 'a> {
@@ -43,10 +52,17 @@ struct NameCheck<'a, 'ast: 'a> {
     fct: &'a Fct<'ast>,
     src: &'a mut FctSrc,
     ast: &'ast Function,
+
+    // symbol levels below this index belong to enclosing functions
+    level: usize,
+
+    // true if `self` of an enclosing method can be captured
+    outer_self: bool,
 }
 
 impl<'a, 'ast> NameCheck<'a, 'ast> {
     fn check(&mut self) {
+        self.level = self.ctxt.sym.lock().levels();
         self.ctxt.sym.lock().push_level();
 
         if self.fct.has_self() {
@@ -106,8 +122,11 @@ impl<'a, 'ast> NameCheck<'a, 'ast> {
 
         var.id = var_id;
 
-        let result = match self.ctxt.sym.lock().get(name) {
-            Some(sym) => {
+        let result = match self.ctxt.sym.lock().get_with_level(name) {
+            // variables of enclosing functions can always be shadowed
+            Some((SymVar(_), level)) if level < self.level => Ok(var_id),
+
+            Some((sym, _)) => {
                 if replacable(&sym) {
                     Ok(var_id)
                 } else {
@@ -153,6 +172,31 @@ impl<'a, 'ast> NameCheck<'a, 'ast> {
         }
     }
 
+    fn check_stmt_let_tuple(&mut self, tuple: &'ast StmtLetTupleType) {
+        self.visit_expr(&tuple.expr);
+
+        for binding in &tuple.bindings {
+            let var_ctxt = Var {
+                id: VarId(0),
+                name: binding.name,
+                reassignable: tuple.reassignable,
+                ty: BuiltinType::Unit,
+                node_id: binding.id,
+            };
+
+            match self.add_var(var_ctxt, |sym| !sym.is_class()) {
+                Ok(var_id) => {
+                    self.src.map_vars.insert(binding.id, var_id);
+                }
+
+                Err(_) => {
+                    let name = str(self.ctxt, binding.name);
+                    report(self.ctxt, binding.pos, Msg::ShadowClass(name));
+                }
+            }
+        }
+    }
+
     fn check_stmt_for(&mut self, for_loop: &'ast StmtForType) {
         self.visit_expr(&for_loop.expr);
 
@@ -217,6 +261,63 @@ impl<'a, 'ast> NameCheck<'a, 'ast> {
         }
     }
 
+    fn check_expr_match(&mut self, expr: &'ast ExprMatchType) {
+        self.visit_expr(&expr.expr);
+
+        for arm in &expr.arms {
+            self.ctxt.sym.lock().push_level();
+
+            match arm.pattern {
+                MatchPattern::Type(Some(name), _) => self.add_match_var(arm.id, arm.pos, name),
+
+                MatchPattern::Enum(ref path, ref bindings) => {
+                    self.check_match_enum(arm.id, arm.pos, path);
+
+                    for binding in bindings {
+                        if let Some(name) = binding.name {
+                            self.add_match_var(binding.id, binding.pos, name);
+                        }
+                    }
+                }
+
+                _ => {}
+            }
+
+            self.visit_expr(&arm.value);
+            self.ctxt.sym.lock().pop_level();
+        }
+    }
+
+    fn add_match_var(&mut self, id: NodeId, pos: Position, name: Name) {
+        let var_ctxt = Var {
+            id: VarId(0),
+            name: name,
+            ty: BuiltinType::Unit,
+            reassignable: false,
+            node_id: id,
+        };
+
+        match self.add_var(var_ctxt, |sym| !sym.is_class()) {
+            Ok(var_id) => {
+                self.src.map_vars.insert(id, var_id);
+            }
+
+            Err(_) => {
+                let name = str(self.ctxt, name);
+                report(self.ctxt, pos, Msg::ShadowClass(name));
+            }
+        }
+    }
+
+    // the variant of an enum pattern is stored as ident of the arm
+    fn check_match_enum(&mut self, id: NodeId, pos: Position, path: &Path) {
+        let (module, idx) = semck::module_prefix(self.ctxt, &path.path);
+        let name = path[idx];
+        let sym = semck::module_item(self.ctxt, module, name, pos);
+
+        self.check_enum_value(id, pos, path, idx, sym);
+    }
+
     fn check_stmt_block(&mut self, block: &'ast StmtBlockType) {
         self.ctxt.sym.lock().push_level();
         for stmt in &block.stmts {
@@ -225,26 +326,61 @@ impl<'a, 'ast> NameCheck<'a, 'ast> {
         self.ctxt.sym.lock().pop_level();
     }
 
+    fn ident_for_var(&mut self, name: Name, var_id: VarId, level: usize) -> IdentType {
+        if level >= self.level {
+            IdentType::Var(var_id)
+        } else {
+            self.capture(name)
+        }
+    }
+
+    // stores the value of a variable of an enclosing function
+    // in a field of the closure class
+    fn capture(&mut self, name: Name) -> IdentType {
+        let cls = self.ctxt.classes.idx(self.fct.cls_id());
+        let mut cls = cls.write();
+
+        let field_id = if let Some(idx) = cls.fields.iter().position(|f| f.name == name) {
+            FieldId::from(idx)
+        } else {
+            let id = FieldId::from(cls.fields.len());
+
+            cls.fields.push(Field {
+                id: id,
+                name: name,
+                ty: BuiltinType::Unit,
+                offset: 0,
+                reassignable: false,
+                is_pub: false,
+            });
+
+            id
+        };
+
+        IdentType::Field(cls.ty, field_id)
+    }
+
     fn check_expr_ident(&mut self, ident: &'ast ExprIdentType) {
-        let sym = self.ctxt.sym.lock().get(ident.name);
+        let sym = self.ctxt.sym.lock().get_with_level(ident.name);
 
         match sym {
-            Some(SymVar(id)) => {
-                self.src.map_idents.insert(ident.id, IdentType::Var(id));
+            Some((SymVar(id), level)) => {
+                let ident_type = self.ident_for_var(ident.name, id, level);
+                self.src.map_idents.insert(ident.id, ident_type);
                 return;
             }
 
-            Some(SymGlobal(id)) => {
+            Some((SymGlobal(id), _)) => {
                 self.src.map_idents.insert(ident.id, IdentType::Global(id));
                 return;
             }
 
-            Some(SymStruct(id)) => {
+            Some((SymStruct(id), _)) => {
                 self.src.map_idents.insert(ident.id, IdentType::Struct(id));
                 return;
             }
 
-            Some(SymConst(id)) => {
+            Some((SymConst(id), _)) => {
                 self.src.map_idents.insert(ident.id, IdentType::Const(id));
                 return;
             }
@@ -273,6 +409,40 @@ impl<'a, 'ast> NameCheck<'a, 'ast> {
         }
 
         if call.path.len() > 1 {
+            let (module, idx) = semck::module_prefix(self.ctxt, &call.path.path);
+            let name = call.path[idx];
+            let sym = semck::module_item(self.ctxt, module, name, call.pos);
+            let last = idx + 1 == call.path.len();
+
+            match sym {
+                // function or class of another module
+                Some(SymFct(fct_id)) if last => {
+                    let call_type = CallType::Fct(fct_id, TypeParams::empty(), TypeParams::empty());
+                    self.src.map_calls.insert(call.id, Arc::new(call_type));
+                }
+
+                Some(SymClass(cls_id)) if last => {
+                    let call_type = CallType::CtorNew(cls_id, FctId(0), TypeParams::empty());
+                    self.src.map_calls.insert(call.id, Arc::new(call_type));
+                }
+
+                // construction of enum value with payload
+                Some(SymEnum(enum_id)) if idx + 2 == call.path.len() => {
+                    if let Some(idx) = self.enum_variant(enum_id, call.path[idx + 1], call.pos) {
+                        let call_type = CallType::EnumValue(enum_id, idx);
+                        self.src.map_calls.insert(call.id, Arc::new(call_type));
+                    }
+                }
+
+                _ if last => {
+                    let name = self.ctxt.module_path(module, name);
+                    report(self.ctxt, call.pos, Msg::UnknownFunction(name));
+                }
+
+                // static method calls are resolved in typeck
+                _ => {}
+            }
+
             for arg in &call.args {
                 self.visit_expr(arg);
             }
@@ -281,8 +451,9 @@ impl<'a, 'ast> NameCheck<'a, 'ast> {
         }
 
         let name = call.path.name();
+        let sym = self.ctxt.sym.lock().get_with_level(name);
 
-        if let Some(sym) = self.ctxt.sym.lock().get(name) {
+        if let Some((sym, level)) = sym {
             match sym {
                 SymFct(fct_id) => {
                     let call_type = CallType::Fct(fct_id, TypeParams::empty(), TypeParams::empty());
@@ -296,6 +467,13 @@ impl<'a, 'ast> NameCheck<'a, 'ast> {
                     found = true;
                 }
 
+                SymVar(var_id) => {
+                    // call of lambda stored in variable
+                    let ident_type = self.ident_for_var(name, var_id, level);
+                    self.src.map_idents.insert(call.id, ident_type);
+                    found = true;
+                }
+
                 _ => {}
             }
         }
@@ -311,13 +489,287 @@ impl<'a, 'ast> NameCheck<'a, 'ast> {
         }
     }
 
+    fn check_expr_self(&mut self, expr: &'ast ExprSelfType) {
+        // `self` in a lambda refers to the object of the enclosing method
+        if self.fct.is_lambda && self.outer_self {
+            let name = self.ctxt.interner.intern("self");
+            let ident_type = self.capture(name);
+            self.src.map_idents.insert(expr.id, ident_type);
+        }
+    }
+
+    fn check_expr_lambda(&mut self, lambda: &'ast ExprLambdaType) {
+        let cls_id = self.add_lambda_class(lambda);
+        let fct_id = self.add_lambda_fct(lambda, cls_id);
+
+        {
+            let fct = self.ctxt.fcts.idx(fct_id);
+            let fct = fct.read();
+            let src = fct.src();
+            let mut src = src.write();
+
+            let outer_self = if self.fct.is_lambda {
+                self.outer_self
+            } else {
+                self.fct.has_self()
+            };
+
+            let mut nameck = NameCheck {
+                ctxt: self.ctxt,
+                fct: &fct,
+                src: &mut src,
+                ast: fct.ast,
+                level: 0,
+                outer_self: outer_self,
+            };
+
+            nameck.check();
+        }
+
+        let names = {
+            let cls = self.ctxt.classes.idx(cls_id);
+            let cls = cls.read();
+
+            cls.fields.iter().map(|f| f.name).collect::<Vec<_>>()
+        };
+
+        let captures = names
+            .into_iter()
+            .map(|name| self.ident_for_capture(name))
+            .collect();
+
+        self.src.map_lambdas.insert(
+            lambda.id,
+            LambdaInfo {
+                fct_id: fct_id,
+                cls_id: cls_id,
+                captures: captures,
+            },
+        );
+    }
+
+    // resolves a name captured by a lambda in the enclosing function
+    fn ident_for_capture(&mut self, name: Name) -> IdentType {
+        if name == self.ctxt.interner.intern("self") {
+            return if self.fct.is_lambda {
+                self.capture(name)
+            } else {
+                IdentType::Var(VarId(0))
+            };
+        }
+
+        let sym = self.ctxt.sym.lock().get_with_level(name);
+
+        match sym {
+            Some((SymVar(var_id), level)) => self.ident_for_var(name, var_id, level),
+            _ => unreachable!(),
+        }
+    }
+
+    fn add_lambda_class(&mut self, lambda: &'ast ExprLambdaType) -> ClassId {
+        let (type_params, params) = self.enclosing_type_params();
+        let list_id = self.ctxt.lists.lock().insert(params.into());
+
+        let mut classes = self.ctxt.classes.lock();
+        let id: ClassId = classes.len().into();
+
+        // the closure class is named after the enclosing function and
+        // is generic over the type params of the enclosing function
+        let cls = class::Class {
+            id: id,
+            name: self.fct.name,
+            pos: lambda.pos,
+            module: self.fct.module,
+            ty: BuiltinType::Class(id, list_id),
+            parent_class: None,
+            has_open: true,
+            is_abstract: false,
+            internal: false,
+            internal_resolved: false,
+            primary_ctor: false,
+
+            ctors: Vec::new(),
+            fields: Vec::new(),
+            methods: Vec::new(),
+
+            traits: Vec::new(),
+            impls: Vec::new(),
+
+            type_params: type_params,
+            specializations: RwLock::new(HashMap::new()),
+            vtable_len: 0,
+
+            is_array: false,
+            is_str: false,
+        };
+
+        classes.push(Arc::new(RwLock::new(cls)));
+
+        id
+    }
+
+    // type params of the function enclosing a lambda, lambdas nested in
+    // other lambdas use the type params of the outermost function
+    fn enclosing_type_params(&self) -> (Vec<ctxt::TypeParam>, Vec<BuiltinType>) {
+        if self.fct.is_lambda {
+            let cls = self.ctxt.classes.idx(self.fct.cls_id());
+            let cls = cls.read();
+
+            let params = match cls.ty {
+                BuiltinType::Class(_, list_id) => self.ctxt.lists.lock().get(list_id),
+                _ => unreachable!(),
+            };
+
+            return (cls.type_params.clone(), params.iter().collect());
+        }
+
+        let mut type_params = Vec::new();
+        let mut params = Vec::new();
+
+        if let Some(ref ast_type_params) = self.fct.ast.type_params {
+            for (idx, type_param) in ast_type_params.iter().enumerate() {
+                type_params.push(ctxt::TypeParam::new(type_param.name));
+                params.push(BuiltinType::FctTypeParam(self.fct.id, idx.into()));
+            }
+        }
+
+        (type_params, params)
+    }
+
+    fn add_lambda_fct(&mut self, lambda: &'ast ExprLambdaType, cls_id: ClassId) -> FctId {
+        let ast = &*lambda.fct;
+
+        // the lambda body is the only virtual method of the closure
+        // class and is therefore invoked through vtable index 0
+        let mut fct = Fct {
+            id: FctId(0),
+            pos: ast.pos,
+            ast: ast,
+            name: ast.name,
+            module: self.fct.module,
+            param_types: Vec::new(),
+            return_type: BuiltinType::Unit,
+            parent: FctParent::Class(cls_id),
+            has_override: false,
+            has_open: true,
+            has_final: false,
+            is_pub: false,
+            is_static: false,
+            is_abstract: false,
+            internal: false,
+            internal_resolved: false,
+            overrides: None,
+            throws: false,
+            is_lambda: true,
+            ctor: CtorType::None,
+            vtable_index: None,
+            initialized: false,
+            impl_for: None,
+
+            type_params: Vec::new(),
+            kind: FctKind::Source(RwLock::new(FctSrc::new())),
+        };
+
+        let fct_id = {
+            let mut fcts = self.ctxt.fcts.lock();
+            let fct_id = FctId(fcts.len());
+            fct.id = fct_id;
+            fcts.push(Arc::new(RwLock::new(fct)));
+
+            fct_id
+        };
+
+        let cls = self.ctxt.classes.idx(cls_id);
+        cls.write().methods.push(fct_id);
+
+        fct_id
+    }
+
+    fn check_expr_path(&mut self, path: &'ast ExprPathType) {
+        let (module, idx) = semck::module_prefix(self.ctxt, &path.path.path);
+        let name = path.path[idx];
+        let sym = semck::module_item(self.ctxt, module, name, path.pos);
+
+        // global, const or struct of another module
+        if idx + 1 == path.path.len() {
+            let ident_type = match sym {
+                Some(SymGlobal(id)) => IdentType::Global(id),
+                Some(SymStruct(id)) => IdentType::Struct(id),
+                Some(SymConst(id)) => IdentType::Const(id),
+
+                _ => {
+                    let name = self.ctxt.module_path(module, name);
+                    report(self.ctxt, path.pos, Msg::UnknownIdentifier(name));
+                    return;
+                }
+            };
+
+            self.src.map_idents.insert(path.id, ident_type);
+            return;
+        }
+
+        self.check_enum_value(path.id, path.pos, &path.path, idx, sym);
+    }
+
+    // `idx` is the index of the enum name in the path
+    fn check_enum_value(
+        &mut self,
+        id: NodeId,
+        pos: Position,
+        path: &Path,
+        idx: usize,
+        sym: Option<Sym>,
+    ) {
+        match sym {
+            Some(SymEnum(enum_id)) if idx + 2 == path.len() => {
+                if let Some(idx) = self.enum_variant(enum_id, path[idx + 1], pos) {
+                    self.src
+                        .map_idents
+                        .insert(id, IdentType::EnumValue(enum_id, idx));
+                }
+            }
+
+            _ => {
+                let name = str(self.ctxt, path[idx]);
+                report(self.ctxt, pos, Msg::EnumExpected(name));
+            }
+        }
+    }
+
+    fn enum_variant(&mut self, enum_id: EnumId, name: Name, pos: Position) -> Option<usize> {
+        let xenum = self.ctxt.enums.idx(enum_id);
+        let xenum = xenum.lock();
+        let idx = xenum.variant_idx(name);
+
+        if idx.is_none() {
+            let enum_name = str(self.ctxt, xenum.name);
+            let name = str(self.ctxt, name);
+            report(self.ctxt, pos, Msg::UnknownEnumValue(enum_name, name));
+        }
+
+        idx
+    }
+
     fn check_expr_struct(&mut self, struc: &'ast ExprLitStructType) {
-        if let Some(sid) = self.ctxt.sym.lock().get_struct(struc.path.name()) {
+        let (module, idx) = semck::module_prefix(self.ctxt, &struc.path.path);
+        let name = struc.path[idx];
+
+        let sym = if idx + 1 == struc.path.len() {
+            semck::module_item(self.ctxt, module, name, struc.pos)
+        } else {
+            None
+        };
+
+        if let Some(SymStruct(sid)) = sym {
             self.src.map_idents.insert(struc.id, IdentType::Struct(sid));
         } else {
-            let name = self.ctxt.interner.str(struc.path.name()).to_string();
+            let name = self.ctxt.module_path(module, name);
             report(self.ctxt, struc.pos, Msg::UnknownStruct(name));
         }
+
+        for arg in &struc.args {
+            self.visit_expr(&arg.expr);
+        }
     }
 }
 
@@ -354,6 +806,7 @@ impl<'a, 'ast> Visitor<'ast> for NameCheck<'a, 'ast> {
     fn visit_stmt(&mut self, s: &'ast Stmt) {
         match *s {
             StmtVar(ref stmt) => self.check_stmt_var(stmt),
+            StmtLetTuple(ref stmt) => self.check_stmt_let_tuple(stmt),
             StmtBlock(ref stmt) => self.check_stmt_block(stmt),
             StmtDo(ref stmt) => self.check_stmt_do(stmt),
             StmtFor(ref stmt) => self.check_stmt_for(stmt),
@@ -368,6 +821,10 @@ impl<'a, 'ast> Visitor<'ast> for NameCheck<'a, 'ast> {
             &ExprIdent(ref ident) => self.check_expr_ident(ident),
             &ExprCall(ref call) => self.check_expr_call(call),
             &ExprLitStruct(ref lit) => self.check_expr_struct(lit),
+            &ExprPath(ref path) => self.check_expr_path(path),
+            &ExprSelf(ref expr) => self.check_expr_self(expr),
+            &ExprLambda(ref expr) => self.check_expr_lambda(expr),
+            &ExprMatch(ref expr) => self.check_expr_match(expr),
 
             // no need to handle rest of expressions
             _ => visit::walk_expr(self, e),
@@ -431,7 +888,7 @@ mod tests {
         err(
             "fun f() { let f = 1; f(); }",
             pos(1, 22),
-            Msg::UnknownFunction("f".into()),
+            Msg::LambdaExpected("f".into()),
         );
     }
 
@@ -517,6 +974,44 @@ mod tests {
         );
     }
 
+    #[test]
+    fn enum_value() {
+        ok("enum Color { Red, Green } fun f() { Color::Red; }");
+        ok("enum Shape { Circle(int) } fun f() { Shape::Circle(1); }");
+
+        err(
+            "enum Color { Red } fun f() { Color::Blue; }",
+            pos(1, 30),
+            Msg::UnknownEnumValue("Color".into(), "Blue".into()),
+        );
+        err(
+            "enum Shape { Circle(int) } fun f() { Shape::Rect(1); }",
+            pos(1, 38),
+            Msg::UnknownEnumValue("Shape".into(), "Rect".into()),
+        );
+        err(
+            "fun f() { Foo::Bar; }",
+            pos(1, 11),
+            Msg::EnumExpected("Foo".into()),
+        );
+    }
+
+    #[test]
+    fn match_binding() {
+        ok("class Foo fun f(x: Object) -> bool { return match x { foo: Foo => foo === x, _ => false }; }");
+
+        err(
+            "class Foo fun f(x: Object) { match x { foo: Foo => 1, _ => foo }; }",
+            pos(1, 60),
+            Msg::UnknownIdentifier("foo".into()),
+        );
+        err(
+            "class Foo fun f(x: Object) { match x { Foo: Foo => 1, _ => 2 }; }",
+            pos(1, 40),
+            Msg::ShadowClass("Foo".into()),
+        );
+    }
+
     #[test]
     fn const_value() {
         ok("const one: int = 1;
@@ -527,4 +1022,31 @@ mod tests {
     fn for_var() {
         ok("fun f() { for i in range(0, 4) { i; } }");
     }
+
+    #[test]
+    fn qualified_path() {
+        ok_modules(&[
+            (
+                "",
+                "fun main() { util::f(); util::Foo(); util::E::A; util::ONE; }",
+            ),
+            (
+                "util",
+                "pub fun f() {} pub class Foo pub enum E { A } pub const ONE: int = 1;",
+            ),
+        ]);
+        err_modules(
+            &[("", "fun main() { util::f(); }"), ("util", "fun f() {}")],
+            pos(1, 14),
+            Msg::NotAccessible("util::f".into()),
+        );
+        err_modules(
+            &[
+                ("", "fun main() { util::g(); }"),
+                ("util", "pub fun f() {}"),
+            ],
+            pos(1, 14),
+            Msg::UnknownFunction("util::g".into()),
+        );
+    }
 }
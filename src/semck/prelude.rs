@@ -1,5 +1,5 @@
 use class::ClassId;
-use ctxt::{FctKind, Intrinsic, SemContext, TraitId};
+use ctxt::{FctId, FctKind, Intrinsic, SemContext, TraitId};
 use exception;
 use gc::Address;
 use stdlib;
@@ -75,7 +75,20 @@ fn find_trait<'ast>(ctxt: &mut SemContext<'ast>, name: &str) -> TraitId {
     }
 }
 
+fn find_fct<'ast>(ctxt: &mut SemContext<'ast>, name: &str) -> FctId {
+    let iname = ctxt.interner.intern(name);
+    let fct_id = ctxt.sym.lock().get_fct(iname);
+
+    if let Some(fct_id) = fct_id {
+        fct_id
+    } else {
+        panic!("function {} not found!", name);
+    }
+}
+
 pub fn internal_functions<'ast>(ctxt: &mut SemContext<'ast>) {
+    ctxt.vips.range_fct = find_fct(ctxt, "range");
+
     native_fct(ctxt, "fatalError", stdlib::fatal_error as *const u8);
     native_fct(ctxt, "abort", stdlib::abort as *const u8);
     native_fct(ctxt, "exit", stdlib::exit as *const u8);
@@ -96,6 +109,10 @@ pub fn internal_functions<'ast>(ctxt: &mut SemContext<'ast>) {
     );
     native_fct(ctxt, "sleep", stdlib::sleep as *const u8);
 
+    let fctid = ctxt.internal_module_fct("spawnThread");
+    let kind = FctKind::Native(Address::from_ptr(stdlib::spawn_lambda as *const u8));
+    resolve_internal_fct(ctxt, fctid, kind);
+
     native_fct(ctxt, "call", stdlib::call as *const u8);
     native_fct(ctxt, "throwFromNative", stdlib::throw_native as *const u8);
     native_fct(
@@ -103,12 +120,16 @@ pub fn internal_functions<'ast>(ctxt: &mut SemContext<'ast>) {
         "throwFromNativeButNotThrows",
         stdlib::throw_native as *const u8,
     );
+    native_fct(ctxt, "sumFromNative", stdlib::sum_native as *const u8);
 
     native_fct(ctxt, "loadFunction", stdlib::load_function as *const u8);
     native_fct(ctxt, "call0", stdlib::call0 as *const u8);
     native_fct(ctxt, "call1", stdlib::call1 as *const u8);
     native_fct(ctxt, "call2", stdlib::call2 as *const u8);
     native_fct(ctxt, "call3", stdlib::call3 as *const u8);
+    native_fct(ctxt, "call4", stdlib::call4 as *const u8);
+    native_fct(ctxt, "call5", stdlib::call5 as *const u8);
+    native_fct(ctxt, "call6", stdlib::call6 as *const u8);
 
     native_fct(ctxt, "native_malloc", stdlib::native_malloc as *const u8);
     native_fct(ctxt, "native_free", stdlib::native_free as *const u8);
@@ -355,6 +376,10 @@ fn internal_fct<'ast>(ctxt: &mut SemContext<'ast>, name: &str, kind: FctKind) {
     let name = ctxt.interner.intern(name);
     let fctid = ctxt.sym.lock().get_fct(name);
 
+    resolve_internal_fct(ctxt, fctid, kind);
+}
+
+fn resolve_internal_fct<'ast>(ctxt: &mut SemContext<'ast>, fctid: Option<FctId>, kind: FctKind) {
     if let Some(fctid) = fctid {
         let fct = ctxt.fcts.idx(fctid);
         let mut fct = fct.write();
@@ -75,11 +75,14 @@ pub fn returns_value(s: &Stmt) -> Result<(), Position> {
         StmtBreak(ref stmt) => Err(stmt.pos),
         StmtContinue(ref stmt) => Err(stmt.pos),
         StmtVar(ref stmt) => Err(stmt.pos),
+        StmtLetTuple(ref stmt) => Err(stmt.pos),
         StmtExpr(ref stmt) => Err(stmt.pos),
         StmtSpawn(ref stmt) => Err(stmt.pos),
         StmtThrow(_) => Ok(()),
         StmtDefer(ref stmt) => Err(stmt.pos),
         StmtDo(ref stmt) => do_returns_value(stmt),
+        // syntax error was already reported
+        StmtError(_) => Ok(()),
     }
 }
 
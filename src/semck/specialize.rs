@@ -7,7 +7,7 @@ use class::{self, ClassDef, ClassDefId, ClassId, ClassSize, FieldDef, TypeParams
 use ctxt::{SemContext, StructData, StructDef, StructDefId, StructFieldDef, StructId};
 use mem;
 use object::Header;
-use ty::BuiltinType;
+use ty::{BuiltinType, TupleId};
 use vtable::{VTableBox, DISPLAY_SIZE};
 
 pub fn specialize_type<'ast>(
@@ -18,7 +18,9 @@ pub fn specialize_type<'ast>(
     match ty {
         BuiltinType::ClassTypeParam(_, id) => type_params[id.idx()],
 
-        BuiltinType::FctTypeParam(_, _) => panic!("no fct type params expected"),
+        // fields of closure classes store values of the enclosing generic function,
+        // the type params of the closure class are the type params of that function
+        BuiltinType::FctTypeParam(_, id) => type_params[id.idx()],
 
         BuiltinType::Struct(struct_id, list_id) => {
             let params = ctxt.lists.lock().get(list_id);
@@ -48,6 +50,19 @@ pub fn specialize_type<'ast>(
             BuiltinType::Class(cls_id, list_id)
         }
 
+        BuiltinType::Tuple(tuple_id) => {
+            let elements = ctxt.tuple_types.lock().get(tuple_id);
+
+            let elements = elements
+                .iter()
+                .map(|&t| specialize_type(ctxt, t, type_params))
+                .collect::<Vec<_>>();
+
+            let tuple_id = ctxt.tuple_types.lock().insert(elements);
+
+            BuiltinType::Tuple(tuple_id)
+        }
+
         _ => ty,
     }
 }
@@ -132,11 +147,11 @@ fn create_specialized_struct(
         size = offset + field_size;
         align = max(align, field_align);
 
-        if ty.reference_type() {
-            ref_fields.push(offset);
-        }
+        add_ref_fields(ctxt, &mut ref_fields, offset, ty);
     }
 
+    size = mem::align_i32(size, align);
+
     let struct_def = ctxt.struct_defs.idx(id);
     let mut struct_def = struct_def.lock();
     struct_def.size = size;
@@ -147,6 +162,96 @@ fn create_specialized_struct(
     id
 }
 
+// tuples are laid out like structs with one field per element
+pub fn specialize_tuple(ctxt: &SemContext, tuple_id: TupleId) -> StructDefId {
+    if let Some(id) = ctxt.tuple_types.lock().layout(tuple_id) {
+        return id;
+    }
+
+    let elements = ctxt.tuple_types.lock().get(tuple_id);
+
+    let mut size = 0;
+    let mut align = 0;
+    let mut fields = Vec::with_capacity(elements.len());
+    let mut ref_fields = Vec::new();
+
+    for &ty in elements.iter() {
+        debug_assert!(!ty.contains_type_param(ctxt));
+
+        // nil elements are stored as null references
+        let ty = if ty.is_nil() { BuiltinType::Ptr } else { ty };
+
+        let element_size = ty.size(ctxt);
+        let element_align = ty.align(ctxt);
+
+        let offset = mem::align_i32(size, element_align);
+        fields.push(StructFieldDef {
+            offset: offset,
+            ty: ty,
+        });
+
+        size = offset + element_size;
+        align = max(align, element_align);
+
+        add_ref_fields(ctxt, &mut ref_fields, offset, ty);
+    }
+
+    size = mem::align_i32(size, align);
+
+    let mut tuple_types = ctxt.tuple_types.lock();
+
+    if let Some(id) = tuple_types.layout(tuple_id) {
+        return id;
+    }
+
+    let id = {
+        let mut struct_defs = ctxt.struct_defs.lock();
+        let id: StructDefId = struct_defs.len().into();
+
+        struct_defs.push(Arc::new(Mutex::new(StructDef {
+            size: size,
+            align: align,
+            fields: fields,
+            ref_fields: ref_fields,
+        })));
+
+        id
+    };
+
+    tuple_types.set_layout(tuple_id, id);
+
+    id
+}
+
+pub fn specialize_struct_ty(ctxt: &SemContext, ty: BuiltinType) -> StructDefId {
+    match ty {
+        BuiltinType::Struct(struct_id, list_id) => {
+            let params = ctxt.lists.lock().get(list_id);
+            specialize_struct_id_params(ctxt, struct_id, params)
+        }
+
+        BuiltinType::Tuple(tuple_id) => specialize_tuple(ctxt, tuple_id),
+
+        _ => unreachable!(),
+    }
+}
+
+// adds offsets of all references stored in a field of type `ty`,
+// structs and tuples are stored inline and might contain references themselves
+fn add_ref_fields(ctxt: &SemContext, ref_fields: &mut Vec<i32>, offset: i32, ty: BuiltinType) {
+    if ty.is_struct_or_tuple() {
+        let struct_id = specialize_struct_ty(ctxt, ty);
+        let struc = ctxt.struct_defs.idx(struct_id);
+        let struc = struc.lock();
+
+        for &ref_offset in &struc.ref_fields {
+            ref_fields.push(offset + ref_offset);
+        }
+    } else if ty.reference_type() {
+        ref_fields.push(offset);
+    }
+}
+
 pub fn specialize_class_id(ctxt: &SemContext, cls_id: ClassId) -> ClassDefId {
     let cls = ctxt.classes.idx(cls_id);
     let cls = cls.read();
@@ -222,10 +327,18 @@ fn create_specialized_class(
         ref_fields = Vec::new();
 
         size = if cls.is_array {
-            if type_params[0].reference_type() {
+            let element_type = type_params[0];
+
+            if element_type.reference_type() {
                 ClassSize::ObjArray
             } else {
-                ClassSize::Array(type_params[0].size(ctxt))
+                add_ref_fields(ctxt, &mut ref_fields, 0, element_type);
+
+                if ref_fields.is_empty() {
+                    ClassSize::Array(element_type.size(ctxt))
+                } else {
+                    ClassSize::StructArray(element_type.size(ctxt))
+                }
             }
         } else {
             ClassSize::Str
@@ -273,9 +386,7 @@ fn create_specialized_class(
 
             csize = offset + field_size;
 
-            if ty.reference_type() {
-                ref_fields.push(offset);
-            }
+            add_ref_fields(ctxt, &mut ref_fields, offset, ty);
         }
 
         size = ClassSize::Fixed(mem::align_i32(csize, mem::ptr_width()));
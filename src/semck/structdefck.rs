@@ -1,3 +1,5 @@
+use std::collections::HashSet;
+
 use ctxt::{NodeMap, SemContext, StructFieldData, StructId};
 use dora_parser::ast;
 use dora_parser::ast::visit::{self, Visitor};
@@ -15,6 +17,57 @@ pub fn check<'ast>(ctxt: &mut SemContext<'ast>, map_struct_defs: &NodeMap<Struct
     };
 
     clsck.check();
+
+    cycle_detection(ctxt);
+}
+
+// structs are stored inline, a struct containing itself
+// (directly or through other structs) would have infinite size
+fn cycle_detection<'ast>(ctxt: &SemContext<'ast>) {
+    let mut path = Vec::new();
+    let mut visited = HashSet::new();
+
+    for struc in ctxt.structs.iter() {
+        let id = struc.lock().id;
+        check_struct_cycle(ctxt, id, &mut path, &mut visited);
+    }
+}
+
+fn check_struct_cycle<'ast>(
+    ctxt: &SemContext<'ast>,
+    id: StructId,
+    path: &mut Vec<StructId>,
+    visited: &mut HashSet<StructId>,
+) {
+    if visited.contains(&id) {
+        return;
+    }
+
+    let fields = {
+        let struc = ctxt.structs.idx(id);
+        let struc = struc.lock();
+
+        struc
+            .fields
+            .iter()
+            .map(|f| (f.pos, f.ty))
+            .collect::<Vec<_>>()
+    };
+
+    path.push(id);
+
+    for (pos, ty) in fields {
+        if let BuiltinType::Struct(field_id, _) = ty {
+            if path.contains(&field_id) {
+                report(ctxt, pos, Msg::RecursiveStructure);
+            } else {
+                check_struct_cycle(ctxt, field_id, path, visited);
+            }
+        }
+    }
+
+    path.pop();
+    visited.insert(id);
 }
 
 struct StructCheck<'x, 'ast: 'x> {
@@ -32,6 +85,14 @@ impl<'x, 'ast> StructCheck<'x, 'ast> {
 }
 
 impl<'x, 'ast> Visitor<'ast> for StructCheck<'x, 'ast> {
+    fn visit_file(&mut self, f: &'ast ast::File) {
+        let module = self.ctxt.file_module(f);
+
+        self.ctxt.sym.lock().set_module(module);
+        visit::walk_file(self, f);
+        self.ctxt.sym.lock().set_module(None);
+    }
+
     fn visit_struct(&mut self, s: &'ast ast::Struct) {
         self.struct_id = Some(*self.map_struct_defs.get(s.id).unwrap());
 
@@ -73,7 +134,11 @@ fn report(ctxt: &SemContext, pos: Position, msg: Msg) {
 
 #[cfg(test)]
 mod tests {
+    use ctxt::{SemContext, StructDef};
     use dora_parser::error::msg::Msg;
+    use mem;
+    use object::Header;
+    use semck::specialize::{specialize_class_id, specialize_struct_id};
     use semck::tests::*;
 
     #[test]
@@ -93,4 +158,102 @@ mod tests {
             Msg::ShadowField("a".into()),
         );
     }
+
+    fn struct_def<F, R>(ctxt: &SemContext, name: &'static str, f: F) -> R
+    where
+        F: FnOnce(&StructDef) -> R,
+    {
+        let name = ctxt.interner.intern(name);
+        let sid = ctxt.sym.lock().get_struct(name).unwrap();
+        let id = specialize_struct_id(ctxt, sid);
+        let struc = ctxt.struct_defs.idx(id);
+        let struc = struc.lock();
+
+        f(&*struc)
+    }
+
+    #[test]
+    fn struct_size() {
+        ok_with_test(
+            "struct Foo { a: int, b: int }
+             struct Foo1 { a: bool, b: int, c: bool }
+             struct Bar { }",
+            |ctxt| {
+                struct_def(ctxt, "Foo", |s| assert_eq!(8, s.size));
+                struct_def(ctxt, "Foo1", |s| {
+                    assert_eq!(12, s.size);
+                    assert_eq!(4, s.align);
+                });
+                struct_def(ctxt, "Bar", |s| assert_eq!(0, s.size));
+            },
+        );
+    }
+
+    #[test]
+    fn struct_in_struct() {
+        ok_with_test(
+            "struct Foo { a: bool, bar: Bar }
+             struct Bar { a: int }",
+            |ctxt| {
+                struct_def(ctxt, "Foo", |s| {
+                    assert_eq!(8, s.size);
+                    assert_eq!(4, s.fields[1].offset);
+                });
+            },
+        );
+
+        err(
+            "struct Foo { a: int, bar: Bar }
+             struct Bar { b: int, foo: Foo }",
+            pos(2, 35),
+            Msg::RecursiveStructure,
+        );
+
+        err(
+            "struct Foo { a: int, foo: Foo }",
+            pos(1, 22),
+            Msg::RecursiveStructure,
+        );
+    }
+
+    #[test]
+    fn class_in_struct() {
+        ok_with_test(
+            "class Foo(a: bool, b: int)
+             struct Bar { a: int, foo: Foo }
+             struct Baz { a: int, bar: Bar }",
+            |ctxt| {
+                let pw = mem::ptr_width();
+
+                struct_def(ctxt, "Bar", |s| {
+                    assert_eq!(2 * pw, s.size);
+                    assert_eq!(vec![pw], s.ref_fields);
+                });
+
+                struct_def(ctxt, "Baz", |s| {
+                    assert_eq!(3 * pw, s.size);
+                    assert_eq!(vec![2 * pw], s.ref_fields);
+                });
+            },
+        );
+    }
+
+    #[test]
+    fn struct_in_class() {
+        ok_with_test(
+            "class Foo { var bar: Bar; }
+             struct Bar { a: int, foo: Foo }",
+            |ctxt| {
+                let pw = mem::ptr_width();
+                let name = ctxt.interner.intern("Foo");
+                let cls_id = ctxt.sym.lock().get_class(name).unwrap();
+                let cls_id = specialize_class_id(ctxt, cls_id);
+                let cls = ctxt.class_defs.idx(cls_id);
+                let cls = cls.read();
+
+                let header = Header::size();
+                assert_eq!(vec![header + pw], cls.ref_fields);
+            },
+        );
+    }
 }
@@ -31,6 +31,14 @@ impl<'x, 'ast> TraitCheck<'x, 'ast> {
 }
 
 impl<'x, 'ast> Visitor<'ast> for TraitCheck<'x, 'ast> {
+    fn visit_file(&mut self, f: &'ast ast::File) {
+        let module = self.ctxt.file_module(f);
+
+        self.ctxt.sym.lock().set_module(module);
+        visit::walk_file(self, f);
+        self.ctxt.sym.lock().set_module(None);
+    }
+
     fn visit_trait(&mut self, t: &'ast ast::Trait) {
         self.trait_id = Some(*self.map_trait_defs.get(t.id).unwrap());
 
@@ -53,6 +61,7 @@ impl<'x, 'ast> Visitor<'ast> for TraitCheck<'x, 'ast> {
             ast: f,
             pos: f.pos,
             name: f.name,
+            module: self.ctxt.sym.lock().module(),
             param_types: Vec::new(),
             return_type: BuiltinType::Unit,
             parent: FctParent::Trait(self.trait_id.unwrap()),
@@ -66,6 +75,7 @@ impl<'x, 'ast> Visitor<'ast> for TraitCheck<'x, 'ast> {
             internal_resolved: false,
             overrides: None,
             throws: f.throws,
+            is_lambda: false,
             ctor: ast::CtorType::None,
             vtable_index: None,
             initialized: false,
@@ -0,0 +1,237 @@
+use std::collections::HashSet;
+
+use ctxt::{SemContext, TypeAliasId};
+use dora_parser::ast::Type::{self, TypeBasic, TypeLambda, TypeTuple};
+use dora_parser::error::msg::Msg;
+use dora_parser::interner::Name;
+use semck;
+use ty::BuiltinType;
+
+pub fn check_cycles<'ast>(ctxt: &SemContext<'ast>) {
+    for alias in &ctxt.type_aliases {
+        let mut visited = HashSet::new();
+        ctxt.sym.lock().set_module(alias.module);
+
+        if refers_to(
+            ctxt,
+            alias.id,
+            &alias.ast.data_type,
+            &alias.type_params,
+            &mut visited,
+        ) {
+            let name = ctxt.interner.str(alias.name).to_string();
+            let msg = Msg::RecursiveTypeAlias(name);
+            ctxt.diag.lock().report(alias.pos, msg);
+        }
+    }
+
+    ctxt.sym.lock().set_module(None);
+}
+
+// checks whether expanding the type t ever leads back to the alias target
+fn refers_to<'ast>(
+    ctxt: &SemContext<'ast>,
+    target: TypeAliasId,
+    t: &'ast Type,
+    type_params: &[Name],
+    visited: &mut HashSet<TypeAliasId>,
+) -> bool {
+    match *t {
+        TypeBasic(ref basic) => {
+            for param in &basic.params {
+                if refers_to(ctxt, target, param, type_params, visited) {
+                    return true;
+                }
+            }
+
+            let sym = if basic.path.is_empty() {
+                if type_params.contains(&basic.name) {
+                    return false;
+                }
+
+                ctxt.sym.lock().get(basic.name)
+            } else {
+                let mut path = basic.path.clone();
+                path.push(basic.name);
+
+                match semck::module_prefix(ctxt, &path) {
+                    (Some(module), idx) if idx == basic.path.len() => {
+                        ctxt.sym.lock().get_in_module(Some(module), basic.name)
+                    }
+
+                    _ => None,
+                }
+            };
+
+            if let Some(alias_id) = sym.and_then(|sym| sym.to_type_alias()) {
+                if alias_id == target {
+                    return true;
+                }
+
+                if visited.insert(alias_id) {
+                    let alias = &ctxt.type_aliases[alias_id];
+                    let module = ctxt.sym.lock().module();
+
+                    ctxt.sym.lock().set_module(alias.module);
+                    let result = refers_to(
+                        ctxt,
+                        target,
+                        &alias.ast.data_type,
+                        &alias.type_params,
+                        visited,
+                    );
+                    ctxt.sym.lock().set_module(module);
+
+                    return result;
+                }
+            }
+
+            false
+        }
+
+        TypeTuple(ref tuple) => tuple
+            .subtypes
+            .iter()
+            .any(|ty| refers_to(ctxt, target, ty, type_params, visited)),
+
+        TypeLambda(ref lambda) => {
+            lambda
+                .params
+                .iter()
+                .any(|ty| refers_to(ctxt, target, ty, type_params, visited))
+                || refers_to(ctxt, target, &lambda.ret, type_params, visited)
+        }
+
+        _ => false,
+    }
+}
+
+pub fn check<'ast>(ctxt: &SemContext<'ast>) {
+    for alias in &ctxt.type_aliases {
+        ctxt.sym.lock().set_module(alias.module);
+
+        if let Some(ref type_params) = alias.ast.type_params {
+            let mut names = HashSet::new();
+
+            for type_param in type_params {
+                if !names.insert(type_param.name) {
+                    let name = ctxt.interner.str(type_param.name).to_string();
+                    let msg = Msg::TypeParamNameNotUnique(name);
+                    ctxt.diag.lock().report(type_param.pos, msg);
+                }
+
+                if !type_param.bounds.is_empty() {
+                    let name = ctxt.interner.str(alias.name).to_string();
+                    let msg = Msg::TypeAliasBoundsNotAllowed(name);
+                    ctxt.diag.lock().report(type_param.pos, msg);
+                }
+            }
+        }
+
+        // the type params are only known at the use site,
+        // read the aliased type once to report unknown types
+        let params = alias
+            .type_params
+            .iter()
+            .map(|&name| (name, BuiltinType::Error))
+            .collect::<Vec<_>>();
+
+        semck::read_type_with_alias_params(ctxt, &alias.ast.data_type, &params);
+    }
+
+    ctxt.sym.lock().set_module(None);
+}
+
+#[cfg(test)]
+mod tests {
+    use dora_parser::error::msg::Msg;
+    use semck::tests::*;
+
+    #[test]
+    fn type_alias() {
+        ok("type Name = Str; fun f(x: Name) -> Str { return x; }");
+        ok("type Callback = (int) -> bool; fun f(x: Callback) -> (int) -> bool { return x; }");
+        ok("type Grid<T> = Array<Array<T>>;
+            fun f(x: Grid<int>) -> Array<Array<int>> { return x; }");
+        ok(
+            "type Pair<A, B> = (A) -> B; type Inner<T> = Pair<T, Array<T>>;
+            fun f(x: Inner<int>) -> (int) -> Array<int> { return x; }",
+        );
+
+        err(
+            "type Name = Foo;",
+            pos(1, 13),
+            Msg::UnknownType("Foo".into()),
+        );
+        err(
+            "type Grid<T> = Array<T>; fun f(x: Grid<int, int>) {}",
+            pos(1, 35),
+            Msg::WrongNumberTypeAliasParams("Grid".into(), 1, 2),
+        );
+        err(
+            "type Name = Str; fun f() -> Name { return 1; }",
+            pos(1, 36),
+            Msg::ReturnType("Name".into(), "int".into()),
+        );
+    }
+
+    #[test]
+    fn type_alias_names_in_diagnostics() {
+        err(
+            "type Num = int; fun f() { let x: Num = \"a\"; }",
+            pos(1, 27),
+            Msg::AssignType("x".into(), "Num".into(), "Str".into()),
+        );
+        err(
+            "type Grid<T> = Array<Array<T>>; fun f() { let x: Grid<int> = 1; }",
+            pos(1, 43),
+            Msg::AssignType("x".into(), "Grid<int>".into(), "int".into()),
+        );
+        err(
+            "type Num = int; fun f() -> Array<Num> { return 1; }",
+            pos(1, 41),
+            Msg::ReturnType("Array<Num>".into(), "int".into()),
+        );
+        err(
+            "type Num = int; fun f() -> int { let x: Num = 1; return \"a\"; }",
+            pos(1, 50),
+            Msg::ReturnType("int".into(), "Str".into()),
+        );
+    }
+
+    #[test]
+    fn type_alias_params() {
+        err(
+            "type Grid<T, T> = Array<T>;",
+            pos(1, 14),
+            Msg::TypeParamNameNotUnique("T".into()),
+        );
+        err(
+            "trait Foo {} type Grid<T: Foo> = Array<T>;",
+            pos(1, 24),
+            Msg::TypeAliasBoundsNotAllowed("Grid".into()),
+        );
+        err(
+            "type Grid<T> = T<int>;",
+            pos(1, 16),
+            Msg::NoTypeParamsExpected,
+        );
+    }
+
+    #[test]
+    fn recursive_type_alias() {
+        err(
+            "type A = Array<A>;",
+            pos(1, 1),
+            Msg::RecursiveTypeAlias("A".into()),
+        );
+        errors(
+            "type A = B; type B = (A) -> int;",
+            &[
+                (pos(1, 1), Msg::RecursiveTypeAlias("A".into())),
+                (pos(1, 13), Msg::RecursiveTypeAlias("B".into())),
+            ],
+        );
+        ok("type A<B> = Array<B>; type B = A<int>;");
+    }
+}
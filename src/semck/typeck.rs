@@ -2,11 +2,11 @@ use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use std::{f32, f64};
 
-use class::{ClassId, TypeParams};
+use class::{ClassId, FieldId, TypeParams};
 use ctxt;
 use ctxt::{
-    CallType, ConstData, ConstValue, ConvInfo, Fct, FctId, FctParent, FctSrc, ForTypeInfo,
-    IdentType, SemContext, TraitId,
+    CallType, ConstData, ConstValue, ConvInfo, EnumId, Fct, FctId, FctParent, FctSrc, ForTypeInfo,
+    IdentType, MatchArmInfo, MatchInfo, SemContext, TraitId,
 };
 use dora_parser::error::msg::Msg;
 
@@ -17,6 +17,7 @@ use dora_parser::ast::*;
 use dora_parser::interner::Name;
 use dora_parser::lexer::position::Position;
 use dora_parser::lexer::token::{FloatSuffix, IntBase, IntSuffix};
+use semck;
 use semck::specialize::specialize_type;
 use sym::Sym::SymClass;
 use ty::BuiltinType;
@@ -25,7 +26,8 @@ pub fn check<'a, 'ast>(ctxt: &SemContext<'ast>) {
     for fct in ctxt.fcts.iter() {
         let fct = fct.read();
 
-        if !fct.is_src() {
+        // lambdas are checked together with their enclosing function
+        if !fct.is_src() || fct.is_lambda {
             continue;
         }
 
@@ -42,11 +44,13 @@ pub fn check<'a, 'ast>(ctxt: &SemContext<'ast>) {
             negative_expr_id: NodeId(0),
         };
 
+        ctxt.sym.lock().set_module(fct.module);
         typeck.check();
     }
 
     for xconst in ctxt.consts.iter() {
         let mut xconst = xconst.lock();
+        ctxt.sym.lock().set_module(xconst.module);
 
         let (_, value) = {
             let mut constck = ConstCheck {
@@ -60,6 +64,8 @@ pub fn check<'a, 'ast>(ctxt: &SemContext<'ast>) {
 
         xconst.value = value;
     }
+
+    ctxt.sym.lock().set_module(None);
 }
 
 struct TypeCheck<'a, 'ast: 'a> {
@@ -115,7 +121,10 @@ impl<'a, 'ast> TypeCheck<'a, 'ast> {
         if let Some(expr_type) = expr_type {
             if !expr_type.is_error() && !defined_type.allows(self.ctxt, expr_type) {
                 let name = self.ctxt.interner.str(s.name).to_string();
-                let defined_type = defined_type.name(self.ctxt);
+                let defined_type = match s.data_type {
+                    Some(ref data_type) => semck::type_name(self.ctxt, data_type, defined_type),
+                    None => defined_type.name(self.ctxt),
+                };
                 let expr_type = expr_type.name(self.ctxt);
                 let msg = Msg::AssignType(name, defined_type, expr_type);
                 self.ctxt.diag.lock().report(s.pos, msg);
@@ -130,6 +139,72 @@ impl<'a, 'ast> TypeCheck<'a, 'ast> {
         }
     }
 
+    fn check_stmt_let_tuple(&mut self, s: &'ast StmtLetTupleType) {
+        self.visit_expr(&s.expr);
+        let expr_type = self.expr_type;
+
+        let defined_type = if let Some(ref data_type) = s.data_type {
+            let ty = self.src.ty(data_type.id());
+
+            if !expr_type.is_error() && !ty.allows(self.ctxt, expr_type) {
+                let name = s
+                    .bindings
+                    .iter()
+                    .map(|b| self.ctxt.interner.str(b.name).to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let name = format!("({})", name);
+                let defined_type = semck::type_name(self.ctxt, data_type, ty);
+                let expr_type = expr_type.name(self.ctxt);
+                let msg = Msg::AssignType(name, defined_type, expr_type);
+                self.ctxt.diag.lock().report(s.pos, msg);
+            }
+
+            ty
+        } else {
+            expr_type
+        };
+
+        let elements = match defined_type {
+            BuiltinType::Tuple(tuple_id) => {
+                let elements = self.ctxt.tuple_types.lock().get(tuple_id);
+
+                if elements.len() == s.bindings.len() {
+                    Some(elements)
+                } else {
+                    None
+                }
+            }
+
+            _ => None,
+        };
+
+        if elements.is_none() && !defined_type.is_error() {
+            let tyname = defined_type.name(self.ctxt);
+            let msg = Msg::LetTupleMismatch(tyname, s.bindings.len());
+            self.ctxt.diag.lock().report(s.pos, msg);
+        }
+
+        for (idx, binding) in s.bindings.iter().enumerate() {
+            let var = *self.src.map_vars.get(binding.id).unwrap();
+
+            let ty = match elements {
+                Some(ref elements) => elements[idx],
+                None => BuiltinType::Error,
+            };
+
+            if ty.is_nil() {
+                let name = self.ctxt.interner.str(binding.name).to_string();
+                self.ctxt
+                    .diag
+                    .lock()
+                    .report(binding.pos, Msg::VarNeedsTypeInfo(name));
+            }
+
+            self.src.vars[var].ty = ty;
+        }
+    }
+
     fn check_stmt_for(&mut self, s: &'ast StmtForType) {
         self.visit_expr(&s.expr);
         let object_type = self.expr_type;
@@ -249,12 +324,14 @@ impl<'a, 'ast> TypeCheck<'a, 'ast> {
         let fct_type = self.fct.return_type;
 
         if !expr_type.is_error() && !fct_type.allows(self.ctxt, expr_type) {
-            let msg = if expr_type.is_nil() {
-                let fct_type = fct_type.name(self.ctxt);
+            let fct_type = match self.ast.return_type {
+                Some(ref ret) => semck::type_name(self.ctxt, ret, fct_type),
+                None => fct_type.name(self.ctxt),
+            };
 
+            let msg = if expr_type.is_nil() {
                 Msg::IncompatibleWithNil(fct_type)
             } else {
-                let fct_type = fct_type.name(self.ctxt);
                 let expr_type = expr_type.name(self.ctxt);
 
                 Msg::ReturnType(fct_type, expr_type)
@@ -287,6 +364,34 @@ impl<'a, 'ast> TypeCheck<'a, 'ast> {
         }
     }
 
+    fn check_stmt_spawn(&mut self, s: &'ast StmtSpawnType) {
+        self.visit_expr(&s.expr);
+        let ty = self.expr_type;
+
+        let lambda_id = self
+            .ctxt
+            .lambda_types
+            .lock()
+            .insert(Vec::new(), BuiltinType::Unit);
+        let expected = BuiltinType::Lambda(lambda_id);
+
+        if !ty.is_error() && !expected.allows(self.ctxt, ty) {
+            let tyname = ty.name(self.ctxt);
+            self.ctxt
+                .diag
+                .lock()
+                .report(s.pos, Msg::SpawnExpected(tyname));
+        }
+
+        // spawn is implemented as call to the function internal::spawnThread
+        let fct_id = self
+            .ctxt
+            .internal_module_fct("spawnThread")
+            .expect("spawnThread() not found");
+        let call_type = CallType::Fct(fct_id, TypeParams::empty(), TypeParams::empty());
+        self.src.map_calls.insert(s.id, Arc::new(call_type));
+    }
+
     fn check_stmt_do(&mut self, s: &'ast StmtDoType) {
         self.visit_stmt(&s.do_block);
 
@@ -301,45 +406,122 @@ impl<'a, 'ast> TypeCheck<'a, 'ast> {
 
     fn check_expr_ident(&mut self, e: &'ast ExprIdentType) {
         let ident_type = *self.src.map_idents.get(e.id).unwrap();
+        let ty = self.ident_ty(ident_type);
 
-        match ident_type {
-            IdentType::Var(varid) => {
-                let ty = self.src.vars[varid].ty;
-                self.src.set_ty(e.id, ty);
-                self.expr_type = ty;
+        // a struct name on its own is a struct literal without any fields
+        if let IdentType::Struct(sid) = ident_type {
+            let struc = self.ctxt.structs.idx(sid);
+            let struc = struc.lock();
+            let struc_name = self.ctxt.interner.str(struc.name).to_string();
+
+            for field in &struc.fields {
+                let fname = self.ctxt.interner.str(field.name).to_string();
+                self.ctxt.diag.lock().report(
+                    e.pos,
+                    Msg::StructFieldNotInitialized(struc_name.clone(), fname),
+                );
             }
+        }
+
+        self.src.set_ty(e.id, ty);
+        self.expr_type = ty;
+    }
+
+    fn ident_ty(&self, ident_type: IdentType) -> BuiltinType {
+        match ident_type {
+            IdentType::Var(varid) => self.src.vars[varid].ty,
 
             IdentType::Global(globalid) => {
                 let glob = self.ctxt.globals.idx(globalid);
                 let ty = glob.lock().ty;
-                self.src.set_ty(e.id, ty);
-                self.expr_type = ty;
+                ty
             }
 
             IdentType::Field(ty, fieldid) => {
                 let clsid = ty.cls_id(self.ctxt).unwrap();
                 let cls = self.ctxt.classes.idx(clsid);
                 let cls = cls.read();
-                let field = &cls.fields[fieldid];
 
-                self.src.set_ty(e.id, field.ty);
-                self.expr_type = field.ty;
+                cls.fields[fieldid].ty
             }
 
             IdentType::Struct(sid) => {
                 let list_id = self.ctxt.lists.lock().insert(TypeParams::empty());
-                let ty = BuiltinType::Struct(sid, list_id);
-                self.src.set_ty(e.id, ty);
-                self.expr_type = ty;
+                BuiltinType::Struct(sid, list_id)
+            }
+
+            IdentType::StructField(ty, field_id) => {
+                let sid = match ty {
+                    BuiltinType::Struct(sid, _) => sid,
+                    _ => unreachable!(),
+                };
+
+                let struc = self.ctxt.structs.idx(sid);
+                let struc = struc.lock();
+
+                struc.fields[field_id.idx()].ty
             }
 
             IdentType::Const(const_id) => {
                 let xconst = self.ctxt.consts.idx(const_id);
                 let xconst = xconst.lock();
 
-                self.src.set_ty(e.id, xconst.ty);
-                self.expr_type = xconst.ty;
+                xconst.ty
             }
+
+            IdentType::EnumValue(enum_id, _) => BuiltinType::Enum(enum_id),
+        }
+    }
+
+    fn check_expr_path(&mut self, e: &'ast ExprPathType) {
+        let ident_type = *self.src.map_idents.get(e.id).unwrap();
+
+        let ty = match ident_type {
+            IdentType::EnumValue(enum_id, idx) => {
+                self.check_enum_value_args(e.pos, enum_id, idx, &[]);
+                BuiltinType::Enum(enum_id)
+            }
+
+            // global, const or struct of another module
+            _ => self.ident_ty(ident_type),
+        };
+
+        self.src.set_ty(e.id, ty);
+        self.expr_type = ty;
+    }
+
+    fn check_enum_value_args(
+        &mut self,
+        pos: Position,
+        enum_id: EnumId,
+        idx: usize,
+        call_types: &[BuiltinType],
+    ) {
+        let xenum = self.ctxt.enums.idx(enum_id);
+        let xenum = xenum.lock();
+        let variant = &xenum.variants[idx];
+
+        let compatible = variant.types.len() == call_types.len()
+            && variant
+                .types
+                .iter()
+                .zip(call_types)
+                .all(|(def, &arg)| def.allows(self.ctxt, arg));
+
+        if !compatible {
+            let enum_name = self.ctxt.interner.str(xenum.name).to_string();
+            let name = self.ctxt.interner.str(variant.name).to_string();
+            let def = variant
+                .types
+                .iter()
+                .map(|a| a.name(self.ctxt))
+                .collect::<Vec<_>>();
+            let expr = call_types
+                .iter()
+                .map(|a| a.name(self.ctxt))
+                .collect::<Vec<_>>();
+            let msg = Msg::EnumArgsIncompatible(enum_name, name, def, expr);
+            self.ctxt.diag.lock().report(pos, msg);
         }
     }
 
@@ -347,14 +529,39 @@ impl<'a, 'ast> TypeCheck<'a, 'ast> {
         if e.lhs.is_array() {
             let array = e.lhs.to_array().unwrap();
 
-            self.visit_expr(&array.object);
-            let object_type = self.expr_type;
+            let (object_type, index_type, value_type) = if let Some(op) = e.op {
+                // the element is read with `get` before the operator is applied
+                self.visit_expr(&e.lhs);
+                let lhs_type = self.expr_type;
 
-            self.visit_expr(&array.index);
-            let index_type = self.expr_type;
+                let object_type = self.src.ty(array.object.id());
+                let index_type = self.src.ty(array.index.id());
 
-            self.visit_expr(&e.rhs);
-            let value_type = self.expr_type;
+                self.visit_expr(&e.rhs);
+                let rhs_type = self.expr_type;
+
+                let value_type = self.check_assign_op(e, op, lhs_type, rhs_type);
+
+                if lhs_type.is_error() {
+                    self.src.set_ty(e.id, BuiltinType::Error);
+                    self.expr_type = BuiltinType::Error;
+
+                    return;
+                }
+
+                (object_type, index_type, value_type)
+            } else {
+                self.visit_expr(&array.object);
+                let object_type = self.expr_type;
+
+                self.visit_expr(&array.index);
+                let index_type = self.expr_type;
+
+                self.visit_expr(&e.rhs);
+                let value_type = self.expr_type;
+
+                (object_type, index_type, value_type)
+            };
 
             if object_type.is_error() {
                 self.src.set_ty(e.id, BuiltinType::Error);
@@ -396,13 +603,19 @@ impl<'a, 'ast> TypeCheck<'a, 'ast> {
                 self.src.set_ty(e.id, BuiltinType::Error);
                 self.expr_type = BuiltinType::Error;
             }
-        } else if e.lhs.is_field() || e.lhs.is_ident() {
+        } else if e.lhs.is_field() || e.lhs.is_ident() || e.lhs.is_path() {
             self.visit_expr(&e.lhs);
             let lhs_type = self.expr_type;
 
             self.visit_expr(&e.rhs);
             let rhs_type = self.expr_type;
 
+            let rhs_type = if let Some(op) = e.op {
+                self.check_assign_op(e, op, lhs_type, rhs_type)
+            } else {
+                rhs_type
+            };
+
             if let Some(ident_type) = self.src.map_idents.get(e.lhs.id()) {
                 match ident_type {
                     &IdentType::Var(varid) => {
@@ -419,22 +632,25 @@ impl<'a, 'ast> TypeCheck<'a, 'ast> {
                     }
 
                     &IdentType::Field(ty, fieldid) => {
-                        let clsid = ty.cls_id(self.ctxt).unwrap();
-                        let cls = self.ctxt.classes.idx(clsid);
-                        let cls = cls.read();
-
-                        if !self.fct.ctor.is() && !cls.fields[fieldid].reassignable {
-                            self.ctxt.diag.lock().report(e.pos, Msg::LetReassigned);
-                        }
+                        self.check_field_reassignable(e.pos, ty, fieldid);
                     }
 
                     &IdentType::Struct(_) => {
-                        unimplemented!();
+                        self.ctxt.diag.lock().report(e.pos, Msg::LvalueExpected);
+                    }
+
+                    &IdentType::StructField(_, _) => {
+                        let field = e.lhs.to_field().unwrap();
+                        self.check_struct_lvalue(e.pos, &field.object);
                     }
 
                     &IdentType::Const(_) => {
                         self.ctxt.diag.lock().report(e.pos, Msg::AssignmentToConst);
                     }
+
+                    &IdentType::EnumValue(_, _) => {
+                        self.ctxt.diag.lock().report(e.pos, Msg::LvalueExpected);
+                    }
                 }
 
                 if !lhs_type.allows(self.ctxt, rhs_type) {
@@ -444,6 +660,19 @@ impl<'a, 'ast> TypeCheck<'a, 'ast> {
                         let lhs_type = lhs_type.name(self.ctxt);
                         let rhs_type = rhs_type.name(self.ctxt);
 
+                        Msg::AssignType(name, lhs_type, rhs_type)
+                    } else if e.lhs.is_path() {
+                        let path = e.lhs.to_path().unwrap();
+                        let name = path
+                            .path
+                            .path
+                            .iter()
+                            .map(|&name| self.ctxt.interner.str(name).to_string())
+                            .collect::<Vec<_>>()
+                            .join("::");
+                        let lhs_type = lhs_type.name(self.ctxt);
+                        let rhs_type = rhs_type.name(self.ctxt);
+
                         Msg::AssignType(name, lhs_type, rhs_type)
                     } else {
                         let field = e.lhs.to_field().unwrap();
@@ -469,6 +698,103 @@ impl<'a, 'ast> TypeCheck<'a, 'ast> {
         self.expr_type = BuiltinType::Error;
     }
 
+    // `a op= b` invokes the same operator method as `a op b`, the result
+    // is then assigned to `a`. On errors the type of `a` is returned, which
+    // avoids follow-up errors for the assignment itself.
+    fn check_assign_op(
+        &mut self,
+        e: &'ast ExprAssignType,
+        op: AssignOp,
+        lhs_type: BuiltinType,
+        rhs_type: BuiltinType,
+    ) -> BuiltinType {
+        if lhs_type.is_error() || rhs_type.is_error() {
+            self.src.set_ty(op.id, BuiltinType::Error);
+            return lhs_type;
+        }
+
+        let name = self.ctxt.interner.intern(bin_op_method(op.op));
+        let call_types = [rhs_type];
+
+        if let Some((_, fct_id, return_type)) = lookup_method(
+            self.ctxt,
+            lhs_type,
+            false,
+            name,
+            &call_types,
+            &TypeParams::empty(),
+            None,
+        ) {
+            let call_type = CallType::Method(lhs_type, fct_id, TypeParams::empty());
+            self.src
+                .map_calls
+                .insert_or_replace(op.id, Arc::new(call_type));
+
+            self.src.set_ty(op.id, return_type);
+            return_type
+        } else {
+            let lhs_name = lhs_type.name(self.ctxt);
+            let rhs_name = rhs_type.name(self.ctxt);
+            let operator = format!("{}=", op.op.as_str());
+            let msg = Msg::BinOpType(operator, lhs_name, rhs_name);
+
+            self.ctxt.diag.lock().report(e.pos, msg);
+
+            self.src.set_ty(op.id, BuiltinType::Error);
+            lhs_type
+        }
+    }
+
+    fn check_field_reassignable(&mut self, pos: Position, ty: BuiltinType, fieldid: FieldId) {
+        let clsid = ty.cls_id(self.ctxt).unwrap();
+        let cls = self.ctxt.classes.idx(clsid);
+        let cls = cls.read();
+        let field = &cls.fields[fieldid];
+
+        // captured variables are copied into the fields of the closure class
+        if self.fct.is_lambda && clsid == self.fct.cls_id() {
+            let name = self.ctxt.interner.str(field.name).to_string();
+            let msg = Msg::CapturedVarReassigned(name);
+            self.ctxt.diag.lock().report(pos, msg);
+        } else if !self.fct.ctor.is() && !field.reassignable {
+            self.ctxt.diag.lock().report(pos, Msg::LetReassigned);
+        }
+    }
+
+    // assigning a field of a struct modifies the struct in-place, this is only
+    // allowed if the struct itself is stored in an assignable location
+    fn check_struct_lvalue(&mut self, pos: Position, object: &'ast Expr) {
+        let ident_type = self.src.map_idents.get(object.id()).cloned();
+
+        match ident_type {
+            Some(IdentType::Var(varid)) if object.is_ident() => {
+                if !self.src.vars[varid].reassignable {
+                    self.ctxt.diag.lock().report(pos, Msg::LetReassigned);
+                }
+            }
+
+            Some(IdentType::Global(gid)) => {
+                let glob = self.ctxt.globals.idx(gid);
+                if !glob.lock().reassignable {
+                    self.ctxt.diag.lock().report(pos, Msg::LetReassigned);
+                }
+            }
+
+            Some(IdentType::Field(ty, fieldid)) => {
+                self.check_field_reassignable(pos, ty, fieldid);
+            }
+
+            Some(IdentType::StructField(_, _)) => {
+                let field = object.to_field().unwrap();
+                self.check_struct_lvalue(pos, &field.object);
+            }
+
+            _ => {
+                self.ctxt.diag.lock().report(pos, Msg::LvalueExpected);
+            }
+        }
+    }
+
     fn find_method(
         &mut self,
         pos: Position,
@@ -567,18 +893,9 @@ impl<'a, 'ast> TypeCheck<'a, 'ast> {
         match e.op {
             BinOp::Or | BinOp::And => self.check_expr_bin_bool(e, e.op, lhs_type, rhs_type),
             BinOp::Cmp(cmp) => self.check_expr_bin_cmp(e, cmp, lhs_type, rhs_type),
-            BinOp::Add => self.check_expr_bin_method(e, e.op, "plus", lhs_type, rhs_type),
-            BinOp::Sub => self.check_expr_bin_method(e, e.op, "minus", lhs_type, rhs_type),
-            BinOp::Mul => self.check_expr_bin_method(e, e.op, "times", lhs_type, rhs_type),
-            BinOp::Div => self.check_expr_bin_method(e, e.op, "div", lhs_type, rhs_type),
-            BinOp::Mod => self.check_expr_bin_method(e, e.op, "mod", lhs_type, rhs_type),
-            BinOp::BitOr => self.check_expr_bin_method(e, e.op, "bitwiseOr", lhs_type, rhs_type),
-            BinOp::BitAnd => self.check_expr_bin_method(e, e.op, "bitwiseAnd", lhs_type, rhs_type),
-            BinOp::BitXor => self.check_expr_bin_method(e, e.op, "bitwiseXor", lhs_type, rhs_type),
-            BinOp::ShiftL => self.check_expr_bin_method(e, e.op, "shiftLeft", lhs_type, rhs_type),
-            BinOp::ShiftR => self.check_expr_bin_method(e, e.op, "shiftRight", lhs_type, rhs_type),
-            BinOp::UnShiftR => {
-                self.check_expr_bin_method(e, e.op, "unsignedShiftRight", lhs_type, rhs_type)
+            _ => {
+                let name = bin_op_method(e.op);
+                self.check_expr_bin_method(e, e.op, name, lhs_type, rhs_type)
             }
         }
     }
@@ -675,6 +992,10 @@ impl<'a, 'ast> TypeCheck<'a, 'ast> {
                 return;
             }
 
+            CmpOp::Eq | CmpOp::Ne if lhs_type.is_enum() => {
+                self.check_expr_bin_enum(e, e.op, lhs_type, rhs_type)
+            }
+
             CmpOp::Eq | CmpOp::Ne => {
                 self.check_expr_bin_method(e, e.op, "equals", lhs_type, rhs_type)
             }
@@ -686,6 +1007,30 @@ impl<'a, 'ast> TypeCheck<'a, 'ast> {
         self.expr_type = BuiltinType::Bool;
     }
 
+    // values of enums are equal when they are the same variant with equal payloads,
+    // payloads that contain structs or tuples cannot be compared
+    fn check_expr_bin_enum(
+        &mut self,
+        e: &'ast ExprBinType,
+        op: BinOp,
+        lhs_type: BuiltinType,
+        rhs_type: BuiltinType,
+    ) {
+        let comparable = match lhs_type {
+            BuiltinType::Enum(enum_id) => enum_comparable(self.ctxt, enum_id, &mut Vec::new()),
+            _ => unreachable!(),
+        };
+
+        if !comparable || lhs_type != rhs_type {
+            let op = op.as_str().into();
+            let lhs_type = lhs_type.name(self.ctxt);
+            let rhs_type = rhs_type.name(self.ctxt);
+            let msg = Msg::BinOpType(op, lhs_type, rhs_type);
+
+            self.ctxt.diag.lock().report(e.pos, msg);
+        }
+    }
+
     fn check_type(
         &mut self,
         e: &'ast ExprBinType,
@@ -785,15 +1130,34 @@ impl<'a, 'ast> TypeCheck<'a, 'ast> {
             return;
         }
 
-        let call_type = if e.path.len() > 1 {
-            match self.ctxt.sym.lock().get(e.path[0]) {
-                Some(SymClass(cls_id)) => {
-                    assert_eq!(2, e.path.len());
+        if let Some(&ident_type) = self.src.map_idents.get(e.id) {
+            self.check_expr_call_lambda(e, ident_type, &call_types);
+            return;
+        }
+
+        if let Some(call_type) = self.src.map_calls.get(e.id).cloned() {
+            if let CallType::EnumValue(enum_id, idx) = *call_type {
+                self.check_enum_value_args(e.pos, enum_id, idx, &call_types);
 
+                let ty = BuiltinType::Enum(enum_id);
+                self.src.set_ty(e.id, ty);
+                self.expr_type = ty;
+                return;
+            }
+        }
+
+        let call_type = if let Some(call_type) = self.src.map_calls.get(e.id).cloned() {
+            call_type
+        } else {
+            let (module, idx) = semck::module_prefix(self.ctxt, &e.path.path);
+            let sym = semck::module_item(self.ctxt, module, e.path[idx], e.pos);
+
+            match sym {
+                Some(SymClass(cls_id)) if idx + 2 == e.path.len() => {
                     let mut lookup = MethodLookup::new(self.ctxt)
                         .pos(e.pos)
                         .static_method(cls_id)
-                        .name(e.path[1])
+                        .name(e.path[idx + 1])
                         .args(&call_types)
                         .fct_type_params(&type_params);
 
@@ -814,7 +1178,7 @@ impl<'a, 'ast> TypeCheck<'a, 'ast> {
                 }
 
                 _ => {
-                    let name = self.ctxt.interner.str(e.path[0]).to_string();
+                    let name = self.ctxt.module_path(module, e.path[idx]);
                     let msg = Msg::ClassExpected(name);
                     self.ctxt.diag.lock().report(e.pos, msg);
 
@@ -822,8 +1186,6 @@ impl<'a, 'ast> TypeCheck<'a, 'ast> {
                     return;
                 }
             }
-        } else {
-            self.src.map_calls.get(e.id).unwrap().clone()
         };
 
         match *call_type {
@@ -894,6 +1256,51 @@ impl<'a, 'ast> TypeCheck<'a, 'ast> {
         }
     }
 
+    fn check_expr_call_lambda(
+        &mut self,
+        e: &'ast ExprCallType,
+        ident_type: IdentType,
+        call_types: &[BuiltinType],
+    ) {
+        let name = self.ctxt.interner.str(e.path.name()).to_string();
+        let lambda_id = match self.ident_ty(ident_type) {
+            BuiltinType::Lambda(lambda_id) => lambda_id,
+
+            ty => {
+                if !ty.is_error() {
+                    let msg = Msg::LambdaExpected(name);
+                    self.ctxt.diag.lock().report(e.pos, msg);
+                }
+
+                self.src.set_ty(e.id, BuiltinType::Error);
+                self.expr_type = BuiltinType::Error;
+                return;
+            }
+        };
+
+        let lambda = self.ctxt.lambda_types.lock().get(lambda_id);
+        let params = lambda.params();
+
+        if params.len() != call_types.len()
+            || !params
+                .iter()
+                .zip(call_types)
+                .all(|(def, &arg)| def.allows(self.ctxt, arg))
+        {
+            let def = params.iter().map(|t| t.name(self.ctxt)).collect();
+            let expr = call_types.iter().map(|t| t.name(self.ctxt)).collect();
+            let msg = Msg::ParamTypesIncompatible(name, def, expr);
+            self.ctxt.diag.lock().report(e.pos, msg);
+        }
+
+        let call_type = CallType::Lambda(lambda_id);
+        self.src.map_calls.insert(e.id, Arc::new(call_type));
+
+        let ty = lambda.ret();
+        self.src.set_ty(e.id, ty);
+        self.expr_type = ty;
+    }
+
     fn check_expr_delegation(&mut self, e: &'ast ExprDelegationType) {
         let arg_types: Vec<BuiltinType> = e
             .args
@@ -987,8 +1394,11 @@ impl<'a, 'ast> TypeCheck<'a, 'ast> {
         args: &[BuiltinType],
     ) {
         match obj {
-            BuiltinType::FctTypeParam(_, tpid) => {
-                let tp = &self.fct.type_params[tpid.idx()];
+            // inside a lambda this is a type param of the enclosing function
+            BuiltinType::FctTypeParam(fct_id, tpid) => {
+                let fct = self.ctxt.fcts.idx(fct_id);
+                let fct = fct.read();
+                let tp = &fct.type_params[tpid.idx()];
                 self.check_generic_method_call_for_type_param(e, in_try, obj, args, tp);
             }
 
@@ -1052,6 +1462,20 @@ impl<'a, 'ast> TypeCheck<'a, 'ast> {
 
         let ty = self.expr_type;
 
+        if let BuiltinType::Struct(sid, _) = ty {
+            let struc = self.ctxt.structs.idx(sid);
+            let struc = struc.lock();
+
+            if let Some(field) = struc.fields.iter().find(|f| f.name == e.name) {
+                let ident_type = IdentType::StructField(ty, field.id);
+                self.src.map_idents.insert_or_replace(e.id, ident_type);
+
+                self.src.set_ty(e.id, field.ty);
+                self.expr_type = field.ty;
+                return;
+            }
+        }
+
         let cls_id = ty.cls_id(self.ctxt);
 
         if let Some(cls_id) = cls_id {
@@ -1071,6 +1495,15 @@ impl<'a, 'ast> TypeCheck<'a, 'ast> {
                 self.src.map_idents.insert_or_replace(e.id, ident_type);
 
                 let field = &cls.fields[field_id];
+                semck::check_member_access(
+                    self.ctxt,
+                    cls.name,
+                    cls.module,
+                    field.is_pub,
+                    field.name,
+                    e.pos,
+                );
+
                 let class_type_params = ty.type_params(self.ctxt);
                 let fty = replace_type_param(
                     self.ctxt,
@@ -1095,7 +1528,68 @@ impl<'a, 'ast> TypeCheck<'a, 'ast> {
         self.expr_type = BuiltinType::Error;
     }
 
+    fn check_expr_tuple(&mut self, e: &'ast ExprTupleType) {
+        let mut elements = Vec::with_capacity(e.elements.len());
+
+        for element in &e.elements {
+            self.visit_expr(element);
+            elements.push(self.expr_type);
+        }
+
+        let ty = if elements.iter().any(|ty| ty.is_error()) {
+            BuiltinType::Error
+        } else {
+            let tuple_id = self.ctxt.tuple_types.lock().insert(elements);
+            BuiltinType::Tuple(tuple_id)
+        };
+
+        self.src.set_ty(e.id, ty);
+        self.expr_type = ty;
+    }
+
+    fn check_expr_tuple_element(&mut self, e: &'ast ExprTupleElementType) {
+        self.visit_expr(&e.object);
+        let object_type = self.expr_type;
+
+        if object_type.is_error() {
+            self.src.set_ty(e.id, BuiltinType::Error);
+            self.expr_type = BuiltinType::Error;
+            return;
+        }
+
+        if let BuiltinType::Tuple(tuple_id) = object_type {
+            let elements = self.ctxt.tuple_types.lock().get(tuple_id);
+
+            if let Some(&ty) = elements.get(e.index) {
+                self.src.set_ty(e.id, ty);
+                self.expr_type = ty;
+                return;
+            }
+        }
+
+        let msg = Msg::UnknownField(e.index.to_string(), object_type.name(self.ctxt));
+        self.ctxt.diag.lock().report(e.pos, msg);
+
+        self.src.set_ty(e.id, BuiltinType::Error);
+        self.expr_type = BuiltinType::Error;
+    }
+
     fn check_expr_this(&mut self, e: &'ast ExprSelfType) {
+        // `self` in a lambda was captured from the enclosing method
+        if self.fct.is_lambda {
+            let ty = if let Some(&ident_type) = self.src.map_idents.get(e.id) {
+                self.ident_ty(ident_type)
+            } else {
+                let msg = Msg::ThisUnavailable;
+                self.ctxt.diag.lock().report(e.pos, msg);
+                BuiltinType::Unit
+            };
+
+            self.src.set_ty(e.id, ty);
+            self.expr_type = ty;
+            return;
+        }
+
         match self.fct.parent {
             FctParent::Class(clsid) => {
                 let cls = self.ctxt.classes.idx(clsid);
@@ -1181,10 +1675,14 @@ impl<'a, 'ast> TypeCheck<'a, 'ast> {
             self.src.set_ty(e.id, e_type);
 
             if let Some(call_type) = self.src.map_calls.get(call.id) {
-                let fct_id = call_type.fct_id();
-                let fct = self.ctxt.fcts.idx(fct_id);
-                let fct = fct.read();
-                let throws = fct.throws;
+                // lambdas never throw
+                let throws = if call_type.is_lambda() {
+                    false
+                } else {
+                    let fct = self.ctxt.fcts.idx(call_type.fct_id());
+                    let fct = fct.read();
+                    fct.throws
+                };
 
                 if !throws {
                     self.ctxt.diag.lock().report(e.pos, Msg::TryCallNonThrowing);
@@ -1206,7 +1704,15 @@ impl<'a, 'ast> TypeCheck<'a, 'ast> {
                 }
 
                 TryMode::Force => {}
-                TryMode::Opt => panic!("unsupported"),
+
+                // exceptions are turned into nil, only possible for references
+                TryMode::Opt => {
+                    if !e_type.is_error() && !e_type.is_unit() && !e_type.reference_type() {
+                        let e_type = e_type.name(self.ctxt);
+                        let msg = Msg::TryOptValueType(e_type);
+                        self.ctxt.diag.lock().report(e.pos, msg);
+                    }
+                }
             }
 
             self.expr_type = e_type;
@@ -1219,19 +1725,48 @@ impl<'a, 'ast> TypeCheck<'a, 'ast> {
     }
 
     fn check_expr_lambda(&mut self, e: &'ast ExprLambdaType) {
-        let ret = if let Some(ref ty) = e.ret {
-            self.src.ty(ty.id())
-        } else {
-            BuiltinType::Unit
-        };
+        let lambda = self.src.map_lambdas.get(e.id).unwrap().clone();
+        let mut generic = false;
 
-        let params = e
-            .params
-            .iter()
-            .map(|p| self.src.ty(p.data_type.id()))
-            .collect::<Vec<_>>();
+        // captured values are stored in the fields of the closure class,
+        // closures are only generic over the type params of functions
+        for (idx, &capture) in lambda.captures.iter().enumerate() {
+            let ty = self.ident_ty(capture);
+            generic = generic || ty.contains_class_type_param(self.ctxt);
+
+            let cls = self.ctxt.classes.idx(lambda.cls_id);
+            cls.write().fields[idx].ty = ty;
+        }
 
-        let ty = self.ctxt.lambda_types.lock().insert(params, ret);
+        if generic {
+            self.ctxt.diag.lock().report_unimplemented(e.pos);
+        }
+
+        let fct = self.ctxt.fcts.idx(lambda.fct_id);
+        let fct = fct.read();
+
+        {
+            let src = fct.src();
+            let mut src = src.write();
+
+            let mut typeck = TypeCheck {
+                ctxt: self.ctxt,
+                fct: &fct,
+                src: &mut src,
+                ast: fct.ast,
+                expr_type: BuiltinType::Unit,
+                negative_expr_id: NodeId(0),
+            };
+
+            typeck.check();
+        }
+
+        let params = fct.params_without_self().to_vec();
+        let ty = self
+            .ctxt
+            .lambda_types
+            .lock()
+            .insert(params, fct.return_type);
         let ty = BuiltinType::Lambda(ty);
 
         self.expr_type = ty;
@@ -1279,21 +1814,304 @@ impl<'a, 'ast> TypeCheck<'a, 'ast> {
             },
         );
 
-        self.expr_type = if e.is { BuiltinType::Bool } else { check_type };
+        self.expr_type = if e.is { BuiltinType::Bool } else { check_type };
+    }
+
+    fn check_expr_match(&mut self, e: &'ast ExprMatchType) {
+        self.visit_expr(&e.expr);
+        let value_type = self.expr_type;
+        self.src.set_ty(e.expr.id(), value_type);
+
+        let str_type = self.ctxt.cls(self.ctxt.vips.str_class);
+
+        let supported = match value_type {
+            BuiltinType::Bool
+            | BuiltinType::Byte
+            | BuiltinType::Char
+            | BuiltinType::Int
+            | BuiltinType::Long
+            | BuiltinType::Class(_, _)
+            | BuiltinType::Enum(_)
+            | BuiltinType::Error => true,
+            _ => false,
+        };
+
+        if !supported {
+            let value_type = value_type.name(self.ctxt);
+            self.ctxt
+                .diag
+                .lock()
+                .report(e.pos, Msg::MatchTypeUnsupported(value_type));
+        }
+
+        let mut arms = Vec::with_capacity(e.arms.len());
+        let mut result_type: Option<BuiltinType> = None;
+        let mut exhaustive = false;
+
+        let mut ints = HashSet::new();
+        let mut bools = HashSet::new();
+        let mut strs = HashSet::new();
+        let mut classes = Vec::new();
+        let mut variants = HashSet::new();
+
+        for arm in &e.arms {
+            let mut unreachable_arm = exhaustive;
+
+            let info = match arm.pattern {
+                MatchPattern::Underscore => {
+                    exhaustive = true;
+                    MatchArmInfo::Underscore
+                }
+
+                MatchPattern::Lit(ref lit) => {
+                    let (pattern_type, info) = self.check_match_lit(lit);
+
+                    if !pattern_type.is_error()
+                        && !value_type.is_error()
+                        && pattern_type != value_type
+                    {
+                        let pattern_type = pattern_type.name(self.ctxt);
+                        let value_type = value_type.name(self.ctxt);
+                        let msg = Msg::MatchPatternIncompatible(pattern_type, value_type);
+                        self.ctxt.diag.lock().report(arm.pos, msg);
+                    }
+
+                    match info {
+                        MatchArmInfo::Int(value) => unreachable_arm |= !ints.insert(value),
+
+                        MatchArmInfo::Bool(value) => {
+                            unreachable_arm |= !bools.insert(value);
+                            exhaustive |= bools.len() == 2;
+                        }
+
+                        MatchArmInfo::Str => {
+                            let value = lit.to_lit_str().unwrap().value.clone();
+                            unreachable_arm |= !strs.insert(value);
+
+                            if value_type == str_type {
+                                self.check_match_str(arm.id, str_type);
+                            }
+                        }
+
+                        _ => unreachable!(),
+                    }
+
+                    info
+                }
+
+                MatchPattern::Type(_, ref data_type) => {
+                    let check_type = self.src.ty(data_type.id());
+                    let mut valid = false;
+
+                    if value_type.subclass_from(self.ctxt, check_type) {
+                        // every value matches, like the binding of a variable
+                        valid = true;
+                        exhaustive = true;
+                    } else if !check_type.subclass_from(self.ctxt, value_type)
+                        && !value_type.is_error()
+                    {
+                        let check_type = check_type.name(self.ctxt);
+                        let value_type = value_type.name(self.ctxt);
+                        let msg = Msg::MatchPatternIncompatible(check_type, value_type);
+                        self.ctxt.diag.lock().report(arm.pos, msg);
+                    }
+
+                    unreachable_arm |= classes
+                        .iter()
+                        .any(|&cls| check_type.subclass_from(self.ctxt, cls));
+                    classes.push(check_type);
+
+                    if let Some(&var) = self.src.map_vars.get(arm.id) {
+                        self.src.vars[var].ty = check_type;
+                    }
+
+                    match check_type.cls_id(self.ctxt) {
+                        Some(cls_id) => MatchArmInfo::Class(ConvInfo {
+                            cls_id: cls_id,
+                            valid: valid,
+                        }),
+
+                        None => MatchArmInfo::Underscore,
+                    }
+                }
+
+                MatchPattern::Enum(_, ref bindings) => {
+                    match self.check_match_enum(arm, bindings, value_type) {
+                        Some((idx, variant_count)) => {
+                            unreachable_arm |= !variants.insert(idx);
+                            exhaustive |= variants.len() == variant_count;
+
+                            MatchArmInfo::Enum(idx)
+                        }
+
+                        None => MatchArmInfo::Underscore,
+                    }
+                }
+            };
+
+            if unreachable_arm {
+                self.ctxt
+                    .diag
+                    .lock()
+                    .report(arm.pos, Msg::MatchArmUnreachable);
+            }
+
+            arms.push(info);
+
+            self.visit_expr(&arm.value);
+            let arm_type = self.expr_type;
+
+            match result_type {
+                None => result_type = Some(arm_type),
+
+                Some(first_type) if first_type.is_nil() && arm_type.reference_type() => {
+                    result_type = Some(arm_type);
+                }
+
+                // a later arm of a super class widens the type of the match
+                Some(first_type)
+                    if first_type.reference_type()
+                        && !arm_type.is_nil()
+                        && arm_type.allows(self.ctxt, first_type) =>
+                {
+                    result_type = Some(arm_type);
+                }
+
+                Some(first_type) => {
+                    if !first_type.is_error()
+                        && !arm_type.is_error()
+                        && !first_type.allows(self.ctxt, arm_type)
+                    {
+                        let first_type = first_type.name(self.ctxt);
+                        let arm_type = arm_type.name(self.ctxt);
+                        let msg = Msg::MatchArmsIncompatible(first_type, arm_type);
+                        self.ctxt.diag.lock().report(arm.value.pos(), msg);
+                    }
+                }
+            }
+        }
+
+        if !exhaustive && supported && !value_type.is_error() {
+            self.ctxt.diag.lock().report(e.pos, Msg::MatchNotExhaustive);
+        }
+
+        self.src.map_matches.insert(e.id, MatchInfo { arms: arms });
+
+        let ty = result_type.unwrap_or(BuiltinType::Unit);
+        self.src.set_ty(e.id, ty);
+        self.expr_type = ty;
+    }
+
+    // returns the index of the variant and the number of variants of the enum,
+    // the bindings get the types of the payload of the variant. Returns None
+    // if the variant is unknown or belongs to another enum.
+    fn check_match_enum(
+        &mut self,
+        arm: &'ast MatchArm,
+        bindings: &'ast [MatchBinding],
+        value_type: BuiltinType,
+    ) -> Option<(usize, usize)> {
+        let (enum_id, idx) = match self.src.map_idents.get(arm.id) {
+            Some(&IdentType::EnumValue(enum_id, idx)) => (enum_id, idx),
+            _ => return None,
+        };
+
+        let (enum_name, name, types, variant_count) = {
+            let xenum = self.ctxt.enums.idx(enum_id);
+            let xenum = xenum.lock();
+            let variant = &xenum.variants[idx];
+
+            (
+                xenum.name,
+                variant.name,
+                variant.types.clone(),
+                xenum.variants.len(),
+            )
+        };
+
+        if !value_type.is_error() && value_type != BuiltinType::Enum(enum_id) {
+            let pattern_type = BuiltinType::Enum(enum_id).name(self.ctxt);
+            let value_type = value_type.name(self.ctxt);
+            let msg = Msg::MatchPatternIncompatible(pattern_type, value_type);
+            self.ctxt.diag.lock().report(arm.pos, msg);
+
+            return None;
+        }
+
+        if bindings.len() != types.len() {
+            let enum_name = self.ctxt.interner.str(enum_name).to_string();
+            let name = self.ctxt.interner.str(name).to_string();
+            let msg = Msg::MatchPatternWrongNumberOfBindings(
+                enum_name,
+                name,
+                types.len(),
+                bindings.len(),
+            );
+            self.ctxt.diag.lock().report(arm.pos, msg);
+        }
+
+        for (binding, &ty) in bindings.iter().zip(&types) {
+            if let Some(&var) = self.src.map_vars.get(binding.id) {
+                self.src.vars[var].ty = ty;
+            }
+        }
+
+        Some((idx, variant_count))
+    }
+
+    fn check_match_lit(&mut self, lit: &'ast Expr) -> (BuiltinType, MatchArmInfo) {
+        self.visit_expr(lit);
+        let ty = self.expr_type;
+
+        let info = match *lit {
+            ExprLitInt(ref lit) => MatchArmInfo::Int(lit.value as i64),
+            ExprUn(ref un) => {
+                let lit = un.opnd.to_lit_int().unwrap();
+                MatchArmInfo::Int((lit.value as i64).wrapping_neg())
+            }
+            ExprLitChar(ref lit) => MatchArmInfo::Int(lit.value as i64),
+            ExprLitBool(ref lit) => MatchArmInfo::Bool(lit.value),
+            ExprLitStr(_) => MatchArmInfo::Str,
+            _ => unreachable!(),
+        };
+
+        (ty, info)
+    }
+
+    // string patterns are compared with the value through Str::equals
+    fn check_match_str(&mut self, id: NodeId, str_type: BuiltinType) {
+        let name = self.ctxt.interner.intern("equals");
+        let call_types = [str_type];
+
+        let (_, fct_id, _) = lookup_method(
+            self.ctxt,
+            str_type,
+            false,
+            name,
+            &call_types,
+            &TypeParams::empty(),
+            None,
+        )
+        .unwrap();
+
+        let call_type = CallType::Method(str_type, fct_id, TypeParams::empty());
+        self.src.map_calls.insert(id, Arc::new(call_type));
     }
 
     fn check_expr_lit_struct(&mut self, e: &'ast ExprLitStructType) {
         let sid = self.src.map_idents.get(e.id).unwrap().struct_id();
-        let struc = self.ctxt.structs.idx(sid);
-        let struc = struc.lock();
-
         let mut initialized: HashMap<Name, BuiltinType> = Default::default();
 
+        // arguments might access fields of the same struct, check
+        // them before locking the struct
         for arg in &e.args {
             self.visit_expr(&arg.expr);
             initialized.insert(arg.name, self.expr_type);
         }
 
+        let struc = self.ctxt.structs.idx(sid);
+        let struc = struc.lock();
+
         let struc_name = self.ctxt.interner.str(struc.name).to_string();
 
         for field in &struc.fields {
@@ -1365,6 +2183,7 @@ impl<'a, 'ast> Visitor<'ast> for TypeCheck<'a, 'ast> {
             }
             ExprLitStruct(ref expr) => self.check_expr_lit_struct(expr),
             ExprIdent(ref expr) => self.check_expr_ident(expr),
+            ExprPath(ref expr) => self.check_expr_path(expr),
             ExprAssign(ref expr) => self.check_expr_assign(expr),
             ExprUn(ref expr) => self.check_expr_un(expr),
             ExprBin(ref expr) => self.check_expr_bin(expr),
@@ -1378,18 +2197,22 @@ impl<'a, 'ast> Visitor<'ast> for TypeCheck<'a, 'ast> {
             ExprConv(ref expr) => self.check_expr_conv(expr),
             ExprTry(ref expr) => self.check_expr_try(expr),
             ExprLambda(ref expr) => self.check_expr_lambda(expr),
+            ExprMatch(ref expr) => self.check_expr_match(expr),
+            ExprTuple(ref expr) => self.check_expr_tuple(expr),
+            ExprTupleElement(ref expr) => self.check_expr_tuple_element(expr),
         }
     }
 
     fn visit_stmt(&mut self, s: &'ast Stmt) {
         match *s {
             StmtVar(ref stmt) => self.check_stmt_var(stmt),
+            StmtLetTuple(ref stmt) => self.check_stmt_let_tuple(stmt),
             StmtWhile(ref stmt) => self.check_stmt_while(stmt),
             StmtFor(ref stmt) => self.check_stmt_for(stmt),
             StmtIf(ref stmt) => self.check_stmt_if(stmt),
             StmtReturn(ref stmt) => self.check_stmt_return(stmt),
             StmtThrow(ref stmt) => self.check_stmt_throw(stmt),
-            StmtSpawn(_) => unimplemented!(),
+            StmtSpawn(ref stmt) => self.check_stmt_spawn(stmt),
             StmtDefer(ref stmt) => self.check_stmt_defer(stmt),
             StmtDo(ref stmt) => self.check_stmt_do(stmt),
 
@@ -1399,10 +2222,38 @@ impl<'a, 'ast> Visitor<'ast> for TypeCheck<'a, 'ast> {
             StmtLoop(_) => visit::walk_stmt(self, s),
             StmtExpr(_) => visit::walk_stmt(self, s),
             StmtBlock(_) => visit::walk_stmt(self, s),
+            StmtError(_) => visit::walk_stmt(self, s),
         }
     }
 }
 
+// payloads of nested enums are compared as well, `visited`
+// stops the recursion for enums that contain themselves
+fn enum_comparable(ctxt: &SemContext, enum_id: EnumId, visited: &mut Vec<EnumId>) -> bool {
+    if visited.contains(&enum_id) {
+        return true;
+    }
+
+    visited.push(enum_id);
+
+    let types = {
+        let xenum = ctxt.enums.idx(enum_id);
+        let xenum = xenum.lock();
+
+        xenum
+            .variants
+            .iter()
+            .flat_map(|variant| variant.types.iter().cloned())
+            .collect::<Vec<_>>()
+    };
+
+    types.into_iter().all(|ty| match ty {
+        BuiltinType::Struct(_, _) | BuiltinType::Tuple(_) => false,
+        BuiltinType::Enum(enum_id) => enum_comparable(ctxt, enum_id, visited),
+        _ => true,
+    })
+}
+
 fn args_compatible(
     ctxt: &SemContext,
     def: &[BuiltinType],
@@ -1441,6 +2292,7 @@ fn arg_allows(
         | BuiltinType::Byte
         | BuiltinType::Char
         | BuiltinType::Struct(_, _)
+        | BuiltinType::Enum(_)
         | BuiltinType::Int
         | BuiltinType::Long
         | BuiltinType::Float
@@ -1534,6 +2386,24 @@ fn arg_allows(
             //                             sub class for return type
             def == arg
         }
+
+        BuiltinType::Tuple(tuple_id) => {
+            let other_id = match arg {
+                BuiltinType::Tuple(other_id) => other_id,
+                _ => return false,
+            };
+
+            let elements = ctxt.tuple_types.lock().get(tuple_id);
+            let other_elements = ctxt.tuple_types.lock().get(other_id);
+
+            if elements.len() != other_elements.len() {
+                return false;
+            }
+
+            elements.iter().zip(other_elements.iter()).all(|(&e, &o)| {
+                arg_allows(ctxt, e, o, global_cls_id, global_fct_id, cls_tps, fct_tps)
+            })
+        }
     }
 }
 
@@ -1876,6 +2746,22 @@ impl<'a, 'ast> MethodLookup<'a, 'ast> {
         let fct = self.ctxt.fcts.idx(fct_id);
         let fct = fct.read();
 
+        // constructors can be called whenever the class is accessible
+        let is_member = match kind {
+            LookupKind::Method(_) | LookupKind::Static(_) => true,
+            _ => false,
+        };
+
+        if let FctParent::Class(cls_id) = fct.parent {
+            if is_member {
+                let cls_name = self.ctxt.classes.idx(cls_id).read().name;
+                let pos = self.pos.expect("pos not set");
+                semck::check_member_access(
+                    self.ctxt, cls_name, fct.module, fct.is_pub, fct.name, pos,
+                );
+            }
+        }
+
         let cls_id = match fct.parent {
             FctParent::Class(cls_id) => Some(cls_id),
             FctParent::Impl(impl_id) => {
@@ -2069,12 +2955,19 @@ impl<'a, 'ast> MethodLookup<'a, 'ast> {
             }
         }
 
-        let cls_id = ty.cls_id(self.ctxt).unwrap();
-        let cls = self.ctxt.classes.idx(cls_id);
-        let cls = cls.read();
+        // structs don't implement any traits
+        let traits = match ty.cls_id(self.ctxt) {
+            Some(cls_id) => {
+                let cls = self.ctxt.classes.idx(cls_id);
+                let cls = cls.read();
+                cls.traits.clone()
+            }
+
+            None => Vec::new(),
+        };
 
         for &trait_bound in &tp.trait_bounds {
-            if !cls.traits.contains(&trait_bound) {
+            if !traits.contains(&trait_bound) {
                 self.fail_trait_bound(trait_bound, ty);
                 succeeded = false;
             }
@@ -2151,6 +3044,24 @@ impl<'a, 'ast> MethodLookup<'a, 'ast> {
     }
 }
 
+// name of the method implementing an arithmetic or bitwise operator
+fn bin_op_method(op: BinOp) -> &'static str {
+    match op {
+        BinOp::Add => "plus",
+        BinOp::Sub => "minus",
+        BinOp::Mul => "times",
+        BinOp::Div => "div",
+        BinOp::Mod => "mod",
+        BinOp::BitOr => "bitwiseOr",
+        BinOp::BitAnd => "bitwiseAnd",
+        BinOp::BitXor => "bitwiseXor",
+        BinOp::ShiftL => "shiftLeft",
+        BinOp::ShiftR => "shiftRight",
+        BinOp::UnShiftR => "unsignedShiftRight",
+        BinOp::Cmp(_) | BinOp::Or | BinOp::And => unreachable!(),
+    }
+}
+
 fn lookup_method<'ast>(
     ctxt: &SemContext<'ast>,
     object_type: BuiltinType,
@@ -2241,7 +3152,31 @@ fn replace_type_param(
             BuiltinType::Class(cls_id, list_id)
         }
 
-        BuiltinType::Lambda(_) => unimplemented!(),
+        BuiltinType::Lambda(lambda_id) => {
+            let lambda = ctxt.lambda_types.lock().get(lambda_id);
+
+            let params = lambda
+                .params()
+                .iter()
+                .map(|&p| replace_type_param(ctxt, p, cls_tp, fct_tp))
+                .collect::<Vec<_>>();
+            let ret = replace_type_param(ctxt, lambda.ret(), cls_tp, fct_tp);
+
+            let lambda_id = ctxt.lambda_types.lock().insert(params, ret);
+            BuiltinType::Lambda(lambda_id)
+        }
+
+        BuiltinType::Tuple(tuple_id) => {
+            let elements = ctxt.tuple_types.lock().get(tuple_id);
+
+            let elements = elements
+                .iter()
+                .map(|&e| replace_type_param(ctxt, e, cls_tp, fct_tp))
+                .collect::<Vec<_>>();
+
+            let tuple_id = ctxt.tuple_types.lock().insert(elements);
+            BuiltinType::Tuple(tuple_id)
+        }
 
         _ => ty,
     }
@@ -2285,6 +3220,45 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_struct_field() {
+        ok("struct Foo { a: int } fun f(x: Foo) -> int { return x.a; }");
+        ok("struct Foo { a: int, b: Bar } struct Bar { c: bool }
+            fun f(x: Foo) -> bool { return x.b.c; }");
+        ok("struct Foo { a: int } class Bar(let foo: Foo) fun f(x: Bar) -> int { return x.foo.a; }");
+        err(
+            "struct Foo { a: int } fun f(x: Foo) -> int { return x.b; }",
+            pos(1, 54),
+            Msg::UnknownField("b".into(), "Foo".into()),
+        );
+    }
+
+    #[test]
+    fn test_struct_field_assign() {
+        ok("struct Foo { a: int } fun f() { var x = Foo { a: 1 }; x.a = 2; }");
+        ok("struct Foo { a: int, b: Bar } struct Bar { c: int } fun f(var x: Foo) { x.b.c = 2; }");
+        ok("struct Foo { a: int } class Bar(var foo: Foo) fun f(x: Bar) { x.foo.a = 2; }");
+        err(
+            "struct Foo { a: int } fun f(x: Foo) { x.a = 2; }",
+            pos(1, 43),
+            Msg::LetReassigned,
+        );
+        err(
+            "struct Foo { a: int } fun g() -> Foo { return Foo { a: 1 }; } fun f() { g().a = 2; }",
+            pos(1, 79),
+            Msg::LvalueExpected,
+        );
+    }
+
+    #[test]
+    fn test_struct_ident_with_fields() {
+        err(
+            "struct Foo { a: int } fun f() { let x = Foo; }",
+            pos(1, 41),
+            Msg::StructFieldNotInitialized("Foo".into(), "a".into()),
+        );
+    }
+
     #[test]
     fn type_object_field_without_self() {
         err(
@@ -2536,6 +3510,98 @@ mod tests {
         err("fun f() { 1 = 3; }", pos(1, 13), Msg::LvalueExpected);
     }
 
+    #[test]
+    fn type_assign_path() {
+        ok_modules(&[
+            ("", "fun f() { util::counter = 5; util::counter += 1; }"),
+            ("util", "pub var counter: int;"),
+        ]);
+        err_modules(
+            &[
+                ("", "fun f() { util::counter = \"a\"; }"),
+                ("util", "pub var counter: int;"),
+            ],
+            pos(1, 25),
+            Msg::AssignType("util::counter".into(), "int".into(), "Str".into()),
+        );
+        err_modules(
+            &[
+                ("", "fun f() { util::counter = 5; }"),
+                ("util", "pub let counter: int;"),
+            ],
+            pos(1, 25),
+            Msg::LetReassigned,
+        );
+        err_modules(
+            &[
+                ("", "fun f() { util::ONE = 5; }"),
+                ("util", "pub const ONE: int = 1;"),
+            ],
+            pos(1, 21),
+            Msg::AssignmentToConst,
+        );
+        err(
+            "enum Color { Red } fun f() { Color::Red = Color::Red; }",
+            pos(1, 41),
+            Msg::LvalueExpected,
+        );
+    }
+
+    #[test]
+    fn type_member_access() {
+        ok_modules(&[
+            (
+                "",
+                "fun f(a: util::A) -> int { a.g(); util::A::h(); return a.x + a.y; }",
+            ),
+            (
+                "util",
+                "pub class A(pub let x: int) {
+                    pub var y: int;
+                    pub fun g() {}
+                    pub static fun h() {}
+                }",
+            ),
+        ]);
+        ok_modules(&[(
+            "util",
+            "class A(let x: int) { fun g() -> int { return self.x; } }
+             fun f(a: A) -> int { a.g(); return a.x; }",
+        )]);
+        err_modules(
+            &[
+                ("", "fun f(a: util::A) { a.g(); }"),
+                ("util", "pub class A { fun g() {} }"),
+            ],
+            pos(1, 22),
+            Msg::NotAccessible("util::A::g".into()),
+        );
+        err_modules(
+            &[
+                ("", "fun f() { util::A::h(); }"),
+                ("util", "pub class A { static fun h() {} }"),
+            ],
+            pos(1, 11),
+            Msg::NotAccessible("util::A::h".into()),
+        );
+        err_modules(
+            &[
+                ("", "fun f(a: util::A) -> int { return a.x; }"),
+                ("util", "pub class A(let x: int)"),
+            ],
+            pos(1, 36),
+            Msg::NotAccessible("util::A::x".into()),
+        );
+        err_modules(
+            &[
+                ("", "fun f(a: util::A) { a.x = 1; }"),
+                ("util", "pub class A { var x: int; }"),
+            ],
+            pos(1, 22),
+            Msg::NotAccessible("util::A::x".into()),
+        );
+    }
+
     #[test]
     fn type_un_op() {
         ok("fun f(a: int) { !a; -a; +a; }");
@@ -3071,6 +4137,20 @@ mod tests {
         );
     }
 
+    #[test]
+    fn try_opt() {
+        ok("fun one() throws -> Str { return \"1\"; }
+            fun me() -> Str { return try? one(); }");
+        ok("fun one() throws {}
+            fun me() { try? one(); }");
+        err(
+            "fun one() throws -> int { return 1; }
+             fun me() -> int { return try? one(); }",
+            pos(2, 39),
+            Msg::TryOptValueType("int".into()),
+        );
+    }
+
     #[test]
     fn struct_lit() {
         ok("struct Foo {} fun foo() -> Foo { return Foo; }");
@@ -3088,6 +4168,50 @@ mod tests {
         );
     }
 
+    #[test]
+    fn tuple() {
+        ok("fun f() -> (int, bool) { return (1, true); }");
+        ok("fun f(t: (int, Str)) -> Str { return t.1; }");
+        ok("fun f() -> int { let (a, b) = (1, 2L); return a; }");
+        ok("class A fun f() -> (int, A) { return (1, nil); }");
+        ok("fun f() -> int { return g((1, (2, 3))).1.0; }
+            fun g(t: (int, (int, int))) -> (int, (int, int)) { return t; }");
+        err(
+            "fun f(t: (int, bool)) -> int { return t.2; }",
+            pos(1, 40),
+            Msg::UnknownField("2".into(), "(int, bool)".into()),
+        );
+        err(
+            "fun f() -> (int, int) { return (1, true); }",
+            pos(1, 25),
+            Msg::ReturnType("(int, int)".into(), "(int, bool)".into()),
+        );
+    }
+
+    #[test]
+    fn let_tuple() {
+        err(
+            "fun f() { let (a, b) = (1, 2, 3); }",
+            pos(1, 11),
+            Msg::LetTupleMismatch("(int, int, int)".into(), 2),
+        );
+        err(
+            "fun f() { let (a, b) = 1; }",
+            pos(1, 11),
+            Msg::LetTupleMismatch("int".into(), 2),
+        );
+        err(
+            "fun f() { let (a, b): (int, int) = (1, true); }",
+            pos(1, 11),
+            Msg::AssignType("(a, b)".into(), "(int, int)".into(), "(int, bool)".into()),
+        );
+        err(
+            "fun f() { let (a, b) = (1, nil); }",
+            pos(1, 19),
+            Msg::VarNeedsTypeInfo("b".into()),
+        );
+    }
+
     #[test]
     fn lit_long() {
         ok("fun f() -> long { return 1L; }");
@@ -3583,6 +4707,75 @@ mod tests {
         );
     }
 
+    #[test]
+    fn lambda_call() {
+        ok("fun f() { let x = || {}; x(); }");
+        ok("fun f() -> int { let x = |a: int| -> int { return a; }; return x(1); }");
+        ok("fun f(x: (int, int) -> int) -> int { return x(1, 2); }");
+        err(
+            "fun f() { let x = |a: int| {}; x(true); }",
+            pos(1, 32),
+            Msg::ParamTypesIncompatible("x".into(), vec!["int".into()], vec!["bool".into()]),
+        );
+        err(
+            "fun f() { let x = 1; x(); }",
+            pos(1, 22),
+            Msg::LambdaExpected("x".into()),
+        );
+    }
+
+    #[test]
+    fn lambda_capture() {
+        ok("fun f() -> int { let x = 1; let y = || -> int { return x; }; return y(); }");
+        ok("fun f(a: int) { let x = || -> int { return a; }; }");
+        ok("fun f() { let x = 1; let y = || { let z = || -> int { return x; }; }; }");
+        ok("fun f() { let x = 1; let y = |x: bool| -> bool { return x; }; }");
+        ok("class Foo(let a: int) { fun f() -> () -> int { return || -> int { return self.a; }; } }");
+        err(
+            "fun f() { var x = 1; let y = || { x = 2; }; }",
+            pos(1, 37),
+            Msg::CapturedVarReassigned("x".into()),
+        );
+        err(
+            "fun f() { let x = 1; let y = || { x = 2; }; }",
+            pos(1, 37),
+            Msg::CapturedVarReassigned("x".into()),
+        );
+        err(
+            "struct Foo { a: int } fun f() { var x = Foo { a: 1 }; let y = || { x.a = 2; }; }",
+            pos(1, 72),
+            Msg::CapturedVarReassigned("x".into()),
+        );
+        ok("class Foo(var a: int) { fun f() { let y = || { self.a = 2; }; } }");
+        err(
+            "fun f() { let x = || { self; }; }",
+            pos(1, 24),
+            Msg::ThisUnavailable,
+        );
+    }
+
+    #[test]
+    fn spawn() {
+        ok("fun f() { spawn { println(\"hello\"); } }");
+        ok("fun f() { let x = 1; spawn { let y = x + 1; } }");
+        ok("fun f() { spawn || {}; }");
+        err(
+            "fun f() { spawn 1; }",
+            pos(1, 11),
+            Msg::SpawnExpected("int".into()),
+        );
+        err(
+            "fun f() { spawn |a: int| {}; }",
+            pos(1, 11),
+            Msg::SpawnExpected("(int) -> ()".into()),
+        );
+        err(
+            "fun f() { spawnThread(|| {}); }",
+            pos(1, 11),
+            Msg::UnknownFunction("spawnThread".into()),
+        );
+    }
+
     #[test]
     fn generic_trait_method_call() {
         ok("trait Foo { fun bar(); }
@@ -3716,6 +4909,167 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_enum_value() {
+        ok("enum Color { Red, Green } fun f() -> Color { return Color::Green; }");
+        ok("enum Shape { Circle(int), Rect(int, int) }
+            fun f() -> Shape { return Shape::Rect(1, 2); }");
+        err(
+            "enum Shape { Circle(int) } fun f() { Shape::Circle(true); }",
+            pos(1, 38),
+            Msg::EnumArgsIncompatible(
+                "Shape".into(),
+                "Circle".into(),
+                vec!["int".into()],
+                vec!["bool".into()],
+            ),
+        );
+        err(
+            "enum Shape { Circle(int) } fun f() { Shape::Circle; }",
+            pos(1, 38),
+            Msg::EnumArgsIncompatible("Shape".into(), "Circle".into(), vec!["int".into()], vec![]),
+        );
+        err(
+            "enum Color { Red } fun f() -> int { return Color::Red; }",
+            pos(1, 37),
+            Msg::ReturnType("int".into(), "Color".into()),
+        );
+    }
+
+    #[test]
+    fn test_enum_equals() {
+        ok("enum Color { Red, Green } fun f(c: Color) -> bool { return c == Color::Red; }");
+        ok("enum Color { Red, Green } fun f(c: Color) -> bool { return c != Color::Red; }");
+        ok("enum Shape { Circle(int), Named(Str), Empty }
+            fun f(s: Shape) -> bool { return s == Shape::Circle(1); }");
+        ok("enum List { Cons(int, List), Nil }
+            fun f(l: List) -> bool { return l == List::Nil; }");
+        err(
+            "enum Shape { Point((int, int)), Empty }
+            fun f(s: Shape) -> bool { return s == Shape::Empty; }",
+            pos(2, 48),
+            Msg::BinOpType("==".into(), "Shape".into(), "Shape".into()),
+        );
+        err(
+            "enum A { X } enum B { Y } fun f() -> bool { return A::X == B::Y; }",
+            pos(1, 57),
+            Msg::BinOpType("==".into(), "A".into(), "B".into()),
+        );
+    }
+
+    #[test]
+    fn test_match() {
+        ok("fun f(x: int) -> int { return match x { 1 => 10, -2 => 20, _ => 0 }; }");
+        ok("fun f(x: long) -> int { return match x { 1L => 10, _ => 0 }; }");
+        ok("fun f(x: char) -> int { return match x { 'a' => 10, 'b' => 20, _ => 0 }; }");
+        ok("fun f(x: bool) -> int { return match x { true => 1, false => 0 }; }");
+        ok("fun f(x: Str) -> int { return match x { \"a\" => 1, \"b\" => 2, _ => 0 }; }");
+        ok("open class A class B: A fun f(x: A) -> int { return match x { b: B => 1, a: A => 2 }; }");
+        ok("open class A class B: A fun f(x: A) -> A { return match x { b: B => b, _ => x }; }");
+        ok("fun f(x: int) -> Str { return match x { 1 => nil, _ => \"a\" }; }");
+
+        err(
+            "fun f(x: int) -> int { return match x { 1 => 10, 2 => 20 }; }",
+            pos(1, 31),
+            Msg::MatchNotExhaustive,
+        );
+        err(
+            "fun f(x: float) -> int { return match x { _ => 0 }; }",
+            pos(1, 33),
+            Msg::MatchTypeUnsupported("float".into()),
+        );
+        err(
+            "fun f(x: int) -> int { return match x { 'a' => 1, _ => 0 }; }",
+            pos(1, 41),
+            Msg::MatchPatternIncompatible("char".into(), "int".into()),
+        );
+        err(
+            "class A class B fun f(x: A) -> int { return match x { b: B => 1, _ => 2 }; }",
+            pos(1, 55),
+            Msg::MatchPatternIncompatible("B".into(), "A".into()),
+        );
+        err(
+            "fun f(x: int) -> int { return match x { 1 => 1, _ => false }; }",
+            pos(1, 54),
+            Msg::MatchArmsIncompatible("int".into(), "bool".into()),
+        );
+    }
+
+    #[test]
+    fn test_match_enum() {
+        ok("enum Color { Red, Green }
+            fun f(x: Color) -> int { return match x { Color::Red => 1, Color::Green => 2 }; }");
+        ok("enum Shape { Circle(int), Rect(int, long), Empty }
+            fun f(x: Shape) -> long {
+                return match x { Shape::Circle(r) => r.toLong(), Shape::Rect(_, h) => h, _ => 0L };
+            }");
+        err(
+            "enum Color { Red, Green }
+            fun f(x: Color) -> int { return match x { Color::Red => 1 }; }",
+            pos(2, 45),
+            Msg::MatchNotExhaustive,
+        );
+        err(
+            "enum Color { Red, Green } enum Size { Small }
+            fun f(x: Color) -> int { return match x { Size::Small => 1, _ => 2 }; }",
+            pos(2, 55),
+            Msg::MatchPatternIncompatible("Size".into(), "Color".into()),
+        );
+        err(
+            "enum Shape { Rect(int, int) }
+            fun f(x: Shape) -> int { return match x { Shape::Rect(w) => w }; }",
+            pos(2, 55),
+            Msg::MatchPatternWrongNumberOfBindings("Shape".into(), "Rect".into(), 2, 1),
+        );
+        err(
+            "enum Shape { Circle(int) }
+            fun f(x: Shape) -> bool { return match x { Shape::Circle(r) => r }; }",
+            pos(2, 39),
+            Msg::ReturnType("bool".into(), "int".into()),
+        );
+        err(
+            "enum Color { Red }
+            fun f(x: Color) -> int { return match x { Color::Blue => 1, _ => 2 }; }",
+            pos(2, 55),
+            Msg::UnknownEnumValue("Color".into(), "Blue".into()),
+        );
+    }
+
+    #[test]
+    fn test_match_unreachable_arm() {
+        err(
+            "fun f(x: int) -> int { return match x { 1 => 10, 1 => 20, _ => 0 }; }",
+            pos(1, 50),
+            Msg::MatchArmUnreachable,
+        );
+        err(
+            "fun f(x: int) -> int { return match x { _ => 0, 2 => 1 }; }",
+            pos(1, 49),
+            Msg::MatchArmUnreachable,
+        );
+        err(
+            "fun f(x: bool) -> int { return match x { true => 1, false => 0, _ => 2 }; }",
+            pos(1, 65),
+            Msg::MatchArmUnreachable,
+        );
+        err(
+            "fun f(x: Str) -> int { return match x { \"a\" => 1, \"a\" => 2, _ => 0 }; }",
+            pos(1, 51),
+            Msg::MatchArmUnreachable,
+        );
+        err(
+            "open class A class B: A fun f(x: A) -> int { return match x { a: A => 1, b: B => 2 }; }",
+            pos(1, 74),
+            Msg::MatchArmUnreachable,
+        );
+        err(
+            "enum Color { Red, Green }
+            fun f(x: Color) -> int { return match x { Color::Red => 1, Color::Red => 2, _ => 3 }; }",
+            pos(2, 72),
+            Msg::MatchArmUnreachable,
+        );
+    }
+
     #[test]
     fn test_ctor_with_type_param() {
         err(
@@ -3732,4 +5086,41 @@ mod tests {
             Msg::UnknownCtor("Bar".into(), vec!["int".into()]),
         );
     }
+
+    #[test]
+    fn test_compound_assign() {
+        ok("fun f() { var a = 1; a += 2; a <<= 1; a %= 3; }");
+        ok("fun f() { var a = 1.0; a *= 2.0; a /= 4.0; }");
+        ok("fun f() { var s = \"a\"; s += \"b\"; }");
+        ok("class Foo { var a: long = 0L; } fun f(x: Foo) { x.a |= 1L; }");
+        ok("fun f(a: Array<int>) { a[0] -= 1; a[1] ^= a[0]; }");
+        ok("class Foo {
+                fun plus(x: int) -> Foo { return self; }
+            }
+            fun f() { var x = Foo(); x += 1; }");
+
+        err(
+            "fun f() { let a = 1; a += 2; }",
+            pos(1, 24),
+            Msg::LetReassigned,
+        );
+        err(
+            "fun f() { var a = 1; a += true; }",
+            pos(1, 24),
+            Msg::BinOpType("+=".into(), "int".into(), "bool".into()),
+        );
+        err(
+            "fun f(a: Array<bool>) { a[0] -= 1; }",
+            pos(1, 30),
+            Msg::BinOpType("-=".into(), "bool".into(), "int".into()),
+        );
+        err(
+            "class Foo {
+                fun plus(x: int) -> int { return x; }
+            }
+            fun f() { var x = Foo(); x += 1; }",
+            pos(4, 40),
+            Msg::AssignType("x".into(), "Foo".into(), "int".into()),
+        );
+    }
 }
@@ -8,9 +8,9 @@ use std::str;
 use std::thread;
 use std::time::Duration;
 
-use class::TypeParams;
+use class::{Class, ClassDef, TypeParams};
 use ctxt::exception_set;
-use ctxt::get_vm;
+use ctxt::{get_vm, FctId, VM};
 use exception::{alloc_exception, stacktrace_from_last_dtn};
 use gc::{Address, GcReason};
 use handle::root;
@@ -18,6 +18,7 @@ use object::{ByteArray, Obj, Ref, Str};
 use os::signal::Trap;
 use sym::Sym::SymFct;
 use threads::{DoraThread, THREAD};
+use ty::BuiltinType;
 
 pub extern "C" fn byte_to_string(val: u8) -> Ref<Str> {
     let buffer = val.to_string();
@@ -113,6 +114,39 @@ pub extern "C" fn throw_native(val: bool) {
     }
 }
 
+// weighs each argument with its position, a mix-up of arguments passed
+// on the stack changes the result
+pub extern "C" fn sum_native(
+    a0: i32,
+    b0: f64,
+    a1: i32,
+    b1: f64,
+    a2: i32,
+    b2: f64,
+    a3: i32,
+    b3: f64,
+    a4: i32,
+    b4: f64,
+    a5: i32,
+    b5: f64,
+    a6: i32,
+    b6: f64,
+    a7: i32,
+    b7: f64,
+    a8: i32,
+    b8: f64,
+) -> f64 {
+    let ints = [a0, a1, a2, a3, a4, a5, a6, a7, a8];
+    let doubles = [b0, b1, b2, b3, b4, b5, b6, b7, b8];
+    let mut sum = 0.0;
+
+    for (idx, (&a, &b)) in ints.iter().zip(doubles.iter()).enumerate() {
+        sum += (2 * idx + 1) as f64 * a as f64 + (2 * idx + 2) as f64 * b;
+    }
+
+    sum
+}
+
 pub extern "C" fn call(fct: Ref<Str>) {
     let fct_name = fct.to_cstring();
     let fct_name = fct_name.to_str().unwrap();
@@ -182,6 +216,76 @@ pub extern "C" fn gc_alloc(size: usize, array_ref: bool) -> *mut Obj {
     vm.gc.alloc(vm, size, array_ref).to_mut_ptr()
 }
 
+// variants of enums are equal if they have the same class and equal payloads,
+// strings are compared by content, other objects by identity
+pub extern "C" fn enum_equals(lhs: Ref<Obj>, rhs: Ref<Obj>) -> bool {
+    let vm = get_vm();
+
+    enum_values_equal(
+        vm,
+        Address::from_ptr(lhs.raw()),
+        Address::from_ptr(rhs.raw()),
+    )
+}
+
+fn enum_values_equal(vm: &VM, lhs: Address, rhs: Address) -> bool {
+    let cls = lhs.to_obj().header().vtbl().classptr();
+
+    if cls != rhs.to_obj().header().vtbl().classptr() {
+        return false;
+    }
+
+    let cls = unsafe { &*cls };
+
+    // the first field stores the tag of the variant
+    for field in &cls.fields[1..] {
+        let lhs = lhs.offset(field.offset as usize);
+        let rhs = rhs.offset(field.offset as usize);
+
+        let equal = unsafe {
+            match field.ty {
+                BuiltinType::Unit => true,
+                BuiltinType::Bool | BuiltinType::Byte => *lhs.to_ptr::<u8>() == *rhs.to_ptr::<u8>(),
+                BuiltinType::Char | BuiltinType::Int => {
+                    *lhs.to_ptr::<i32>() == *rhs.to_ptr::<i32>()
+                }
+                BuiltinType::Long => *lhs.to_ptr::<i64>() == *rhs.to_ptr::<i64>(),
+                BuiltinType::Float => *lhs.to_ptr::<f32>() == *rhs.to_ptr::<f32>(),
+                BuiltinType::Double => *lhs.to_ptr::<f64>() == *rhs.to_ptr::<f64>(),
+                BuiltinType::Struct(_, _) | BuiltinType::Tuple(_) => unreachable!(),
+                ty => references_equal(vm, ty, *lhs.to_ptr::<Address>(), *rhs.to_ptr::<Address>()),
+            }
+        };
+
+        if !equal {
+            return false;
+        }
+    }
+
+    true
+}
+
+fn references_equal(vm: &VM, ty: BuiltinType, lhs: Address, rhs: Address) -> bool {
+    if lhs == rhs {
+        return true;
+    } else if lhs.is_null() || rhs.is_null() {
+        return false;
+    }
+
+    match ty {
+        BuiltinType::Enum(_) => enum_values_equal(vm, lhs, rhs),
+
+        BuiltinType::Class(cls_id, _) if cls_id == vm.vips.str_class => {
+            let lhs = unsafe { &*lhs.to_ptr::<Str>() };
+            let rhs = unsafe { &*rhs.to_ptr::<Str>() };
+
+            lhs.content() == rhs.content()
+        }
+
+        _ => false,
+    }
+}
+
 pub extern "C" fn gc_collect() {
     let vm = get_vm();
     vm.gc.collect(vm, GcReason::ForceCollect);
@@ -271,6 +375,47 @@ pub extern "C" fn call3(addr: *const u8, arg1: usize, arg2: usize, arg3: usize)
     fct(arg1, arg2, arg3)
 }
 
+pub extern "C" fn call4(
+    addr: *const u8,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+) -> usize {
+    let fct: extern "C" fn(usize, usize, usize, usize) -> usize = unsafe { mem::transmute(addr) };
+
+    fct(arg1, arg2, arg3, arg4)
+}
+
+pub extern "C" fn call5(
+    addr: *const u8,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+    arg5: usize,
+) -> usize {
+    let fct: extern "C" fn(usize, usize, usize, usize, usize) -> usize =
+        unsafe { mem::transmute(addr) };
+
+    fct(arg1, arg2, arg3, arg4, arg5)
+}
+
+pub extern "C" fn call6(
+    addr: *const u8,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+    arg5: usize,
+    arg6: usize,
+) -> usize {
+    let fct: extern "C" fn(usize, usize, usize, usize, usize, usize) -> usize =
+        unsafe { mem::transmute(addr) };
+
+    fct(arg1, arg2, arg3, arg4, arg5, arg6)
+}
+
 pub extern "C" fn native_malloc(size: usize) -> *const u8 {
     unsafe { libc::malloc(size) as *const u8 }
 }
@@ -303,6 +448,34 @@ pub extern "C" fn trap(trap_id: u32) {
 }
 
 pub extern "C" fn spawn_thread(obj: Ref<Obj>) {
+    start_thread(obj, |vm, cls, _| {
+        let name = vm.interner.intern("run");
+        let run = cls
+            .find_method(vm, name, false)
+            .expect("run() method not found");
+
+        (run, TypeParams::empty())
+    });
+}
+
+pub extern "C" fn spawn_lambda(obj: Ref<Obj>) {
+    // lambdas are compiled for the type params of their closure
+    start_thread(obj, |vm, cls, cls_def| {
+        let lambda = cls
+            .methods
+            .iter()
+            .cloned()
+            .find(|&fct_id| vm.fcts.idx(fct_id).read().is_lambda)
+            .expect("lambda not found in closure");
+
+        (lambda, cls_def.type_params.clone())
+    });
+}
+
+fn start_thread<F>(obj: Ref<Obj>, find_main: F)
+where
+    F: FnOnce(&VM, &Class, &ClassDef) -> (FctId, TypeParams) + Send + 'static,
+{
     use baseline;
     use exception::DoraToNativeInfo;
 
@@ -316,13 +489,11 @@ pub extern "C" fn spawn_thread(obj: Ref<Obj>) {
             *tld.borrow_mut() = thread;
         });
 
-        let main = {
-            let cls_id = obj.header().vtbl().class().cls_id;
-            let cls = vm.classes.idx(cls_id);
+        let (main, fct_type_params) = {
+            let cls_def = obj.header().vtbl().class();
+            let cls = vm.classes.idx(cls_def.cls_id);
             let cls = cls.read();
-            let name = vm.interner.intern("run");
-            cls.find_method(vm, name, false)
-                .expect("run() method not found")
+            find_main(vm, &*cls, cls_def)
         };
 
         let tld = THREAD.with(|thread| {
@@ -334,16 +505,16 @@ pub extern "C" fn spawn_thread(obj: Ref<Obj>) {
 
         let fct_ptr = {
             let mut dtn = DoraToNativeInfo::new();
-            let type_params = TypeParams::empty();
+            let cls_type_params = TypeParams::empty();
 
             THREAD.with(|thread| {
                 thread.borrow().use_dtn(&mut dtn, || {
-                    baseline::generate(vm, main, &type_params, &type_params)
+                    baseline::generate(vm, main, &cls_type_params, &fct_type_params)
                 })
             })
         };
 
-        // execute the tread object's run-method
+        // execute the thread's main function
         let dora_entry_thunk = vm.dora_entry_thunk();
         let fct: extern "C" fn(Address, Address, Ref<Obj>) =
             unsafe { mem::transmute(dora_entry_thunk) };
@@ -1,4 +1,5 @@
-use std::collections::HashMap;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 
 use self::Sym::*;
 
@@ -9,15 +10,45 @@ use dora_parser::interner::Name;
 #[derive(Debug)]
 pub struct SymTable {
     levels: Vec<SymLevel>,
+    modules: Vec<ModuleLevel>,
+
+    // module of the code currently checked, top-level symbols
+    // are inserted into and looked up in this module first
+    module: Option<ModuleId>,
+
+    // names imported into a module and the imports that were looked up
+    imports: HashSet<(Option<ModuleId>, Name)>,
+    used_imports: RefCell<HashSet<(Option<ModuleId>, Name)>>,
 }
 
 impl SymTable {
     pub fn new() -> SymTable {
         SymTable {
             levels: vec![SymLevel::new()],
+            modules: Vec::new(),
+            module: None,
+            imports: HashSet::new(),
+            used_imports: RefCell::new(HashSet::new()),
         }
     }
 
+    pub fn add_module(&mut self, id: ModuleId) {
+        assert_eq!(id.idx(), self.modules.len());
+
+        self.modules.push(ModuleLevel {
+            level: SymLevel::new(),
+            public: HashSet::new(),
+        });
+    }
+
+    pub fn module(&self) -> Option<ModuleId> {
+        self.module
+    }
+
+    pub fn set_module(&mut self, module: Option<ModuleId>) {
+        self.module = module;
+    }
+
     pub fn push_level(&mut self) {
         self.levels.push(SymLevel::new());
     }
@@ -33,13 +64,67 @@ impl SymTable {
     }
 
     pub fn get(&self, name: Name) -> Option<Sym> {
-        for level in self.levels.iter().rev() {
+        self.get_with_level(name).map(|(sym, _)| sym)
+    }
+
+    // also returns the index of the level the symbol was found in
+    pub fn get_with_level(&self, name: Name) -> Option<(Sym, usize)> {
+        for (idx, level) in self.levels.iter().enumerate().skip(1).rev() {
             if let Some(val) = level.get(name) {
-                return Some(val.clone());
+                return Some((val.clone(), idx));
             }
         }
 
-        None
+        // symbols of the current module shadow the prelude
+        if let Some(module) = self.module {
+            if let Some(val) = self.modules[module.idx()].level.get(name) {
+                self.use_import(name);
+                return Some((val.clone(), 0));
+            }
+        }
+
+        let val = self.levels[0].get(name).map(|val| (val.clone(), 0));
+
+        // imports of the root module are stored with the prelude
+        if val.is_some() && self.module.is_none() {
+            self.use_import(name);
+        }
+
+        val
+    }
+
+    fn use_import(&self, name: Name) {
+        let key = (self.module, name);
+
+        if self.imports.contains(&key) {
+            self.used_imports.borrow_mut().insert(key);
+        }
+    }
+
+    pub fn import_used(&self, module: Option<ModuleId>, name: Name) -> bool {
+        self.used_imports.borrow().contains(&(module, name))
+    }
+
+    // finds a top-level symbol of the given module without looking
+    // into enclosing scopes, None stands for the root module
+    pub fn get_in_module(&self, module: Option<ModuleId>, name: Name) -> Option<Sym> {
+        let level = match module {
+            Some(module) => &self.modules[module.idx()].level,
+            None => &self.levels[0],
+        };
+
+        level.get(name).cloned()
+    }
+
+    pub fn is_public(&self, module: ModuleId, name: Name) -> bool {
+        self.modules[module.idx()].public.contains(&name)
+    }
+
+    // marks a top-level symbol of the current module as public
+    pub fn set_public(&mut self, name: Name) {
+        if let Some(module) = self.module {
+            self.modules[module.idx()].public.insert(name);
+        }
     }
 
     pub fn get_var(&self, name: Name) -> Option<VarId> {
@@ -58,13 +143,47 @@ impl SymTable {
         self.get(name).and_then(|n| n.to_struct())
     }
 
+    pub fn get_enum(&self, name: Name) -> Option<EnumId> {
+        self.get(name).and_then(|n| n.to_enum())
+    }
+
     pub fn get_trait(&self, name: Name) -> Option<TraitId> {
         self.get(name).and_then(|n| n.to_trait())
     }
 
+    // imported symbols are private to the importing module
+    pub fn insert_import(&mut self, name: Name, sym: Sym) -> Option<Sym> {
+        self.imports.insert((self.module, name));
+        self.insert(name, sym)
+    }
+
     pub fn insert(&mut self, name: Name, sym: Sym) -> Option<Sym> {
+        if self.levels.len() == 1 {
+            if let Some(module) = self.module {
+                return self.modules[module.idx()].level.insert(name, sym);
+            }
+        }
+
         self.levels.last_mut().unwrap().insert(name, sym)
     }
+
+    pub fn insert_in_module(
+        &mut self,
+        module: Option<ModuleId>,
+        name: Name,
+        sym: Sym,
+    ) -> Option<Sym> {
+        match module {
+            Some(module) => self.modules[module.idx()].level.insert(name, sym),
+            None => self.levels[0].insert(name, sym),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct ModuleLevel {
+    level: SymLevel,
+    public: HashSet<Name>,
 }
 
 #[derive(Debug)]
@@ -96,11 +215,14 @@ pub enum Sym {
     SymVar(VarId),
     SymClass(ClassId),
     SymStruct(StructId),
+    SymEnum(EnumId),
     SymTrait(TraitId),
     SymGlobal(GlobalId),
     SymClassTypeParam(ClassId, TypeParamId),
     SymFctTypeParam(FctId, TypeParamId),
     SymConst(ConstId),
+    SymTypeAlias(TypeAliasId),
+    SymModule(ModuleId),
 }
 
 impl Sym {
@@ -160,6 +282,20 @@ impl Sym {
         }
     }
 
+    pub fn is_enum(&self) -> bool {
+        match *self {
+            SymEnum(_) => true,
+            _ => false,
+        }
+    }
+
+    pub fn to_enum(&self) -> Option<EnumId> {
+        match *self {
+            SymEnum(id) => Some(id),
+            _ => None,
+        }
+    }
+
     pub fn is_trait(&self) -> bool {
         match *self {
             SymTrait(_) => true,
@@ -209,4 +345,32 @@ impl Sym {
             _ => None,
         }
     }
+
+    pub fn is_type_alias(&self) -> bool {
+        match *self {
+            SymTypeAlias(_) => true,
+            _ => false,
+        }
+    }
+
+    pub fn to_type_alias(&self) -> Option<TypeAliasId> {
+        match *self {
+            SymTypeAlias(id) => Some(id),
+            _ => None,
+        }
+    }
+
+    pub fn is_module(&self) -> bool {
+        match *self {
+            SymModule(_) => true,
+            _ => false,
+        }
+    }
+
+    pub fn to_module(&self) -> Option<ModuleId> {
+        match *self {
+            SymModule(id) => Some(id),
+            _ => None,
+        }
+    }
 }
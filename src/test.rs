@@ -1,4 +1,5 @@
 use dora_parser::ast::Ast;
+use dora_parser::error::diag::Diagnostic;
 use dora_parser::interner::Interner;
 use dora_parser::lexer::reader::Reader;
 use dora_parser::parser::{NodeIdGenerator, Parser};
@@ -7,6 +8,7 @@ use ctxt::VM;
 use driver::cmd::Args;
 use os;
 use semck;
+use semck::lintck;
 
 pub fn parse<F, T>(code: &'static str, f: F) -> T
 where
@@ -14,7 +16,7 @@ where
 {
     parse_with_errors(code, |vm| {
         if vm.diag.lock().has_errors() {
-            vm.diag.lock().dump();
+            vm.diag.lock().dump(vm.ast);
             println!("{}", code);
             panic!("unexpected error in test::parse()");
         }
@@ -24,6 +26,15 @@ where
 }
 
 pub fn parse_with_errors<F, T>(code: &'static str, f: F) -> T
+where
+    F: FnOnce(&VM) -> T,
+{
+    parse_modules_with_errors(&[("", code)], f)
+}
+
+// parses one file per module, modules are given by their
+// qualified name and the root module by the empty string
+pub fn parse_modules_with_errors<F, T>(files: &[(&'static str, &'static str)], f: F) -> T
 where
     F: FnOnce(&VM) -> T,
 {
@@ -32,23 +43,45 @@ where
     let id_generator = NodeIdGenerator::new();
     let mut interner = Interner::new();
     let mut ast = Ast::new();
+    let mut diag = Diagnostic::new();
     let args: Args = Default::default();
 
     for file in &["stdlib/prelude.dora", "stdlib/str.dora", "stdlib/test.dora"] {
         let reader = Reader::from_file(file).unwrap();
         let mut parser = Parser::new(reader, &id_generator, &mut ast, &mut interner);
-        parser.parse().unwrap()
+        parser.parse(&mut diag);
     }
 
     {
-        let reader = Reader::from_string(code);
+        let reader = Reader::from_file("stdlib/internal/thread.dora").unwrap();
         let mut parser = Parser::new(reader, &id_generator, &mut ast, &mut interner);
-        parser.parse().unwrap()
+        parser.parse(&mut diag);
+    }
+
+    ast.files.last_mut().unwrap().module = vec!["internal".to_string()];
+
+    for &(module, code) in files {
+        {
+            let reader = Reader::from_string(code);
+            let mut parser = Parser::new(reader, &id_generator, &mut ast, &mut interner);
+            parser.parse(&mut diag);
+        }
+
+        if !module.is_empty() {
+            let file = ast.files.last_mut().unwrap();
+            file.module = module.split("::").map(|name| name.to_string()).collect();
+        }
     }
 
+    assert!(!diag.has_errors());
+
     let mut vm = VM::new(args, &ast, interner);
 
     semck::check(&mut vm);
 
+    if !vm.diag.lock().has_errors() {
+        lintck::check(&vm);
+    }
+
     f(&vm)
 }
@@ -3,6 +3,7 @@ use std::cell::RefCell;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 
+use boots::interpreter::InterpreterFrames;
 use exception::DoraToNativeInfo;
 use gc::{Address, Region};
 use handle::HandleMemory;
@@ -71,6 +72,7 @@ pub struct DoraThread {
     pub state: AtomicUsize,
     pub saved_pc: AtomicUsize,
     pub saved_fp: AtomicUsize,
+    pub interpreter: InterpreterFrames,
 }
 
 unsafe impl Sync for DoraThread {}
@@ -85,6 +87,7 @@ impl DoraThread {
             state: AtomicUsize::new(ThreadState::Uninitialized as usize),
             saved_pc: AtomicUsize::new(0),
             saved_fp: AtomicUsize::new(0),
+            interpreter: InterpreterFrames::new(),
         })
     }
 
@@ -3,7 +3,7 @@ use std::sync::Arc;
 
 use class::{ClassId, TypeParamId, TypeParams};
 use ctxt::VM;
-use ctxt::{FctId, StructId, TraitId};
+use ctxt::{EnumId, FctId, StructDefId, StructId, TraitId};
 use mem;
 use semck;
 
@@ -41,6 +41,9 @@ pub enum BuiltinType {
     // some struct
     Struct(StructId, TypeListId),
 
+    // some enum
+    Enum(EnumId),
+
     // some trait
     Trait(TraitId),
 
@@ -50,6 +53,9 @@ pub enum BuiltinType {
 
     // some lambda
     Lambda(LambdaId),
+
+    // some tuple
+    Tuple(TupleId),
 }
 
 impl BuiltinType {
@@ -81,6 +87,32 @@ impl BuiltinType {
         }
     }
 
+    pub fn is_struct(&self) -> bool {
+        match *self {
+            BuiltinType::Struct(_, _) => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_tuple(&self) -> bool {
+        match *self {
+            BuiltinType::Tuple(_) => true,
+            _ => false,
+        }
+    }
+
+    // structs and tuples are both stored inline and passed by address
+    pub fn is_struct_or_tuple(&self) -> bool {
+        self.is_struct() || self.is_tuple()
+    }
+
+    pub fn is_enum(&self) -> bool {
+        match *self {
+            BuiltinType::Enum(_) => true,
+            _ => false,
+        }
+    }
+
     pub fn is_float(&self) -> bool {
         match self {
             &BuiltinType::Float | &BuiltinType::Double => true,
@@ -145,7 +177,43 @@ impl BuiltinType {
                 params.iter().any(|t| t.contains_type_param(vm))
             }
 
-            &BuiltinType::Lambda(_) => unimplemented!(),
+            &BuiltinType::Lambda(id) => {
+                let lambda = vm.lambda_types.lock().get(id);
+                lambda.params.iter().any(|t| t.contains_type_param(vm))
+                    || lambda.ret.contains_type_param(vm)
+            }
+
+            &BuiltinType::Tuple(id) => {
+                let elements = vm.tuple_types.lock().get(id);
+                elements.iter().any(|t| t.contains_type_param(vm))
+            }
+
+            _ => false,
+        }
+    }
+
+    pub fn contains_class_type_param(&self, vm: &VM) -> bool {
+        match self {
+            &BuiltinType::ClassTypeParam(_, _) => true,
+
+            &BuiltinType::Class(_, list_id) => {
+                let params = vm.lists.lock().get(list_id);
+                params.iter().any(|t| t.contains_class_type_param(vm))
+            }
+
+            &BuiltinType::Lambda(id) => {
+                let lambda = vm.lambda_types.lock().get(id);
+                lambda
+                    .params
+                    .iter()
+                    .any(|t| t.contains_class_type_param(vm))
+                    || lambda.ret.contains_class_type_param(vm)
+            }
+
+            &BuiltinType::Tuple(id) => {
+                let elements = vm.tuple_types.lock().get(id);
+                elements.iter().any(|t| t.contains_class_type_param(vm))
+            }
 
             _ => false,
         }
@@ -163,7 +231,9 @@ impl BuiltinType {
             | BuiltinType::Int
             | BuiltinType::Long
             | BuiltinType::Float
-            | BuiltinType::Double => true,
+            | BuiltinType::Double
+            | BuiltinType::Struct(_, _)
+            | BuiltinType::Tuple(_) => true,
             _ => false,
         }
     }
@@ -234,6 +304,11 @@ impl BuiltinType {
                     format!("{}<{}>", name, params)
                 }
             }
+            BuiltinType::Enum(id) => {
+                let xenum = vm.enums.idx(id);
+                let xenum = xenum.lock();
+                vm.interner.str(xenum.name).to_string()
+            }
             BuiltinType::Trait(tid) => {
                 let xtrait = vm.traits[tid].read();
                 vm.interner.str(xtrait.name).to_string()
@@ -262,6 +337,17 @@ impl BuiltinType {
 
                 format!("({}) -> {}", params, ret)
             }
+
+            BuiltinType::Tuple(id) => {
+                let elements = vm.tuple_types.lock().get(id);
+                let elements = elements
+                    .iter()
+                    .map(|ty| ty.name(vm))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+
+                format!("({})", elements)
+            }
         }
     }
 
@@ -275,7 +361,8 @@ impl BuiltinType {
             | BuiltinType::Bool
             | BuiltinType::Byte
             | BuiltinType::Char
-            | BuiltinType::Struct(_, _) => *self == other,
+            | BuiltinType::Struct(_, _)
+            | BuiltinType::Enum(_) => *self == other,
             BuiltinType::Int => *self == other,
             BuiltinType::Long => *self == other,
             BuiltinType::Float | BuiltinType::Double => *self == other,
@@ -296,6 +383,28 @@ impl BuiltinType {
                 //                             sub class for return type
                 *self == other
             }
+
+            BuiltinType::Tuple(id) => match other {
+                BuiltinType::Tuple(other_id) => {
+                    if id == other_id {
+                        return true;
+                    }
+
+                    let elements = vm.tuple_types.lock().get(id);
+                    let other_elements = vm.tuple_types.lock().get(other_id);
+
+                    elements.len() == other_elements.len()
+                        && elements.iter().zip(other_elements.iter()).all(|(e, o)| {
+                            if e.is_nil() {
+                                o.is_nil()
+                            } else {
+                                e.allows(vm, *o)
+                            }
+                        })
+                }
+
+                _ => false,
+            },
         }
     }
 
@@ -320,9 +429,10 @@ impl BuiltinType {
             BuiltinType::Double => 8,
             BuiltinType::Nil => panic!("no size for nil."),
             BuiltinType::This => panic!("no size for Self."),
-            BuiltinType::Class(_, _) | BuiltinType::Lambda(_) | BuiltinType::Ptr => {
-                mem::ptr_width()
-            }
+            BuiltinType::Class(_, _)
+            | BuiltinType::Lambda(_)
+            | BuiltinType::Enum(_)
+            | BuiltinType::Ptr => mem::ptr_width(),
             BuiltinType::Struct(sid, list_id) => {
                 let params = vm.lists.lock().get(list_id);
                 let sid = semck::specialize::specialize_struct_id_params(vm, sid, params);
@@ -331,6 +441,13 @@ impl BuiltinType {
 
                 struc.size
             }
+            BuiltinType::Tuple(id) => {
+                let sid = semck::specialize::specialize_tuple(vm, id);
+                let tuple = vm.struct_defs.idx(sid);
+                let tuple = tuple.lock();
+
+                tuple.size
+            }
             BuiltinType::Trait(_) => 2 * mem::ptr_width(),
             BuiltinType::ClassTypeParam(_, _) | BuiltinType::FctTypeParam(_, _) => {
                 panic!("no size for type variable.")
@@ -351,9 +468,10 @@ impl BuiltinType {
             BuiltinType::Double => 8,
             BuiltinType::Nil => panic!("no alignment for nil."),
             BuiltinType::This => panic!("no alignment for Self."),
-            BuiltinType::Class(_, _) | BuiltinType::Lambda(_) | BuiltinType::Ptr => {
-                mem::ptr_width()
-            }
+            BuiltinType::Class(_, _)
+            | BuiltinType::Lambda(_)
+            | BuiltinType::Enum(_)
+            | BuiltinType::Ptr => mem::ptr_width(),
             BuiltinType::Struct(sid, list_id) => {
                 let params = vm.lists.lock().get(list_id);
                 let sid = semck::specialize::specialize_struct_id_params(vm, sid, params);
@@ -362,6 +480,13 @@ impl BuiltinType {
 
                 struc.align
             }
+            BuiltinType::Tuple(id) => {
+                let sid = semck::specialize::specialize_tuple(vm, id);
+                let tuple = vm.struct_defs.idx(sid);
+                let tuple = tuple.lock();
+
+                tuple.align
+            }
             BuiltinType::Trait(_) => mem::ptr_width(),
             BuiltinType::ClassTypeParam(_, _) | BuiltinType::FctTypeParam(_, _) => {
                 panic!("no alignment for type variable.")
@@ -382,10 +507,13 @@ impl BuiltinType {
             BuiltinType::Double => MachineMode::Float64,
             BuiltinType::Nil => panic!("no machine mode for nil."),
             BuiltinType::This => panic!("no machine mode for Self."),
-            BuiltinType::Class(_, _) | BuiltinType::Lambda(_) | BuiltinType::Ptr => {
-                MachineMode::Ptr
-            }
-            BuiltinType::Struct(_, _) => panic!("no machine mode for struct."),
+            // enum values are objects on the heap
+            BuiltinType::Class(_, _)
+            | BuiltinType::Lambda(_)
+            | BuiltinType::Enum(_)
+            | BuiltinType::Ptr => MachineMode::Ptr,
+            // structs and tuples are passed around by their address
+            BuiltinType::Struct(_, _) | BuiltinType::Tuple(_) => MachineMode::Ptr,
             BuiltinType::Trait(_) => unimplemented!(),
             BuiltinType::ClassTypeParam(_, _) | BuiltinType::FctTypeParam(_, _) => {
                 panic!("no machine mode for type variable.")
@@ -394,7 +522,7 @@ impl BuiltinType {
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum MachineMode {
     Int8,
     Int32,
@@ -522,6 +650,68 @@ pub struct LambdaType {
     ret: BuiltinType,
 }
 
+impl LambdaType {
+    pub fn params(&self) -> &[BuiltinType] {
+        &self.params
+    }
+
+    pub fn ret(&self) -> BuiltinType {
+        self.ret
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct TupleId(usize);
+
+impl From<usize> for TupleId {
+    fn from(val: usize) -> TupleId {
+        TupleId(val)
+    }
+}
+
+pub struct TupleTypes {
+    types: HashMap<Arc<Vec<BuiltinType>>, TupleId>,
+    values: Vec<Arc<Vec<BuiltinType>>>,
+    layouts: Vec<Option<StructDefId>>,
+}
+
+impl TupleTypes {
+    pub fn new() -> TupleTypes {
+        TupleTypes {
+            types: HashMap::new(),
+            values: Vec::new(),
+            layouts: Vec::new(),
+        }
+    }
+
+    pub fn insert(&mut self, elements: Vec<BuiltinType>) -> TupleId {
+        if let Some(&val) = self.types.get(&elements) {
+            return val;
+        }
+
+        let id = TupleId(self.values.len());
+        let elements = Arc::new(elements);
+        self.types.insert(elements.clone(), id);
+
+        self.values.push(elements);
+        self.layouts.push(None);
+
+        id
+    }
+
+    pub fn get(&self, id: TupleId) -> Arc<Vec<BuiltinType>> {
+        self.values[id.0].clone()
+    }
+
+    pub fn layout(&self, id: TupleId) -> Option<StructDefId> {
+        self.layouts[id.0]
+    }
+
+    pub fn set_layout(&mut self, id: TupleId, layout: StructDefId) {
+        self.layouts[id.0] = Some(layout);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -207,7 +207,7 @@ def read_error_message(content)
     if line == "1 error found."
       return position, message
 
-    elsif (m = line.match(/^error at (\d+:\d+): (.+)$/)) != nil
+    elsif (m = line.match(/^error at (?:.+:)?(\d+:\d+): (.+)$/)) != nil
       position = m[1].to_s
       message = m[2].to_s
     end